@@ -82,7 +82,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let frame_queue = ArrayQueue::<_, 64>::new();
 
-    let mut uavcan: canadensis::CoreNode<_, _, 4, 4> =
+    let mut uavcan: canadensis::CoreNode<_, _, 4, 4, 4, 4> =
         canadensis::CoreNode::new(clock.clone(), node_id, Mtu::Can8, frame_queue);
 
     let heartbeat_token = uavcan
@@ -137,12 +137,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         if new_second {
             // Publish heartbeat
-            let heartbeat = Heartbeat {
-                uptime: run_time_seconds,
-                health: Health::Nominal,
-                mode: Mode::Operational,
-                vendor_specific_status_code: 0,
-            };
+            let heartbeat = Heartbeat::new(run_time_seconds, Health::Nominal, Mode::Operational);
             uavcan
                 .publish(&heartbeat_token, &heartbeat)
                 .expect("Out of memory");
@@ -207,11 +202,11 @@ impl TransferHandler<Microseconds64> for BasicTransferHandler {
         if transfer.header.service == GetInfoRequest::SERVICE {
             // Send a node information response
             let response = GetInfoResponse {
-                protocol_version: Version { major: 1, minor: 0 },
-                hardware_version: Version { major: 0, minor: 0 },
-                software_version: Version { major: 0, minor: 1 },
+                protocol_version: Version::new(1, 0),
+                hardware_version: Version::new(0, 0),
+                software_version: Version::new(0, 1),
                 software_vcs_revision_id: 0,
-                unique_id: self.unique_id.clone(),
+                unique_id: self.unique_id,
                 name: heapless::Vec::from_iter(b"org.samcrow.basic_node".iter().cloned()),
                 software_image_crc: None,
                 certificate_of_authenticity: heapless::Vec::new(),
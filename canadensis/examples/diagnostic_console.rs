@@ -21,7 +21,7 @@ use canadensis_data_types::uavcan::diagnostic::severity::Severity;
 use canadensis_encoding::{DataType, Deserialize, ReadCursor};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let interface = env::args().skip(1).next().unwrap_or_else(|| {
+    let interface = env::args().nth(1).unwrap_or_else(|| {
         eprintln!("Expected a SocketCAN interface name");
         process::exit(-1);
     });
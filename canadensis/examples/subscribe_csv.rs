@@ -97,6 +97,7 @@ impl Message for F32Message {}
 
 impl DataType for F32Message {
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 4;
 }
 
 impl Deserialize for F32Message {
@@ -11,7 +11,7 @@ use canadensis_can::{Mtu, OutOfMemoryError, Transmitter};
 use canadensis_core::time::Instant;
 use canadensis_core::transfer::{Header, MessageHeader, Transfer};
 use canadensis_core::{Priority, SubjectId, TransferId};
-use canadensis_encoding::{Message, Serialize};
+use canadensis_encoding::{Message, Serialize, SerializeError};
 
 /// A transmitter that sends anonymous messages and does not require a node ID
 ///
@@ -68,45 +68,51 @@ where
     where
         Q: FrameSink<C::Instant>,
     {
-        // Check that the message fits into one frame
-        // (subtract one byte to leave room for the tail byte)
-        let mtu_bits = (self.mtu.as_bytes() - 1) * 8;
-        if payload.size_bits() > mtu_bits {
-            return Err(AnonymousPublishError::Length);
-        }
-        // Part 1: Serialize
-        let deadline = self.timeout + now;
-        do_serialize(payload, |payload_bytes| {
-            self.send_payload(payload_bytes, deadline, transmitter)
-        })?;
+        let header = MessageHeader {
+            timestamp: self.timeout + now,
+            transfer_id: self.next_transfer_id,
+            priority: self.priority,
+            subject: self.subject,
+            source: None,
+        };
+        send_anonymous(header, payload, self.mtu, transmitter)?;
+        self.next_transfer_id = self.next_transfer_id.increment();
         Ok(())
     }
+}
 
-    fn send_payload<Q>(
-        &mut self,
-        payload: &[u8],
-        deadline: C::Instant,
-        transmitter: &mut Transmitter<Q>,
-    ) -> Result<(), OutOfMemoryError>
-    where
-        Q: FrameSink<C::Instant>,
-    {
-        // Assemble the transfer
-        let transfer: Transfer<&[u8], C::Instant> = Transfer {
+/// Serializes an anonymous message and pushes it into a transmitter
+///
+/// The source of the header is ignored.
+///
+/// This function returns an error if the serialized message is too long to fit into one frame
+/// with the provided MTU, or if memory allocation fails.
+pub(crate) fn send_anonymous<I, T, Q>(
+    header: MessageHeader<I>,
+    payload: &T,
+    mtu: Mtu,
+    transmitter: &mut Transmitter<Q>,
+) -> Result<(), AnonymousPublishError>
+where
+    I: Clone,
+    T: Serialize,
+    Q: FrameSink<I>,
+{
+    do_serialize::<_, _, _, AnonymousPublishError>(payload, |payload_bytes| {
+        // Check that the message fits into one frame
+        // (subtract one byte to leave room for the tail byte)
+        if payload_bytes.len() > mtu.as_bytes() - 1 {
+            return Ok(Err(AnonymousPublishError::Length));
+        }
+        let transfer: Transfer<&[u8], I> = Transfer {
             header: Header::Message(MessageHeader {
-                timestamp: deadline,
-                transfer_id: self.next_transfer_id,
-                priority: self.priority,
-                subject: self.subject,
                 source: None,
+                ..header
             }),
-            payload,
+            payload: payload_bytes,
         };
-        self.next_transfer_id = self.next_transfer_id.increment();
-
-        transmitter.push(transfer)?;
-        Ok(())
-    }
+        transmitter.push(transfer).map(Ok)
+    })?
 }
 
 /// Errors that can occur when publishing an anonymous message
@@ -116,6 +122,9 @@ pub enum AnonymousPublishError {
     Length,
     /// Not enough memory was available
     Memory(OutOfMemoryError),
+    /// The message, or a delimited composite value inside it, was larger than the extent of its
+    /// type
+    Serialize(SerializeError),
 }
 
 impl From<OutOfMemoryError> for AnonymousPublishError {
@@ -123,3 +132,9 @@ impl From<OutOfMemoryError> for AnonymousPublishError {
         AnonymousPublishError::Memory(inner)
     }
 }
+
+impl From<SerializeError> for AnonymousPublishError {
+    fn from(inner: SerializeError) -> Self {
+        AnonymousPublishError::Serialize(inner)
+    }
+}
@@ -0,0 +1,367 @@
+//!
+//! Buffering of incoming messages
+//!
+//! A [`MessageBuffer`] is a [`TransferHandler`] that deserializes messages on one subject and
+//! stores them until the application removes them. This separates receiving frames from
+//! processing messages, for example when frames are received in an interrupt handler and
+//! messages are processed in a lower-priority loop.
+//!
+
+use canadensis_core::time::Instant;
+use canadensis_core::transfer::{MessageHeader, MessageTransfer};
+use canadensis_core::SubjectId;
+use canadensis_encoding::{Deserialize, Message};
+
+use crate::{Node, TransferHandler};
+
+/// What a buffer does when it receives a message and it is already full
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// Remove the oldest message from the buffer to make space for the new message
+    ///
+    /// This is usually the best choice for control loops, which only need the most recent
+    /// sample.
+    KeepLatest,
+    /// Drop the new message and keep the messages already in the buffer
+    KeepOldest,
+    /// Drop the new message, and make the next call to [`MessageBuffer::pop`] return an error
+    FailFast,
+}
+
+/// A message and the header of the transfer that contained it
+#[derive(Debug, Clone)]
+pub struct ReceivedMessage<T, I> {
+    /// The transfer header
+    pub header: MessageHeader<I>,
+    /// The message
+    pub message: T,
+}
+
+/// An error returned from [`MessageBuffer::pop`] when a buffer with the
+/// [`FailFast`](OverflowPolicy::FailFast) policy has dropped one or more messages
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Overflowed;
+
+/// Stores up to `N` messages of type `T` received on a subject
+///
+/// The buffer does not subscribe to the subject. The application must also call
+/// [`Node::subscribe_message`] with the buffer's subject.
+///
+/// Messages that can't be deserialized are dropped and are not counted as dropped messages.
+pub struct MessageBuffer<T, I, const N: usize> {
+    /// The subject that this buffer handles
+    subject: SubjectId,
+    /// Buffered messages
+    messages: BoundedQueue<ReceivedMessage<T, I>, N>,
+}
+
+impl<T, I, const N: usize> MessageBuffer<T, I, N> {
+    /// Creates an empty buffer that handles messages on a subject
+    pub fn new(subject: SubjectId, policy: OverflowPolicy) -> Self {
+        MessageBuffer {
+            subject,
+            messages: BoundedQueue::new(policy),
+        }
+    }
+
+    /// Returns the subject that this buffer handles
+    pub fn subject(&self) -> SubjectId {
+        self.subject
+    }
+
+    /// Returns the overflow policy of this buffer
+    pub fn policy(&self) -> OverflowPolicy {
+        self.messages.policy
+    }
+
+    /// Changes the overflow policy of this buffer
+    ///
+    /// Any overflow that has not been reported yet is forgotten, so changing the policy to
+    /// [`FailFast`](OverflowPolicy::FailFast) does not report an overflow that happened earlier.
+    pub fn set_policy(&mut self, policy: OverflowPolicy) {
+        self.messages.set_policy(policy);
+    }
+
+    /// Removes and returns the oldest message in this buffer
+    ///
+    /// If the policy is [`FailFast`](OverflowPolicy::FailFast) and a message has been dropped
+    /// since the last call, this function returns an error once. The messages in the buffer
+    /// can then be removed with later calls.
+    pub fn pop(&mut self) -> Result<Option<ReceivedMessage<T, I>>, Overflowed> {
+        self.messages.pop()
+    }
+
+    /// Removes all messages except the most recent one, and returns the most recent one
+    ///
+    /// The removed messages are not counted as dropped.
+    pub fn take_latest(&mut self) -> Option<ReceivedMessage<T, I>> {
+        let latest = self.messages.items.pop_back();
+        self.messages.items.clear();
+        latest
+    }
+
+    /// Returns the number of messages in this buffer
+    pub fn len(&self) -> usize {
+        self.messages.items.len()
+    }
+
+    /// Returns true if this buffer contains no messages
+    pub fn is_empty(&self) -> bool {
+        self.messages.items.is_empty()
+    }
+
+    /// Returns the total number of messages that this buffer has dropped because it was full
+    ///
+    /// This count saturates at `u32::MAX`.
+    pub fn dropped(&self) -> u32 {
+        self.messages.dropped
+    }
+}
+
+/// A fixed-capacity queue that applies an overflow policy
+pub(crate) struct BoundedQueue<E, const N: usize> {
+    /// What to do when the queue is full
+    pub policy: OverflowPolicy,
+    /// Queued items, oldest first
+    pub items: heapless::Deque<E, N>,
+    /// The total number of items dropped because the queue was full
+    pub dropped: u32,
+    /// True if an item has been dropped since the last `FailFast` error was reported
+    overflowed: bool,
+}
+
+impl<E, const N: usize> BoundedQueue<E, N> {
+    pub fn new(policy: OverflowPolicy) -> Self {
+        BoundedQueue {
+            policy,
+            items: heapless::Deque::new(),
+            dropped: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Changes the overflow policy and forgets any overflow that has not been reported
+    pub fn set_policy(&mut self, policy: OverflowPolicy) {
+        self.policy = policy;
+        self.overflowed = false;
+    }
+
+    /// Removes and returns the oldest item, or reports an overflow under the `FailFast` policy
+    pub fn pop(&mut self) -> Result<Option<E>, Overflowed> {
+        if self.overflowed && self.policy == OverflowPolicy::FailFast {
+            self.overflowed = false;
+            return Err(Overflowed);
+        }
+        Ok(self.items.pop_front())
+    }
+
+    /// Adds an item, dropping an item if the queue is full
+    pub fn push(&mut self, item: E) {
+        if self.items.is_full() {
+            self.dropped = self.dropped.saturating_add(1);
+            match self.policy {
+                OverflowPolicy::KeepLatest => {
+                    self.items.pop_front();
+                }
+                OverflowPolicy::KeepOldest => return,
+                OverflowPolicy::FailFast => {
+                    self.overflowed = true;
+                    return;
+                }
+            }
+        }
+        // The queue now has space, unless N is zero
+        let _ = self.items.push_back(item);
+    }
+}
+
+impl<T, I, P, const N: usize> TransferHandler<I, P> for MessageBuffer<T, I, N>
+where
+    T: Message + Deserialize,
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_message<M: Node<Instant = I>>(
+        &mut self,
+        _node: &mut M,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.subject != self.subject {
+            return false;
+        }
+        if let Ok(message) = T::deserialize_from_bytes(transfer.payload.as_ref()) {
+            self.messages.push(ReceivedMessage {
+                header: transfer.header.clone(),
+                message,
+            });
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use core::convert::TryFrom;
+    use std::vec::Vec;
+
+    use canadensis_can::queue::ArrayQueue;
+    use canadensis_can::Mtu;
+    use canadensis_core::time::{ManualClock, Microseconds32};
+    use canadensis_core::transfer::{MessageHeader, MessageTransfer};
+    use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+    use canadensis_encoding::{DataType, Deserialize, DeserializeError, Message, ReadCursor};
+
+    use super::{BoundedQueue, MessageBuffer, OverflowPolicy, Overflowed};
+    use crate::{CoreNode, TransferHandler};
+
+    /// A message that contains one byte
+    struct Byte(u8);
+
+    impl DataType for Byte {
+        const EXTENT_BYTES: Option<u32> = None;
+        const MAX_SERIALIZED_SIZE: usize = 1;
+    }
+
+    impl Message for Byte {}
+
+    impl Deserialize for Byte {
+        fn in_bit_length_set(bit_length: usize) -> bool {
+            bit_length == 8
+        }
+
+        fn deserialize_in_place(
+            &mut self,
+            cursor: &mut ReadCursor<'_>,
+        ) -> Result<(), DeserializeError> {
+            self.0 = cursor.read_aligned_u8();
+            Ok(())
+        }
+
+        fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError> {
+            Ok(Byte(cursor.read_aligned_u8()))
+        }
+    }
+
+    fn filled(policy: OverflowPolicy) -> BoundedQueue<u8, 3> {
+        let mut queue = BoundedQueue::new(policy);
+        for item in 0..5 {
+            queue.push(item);
+        }
+        queue
+    }
+
+    fn drain(queue: &mut BoundedQueue<u8, 3>) -> Vec<u8> {
+        let mut items = Vec::new();
+        while let Ok(Some(item)) = queue.pop() {
+            items.push(item);
+        }
+        items
+    }
+
+    #[test]
+    fn keep_latest() {
+        let mut queue = filled(OverflowPolicy::KeepLatest);
+        assert_eq!(2, queue.dropped);
+        assert_eq!(Ok(Some(2)), queue.pop());
+        assert_eq!(std::vec![3, 4], drain(&mut queue));
+        assert_eq!(Ok(None), queue.pop());
+    }
+
+    #[test]
+    fn keep_oldest() {
+        let mut queue = filled(OverflowPolicy::KeepOldest);
+        assert_eq!(2, queue.dropped);
+        assert_eq!(Ok(Some(0)), queue.pop());
+        assert_eq!(std::vec![1, 2], drain(&mut queue));
+        assert_eq!(Ok(None), queue.pop());
+    }
+
+    #[test]
+    fn fail_fast() {
+        let mut queue = filled(OverflowPolicy::FailFast);
+        assert_eq!(2, queue.dropped);
+        // The error is reported once, then the oldest items can be removed
+        assert_eq!(Err(Overflowed), queue.pop());
+        assert_eq!(Ok(Some(0)), queue.pop());
+        assert_eq!(std::vec![1, 2], drain(&mut queue));
+        assert_eq!(Ok(None), queue.pop());
+    }
+
+    #[test]
+    fn set_policy_clears_overflow() {
+        let mut queue = filled(OverflowPolicy::FailFast);
+        queue.set_policy(OverflowPolicy::KeepOldest);
+        queue.set_policy(OverflowPolicy::FailFast);
+        assert_eq!(Ok(Some(0)), queue.pop());
+
+        // An overflow under another policy is not reported after changing to FailFast
+        let mut queue = filled(OverflowPolicy::KeepLatest);
+        queue.set_policy(OverflowPolicy::FailFast);
+        assert_eq!(Ok(Some(2)), queue.pop());
+    }
+
+    #[test]
+    fn dropped_saturates() {
+        let mut queue: BoundedQueue<u8, 1> = BoundedQueue::new(OverflowPolicy::KeepOldest);
+        queue.dropped = u32::MAX - 1;
+        queue.push(0);
+        queue.push(1);
+        queue.push(2);
+        assert_eq!(u32::MAX, queue.dropped);
+    }
+
+    #[test]
+    fn message_buffer() {
+        let subject = SubjectId::try_from(100).unwrap();
+        let mut node: CoreNode<
+            ManualClock<Microseconds32>,
+            ArrayQueue<Microseconds32, 4>,
+            2,
+            2,
+            2,
+            2,
+        > = CoreNode::new(
+            ManualClock::new(Microseconds32::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            ArrayQueue::new(),
+        );
+        let mut buffer: MessageBuffer<Byte, Microseconds32, 2> =
+            MessageBuffer::new(subject, OverflowPolicy::FailFast);
+        for value in 0..3u8 {
+            let transfer = MessageTransfer {
+                header: MessageHeader {
+                    timestamp: Microseconds32::new(u32::from(value)),
+                    transfer_id: TransferId::try_from(value).unwrap(),
+                    priority: Priority::Nominal,
+                    subject,
+                    source: Some(NodeId::try_from(20).unwrap()),
+                },
+                payload: [value],
+            };
+            assert!(buffer.handle_message(&mut node, &transfer));
+        }
+        // Another subject is not handled
+        let other = MessageTransfer {
+            header: MessageHeader {
+                timestamp: Microseconds32::new(0),
+                transfer_id: TransferId::default(),
+                priority: Priority::Nominal,
+                subject: SubjectId::try_from(101).unwrap(),
+                source: None,
+            },
+            payload: [0u8],
+        };
+        assert!(!buffer.handle_message(&mut node, &other));
+
+        assert_eq!(2, buffer.len());
+        assert_eq!(1, buffer.dropped());
+        buffer.set_policy(OverflowPolicy::KeepLatest);
+        let latest = buffer.take_latest().unwrap();
+        assert_eq!(1, latest.message.0);
+        assert!(buffer.is_empty());
+        assert!(matches!(buffer.pop(), Ok(None)));
+    }
+}
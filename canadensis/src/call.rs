@@ -0,0 +1,521 @@
+//!
+//! Asynchronous service calls
+//!
+//! [`service_client`] sets up a node to send requests for a service, and returns a
+//! [`ServiceClient`] and a [`ServiceClientHandler`]. The client sends requests and returns
+//! futures that resolve to the responses. The handler is a [`TransferHandler`] that receives
+//! the responses, and it must be passed to [`Node::accept_frame`] along with other handlers.
+//! The code that accepts frames must also call [`ServiceClientHandler::check_timeouts`]
+//! periodically so that calls without responses time out.
+//!
+//! Like the [streams](crate::stream), the client and handler must be used on one thread.
+//!
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use canadensis_can::OutOfMemoryError;
+use canadensis_core::time::{Clock, Instant};
+use canadensis_core::transfer::ServiceTransfer;
+use canadensis_core::{NodeId, Priority, ServiceId, TransferId};
+use canadensis_encoding::{
+    Deserialize, DeserializeError, Request, Response, Serialize, SerializeError,
+};
+
+use crate::{Node, SendError, ServiceToken, StartSendError, TransferHandler};
+
+/// Errors that can occur when calling a service
+#[derive(Debug)]
+pub enum CallError {
+    /// Not enough memory was available to send the request
+    Memory(OutOfMemoryError),
+    /// The request was larger than the extent of its type, so it was not sent
+    Serialize(SerializeError),
+    /// The client already has the maximum number of calls waiting for responses
+    Capacity,
+    /// No response was received before the timeout
+    Timeout,
+    /// The response could not be deserialized
+    Deserialize(DeserializeError),
+    /// The handler was dropped, so the response can't be received
+    Closed,
+}
+
+impl From<OutOfMemoryError> for CallError {
+    fn from(inner: OutOfMemoryError) -> Self {
+        CallError::Memory(inner)
+    }
+}
+
+/// A handler and client returned from [`service_client`]
+pub type ClientParts<T, R, I, const C: usize> =
+    (ServiceClientHandler<R, I, C>, ServiceClient<T, R, I, C>);
+
+/// Sets up a node to send requests of type `T` and receive responses of type `R`
+///
+/// The returned client can have up to `C` calls waiting for responses at the same time.
+///
+/// `timeout` is the time to wait for each response. It is also used as the deadline for sending
+/// each request and as the timeout for receiving multi-frame responses.
+pub fn service_client<T, R, N, const C: usize>(
+    node: &mut N,
+    service: ServiceId,
+    timeout: <N::Instant as Instant>::Duration,
+    response_payload_size_max: usize,
+    priority: Priority,
+) -> Result<ClientParts<T, R, N::Instant, C>, StartSendError>
+where
+    T: Request,
+    N: Node,
+{
+    let token =
+        node.start_sending_requests(service, timeout, response_payload_size_max, priority)?;
+    let shared = Rc::new(RefCell::new(Calls {
+        pending: heapless::Vec::new(),
+        next_call: 0,
+        handler_alive: true,
+    }));
+    Ok((
+        ServiceClientHandler {
+            service,
+            shared: shared.clone(),
+        },
+        ServiceClient {
+            token,
+            timeout,
+            shared,
+            _response: PhantomData,
+        },
+    ))
+}
+
+/// Calls that are waiting for responses
+struct Calls<R, I, const C: usize> {
+    pending: heapless::Vec<PendingCall<R, I>, C>,
+    /// The number that identifies the next call
+    next_call: u32,
+    /// False if the handler has been dropped
+    handler_alive: bool,
+}
+
+impl<R, I, const C: usize> Calls<R, I, C> {
+    /// Returns the index of the call with a number
+    fn find(&self, number: u32) -> Option<usize> {
+        self.pending.iter().position(|call| call.number == number)
+    }
+
+    /// Returns the index of an unfinished call that a response with a source and transfer ID
+    /// belongs to
+    fn find_unfinished(&self, destination: NodeId, transfer_id: TransferId) -> Option<usize> {
+        self.pending.iter().position(|call| {
+            call.result.is_none()
+                && call.destination == destination
+                && call.transfer_id == transfer_id
+        })
+    }
+}
+
+struct PendingCall<R, I> {
+    /// The number that identifies this call
+    number: u32,
+    /// The node that the request was sent to
+    destination: NodeId,
+    /// The transfer ID of the request (and the response)
+    transfer_id: TransferId,
+    /// The time when the call times out
+    deadline: I,
+    /// The result, if the call has finished
+    result: Option<Result<R, CallError>>,
+    /// The waker of the task waiting for the response
+    waker: Option<Waker>,
+}
+
+impl<R, I> PendingCall<R, I> {
+    fn finish(&mut self, result: Result<R, CallError>) {
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Sends requests and returns futures that resolve to responses
+pub struct ServiceClient<T, R, I: Instant, const C: usize> {
+    token: ServiceToken<T>,
+    timeout: I::Duration,
+    shared: Rc<RefCell<Calls<R, I, C>>>,
+    _response: PhantomData<R>,
+}
+
+impl<T, R, I, const C: usize> ServiceClient<T, R, I, C>
+where
+    T: Request + Serialize,
+    R: Response + Deserialize,
+    I: Instant,
+{
+    /// Sends a request to a node and returns a future that resolves to the response
+    ///
+    /// The request is sent (added to the transmit queue) before this function returns. If it can't
+    /// be sent, the future resolves to an error immediately.
+    pub fn call<N>(&self, node: &mut N, request: &T, destination: NodeId) -> ResponseFuture<R, I, C>
+    where
+        N: Node<Instant = I>,
+    {
+        let key = self.start_call(node, request, destination);
+        ResponseFuture {
+            shared: self.shared.clone(),
+            key,
+        }
+    }
+
+    fn start_call<N>(
+        &self,
+        node: &mut N,
+        request: &T,
+        destination: NodeId,
+    ) -> Result<u32, Option<CallError>>
+    where
+        N: Node<Instant = I>,
+    {
+        if self.shared.borrow().pending.is_full() {
+            return Err(Some(CallError::Capacity));
+        }
+        let transfer_id = node
+            .send_request(&self.token, request, destination)
+            .map_err(|e| {
+                Some(match e {
+                    SendError::Memory(e) => CallError::Memory(e),
+                    SendError::Serialize(e) => CallError::Serialize(e),
+                })
+            })?;
+        let deadline = self.timeout + node.clock_mut().now();
+
+        let mut calls = self.shared.borrow_mut();
+        // An unfinished call with the same transfer ID must be so old that the transfer ID has
+        // wrapped around
+        if let Some(stale) = calls.find_unfinished(destination, transfer_id) {
+            calls.pending[stale].finish(Err(CallError::Timeout));
+        }
+        let number = calls.next_call;
+        calls.next_call = calls.next_call.wrapping_add(1);
+        let call = PendingCall {
+            number,
+            destination,
+            transfer_id,
+            deadline,
+            result: None,
+            waker: None,
+        };
+        if calls.pending.push(call).is_err() {
+            return Err(Some(CallError::Capacity));
+        }
+        Ok(number)
+    }
+
+    /// Returns the number of calls that are waiting for responses
+    pub fn pending_calls(&self) -> usize {
+        self.shared.borrow().pending.len()
+    }
+}
+
+/// Receives responses for a [`ServiceClient`]
+pub struct ServiceClientHandler<R, I, const C: usize> {
+    service: ServiceId,
+    shared: Rc<RefCell<Calls<R, I, C>>>,
+}
+
+impl<R, I, const C: usize> ServiceClientHandler<R, I, C>
+where
+    I: Instant,
+{
+    /// Makes calls that have not received responses before their deadlines fail with
+    /// [`CallError::Timeout`]
+    ///
+    /// This function should be called frequently.
+    pub fn check_timeouts(&mut self, now: I) {
+        for call in self.shared.borrow_mut().pending.iter_mut() {
+            if call.result.is_none() && now.overflow_safe_compare(&call.deadline) != Ordering::Less
+            {
+                call.finish(Err(CallError::Timeout));
+            }
+        }
+    }
+}
+
+impl<R, I, P, const C: usize> TransferHandler<I, P> for ServiceClientHandler<R, I, C>
+where
+    R: Response + Deserialize,
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_response<N: Node<Instant = I>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.service != self.service {
+            return false;
+        }
+        let mut calls = self.shared.borrow_mut();
+        if let Some(index) =
+            calls.find_unfinished(transfer.header.source, transfer.header.transfer_id)
+        {
+            let result = R::deserialize_from_bytes(transfer.payload.as_ref())
+                .map_err(CallError::Deserialize);
+            calls.pending[index].finish(result);
+        }
+        true
+    }
+}
+
+impl<R, I, const C: usize> Drop for ServiceClientHandler<R, I, C> {
+    fn drop(&mut self) {
+        let mut calls = self.shared.borrow_mut();
+        calls.handler_alive = false;
+        for call in calls.pending.iter_mut() {
+            if call.result.is_none() {
+                call.finish(Err(CallError::Closed));
+            }
+        }
+    }
+}
+
+/// A future that resolves to the response to a request
+pub struct ResponseFuture<R, I, const C: usize> {
+    shared: Rc<RefCell<Calls<R, I, C>>>,
+    /// The number of the call, or the error that prevented the request from being sent
+    /// (None after the future has completed)
+    key: Result<u32, Option<CallError>>,
+}
+
+impl<R, I, const C: usize> Future for ResponseFuture<R, I, C> {
+    type Output = Result<R, CallError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let number = match &mut self.key {
+            Ok(key) => *key,
+            Err(e) => {
+                return Poll::Ready(Err(e
+                    .take()
+                    .expect("ResponseFuture polled after completion")))
+            }
+        };
+        let mut calls = self.shared.borrow_mut();
+        match calls.find(number) {
+            Some(index) => {
+                if calls.pending[index].result.is_some() {
+                    let call = calls.pending.swap_remove(index);
+                    drop(calls);
+                    self.key = Err(None);
+                    Poll::Ready(call.result.unwrap())
+                } else if calls.handler_alive {
+                    calls.pending[index].waker = Some(cx.waker().clone());
+                    Poll::Pending
+                } else {
+                    calls.pending.swap_remove(index);
+                    drop(calls);
+                    self.key = Err(None);
+                    Poll::Ready(Err(CallError::Closed))
+                }
+            }
+            None => {
+                drop(calls);
+                self.key = Err(None);
+                Poll::Ready(Err(CallError::Closed))
+            }
+        }
+    }
+}
+
+impl<R, I, const C: usize> Drop for ResponseFuture<R, I, C> {
+    fn drop(&mut self) {
+        // Stop waiting for the response
+        if let Ok(number) = self.key {
+            let mut calls = self.shared.borrow_mut();
+            if let Some(index) = calls.find(number) {
+                calls.pending.swap_remove(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate canadensis_data_types;
+    extern crate std;
+
+    use alloc::sync::Arc;
+    use core::convert::TryFrom;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::task::{Context, Poll, Waker};
+    use std::task::Wake;
+
+    use canadensis_can::queue::ArrayQueue;
+    use canadensis_can::Mtu;
+    use canadensis_core::time::{milliseconds, ManualClock, Microseconds32};
+    use canadensis_core::transfer::{ServiceHeader, ServiceTransfer};
+    use canadensis_core::{NodeId, Priority, TransferId};
+
+    use self::canadensis_data_types::uavcan::node::execute_command::{
+        Command, ExecuteCommandRequest, ExecuteCommandResponse, Status,
+    };
+    use super::{service_client, CallError, ResponseFuture, ServiceClient, ServiceClientHandler};
+    use crate::{CoreNode, TransferHandler};
+
+    type TestNode =
+        CoreNode<ManualClock<Microseconds32>, ArrayQueue<Microseconds32, 8>, 2, 2, 2, 2>;
+    type Client = ServiceClient<ExecuteCommandRequest, ExecuteCommandResponse, Microseconds32, 2>;
+    type Handler = ServiceClientHandler<ExecuteCommandResponse, Microseconds32, 2>;
+    type Response = ResponseFuture<ExecuteCommandResponse, Microseconds32, 2>;
+
+    /// A waker that counts the times it has been woken
+    #[derive(Default)]
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn server() -> NodeId {
+        NodeId::try_from(20).unwrap()
+    }
+
+    fn setup() -> (TestNode, Handler, Client) {
+        let mut node = CoreNode::new(
+            ManualClock::new(Microseconds32::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            ArrayQueue::new(),
+        );
+        let (handler, client) = service_client(
+            &mut node,
+            ExecuteCommandRequest::SERVICE,
+            milliseconds(100),
+            48,
+            Priority::Nominal,
+        )
+        .unwrap();
+        (node, handler, client)
+    }
+
+    fn call(node: &mut TestNode, client: &Client) -> Response {
+        client.call(
+            node,
+            &ExecuteCommandRequest::new(Command::Restart),
+            server(),
+        )
+    }
+
+    fn respond(node: &mut TestNode, handler: &mut Handler, transfer_id: u8, payload: &[u8]) {
+        let transfer = ServiceTransfer {
+            header: ServiceHeader {
+                timestamp: Microseconds32::new(0),
+                transfer_id: TransferId::try_from(transfer_id).unwrap(),
+                priority: Priority::Nominal,
+                service: ExecuteCommandRequest::SERVICE,
+                source: server(),
+                destination: NodeId::try_from(10).unwrap(),
+            },
+            payload,
+        };
+        assert!(handler.handle_response(node, &transfer));
+    }
+
+    fn poll(
+        future: &mut Response,
+        waker: &Waker,
+    ) -> Poll<Result<ExecuteCommandResponse, CallError>> {
+        Pin::new(future).poll(&mut Context::from_waker(waker))
+    }
+
+    #[test]
+    fn response() {
+        let (mut node, mut handler, client) = setup();
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+        let mut future = call(&mut node, &client);
+        assert!(poll(&mut future, &waker).is_pending());
+
+        // A response with the wrong transfer ID is ignored
+        respond(&mut node, &mut handler, 5, &[0]);
+        assert_eq!(0, count.0.load(Ordering::SeqCst));
+        respond(&mut node, &mut handler, 0, &[0]);
+        assert_eq!(1, count.0.load(Ordering::SeqCst));
+        match poll(&mut future, &waker) {
+            Poll::Ready(Ok(response)) => assert_eq!(Status::Success, response.status),
+            other => panic!("Unexpected poll result {:?}", other.map(|r| r.map(|_| ()))),
+        }
+        assert_eq!(0, client.pending_calls());
+    }
+
+    #[test]
+    fn timeout() {
+        let (mut node, mut handler, client) = setup();
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+        let mut future = call(&mut node, &client);
+        assert!(poll(&mut future, &waker).is_pending());
+
+        handler.check_timeouts(Microseconds32::new(99_999));
+        assert!(poll(&mut future, &waker).is_pending());
+        handler.check_timeouts(Microseconds32::new(100_000));
+        assert_eq!(1, count.0.load(Ordering::SeqCst));
+        assert!(matches!(
+            poll(&mut future, &waker),
+            Poll::Ready(Err(CallError::Timeout))
+        ));
+        assert_eq!(0, client.pending_calls());
+
+        // A late response is ignored
+        respond(&mut node, &mut handler, 0, &[0]);
+    }
+
+    #[test]
+    fn drop_future_removes_call() {
+        let (mut node, mut handler, client) = setup();
+        let first = call(&mut node, &client);
+        let _second = call(&mut node, &client);
+        assert_eq!(2, client.pending_calls());
+        assert!(matches!(
+            poll(
+                &mut call(&mut node, &client),
+                &Waker::from(Arc::new(CountWaker::default()))
+            ),
+            Poll::Ready(Err(CallError::Capacity))
+        ));
+
+        drop(first);
+        assert_eq!(1, client.pending_calls());
+        // The response to the dropped call is ignored
+        respond(&mut node, &mut handler, 0, &[0]);
+        assert_eq!(1, client.pending_calls());
+        // Space for another call
+        let _third = call(&mut node, &client);
+        assert_eq!(2, client.pending_calls());
+    }
+
+    #[test]
+    fn drop_handler_closes_calls() {
+        let (mut node, handler, client) = setup();
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+        let mut waiting = call(&mut node, &client);
+        assert!(poll(&mut waiting, &waker).is_pending());
+        drop(handler);
+        assert_eq!(1, count.0.load(Ordering::SeqCst));
+        assert!(matches!(
+            poll(&mut waiting, &waker),
+            Poll::Ready(Err(CallError::Closed))
+        ));
+        assert_eq!(0, client.pending_calls());
+    }
+}
@@ -0,0 +1,232 @@
+//!
+//! Several logical nodes that share one transport
+//!
+//! A [`CompositeNode`] holds up to `K` nodes with different node IDs. Each node has its own
+//! subscriptions, publishers, and transmit queue, and can be wrapped in higher-level node types
+//! that send heartbeats and handle registers. The composite node routes frames between the
+//! driver and the nodes:
+//!
+//! * A frame from the driver goes to every node except the node that sent it
+//! * A frame from a node's transmit queue goes to the driver, and also to the other nodes
+//!   because a CAN controller does not receive the frames that it sends
+//!
+//! Each node discards the service frames that are addressed to other nodes, unless it is
+//! sniffing them.
+//!
+
+use alloc::vec::Vec;
+
+use canadensis_can::queue::FrameQueueSource;
+use canadensis_can::{CanId, Frame, OutOfMemoryError};
+use canadensis_core::time::Clock;
+use canadensis_core::NodeId;
+use canadensis_filter_config::Filter;
+use fallible_collections::FallibleVec;
+
+use crate::{Node, TransferHandler};
+
+/// A collection of up to `K` nodes with different node IDs that share one transport
+///
+/// The transfer handler that is passed to [`accept_frame`](#method.accept_frame) and
+/// [`pop_frame`](#method.pop_frame) handles transfers for all nodes. It can use
+/// [`Node::node_id`] to find out which node received each transfer.
+pub struct CompositeNode<N, const K: usize> {
+    nodes: heapless::Vec<N, K>,
+}
+
+/// An error from [`CompositeNode::add`]
+#[derive(Debug)]
+pub enum AddNodeError<N> {
+    /// The composite node already has `K` nodes
+    Full(N),
+    /// The composite node already has a node with the same node ID
+    DuplicateId(N),
+}
+
+impl<N, const K: usize> CompositeNode<N, K>
+where
+    N: Node,
+    N::FrameQueue: FrameQueueSource<N::Instant>,
+{
+    /// Creates a composite node with no nodes
+    pub fn new() -> Self {
+        CompositeNode {
+            nodes: heapless::Vec::new(),
+        }
+    }
+
+    /// Adds a node
+    ///
+    /// This function returns an error containing the node if there is no space for it or another
+    /// node has the same node ID.
+    pub fn add(&mut self, node: N) -> Result<(), AddNodeError<N>> {
+        if self.node(node.node_id()).is_some() {
+            return Err(AddNodeError::DuplicateId(node));
+        }
+        self.nodes.push(node).map_err(AddNodeError::Full)
+    }
+
+    /// Removes and returns the node with a node ID
+    ///
+    /// Frames in the node's transmit queue are not sent.
+    pub fn remove(&mut self, id: NodeId) -> Option<N> {
+        let index = self.index_of(id)?;
+        Some(self.nodes.swap_remove(index))
+    }
+
+    /// Returns a reference to the node with a node ID
+    pub fn node(&self, id: NodeId) -> Option<&N> {
+        self.nodes.iter().find(|node| node.node_id() == id)
+    }
+
+    /// Returns a mutable reference to the node with a node ID
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut N> {
+        self.nodes.iter_mut().find(|node| node.node_id() == id)
+    }
+
+    /// Returns the nodes
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    /// Returns mutable references to the nodes
+    ///
+    /// This can be used to run periodic tasks on every node.
+    pub fn nodes_mut(&mut self) -> &mut [N] {
+        &mut self.nodes
+    }
+
+    /// Handles an incoming frame from the driver
+    ///
+    /// The frame is given to every node except the node that sent it. If a frame completes a
+    /// transfer, the transfer is passed to the handler along with the node that received it.
+    ///
+    /// If memory allocation fails for one node, the frame is still given to the other nodes and
+    /// this function returns an error.
+    pub fn accept_frame<H>(
+        &mut self,
+        frame: Frame<N::Instant>,
+        handler: &mut H,
+    ) -> Result<(), OutOfMemoryError>
+    where
+        H: TransferHandler<N::Instant, N::Payload>,
+    {
+        let source = self.index_of_source(frame.id());
+        self.deliver(frame, source, handler)
+    }
+
+    /// Removes the highest-priority frame from the transmit queues of the nodes and returns it
+    ///
+    /// The frame is also given to the other nodes, as if they had received it from the driver.
+    /// Transfers that this completes are passed to the handler.
+    ///
+    /// The driver should call this function only when it can send a frame, because a frame
+    /// can't be returned to the queue after the other nodes have received it.
+    pub fn pop_frame<H>(
+        &mut self,
+        handler: &mut H,
+    ) -> Result<Option<Frame<N::Instant>>, OutOfMemoryError>
+    where
+        H: TransferHandler<N::Instant, N::Payload>,
+    {
+        let index = match self.highest_priority_queue() {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let frame = self.nodes[index].frame_queue_mut().pop_frame().unwrap();
+        if self.nodes.len() > 1 {
+            self.deliver(frame.clone(), Some(index), handler)?;
+        }
+        Ok(Some(frame))
+    }
+
+    /// Returns a reference to the highest-priority frame in the transmit queues of the nodes
+    pub fn peek_frame(&self) -> Option<&Frame<N::Instant>> {
+        let index = self.highest_priority_queue()?;
+        self.nodes[index].frame_queue().peek_frame()
+    }
+
+    /// Returns a set of filters that accept the frames that any of the nodes is subscribed to
+    pub fn frame_filters(&self) -> Result<Vec<Filter>, OutOfMemoryError> {
+        let mut filters = Vec::new();
+        for node in self.nodes.iter() {
+            let node_filters = node.frame_filters()?;
+            FallibleVec::try_reserve(&mut filters, node_filters.len())?;
+            filters.extend(node_filters);
+        }
+        Ok(filters)
+    }
+
+    /// Deletes all incoming transfer sessions that have timed out in every node
+    pub fn clean_expired_sessions(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.clean_expired_sessions();
+        }
+    }
+
+    /// Gives a frame to every node except the node at index `except`
+    ///
+    /// Each node receives a copy of the frame with a timestamp from its own clock.
+    fn deliver<H>(
+        &mut self,
+        frame: Frame<N::Instant>,
+        except: Option<usize>,
+        handler: &mut H,
+    ) -> Result<(), OutOfMemoryError>
+    where
+        H: TransferHandler<N::Instant, N::Payload>,
+    {
+        let mut status = Ok(());
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            if Some(index) == except {
+                continue;
+            }
+            let now = node.clock_mut().now();
+            let copy = Frame::new(now, frame.id(), frame.data());
+            if let Err(e) = node.accept_frame(copy, handler) {
+                status = Err(e);
+            }
+        }
+        status
+    }
+
+    /// Returns the index of the node whose queue has the highest-priority (lowest CAN ID) frame
+    fn highest_priority_queue(&self) -> Option<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| {
+                node.frame_queue()
+                    .peek_frame()
+                    .map(|frame| (frame.id(), index))
+            })
+            .min()
+            .map(|(_, index)| index)
+    }
+
+    fn index_of(&self, id: NodeId) -> Option<usize> {
+        self.nodes.iter().position(|node| node.node_id() == id)
+    }
+
+    /// Returns the index of the node that sent a frame, if it is one of the nodes
+    fn index_of_source(&self, id: CanId) -> Option<usize> {
+        let bits = u32::from(id);
+        let service = bits & (1 << 25) != 0;
+        let anonymous = !service && bits & (1 << 24) != 0;
+        if anonymous {
+            None
+        } else {
+            self.index_of(NodeId::from_truncating((bits & 0x7f) as u8))
+        }
+    }
+}
+
+impl<N, const K: usize> Default for CompositeNode<N, K>
+where
+    N: Node,
+    N::FrameQueue: FrameQueueSource<N::Instant>,
+{
+    fn default() -> Self {
+        CompositeNode::new()
+    }
+}
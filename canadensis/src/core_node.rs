@@ -1,24 +1,32 @@
 use alloc::vec::Vec;
-use core::iter;
-use core::marker::PhantomData;
 
 use fallible_collections::FallibleVec;
 
+use canadensis_can::buffer::{BufferAllocator, HeapAllocator};
 use canadensis_can::queue::{FrameQueueSource, FrameSink};
 use canadensis_can::{Frame, Mtu, OutOfMemoryError, Receiver, ServiceSubscribeError, Transmitter};
-use canadensis_core::time::{Clock, Instant};
+use canadensis_core::time::{milliseconds, Clock, Instant};
 use canadensis_core::transfer::{
-    Header, MessageTransfer, ServiceHeader, ServiceTransfer, Transfer,
+    Header, MessageHeader, MessageTransfer, ServiceHeader, ServiceTransfer, Transfer,
 };
+use canadensis_core::transfer_id_tracker::{TransferIdKey, TransferIdTracker};
 use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
-use canadensis_encoding::{Message, Request, Response, Serialize, WriteCursor};
+use canadensis_encoding::{Message, Request, Response, Serialize, SerializeError};
 
-use crate::hash::TrivialIndexMap;
-use crate::publisher::Publisher;
-use crate::requester::Requester;
-use crate::{Node, PublishToken, ResponseToken, ServiceToken, StartSendError, TransferHandler};
+use crate::anonymous::{send_anonymous, AnonymousPublishError};
+use crate::rate_limit::RateLimit;
+use crate::senders::Senders;
+use crate::split::NodeTransmitter;
+use crate::transfer_ids::TransferIdStorage;
+use crate::{
+    Node, PublishToken, ResponseToken, SendError, ServiceToken, StartSendError, SubscribeError,
+    TransferHandler,
+};
 use canadensis_filter_config::Filter;
 
+/// The initial node-wide default timeout for outgoing transfers, in milliseconds
+pub const DEFAULT_TIMEOUT_MS: u32 = 1000;
+
 /// A high-level interface with UAVCAN node functionality
 ///
 /// Type parameters:
@@ -26,41 +34,245 @@ use canadensis_filter_config::Filter;
 /// * `Q`: The queue type used to store outgoing frames
 /// * `P`: The maximum number of topics that can be published
 /// * `R`: The maximum number of services for which requests can be sent
+/// * `MS`: The maximum number of subjects that can be subscribed to
+/// * `SS`: The maximum number of service subscriptions (services for which requests can be
+///   received, and services whose requests or responses are sniffed)
+/// * `A`: The allocator that provides memory for incoming transfer payloads
 ///
-pub struct CoreNode<C, Q, const P: usize, const R: usize>
-where
+pub struct CoreNode<
+    C,
+    Q,
+    const P: usize,
+    const R: usize,
+    const MS: usize,
+    const SS: usize,
+    A = HeapAllocator,
+> where
     C: Clock,
+    A: BufferAllocator,
 {
     clock: C,
     transmitter: Transmitter<Q>,
-    receiver: Receiver<C::Instant>,
+    receiver: Receiver<C::Instant, A>,
     node_id: NodeId,
-    publishers: TrivialIndexMap<SubjectId, Publisher<C::Instant>, P>,
-    requesters: TrivialIndexMap<ServiceId, Requester<C::Instant>, R>,
+    /// The transport MTU
+    mtu: Mtu,
+    senders: Senders<C::Instant, P, R>,
+    /// Subjects whose publishers were moved to a transmitter by [`split`](CoreNode::split)
+    split_subjects: heapless::Vec<SubjectId, P>,
+    /// Services whose requesters were moved to a transmitter by [`split`](CoreNode::split)
+    split_services: heapless::Vec<ServiceId, R>,
+    /// The next transfer IDs of anonymous messages, for up to `P` subjects
+    anonymous_transfer_ids: TransferIdTracker<P>,
+    message_subscriptions: heapless::Vec<SubjectId, MS>,
+    service_subscriptions: heapless::Vec<ServicePort, SS>,
+    /// The timeout that publishers and requesters use if they don't specify one
+    default_timeout: <C::Instant as Instant>::Duration,
 }
 
-impl<C, Q, const P: usize, const R: usize> CoreNode<C, Q, P, R>
+/// A service subscription that counts against the service subscription capacity
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ServicePort {
+    Request(ServiceId),
+    SniffedResponse(ServiceId),
+}
+
+impl<C, Q, const P: usize, const R: usize, const MS: usize, const SS: usize>
+    CoreNode<C, Q, P, R, MS, SS>
 where
     C: Clock,
     Q: FrameSink<C::Instant>,
 {
     pub fn new(clock: C, node_id: NodeId, mtu: Mtu, transmit_queue: Q) -> Self {
+        CoreNode::with_allocator(clock, node_id, mtu, transmit_queue, HeapAllocator)
+    }
+}
+
+impl<C, Q, const P: usize, const R: usize, const MS: usize, const SS: usize, A>
+    CoreNode<C, Q, P, R, MS, SS, A>
+where
+    C: Clock,
+    Q: FrameSink<C::Instant>,
+    A: BufferAllocator,
+{
+    /// Creates a node that uses the provided allocator for incoming transfer payloads
+    pub fn with_allocator(
+        clock: C,
+        node_id: NodeId,
+        mtu: Mtu,
+        transmit_queue: Q,
+        allocator: A,
+    ) -> Self {
         CoreNode {
             clock,
             transmitter: Transmitter::new(mtu, transmit_queue),
-            receiver: Receiver::new(node_id, mtu),
+            receiver: Receiver::with_allocator(node_id, mtu, allocator),
             node_id,
-            publishers: TrivialIndexMap::new(),
-            requesters: TrivialIndexMap::new(),
+            mtu,
+            senders: Senders::new(node_id),
+            split_subjects: heapless::Vec::new(),
+            split_services: heapless::Vec::new(),
+            anonymous_transfer_ids: TransferIdTracker::new(),
+            message_subscriptions: heapless::Vec::new(),
+            service_subscriptions: heapless::Vec::new(),
+            default_timeout: milliseconds(DEFAULT_TIMEOUT_MS),
+        }
+    }
+
+    /// Splits this node into a transmitter that publishes messages and sends requests, and a
+    /// node that receives frames
+    ///
+    /// The transmitter takes all publishers and requesters that this node has set up,
+    /// and uses `transmit_queue` for its outgoing frames. Tokens that were created before
+    /// splitting must be used with the transmitter.
+    ///
+    /// The returned node keeps all subscriptions and receives responses to requests that the
+    /// transmitter sends. Its handlers can still send responses, and can publish and send
+    /// requests using new publishers and requesters. The subjects and services that the
+    /// transmitter took stay reserved: starting to publish or send requests on one of them
+    /// returns [`StartSendError::Duplicate`], and they still count against the `P` and `R`
+    /// capacities.
+    ///
+    /// A `canadensis_node::BasicNode` only lists the ports of the node in its port list. Use its
+    /// `add_transmitter_ports` function to list the ports of the transmitter too.
+    pub fn split<QT>(mut self, transmit_queue: QT) -> (NodeTransmitter<C, QT, P, R>, Self)
+    where
+        C: Clone,
+        QT: FrameSink<C::Instant>,
+    {
+        let senders = core::mem::replace(&mut self.senders, Senders::new(self.node_id));
+        // Both vectors have enough capacity because the senders and the ports split earlier
+        // together never exceed P and R
+        for subject in senders.subjects() {
+            self.split_subjects
+                .push(subject)
+                .expect("Too many split subjects");
+        }
+        for service in senders.services() {
+            self.split_services
+                .push(service)
+                .expect("Too many split services");
+        }
+        let transmitter = NodeTransmitter::new(
+            self.clock.clone(),
+            self.mtu,
+            transmit_queue,
+            self.node_id,
+            senders,
+        );
+        (transmitter, self)
+    }
+
+    /// Saves the transfer IDs of all publishers and requesters
+    pub fn save_transfer_ids<S>(&self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        self.senders.save_transfer_ids(storage)
+    }
+
+    /// Loads the transfer IDs of all publishers and requesters that have saved transfer IDs
+    ///
+    /// This should be called after starting to publish and send requests.
+    pub fn restore_transfer_ids<S>(&mut self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        self.senders.restore_transfer_ids(storage)
+    }
+
+    /// Sets or removes the rate limit for messages published with a token
+    ///
+    /// When publishing a message would exceed the limit, the message is dropped and
+    /// the publish function returns `Ok(())`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token is not valid for this node.
+    pub fn set_publish_rate_limit<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        limit: Option<RateLimit<<C::Instant as Instant>::Duration>>,
+    ) {
+        self.senders.set_publish_rate_limit(token, limit)
+    }
+
+    /// Returns the number of messages published with a token that have been dropped because
+    /// of the rate limit
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token is not valid for this node.
+    pub fn rate_limited_messages<T>(&self, token: &PublishToken<T>) -> u32 {
+        self.senders.rate_limited_messages(token)
+    }
+
+    /// Enables transfer ID gap detection for a subject that this node is subscribed to
+    ///
+    /// See [`Receiver::enable_gap_detection`] for details.
+    pub fn enable_gap_detection(&mut self, subject: SubjectId) -> Result<(), OutOfMemoryError> {
+        self.receiver.enable_gap_detection(subject)
+    }
+
+    /// Disables transfer ID gap detection for a subject
+    pub fn disable_gap_detection(&mut self, subject: SubjectId) {
+        self.receiver.disable_gap_detection(subject)
+    }
+
+    /// Returns the number of messages on a subject from a source node that did not arrive,
+    /// or None if gap detection is not enabled for the subject
+    pub fn missed_transfers(&self, subject: SubjectId, source: NodeId) -> Option<u64> {
+        self.receiver.missed_transfers(subject, source)
+    }
+
+    /// Returns the number of messages on a subject from all source nodes that did not arrive,
+    /// or None if gap detection is not enabled for the subject
+    pub fn total_missed_transfers(&self, subject: SubjectId) -> Option<u64> {
+        self.receiver.total_missed_transfers(subject)
+    }
+
+    /// Sets the missed message counts for a subject to zero
+    pub fn reset_missed_transfers(&mut self, subject: SubjectId) {
+        self.receiver.reset_missed_transfers(subject)
+    }
+
+    /// Sets whether this node drops anonymous messages on a subject that it is subscribed to
+    ///
+    /// See [`Receiver::set_ignore_anonymous`] for details.
+    pub fn set_ignore_anonymous(&mut self, subject: SubjectId, ignore: bool) {
+        self.receiver.set_ignore_anonymous(subject, ignore)
+    }
+
+    /// Returns the number of anonymous messages on a subject that this node has dropped,
+    /// or None if this node is not subscribed to the subject
+    pub fn ignored_anonymous_messages(&self, subject: SubjectId) -> Option<u64> {
+        self.receiver.ignored_anonymous_messages(subject)
+    }
+
+    /// Records a service subscription, or returns an error if there is no space for it
+    ///
+    /// Subscribing again to a port that is already recorded does not use any more space.
+    fn add_service_subscription(&mut self, port: ServicePort) -> Result<(), SubscribeError> {
+        if !self.service_subscriptions.contains(&port) {
+            self.service_subscriptions
+                .push(port)
+                .map_err(|_| SubscribeError::Capacity)?;
         }
+        Ok(())
+    }
+
+    /// Removes a service subscription record
+    fn remove_service_subscription(&mut self, port: ServicePort) {
+        self.service_subscriptions
+            .retain(|existing| *existing != port);
     }
 
     fn handle_incoming_transfer<H>(
         &mut self,
-        transfer: Transfer<Vec<u8>, C::Instant>,
+        transfer: Transfer<A::Buffer, C::Instant>,
         handler: &mut H,
     ) where
-        H: TransferHandler<<Self as Node>::Instant>,
+        H: TransferHandler<<Self as Node>::Instant, A::Buffer>,
     {
         match transfer.header {
             Header::Message(message_header) => {
@@ -70,6 +282,20 @@ where
                 };
                 handler.handle_message(self, &message_transfer);
             }
+            Header::Request(service_header) if service_header.destination != self.node_id => {
+                let service_transfer = ServiceTransfer {
+                    header: service_header,
+                    payload: transfer.payload,
+                };
+                handler.handle_sniffed_request(self, &service_transfer);
+            }
+            Header::Response(service_header) if service_header.destination != self.node_id => {
+                let service_transfer = ServiceTransfer {
+                    header: service_header,
+                    payload: transfer.payload,
+                };
+                handler.handle_sniffed_response(self, &service_transfer);
+            }
             Header::Request(service_header) => {
                 let token = ResponseToken {
                     service: service_header.service,
@@ -114,14 +340,17 @@ where
     }
 }
 
-impl<C, Q, const P: usize, const R: usize> Node for CoreNode<C, Q, P, R>
+impl<C, Q, const P: usize, const R: usize, const MS: usize, const SS: usize, A> Node
+    for CoreNode<C, Q, P, R, MS, SS, A>
 where
     C: Clock,
     Q: FrameSink<C::Instant>,
+    A: BufferAllocator,
 {
     type Clock = C;
     type Instant = <C as Clock>::Instant;
     type FrameQueue = Q;
+    type Payload = A::Buffer;
 
     fn accept_frame<H>(
         &mut self,
@@ -129,7 +358,7 @@ where
         handler: &mut H,
     ) -> Result<(), OutOfMemoryError>
     where
-        H: TransferHandler<Self::Instant>,
+        H: TransferHandler<Self::Instant, Self::Payload>,
     {
         if let Some(transfer) = self.receiver.accept(frame)? {
             self.handle_incoming_transfer(transfer, handler)
@@ -146,14 +375,12 @@ where
     where
         T: Message,
     {
-        let token = PublishToken(subject, PhantomData);
-        if self.publishers.contains_key(&subject) {
+        if self.split_subjects.contains(&subject) {
             Err(StartSendError::Duplicate)
+        } else if self.senders.publisher_count() + self.split_subjects.len() >= P {
+            Err(StartSendError::Capacity)
         } else {
-            self.publishers
-                .insert(subject, Publisher::new(self.node_id, timeout, priority))
-                .map(|_| token)
-                .map_err(|_| StartSendError::Memory(OutOfMemoryError))
+            self.senders.start_publishing(subject, timeout, priority)
         }
     }
 
@@ -161,18 +388,66 @@ where
     where
         T: Message,
     {
-        self.publishers.remove(&token.0);
+        self.senders.stop_publishing(token.0);
     }
 
-    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), OutOfMemoryError>
+    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), SendError>
+    where
+        T: Message + Serialize,
+    {
+        self.senders.publish(
+            self.clock.now(),
+            None,
+            token,
+            payload,
+            &mut self.transmitter,
+        )
+    }
+
+    fn publish_with_timeout<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        payload: &T,
+        timeout: <C::Instant as Instant>::Duration,
+    ) -> Result<(), SendError>
+    where
+        T: Message + Serialize,
+    {
+        self.senders.publish(
+            self.clock.now(),
+            Some(timeout),
+            token,
+            payload,
+            &mut self.transmitter,
+        )
+    }
+
+    fn publish_anonymous<T>(
+        &mut self,
+        subject: SubjectId,
+        payload: &T,
+        priority: Priority,
+    ) -> Result<(), AnonymousPublishError>
     where
         T: Message + Serialize,
     {
-        let publisher = self
-            .publishers
-            .get_mut(&token.0)
-            .expect("Bug: Token exists but no subscriber");
-        publisher.publish(self.clock.now(), token.0, payload, &mut self.transmitter)
+        // If there is no space to track another subject, use the default transfer ID. Receivers
+        // can't tell anonymous publishers apart, so they do not rely on the transfer IDs of
+        // anonymous messages.
+        let key = TransferIdKey::Message(subject);
+        let transfer_id = self.anonymous_transfer_ids.peek(key);
+        let header = MessageHeader {
+            timestamp: self.default_timeout + self.clock.now(),
+            transfer_id,
+            priority,
+            subject,
+            source: None,
+        };
+        send_anonymous(header, payload, self.mtu, &mut self.transmitter)?;
+        let _ = self
+            .anonymous_transfer_ids
+            .set(key, transfer_id.increment());
+        Ok(())
     }
 
     /// Sets up to send requests for a service
@@ -188,16 +463,18 @@ where
     where
         T: Request,
     {
-        let token = ServiceToken(service, PhantomData);
-        if self.requesters.contains_key(&service) {
+        if self.split_services.contains(&service)
+            || self
+                .service_subscriptions
+                .contains(&ServicePort::SniffedResponse(service))
+        {
             Err(StartSendError::Duplicate)
+        } else if self.senders.requester_count() + self.split_services.len() >= R {
+            Err(StartSendError::Capacity)
         } else {
-            self.requesters
-                .insert(
-                    service,
-                    Requester::new(self.node_id, receive_timeout, priority),
-                )
-                .map_err(|_| StartSendError::Memory(OutOfMemoryError))?;
+            let token = self
+                .senders
+                .start_sending_requests(service, receive_timeout, priority)?;
             match self.receiver.subscribe_response(
                 service,
                 response_payload_size_max,
@@ -206,7 +483,7 @@ where
                 Ok(()) => Ok(token),
                 Err(e) => {
                     // Clean up requester
-                    self.requesters.remove(&service);
+                    self.senders.stop_sending_requests(service);
                     // Because a CoreNode can't be anonymous, the above function can't return an Anonymous error.
                     match e {
                         ServiceSubscribeError::Memory(e) => Err(e.into()),
@@ -223,7 +500,7 @@ where
     where
         T: Request,
     {
-        self.requesters.remove(&token.0);
+        self.senders.stop_sending_requests(token.0);
     }
 
     fn send_request<T>(
@@ -231,17 +508,13 @@ where
         token: &ServiceToken<T>,
         payload: &T,
         destination: NodeId,
-    ) -> Result<TransferId, OutOfMemoryError>
+    ) -> Result<TransferId, SendError>
     where
         T: Request + Serialize,
     {
-        let requester = self
-            .requesters
-            .get_mut(&token.0)
-            .expect("Bug: No requester for token");
-        requester.send(
+        self.senders.send_request(
             self.clock.now(),
-            token.0,
+            token,
             payload,
             destination,
             &mut self.transmitter,
@@ -253,9 +526,20 @@ where
         subject: SubjectId,
         payload_size_max: usize,
         timeout: <C::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError> {
-        self.receiver
-            .subscribe_message(subject, payload_size_max, timeout)
+    ) -> Result<(), SubscribeError> {
+        let new_subject = !self.message_subscriptions.contains(&subject);
+        if new_subject {
+            self.message_subscriptions
+                .push(subject)
+                .map_err(|_| SubscribeError::Capacity)?;
+        }
+        let status = self
+            .receiver
+            .subscribe_message(subject, payload_size_max, timeout);
+        if status.is_err() && new_subject {
+            self.message_subscriptions.pop();
+        }
+        status.map_err(SubscribeError::from)
     }
 
     fn subscribe_request(
@@ -263,23 +547,92 @@ where
         service: ServiceId,
         payload_size_max: usize,
         timeout: <C::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError> {
+    ) -> Result<(), SubscribeError> {
+        let port = ServicePort::Request(service);
+        let new_port = !self.service_subscriptions.contains(&port);
+        self.add_service_subscription(port)?;
         let status = self
             .receiver
             .subscribe_request(service, payload_size_max, timeout);
         // Because a CoreNode can't be anonymous, the above function can't return an Anonymous error.
-        status.map_err(|e| match e {
-            ServiceSubscribeError::Memory(e) => e,
-            ServiceSubscribeError::Anonymous => unreachable!("CoreNode is never anonymous"),
+        status.map_err(|e| {
+            if new_port {
+                self.remove_service_subscription(port);
+            }
+            match e {
+                ServiceSubscribeError::Memory(e) => SubscribeError::Memory(e),
+                ServiceSubscribeError::Anonymous => unreachable!("CoreNode is never anonymous"),
+            }
         })
     }
 
+    fn unsubscribe_message(&mut self, subject: SubjectId) {
+        self.message_subscriptions
+            .retain(|existing| *existing != subject);
+        self.receiver.unsubscribe_message(subject);
+    }
+
+    fn unsubscribe_request(&mut self, service: ServiceId) {
+        self.remove_service_subscription(ServicePort::Request(service));
+        self.receiver.unsubscribe_request(service);
+    }
+
+    fn sniff_requests(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <C::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError> {
+        let port = ServicePort::Request(service);
+        let new_port = !self.service_subscriptions.contains(&port);
+        self.add_service_subscription(port)?;
+        self.receiver
+            .sniff_requests(service, payload_size_max, timeout)
+            .map_err(|e| {
+                if new_port {
+                    self.remove_service_subscription(port);
+                }
+                SubscribeError::Memory(e)
+            })
+    }
+
+    fn sniff_responses(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <C::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError> {
+        if self.split_services.contains(&service) || self.senders.services().any(|s| s == service) {
+            // The requester for this service is subscribed to its responses
+            return Err(SubscribeError::Duplicate);
+        }
+        let port = ServicePort::SniffedResponse(service);
+        let new_port = !self.service_subscriptions.contains(&port);
+        self.add_service_subscription(port)?;
+        self.receiver
+            .sniff_responses(service, payload_size_max, timeout)
+            .map_err(|e| {
+                if new_port {
+                    self.remove_service_subscription(port);
+                }
+                SubscribeError::Memory(e)
+            })
+    }
+
+    fn stop_sniffing_responses(&mut self, service: ServiceId) {
+        let port = ServicePort::SniffedResponse(service);
+        if self.service_subscriptions.contains(&port) {
+            self.remove_service_subscription(port);
+            self.receiver.unsubscribe_response(service);
+        }
+    }
+
     fn send_response<T>(
         &mut self,
         token: ResponseToken,
         timeout: <C::Instant as Instant>::Duration,
         payload: &T,
-    ) -> Result<(), OutOfMemoryError>
+    ) -> Result<(), SendError>
     where
         T: Response + Serialize,
     {
@@ -312,15 +665,30 @@ where
         self.node_id
     }
 
+    fn default_timeout(&self) -> <C::Instant as Instant>::Duration {
+        self.default_timeout
+    }
+
+    fn set_default_timeout(&mut self, timeout: <C::Instant as Instant>::Duration) {
+        self.default_timeout = timeout;
+    }
+
     fn frame_filters(&self) -> Result<Vec<Filter>, OutOfMemoryError> {
         self.receiver.frame_filters()
     }
+
+    fn clean_expired_sessions(&mut self) {
+        let now = self.clock.now();
+        self.receiver.clean_expired(now);
+    }
 }
 
-impl<C, Q, const P: usize, const R: usize> CoreNode<C, Q, P, R>
+impl<C, Q, const P: usize, const R: usize, const MS: usize, const SS: usize, A>
+    CoreNode<C, Q, P, R, MS, SS, A>
 where
     C: Clock,
     Q: FrameQueueSource<C::Instant>,
+    A: BufferAllocator,
 {
     /// Removes an outgoing frame from the queue and returns it
     pub fn pop_frame(&mut self) -> Option<Frame<C::Instant>> {
@@ -342,21 +710,288 @@ where
 const STACK_THRESHOLD: usize = 64;
 
 /// Serializes a payload into a buffer and passes the buffer to a closure
-pub(crate) fn do_serialize<T, F, R>(payload: &T, operation: F) -> Result<R, OutOfMemoryError>
+///
+/// If the payload or a delimited composite value inside it is larger than the extent of its type,
+/// this function returns an error without calling the closure.
+pub(crate) fn do_serialize<T, F, R, E>(payload: &T, operation: F) -> Result<R, E>
 where
     T: Serialize,
     F: FnOnce(&[u8]) -> Result<R, OutOfMemoryError>,
+    E: From<OutOfMemoryError> + From<SerializeError>,
 {
-    let payload_bytes = (payload.size_bits() + 7) / 8;
+    let payload_bytes = payload.size_bits().div_ceil(8);
     if payload_bytes > STACK_THRESHOLD {
-        let mut bytes: Vec<u8> = FallibleVec::try_with_capacity(payload_bytes)?;
-        bytes.extend(iter::repeat(0).take(payload_bytes));
-        payload.serialize(&mut WriteCursor::new(&mut bytes));
-        operation(&bytes)
+        let mut bytes: Vec<u8> =
+            FallibleVec::try_with_capacity(payload_bytes).map_err(OutOfMemoryError::from)?;
+        bytes.extend(core::iter::repeat_n(0, payload_bytes));
+        payload.try_serialize_to_bytes(&mut bytes)?;
+        Ok(operation(&bytes)?)
     } else {
         let mut bytes = [0u8; STACK_THRESHOLD];
         let bytes = &mut bytes[..payload_bytes];
-        payload.serialize(&mut WriteCursor::new(bytes));
-        operation(bytes)
+        payload.try_serialize_to_bytes(bytes)?;
+        Ok(operation(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use core::convert::TryFrom;
+    use std::vec;
+    use std::vec::Vec;
+
+    use canadensis_can::queue::{ArrayQueue, FrameQueueSource};
+    use canadensis_can::{CanId, Frame, Mtu};
+    use canadensis_core::time::{milliseconds, Instant, ManualClock, Microseconds32};
+    use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
+    use canadensis_encoding::{
+        DataType, Message, Request, Response, Serialize, SerializeError, WriteCursor,
+    };
+
+    use crate::anonymous::AnonymousPublishError;
+    use crate::{
+        CoreNode, Node, ResponseToken, SendError, StartSendError, SubscribeError, TransferHandler,
+    };
+
+    /// A delimited type with a variable number of bytes and an extent of 4 bytes
+    ///
+    /// This is not a valid DSDL type, because its maximum size is greater than its extent.
+    struct Bytes(Vec<u8>);
+
+    impl DataType for Bytes {
+        const EXTENT_BYTES: Option<u32> = Some(4);
+        const MAX_SERIALIZED_SIZE: usize = 80;
+    }
+
+    impl Serialize for Bytes {
+        fn size_bits(&self) -> usize {
+            8 * self.0.len()
+        }
+
+        fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+            cursor.write_aligned_bytes(&self.0);
+        }
+    }
+
+    impl Message for Bytes {}
+    impl Request for Bytes {}
+    impl Response for Bytes {}
+
+    type TestNode =
+        CoreNode<ManualClock<Microseconds32>, ArrayQueue<Microseconds32, 32>, 2, 2, 2, 2>;
+
+    fn node() -> TestNode {
+        CoreNode::new(
+            ManualClock::new(Microseconds32::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            ArrayQueue::new(),
+        )
+    }
+
+    fn is_extent_error<T>(result: Result<T, SendError>) -> bool {
+        matches!(result, Err(SendError::Serialize(SerializeError::Extent)))
+    }
+
+    #[test]
+    fn payloads_larger_than_extent_are_not_sent() {
+        let mut node = node();
+        let subject = SubjectId::try_from(100).unwrap();
+        let service = ServiceId::try_from(100).unwrap();
+        let publish_token = node
+            .start_publishing(subject, milliseconds(100), Priority::Nominal)
+            .unwrap();
+        let service_token = node
+            .start_sending_requests(service, milliseconds(100), 4, Priority::Nominal)
+            .unwrap();
+        let destination = NodeId::try_from(20).unwrap();
+        let response_token = ResponseToken {
+            service,
+            client: destination,
+            transfer: TransferId::default(),
+            priority: Priority::Nominal,
+        };
+
+        // Small payloads use a buffer on the stack and large ones use the heap. Check both.
+        for &length in [5, 70].iter() {
+            let payload = Bytes(vec![0xaa; length]);
+            assert!(is_extent_error(node.publish(&publish_token, &payload)));
+            assert!(is_extent_error(node.send_request(
+                &service_token,
+                &payload,
+                destination
+            )));
+            assert!(is_extent_error(node.send_response(
+                response_token.clone(),
+                milliseconds(100),
+                &payload
+            )));
+            assert!(node.frame_queue_mut().pop_frame().is_none());
+        }
+
+        let payload = Bytes(vec![0xaa; 4]);
+        node.publish(&publish_token, &payload).unwrap();
+        assert!(node.frame_queue_mut().pop_frame().is_some());
+    }
+    /// A sealed type with a variable number of bytes
+    struct Raw(Vec<u8>);
+
+    impl DataType for Raw {
+        const EXTENT_BYTES: Option<u32> = None;
+        const MAX_SERIALIZED_SIZE: usize = 80;
+    }
+
+    impl Serialize for Raw {
+        fn size_bits(&self) -> usize {
+            8 * self.0.len()
+        }
+
+        fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+            cursor.write_aligned_bytes(&self.0);
+        }
+    }
+
+    impl Message for Raw {}
+
+    #[test]
+    fn publish_anonymous_too_long() {
+        let mut node = node();
+        let subject = SubjectId::try_from(100).unwrap();
+        // 7 bytes and a tail byte fit into one frame
+        assert!(matches!(
+            node.publish_anonymous(subject, &Raw(vec![0; 8]), Priority::Nominal),
+            Err(AnonymousPublishError::Length)
+        ));
+        assert!(node.frame_queue_mut().pop_frame().is_none());
+        // The transfer ID of a message that was not sent is used for the next message
+        node.publish_anonymous(subject, &Raw(vec![0; 7]), Priority::Nominal)
+            .unwrap();
+        let frame = node.frame_queue_mut().pop_frame().unwrap();
+        assert_eq!(8, frame.data().len());
+        assert_eq!(0, frame.data()[7] & 0x1f);
+    }
+
+    #[test]
+    fn publish_anonymous_pseudo_id() {
+        let mut node = node();
+        let subject = SubjectId::try_from(100).unwrap();
+        node.publish_anonymous(subject, &Raw(vec![0xaa; 4]), Priority::Nominal)
+            .unwrap();
+        let id = u32::from(node.frame_queue_mut().pop_frame().unwrap().id());
+        // Anonymous message bit
+        assert_ne!(0, id & (1 << 24));
+        assert_eq!(100, (id >> 8) & 0x1fff);
+        // The source is a pseudo-ID based on the payload (0x55 ^ 0xaa ^ 0xaa ^ 0xaa ^ 0xaa),
+        // not the ID of this node
+        assert_eq!(0x55, id & 0x7f);
+    }
+
+    #[test]
+    fn publish_anonymous_transfer_ids() {
+        let mut node = node();
+        let subject_a = SubjectId::try_from(100).unwrap();
+        let subject_b = SubjectId::try_from(101).unwrap();
+        let transfer_ids = |node: &mut TestNode, subject| {
+            node.publish_anonymous(subject, &Raw(vec![1]), Priority::Nominal)
+                .unwrap();
+            node.frame_queue_mut().pop_frame().unwrap().data()[1] & 0x1f
+        };
+        assert_eq!(0, transfer_ids(&mut node, subject_a));
+        assert_eq!(1, transfer_ids(&mut node, subject_a));
+        assert_eq!(0, transfer_ids(&mut node, subject_b));
+        assert_eq!(2, transfer_ids(&mut node, subject_a));
+        assert_eq!(1, transfer_ids(&mut node, subject_b));
+        // Anonymous messages do not use the transfer IDs of a publisher on the same subject
+        let token = node
+            .start_publishing(subject_a, milliseconds(100), Priority::Nominal)
+            .unwrap();
+        node.publish(&token, &Raw(vec![1])).unwrap();
+        assert_eq!(
+            0,
+            node.frame_queue_mut().pop_frame().unwrap().data()[1] & 0x1f
+        );
+        assert_eq!(3, transfer_ids(&mut node, subject_a));
+    }
+
+    struct Ignore;
+
+    impl<I: Instant, P> TransferHandler<I, P> for Ignore {}
+
+    #[test]
+    fn reset_missed_transfers() {
+        let mut node = node();
+        let subject = SubjectId::try_from(7509).unwrap();
+        let source = NodeId::try_from(42).unwrap();
+        node.subscribe_message(subject, 7, milliseconds(1000))
+            .unwrap();
+        node.enable_gap_detection(subject).unwrap();
+        // Heartbeats from node 42 with transfer IDs 0 and 3
+        for &transfer_id in [0u8, 3].iter() {
+            let frame = Frame::new(
+                Microseconds32::new(0),
+                CanId::try_from(0x107d552a).unwrap(),
+                &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0 | transfer_id],
+            );
+            node.accept_frame(frame, &mut Ignore).unwrap();
+        }
+        assert_eq!(Some(2), node.missed_transfers(subject, source));
+        assert_eq!(Some(2), node.total_missed_transfers(subject));
+        node.reset_missed_transfers(subject);
+        assert_eq!(Some(0), node.missed_transfers(subject, source));
+    }
+
+    #[test]
+    fn stop_sniffing_responses_frees_capacity() {
+        let mut node = node();
+        let services: Vec<ServiceId> = (1..=3).map(|id| ServiceId::try_from(id).unwrap()).collect();
+        node.sniff_responses(services[0], 8, milliseconds(1000))
+            .unwrap();
+        node.sniff_responses(services[1], 8, milliseconds(1000))
+            .unwrap();
+        assert!(matches!(
+            node.sniff_responses(services[2], 8, milliseconds(1000)),
+            Err(SubscribeError::Capacity)
+        ));
+
+        node.stop_sniffing_responses(services[0]);
+        node.sniff_responses(services[2], 8, milliseconds(1000))
+            .unwrap();
+        // Stopping again, or stopping a service that is not sniffed, has no effect
+        node.stop_sniffing_responses(services[0]);
+        assert!(matches!(
+            node.sniff_responses(services[0], 8, milliseconds(1000)),
+            Err(SubscribeError::Capacity)
+        ));
+    }
+
+    #[test]
+    fn sniffing_responses_conflicts_with_requester() {
+        let mut node = node();
+        let requested = ServiceId::try_from(1).unwrap();
+        let sniffed = ServiceId::try_from(2).unwrap();
+        node.start_sending_requests::<Bytes>(requested, milliseconds(1000), 8, Priority::Nominal)
+            .unwrap();
+        assert!(matches!(
+            node.sniff_responses(requested, 8, milliseconds(1000)),
+            Err(SubscribeError::Duplicate)
+        ));
+
+        node.sniff_responses(sniffed, 8, milliseconds(1000))
+            .unwrap();
+        assert!(matches!(
+            node.start_sending_requests::<Bytes>(sniffed, milliseconds(1000), 8, Priority::Nominal),
+            Err(StartSendError::Duplicate)
+        ));
+        node.stop_sniffing_responses(sniffed);
+        node.start_sending_requests::<Bytes>(sniffed, milliseconds(1000), 8, Priority::Nominal)
+            .unwrap();
+        // Stopping has no effect on the requester's response subscription
+        node.stop_sniffing_responses(sniffed);
+        assert!(matches!(
+            node.sniff_responses(sniffed, 8, milliseconds(1000)),
+            Err(SubscribeError::Duplicate)
+        ));
     }
 }
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 extern crate alloc;
 extern crate fallible_collections;
@@ -12,12 +12,27 @@ extern crate canadensis_filter_config;
 
 mod core_node;
 mod hash;
+mod senders;
+mod split;
 
 pub mod anonymous;
+pub mod buffer;
+#[cfg(feature = "async")]
+pub mod call;
+pub mod composite;
 mod publisher;
+pub mod rate_limit;
 mod requester;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub mod shared;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod transfer_ids;
 
-pub use crate::core_node::CoreNode;
+pub use crate::core_node::{CoreNode, DEFAULT_TIMEOUT_MS};
+pub use crate::split::NodeTransmitter;
+
+use crate::anonymous::AnonymousPublishError;
 
 use alloc::vec::Vec;
 use core::marker::PhantomData;
@@ -26,10 +41,20 @@ use canadensis_can::{Frame, OutOfMemoryError};
 use canadensis_core::time::{Clock, Instant};
 use canadensis_core::transfer::*;
 use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
-use canadensis_encoding::{Message, Request, Response, Serialize};
+use canadensis_encoding::{Message, Request, Response, Serialize, SerializeError};
 use canadensis_filter_config::Filter;
 
 /// A token from a request that is needed to send a response
+///
+/// The response inherits the service ID, transfer ID, and priority of the request. The priority
+/// can be changed with [`with_priority`](#method.with_priority), but responding at a lower
+/// priority than the request may cause priority inversion.
+///
+/// The token does not carry a deadline. A request transfer does not tell the server how long the
+/// client will wait for a response, so there is no deadline to inherit, and the receive timestamp
+/// of the request is not a deadline either. The deadline of the response comes from the timeout
+/// passed to [`Node::send_response`]. A handler that wants to limit the time from receiving the
+/// request to sending the response can calculate that timeout from the request timestamp.
 #[derive(Debug, Clone)]
 pub struct ResponseToken {
     /// ID of the service that this is a response for
@@ -42,8 +67,38 @@ pub struct ResponseToken {
     priority: Priority,
 }
 
+impl ResponseToken {
+    /// Returns the ID of the service
+    pub fn service(&self) -> ServiceId {
+        self.service
+    }
+    /// Returns the ID of the node that sent the request, which will receive the response
+    pub fn client(&self) -> NodeId {
+        self.client
+    }
+    /// Returns the transfer ID of the request, which the response will also use
+    pub fn transfer_id(&self) -> TransferId {
+        self.transfer
+    }
+    /// Returns the priority that the response will be sent with
+    ///
+    /// This is the priority of the request unless it has been changed.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+    /// Changes the priority that the response will be sent with
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
 /// Something that may be able to handle incoming transfers
-pub trait TransferHandler<I: Instant> {
+///
+/// `P` is the type that holds the payload of each transfer. Nodes that use the default memory
+/// allocator for incoming transfers provide payloads as `Vec<u8>`. A handler that does not depend
+/// on the payload type can implement `TransferHandler<I, P>` for all `P: AsRef<[u8]>`.
+pub trait TransferHandler<I: Instant, P = Vec<u8>> {
     /// Potentially handles an incoming message transfer
     ///
     /// This function returns true if the message was handled and should not be sent on to other
@@ -53,9 +108,9 @@ pub trait TransferHandler<I: Instant> {
     fn handle_message<N: Node<Instant = I>>(
         &mut self,
         node: &mut N,
-        transfer: &MessageTransfer<Vec<u8>, I>,
+        transfer: &MessageTransfer<P, I>,
     ) -> bool {
-        drop((node, transfer));
+        let _ = (node, transfer);
         false
     }
 
@@ -69,9 +124,9 @@ pub trait TransferHandler<I: Instant> {
         &mut self,
         node: &mut N,
         token: ResponseToken,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool {
-        drop((node, token, transfer));
+        let _ = (node, token, transfer);
         false
     }
 
@@ -84,9 +139,45 @@ pub trait TransferHandler<I: Instant> {
     fn handle_response<N: Node<Instant = I>>(
         &mut self,
         node: &mut N,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        let _ = (node, transfer);
+        false
+    }
+
+    /// Potentially handles an incoming service request that was addressed to another node
+    ///
+    /// The node only receives these requests for services that it is sniffing
+    /// (see [`Node::sniff_requests`](Node#tymethod.sniff_requests)).
+    ///
+    /// This function returns true if the request was handled and should not be sent on to other
+    /// handlers.
+    ///
+    /// The default implementation does nothing and returns false.
+    fn handle_sniffed_request<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        let _ = (node, transfer);
+        false
+    }
+
+    /// Potentially handles an incoming service response that was addressed to another node
+    ///
+    /// The node only receives these responses for services that it is sniffing
+    /// (see [`Node::sniff_responses`](Node#tymethod.sniff_responses)).
+    ///
+    /// This function returns true if the response was handled and should not be sent on to other
+    /// handlers.
+    ///
+    /// The default implementation does nothing and returns false.
+    fn handle_sniffed_response<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool {
-        drop((node, transfer));
+        let _ = (node, transfer);
         false
     }
 
@@ -96,7 +187,7 @@ pub trait TransferHandler<I: Instant> {
     fn chain<H>(self, next: H) -> TransferHandlerChain<Self, H>
     where
         Self: Sized,
-        H: TransferHandler<I>,
+        H: TransferHandler<I, P>,
     {
         TransferHandlerChain::new(self, next)
     }
@@ -118,16 +209,16 @@ impl<H0, H1> TransferHandlerChain<H0, H1> {
     }
 }
 
-impl<I, H0, H1> TransferHandler<I> for TransferHandlerChain<H0, H1>
+impl<I, P, H0, H1> TransferHandler<I, P> for TransferHandlerChain<H0, H1>
 where
     I: Instant,
-    H0: TransferHandler<I>,
-    H1: TransferHandler<I>,
+    H0: TransferHandler<I, P>,
+    H1: TransferHandler<I, P>,
 {
     fn handle_message<N: Node<Instant = I>>(
         &mut self,
         node: &mut N,
-        transfer: &MessageTransfer<Vec<u8>, I>,
+        transfer: &MessageTransfer<P, I>,
     ) -> bool {
         let handled = self.handler0.handle_message(node, transfer);
         if handled {
@@ -141,7 +232,7 @@ where
         &mut self,
         node: &mut N,
         token: ResponseToken,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool {
         let handled = self.handler0.handle_request(node, token.clone(), transfer);
         if handled {
@@ -154,7 +245,7 @@ where
     fn handle_response<N: Node<Instant = I>>(
         &mut self,
         node: &mut N,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool {
         let handled = self.handler0.handle_response(node, transfer);
         if handled {
@@ -163,6 +254,32 @@ where
             self.handler1.handle_response(node, transfer)
         }
     }
+
+    fn handle_sniffed_request<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        let handled = self.handler0.handle_sniffed_request(node, transfer);
+        if handled {
+            true
+        } else {
+            self.handler1.handle_sniffed_request(node, transfer)
+        }
+    }
+
+    fn handle_sniffed_response<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        let handled = self.handler0.handle_sniffed_response(node, transfer);
+        if handled {
+            true
+        } else {
+            self.handler1.handle_sniffed_response(node, transfer)
+        }
+    }
 }
 
 /// A UAVCAN node
@@ -176,6 +293,8 @@ pub trait Node {
     type Instant: Instant;
     /// The queue of outgoing frames that this node uses
     type FrameQueue;
+    /// The type that holds the payloads of incoming transfers
+    type Payload;
 
     /// Handles an incoming frame
     ///
@@ -190,13 +309,17 @@ pub trait Node {
         handler: &mut H,
     ) -> Result<(), OutOfMemoryError>
     where
-        H: TransferHandler<Self::Instant>;
+        H: TransferHandler<Self::Instant, Self::Payload>;
 
     /// Starts publishing messages on subject
     ///
     /// The returned [`PublishToken`] can be used with the [`publish`](#tymethod.publish) function to
     /// send a message.
     ///
+    /// Each message transfer must be sent within `timeout` after it is published, or it will be
+    /// dropped. Publishers that do not need a specific timeout can use
+    /// [`default_timeout`](#tymethod.default_timeout).
+    ///
     /// This function returns an error if memory for the publishing data could not be allocated,
     /// or if the subject ID is already in use.
     fn start_publishing<T>(
@@ -216,7 +339,45 @@ pub trait Node {
     /// Publishes a message
     ///
     /// A token can be created by calling [`start_publishing`](#tymethod.start_publishing).
-    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), OutOfMemoryError>
+    ///
+    /// This function returns an error if memory could not be allocated, or if the message is
+    /// larger than the extent of its type.
+    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), SendError>
+    where
+        T: Message + Serialize;
+
+    /// Publishes a message that must be sent within `timeout` after this function is called,
+    /// instead of the timeout that was provided when starting to publish
+    ///
+    /// This can be used to publish with the current [default timeout](#tymethod.default_timeout),
+    /// which may change after a publisher is created.
+    fn publish_with_timeout<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        payload: &T,
+        timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SendError>
+    where
+        T: Message + Serialize;
+
+    /// Publishes a message anonymously, without the node ID of this node
+    ///
+    /// This works even though this node has a node ID. The message does not need a publish token,
+    /// and the subject is not included in the list of ports that this node publishes on.
+    /// Like the messages that anonymous nodes send, the message must fit into one frame and
+    /// the transport will give it a pseudo-ID as its source.
+    ///
+    /// The deadline for sending the message is the [default timeout](#tymethod.default_timeout)
+    /// after this function is called.
+    ///
+    /// This function returns an error if the serialized message is too long to fit into one
+    /// frame, or if memory could not be allocated.
+    fn publish_anonymous<T>(
+        &mut self,
+        subject: SubjectId,
+        payload: &T,
+        priority: Priority,
+    ) -> Result<(), AnonymousPublishError>
     where
         T: Message + Serialize;
 
@@ -224,6 +385,9 @@ pub trait Node {
     ///
     /// This also subscribes to the corresponding responses.
     ///
+    /// `receive_timeout` is used as the deadline for sending each request, and as the
+    /// timeout for receiving multi-frame responses.
+    ///
     /// This function returns an error if memory could not be allocated,
     /// or if the subject ID is already in use.
     fn start_sending_requests<T>(
@@ -244,42 +408,106 @@ pub trait Node {
     /// Sends a service request to another node
     ///
     /// On success, this function returns the transfer ID of the request.
+    ///
+    /// This function returns an error if memory could not be allocated, or if the request is
+    /// larger than the extent of its type.
     fn send_request<T>(
         &mut self,
         token: &ServiceToken<T>,
         payload: &T,
         destination: NodeId,
-    ) -> Result<TransferId, OutOfMemoryError>
+    ) -> Result<TransferId, SendError>
     where
         T: Request + Serialize;
 
     /// Subscribes to messages on a topic
+    ///
+    /// This function returns an error if memory could not be allocated, or if the node
+    /// has no space for another message subscription.
     fn subscribe_message(
         &mut self,
         subject: SubjectId,
         payload_size_max: usize,
         timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError>;
+    ) -> Result<(), SubscribeError>;
 
     /// Subscribes to requests for a service
+    ///
+    /// This function returns an error if memory could not be allocated, or if the node
+    /// has no space for another service subscription.
     fn subscribe_request(
         &mut self,
         service: ServiceId,
         payload_size_max: usize,
         timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError>;
+    ) -> Result<(), SubscribeError>;
+
+    /// Unsubscribes from messages on a subject
+    ///
+    /// This has no effect if the node is not subscribed to the subject.
+    fn unsubscribe_message(&mut self, subject: SubjectId);
+
+    /// Unsubscribes from requests for a service
+    ///
+    /// This also stops sniffing requests for the service. It has no effect if the node is not
+    /// subscribed to requests for the service.
+    fn unsubscribe_request(&mut self, service: ServiceId);
+
+    /// Subscribes to requests for a service, including requests addressed to other nodes
+    ///
+    /// Requests addressed to this node are passed to
+    /// [`TransferHandler::handle_request`](TransferHandler#method.handle_request) as usual. Requests
+    /// addressed to other nodes are passed to
+    /// [`TransferHandler::handle_sniffed_request`](TransferHandler#method.handle_sniffed_request).
+    fn sniff_requests(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError>;
+
+    /// Subscribes to responses for a service, including responses addressed to other nodes
+    ///
+    /// Responses addressed to this node are passed to
+    /// [`TransferHandler::handle_response`](TransferHandler#method.handle_response) as usual.
+    /// Responses addressed to other nodes are passed to
+    /// [`TransferHandler::handle_sniffed_response`](TransferHandler#method.handle_sniffed_response).
+    ///
+    /// This function returns [`SubscribeError::Duplicate`] if this node sends requests for the
+    /// service, because its requester already subscribes to the responses. While this node is
+    /// sniffing responses for a service, it can't start sending requests for the service.
+    fn sniff_responses(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError>;
+
+    /// Stops sniffing responses for a service
+    ///
+    /// This frees the service subscription that [`sniff_responses`](#tymethod.sniff_responses)
+    /// used. It has no effect if the node is not sniffing responses for the service.
+    fn stop_sniffing_responses(&mut self, service: ServiceId);
 
     /// Responds to a service request
     ///
     /// This function requires a response token to match this response to its corresponding
     /// request. The token is passed to a transfer handler along with a request, so that the handler
     /// can send a response.
+    ///
+    /// The response is sent with the priority from the token, which is the priority of the request
+    /// by default. The deadline for sending the response is `timeout` after this function
+    /// is called. The deadline is not inherited from the request, because requests do not have
+    /// deadlines (see [`ResponseToken`]).
+    ///
+    /// This function returns an error if memory could not be allocated, or if the response is
+    /// larger than the extent of its type.
     fn send_response<T>(
         &mut self,
         token: ResponseToken,
         timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
         payload: &T,
-    ) -> Result<(), OutOfMemoryError>
+    ) -> Result<(), SendError>
     where
         T: Response + Serialize;
 
@@ -297,8 +525,31 @@ pub trait Node {
     /// Returns the identifier of this node
     fn node_id(&self) -> NodeId;
 
+    /// Returns the node-wide default timeout for outgoing transfers
+    ///
+    /// The default value is [`DEFAULT_TIMEOUT_MS`] milliseconds. Code that uses the default
+    /// timeout should call this function each time it sends a transfer, so that changes take
+    /// effect immediately.
+    fn default_timeout(&self) -> <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration;
+
+    /// Sets the node-wide default timeout for outgoing transfers
+    ///
+    /// This does not change the timeouts of existing publishers and requesters. Transfers that
+    /// use the default timeout, like heartbeats and `uavcan.node.GetInfo` responses from
+    /// `canadensis_node`, use the new timeout from the next time they are sent.
+    fn set_default_timeout(
+        &mut self,
+        timeout: <<<Self as Node>::Clock as Clock>::Instant as Instant>::Duration,
+    );
+
     /// Returns a set of filters that accept the frames this node is subscribed to
     fn frame_filters(&self) -> Result<Vec<Filter>, OutOfMemoryError>;
+
+    /// Deletes all incoming transfer sessions that have timed out according to the current time
+    /// from the clock
+    ///
+    /// This frees the memory used for incomplete transfers even when no frames are arriving.
+    fn clean_expired_sessions(&mut self);
 }
 
 /// A token returned from [`Node::start_publishing`](Node#tymethod.start_publishing) that can be
@@ -334,6 +585,8 @@ pub enum StartSendError {
     Memory(OutOfMemoryError),
     /// The provided subject ID or service ID is already in use
     Duplicate,
+    /// The node has no space for another publisher, requester, or subscription
+    Capacity,
 }
 
 impl From<OutOfMemoryError> for StartSendError {
@@ -341,3 +594,56 @@ impl From<OutOfMemoryError> for StartSendError {
         StartSendError::Memory(inner)
     }
 }
+
+impl From<SubscribeError> for StartSendError {
+    fn from(inner: SubscribeError) -> Self {
+        match inner {
+            SubscribeError::Memory(e) => StartSendError::Memory(e),
+            SubscribeError::Capacity => StartSendError::Capacity,
+            SubscribeError::Duplicate => StartSendError::Duplicate,
+        }
+    }
+}
+
+/// Errors that may occur when sending a message, request, or response
+#[derive(Debug)]
+pub enum SendError {
+    /// Memory could not be allocated
+    Memory(OutOfMemoryError),
+    /// The payload, or a delimited composite value inside it, was larger than the extent of its
+    /// type
+    ///
+    /// A receiver would have discarded part of the payload, so nothing was sent.
+    Serialize(SerializeError),
+}
+
+impl From<OutOfMemoryError> for SendError {
+    fn from(inner: OutOfMemoryError) -> Self {
+        SendError::Memory(inner)
+    }
+}
+
+impl From<SerializeError> for SendError {
+    fn from(inner: SerializeError) -> Self {
+        SendError::Serialize(inner)
+    }
+}
+
+/// Errors that may occur when subscribing to messages or service transfers
+#[derive(Debug)]
+pub enum SubscribeError {
+    /// Memory could not be allocated
+    Memory(OutOfMemoryError),
+    /// The node has no space for another subscription
+    Capacity,
+    /// The node already uses the port in a way that conflicts with this subscription
+    ///
+    /// For example, a node can't sniff responses for a service that it sends requests for.
+    Duplicate,
+}
+
+impl From<OutOfMemoryError> for SubscribeError {
+    fn from(inner: OutOfMemoryError) -> Self {
+        SubscribeError::Memory(inner)
+    }
+}
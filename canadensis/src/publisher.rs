@@ -1,4 +1,6 @@
 use crate::core_node::do_serialize;
+use crate::rate_limit::{RateLimit, TokenBucket};
+use crate::SendError;
 use canadensis_can::queue::FrameSink;
 use canadensis_can::{OutOfMemoryError, Transmitter};
 use canadensis_core::time::Instant;
@@ -18,6 +20,8 @@ pub struct Publisher<I: Instant> {
     priority: Priority,
     /// ID of this node
     source: NodeId,
+    /// Rate limit for messages
+    rate_limit: Option<TokenBucket<I>>,
 }
 
 impl<I: Instant> Publisher<I> {
@@ -32,22 +36,55 @@ impl<I: Instant> Publisher<I> {
             timeout,
             priority,
             source: node_id,
+            rate_limit: None,
         }
     }
 
+    /// Returns the transfer ID of the next message
+    pub fn next_transfer_id(&self) -> TransferId {
+        self.next_transfer_id
+    }
+
+    /// Sets the transfer ID of the next message
+    pub fn set_next_transfer_id(&mut self, transfer_id: TransferId) {
+        self.next_transfer_id = transfer_id;
+    }
+
+    /// Returns the timeout for sending a message
+    pub fn timeout(&self) -> I::Duration {
+        self.timeout
+    }
+
+    /// Sets or removes the rate limit
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit<I::Duration>>) {
+        self.rate_limit = limit.map(TokenBucket::new);
+    }
+
+    /// Returns the number of messages that have been dropped because of the rate limit
+    pub fn rate_limited(&self) -> u32 {
+        self.rate_limit.as_ref().map_or(0, TokenBucket::rejected)
+    }
+
+    /// Publishes a message that must be sent within `timeout` after `now`
     pub fn publish<T, Q>(
         &mut self,
         now: I,
+        timeout: I::Duration,
         subject: SubjectId,
         payload: &T,
         transmitter: &mut Transmitter<Q>,
-    ) -> Result<(), OutOfMemoryError>
+    ) -> Result<(), SendError>
     where
         T: Serialize,
         I: Instant,
         Q: FrameSink<I>,
     {
-        let deadline = self.timeout + now;
+        if let Some(rate_limit) = &mut self.rate_limit {
+            if !rate_limit.try_acquire(now) {
+                return Ok(());
+            }
+        }
+        let deadline = timeout + now;
         // Part 1: Serialize
         do_serialize(payload, |payload_bytes| {
             // Part 2: Split into frames and put frames in the queue
@@ -0,0 +1,180 @@
+//!
+//! Rate limiting for outgoing transfers
+//!
+//! A publisher with a rate limit drops messages that would exceed the limit, so that one
+//! publisher that sends too many messages can't use all the bandwidth of the bus.
+//!
+
+use canadensis_core::time::Instant;
+
+/// A limit on the rate of transfers
+///
+/// Up to `burst` transfers can be sent at once. After that, one more transfer can be sent after
+/// each `interval`.
+#[derive(Debug, Copy, Clone)]
+pub struct RateLimit<D> {
+    /// The maximum number of transfers that can be sent at once
+    pub burst: u32,
+    /// The time needed to allow one more transfer
+    pub interval: D,
+}
+
+/// A token bucket that applies a rate limit
+///
+/// Publishers use this internally. It is also available for applications and other libraries
+/// that need to limit the rate of something other than the messages on one subject.
+///
+/// The bucket measures the time since it was last used, so it works correctly with instants
+/// that wrap around as long as it is used at least once per wrap-around period.
+#[derive(Debug, Clone)]
+pub struct TokenBucket<I: Instant> {
+    /// The rate limit
+    limit: RateLimit<I::Duration>,
+    /// The number of transfers that can be sent now
+    tokens: u32,
+    /// The time from which the next token is earned, or None if the bucket has not been used
+    ///
+    /// When the bucket is full, this is the time when it was last used.
+    last_refill: Option<I>,
+    /// The number of transfers that have been rejected
+    rejected: u32,
+}
+
+impl<I: Instant> TokenBucket<I> {
+    /// Creates a full bucket
+    pub fn new(limit: RateLimit<I::Duration>) -> Self {
+        TokenBucket {
+            limit,
+            tokens: limit.burst,
+            last_refill: None,
+            rejected: 0,
+        }
+    }
+
+    /// Takes a token and returns true if a transfer can be sent now, or returns false if the
+    /// transfer must be dropped
+    pub fn try_acquire(&mut self, now: I) -> bool {
+        let mut last_refill = self.last_refill.unwrap_or(now);
+        // Add the tokens that have been earned since the last call
+        while self.tokens < self.limit.burst
+            && now.duration_since(&last_refill) >= self.limit.interval
+        {
+            self.tokens += 1;
+            last_refill = self.limit.interval + last_refill;
+        }
+        if self.tokens == self.limit.burst {
+            // A full bucket does not earn more tokens, so start counting from now
+            last_refill = now;
+        }
+        self.last_refill = Some(last_refill);
+
+        if self.tokens != 0 {
+            self.tokens -= 1;
+            true
+        } else {
+            self.rejected = self.rejected.saturating_add(1);
+            false
+        }
+    }
+
+    /// Returns the number of transfers that have been rejected (saturating at `u32::MAX`)
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+
+    use super::{RateLimit, TokenBucket};
+
+    fn new_bucket() -> TokenBucket<Microseconds32> {
+        TokenBucket::new(RateLimit {
+            burst: 2,
+            interval: MicrosecondDuration32::new(1000),
+        })
+    }
+
+    fn acquire(bucket: &mut TokenBucket<Microseconds32>, now: u32) -> bool {
+        bucket.try_acquire(Microseconds32::new(now))
+    }
+
+    #[test]
+    fn burst_and_refill() {
+        let mut bucket = new_bucket();
+        assert!(acquire(&mut bucket, 0));
+        assert!(acquire(&mut bucket, 0));
+        assert!(!acquire(&mut bucket, 0));
+        assert!(!acquire(&mut bucket, 999));
+        // One token each interval
+        assert!(acquire(&mut bucket, 1000));
+        assert!(!acquire(&mut bucket, 1500));
+        assert!(acquire(&mut bucket, 2000));
+        assert!(!acquire(&mut bucket, 2000));
+        // Two intervals fill the bucket, and no more
+        assert!(acquire(&mut bucket, 10_000));
+        assert!(acquire(&mut bucket, 10_000));
+        assert!(!acquire(&mut bucket, 10_000));
+        assert_eq!(5, bucket.rejected());
+    }
+
+    #[test]
+    fn full_bucket_does_not_save_time() {
+        let mut bucket = new_bucket();
+        assert!(acquire(&mut bucket, 0));
+        // The bucket is full again at 1000, and the time after that does not count
+        assert!(acquire(&mut bucket, 1700));
+        assert!(acquire(&mut bucket, 1700));
+        assert!(!acquire(&mut bucket, 2600));
+        assert!(acquire(&mut bucket, 2700));
+    }
+
+    #[test]
+    fn refill_across_wrap() {
+        let mut bucket = new_bucket();
+        let start = u32::MAX - 500;
+        assert!(acquire(&mut bucket, start));
+        assert!(acquire(&mut bucket, start));
+        assert!(!acquire(&mut bucket, start));
+        // 1000 microseconds after the start, after the clock has wrapped around
+        assert!(!acquire(&mut bucket, 498));
+        assert!(acquire(&mut bucket, 499));
+        assert!(!acquire(&mut bucket, 499));
+    }
+
+    #[test]
+    fn long_idle() {
+        // An empty bucket is used again after more than half of the clock period
+        let mut bucket = new_bucket();
+        assert!(acquire(&mut bucket, 0));
+        assert!(acquire(&mut bucket, 0));
+        let later = u32::MAX / 2 + 10_000;
+        assert!(acquire(&mut bucket, later));
+        assert!(acquire(&mut bucket, later));
+        assert!(!acquire(&mut bucket, later));
+
+        // The same, but the idle time crosses the wrap-around
+        let mut bucket = new_bucket();
+        let start = u32::MAX - 100;
+        assert!(acquire(&mut bucket, start));
+        assert!(acquire(&mut bucket, start));
+        let later = start.wrapping_add(u32::MAX / 2 + 10_000);
+        assert!(acquire(&mut bucket, later));
+        assert!(acquire(&mut bucket, later));
+        assert!(!acquire(&mut bucket, later));
+        assert_eq!(1, bucket.rejected());
+    }
+
+    #[test]
+    fn rejected_saturates() {
+        let mut bucket = TokenBucket::new(RateLimit {
+            burst: 0,
+            interval: MicrosecondDuration32::new(1000),
+        });
+        bucket.rejected = u32::MAX - 1;
+        assert!(!acquire(&mut bucket, 0));
+        assert!(!acquire(&mut bucket, 5000));
+        assert_eq!(u32::MAX, bucket.rejected());
+    }
+}
@@ -6,6 +6,9 @@ use canadensis_core::transfer::{Header, ServiceHeader, Transfer};
 use canadensis_core::{NodeId, Priority, ServiceId, TransferId};
 use canadensis_encoding::Serialize;
 
+use crate::transfer_ids::TransferIdStorage;
+use crate::SendError;
+
 /// Assembles transfers and manages transfer IDs to send service requests
 pub struct Requester<I: Instant> {
     /// The ID of this node
@@ -35,6 +38,30 @@ impl<I: Instant> Requester<I> {
         }
     }
 
+    /// Saves the transfer IDs of the next requests to each destination that has been sent
+    /// a request, or that had a transfer ID restored
+    pub fn save_transfer_ids<S>(&self, service: ServiceId, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        for (destination, transfer_id) in self.next_transfer_ids.tracked() {
+            storage.save_request(service, destination, transfer_id);
+        }
+    }
+
+    /// Loads the transfer IDs of the next requests to each destination
+    pub fn restore_transfer_ids<S>(&mut self, service: ServiceId, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        for destination in 0..=NodeId::MAX.to_u8() {
+            let destination = NodeId::from_truncating(destination);
+            if let Some(transfer_id) = storage.load_request(service, destination) {
+                self.next_transfer_ids.set(destination, transfer_id);
+            }
+        }
+    }
+
     pub fn send<T, Q>(
         &mut self,
         now: I,
@@ -42,7 +69,7 @@ impl<I: Instant> Requester<I> {
         payload: &T,
         destination: NodeId,
         transmitter: &mut Transmitter<Q>,
-    ) -> Result<TransferId, OutOfMemoryError>
+    ) -> Result<TransferId, SendError>
     where
         T: Serialize,
         Q: FrameSink<I>,
@@ -90,6 +117,10 @@ const NUM_TRANSFER_IDS: usize = (NodeId::MAX.to_u8() as usize) + 1;
 /// A map from destination node IDs to transfer IDs of the next transfer
 struct NextTransferIds {
     ids: [TransferId; NUM_TRANSFER_IDS],
+    /// A bit for each destination that has been sent a request or had its transfer ID set
+    ///
+    /// The transfer ID alone does not show this, because it wraps around to the default value.
+    tracked: u128,
 }
 
 impl NextTransferIds {
@@ -97,14 +128,143 @@ impl NextTransferIds {
     pub fn new() -> Self {
         NextTransferIds {
             ids: [TransferId::default(); NUM_TRANSFER_IDS],
+            tracked: 0,
         }
     }
     /// Returns the next transfer ID for the provided node, and increments the stored transfer
     /// ID
     pub fn get_and_increment(&mut self, destination: NodeId) -> TransferId {
+        self.tracked |= 1 << usize::from(destination);
         let entry = &mut self.ids[usize::from(destination)];
         let current = *entry;
         *entry = entry.increment();
         current
     }
+
+    /// Sets the next transfer ID for the provided node
+    pub fn set(&mut self, destination: NodeId, transfer_id: TransferId) {
+        self.tracked |= 1 << usize::from(destination);
+        self.ids[usize::from(destination)] = transfer_id;
+    }
+
+    /// Returns the destinations and next transfer IDs of all tracked destinations
+    fn tracked(&self) -> impl Iterator<Item = (NodeId, TransferId)> + '_ {
+        self.ids
+            .iter()
+            .enumerate()
+            .filter(move |(node, _)| self.tracked & (1 << node) != 0)
+            .map(|(node, id)| (NodeId::from_truncating(node as u8), *id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate canadensis_data_types;
+    extern crate std;
+
+    use core::convert::TryFrom;
+    use std::collections::BTreeMap;
+
+    use canadensis_can::queue::ArrayQueue;
+    use canadensis_can::{Mtu, Transmitter};
+    use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+    use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
+
+    use self::canadensis_data_types::uavcan::node::get_info::GetInfoRequest;
+    use super::Requester;
+    use crate::transfer_ids::TransferIdStorage;
+
+    #[derive(Default)]
+    struct MapStorage {
+        requests: BTreeMap<(ServiceId, NodeId), TransferId>,
+    }
+
+    impl TransferIdStorage for MapStorage {
+        fn save_message(&mut self, _subject: SubjectId, _next_transfer_id: TransferId) {}
+        fn load_message(&mut self, _subject: SubjectId) -> Option<TransferId> {
+            None
+        }
+        fn save_request(
+            &mut self,
+            service: ServiceId,
+            destination: NodeId,
+            next_transfer_id: TransferId,
+        ) {
+            self.requests
+                .insert((service, destination), next_transfer_id);
+        }
+        fn load_request(&mut self, service: ServiceId, destination: NodeId) -> Option<TransferId> {
+            self.requests.get(&(service, destination)).copied()
+        }
+    }
+
+    fn new_requester() -> Requester<Microseconds32> {
+        Requester::new(
+            NodeId::try_from(1).unwrap(),
+            MicrosecondDuration32::new(1000),
+            Priority::Nominal,
+        )
+    }
+
+    #[test]
+    fn save_restore_wrapped_transfer_id() {
+        let service = GetInfoRequest::SERVICE;
+        let wrapped = NodeId::try_from(5).unwrap();
+        let other = NodeId::try_from(6).unwrap();
+        let mut transmitter = Transmitter::new(Mtu::Can8, ArrayQueue::<Microseconds32, 64>::new());
+        let mut requester = new_requester();
+        // 32 requests to one destination wrap its transfer ID back to 0
+        for _ in 0..32 {
+            requester
+                .send(
+                    Microseconds32::new(0),
+                    service,
+                    &GetInfoRequest {},
+                    wrapped,
+                    &mut transmitter,
+                )
+                .unwrap();
+        }
+        requester
+            .send(
+                Microseconds32::new(0),
+                service,
+                &GetInfoRequest {},
+                other,
+                &mut transmitter,
+            )
+            .unwrap();
+
+        let mut storage = MapStorage::default();
+        requester.save_transfer_ids(service, &mut storage);
+        assert_eq!(2, storage.requests.len());
+        assert_eq!(
+            Some(&TransferId::default()),
+            storage.requests.get(&(service, wrapped))
+        );
+        assert_eq!(
+            Some(&TransferId::try_from(1).unwrap()),
+            storage.requests.get(&(service, other))
+        );
+
+        // A restored requester continues from the saved transfer IDs, and saves them again
+        // even though it has not sent any requests
+        let mut restored = new_requester();
+        restored.restore_transfer_ids(service, &mut storage);
+        let mut saved_again = MapStorage::default();
+        restored.save_transfer_ids(service, &mut saved_again);
+        assert_eq!(storage.requests, saved_again.requests);
+        assert_eq!(
+            TransferId::try_from(1).unwrap(),
+            restored
+                .send(
+                    Microseconds32::new(0),
+                    service,
+                    &GetInfoRequest {},
+                    other,
+                    &mut transmitter,
+                )
+                .unwrap()
+        );
+    }
 }
@@ -0,0 +1,222 @@
+//!
+//! Publishers and requesters
+//!
+//! [`CoreNode`](crate::CoreNode) and [`NodeTransmitter`](crate::NodeTransmitter) both send
+//! messages and requests. This module has the code that they share, so that splitting a node
+//! only moves a [`Senders`] from one to the other.
+//!
+
+use core::marker::PhantomData;
+
+use canadensis_can::queue::FrameSink;
+use canadensis_can::Transmitter;
+use canadensis_core::time::Instant;
+use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
+use canadensis_encoding::{Message, Request, Serialize};
+
+use crate::hash::TrivialIndexMap;
+use crate::publisher::Publisher;
+use crate::rate_limit::RateLimit;
+use crate::requester::Requester;
+use crate::transfer_ids::TransferIdStorage;
+use crate::{PublishToken, SendError, ServiceToken, StartSendError};
+
+/// The publishers and requesters of a node
+///
+/// Type parameters:
+/// * `P`: The maximum number of topics that can be published
+/// * `R`: The maximum number of services for which requests can be sent
+pub(crate) struct Senders<I: Instant, const P: usize, const R: usize> {
+    node_id: NodeId,
+    publishers: TrivialIndexMap<SubjectId, Publisher<I>, P>,
+    requesters: TrivialIndexMap<ServiceId, Requester<I>, R>,
+}
+
+impl<I: Instant, const P: usize, const R: usize> Senders<I, P, R> {
+    /// Creates an empty set of publishers and requesters
+    pub fn new(node_id: NodeId) -> Self {
+        Senders {
+            node_id,
+            publishers: TrivialIndexMap::new(),
+            requesters: TrivialIndexMap::new(),
+        }
+    }
+
+    /// Returns the number of publishers
+    pub fn publisher_count(&self) -> usize {
+        self.publishers.len()
+    }
+
+    /// Returns the number of requesters
+    pub fn requester_count(&self) -> usize {
+        self.requesters.len()
+    }
+
+    /// Returns the subjects that messages are published on
+    pub fn subjects(&self) -> impl Iterator<Item = SubjectId> + '_ {
+        self.publishers.keys().copied()
+    }
+
+    /// Returns the services that requests are sent for
+    pub fn services(&self) -> impl Iterator<Item = ServiceId> + '_ {
+        self.requesters.keys().copied()
+    }
+
+    /// Adds a publisher
+    pub fn start_publishing<T>(
+        &mut self,
+        subject: SubjectId,
+        timeout: I::Duration,
+        priority: Priority,
+    ) -> Result<PublishToken<T>, StartSendError>
+    where
+        T: Message,
+    {
+        if self.publishers.contains_key(&subject) {
+            Err(StartSendError::Duplicate)
+        } else {
+            self.publishers
+                .insert(subject, Publisher::new(self.node_id, timeout, priority))
+                .map(|_| PublishToken(subject, PhantomData))
+                .map_err(|_| StartSendError::Capacity)
+        }
+    }
+
+    /// Removes a publisher
+    pub fn stop_publishing(&mut self, subject: SubjectId) {
+        self.publishers.remove(&subject);
+    }
+
+    /// Publishes a message
+    ///
+    /// If `timeout` is None, the publisher's own timeout is used.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no publisher for the token.
+    pub fn publish<T, Q>(
+        &mut self,
+        now: I,
+        timeout: Option<I::Duration>,
+        token: &PublishToken<T>,
+        payload: &T,
+        transmitter: &mut Transmitter<Q>,
+    ) -> Result<(), SendError>
+    where
+        T: Message + Serialize,
+        Q: FrameSink<I>,
+    {
+        let publisher = self
+            .publishers
+            .get_mut(&token.0)
+            .expect("No publisher for token");
+        let timeout = timeout.unwrap_or_else(|| publisher.timeout());
+        publisher.publish(now, timeout, token.0, payload, transmitter)
+    }
+
+    /// Adds a requester
+    pub fn start_sending_requests<T>(
+        &mut self,
+        service: ServiceId,
+        timeout: I::Duration,
+        priority: Priority,
+    ) -> Result<ServiceToken<T>, StartSendError>
+    where
+        T: Request,
+    {
+        if self.requesters.contains_key(&service) {
+            Err(StartSendError::Duplicate)
+        } else {
+            self.requesters
+                .insert(service, Requester::new(self.node_id, timeout, priority))
+                .map(|_| ServiceToken(service, PhantomData))
+                .map_err(|_| StartSendError::Capacity)
+        }
+    }
+
+    /// Removes a requester
+    pub fn stop_sending_requests(&mut self, service: ServiceId) {
+        self.requesters.remove(&service);
+    }
+
+    /// Sends a request and returns its transfer ID
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no requester for the token.
+    pub fn send_request<T, Q>(
+        &mut self,
+        now: I,
+        token: &ServiceToken<T>,
+        payload: &T,
+        destination: NodeId,
+        transmitter: &mut Transmitter<Q>,
+    ) -> Result<TransferId, SendError>
+    where
+        T: Request + Serialize,
+        Q: FrameSink<I>,
+    {
+        let requester = self
+            .requesters
+            .get_mut(&token.0)
+            .expect("No requester for token");
+        requester.send(now, token.0, payload, destination, transmitter)
+    }
+
+    /// Saves the transfer IDs of all publishers and requesters
+    pub fn save_transfer_ids<S>(&self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        for (subject, publisher) in self.publishers.iter() {
+            storage.save_message(*subject, publisher.next_transfer_id());
+        }
+        for (service, requester) in self.requesters.iter() {
+            requester.save_transfer_ids(*service, storage);
+        }
+    }
+
+    /// Loads the transfer IDs of all publishers and requesters that have saved transfer IDs
+    pub fn restore_transfer_ids<S>(&mut self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        for (subject, publisher) in self.publishers.iter_mut() {
+            if let Some(transfer_id) = storage.load_message(*subject) {
+                publisher.set_next_transfer_id(transfer_id);
+            }
+        }
+        for (service, requester) in self.requesters.iter_mut() {
+            requester.restore_transfer_ids(*service, storage);
+        }
+    }
+
+    /// Sets or removes the rate limit for messages published with a token
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no publisher for the token.
+    pub fn set_publish_rate_limit<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        limit: Option<RateLimit<I::Duration>>,
+    ) {
+        self.publishers
+            .get_mut(&token.0)
+            .expect("No publisher for token")
+            .set_rate_limit(limit);
+    }
+
+    /// Returns the number of messages published with a token that have been dropped because
+    /// of the rate limit
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no publisher for the token.
+    pub fn rate_limited_messages<T>(&self, token: &PublishToken<T>) -> u32 {
+        self.publishers
+            .get(&token.0)
+            .expect("No publisher for token")
+            .rate_limited()
+    }
+}
@@ -0,0 +1,168 @@
+//!
+//! Nodes that can be shared between threads or interrupt handlers
+//!
+//! The wrappers in this module lock a node for each operation, so that several threads or tasks
+//! can publish messages and send requests using the same node.
+//!
+//! The wrappers do not implement [`Node`] because some of its functions return references
+//! into the node, which could not be used after the lock is released. Instead, they provide
+//! functions for common operations, and a `with` function for everything else.
+//!
+//! * [`SharedNode`] uses a `std::sync::Mutex` (requires the `std` feature)
+//! * [`CriticalSectionNode`] uses a critical section, which is suitable for microcontrollers
+//!   (requires the `critical-section` feature)
+//!
+
+use canadensis_can::{Frame, OutOfMemoryError};
+use canadensis_core::time::Instant;
+use canadensis_core::{NodeId, TransferId};
+use canadensis_encoding::{Message, Request, Response, Serialize};
+
+use crate::{Node, PublishToken, ResponseToken, SendError, ServiceToken, TransferHandler};
+
+/// Generates the convenience functions that each wrapper provides using its `with` function
+macro_rules! shared_node_functions {
+    () => {
+        /// Publishes a message
+        pub fn publish<T>(&self, token: &PublishToken<T>, payload: &T) -> Result<(), SendError>
+        where
+            T: Message + Serialize,
+        {
+            self.with(|node| node.publish(token, payload))
+        }
+
+        /// Sends a service request to another node
+        ///
+        /// On success, this function returns the transfer ID of the request.
+        pub fn send_request<T>(
+            &self,
+            token: &ServiceToken<T>,
+            payload: &T,
+            destination: NodeId,
+        ) -> Result<TransferId, SendError>
+        where
+            T: Request + Serialize,
+        {
+            self.with(|node| node.send_request(token, payload, destination))
+        }
+
+        /// Sends a response to a request
+        pub fn send_response<T>(
+            &self,
+            token: ResponseToken,
+            timeout: <N::Instant as Instant>::Duration,
+            payload: &T,
+        ) -> Result<(), SendError>
+        where
+            T: Response + Serialize,
+        {
+            self.with(|node| node.send_response(token, timeout, payload))
+        }
+
+        /// Handles an incoming frame
+        ///
+        /// The node stays locked while the handler runs, so the handler must use the node that
+        /// it receives instead of this wrapper.
+        pub fn accept_frame<H>(
+            &self,
+            frame: Frame<N::Instant>,
+            handler: &mut H,
+        ) -> Result<(), OutOfMemoryError>
+        where
+            H: TransferHandler<N::Instant, N::Payload>,
+        {
+            self.with(|node| node.accept_frame(frame, handler))
+        }
+    };
+}
+
+/// A node that can be shared between threads, protected by a mutex
+///
+/// Clones of a `SharedNode` refer to the same node.
+#[cfg(feature = "std")]
+pub struct SharedNode<N> {
+    node: std::sync::Arc<std::sync::Mutex<N>>,
+}
+
+#[cfg(feature = "std")]
+impl<N> Clone for SharedNode<N> {
+    fn clone(&self) -> Self {
+        SharedNode {
+            node: self.node.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N> SharedNode<N>
+where
+    N: Node,
+{
+    /// Wraps a node
+    pub fn new(node: N) -> Self {
+        SharedNode {
+            node: std::sync::Arc::new(std::sync::Mutex::new(node)),
+        }
+    }
+
+    /// Locks the node and calls a function with it
+    ///
+    /// If another thread panicked while it had the node locked, the node is still used.
+    ///
+    /// Calling this function from inside the function passed to another call to `with` on the
+    /// same thread will deadlock or panic.
+    pub fn with<F, R>(&self, operation: F) -> R
+    where
+        F: FnOnce(&mut N) -> R,
+    {
+        let mut node = self
+            .node
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        operation(&mut node)
+    }
+
+    shared_node_functions!();
+}
+
+/// A node that can be shared between interrupt handlers and the main thread, protected by a
+/// critical section
+///
+/// This type can be stored in a `static` variable.
+#[cfg(feature = "critical-section")]
+pub struct CriticalSectionNode<N> {
+    node: critical_section::Mutex<core::cell::RefCell<N>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<N> CriticalSectionNode<N> {
+    /// Wraps a node
+    pub const fn new(node: N) -> Self {
+        CriticalSectionNode {
+            node: critical_section::Mutex::new(core::cell::RefCell::new(node)),
+        }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<N> CriticalSectionNode<N>
+where
+    N: Node,
+{
+    /// Enters a critical section and calls a function with the node
+    ///
+    /// The function should return quickly, because interrupts may be disabled while it runs.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it is called from inside the function passed to another call
+    /// to `with` on the same node.
+    pub fn with<F, R>(&self, operation: F) -> R
+    where
+        F: FnOnce(&mut N) -> R,
+    {
+        critical_section::with(|cs| operation(&mut self.node.borrow_ref_mut(cs)))
+    }
+
+    shared_node_functions!();
+}
@@ -0,0 +1,330 @@
+//!
+//! Separate transmit and receive halves of a node
+//!
+//! [`CoreNode::split`](crate::CoreNode::split) moves the publishers and requesters of a node into
+//! a [`NodeTransmitter`] with its own outgoing frame queue. An application can then publish from
+//! one task or interrupt priority while another task receives frames and runs handlers.
+//!
+
+use canadensis_can::queue::{FrameQueueSource, FrameSink};
+use canadensis_can::{Frame, Mtu, OutOfMemoryError, Transmitter};
+use canadensis_core::time::{Clock, Instant};
+use canadensis_core::{NodeId, ServiceId, SubjectId, TransferId};
+use canadensis_encoding::{Message, Request, Serialize};
+
+use crate::rate_limit::RateLimit;
+use crate::senders::Senders;
+use crate::transfer_ids::TransferIdStorage;
+use crate::{PublishToken, SendError, ServiceToken};
+
+/// The transmit half of a node, which publishes messages and sends requests
+///
+/// A transmitter is created by [`CoreNode::split`](crate::CoreNode::split). It has its own queue
+/// of outgoing frames, so it can be used in a different task or at a different interrupt priority
+/// from the node that receives frames. The driver must send the frames from both queues.
+///
+/// Responses to requests sent from a transmitter are received by the node that it was split from.
+///
+/// The publishers and requesters of a transmitter are fixed when it is created. Their subjects and
+/// services stay reserved in the node that it was split from, so that the two halves never send on
+/// the same port.
+///
+/// Type parameters:
+/// * `C`: The clock used to get the current time
+/// * `Q`: The queue type used to store outgoing frames
+/// * `P`: The maximum number of topics that can be published
+/// * `R`: The maximum number of services for which requests can be sent
+///
+pub struct NodeTransmitter<C, Q, const P: usize, const R: usize>
+where
+    C: Clock,
+{
+    clock: C,
+    transmitter: Transmitter<Q>,
+    node_id: NodeId,
+    senders: Senders<C::Instant, P, R>,
+}
+
+impl<C, Q, const P: usize, const R: usize> NodeTransmitter<C, Q, P, R>
+where
+    C: Clock,
+    Q: FrameSink<C::Instant>,
+{
+    pub(crate) fn new(
+        clock: C,
+        mtu: Mtu,
+        transmit_queue: Q,
+        node_id: NodeId,
+        senders: Senders<C::Instant, P, R>,
+    ) -> Self {
+        NodeTransmitter {
+            clock,
+            transmitter: Transmitter::new(mtu, transmit_queue),
+            node_id,
+            senders,
+        }
+    }
+
+    /// Returns the subjects that this transmitter publishes on
+    pub fn subjects(&self) -> impl Iterator<Item = SubjectId> + '_ {
+        self.senders.subjects()
+    }
+
+    /// Returns the services that this transmitter sends requests for
+    pub fn services(&self) -> impl Iterator<Item = ServiceId> + '_ {
+        self.senders.services()
+    }
+
+    /// Publishes a message
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token was not created by this transmitter or by the node
+    /// before it was split.
+    pub fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), SendError>
+    where
+        T: Message + Serialize,
+    {
+        self.senders.publish(
+            self.clock.now(),
+            None,
+            token,
+            payload,
+            &mut self.transmitter,
+        )
+    }
+
+    /// Saves the transfer IDs of all publishers and requesters
+    pub fn save_transfer_ids<S>(&self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        self.senders.save_transfer_ids(storage)
+    }
+
+    /// Loads the transfer IDs of all publishers and requesters that have saved transfer IDs
+    ///
+    /// This should be called after starting to publish and send requests.
+    pub fn restore_transfer_ids<S>(&mut self, storage: &mut S)
+    where
+        S: TransferIdStorage,
+    {
+        self.senders.restore_transfer_ids(storage)
+    }
+
+    /// Sets or removes the rate limit for messages published with a token
+    ///
+    /// When publishing a message would exceed the limit, the message is dropped and
+    /// the publish function returns `Ok(())`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token is not valid for this transmitter.
+    pub fn set_publish_rate_limit<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        limit: Option<RateLimit<<C::Instant as Instant>::Duration>>,
+    ) {
+        self.senders.set_publish_rate_limit(token, limit)
+    }
+
+    /// Returns the number of messages published with a token that have been dropped because
+    /// of the rate limit
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token is not valid for this transmitter.
+    pub fn rate_limited_messages<T>(&self, token: &PublishToken<T>) -> u32 {
+        self.senders.rate_limited_messages(token)
+    }
+
+    /// Sends a service request to another node
+    ///
+    /// On success, this function returns the transfer ID of the request.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the token was not created by the node before it was split.
+    pub fn send_request<T>(
+        &mut self,
+        token: &ServiceToken<T>,
+        payload: &T,
+        destination: NodeId,
+    ) -> Result<TransferId, SendError>
+    where
+        T: Request + Serialize,
+    {
+        self.senders.send_request(
+            self.clock.now(),
+            token,
+            payload,
+            destination,
+            &mut self.transmitter,
+        )
+    }
+
+    /// Returns a reference to the enclosed clock
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// Returns a mutable reference to the enclosed clock
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Returns a reference to the queue of outgoing frames
+    pub fn frame_queue(&self) -> &Q {
+        self.transmitter.frame_queue()
+    }
+    /// Returns a mutable reference to the queue of outgoing frames
+    pub fn frame_queue_mut(&mut self) -> &mut Q {
+        self.transmitter.frame_queue_mut()
+    }
+
+    /// Returns the identifier of this node
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+}
+
+impl<C, Q, const P: usize, const R: usize> NodeTransmitter<C, Q, P, R>
+where
+    C: Clock,
+    Q: FrameQueueSource<C::Instant>,
+{
+    /// Removes an outgoing frame from the queue and returns it
+    pub fn pop_frame(&mut self) -> Option<Frame<C::Instant>> {
+        self.transmitter.frame_queue_mut().pop_frame()
+    }
+
+    /// Returns a reference to the next outgoing frame in the queue, and does not remove it
+    pub fn peek_frame(&mut self) -> Option<&Frame<C::Instant>> {
+        self.transmitter.frame_queue_mut().peek_frame()
+    }
+
+    /// Returns an outgoing frame to the queue so that it can be transmitted later
+    pub fn return_frame(&mut self, frame: Frame<C::Instant>) -> Result<(), OutOfMemoryError> {
+        self.transmitter.frame_queue_mut().return_frame(frame)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate canadensis_data_types;
+    extern crate std;
+
+    use core::convert::TryFrom;
+    use std::vec;
+    use std::vec::Vec;
+
+    use canadensis_can::queue::{ArrayQueue, FrameQueueSource};
+    use canadensis_can::Mtu;
+    use canadensis_core::time::{milliseconds, ManualClock, Microseconds32};
+    use canadensis_core::{NodeId, Priority, SubjectId};
+
+    use self::canadensis_data_types::uavcan::node::get_info::GetInfoRequest;
+    use self::canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+    use self::canadensis_data_types::uavcan::node::port::list::List;
+    use crate::{CoreNode, Node, StartSendError};
+
+    type Queue = ArrayQueue<Microseconds32, 16>;
+    type TestNode = CoreNode<ManualClock<Microseconds32>, Queue, 2, 2, 2, 2>;
+
+    fn node() -> TestNode {
+        CoreNode::new(
+            ManualClock::new(Microseconds32::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            Queue::new(),
+        )
+    }
+
+    /// Returns the transfer ID of a single-frame transfer
+    fn transfer_id(frame_data: &[u8]) -> u8 {
+        frame_data.last().unwrap() & 0x1f
+    }
+
+    #[test]
+    fn moved_ports_are_reserved() {
+        let mut node = node();
+        let _heartbeat = node
+            .start_publishing::<Heartbeat>(
+                Heartbeat::SUBJECT,
+                milliseconds(1000),
+                Priority::Nominal,
+            )
+            .unwrap();
+        let _get_info = node
+            .start_sending_requests::<GetInfoRequest>(
+                GetInfoRequest::SERVICE,
+                milliseconds(1000),
+                64,
+                Priority::Nominal,
+            )
+            .unwrap();
+        let (transmitter, mut node) = node.split(Queue::new());
+        assert_eq!(
+            vec![Heartbeat::SUBJECT],
+            transmitter.subjects().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![GetInfoRequest::SERVICE],
+            transmitter.services().collect::<Vec<_>>()
+        );
+
+        assert!(matches!(
+            node.start_publishing::<Heartbeat>(
+                Heartbeat::SUBJECT,
+                milliseconds(1000),
+                Priority::Nominal
+            ),
+            Err(StartSendError::Duplicate)
+        ));
+        assert!(matches!(
+            node.start_sending_requests::<GetInfoRequest>(
+                GetInfoRequest::SERVICE,
+                milliseconds(1000),
+                64,
+                Priority::Nominal,
+            ),
+            Err(StartSendError::Duplicate)
+        ));
+
+        // The moved publisher counts against the capacity of 2
+        let _list = node
+            .start_publishing::<List>(List::SUBJECT, milliseconds(1000), Priority::Nominal)
+            .unwrap();
+        assert!(matches!(
+            node.start_publishing::<List>(
+                SubjectId::try_from(100).unwrap(),
+                milliseconds(1000),
+                Priority::Nominal
+            ),
+            Err(StartSendError::Capacity)
+        ));
+    }
+
+    #[test]
+    fn transmitter_continues_transfer_ids() {
+        let mut node = node();
+        let heartbeat = node
+            .start_publishing::<Heartbeat>(
+                Heartbeat::SUBJECT,
+                milliseconds(1000),
+                Priority::Nominal,
+            )
+            .unwrap();
+        node.publish(&heartbeat, &Heartbeat::default()).unwrap();
+        let frame = node.frame_queue_mut().pop_frame().unwrap();
+        assert_eq!(0, transfer_id(frame.data()));
+
+        let (mut transmitter, mut node) = node.split(Queue::new());
+        transmitter
+            .publish(&heartbeat, &Heartbeat::default())
+            .unwrap();
+        // The frame goes into the transmitter's queue, not the node's queue
+        assert!(node.frame_queue_mut().pop_frame().is_none());
+        let frame = transmitter.pop_frame().unwrap();
+        assert_eq!(1, transfer_id(frame.data()));
+    }
+}
@@ -0,0 +1,481 @@
+//!
+//! Asynchronous streams of incoming messages and service requests
+//!
+//! Each function in this module returns a handler and a stream. The handler is a
+//! [`TransferHandler`] that the code that accepts frames passes to
+//! [`Node::accept_frame`]. The stream yields the transfers that the
+//! handler receives, so an asynchronous task can wait for them (for example, with `select!`).
+//!
+//! The handler and stream share a queue with a fixed capacity and an [`OverflowPolicy`].
+//! They use reference counting without locks, so they must be used on one thread
+//! (for example, in tasks of a single-threaded executor). When the handler is dropped, the stream
+//! ends after yielding the remaining queued items.
+//!
+//! Handlers do not subscribe to subjects or services. The application must also subscribe using
+//! the node.
+//!
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use canadensis_core::time::Instant;
+use canadensis_core::transfer::{MessageTransfer, ServiceHeader, ServiceTransfer};
+use canadensis_core::{ServiceId, SubjectId};
+use canadensis_encoding::{Deserialize, Message, Request};
+use futures_core::Stream;
+
+use crate::buffer::{BoundedQueue, OverflowPolicy, Overflowed, ReceivedMessage};
+use crate::{Node, ResponseToken, TransferHandler};
+
+/// A service request and the header of the transfer that contained it
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest<T, I> {
+    /// The transfer header
+    pub header: ServiceHeader<I>,
+    /// The request
+    pub request: T,
+}
+
+/// Creates a handler that receives messages on a subject and a stream that yields them
+///
+/// The stream can hold up to `N` messages that have not been removed.
+pub fn message_stream<T, I, const N: usize>(
+    subject: SubjectId,
+    policy: OverflowPolicy,
+) -> (MessageStreamHandler<T, I, N>, MessageStream<T, I, N>) {
+    let shared = Shared::new(policy);
+    (
+        MessageStreamHandler {
+            subject,
+            shared: shared.clone(),
+        },
+        MessageStream { shared },
+    )
+}
+
+/// Creates a handler that receives requests for a service and a stream that yields them
+///
+/// Each item from the stream contains a token that can be used to respond to the request
+/// with [`Node::send_response`].
+///
+/// The stream can hold up to `N` requests that have not been removed.
+pub fn request_stream<T, I, const N: usize>(
+    service: ServiceId,
+    policy: OverflowPolicy,
+) -> (RequestStreamHandler<T, I, N>, RequestStream<T, I, N>) {
+    let shared = Shared::new(policy);
+    (
+        RequestStreamHandler {
+            service,
+            shared: shared.clone(),
+        },
+        RequestStream { shared },
+    )
+}
+
+/// A reference to the state shared between a handler and a stream
+type SharedRef<E, const N: usize> = Rc<RefCell<Shared<E, N>>>;
+
+/// State shared between a handler and a stream
+struct Shared<E, const N: usize> {
+    /// Items that the stream has not yielded
+    queue: BoundedQueue<E, N>,
+    /// The waker of the task waiting for the next item
+    waker: Option<Waker>,
+}
+
+impl<E, const N: usize> Shared<E, N> {
+    fn new(policy: OverflowPolicy) -> SharedRef<E, N> {
+        Rc::new(RefCell::new(Shared {
+            queue: BoundedQueue::new(policy),
+            waker: None,
+        }))
+    }
+
+    /// Adds an item and wakes the waiting task
+    fn push(shared: &RefCell<Self>, item: E) {
+        let mut shared = shared.borrow_mut();
+        shared.queue.push(item);
+        shared.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Removes the next item, ends the stream if the handler has been dropped, or registers
+    /// the task to be woken when an item arrives
+    fn poll_next(
+        shared: &SharedRef<E, N>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<E, Overflowed>>> {
+        let mut borrowed = shared.borrow_mut();
+        match borrowed.queue.pop() {
+            Ok(Some(item)) => Poll::Ready(Some(Ok(item))),
+            Err(e) => Poll::Ready(Some(Err(e))),
+            Ok(None) => {
+                if Rc::strong_count(shared) == 1 {
+                    // Handler dropped
+                    Poll::Ready(None)
+                } else {
+                    borrowed.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+/// Receives messages and sends them to a [`MessageStream`]
+pub struct MessageStreamHandler<T, I, const N: usize> {
+    subject: SubjectId,
+    shared: SharedRef<ReceivedMessage<T, I>, N>,
+}
+
+impl<T, I, P, const N: usize> TransferHandler<I, P> for MessageStreamHandler<T, I, N>
+where
+    T: Message + Deserialize,
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_message<M: Node<Instant = I>>(
+        &mut self,
+        _node: &mut M,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.subject != self.subject {
+            return false;
+        }
+        if let Ok(message) = T::deserialize_from_bytes(transfer.payload.as_ref()) {
+            Shared::push(
+                &self.shared,
+                ReceivedMessage {
+                    header: transfer.header.clone(),
+                    message,
+                },
+            );
+        }
+        true
+    }
+}
+
+impl<T, I, const N: usize> Drop for MessageStreamHandler<T, I, N> {
+    fn drop(&mut self) {
+        // Wake the stream so that it can end
+        self.shared.borrow_mut().wake();
+    }
+}
+
+/// A stream of messages received on a subject
+///
+/// The stream yields an error when its policy is [`FailFast`](OverflowPolicy::FailFast) and
+/// messages have been dropped.
+pub struct MessageStream<T, I, const N: usize> {
+    shared: SharedRef<ReceivedMessage<T, I>, N>,
+}
+
+impl<T, I, const N: usize> MessageStream<T, I, N> {
+    /// Returns the total number of messages that have been dropped because the stream was full
+    pub fn dropped(&self) -> u32 {
+        self.shared.borrow().queue.dropped
+    }
+}
+
+impl<T, I, const N: usize> Stream for MessageStream<T, I, N> {
+    type Item = Result<ReceivedMessage<T, I>, Overflowed>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Shared::poll_next(&self.shared, cx)
+    }
+}
+
+/// Receives service requests and sends them to a [`RequestStream`]
+pub struct RequestStreamHandler<T, I, const N: usize> {
+    service: ServiceId,
+    shared: SharedRef<(ReceivedRequest<T, I>, ResponseToken), N>,
+}
+
+impl<T, I, P, const N: usize> TransferHandler<I, P> for RequestStreamHandler<T, I, N>
+where
+    T: Request + Deserialize,
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_request<M: Node<Instant = I>>(
+        &mut self,
+        _node: &mut M,
+        token: ResponseToken,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.service != self.service {
+            return false;
+        }
+        if let Ok(request) = T::deserialize_from_bytes(transfer.payload.as_ref()) {
+            let request = ReceivedRequest {
+                header: transfer.header.clone(),
+                request,
+            };
+            Shared::push(&self.shared, (request, token));
+        }
+        true
+    }
+}
+
+impl<T, I, const N: usize> Drop for RequestStreamHandler<T, I, N> {
+    fn drop(&mut self) {
+        // Wake the stream so that it can end
+        self.shared.borrow_mut().wake();
+    }
+}
+
+/// A stream of requests for a service, with the tokens used to respond to them
+///
+/// The stream yields an error when its policy is [`FailFast`](OverflowPolicy::FailFast) and
+/// requests have been dropped.
+pub struct RequestStream<T, I, const N: usize> {
+    shared: SharedRef<(ReceivedRequest<T, I>, ResponseToken), N>,
+}
+
+impl<T, I, const N: usize> RequestStream<T, I, N> {
+    /// Returns the total number of requests that have been dropped because the stream was full
+    pub fn dropped(&self) -> u32 {
+        self.shared.borrow().queue.dropped
+    }
+}
+
+impl<T, I, const N: usize> Stream for RequestStream<T, I, N> {
+    type Item = Result<(ReceivedRequest<T, I>, ResponseToken), Overflowed>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Shared::poll_next(&self.shared, cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate canadensis_data_types;
+    extern crate std;
+
+    use alloc::sync::Arc;
+    use core::convert::TryFrom;
+    use core::pin::Pin;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::task::{Context, Poll, Waker};
+    use std::task::Wake;
+    use std::vec;
+    use std::vec::Vec;
+
+    use canadensis_can::queue::ArrayQueue;
+    use canadensis_can::Mtu;
+    use canadensis_core::time::{ManualClock, Microseconds32};
+    use canadensis_core::transfer::{
+        MessageHeader, MessageTransfer, ServiceHeader, ServiceTransfer,
+    };
+    use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+    use canadensis_encoding::Serialize;
+    use futures_core::Stream;
+
+    use self::canadensis_data_types::uavcan::node::get_info::GetInfoRequest;
+    use self::canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+    use super::{message_stream, request_stream, MessageStream, MessageStreamHandler};
+    use crate::buffer::{OverflowPolicy, Overflowed};
+    use crate::{CoreNode, ResponseToken, TransferHandler};
+
+    type TestNode =
+        CoreNode<ManualClock<Microseconds32>, ArrayQueue<Microseconds32, 4>, 2, 2, 2, 2>;
+    type Handler = MessageStreamHandler<Heartbeat, Microseconds32, 2>;
+    type TestStream = MessageStream<Heartbeat, Microseconds32, 2>;
+
+    /// A waker that counts the times it has been woken
+    #[derive(Default)]
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl CountWaker {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn node() -> TestNode {
+        CoreNode::new(
+            ManualClock::new(Microseconds32::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            ArrayQueue::new(),
+        )
+    }
+
+    fn send_heartbeat(node: &mut TestNode, handler: &mut Handler, subject: SubjectId, uptime: u32) {
+        let heartbeat = Heartbeat {
+            uptime,
+            ..Heartbeat::default()
+        };
+        let mut payload = vec![0u8; heartbeat.size_bits().div_ceil(8)];
+        heartbeat.serialize_to_bytes(&mut payload);
+        let transfer = MessageTransfer {
+            header: MessageHeader {
+                timestamp: Microseconds32::new(0),
+                transfer_id: TransferId::default(),
+                priority: Priority::Nominal,
+                subject,
+                source: Some(NodeId::try_from(20).unwrap()),
+            },
+            payload,
+        };
+        assert_eq!(
+            subject == Heartbeat::SUBJECT,
+            handler.handle_message(node, &transfer)
+        );
+    }
+
+    /// Polls a stream and returns the uptimes of the heartbeats that it yields
+    fn poll(stream: &mut TestStream, waker: &Waker) -> Poll<Option<Result<u32, Overflowed>>> {
+        let mut cx = Context::from_waker(waker);
+        Pin::new(stream)
+            .poll_next(&mut cx)
+            .map(|item| item.map(|result| result.map(|message| message.message.uptime)))
+    }
+
+    #[test]
+    fn wakes_when_message_arrives() {
+        let mut node = node();
+        let (mut handler, mut stream) =
+            message_stream(Heartbeat::SUBJECT, OverflowPolicy::KeepLatest);
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+
+        assert_eq!(Poll::Pending, poll(&mut stream, &waker));
+        assert_eq!(0, count.count());
+        // A message on another subject does not wake the task
+        send_heartbeat(&mut node, &mut handler, SubjectId::try_from(1).unwrap(), 0);
+        assert_eq!(0, count.count());
+
+        send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, 1);
+        assert_eq!(1, count.count());
+        // The waker is used once
+        send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, 2);
+        assert_eq!(1, count.count());
+
+        assert_eq!(Poll::Ready(Some(Ok(1))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Ready(Some(Ok(2))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Pending, poll(&mut stream, &waker));
+        send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, 3);
+        assert_eq!(2, count.count());
+    }
+
+    #[test]
+    fn ends_when_handler_dropped() {
+        let mut node = node();
+        let (mut handler, mut stream) =
+            message_stream(Heartbeat::SUBJECT, OverflowPolicy::KeepLatest);
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+
+        send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, 1);
+        assert_eq!(Poll::Ready(Some(Ok(1))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Pending, poll(&mut stream, &waker));
+        send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, 2);
+        assert_eq!(1, count.count());
+        // Wait again, then drop the handler
+        assert_eq!(Poll::Ready(Some(Ok(2))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Pending, poll(&mut stream, &waker));
+        drop(handler);
+        assert_eq!(2, count.count());
+        assert_eq!(Poll::Ready(None), poll(&mut stream, &waker));
+    }
+
+    #[test]
+    fn queued_messages_yielded_after_handler_dropped() {
+        let mut node = node();
+        let (mut handler, mut stream) =
+            message_stream(Heartbeat::SUBJECT, OverflowPolicy::KeepLatest);
+        let waker = Waker::from(Arc::new(CountWaker::default()));
+        for uptime in 0..3 {
+            send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, uptime);
+        }
+        drop(handler);
+        assert_eq!(1, stream.dropped());
+        let mut uptimes = Vec::new();
+        while let Poll::Ready(Some(item)) = poll(&mut stream, &waker) {
+            uptimes.push(item.unwrap());
+        }
+        assert_eq!(vec![1, 2], uptimes);
+    }
+
+    #[test]
+    fn fail_fast_error() {
+        let mut node = node();
+        let (mut handler, mut stream) =
+            message_stream(Heartbeat::SUBJECT, OverflowPolicy::FailFast);
+        let waker = Waker::from(Arc::new(CountWaker::default()));
+        for uptime in 0..3 {
+            send_heartbeat(&mut node, &mut handler, Heartbeat::SUBJECT, uptime);
+        }
+        assert_eq!(
+            Poll::Ready(Some(Err(Overflowed))),
+            poll(&mut stream, &waker)
+        );
+        assert_eq!(Poll::Ready(Some(Ok(0))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Ready(Some(Ok(1))), poll(&mut stream, &waker));
+        assert_eq!(Poll::Pending, poll(&mut stream, &waker));
+    }
+
+    #[test]
+    fn request_stream_yields_tokens() {
+        let mut node = node();
+        let (mut handler, mut stream) = request_stream::<GetInfoRequest, Microseconds32, 2>(
+            GetInfoRequest::SERVICE,
+            OverflowPolicy::KeepLatest,
+        );
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(Arc::clone(&count));
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+
+        let client = NodeId::try_from(20).unwrap();
+        let transfer_id = TransferId::try_from(7).unwrap();
+        let token = ResponseToken {
+            service: GetInfoRequest::SERVICE,
+            client,
+            transfer: transfer_id,
+            priority: Priority::High,
+        };
+        let transfer = ServiceTransfer {
+            header: ServiceHeader {
+                timestamp: Microseconds32::new(0),
+                transfer_id,
+                priority: Priority::High,
+                service: GetInfoRequest::SERVICE,
+                source: client,
+                destination: NodeId::try_from(10).unwrap(),
+            },
+            payload: [0u8; 0],
+        };
+        assert!(handler.handle_request(&mut node, token, &transfer));
+        assert_eq!(1, count.count());
+
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(Ok((request, token)))) => {
+                assert_eq!(client, request.header.source);
+                assert_eq!(client, token.client());
+                assert_eq!(transfer_id, token.transfer_id());
+            }
+            _ => panic!("Expected a request"),
+        }
+        drop(handler);
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+//!
+//! Persistence of transfer IDs
+//!
+//! Receivers drop a transfer if it has the same transfer ID as the previous transfer on the same
+//! port from the same node, and the previous transfer was received within the transfer-ID timeout.
+//! A node that restarts quickly and begins counting transfer IDs from zero again may have its
+//! first transfers dropped.
+//!
+//! To prevent this, a node can save its transfer IDs using a [`TransferIdStorage`] and restore
+//! them after it restarts. The node should save its transfer IDs shortly before it restarts, or
+//! periodically.
+//!
+
+use canadensis_core::{NodeId, ServiceId, SubjectId, TransferId};
+
+/// Somewhere to store the transfer IDs of outgoing messages and requests
+///
+/// Implementations usually keep the transfer IDs in memory that is not cleared when the node
+/// restarts, or in non-volatile memory.
+pub trait TransferIdStorage {
+    /// Saves the transfer ID of the next message on a subject
+    fn save_message(&mut self, subject: SubjectId, next_transfer_id: TransferId);
+    /// Loads the transfer ID of the next message on a subject, if one has been saved
+    fn load_message(&mut self, subject: SubjectId) -> Option<TransferId>;
+
+    /// Saves the transfer ID of the next request for a service to a destination node
+    fn save_request(
+        &mut self,
+        service: ServiceId,
+        destination: NodeId,
+        next_transfer_id: TransferId,
+    );
+    /// Loads the transfer ID of the next request for a service to a destination node, if one has
+    /// been saved
+    fn load_request(&mut self, service: ServiceId, destination: NodeId) -> Option<TransferId>;
+}
@@ -0,0 +1,102 @@
+//!
+//! Benchmarks for serializing and deserializing data types
+//!
+
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate criterion;
+extern crate heapless;
+
+use canadensis_data_types::bits::BitArray;
+use canadensis_data_types::uavcan::diagnostic::record::Record;
+use canadensis_data_types::uavcan::diagnostic::severity::Severity;
+use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
+use canadensis_data_types::uavcan::node::health::Health;
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_data_types::uavcan::node::mode::Mode;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_data_types::uavcan::node::port::service_id_list::ServiceIdList;
+use canadensis_data_types::uavcan::node::port::subject_id_list::SubjectIdList;
+use canadensis_data_types::uavcan::register::value::Value;
+use canadensis_encoding::{Deserialize, Serialize};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// Benchmarks serializing and deserializing a value
+fn bench_type<T>(c: &mut Criterion, name: &str, value: &T)
+where
+    T: Serialize + Deserialize,
+{
+    let length = value.size_bits().div_ceil(8);
+    let mut bytes = vec![0u8; length];
+    value.serialize_to_bytes(&mut bytes);
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(length as u64));
+    group.bench_function("serialize", |b| {
+        let mut buffer = vec![0u8; length];
+        b.iter(|| black_box(value).serialize_to_bytes(&mut buffer))
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| T::deserialize_from_bytes(black_box(&bytes)).unwrap())
+    });
+    group.finish();
+}
+
+fn heartbeat() -> Heartbeat {
+    Heartbeat::new(3600, Health::Nominal, Mode::Operational)
+}
+
+fn get_info() -> GetInfoResponse {
+    GetInfoResponse {
+        unique_id: [0x5a; 16],
+        name: heapless::Vec::from_slice(b"org.samcrow.canadensis.benchmark").unwrap(),
+        software_image_crc: Some(0x1234_5678_9abc_def0),
+        certificate_of_authenticity: (0..222).map(|i| i as u8).collect(),
+        ..GetInfoResponse::default()
+    }
+}
+
+fn port_list() -> List {
+    let mut publishers = BitArray::new(usize::from(SubjectIdList::CAPACITY));
+    let mut subscribers = BitArray::new(usize::from(SubjectIdList::CAPACITY));
+    for subject in (0..usize::from(SubjectIdList::CAPACITY)).step_by(7) {
+        publishers.set(subject, true);
+        subscribers.set((subject + 3) % usize::from(SubjectIdList::CAPACITY), true);
+    }
+    let mut clients = ServiceIdList::default();
+    clients.mask.fill(true);
+    List {
+        publishers: SubjectIdList::Mask(publishers),
+        subscribers: SubjectIdList::Mask(subscribers),
+        clients,
+        servers: ServiceIdList::default(),
+    }
+}
+
+fn record() -> Record {
+    Record {
+        timestamp: Default::default(),
+        severity: Severity::Warning,
+        text: (0..255).map(|i| b'a' + (i % 26) as u8).collect(),
+    }
+}
+
+fn real32_array() -> Value {
+    Value::Real32((0..64).map(|i| i as f32 * 0.25).collect())
+}
+
+fn real64_array() -> Value {
+    Value::Real64((0..32).map(|i| f64::from(i) * 0.125).collect())
+}
+
+fn bench_encoding(c: &mut Criterion) {
+    bench_type(c, "Heartbeat", &heartbeat());
+    bench_type(c, "GetInfoResponse", &get_info());
+    bench_type(c, "port.List", &port_list());
+    bench_type(c, "diagnostic.Record", &record());
+    bench_type(c, "Value real32[64]", &real32_array());
+    bench_type(c, "Value real64[32]", &real64_array());
+}
+
+criterion_group!(benches, bench_encoding);
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+//!
+//! Benchmarks for the CAN transport: transmitting, receiving, and transfer CRCs
+//!
+
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate criterion;
+
+use std::convert::TryFrom;
+
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{Frame, Mtu, Receiver, TransferCrc, Transmitter};
+use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+use canadensis_core::transfer::{Header, MessageHeader, Transfer};
+use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Payload lengths to benchmark, from a single Classic CAN frame to a long multi-frame transfer
+const PAYLOAD_LENGTHS: [usize; 5] = [7, 63, 256, 1024, 4096];
+
+const SUBJECT: SubjectId = SubjectId::from_truncating(1000);
+
+fn mtus() -> Vec<Mtu> {
+    #[allow(unused_mut)]
+    let mut mtus = vec![Mtu::Can8];
+    #[cfg(feature = "can-fd")]
+    mtus.push(Mtu::CanFd64);
+    mtus
+}
+
+fn make_payload(length: usize) -> Vec<u8> {
+    (0..length).map(|i| i as u8).collect()
+}
+
+fn make_transfer(payload: &[u8]) -> Transfer<&[u8], Microseconds32> {
+    Transfer {
+        header: Header::Message(MessageHeader {
+            timestamp: Microseconds32::new(0),
+            transfer_id: TransferId::try_from(3).unwrap(),
+            priority: Priority::Nominal,
+            subject: SUBJECT,
+            source: Some(NodeId::from_truncating(42)),
+        }),
+        payload,
+    }
+}
+
+/// Splits a transfer into frames and returns the frames
+fn make_frames(mtu: Mtu, payload: &[u8]) -> Vec<Frame<Microseconds32>> {
+    let mut transmitter = Transmitter::new(mtu, HeapQueue::new());
+    transmitter.push(make_transfer(payload)).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = transmitter.frame_queue_mut().pop_frame() {
+        frames.push(frame);
+    }
+    frames
+}
+
+fn bench_transmit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Transmitter::push");
+    for mtu in mtus() {
+        for &length in PAYLOAD_LENGTHS.iter() {
+            let payload = make_payload(length);
+            let mut transmitter = Transmitter::new(mtu, HeapQueue::new());
+            group.throughput(Throughput::Bytes(length as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("MTU {}", mtu.as_bytes()), length),
+                &payload,
+                |b, payload| {
+                    b.iter(|| {
+                        transmitter.push(make_transfer(black_box(payload))).unwrap();
+                        // Empty the queue so that it does not grow
+                        while let Some(frame) = transmitter.frame_queue_mut().pop_frame() {
+                            black_box(frame);
+                        }
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_receive(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Receiver::accept");
+    for mtu in mtus() {
+        for &length in PAYLOAD_LENGTHS.iter() {
+            let frames = make_frames(mtu, &make_payload(length));
+            let mut receiver = Receiver::new(NodeId::from_truncating(1), mtu);
+            receiver
+                .subscribe_message(SUBJECT, length, MicrosecondDuration32::new(1_000_000))
+                .unwrap();
+            group.throughput(Throughput::Bytes(length as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("MTU {}", mtu.as_bytes()), length),
+                &frames,
+                |b, frames| {
+                    b.iter(|| {
+                        let mut transfer = None;
+                        for frame in frames.iter() {
+                            transfer = receiver.accept(black_box(frame.clone())).unwrap();
+                        }
+                        black_box(transfer.expect("No transfer received"))
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("TransferCrc::add_bytes");
+    for &length in PAYLOAD_LENGTHS.iter() {
+        let payload = make_payload(length);
+        group.throughput(Throughput::Bytes(length as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(length),
+            &payload,
+            |b, payload| {
+                b.iter(|| {
+                    let mut crc = TransferCrc::new();
+                    crc.add_bytes(black_box(payload));
+                    crc.get()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_transmit, bench_receive, bench_crc);
+criterion_main!(benches);
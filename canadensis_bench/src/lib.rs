@@ -0,0 +1,12 @@
+//!
+//! Benchmarks for the hot paths of canadensis
+//!
+//! This crate has no library code. The benchmarks are in the `benches` folder:
+//!
+//! * `transport`: Splitting transfers into frames, reassembling transfers, and calculating
+//!   transfer CRCs
+//! * `encoding`: Serializing and deserializing representative data types
+//!
+//! Run them with `cargo bench -p canadensis_bench`. Add `--features can-fd` to also benchmark
+//! transfers with a 64-byte MTU.
+//!
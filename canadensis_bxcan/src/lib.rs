@@ -12,10 +12,12 @@ extern crate log;
 extern crate nb;
 
 pub mod pnp;
+pub mod unique_id;
 
 use bxcan::filter::{BankConfig, Mask32};
 use bxcan::{Can, ExtendedId, FilterOwner, Instance, Mailbox};
 use canadensis::{Node, TransferHandler};
+use canadensis_can::bus_status::{BusMonitor, BusStatus, ErrorCounters, ErrorState};
 use canadensis_can::queue::FrameQueueSource;
 use canadensis_can::OutOfMemoryError;
 use canadensis_core::time::{Clock, Instant};
@@ -62,11 +64,28 @@ where
         configure_node_filters(&self.node, &mut self.can)
     }
 
+    /// Enables or disables silent (listen-only) mode on the CAN peripheral
+    ///
+    /// In silent mode, the peripheral receives frames but only sends recessive bits, so it does not
+    /// transmit frames, acknowledge frames from other nodes, or send error frames. A node that
+    /// uses a [`ListenOnlyQueue`](canadensis_can::queue::ListenOnlyQueue) and a peripheral in
+    /// silent mode can't affect the bus at all.
+    ///
+    /// This function briefly takes the peripheral off the bus, and blocks until it has
+    /// synchronized with the bus again.
+    pub fn set_silent(&mut self, silent: bool) {
+        self.can.modify_config().set_silent(silent);
+        match nb::block!(self.can.enable()) {
+            Ok(()) => {}
+            Err(infallible) => match infallible {},
+        }
+    }
+
     /// Receives all incoming CAN frames from the CAN peripheral, converts them into transfers,
     /// and passes all completed transfers to the provided handler
     pub fn receive_frames<H>(&mut self, handler: &mut H) -> Result<(), OutOfMemoryError>
     where
-        H: TransferHandler<N::Instant>,
+        H: TransferHandler<N::Instant, N::Payload>,
     {
         loop {
             match self.can.receive() {
@@ -154,6 +173,52 @@ where
     Ok(())
 }
 
+impl<N, C> BusMonitor for BxCanNode<N, C>
+where
+    N: Node,
+    C: Instance,
+{
+    fn bus_status(&mut self) -> BusStatus {
+        read_bus_status::<C>()
+    }
+
+    /// Leaves and re-enters normal mode
+    ///
+    /// The bxCAN peripheral is configured to recover from bus-off automatically, so this is
+    /// necessary only if automatic recovery does not work.
+    fn restart(&mut self) {
+        drop(self.can.modify_config());
+        let _ = nb::block!(self.can.enable());
+    }
+}
+
+/// Offset of the error status register (CAN_ESR) in the peripheral register block
+const ESR_OFFSET: usize = 0x18;
+
+/// Reads the error state and counters from the error status register
+fn read_bus_status<C: Instance>() -> BusStatus {
+    // Safety: The Instance trait requires that REGISTERS points to the peripheral registers.
+    // The error status register is read-only except for the last error code field, so reading
+    // it does not interfere with anything else.
+    let esr = unsafe {
+        core::ptr::read_volatile((C::REGISTERS as *const u8).add(ESR_OFFSET) as *const u32)
+    };
+    let state = if esr & (1 << 2) != 0 {
+        ErrorState::BusOff
+    } else if esr & (1 << 1) != 0 {
+        ErrorState::Passive
+    } else {
+        ErrorState::Active
+    };
+    BusStatus {
+        state,
+        counters: ErrorCounters {
+            transmit: ((esr >> 16) & 0xff) as u16,
+            receive: (esr >> 24) as u16,
+        },
+    }
+}
+
 /// Puts one frame in a transmit mailbox to be sent
 ///
 /// If all mailboxes are full with frames of equal or greater priority, this function returns
@@ -229,6 +294,15 @@ pub struct DeadlineTracker<I> {
     deadlines: [Option<I>; 3],
 }
 
+impl<I> Default for DeadlineTracker<I>
+where
+    I: Clone,
+{
+    fn default() -> Self {
+        DeadlineTracker::new()
+    }
+}
+
 impl<I> DeadlineTracker<I>
 where
     I: Clone,
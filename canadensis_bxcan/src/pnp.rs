@@ -89,6 +89,5 @@ where
     let now = clock.now();
     let frame = client.assemble_request(now);
     let bxcan_frame = crate::uavcan_frame_to_bxcan(&frame);
-    can.transmit(&bxcan_frame)
-        .map(|displaced_frame| drop(displaced_frame))
+    can.transmit(&bxcan_frame).map(drop)
 }
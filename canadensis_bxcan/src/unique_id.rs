@@ -0,0 +1,55 @@
+//!
+//! Unique IDs from the unique device ID registers of STM32 microcontrollers
+//!
+
+use canadensis_core::unique_id::UniqueIdProvider;
+
+/// Reads the 96-bit unique device ID of an STM32 microcontroller
+///
+/// The unique ID of the node contains the 12 bytes of the device ID, in the order that they are
+/// stored in memory, followed by four zero bytes.
+///
+/// The address of the device ID registers depends on the microcontroller family. The associated
+/// constants have the addresses for some families. Other families can be found in the
+/// "Device electronic signature" section of the reference manual.
+#[derive(Debug, Copy, Clone)]
+pub struct Stm32UniqueId {
+    address: usize,
+}
+
+impl Stm32UniqueId {
+    /// The address of the device ID registers on STM32F0 and STM32F3 microcontrollers
+    pub const F0_F3: usize = 0x1fff_f7ac;
+    /// The address of the device ID registers on STM32F1 microcontrollers
+    pub const F1: usize = 0x1fff_f7e8;
+    /// The address of the device ID registers on STM32F2 and STM32F4 microcontrollers
+    pub const F2_F4: usize = 0x1fff_7a10;
+    /// The address of the device ID registers on STM32F7 microcontrollers
+    pub const F7: usize = 0x1ff0_f420;
+    /// The address of the device ID registers on STM32L4 and STM32G4 microcontrollers
+    pub const L4_G4: usize = 0x1fff_7590;
+
+    /// Creates a unique ID provider that reads the 12 bytes of the device ID starting at
+    /// the provided address
+    ///
+    /// # Safety
+    ///
+    /// The address must be the address of the device ID registers on the microcontroller that
+    /// this code runs on.
+    pub const unsafe fn new(address: usize) -> Self {
+        Stm32UniqueId { address }
+    }
+}
+
+impl UniqueIdProvider for Stm32UniqueId {
+    fn unique_id(&self) -> [u8; 16] {
+        let mut id = [0u8; 16];
+        let registers = self.address as *const u32;
+        for (i, chunk) in id[..12].chunks_exact_mut(4).enumerate() {
+            // Safety: The address was checked when this provider was created
+            let word = unsafe { core::ptr::read_volatile(registers.add(i)) };
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        id
+    }
+}
@@ -0,0 +1,179 @@
+//!
+//! Bit rate detection
+//!
+//! A [`BitRateDetector`] finds the bit rate of a CAN bus by trying each candidate bit rate in
+//! turn. The driver should put the CAN controller in listen-only (silent) mode while detecting, so
+//! that it does not send error frames or acknowledgements at an incorrect bit rate. A controller
+//! with the wrong bit rate will not receive any valid frames, so the first bit rate that receives
+//! enough valid frames is the correct one.
+//!
+
+use core::cmp::Ordering;
+
+use canadensis_core::time::Instant;
+
+/// Commonly used CAN bit rates, from fastest to slowest
+pub const COMMON_BIT_RATES: [u32; 6] = [1_000_000, 500_000, 250_000, 125_000, 100_000, 50_000];
+
+/// The result of a bit rate detection step
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Detection {
+    /// The detector is still trying the current bit rate
+    Listening,
+    /// No frames were received at the previous bit rate. The driver should switch to this bit
+    /// rate.
+    Switch(u32),
+    /// This bit rate has received enough valid frames and is the bit rate of the bus
+    Detected(u32),
+}
+
+/// Cycles through candidate bit rates until one receives valid frames
+///
+/// To detect the bit rate:
+/// 1. Put the CAN controller in listen-only mode with the bit rate returned by
+///    [`bit_rate`](#method.bit_rate)
+/// 2. Call [`frame_received`](#method.frame_received) for each valid frame that the controller
+///    receives
+/// 3. Call [`poll`](#method.poll) periodically, and switch the controller to a new bit rate when
+///    it returns [`Detection::Switch`]
+/// 4. When either function returns [`Detection::Detected`], configure the controller in normal
+///    mode with the detected bit rate
+///
+/// If no bit rate receives any frames (for example, because no other nodes are transmitting),
+/// the detector keeps cycling through the candidates.
+#[derive(Debug, Clone)]
+pub struct BitRateDetector<'c, I: Instant> {
+    /// The bit rates to try
+    candidates: &'c [u32],
+    /// The index in candidates of the current bit rate
+    index: usize,
+    /// The time to listen at each bit rate
+    dwell: I::Duration,
+    /// The time when the detector will switch to the next bit rate
+    switch_time: I,
+    /// The number of valid frames received at the current bit rate
+    frames: u32,
+    /// The number of valid frames required to detect a bit rate
+    required_frames: u32,
+    /// The detected bit rate
+    detected: Option<u32>,
+}
+
+impl<'c, I: Instant> BitRateDetector<'c, I> {
+    /// Creates a detector that starts listening at the first candidate bit rate
+    ///
+    /// candidates: The bit rates to try, in order
+    ///
+    /// dwell: The time to listen at each bit rate. This should be longer than the interval between
+    /// messages that other nodes send (such as heartbeats, which are sent every second).
+    ///
+    /// required_frames: The number of valid frames that must be received to detect a bit rate
+    /// (at least 1)
+    ///
+    /// now: The current time
+    ///
+    /// # Panics
+    ///
+    /// This function panics if candidates is empty.
+    pub fn new(candidates: &'c [u32], dwell: I::Duration, required_frames: u32, now: I) -> Self {
+        assert!(!candidates.is_empty(), "No candidate bit rates");
+        BitRateDetector {
+            candidates,
+            index: 0,
+            dwell,
+            switch_time: dwell + now,
+            frames: 0,
+            required_frames: required_frames.max(1),
+            detected: None,
+        }
+    }
+
+    /// Returns the bit rate that the CAN controller should currently use
+    pub fn bit_rate(&self) -> u32 {
+        self.candidates[self.index]
+    }
+
+    /// Returns the detected bit rate, or None if detection has not finished
+    pub fn detected(&self) -> Option<u32> {
+        self.detected
+    }
+
+    /// Records that the CAN controller received a valid frame at the current bit rate
+    pub fn frame_received(&mut self) -> Detection {
+        if let Some(detected) = self.detected {
+            return Detection::Detected(detected);
+        }
+        self.frames = self.frames.saturating_add(1);
+        if self.frames >= self.required_frames {
+            let bit_rate = self.bit_rate();
+            log::info!("Detected bit rate {}", bit_rate);
+            self.detected = Some(bit_rate);
+            Detection::Detected(bit_rate)
+        } else {
+            Detection::Listening
+        }
+    }
+
+    /// Records that the CAN controller detected an error at the current bit rate
+    ///
+    /// An incorrect bit rate usually causes errors. This function moves to the next bit rate
+    /// immediately instead of waiting for the dwell time to expire.
+    pub fn error_detected(&mut self, now: I) -> Detection {
+        match self.detected {
+            Some(detected) => Detection::Detected(detected),
+            None => self.switch(now),
+        }
+    }
+
+    /// Checks if the dwell time at the current bit rate has expired, and moves to the next
+    /// bit rate if it has
+    pub fn poll(&mut self, now: I) -> Detection {
+        if let Some(detected) = self.detected {
+            Detection::Detected(detected)
+        } else if now.overflow_safe_compare(&self.switch_time) != Ordering::Less {
+            self.switch(now)
+        } else {
+            Detection::Listening
+        }
+    }
+
+    fn switch(&mut self, now: I) -> Detection {
+        self.index = (self.index + 1) % self.candidates.len();
+        self.frames = 0;
+        self.switch_time = self.dwell + now;
+        Detection::Switch(self.bit_rate())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitRateDetector, Detection, COMMON_BIT_RATES};
+    use canadensis_core::time::{milliseconds, Microseconds32};
+
+    type TestInstant = Microseconds32;
+
+    fn ms(milliseconds: u32) -> TestInstant {
+        TestInstant::new(milliseconds * 1000)
+    }
+
+    #[test]
+    fn detect() {
+        let mut detector = BitRateDetector::new(&COMMON_BIT_RATES, milliseconds(100), 2, ms(0));
+        assert_eq!(detector.bit_rate(), 1_000_000);
+        assert_eq!(detector.poll(ms(50)), Detection::Listening);
+        assert_eq!(detector.poll(ms(100)), Detection::Switch(500_000));
+        assert_eq!(detector.error_detected(ms(110)), Detection::Switch(250_000));
+        assert_eq!(detector.frame_received(), Detection::Listening);
+        assert_eq!(detector.frame_received(), Detection::Detected(250_000));
+        assert_eq!(detector.poll(ms(1000)), Detection::Detected(250_000));
+        assert_eq!(detector.detected(), Some(250_000));
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut detector = BitRateDetector::new(&[500_000, 125_000], milliseconds(10), 1, ms(0));
+        assert_eq!(detector.poll(ms(10)), Detection::Switch(125_000));
+        assert_eq!(detector.poll(ms(20)), Detection::Switch(500_000));
+        assert_eq!(detector.frame_received(), Detection::Detected(500_000));
+    }
+}
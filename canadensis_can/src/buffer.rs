@@ -0,0 +1,262 @@
+//!
+//! Memory for received transfers
+//!
+//! A [`Receiver`](crate::Receiver) uses a [`BufferAllocator`] to get memory for the payloads of
+//! incoming transfers. The default [`HeapAllocator`] uses `Vec`s from the global allocator.
+//!
+//! A [`BlockPool`] contains a fixed number of fixed-size blocks. When a receiver uses a block pool,
+//! the memory used to reassemble transfers has a size that is known at compile time, and running
+//! out of blocks is reported in the same way as any other memory allocation failure.
+//!
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::ops::Deref;
+
+use fallible_collections::FallibleVec;
+
+use crate::OutOfMemoryError;
+
+#[cfg(target_has_atomic = "8")]
+pub use self::pool::{BlockPool, PoolBuffer};
+
+/// A buffer that holds the payload of a transfer
+pub trait TransferBuffer: Deref<Target = [u8]> + Debug {
+    /// Appends bytes to the end of this buffer
+    ///
+    /// This function returns an error if the buffer does not have enough space and cannot
+    /// allocate more memory.
+    fn try_extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), OutOfMemoryError>;
+    /// Shortens this buffer, keeping the first `length` bytes
+    ///
+    /// If `length` is greater than or equal to the current length, this has no effect.
+    fn truncate(&mut self, length: usize);
+}
+
+/// Something that can allocate buffers for received transfers
+pub trait BufferAllocator {
+    /// The type of buffer that this allocator provides
+    type Buffer: TransferBuffer;
+
+    /// Allocates an empty buffer that can hold at least `capacity` bytes
+    fn allocate(&mut self, capacity: usize) -> Result<Self::Buffer, OutOfMemoryError>;
+}
+
+/// An allocator that uses `Vec`s from the global allocator
+#[derive(Debug, Default, Clone)]
+pub struct HeapAllocator;
+
+impl BufferAllocator for HeapAllocator {
+    type Buffer = Vec<u8>;
+
+    fn allocate(&mut self, capacity: usize) -> Result<Self::Buffer, OutOfMemoryError> {
+        Ok(FallibleVec::try_with_capacity(capacity)?)
+    }
+}
+
+impl TransferBuffer for Vec<u8> {
+    fn try_extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), OutOfMemoryError> {
+        Ok(FallibleVec::try_extend_from_slice(self, bytes)?)
+    }
+
+    fn truncate(&mut self, length: usize) {
+        Vec::truncate(self, length)
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+mod pool {
+    use core::cell::UnsafeCell;
+    use core::fmt;
+    use core::ops::Deref;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::{BufferAllocator, TransferBuffer};
+    use crate::OutOfMemoryError;
+
+    /// A pool of `N` blocks of memory, each `B` bytes long
+    ///
+    /// A pool is usually declared as a `static` item, and a receiver uses a `&'static BlockPool`
+    /// as its allocator. Each block holds one transfer, so `B` must be at least as large as the
+    /// largest payload that the receiver is subscribed to, including space for padding and the
+    /// transfer CRC.
+    ///
+    /// Blocks are returned to the pool when the corresponding [`PoolBuffer`]s are dropped.
+    pub struct BlockPool<const B: usize, const N: usize> {
+        /// The blocks of memory
+        blocks: [UnsafeCell<[u8; B]>; N],
+        /// For each block, true if the block is currently allocated
+        allocated: [AtomicBool; N],
+    }
+
+    // Safety: Each block is accessed only through the PoolBuffer that has exclusive access to it,
+    // as recorded in self.allocated.
+    unsafe impl<const B: usize, const N: usize> Sync for BlockPool<B, N> {}
+
+    impl<const B: usize, const N: usize> BlockPool<B, N> {
+        // These constants are used only to initialize the arrays
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY_BLOCK: UnsafeCell<[u8; B]> = UnsafeCell::new([0; B]);
+        #[allow(clippy::declare_interior_mutable_const)]
+        const NOT_ALLOCATED: AtomicBool = AtomicBool::new(false);
+
+        /// Creates a pool with all blocks available
+        pub const fn new() -> Self {
+            BlockPool {
+                blocks: [Self::EMPTY_BLOCK; N],
+                allocated: [Self::NOT_ALLOCATED; N],
+            }
+        }
+
+        /// Returns the size of each block in bytes
+        pub fn block_size(&self) -> usize {
+            B
+        }
+
+        /// Returns the number of blocks that are currently allocated
+        pub fn allocated_blocks(&self) -> usize {
+            self.allocated
+                .iter()
+                .filter(|allocated| allocated.load(Ordering::Relaxed))
+                .count()
+        }
+
+        /// Returns the number of blocks that are currently available
+        pub fn free_blocks(&self) -> usize {
+            N - self.allocated_blocks()
+        }
+    }
+
+    impl<const B: usize, const N: usize> Default for BlockPool<B, N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<const B: usize, const N: usize> fmt::Debug for BlockPool<B, N> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("BlockPool")
+                .field("block_size", &B)
+                .field("blocks", &N)
+                .field("allocated_blocks", &self.allocated_blocks())
+                .finish()
+        }
+    }
+
+    impl<'p, const B: usize, const N: usize> BufferAllocator for &'p BlockPool<B, N> {
+        type Buffer = PoolBuffer<'p, B>;
+
+        fn allocate(&mut self, capacity: usize) -> Result<Self::Buffer, OutOfMemoryError> {
+            if capacity > B {
+                return Err(OutOfMemoryError);
+            }
+            let pool: &'p BlockPool<B, N> = self;
+            for (block, allocated) in pool.blocks.iter().zip(pool.allocated.iter()) {
+                if allocated
+                    .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    // Safety: This block was not allocated, and now it is allocated only to this
+                    // buffer. Nothing else will access it until the buffer releases it.
+                    let block = unsafe { &mut *block.get() };
+                    return Ok(PoolBuffer {
+                        block,
+                        length: 0,
+                        allocated,
+                    });
+                }
+            }
+            Err(OutOfMemoryError)
+        }
+    }
+
+    /// A buffer that uses a block from a [`BlockPool`]
+    pub struct PoolBuffer<'p, const B: usize> {
+        /// The memory block
+        block: &'p mut [u8; B],
+        /// The number of valid bytes at the beginning of the block
+        length: usize,
+        /// The allocation flag for the block, to be cleared when this buffer is dropped
+        allocated: &'p AtomicBool,
+    }
+
+    impl<const B: usize> Deref for PoolBuffer<'_, B> {
+        type Target = [u8];
+
+        fn deref(&self) -> &Self::Target {
+            &self.block[..self.length]
+        }
+    }
+
+    impl<const B: usize> AsRef<[u8]> for PoolBuffer<'_, B> {
+        fn as_ref(&self) -> &[u8] {
+            self
+        }
+    }
+
+    impl<const B: usize> TransferBuffer for PoolBuffer<'_, B> {
+        fn try_extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), OutOfMemoryError> {
+            let new_length = self.length + bytes.len();
+            if new_length > B {
+                return Err(OutOfMemoryError);
+            }
+            self.block[self.length..new_length].copy_from_slice(bytes);
+            self.length = new_length;
+            Ok(())
+        }
+
+        fn truncate(&mut self, length: usize) {
+            self.length = self.length.min(length);
+        }
+    }
+
+    impl<const B: usize> fmt::Debug for PoolBuffer<'_, B> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+
+    impl<const B: usize> Drop for PoolBuffer<'_, B> {
+        fn drop(&mut self) {
+            self.allocated.store(false, Ordering::Release);
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::BlockPool;
+        use crate::buffer::{BufferAllocator, TransferBuffer};
+
+        #[test]
+        fn allocate_and_release() {
+            let pool = BlockPool::<8, 2>::new();
+            let mut allocator = &pool;
+            let mut buffer1 = allocator.allocate(8).unwrap();
+            let buffer2 = allocator.allocate(1).unwrap();
+            assert_eq!(pool.free_blocks(), 0);
+            assert!(allocator.allocate(1).is_err());
+
+            buffer1.try_extend_from_slice(&[1, 2, 3]).unwrap();
+            buffer1.try_extend_from_slice(&[4, 5, 6, 7, 8]).unwrap();
+            assert!(buffer1.try_extend_from_slice(&[9]).is_err());
+            assert_eq!(&*buffer1, &[1, 2, 3, 4, 5, 6, 7, 8]);
+            buffer1.truncate(2);
+            assert_eq!(&*buffer1, &[1, 2]);
+
+            drop(buffer2);
+            assert_eq!(pool.free_blocks(), 1);
+            let buffer3 = allocator.allocate(0).unwrap();
+            assert!(buffer3.is_empty());
+            drop(buffer1);
+            drop(buffer3);
+            assert_eq!(pool.free_blocks(), 2);
+        }
+
+        #[test]
+        fn too_large() {
+            let pool = BlockPool::<8, 2>::new();
+            assert!((&pool).allocate(9).is_err());
+            assert_eq!(pool.free_blocks(), 2);
+        }
+    }
+}
@@ -0,0 +1,576 @@
+//!
+//! Bus load estimation
+//!
+//! A [`BusLoadEstimator`] calculates the time that each observed frame occupied the bus,
+//! including stuff bits, and reports the fraction of time that the bus was busy over a sliding
+//! window. Frames are grouped by the priority in their CAN IDs.
+//!
+
+use canadensis_core::time::{Duration, Instant};
+use canadensis_core::Priority;
+
+use crate::{CanId, Frame};
+
+/// Number of priority levels
+const PRIORITY_LEVELS: usize = 8;
+
+/// Nanoseconds per second
+const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Bit rates of a CAN or CAN FD bus
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BitRates {
+    /// The bit rate used for arbitration, in bits per second
+    ///
+    /// On a classic CAN bus, this is the bit rate for all parts of the frame.
+    pub nominal: u32,
+    /// The bit rate used for the data phase of CAN FD frames with bit rate switching,
+    /// in bits per second
+    ///
+    /// If this is None, all frames with 8 or fewer bytes of data are assumed to be classic CAN
+    /// frames. Frames with more than 8 bytes of data are assumed to be CAN FD frames without bit
+    /// rate switching.
+    pub data: Option<u32>,
+}
+
+impl BitRates {
+    /// Returns bit rates for a classic CAN bus
+    pub fn classic(nominal: u32) -> Self {
+        BitRates {
+            nominal,
+            data: None,
+        }
+    }
+
+    /// Returns bit rates for a CAN FD bus with bit rate switching
+    pub fn fd(nominal: u32, data: u32) -> Self {
+        BitRates {
+            nominal,
+            data: Some(data),
+        }
+    }
+
+    /// Calculates the time that a frame occupies the bus, in nanoseconds
+    ///
+    /// This includes stuff bits, the end of frame, and the interframe space.
+    pub fn frame_nanoseconds<I>(&self, frame: &Frame<I>) -> u64 {
+        let bits = frame_bits(frame.id(), frame.data(), self.data.is_some());
+        let data_rate = self.data.unwrap_or(self.nominal);
+        bits_to_nanoseconds(bits.nominal, self.nominal) + bits_to_nanoseconds(bits.data, data_rate)
+    }
+}
+
+fn bits_to_nanoseconds(bits: u32, bit_rate: u32) -> u64 {
+    if bit_rate == 0 {
+        0
+    } else {
+        (u64::from(bits) * NANOSECONDS_PER_SECOND).div_ceil(u64::from(bit_rate))
+    }
+}
+
+/// The number of bits on the bus used to transmit a frame
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FrameBits {
+    /// Bits transmitted at the nominal bit rate
+    pub nominal: u32,
+    /// Bits transmitted at the data bit rate (always zero for classic CAN frames)
+    pub data: u32,
+}
+
+impl FrameBits {
+    /// Returns the total number of bits
+    pub fn total(&self) -> u32 {
+        self.nominal + self.data
+    }
+}
+
+/// Calculates the number of bits needed to send a frame with an extended ID, including stuff bits,
+/// the end of frame, and the interframe space
+///
+/// If `bit_rate_switch` is true or the data is longer than 8 bytes, the frame is sent as a CAN FD
+/// frame. If `bit_rate_switch` is true, the bits from the bit rate switch bit to the CRC
+/// delimiter are counted as data bits.
+///
+/// The data length should be a valid CAN or CAN FD frame length.
+pub fn frame_bits(id: CanId, data: &[u8], bit_rate_switch: bool) -> FrameBits {
+    if data.len() > 8 || bit_rate_switch {
+        fd_frame_bits(id, data, bit_rate_switch)
+    } else {
+        classic_frame_bits(id, data)
+    }
+}
+
+/// Bits after the CRC that are never stuffed: CRC delimiter, ACK slot, ACK delimiter,
+/// end of frame, and interframe space
+const CLASSIC_TRAILER_BITS: u32 = 1 + 1 + 1 + 7 + 3;
+/// Bits after the CRC delimiter in a CAN FD frame
+const FD_TRAILER_BITS: u32 = 1 + 1 + 7 + 3;
+
+fn classic_frame_bits(id: CanId, data: &[u8]) -> FrameBits {
+    let mut stuffer = BitStuffer::new();
+    let mut crc = Crc15::new();
+    let mut write = |value: u32, bits: u32| {
+        for i in (0..bits).rev() {
+            let bit = (value >> i) & 1 == 1;
+            crc.add(bit);
+            stuffer.add(bit);
+        }
+    };
+    write_extended_arbitration(&mut write, id);
+    // RTR, r1, r0
+    write(0, 3);
+    // DLC
+    write(data.len() as u32, 4);
+    for &byte in data {
+        write(u32::from(byte), 8);
+    }
+    let crc_value = crc.get();
+    for i in (0..15).rev() {
+        stuffer.add((crc_value >> i) & 1 == 1);
+    }
+
+    FrameBits {
+        nominal: stuffer.bits() + CLASSIC_TRAILER_BITS,
+        data: 0,
+    }
+}
+
+fn fd_frame_bits(id: CanId, data: &[u8], bit_rate_switch: bool) -> FrameBits {
+    let mut stuffer = BitStuffer::new();
+    write_extended_arbitration(&mut |value, bits| stuffer.add_bits(value, bits), id);
+    // RRS, FDF, res
+    stuffer.add_bits(0b010, 3);
+    // BRS (the bit rate switches at the sample point of this bit)
+    stuffer.add_bits(u32::from(bit_rate_switch), 1);
+    let arbitration_bits = stuffer.bits();
+    // ESI
+    stuffer.add_bits(0, 1);
+    stuffer.add_bits(u32::from(fd_dlc(data.len())), 4);
+    for &byte in data {
+        stuffer.add_bits(u32::from(byte), 8);
+    }
+    let dynamic_bits = stuffer.bits() - arbitration_bits;
+
+    // The stuff count and CRC use fixed stuff bits: one before the stuff count and one after
+    // every four bits.
+    let crc_length = if data.len() > 16 { 21 } else { 17 };
+    let stuff_count_and_crc = 4 + crc_length;
+    let fixed_stuff_bits = 1 + (stuff_count_and_crc - 1) / 4;
+    // The CRC delimiter is the last bit sent at the data bit rate
+    let data_phase_bits = dynamic_bits + stuff_count_and_crc + fixed_stuff_bits + 1;
+
+    if bit_rate_switch {
+        FrameBits {
+            nominal: arbitration_bits + FD_TRAILER_BITS,
+            data: data_phase_bits,
+        }
+    } else {
+        FrameBits {
+            nominal: arbitration_bits + data_phase_bits + FD_TRAILER_BITS,
+            data: 0,
+        }
+    }
+}
+
+/// Writes the start of frame bit and the bits of an extended ID, up to and including the IDE bit
+/// and the second part of the ID
+fn write_extended_arbitration<W: FnMut(u32, u32)>(write: &mut W, id: CanId) {
+    let id = u32::from(id);
+    // SOF
+    write(0, 1);
+    // Base ID (11 most significant bits)
+    write(id >> 18, 11);
+    // SRR and IDE
+    write(0b11, 2);
+    // Extended ID (18 least significant bits)
+    write(id & 0x3_ffff, 18);
+}
+
+/// Returns the CAN FD data length code for a data length
+fn fd_dlc(length: usize) -> u8 {
+    match length {
+        0..=8 => length as u8,
+        9..=12 => 9,
+        13..=16 => 10,
+        17..=20 => 11,
+        21..=24 => 12,
+        25..=32 => 13,
+        33..=48 => 14,
+        _ => 15,
+    }
+}
+
+/// Counts bits, adding a stuff bit after every five consecutive bits with the same value
+struct BitStuffer {
+    bits: u32,
+    last: bool,
+    run: u32,
+}
+
+impl BitStuffer {
+    fn new() -> Self {
+        BitStuffer {
+            bits: 0,
+            // The bus is recessive before the start of frame
+            last: true,
+            run: 0,
+        }
+    }
+
+    fn add(&mut self, bit: bool) {
+        self.bits += 1;
+        if bit == self.last {
+            self.run += 1;
+        } else {
+            self.last = bit;
+            self.run = 1;
+        }
+        if self.run == 5 {
+            // Add a stuff bit with the opposite value, which starts a new run
+            self.bits += 1;
+            self.last = !bit;
+            self.run = 1;
+        }
+    }
+
+    /// Adds the `bits` least significant bits of a value, most significant first
+    fn add_bits(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            self.add((value >> i) & 1 == 1);
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        self.bits
+    }
+}
+
+/// The 15-bit CRC used in classic CAN frames
+struct Crc15(u16);
+
+impl Crc15 {
+    fn new() -> Self {
+        Crc15(0)
+    }
+
+    fn add(&mut self, bit: bool) {
+        let next = bit ^ ((self.0 >> 14) & 1 == 1);
+        self.0 = (self.0 << 1) & 0x7fff;
+        if next {
+            self.0 ^= 0x4599;
+        }
+    }
+
+    fn get(&self) -> u32 {
+        u32::from(self.0)
+    }
+}
+
+/// Bus load, as a fraction of time that the bus was busy
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct BusLoad {
+    /// The load from frames at each priority level, indexed by priority value
+    /// (0 = exceptional, 7 = optional)
+    per_priority: [f32; PRIORITY_LEVELS],
+}
+
+impl BusLoad {
+    /// Returns the load from frames with a priority
+    pub fn priority(&self, priority: Priority) -> f32 {
+        self.per_priority[usize::from(u8::from(priority))]
+    }
+
+    /// Returns the load from all frames
+    pub fn total(&self) -> f32 {
+        self.per_priority.iter().sum()
+    }
+}
+
+/// Measures bus utilization over a sliding window
+///
+/// Type parameters:
+/// * `I`: The instant type used for frame timestamps
+/// * `B`: The number of buckets in the window (must not be zero)
+///
+/// The window is divided into `B` buckets of equal length. When time passes the end of the
+/// newest bucket, the oldest bucket is discarded. A larger number of buckets makes the window
+/// slide more smoothly, but uses more memory.
+///
+/// Each frame is counted at its timestamp. For received frames, this is the time when the frame
+/// was received.
+#[derive(Debug)]
+pub struct BusLoadEstimator<I: Instant, const B: usize> {
+    bit_rates: BitRates,
+    /// Length of each bucket in milliseconds
+    bucket_millis: u32,
+    /// Start time of the newest bucket, or None if no frames have been observed
+    bucket_start: Option<I>,
+    /// Index of the newest bucket
+    newest: usize,
+    /// Busy time in nanoseconds in each bucket, for each priority level
+    buckets: [[u64; PRIORITY_LEVELS]; B],
+}
+
+impl<I: Instant, const B: usize> BusLoadEstimator<I, B> {
+    /// Creates an estimator
+    ///
+    /// window_millis: The length of the window, in milliseconds. This should be a multiple of `B`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `B` is zero or `window_millis` is less than `B`.
+    pub fn new(bit_rates: BitRates, window_millis: u32) -> Self {
+        assert_ne!(B, 0, "Bus load estimator must have at least one bucket");
+        let bucket_millis = window_millis / B as u32;
+        assert_ne!(bucket_millis, 0, "Bus load window too short");
+        BusLoadEstimator {
+            bit_rates,
+            bucket_millis,
+            bucket_start: None,
+            newest: 0,
+            buckets: [[0; PRIORITY_LEVELS]; B],
+        }
+    }
+
+    /// Records a frame that was sent or received
+    pub fn observe(&mut self, frame: &Frame<I>) {
+        self.advance(frame.timestamp());
+        let priority = (u32::from(frame.id()) >> 26) as usize & 0x7;
+        let nanoseconds = self.bit_rates.frame_nanoseconds(frame);
+        let slot = &mut self.buckets[self.newest][priority];
+        *slot = slot.saturating_add(nanoseconds);
+    }
+
+    /// Returns the bus load over the window that ends at `now`
+    ///
+    /// The newest bucket is usually incomplete, so the reported load may be slightly lower than
+    /// the real load.
+    pub fn load(&mut self, now: I) -> BusLoad {
+        self.advance(now);
+        let window_nanoseconds = u64::from(self.bucket_millis) * 1_000_000 * B as u64;
+        let mut load = BusLoad::default();
+        for bucket in self.buckets.iter() {
+            for (total, busy) in load.per_priority.iter_mut().zip(bucket.iter()) {
+                *total += *busy as f32 / window_nanoseconds as f32;
+            }
+        }
+        load
+    }
+
+    /// Returns the bit rates used to calculate frame durations
+    pub fn bit_rates(&self) -> BitRates {
+        self.bit_rates
+    }
+
+    /// Moves the window forward so that the newest bucket contains `now`
+    fn advance(&mut self, now: I) {
+        let bucket_start = match self.bucket_start {
+            Some(start) => start,
+            None => {
+                self.bucket_start = Some(now);
+                return;
+            }
+        };
+        let elapsed = now.duration_since(&bucket_start);
+        let elapsed_millis = elapsed
+            .as_secs()
+            .saturating_mul(1000)
+            .saturating_add(u64::from(elapsed.subsec_nanos() / 1_000_000));
+        let buckets_elapsed = elapsed_millis / u64::from(self.bucket_millis);
+        if buckets_elapsed == 0 {
+            return;
+        }
+        if buckets_elapsed >= B as u64 {
+            // Everything in the window is too old
+            self.buckets = [[0; PRIORITY_LEVELS]; B];
+            self.newest = 0;
+            self.bucket_start = Some(now);
+        } else {
+            for _ in 0..buckets_elapsed {
+                self.newest = (self.newest + 1) % B;
+                self.buckets[self.newest] = [0; PRIORITY_LEVELS];
+            }
+            // buckets_elapsed * bucket_millis is less than the window length, which fits in a u32
+            let advance_millis = buckets_elapsed as u32 * self.bucket_millis;
+            match I::Duration::from_millis(advance_millis) {
+                Some(advance) => self.bucket_start = Some(advance + bucket_start),
+                None => self.bucket_start = Some(now),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use canadensis_core::time::Microseconds32;
+    use core::convert::TryFrom;
+
+    const HEARTBEAT_DATA: [u8; 8] = [0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0];
+
+    /// A heartbeat frame with nominal priority
+    fn heartbeat_frame(time: u32) -> Frame<Microseconds32> {
+        Frame::new(
+            Microseconds32::new(time),
+            CanId::try_from(0x107d552a).unwrap(),
+            &HEARTBEAT_DATA,
+        )
+    }
+
+    /// The same heartbeat frame with low priority
+    fn low_priority_frame(time: u32) -> Frame<Microseconds32> {
+        Frame::new(
+            Microseconds32::new(time),
+            CanId::try_from(0x147d552a).unwrap(),
+            &HEARTBEAT_DATA,
+        )
+    }
+
+    #[test]
+    fn bit_stuffing() {
+        let mut stuffer = BitStuffer::new();
+        // Five dominant bits need one stuff bit, and the stuff bit starts a new run
+        for _ in 0..5 {
+            stuffer.add(false);
+        }
+        assert_eq!(stuffer.bits(), 6);
+        for _ in 0..4 {
+            stuffer.add(true);
+        }
+        assert_eq!(stuffer.bits(), 11);
+    }
+
+    #[test]
+    fn classic_frame_length() {
+        let id = CanId::try_from(0x107d552a).unwrap();
+        // Unstuffed length is 67 bits + 8 bits per byte
+        assert_eq!(
+            frame_bits(id, &[], false),
+            FrameBits {
+                nominal: 70,
+                data: 0
+            }
+        );
+        assert_eq!(
+            frame_bits(id, &[0; 8], false),
+            FrameBits {
+                nominal: 147,
+                data: 0
+            }
+        );
+        assert_eq!(
+            frame_bits(id, &HEARTBEAT_DATA, false),
+            FrameBits {
+                nominal: 142,
+                data: 0
+            }
+        );
+        let low_id = CanId::try_from(0x147d552a).unwrap();
+        assert_eq!(frame_bits(low_id, &HEARTBEAT_DATA, false).total(), 141);
+    }
+
+    #[test]
+    fn fd_frame_length() {
+        let id = CanId::try_from(0x107d552a).unwrap();
+        // Arbitration: 36 bits + 2 stuff bits, then 12 trailer bits
+        // Data phase: ESI + DLC + 512 data bits + stuff count + CRC21 + fixed stuff bits
+        // + CRC delimiter
+        assert_eq!(
+            frame_bits(id, &[0x55; 64], true),
+            FrameBits {
+                nominal: 38 + 12,
+                data: 5 + 512 + 25 + 7 + 1
+            }
+        );
+        // Without bit rate switching, everything is at the nominal bit rate
+        assert_eq!(
+            frame_bits(id, &[0; 12], false),
+            FrameBits {
+                nominal: 198,
+                data: 0
+            }
+        );
+    }
+
+    #[test]
+    fn frame_time() {
+        assert_eq!(
+            BitRates::classic(1_000_000).frame_nanoseconds(&heartbeat_frame(0)),
+            142_000
+        );
+        // 142 bits at 125 kbit/s
+        assert_eq!(
+            BitRates::classic(125_000).frame_nanoseconds(&heartbeat_frame(0)),
+            1_136_000
+        );
+        // On a CAN FD bus with bit rate switching, 50 bits at 1 Mbit/s and 106 bits at 4 Mbit/s
+        assert_eq!(
+            BitRates::fd(1_000_000, 4_000_000).frame_nanoseconds(&heartbeat_frame(0)),
+            50_000 + 26_500
+        );
+        // Partial nanoseconds are rounded up: 142 bits at 3 Mbit/s is 47333.3 ns
+        assert_eq!(
+            BitRates::classic(3_000_000).frame_nanoseconds(&heartbeat_frame(0)),
+            47_334
+        );
+    }
+
+    #[test]
+    fn load_sliding_window() {
+        let mut estimator =
+            BusLoadEstimator::<Microseconds32, 10>::new(BitRates::classic(1_000_000), 1000);
+        // 100 nominal-priority frames in the first 100 milliseconds (bucket 0)
+        for i in 0..100 {
+            estimator.observe(&heartbeat_frame(i * 1000));
+        }
+        // 50 low-priority frames from 500 to 550 milliseconds (bucket 5)
+        for i in 0..50 {
+            estimator.observe(&low_priority_frame(500_000 + i * 1000));
+        }
+        assert_eq!(estimator.buckets[0][4], 100 * 142_000);
+        assert_eq!(estimator.buckets[5][5], 50 * 141_000);
+
+        let load = estimator.load(Microseconds32::new(999_000));
+        assert_eq!(load.priority(Priority::Nominal), 0.0142);
+        assert_eq!(load.priority(Priority::Low), 0.00705);
+        assert_eq!(load.priority(Priority::Exceptional), 0.0);
+        assert_eq!(load.total(), 0.0142f32 + 0.00705f32);
+
+        // After the window moves past the first bucket, its frames are no longer counted
+        let load = estimator.load(Microseconds32::new(1_050_000));
+        assert_eq!(load.priority(Priority::Nominal), 0.0);
+        assert_eq!(load.priority(Priority::Low), 0.00705);
+        assert_eq!(load.total(), 0.00705);
+
+        // The bucket from 500 to 600 milliseconds leaves the window when the bucket from
+        // 1500 to 1600 milliseconds starts
+        let load = estimator.load(Microseconds32::new(1_499_999));
+        assert_eq!(load.total(), 0.00705);
+        let load = estimator.load(Microseconds32::new(1_500_000));
+        assert_eq!(load, BusLoad::default());
+    }
+
+    #[test]
+    fn load_after_idle_window() {
+        let mut estimator =
+            BusLoadEstimator::<Microseconds32, 4>::new(BitRates::classic(500_000), 200);
+        for i in 0..10 {
+            estimator.observe(&heartbeat_frame(i * 1000));
+        }
+        // 10 frames of 284 microseconds in a 200 millisecond window
+        assert_eq!(estimator.load(Microseconds32::new(10_000)).total(), 0.0142);
+
+        // After more than a whole window of silence, all buckets are cleared and new frames
+        // are counted from the time they arrive
+        estimator.observe(&heartbeat_frame(5_000_000));
+        assert_eq!(
+            estimator.load(Microseconds32::new(5_000_000)).total(),
+            0.00142
+        );
+        assert_eq!(
+            estimator.load(Microseconds32::new(5_199_999)).total(),
+            0.00142
+        );
+        assert_eq!(estimator.load(Microseconds32::new(5_200_000)).total(), 0.0);
+    }
+}
@@ -0,0 +1,390 @@
+//!
+//! CAN controller error states and bus-off recovery
+//!
+//! A CAN controller counts transmit and receive errors. As the counters increase, the controller
+//! moves from the error-active state to error-passive, and then to bus-off, where it stops
+//! communicating. Drivers implement [`BusMonitor`] to report these states, and a
+//! [`BusOffRecovery`] decides when to restart a controller that has gone bus-off.
+//!
+//! Drivers can also report individual errors and state changes as [`BusEvent`]s. Drivers that
+//! only have status registers can use a [`BusEventDetector`] to turn changes in the status into
+//! events.
+//!
+
+use core::cmp::Ordering;
+
+use canadensis_core::time::{Duration, Instant};
+
+/// The fault confinement state of a CAN controller
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ErrorState {
+    /// Both error counters are below 128 and the controller is communicating normally
+    Active,
+    /// At least one error counter is 128 or more
+    ///
+    /// The controller can still send and receive frames, but it waits longer before transmitting
+    /// and can't signal errors in frames sent by other nodes.
+    Passive,
+    /// The transmit error counter has exceeded 255 and the controller has stopped communicating
+    BusOff,
+}
+
+/// The error counters of a CAN controller
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ErrorCounters {
+    /// Transmit error counter
+    pub transmit: u16,
+    /// Receive error counter
+    pub receive: u16,
+}
+
+/// The error state and counters of a CAN controller
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BusStatus {
+    /// The fault confinement state
+    pub state: ErrorState,
+    /// The error counters
+    pub counters: ErrorCounters,
+}
+
+impl ErrorState {
+    /// Returns the state that corresponds to a set of error counters
+    pub fn from_counters(counters: &ErrorCounters) -> Self {
+        if counters.transmit > 255 {
+            ErrorState::BusOff
+        } else if counters.transmit >= 128 || counters.receive >= 128 {
+            ErrorState::Passive
+        } else {
+            ErrorState::Active
+        }
+    }
+}
+
+/// The error counter value at which controllers usually report a warning
+pub const ERROR_WARNING_LIMIT: u16 = 96;
+
+/// An error or state change reported by a CAN controller
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BusEvent {
+    /// The controller changed to a different error state
+    StateChanged(ErrorState),
+    /// The controller restarted after it was bus-off, and is now error-active
+    Restarted,
+    /// An error counter reached the warning limit
+    Warning,
+    /// The error counters changed
+    Counters(ErrorCounters),
+    /// The controller lost arbitration while sending a frame
+    ///
+    /// This is a normal part of CAN operation and is not an error.
+    ArbitrationLost,
+    /// No other node acknowledged a frame that the controller sent
+    NoAcknowledgement,
+    /// The controller detected a bit, stuff, form, or CRC error
+    ProtocolViolation,
+    /// A frame could not be sent before a timeout
+    TransmitTimeout,
+    /// The controller or driver ran out of space and discarded received frames
+    ReceiveOverflow,
+    /// The transceiver detected a problem with the bus wires
+    Transceiver,
+}
+
+impl BusEvent {
+    /// Returns true if this event indicates a problem with the bus or the controller
+    ///
+    /// State changes are not counted as problems because the new state describes the problem.
+    pub fn is_error(&self) -> bool {
+        match self {
+            BusEvent::StateChanged(_)
+            | BusEvent::Restarted
+            | BusEvent::Counters(_)
+            | BusEvent::ArbitrationLost => false,
+            BusEvent::Warning
+            | BusEvent::NoAcknowledgement
+            | BusEvent::ProtocolViolation
+            | BusEvent::TransmitTimeout
+            | BusEvent::ReceiveOverflow
+            | BusEvent::Transceiver => true,
+        }
+    }
+}
+
+/// A CAN driver that can report the error state of its controller
+pub trait BusMonitor {
+    /// Reads the current error state and counters from the controller
+    fn bus_status(&mut self) -> BusStatus;
+    /// Restarts the controller after it has gone bus-off
+    ///
+    /// Controllers that always recover from bus-off automatically can implement this
+    /// as a no-op.
+    fn restart(&mut self);
+    /// Removes and returns the oldest error event that the controller has reported
+    ///
+    /// The default implementation never returns any events. Drivers that receive error reports
+    /// from their controllers should override it.
+    fn next_event(&mut self) -> Option<BusEvent> {
+        None
+    }
+}
+
+/// Produces bus events from changes in the status of a controller
+///
+/// This is useful for controllers that have status registers but do not report individual
+/// errors.
+#[derive(Debug, Clone)]
+pub struct BusEventDetector {
+    /// The status from the previous update
+    last: BusStatus,
+}
+
+impl BusEventDetector {
+    /// Creates a detector that assumes that the controller starts error-active with both error
+    /// counters at zero
+    pub fn new() -> Self {
+        BusEventDetector {
+            last: BusStatus {
+                state: ErrorState::Active,
+                counters: ErrorCounters::default(),
+            },
+        }
+    }
+
+    /// Compares a new status with the previous status and passes an event to `handler` for
+    /// each change
+    pub fn update<F>(&mut self, status: BusStatus, mut handler: F)
+    where
+        F: FnMut(BusEvent),
+    {
+        let last = core::mem::replace(&mut self.last, status);
+        if status.state != last.state {
+            handler(BusEvent::StateChanged(status.state));
+        }
+        if status.counters != last.counters {
+            let max_counter = |counters: &ErrorCounters| counters.transmit.max(counters.receive);
+            if status.state == ErrorState::Active
+                && max_counter(&status.counters) >= ERROR_WARNING_LIMIT
+                && max_counter(&last.counters) < ERROR_WARNING_LIMIT
+            {
+                handler(BusEvent::Warning);
+            }
+            handler(BusEvent::Counters(status.counters));
+        }
+    }
+
+    /// Returns the status from the most recent update
+    pub fn status(&self) -> BusStatus {
+        self.last
+    }
+}
+
+impl Default for BusEventDetector {
+    fn default() -> Self {
+        BusEventDetector::new()
+    }
+}
+
+/// Restarts a CAN controller after it goes bus-off, waiting longer after each failed attempt
+///
+/// When the controller goes bus-off, the first restart happens after `initial_delay`. If the
+/// controller goes bus-off again before it has been error-active for `initial_delay`, the
+/// delay doubles, up to `max_delay`. This keeps a node with a faulty transceiver from disrupting
+/// the bus with constant errors.
+#[derive(Debug, Clone)]
+pub struct BusOffRecovery<I: Instant> {
+    initial_delay: I::Duration,
+    max_delay: I::Duration,
+    /// The delay to use for the next restart
+    delay: I::Duration,
+    /// The time when the controller should be restarted, if it is bus-off
+    restart_time: Option<I>,
+    /// The time when the controller was restarted, if it has not yet been active long enough
+    /// to reset the delay
+    last_restart: Option<I>,
+    /// The number of times the controller has been restarted
+    restarts: u32,
+}
+
+impl<I: Instant> BusOffRecovery<I> {
+    /// Creates a recovery policy
+    pub fn new(initial_delay: I::Duration, max_delay: I::Duration) -> Self {
+        BusOffRecovery {
+            initial_delay,
+            max_delay,
+            delay: initial_delay,
+            restart_time: None,
+            last_restart: None,
+            restarts: 0,
+        }
+    }
+
+    /// Checks the state of a controller and restarts it if it has been bus-off long enough
+    ///
+    /// This function should be called periodically. It returns the status that it read from
+    /// the controller.
+    pub fn poll<B: BusMonitor>(&mut self, bus: &mut B, now: I) -> BusStatus {
+        let status = bus.bus_status();
+        match status.state {
+            ErrorState::BusOff => match self.restart_time {
+                None => {
+                    if self.last_restart.is_some() {
+                        // Went bus-off again soon after a restart
+                        self.delay = double_capped(self.delay, self.max_delay);
+                    }
+                    self.restart_time = Some(self.delay + now);
+                }
+                Some(restart_time) => {
+                    if now.overflow_safe_compare(&restart_time) != Ordering::Less {
+                        log::info!("Restarting CAN controller after bus-off");
+                        bus.restart();
+                        self.restart_time = None;
+                        self.last_restart = Some(now);
+                        self.restarts = self.restarts.wrapping_add(1);
+                    }
+                }
+            },
+            ErrorState::Active | ErrorState::Passive => {
+                self.restart_time = None;
+                if let Some(last_restart) = self.last_restart {
+                    if status.state == ErrorState::Active
+                        && now.duration_since(&last_restart) >= self.initial_delay
+                    {
+                        // Recovered
+                        self.last_restart = None;
+                        self.delay = self.initial_delay;
+                    }
+                }
+            }
+        }
+        status
+    }
+
+    /// Returns the number of times this policy has restarted the controller
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Returns the delay that will be used before the next restart
+    pub fn delay(&self) -> I::Duration {
+        self.delay
+    }
+}
+
+/// Returns twice delay, but not more than max
+fn double_capped<D: Duration>(delay: D, max: D) -> D {
+    if delay >= max {
+        return max;
+    }
+    let doubled = delay + delay;
+    if doubled > max {
+        max
+    } else {
+        doubled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        BusEvent, BusEventDetector, BusMonitor, BusOffRecovery, BusStatus, ErrorCounters,
+        ErrorState,
+    };
+    use alloc::vec::Vec;
+    use canadensis_core::time::{milliseconds, Microseconds32};
+
+    type TestInstant = Microseconds32;
+
+    struct FakeBus {
+        state: ErrorState,
+        restarts: u32,
+    }
+
+    impl BusMonitor for FakeBus {
+        fn bus_status(&mut self) -> BusStatus {
+            BusStatus {
+                state: self.state,
+                counters: ErrorCounters::default(),
+            }
+        }
+
+        fn restart(&mut self) {
+            self.restarts += 1;
+            self.state = ErrorState::Active;
+        }
+    }
+
+    fn ms(milliseconds: u32) -> TestInstant {
+        TestInstant::new(milliseconds * 1000)
+    }
+
+    #[test]
+    fn backoff() {
+        let mut bus = FakeBus {
+            state: ErrorState::Active,
+            restarts: 0,
+        };
+        let mut recovery = BusOffRecovery::<TestInstant>::new(milliseconds(10), milliseconds(30));
+        recovery.poll(&mut bus, ms(0));
+
+        bus.state = ErrorState::BusOff;
+        recovery.poll(&mut bus, ms(1));
+        recovery.poll(&mut bus, ms(10));
+        assert_eq!(bus.restarts, 0);
+        recovery.poll(&mut bus, ms(11));
+        assert_eq!(bus.restarts, 1);
+
+        // Bus-off again right away, so the delay doubles
+        bus.state = ErrorState::BusOff;
+        recovery.poll(&mut bus, ms(12));
+        recovery.poll(&mut bus, ms(31));
+        assert_eq!(bus.restarts, 1);
+        recovery.poll(&mut bus, ms(32));
+        assert_eq!(bus.restarts, 2);
+
+        // The delay is limited to the maximum
+        bus.state = ErrorState::BusOff;
+        recovery.poll(&mut bus, ms(33));
+        assert_eq!(recovery.delay(), milliseconds(30));
+        recovery.poll(&mut bus, ms(63));
+        assert_eq!(bus.restarts, 3);
+
+        // After the controller stays active long enough, the delay goes back to the start
+        recovery.poll(&mut bus, ms(100));
+        assert_eq!(recovery.delay(), milliseconds(10));
+        assert_eq!(recovery.restarts(), 3);
+    }
+
+    #[test]
+    fn detect_events() {
+        let mut detector = BusEventDetector::new();
+        let mut events = Vec::new();
+        let status = |state, transmit, receive| BusStatus {
+            state,
+            counters: ErrorCounters { transmit, receive },
+        };
+
+        detector.update(status(ErrorState::Active, 0, 0), |e| events.push(e));
+        assert!(events.is_empty());
+
+        detector.update(status(ErrorState::Active, 96, 8), |e| events.push(e));
+        assert_eq!(
+            events,
+            [
+                BusEvent::Warning,
+                BusEvent::Counters(ErrorCounters {
+                    transmit: 96,
+                    receive: 8
+                })
+            ]
+        );
+        events.clear();
+
+        detector.update(status(ErrorState::BusOff, 256, 8), |e| events.push(e));
+        assert_eq!(events[0], BusEvent::StateChanged(ErrorState::BusOff));
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            ErrorState::from_counters(&detector.status().counters),
+            ErrorState::BusOff
+        );
+    }
+}
@@ -54,6 +54,18 @@ impl Mtu {
     pub fn as_bytes(&self) -> usize {
         *self as usize
     }
+
+    /// Returns the MTU with the provided number of bytes, if it is supported
+    ///
+    /// A value of 64 is supported only when the `can-fd` feature is enabled.
+    pub fn from_bytes(bytes: usize) -> Option<Self> {
+        match bytes {
+            8 => Some(Mtu::Can8),
+            #[cfg(feature = "can-fd")]
+            64 => Some(Mtu::CanFd64),
+            _ => None,
+        }
+    }
 }
 
 /// Maximum number of bytes in a frame
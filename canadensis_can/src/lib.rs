@@ -17,15 +17,21 @@ extern crate log;
 pub use crate::crc::TransferCrc;
 pub use crate::data::*;
 pub use crate::error::*;
-pub use crate::rx::{Receiver, ServiceSubscribeError};
-pub use crate::tx::Transmitter;
+pub use crate::rx::{OversizeFramePolicy, Receiver, ServiceSubscribeError, SubjectSet};
+pub use crate::tx::{ChunkedTransfer, Transmitter};
 
+pub mod bit_rate;
+pub mod buffer;
+pub mod bus_load;
+pub mod bus_status;
 mod crc;
 mod data;
 mod error;
 pub mod queue;
 pub mod redundant;
 mod rx;
+pub mod stream;
+pub mod test_vectors;
 mod tx;
 
 use core::cmp;
@@ -47,13 +53,10 @@ fn calculate_frame_stats(payload_length: usize, mtu: usize) -> FrameStats {
     };
     // Total length of all tail bytes
     // Divide and round up (minimum 1 tail byte)
-    let tail_bytes = cmp::max(
-        1,
-        (payload_length + crc_length + (mtu_without_tail - 1)) / mtu_without_tail,
-    );
+    let tail_bytes = cmp::max(1, (payload_length + crc_length).div_ceil(mtu_without_tail));
     // Total length of the payloads of all frames, including CRC and tail bytes
     let total_length = payload_length + crc_length + tail_bytes;
-    let frames = (total_length + mtu - 1) / mtu;
+    let frames = total_length.div_ceil(mtu);
 
     // Get the number of bytes in the last frame (may be 0)
     let last_frame_length = total_length % mtu;
@@ -1,4 +1,4 @@
-use crate::queue::{FrameQueueSource, FrameSink};
+use crate::queue::{FrameQueueSource, FrameQueueStatus, FrameSink};
 use crate::{Frame, OutOfMemoryError};
 use core::mem::{self, MaybeUninit};
 use core::ptr;
@@ -18,6 +18,8 @@ pub struct ArrayQueue<I, const N: usize> {
     head: usize,
     /// The number of valid frames in the queue
     length: usize,
+    /// The largest value of length since the queue was created or the high water mark was reset
+    high_water_mark: usize,
 }
 
 impl<I, const N: usize> ArrayQueue<I, N>
@@ -48,6 +50,7 @@ where
             items,
             head: 0,
             length: 0,
+            high_water_mark: 0,
         }
     }
 
@@ -105,6 +108,7 @@ where
             let tail_index = self.head.wrapping_add(self.length) % N;
             self.items[tail_index] = frame;
             self.length += 1;
+            self.high_water_mark = self.high_water_mark.max(self.length);
 
             // Move the frame towards the front (lower index) until the frame in front of it
             // has a lesser or equal CAN ID
@@ -160,6 +164,7 @@ where
             self.decrement_head();
             self.items[self.head] = frame;
             self.length += 1;
+            self.high_water_mark = self.high_water_mark.max(self.length);
 
             // Move the frame towards the back (higher index) until the frame behind it
             // has a greater or equal CAN ID
@@ -184,6 +189,66 @@ where
     }
 }
 
+impl<I, const N: usize> FrameQueueStatus<I> for ArrayQueue<I, N> {
+    type Iter<'a>
+        = ArrayQueueIter<'a, I, N>
+    where
+        I: 'a;
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(N)
+    }
+
+    fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    fn reset_high_water_mark(&mut self) {
+        self.high_water_mark = self.length;
+    }
+
+    /// Returns an iterator over the frames in this queue, starting at the front
+    fn iter(&self) -> Self::Iter<'_> {
+        ArrayQueueIter {
+            queue: self,
+            offset: 0,
+        }
+    }
+}
+
+/// An iterator over the frames in an [`ArrayQueue`], from front to back
+#[derive(Debug)]
+pub struct ArrayQueueIter<'a, I, const N: usize> {
+    queue: &'a ArrayQueue<I, N>,
+    /// The position of the next frame relative to the head of the queue
+    offset: usize,
+}
+
+impl<'a, I, const N: usize> Iterator for ArrayQueueIter<'a, I, N> {
+    type Item = &'a Frame<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset < self.queue.length {
+            let index = (self.queue.head + self.offset) % N;
+            self.offset += 1;
+            Some(&self.queue.items[index])
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.queue.length - self.offset;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for ArrayQueueIter<'_, I, N> {}
+
 impl<I, const N: usize> Default for ArrayQueue<I, N>
 where
     I: Default,
@@ -197,8 +262,9 @@ where
 mod test {
     use super::ArrayQueue;
     use super::FrameSink;
-    use crate::queue::FrameQueueSource;
+    use crate::queue::{FrameQueueSource, FrameQueueStatus};
     use crate::{CanId, Frame};
+    use alloc::vec::Vec;
     use core::convert::TryFrom;
 
     fn frame_with_id(id: u32, data: u8) -> Frame<()> {
@@ -349,4 +415,38 @@ mod test {
         assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 6)));
         assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 7)));
     }
+
+    #[test]
+    fn status() {
+        let mut queue = ArrayQueue::<(), 4>::new();
+        assert_eq!(queue.free(), Some(4));
+        queue.push_frame(frame_with_id(2, 0)).unwrap();
+        queue.push_frame(frame_with_id(1, 0)).unwrap();
+        queue.push_frame(frame_with_id(3, 0)).unwrap();
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(1, 0)));
+        // Wrap around the end of the array
+        queue.push_frame(frame_with_id(4, 0)).unwrap();
+        queue.push_frame(frame_with_id(5, 0)).unwrap();
+        assert_eq!(queue.free(), Some(0));
+        assert_eq!(queue.high_water_mark(), 4);
+
+        let frames: Vec<Frame<()>> = FrameQueueStatus::iter(&queue).cloned().collect();
+        assert_eq!(
+            frames,
+            [
+                frame_with_id(2, 0),
+                frame_with_id(3, 0),
+                frame_with_id(4, 0),
+                frame_with_id(5, 0)
+            ]
+        );
+        assert_eq!(FrameQueueStatus::iter(&queue).len(), 4);
+
+        queue.pop_frame().unwrap();
+        queue.pop_frame().unwrap();
+        assert_eq!(queue.high_water_mark(), 4);
+        queue.reset_high_water_mark();
+        assert_eq!(queue.high_water_mark(), 2);
+        assert_eq!(queue.free(), Some(2));
+    }
 }
@@ -0,0 +1,249 @@
+use crate::queue::{FrameQueueStatus, FrameSink};
+use crate::{Frame, OutOfMemoryError};
+use core::iter::Chain;
+use core::slice;
+
+/// A frame queue with two fixed-capacity buffers, for drivers that transmit many frames at once
+///
+/// The transmitter adds frames to one buffer (the fill buffer). When the driver is ready to send,
+/// it calls [`start_transmission`](#method.start_transmission) to take the fill buffer as a
+/// contiguous slice of frames, which it can give to a DMA controller or transmit in a single
+/// interrupt handler. The transmitter then adds new frames to the other buffer. When the driver
+/// has finished sending the frames, it calls [`finish_transmission`](#method.finish_transmission)
+/// so that the buffer can be filled again.
+///
+/// Within each buffer, frames are in order by CAN ID and then first-in, first-out. Frames in the
+/// buffer being transmitted always go out before frames in the fill buffer, even if the fill
+/// buffer contains frames with higher priority.
+///
+/// Because of this, the queue does not implement
+/// [`FrameQueueSource`](crate::queue::FrameQueueSource), which requires frames to come out in
+/// order by CAN ID. Drivers that send one frame at a time
+/// should use an [`ArrayQueue`](crate::queue::ArrayQueue) or a
+/// [`HeapQueue`](crate::queue::HeapQueue) instead.
+///
+/// `N` is the maximum number of frames in each buffer. This should be at least as large as the
+/// number of frames required for the largest outgoing transfer that will be sent.
+#[derive(Debug)]
+pub struct DoubleBufferQueue<I, const N: usize> {
+    /// The two buffers
+    buffers: [heapless::Vec<Frame<I>, N>; 2],
+    /// The index in self.buffers of the fill buffer
+    fill: usize,
+    /// True if the other buffer has been given to the driver
+    transmitting: bool,
+    /// The largest number of frames in both buffers since the queue was created or the high
+    /// water mark was reset
+    high_water_mark: usize,
+}
+
+impl<I, const N: usize> DoubleBufferQueue<I, N> {
+    /// Returns a new queue with both buffers empty
+    pub const fn new() -> Self {
+        DoubleBufferQueue {
+            buffers: [heapless::Vec::new(), heapless::Vec::new()],
+            fill: 0,
+            transmitting: false,
+            high_water_mark: 0,
+        }
+    }
+
+    /// Takes the frames in the fill buffer for transmission
+    ///
+    /// This function returns None if the driver has not finished transmitting the frames from
+    /// the previous call, or if there are no frames to send.
+    ///
+    /// The frames in the returned slice will not be moved or modified until the driver
+    /// calls [`finish_transmission`](#method.finish_transmission), even if more frames are added
+    /// to the queue.
+    pub fn start_transmission(&mut self) -> Option<&[Frame<I>]> {
+        if self.transmitting || self.buffers[self.fill].is_empty() {
+            None
+        } else {
+            self.transmitting = true;
+            self.fill = 1 - self.fill;
+            Some(&self.buffers[1 - self.fill])
+        }
+    }
+
+    /// Returns the frames that the driver is transmitting, or None if no transmission has been
+    /// started
+    pub fn transmitting_frames(&self) -> Option<&[Frame<I>]> {
+        if self.transmitting {
+            Some(&self.buffers[1 - self.fill])
+        } else {
+            None
+        }
+    }
+
+    /// Releases the buffer that the driver was transmitting so that it can be filled again
+    ///
+    /// The frames in the buffer are discarded. If no transmission has been started, this function
+    /// has no effect.
+    pub fn finish_transmission(&mut self) {
+        if self.transmitting {
+            self.buffers[1 - self.fill].clear();
+            self.transmitting = false;
+        }
+    }
+
+    /// Returns the number of frames in the fill buffer
+    pub fn pending_frames(&self) -> usize {
+        self.buffers[self.fill].len()
+    }
+
+    fn total_frames(&self) -> usize {
+        self.buffers[0].len() + self.buffers[1].len()
+    }
+}
+
+impl<I, const N: usize> Default for DoubleBufferQueue<I, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, const N: usize> FrameSink<I> for DoubleBufferQueue<I, N> {
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        if N - self.buffers[self.fill].len() >= additional {
+            Ok(())
+        } else {
+            Err(OutOfMemoryError)
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        // Doesn't dynamically allocate memory, nothing to do
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        let inserted_frame_id = frame.id();
+        let buffer = &mut self.buffers[self.fill];
+        buffer.push(frame).map_err(|_| OutOfMemoryError)?;
+
+        // Move the frame towards the front until the frame in front of it has a lesser or equal
+        // CAN ID
+        let mut inserted_index = buffer.len() - 1;
+        while inserted_index != 0 && buffer[inserted_index - 1].id() > inserted_frame_id {
+            buffer.swap(inserted_index, inserted_index - 1);
+            inserted_index -= 1;
+        }
+
+        self.high_water_mark = self.high_water_mark.max(self.total_frames());
+        Ok(())
+    }
+}
+
+impl<I, const N: usize> FrameQueueStatus<I> for DoubleBufferQueue<I, N> {
+    type Iter<'a>
+        = Chain<slice::Iter<'a, Frame<I>>, slice::Iter<'a, Frame<I>>>
+    where
+        I: 'a;
+
+    /// Returns the number of frames in both buffers
+    fn len(&self) -> usize {
+        self.total_frames()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(2 * N)
+    }
+
+    /// Returns the number of frames that can be added to the fill buffer
+    ///
+    /// Space in the buffer being transmitted is not available until the transmission finishes.
+    fn free(&self) -> Option<usize> {
+        Some(N - self.buffers[self.fill].len())
+    }
+
+    fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    fn reset_high_water_mark(&mut self) {
+        self.high_water_mark = self.total_frames();
+    }
+
+    /// Returns an iterator over the frames being transmitted, followed by the frames in the fill
+    /// buffer
+    fn iter(&self) -> Self::Iter<'_> {
+        self.buffers[1 - self.fill]
+            .iter()
+            .chain(self.buffers[self.fill].iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DoubleBufferQueue;
+    use crate::queue::{FrameQueueStatus, FrameSink};
+    use crate::{CanId, Frame};
+    use core::convert::TryFrom;
+
+    fn frame_with_id(id: u32, data: u8) -> Frame<()> {
+        let id = CanId::try_from(id).unwrap();
+        Frame::new((), id, &[data])
+    }
+
+    #[test]
+    fn fill_and_transmit() {
+        let mut queue = DoubleBufferQueue::<(), 3>::new();
+        assert!(queue.start_transmission().is_none());
+
+        queue.push_frame(frame_with_id(10, 0)).unwrap();
+        queue.push_frame(frame_with_id(5, 0)).unwrap();
+        queue.push_frame(frame_with_id(10, 1)).unwrap();
+        assert!(queue.push_frame(frame_with_id(1, 0)).is_err());
+        assert_eq!(
+            queue.start_transmission().unwrap(),
+            &[
+                frame_with_id(5, 0),
+                frame_with_id(10, 0),
+                frame_with_id(10, 1)
+            ]
+        );
+
+        // New frames go into the other buffer while the first is being transmitted
+        queue.push_frame(frame_with_id(1, 0)).unwrap();
+        assert_eq!(queue.pending_frames(), 1);
+        assert_eq!(queue.len(), 4);
+        assert!(queue.start_transmission().is_none());
+        assert_eq!(queue.transmitting_frames().unwrap().len(), 3);
+
+        queue.finish_transmission();
+        assert!(queue.transmitting_frames().is_none());
+        assert_eq!(queue.start_transmission().unwrap(), &[frame_with_id(1, 0)]);
+        queue.finish_transmission();
+
+        assert!(queue.is_empty());
+        assert_eq!(queue.high_water_mark(), 4);
+        assert_eq!(queue.free(), Some(3));
+    }
+
+    #[test]
+    fn free_space_in_fill_buffer() {
+        let mut queue = DoubleBufferQueue::<(), 2>::new();
+        assert_eq!(queue.free(), Some(2));
+        queue.push_frame(frame_with_id(3, 0)).unwrap();
+        queue.push_frame(frame_with_id(4, 0)).unwrap();
+        // The fill buffer is full, even though the other buffer is empty
+        assert_eq!(queue.free(), Some(0));
+        assert!(queue.push_frame(frame_with_id(5, 0)).is_err());
+
+        queue.start_transmission().unwrap();
+        assert_eq!(queue.free(), Some(2));
+        queue.push_frame(frame_with_id(5, 0)).unwrap();
+        assert_eq!(queue.free(), Some(1));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn reserve_in_fill_buffer() {
+        let mut queue = DoubleBufferQueue::<(), 2>::new();
+        queue.try_reserve(2).unwrap();
+        queue.push_frame(frame_with_id(3, 0)).unwrap();
+        assert!(queue.try_reserve(2).is_err());
+        queue.start_transmission().unwrap();
+        queue.try_reserve(2).unwrap();
+    }
+}
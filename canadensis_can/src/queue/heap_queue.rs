@@ -0,0 +1,300 @@
+use crate::queue::{FrameQueueSource, FrameQueueStatus, FrameSink};
+use crate::{CanId, Frame, OutOfMemoryError};
+use alloc::collections::binary_heap::{self, BinaryHeap};
+use core::cmp::Ordering;
+
+/// A frame queue implemented as a binary heap with dynamically allocated memory
+///
+/// Pushing and popping frames takes O(log n) time, so this queue is more efficient than
+/// [`ArrayQueue`](crate::queue::ArrayQueue) when it holds many frames. Its capacity is limited
+/// only by the available memory.
+#[derive(Debug)]
+pub struct HeapQueue<I> {
+    /// The frames in this queue, with the frame that should be sent first at the top
+    frames: BinaryHeap<Entry<I>>,
+    /// The sequence number for the next frame pushed to the back of the queue
+    next_back: i64,
+    /// The sequence number of the frame at the front of the queue, if that frame was returned
+    /// to the queue
+    front: i64,
+    /// The largest number of frames in the queue since it was created or the high water mark
+    /// was reset
+    high_water_mark: usize,
+}
+
+impl<I> HeapQueue<I> {
+    /// Returns a new empty queue
+    pub fn new() -> Self {
+        HeapQueue {
+            frames: BinaryHeap::new(),
+            next_back: 0,
+            front: 0,
+            high_water_mark: 0,
+        }
+    }
+
+    /// Returns a new empty queue with space for at least `capacity` frames
+    pub fn with_capacity(capacity: usize) -> Self {
+        HeapQueue {
+            frames: BinaryHeap::with_capacity(capacity),
+            next_back: 0,
+            front: 0,
+            high_water_mark: 0,
+        }
+    }
+
+    /// Returns the number of frames in this queue
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    /// Returns true if this queue does not contain any frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the number of frames that this queue can hold without allocating more memory
+    pub fn capacity(&self) -> usize {
+        self.frames.capacity()
+    }
+
+    fn push_entry(&mut self, frame: Frame<I>, sequence: i64) -> Result<(), OutOfMemoryError> {
+        FrameSink::try_reserve(self, 1)?;
+        self.frames.push(Entry { frame, sequence });
+        self.high_water_mark = self.high_water_mark.max(self.frames.len());
+        Ok(())
+    }
+
+    fn reset_sequence_if_empty(&mut self) {
+        if self.frames.is_empty() {
+            // Nothing to be ordered relative to, so the sequence numbers can start over
+            self.next_back = 0;
+            self.front = 0;
+        }
+    }
+}
+
+impl<I> Default for HeapQueue<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> FrameSink<I> for HeapQueue<I> {
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        self.frames
+            .try_reserve(additional)
+            .map_err(|_| OutOfMemoryError)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.frames.shrink_to_fit()
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.reset_sequence_if_empty();
+        self.push_entry(frame, self.next_back)?;
+        self.next_back += 1;
+        Ok(())
+    }
+}
+
+impl<I> FrameQueueSource<I> for HeapQueue<I> {
+    fn peek_frame(&self) -> Option<&Frame<I>> {
+        self.frames.peek().map(|entry| &entry.frame)
+    }
+
+    fn pop_frame(&mut self) -> Option<Frame<I>> {
+        self.frames.pop().map(|entry| entry.frame)
+    }
+
+    fn return_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.reset_sequence_if_empty();
+        self.push_entry(frame, self.front - 1)?;
+        self.front -= 1;
+        Ok(())
+    }
+}
+
+impl<I> FrameQueueStatus<I> for HeapQueue<I> {
+    type Iter<'a>
+        = HeapQueueIter<'a, I>
+    where
+        I: 'a;
+
+    fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+
+    fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    fn reset_high_water_mark(&mut self) {
+        self.high_water_mark = self.frames.len();
+    }
+
+    /// Returns an iterator over the frames in this queue, in no particular order
+    fn iter(&self) -> Self::Iter<'_> {
+        HeapQueueIter {
+            inner: self.frames.iter(),
+        }
+    }
+}
+
+/// An iterator over the frames in a [`HeapQueue`], in no particular order
+#[derive(Debug)]
+pub struct HeapQueueIter<'a, I> {
+    inner: binary_heap::Iter<'a, Entry<I>>,
+}
+
+impl<'a, I> Iterator for HeapQueueIter<'a, I> {
+    type Item = &'a Frame<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// A frame in a heap queue
+///
+/// Entries are ordered so that the greatest entry has the lowest CAN ID and, among frames with
+/// the same CAN ID, the lowest sequence number.
+#[derive(Debug)]
+struct Entry<I> {
+    frame: Frame<I>,
+    /// The position of this frame relative to other frames with the same CAN ID
+    ///
+    /// Frames pushed to the back get increasing sequence numbers, and frames returned to the front
+    /// get decreasing sequence numbers.
+    sequence: i64,
+}
+
+impl<I> Entry<I> {
+    fn key(&self) -> (CanId, i64) {
+        (self.frame.id(), self.sequence)
+    }
+}
+
+impl<I> PartialEq for Entry<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<I> Eq for Entry<I> {}
+
+impl<I> PartialOrd for Entry<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I> Ord for Entry<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap, so reverse the order
+        other.key().cmp(&self.key())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HeapQueue;
+    use crate::queue::{FrameQueueSource, FrameQueueStatus, FrameSink};
+    use crate::{CanId, Frame};
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+
+    fn frame_with_id(id: u32, data: u8) -> Frame<()> {
+        let id = CanId::try_from(id).unwrap();
+        Frame::new((), id, &[data])
+    }
+
+    #[test]
+    fn priority_then_fifo() {
+        let mut queue = HeapQueue::new();
+        queue.push_frame(frame_with_id(10, 0)).unwrap();
+        queue.push_frame(frame_with_id(10, 1)).unwrap();
+        queue.push_frame(frame_with_id(5, 0)).unwrap();
+        queue.push_frame(frame_with_id(5, 1)).unwrap();
+        queue.push_frame(frame_with_id(10, 2)).unwrap();
+        queue.push_frame(frame_with_id(10, 3)).unwrap();
+        assert_eq!(queue.len(), 6);
+
+        assert_eq!(queue.peek_frame(), Some(&frame_with_id(5, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(5, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(5, 1)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(10, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(10, 1)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(10, 2)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(10, 3)));
+        assert_eq!(queue.pop_frame(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn return_frame_goes_in_front() {
+        let mut queue = HeapQueue::new();
+        for i in 0..4 {
+            queue.push_frame(frame_with_id(128, i)).unwrap();
+        }
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 1)));
+        queue.push_frame(frame_with_id(10, 0)).unwrap();
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(10, 0)));
+        // The driver returns the frames that were displaced from its mailboxes
+        queue.return_frame(frame_with_id(128, 1)).unwrap();
+        queue.return_frame(frame_with_id(128, 0)).unwrap();
+
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 1)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 2)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(128, 3)));
+        assert_eq!(queue.pop_frame(), None);
+    }
+
+    #[test]
+    fn many_frames() {
+        let mut queue = HeapQueue::with_capacity(16);
+        queue.try_reserve(4096).unwrap();
+        for i in 0..4096u32 {
+            queue
+                .push_frame(frame_with_id((i * 7919) % 64, (i / 64) as u8))
+                .unwrap();
+        }
+        let mut last: Option<Frame<()>> = None;
+        while let Some(frame) = queue.pop_frame() {
+            if let Some(last) = last {
+                assert!(
+                    last.id() < frame.id()
+                        || (last.id() == frame.id() && last.data()[0] < frame.data()[0])
+                );
+            }
+            last = Some(frame);
+        }
+    }
+
+    #[test]
+    fn status() {
+        let mut queue = HeapQueue::new();
+        assert_eq!(FrameQueueStatus::capacity(&queue), None);
+        assert_eq!(queue.free(), None);
+        for i in 0..3 {
+            queue.push_frame(frame_with_id(128, i)).unwrap();
+        }
+        queue.pop_frame().unwrap();
+        assert_eq!(queue.high_water_mark(), 3);
+        let mut frames: Vec<Frame<()>> = FrameQueueStatus::iter(&queue).cloned().collect();
+        frames.sort_by_key(|frame| frame.data()[0]);
+        assert_eq!(frames, [frame_with_id(128, 1), frame_with_id(128, 2)]);
+        queue.reset_high_water_mark();
+        assert_eq!(queue.high_water_mark(), 2);
+    }
+}
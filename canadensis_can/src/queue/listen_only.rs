@@ -0,0 +1,120 @@
+use core::iter::Empty;
+use core::marker::PhantomData;
+
+use crate::queue::{FrameQueueSource, FrameQueueStatus, FrameSink};
+use crate::{Frame, OutOfMemoryError};
+
+/// A frame queue for nodes that must never transmit, like bus monitors and test equipment
+///
+/// This queue discards every frame that is pushed onto it and never returns any frames, so a node
+/// that uses it can't send anything. Heartbeats, responses, and other transfers that the node
+/// tries to send are counted and dropped, and the operations that sent them succeed so that
+/// the rest of the application does not need a separate code path for listen-only operation.
+///
+/// For a stronger guarantee, the CAN controller should also be put in its silent (listen-only)
+/// mode if it has one. In that mode the controller does not send acknowledgements or error
+/// frames, so it can't affect the bus at all.
+#[derive(Debug, Default)]
+pub struct ListenOnlyQueue<I> {
+    /// The number of frames that have been discarded
+    discarded: u64,
+    _instant: PhantomData<I>,
+}
+
+impl<I> ListenOnlyQueue<I> {
+    /// Creates a queue
+    pub const fn new() -> Self {
+        ListenOnlyQueue {
+            discarded: 0,
+            _instant: PhantomData,
+        }
+    }
+
+    /// Returns the number of frames that would have been transmitted if this node were not
+    /// listen-only
+    pub fn discarded_frames(&self) -> u64 {
+        self.discarded
+    }
+}
+
+impl<I> FrameSink<I> for ListenOnlyQueue<I> {
+    fn try_reserve(&mut self, _additional: usize) -> Result<(), OutOfMemoryError> {
+        Ok(())
+    }
+
+    fn shrink_to_fit(&mut self) {}
+
+    fn push_frame(&mut self, _frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.discarded = self.discarded.wrapping_add(1);
+        Ok(())
+    }
+}
+
+impl<I> FrameQueueSource<I> for ListenOnlyQueue<I> {
+    fn peek_frame(&self) -> Option<&Frame<I>> {
+        None
+    }
+
+    fn pop_frame(&mut self) -> Option<Frame<I>> {
+        None
+    }
+
+    fn return_frame(&mut self, _frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        // This frame can't have come from this queue. Discard it.
+        Ok(())
+    }
+}
+
+impl<I> FrameQueueStatus<I> for ListenOnlyQueue<I> {
+    type Iter<'a>
+        = Empty<&'a Frame<I>>
+    where
+        I: 'a;
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn high_water_mark(&self) -> usize {
+        0
+    }
+
+    fn reset_high_water_mark(&mut self) {}
+
+    fn iter(&self) -> Self::Iter<'_> {
+        core::iter::empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Mtu, Transmitter};
+    use canadensis_core::transfer::{Header, MessageHeader, Transfer};
+    use canadensis_core::{Priority, SubjectId, TransferId};
+    use core::convert::TryFrom;
+
+    #[test]
+    fn discards_everything() {
+        let mut transmitter = Transmitter::new(Mtu::Can8, ListenOnlyQueue::<u32>::new());
+        let transfer = Transfer {
+            header: Header::Message(MessageHeader {
+                timestamp: 100,
+                transfer_id: TransferId::const_default(),
+                priority: Priority::Nominal,
+                subject: SubjectId::try_from(7509).unwrap(),
+                source: None,
+            }),
+            payload: &[0u8; 7][..],
+        };
+        transmitter.push(transfer).unwrap();
+        let queue = transmitter.frame_queue_mut();
+        assert_eq!(queue.discarded_frames(), 1);
+        assert!(queue.pop_frame().is_none());
+        assert!(queue.is_empty());
+    }
+}
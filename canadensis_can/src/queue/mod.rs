@@ -1,10 +1,22 @@
 //! Queues of outgoing CAN frames
 
 mod array_queue;
+mod double_buffer;
+mod heap_queue;
+mod listen_only;
+#[cfg(target_has_atomic = "8")]
+mod shared;
 
-pub use self::array_queue::ArrayQueue;
+pub use self::array_queue::{ArrayQueue, ArrayQueueIter};
+pub use self::double_buffer::DoubleBufferQueue;
+pub use self::heap_queue::{HeapQueue, HeapQueueIter};
+pub use self::listen_only::ListenOnlyQueue;
+#[cfg(target_has_atomic = "8")]
+pub use self::shared::{SharedQueue, SharedQueueGuard, SharedSink};
 
-use crate::{Frame, OutOfMemoryError};
+use canadensis_core::transfer::Transfer;
+
+use crate::{Frame, Mtu, OutOfMemoryError};
 
 /// A queue of outgoing frames that a transmitter uses to send transfers
 pub trait FrameSink<I> {
@@ -23,6 +35,24 @@ pub trait FrameSink<I> {
     /// frames with an equal or lesser CAN ID. This keeps the frames in order by priority and then
     /// by first-in, first-out.
     fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError>;
+
+    /// Splits a transfer into frames of up to `mtu` bytes and pushes them onto this queue
+    ///
+    /// The default implementation reserves space for all the frames and then pushes each frame.
+    /// Queues that send frames to interfaces with different MTUs (like a
+    /// [`RedundantQueue`](crate::redundant::RedundantQueue) containing an
+    /// [`MtuQueue`](crate::redundant::MtuQueue)) override this function to split the transfer
+    /// differently for each interface.
+    fn push_transfer(
+        &mut self,
+        mtu: Mtu,
+        transfer: Transfer<&[u8], I>,
+    ) -> Result<(), OutOfMemoryError>
+    where
+        I: Clone,
+    {
+        crate::tx::push_transfer_frames(self, mtu, transfer)
+    }
 }
 
 /// A queue of outgoing frames that can be used to copy frames to a CAN controller driver
@@ -42,5 +72,79 @@ pub trait FrameQueueSource<I> {
     ///
     /// The frame must end up behind all existing frames with a lesser CAN ID, but in front of all
     /// frames with a greater or equal CAN ID.
+    ///
+    /// If no frames have been added since the last call to `pop_frame()`, returning the frame that
+    /// it removed must succeed. A driver can therefore pop a frame, try to put it in a transmit
+    /// mailbox, and return it if the mailbox turns out to be full, without storing the frame
+    /// itself.
     fn return_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError>;
 }
+
+/// A frame queue that can report how many frames it contains
+///
+/// Applications can use this information to monitor the transmit backlog and to choose queue
+/// sizes.
+pub trait FrameQueueStatus<I> {
+    /// An iterator over references to the frames in a queue
+    type Iter<'a>: Iterator<Item = &'a Frame<I>>
+    where
+        Self: 'a,
+        I: 'a;
+
+    /// Returns the number of frames in this queue
+    fn len(&self) -> usize;
+    /// Returns true if this queue does not contain any frames
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns the maximum number of frames that this queue can hold, or None if the capacity
+    /// is limited only by the available memory
+    fn capacity(&self) -> Option<usize>;
+    /// Returns the number of additional frames that this queue can hold, or None if the capacity
+    /// is limited only by the available memory
+    fn free(&self) -> Option<usize> {
+        self.capacity()
+            .map(|capacity| capacity.saturating_sub(self.len()))
+    }
+    /// Returns the largest number of frames that this queue has held since it was created or
+    /// since the last call to [`reset_high_water_mark`](#tymethod.reset_high_water_mark)
+    fn high_water_mark(&self) -> usize;
+    /// Resets the high water mark to the current number of frames in the queue
+    fn reset_high_water_mark(&mut self);
+    /// Returns an iterator over the frames in this queue, without removing them
+    ///
+    /// The order of the frames depends on the queue implementation.
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+/// A mutable reference to a sink is also a sink
+///
+/// This allows a transmitter to use a `&mut dyn FrameSink<I>`, so that only one copy of the
+/// transmitter code is needed for all queue types.
+impl<I, Q> FrameSink<I> for &mut Q
+where
+    Q: FrameSink<I> + ?Sized,
+{
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        (**self).try_reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        (**self).shrink_to_fit()
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        (**self).push_frame(frame)
+    }
+
+    fn push_transfer(
+        &mut self,
+        mtu: Mtu,
+        transfer: Transfer<&[u8], I>,
+    ) -> Result<(), OutOfMemoryError>
+    where
+        I: Clone,
+    {
+        (**self).push_transfer(mtu, transfer)
+    }
+}
@@ -0,0 +1,280 @@
+use crate::queue::{FrameQueueSource, FrameSink};
+use crate::{Frame, OutOfMemoryError};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A frame queue that several producers can add frames to at the same time
+///
+/// Each producer uses a [`SharedSink`], which implements [`FrameSink`] and can be given to its
+/// own [`Transmitter`](crate::Transmitter). The driver calls [`lock`](#method.lock) to get
+/// exclusive access to the queue and remove frames. Because all frames go into the same inner
+/// queue `Q`, they are transmitted in order by priority no matter which producer sent them.
+///
+/// Access to the inner queue is protected by a spin lock. This is suitable for multi-core
+/// microcontrollers and threads on an operating system. On a single core, a frame must not be
+/// added or removed from an interrupt handler that may interrupt code that holds the lock,
+/// because that would spin forever.
+///
+/// Reservations made with [`FrameSink::try_reserve`] are shared between all producers, so
+/// a producer that has reserved space for a transfer will not run out of space part way
+/// through because another producer added frames.
+pub struct SharedQueue<Q> {
+    /// True if the inner state is locked
+    locked: AtomicBool,
+    /// The queue and reservation state
+    inner: UnsafeCell<Inner<Q>>,
+}
+
+struct Inner<Q> {
+    queue: Q,
+    /// The total number of frames reserved by all sinks
+    reserved: usize,
+}
+
+// Safety: The inner state is accessed only while the lock is held.
+unsafe impl<Q: Send> Sync for SharedQueue<Q> {}
+
+impl<Q> SharedQueue<Q> {
+    /// Creates a shared queue that wraps a queue
+    pub const fn new(queue: Q) -> Self {
+        SharedQueue {
+            locked: AtomicBool::new(false),
+            inner: UnsafeCell::new(Inner { queue, reserved: 0 }),
+        }
+    }
+
+    /// Returns a new sink that producers can use to add frames to this queue
+    pub fn sink(&self) -> SharedSink<'_, Q> {
+        SharedSink {
+            shared: self,
+            reserved: 0,
+        }
+    }
+
+    /// Waits until no other code is accessing the queue, and then returns a guard that
+    /// provides exclusive access to it
+    ///
+    /// The queue is unlocked when the guard is dropped.
+    pub fn lock(&self) -> SharedQueueGuard<'_, Q> {
+        SharedQueueGuard {
+            inner: self.lock_inner(),
+        }
+    }
+
+    /// Consumes this shared queue and returns the inner queue
+    pub fn into_inner(self) -> Q {
+        self.inner.into_inner().queue
+    }
+
+    fn lock_inner(&self) -> InnerGuard<'_, Q> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                hint::spin_loop();
+            }
+        }
+        InnerGuard { shared: self }
+    }
+}
+
+impl<Q> fmt::Debug for SharedQueue<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedQueue")
+            .field("locked", &self.locked.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Exclusive access to the inner state of a shared queue
+struct InnerGuard<'q, Q> {
+    shared: &'q SharedQueue<Q>,
+}
+
+impl<Q> Deref for InnerGuard<'_, Q> {
+    type Target = Inner<Q>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: This guard holds the lock
+        unsafe { &*self.shared.inner.get() }
+    }
+}
+
+impl<Q> DerefMut for InnerGuard<'_, Q> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: This guard holds the lock
+        unsafe { &mut *self.shared.inner.get() }
+    }
+}
+
+impl<Q> Drop for InnerGuard<'_, Q> {
+    fn drop(&mut self) {
+        self.shared.locked.store(false, Ordering::Release);
+    }
+}
+
+/// Exclusive access to the queue inside a [`SharedQueue`]
+///
+/// The guard dereferences to the inner queue and also implements [`FrameQueueSource`], so
+/// a driver can use it to remove frames.
+pub struct SharedQueueGuard<'q, Q> {
+    inner: InnerGuard<'q, Q>,
+}
+
+impl<Q> Deref for SharedQueueGuard<'_, Q> {
+    type Target = Q;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.queue
+    }
+}
+
+impl<Q> DerefMut for SharedQueueGuard<'_, Q> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner.queue
+    }
+}
+
+impl<Q> fmt::Debug for SharedQueueGuard<'_, Q>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedQueueGuard")
+            .field(&self.inner.queue)
+            .finish()
+    }
+}
+
+impl<I, Q> FrameQueueSource<I> for SharedQueueGuard<'_, Q>
+where
+    Q: FrameQueueSource<I>,
+{
+    fn peek_frame(&self) -> Option<&Frame<I>> {
+        self.inner.queue.peek_frame()
+    }
+
+    fn pop_frame(&mut self) -> Option<Frame<I>> {
+        self.inner.queue.pop_frame()
+    }
+
+    fn return_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.inner.queue.return_frame(frame)
+    }
+}
+
+/// A handle that one producer uses to add frames to a [`SharedQueue`]
+///
+/// When a sink is dropped, any space that it reserved and did not use is released.
+pub struct SharedSink<'q, Q> {
+    shared: &'q SharedQueue<Q>,
+    /// The number of frames that this sink has reserved and not yet pushed
+    reserved: usize,
+}
+
+impl<Q> fmt::Debug for SharedSink<'_, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSink")
+            .field("reserved", &self.reserved)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Q> Clone for SharedSink<'_, Q> {
+    /// Returns another sink for the same queue, with no space reserved
+    fn clone(&self) -> Self {
+        self.shared.sink()
+    }
+}
+
+impl<I, Q> FrameSink<I> for SharedSink<'_, Q>
+where
+    Q: FrameSink<I>,
+{
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        let mut inner = self.shared.lock_inner();
+        // Keep the space that other sinks have reserved, and replace this sink's reservation
+        let total = inner.reserved - self.reserved + additional;
+        inner.queue.try_reserve(total)?;
+        inner.reserved = total;
+        self.reserved = additional;
+        Ok(())
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.shared.lock_inner().queue.shrink_to_fit()
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        let mut inner = self.shared.lock_inner();
+        if self.reserved == 0 {
+            // Don't use space that other sinks have reserved
+            let reserved = inner.reserved;
+            inner.queue.try_reserve(reserved + 1)?;
+        }
+        inner.queue.push_frame(frame)?;
+        if self.reserved != 0 {
+            self.reserved -= 1;
+            inner.reserved -= 1;
+        }
+        Ok(())
+    }
+}
+
+impl<Q> Drop for SharedSink<'_, Q> {
+    fn drop(&mut self) {
+        if self.reserved != 0 {
+            self.shared.lock_inner().reserved -= self.reserved;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SharedQueue;
+    use crate::queue::{ArrayQueue, FrameQueueSource, FrameSink};
+    use crate::{CanId, Frame};
+    use core::convert::TryFrom;
+
+    fn frame_with_id(id: u32, data: u8) -> Frame<()> {
+        let id = CanId::try_from(id).unwrap();
+        Frame::new((), id, &[data])
+    }
+
+    #[test]
+    fn reservations_are_shared() {
+        let shared = SharedQueue::new(ArrayQueue::<(), 4>::new());
+        let mut sink_a = shared.sink();
+        let mut sink_b = shared.sink();
+
+        sink_a.try_reserve(3).unwrap();
+        assert!(sink_b.try_reserve(2).is_err());
+        // Sink B can use the space that sink A did not reserve
+        sink_b.push_frame(frame_with_id(1, 0)).unwrap();
+        assert!(sink_b.push_frame(frame_with_id(1, 1)).is_err());
+
+        sink_a.push_frame(frame_with_id(2, 0)).unwrap();
+        sink_a.push_frame(frame_with_id(2, 1)).unwrap();
+        sink_a.push_frame(frame_with_id(2, 2)).unwrap();
+
+        let mut queue = shared.lock();
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(1, 0)));
+        assert_eq!(queue.pop_frame(), Some(frame_with_id(2, 0)));
+    }
+
+    #[test]
+    fn drop_releases_reservation() {
+        let shared = SharedQueue::new(ArrayQueue::<(), 2>::new());
+        let mut sink_a = shared.sink();
+        sink_a.try_reserve(2).unwrap();
+        let mut sink_b = sink_a.clone();
+        assert!(sink_b.try_reserve(1).is_err());
+        drop(sink_a);
+        sink_b.try_reserve(2).unwrap();
+    }
+}
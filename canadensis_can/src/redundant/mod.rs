@@ -2,5 +2,9 @@
 
 mod deduplicator;
 pub use self::deduplicator::Deduplicator;
+mod mtu_queue;
+pub use self::mtu_queue::MtuQueue;
 mod redundant_queue;
 pub use self::redundant_queue::RedundantQueue;
+mod transfer_deduplicator;
+pub use self::transfer_deduplicator::TransferDeduplicator;
@@ -0,0 +1,142 @@
+use canadensis_core::transfer::Transfer;
+
+use crate::queue::{FrameQueueSource, FrameQueueStatus, FrameSink};
+use crate::{Frame, Mtu, OutOfMemoryError};
+
+/// A frame queue for an interface that has its own MTU
+///
+/// When a transmitter sends a transfer, an `MtuQueue` splits the transfer into frames using its
+/// own MTU instead of the transmitter's MTU. Inside a [`RedundantQueue`](super::RedundantQueue),
+/// this allows one node to send the same transfers on interfaces with different MTUs, such as
+/// one Classic CAN bus and one CAN FD bus.
+///
+/// The receiver of a node with interfaces that have different MTUs should use the largest MTU.
+///
+/// # Examples
+///
+/// ```
+/// # use canadensis_can::redundant::{MtuQueue, RedundantQueue};
+/// # use canadensis_can::queue::ArrayQueue;
+/// # use canadensis_can::{Mtu, Transmitter};
+/// # use canadensis_core::time::Microseconds32;
+/// let queue = RedundantQueue::new(
+///     MtuQueue::new(Mtu::Can8, ArrayQueue::<Microseconds32, 64>::new()),
+///     MtuQueue::new(Mtu::Can8, ArrayQueue::<Microseconds32, 64>::new()),
+/// );
+/// // The MTU of the transmitter is not used
+/// let transmitter = Transmitter::new(Mtu::Can8, queue);
+/// ```
+pub struct MtuQueue<Q> {
+    /// The MTU of the interface
+    mtu: Mtu,
+    /// The queue of frames for the interface
+    queue: Q,
+}
+
+impl<Q> MtuQueue<Q> {
+    /// Creates a queue for an interface with the provided MTU
+    pub fn new(mtu: Mtu, queue: Q) -> Self {
+        MtuQueue { mtu, queue }
+    }
+
+    /// Returns the MTU of the interface
+    pub fn mtu(&self) -> Mtu {
+        self.mtu
+    }
+    /// Sets the MTU of the interface
+    ///
+    /// This will take effect on the next transfer.
+    pub fn set_mtu(&mut self, mtu: Mtu) {
+        self.mtu = mtu;
+    }
+
+    /// Returns a reference to the enclosed queue
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+    /// Returns a mutable reference to the enclosed queue
+    pub fn queue_mut(&mut self) -> &mut Q {
+        &mut self.queue
+    }
+    /// Returns the enclosed queue
+    pub fn into_inner(self) -> Q {
+        self.queue
+    }
+}
+
+impl<I, Q> FrameSink<I> for MtuQueue<Q>
+where
+    Q: FrameSink<I>,
+{
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        self.queue.try_reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.queue.shrink_to_fit()
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.queue.push_frame(frame)
+    }
+
+    /// Splits a transfer into frames using the MTU of this queue, ignoring the provided MTU
+    fn push_transfer(
+        &mut self,
+        _mtu: Mtu,
+        transfer: Transfer<&[u8], I>,
+    ) -> Result<(), OutOfMemoryError>
+    where
+        I: Clone,
+    {
+        self.queue.push_transfer(self.mtu, transfer)
+    }
+}
+
+impl<I, Q> FrameQueueSource<I> for MtuQueue<Q>
+where
+    Q: FrameQueueSource<I>,
+{
+    fn peek_frame(&self) -> Option<&Frame<I>> {
+        self.queue.peek_frame()
+    }
+
+    fn pop_frame(&mut self) -> Option<Frame<I>> {
+        self.queue.pop_frame()
+    }
+
+    fn return_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        self.queue.return_frame(frame)
+    }
+}
+
+impl<I, Q> FrameQueueStatus<I> for MtuQueue<Q>
+where
+    Q: FrameQueueStatus<I>,
+{
+    type Iter<'a>
+        = Q::Iter<'a>
+    where
+        Self: 'a,
+        I: 'a;
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        self.queue.capacity()
+    }
+
+    fn high_water_mark(&self) -> usize {
+        self.queue.high_water_mark()
+    }
+
+    fn reset_high_water_mark(&mut self) {
+        self.queue.reset_high_water_mark()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.queue.iter()
+    }
+}
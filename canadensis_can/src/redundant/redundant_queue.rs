@@ -1,5 +1,7 @@
+use canadensis_core::transfer::Transfer;
+
 use crate::queue::FrameSink;
-use crate::{Frame, OutOfMemoryError};
+use crate::{Frame, Mtu, OutOfMemoryError};
 
 /// An aggregation of two outgoing frame queues that can be used for double-redundant transports
 ///
@@ -204,4 +206,19 @@ where
         // This is successful if the frame got onto at least one queue.
         push_status_0.or(push_status_1)
     }
+
+    /// Splits a transfer into frames separately for each queue, returning `Ok(())` if the
+    /// operation succeeded on at least one queue
+    ///
+    /// This allows each queue to use a different MTU.
+    fn push_transfer(
+        &mut self,
+        mtu: Mtu,
+        transfer: Transfer<&[u8], I>,
+    ) -> Result<(), OutOfMemoryError> {
+        let push_status_0 = self.queue0.push_transfer(mtu, transfer.clone());
+        let push_status_1 = self.queue1.push_transfer(mtu, transfer);
+        // This is successful if the transfer got onto at least one queue.
+        push_status_0.or(push_status_1)
+    }
 }
@@ -0,0 +1,211 @@
+use canadensis_core::time::Instant;
+use canadensis_core::transfer::Header;
+use canadensis_core::{NodeId, PortId, TransferId};
+
+/// The number of distinct transfer IDs
+const TRANSFER_ID_MODULO: u8 = 32;
+
+/// Deduplicates incoming transfers from multiple receivers
+///
+/// Type parameters:
+/// * `I`: The Instant type used for timing
+/// * `S`: The maximum number of sessions (combinations of transfer kind, port ID, and source
+///   node) to track
+///
+/// # Behavior
+///
+/// Unlike [`Deduplicator`](super::Deduplicator), which selects frames from one transport, this
+/// deduplicator works on complete transfers. Each transport can have its own
+/// [`Receiver`](crate::Receiver), and the transfers from all receivers go through one
+/// `TransferDeduplicator` before they are handled.
+///
+/// A transfer is a duplicate if a transfer with the same kind, port ID, source node, and transfer
+/// ID was accepted less than `window` before it. Because a slow transport may deliver a transfer
+/// after a faster transport has already delivered the next one, a transfer whose ID is less than
+/// the last accepted ID (accounting for wraparound, within half of the transfer ID range) is also
+/// a duplicate. After the window has passed with no accepted transfers, the next transfer in the
+/// session is always accepted.
+///
+/// The window should be shorter than the time it takes a publisher to use all 32 transfer IDs,
+/// but longer than the largest difference in latency between the transports.
+///
+/// Anonymous transfers do not have a source node ID, so they are always accepted.
+///
+/// If `S` sessions are already being tracked and a transfer arrives for a new session, sessions
+/// whose windows have expired are removed. If there are still no free slots, the transfer is
+/// accepted and the session is not tracked.
+#[derive(Debug)]
+pub struct TransferDeduplicator<I: Instant, const S: usize> {
+    /// The tracked sessions
+    sessions: heapless::Vec<Session<I>, S>,
+    /// The maximum time between copies of the same transfer
+    window: I::Duration,
+}
+
+impl<I, const S: usize> TransferDeduplicator<I, S>
+where
+    I: Instant,
+{
+    /// Creates a deduplicator
+    ///
+    /// window: The maximum time between the arrivals of copies of the same transfer
+    pub fn new(window: I::Duration) -> Self {
+        TransferDeduplicator {
+            sessions: heapless::Vec::new(),
+            window,
+        }
+    }
+
+    /// Determines if a transfer should be handled
+    ///
+    /// This function returns true if the transfer is new, or false if it is a duplicate of
+    /// a transfer that was already accepted.
+    pub fn accept(&mut self, header: &Header<I>) -> bool {
+        let source = match header.source() {
+            Some(source) => source,
+            None => return true,
+        };
+        let key = SessionKey {
+            kind: SessionKind::from_header(header),
+            port: header.port_id(),
+            source,
+        };
+        let now = header.timestamp();
+        let transfer_id = header.transfer_id();
+
+        if let Some(session) = self.sessions.iter_mut().find(|session| session.key == key) {
+            let in_window = now.duration_since(&session.last_time) <= self.window;
+            if in_window && !is_after(transfer_id, session.last_transfer_id) {
+                // Duplicate or old transfer
+                false
+            } else {
+                session.last_transfer_id = transfer_id;
+                session.last_time = now;
+                true
+            }
+        } else {
+            let session = Session {
+                key,
+                last_transfer_id: transfer_id,
+                last_time: now,
+            };
+            if self.sessions.is_full() {
+                self.clean_expired(now);
+            }
+            // If there is still no space, accept without tracking
+            let _ = self.sessions.push(session);
+            true
+        }
+    }
+
+    /// Removes all sessions whose windows have expired
+    pub fn clean_expired(&mut self, now: I) {
+        let window = self.window;
+        self.sessions
+            .retain(|session| now.duration_since(&session.last_time) <= window);
+    }
+
+    /// Removes all sessions
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+}
+
+/// Returns true if `id` is after `last`, accounting for wraparound
+///
+/// IDs in the half of the range after `last` are considered to be after it.
+fn is_after(id: TransferId, last: TransferId) -> bool {
+    let distance = u8::from(id).wrapping_sub(u8::from(last)) % TRANSFER_ID_MODULO;
+    distance != 0 && distance < TRANSFER_ID_MODULO / 2
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SessionKind {
+    Message,
+    Request,
+    Response,
+}
+
+impl SessionKind {
+    fn from_header<I>(header: &Header<I>) -> Self {
+        match header {
+            Header::Message(_) => SessionKind::Message,
+            Header::Request(_) => SessionKind::Request,
+            Header::Response(_) => SessionKind::Response,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct SessionKey {
+    kind: SessionKind,
+    port: PortId,
+    source: NodeId,
+}
+
+#[derive(Debug)]
+struct Session<I> {
+    key: SessionKey,
+    /// The ID of the last accepted transfer
+    last_transfer_id: TransferId,
+    /// The time when the last transfer was accepted
+    last_time: I,
+}
+
+#[cfg(test)]
+mod test {
+    use super::TransferDeduplicator;
+    use canadensis_core::time::{milliseconds, Microseconds32};
+    use canadensis_core::transfer::{Header, MessageHeader};
+    use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+    use core::convert::TryFrom;
+
+    type TestInstant = Microseconds32;
+
+    fn message(time: u32, source: u8, transfer_id: u8) -> Header<TestInstant> {
+        Header::Message(MessageHeader {
+            timestamp: TestInstant::new(time),
+            transfer_id: TransferId::try_from(transfer_id).unwrap(),
+            priority: Priority::Nominal,
+            subject: SubjectId::try_from(7509).unwrap(),
+            source: Some(NodeId::try_from(source).unwrap()),
+        })
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut deduplicator = TransferDeduplicator::<TestInstant, 4>::new(milliseconds(10));
+        assert!(deduplicator.accept(&message(0, 1, 0)));
+        assert!(!deduplicator.accept(&message(100, 1, 0)));
+        // Same transfer ID from a different source
+        assert!(deduplicator.accept(&message(100, 2, 0)));
+        assert!(deduplicator.accept(&message(200, 1, 1)));
+        // A late copy of the first transfer
+        assert!(!deduplicator.accept(&message(300, 1, 0)));
+        // After the window expires, the same transfer ID is accepted
+        assert!(deduplicator.accept(&message(20_000, 1, 1)));
+    }
+
+    #[test]
+    fn wraparound() {
+        let mut deduplicator = TransferDeduplicator::<TestInstant, 4>::new(milliseconds(10));
+        assert!(deduplicator.accept(&message(0, 1, 30)));
+        assert!(deduplicator.accept(&message(1, 1, 31)));
+        assert!(deduplicator.accept(&message(2, 1, 0)));
+        assert!(!deduplicator.accept(&message(3, 1, 31)));
+        assert!(deduplicator.accept(&message(4, 1, 1)));
+        assert!(!deduplicator.accept(&message(5, 1, 0)));
+    }
+
+    #[test]
+    fn full() {
+        let mut deduplicator = TransferDeduplicator::<TestInstant, 1>::new(milliseconds(10));
+        assert!(deduplicator.accept(&message(0, 1, 0)));
+        // Not tracked
+        assert!(deduplicator.accept(&message(1, 2, 0)));
+        assert!(deduplicator.accept(&message(2, 2, 0)));
+        // The first session has expired, so the new session replaces it
+        assert!(deduplicator.accept(&message(20_000, 2, 0)));
+        assert!(!deduplicator.accept(&message(20_001, 2, 0)));
+    }
+}
@@ -3,18 +3,24 @@
 //!
 
 mod buildup;
+mod sequence;
 mod session;
 mod subscription;
+mod wildcard;
+
+pub use self::wildcard::SubjectSet;
 
 use alloc::vec::Vec;
 use core::convert::{TryFrom, TryInto};
 
 use fallible_collections::FallibleVec;
 
-use crate::data::{CanId, Frame};
+use crate::buffer::{BufferAllocator, HeapAllocator, TransferBuffer};
+use crate::data::{CanId, Frame, FRAME_CAPACITY};
 use crate::error::OutOfMemoryError;
 use crate::rx::session::SessionError;
 use crate::rx::subscription::{Subscription, SubscriptionError};
+use crate::rx::wildcard::Wildcard;
 use crate::Mtu;
 use canadensis_core::time::Instant;
 use canadensis_core::transfer::{Header, MessageHeader, ServiceHeader, Transfer};
@@ -22,18 +28,32 @@ use canadensis_core::{NodeId, PortId, Priority, ServiceId, SubjectId, TransferId
 use canadensis_filter_config::Filter;
 
 /// Handles subscriptions and assembles incoming frames into transfers
+///
+/// The allocator `A` provides memory for the payloads of incoming transfers. By default, payloads
+/// are stored in `Vec`s.
 #[derive(Debug)]
-pub struct Receiver<I: Instant> {
+pub struct Receiver<I: Instant, A: BufferAllocator = HeapAllocator> {
     /// Subscriptions for messages
-    subscriptions_message: Vec<Subscription<I>>,
+    subscriptions_message: Vec<Subscription<I, A::Buffer>>,
     /// Subscriptions for service responses
-    subscriptions_response: Vec<Subscription<I>>,
+    subscriptions_response: Vec<Subscription<I, A::Buffer>>,
     /// Subscriptions for service requests
-    subscriptions_request: Vec<Subscription<I>>,
+    subscriptions_request: Vec<Subscription<I, A::Buffer>>,
+    /// Wildcard message subscriptions, which create subscriptions in subscriptions_message
+    /// when their first frames arrive
+    message_wildcards: Vec<Wildcard<I::Duration>>,
+    /// The allocator for transfer payloads
+    allocator: A,
     /// The ID of this node, or None if this node is anonymous
     id: Option<NodeId>,
+    /// Settings for automatic subscriptions, or None if promiscuous mode is disabled
+    promiscuous: Option<PromiscuousSettings<I::Duration>>,
     /// MTU of the transport
     mtu: Mtu,
+    /// What to do with frames that are longer than the MTU
+    oversize_frame_policy: OversizeFramePolicy,
+    /// Number of frames received that were longer than the MTU
+    oversize_frame_count: u64,
     /// Number of transfers successfully received
     transfer_count: u64,
     /// Number of transfers that could not be received
@@ -48,23 +68,44 @@ impl<I: Instant> Receiver<I> {
     ///
     /// id: The ID of this node. This is used to filter incoming service requests and responses.
     pub fn new(id: NodeId, mtu: Mtu) -> Self {
-        Self::new_inner(Some(id), mtu)
+        Self::new_inner(Some(id), mtu, HeapAllocator)
     }
 
     /// Creates an anonymous receiver
     ///
     /// An anonymous receiver cannot receive service requests or responses.
     pub fn new_anonymous(mtu: Mtu) -> Self {
-        Self::new_inner(None, mtu)
+        Self::new_inner(None, mtu, HeapAllocator)
+    }
+}
+
+impl<I: Instant, A: BufferAllocator> Receiver<I, A> {
+    /// Creates a receiver that uses the provided allocator for transfer payloads
+    ///
+    /// id: The ID of this node. This is used to filter incoming service requests and responses.
+    pub fn with_allocator(id: NodeId, mtu: Mtu, allocator: A) -> Self {
+        Self::new_inner(Some(id), mtu, allocator)
     }
 
-    fn new_inner(id: Option<NodeId>, mtu: Mtu) -> Self {
+    /// Creates an anonymous receiver that uses the provided allocator for transfer payloads
+    ///
+    /// An anonymous receiver cannot receive service requests or responses.
+    pub fn anonymous_with_allocator(mtu: Mtu, allocator: A) -> Self {
+        Self::new_inner(None, mtu, allocator)
+    }
+
+    fn new_inner(id: Option<NodeId>, mtu: Mtu, allocator: A) -> Self {
         Receiver {
             subscriptions_message: Vec::new(),
             subscriptions_response: Vec::new(),
             subscriptions_request: Vec::new(),
+            message_wildcards: Vec::new(),
+            allocator,
             id,
+            promiscuous: None,
             mtu,
+            oversize_frame_policy: OversizeFramePolicy::default(),
+            oversize_frame_count: 0,
             transfer_count: 0,
             error_count: 0,
         }
@@ -78,10 +119,63 @@ impl<I: Instant> Receiver<I> {
         self.id = id;
     }
 
+    /// Enables promiscuous mode
+    ///
+    /// In promiscuous mode, this receiver accepts all valid transfers, including service transfers
+    /// addressed to other nodes. When a frame arrives on a port that this receiver is not
+    /// subscribed to, the receiver creates a subscription for that port with the provided maximum
+    /// payload size and timeout.
+    ///
+    /// This is intended for passive tools like bus monitors and log decoders. Because sessions
+    /// are identified by source node and port, two service transfers sent by the same node to
+    /// different destinations at the same time may interfere with each other.
+    ///
+    /// While promiscuous mode is enabled, [`frame_filters`](#method.frame_filters) returns a
+    /// filter that accepts all frames.
+    pub fn enable_promiscuous(&mut self, payload_size_max: usize, timeout: I::Duration) {
+        self.promiscuous = Some(PromiscuousSettings {
+            payload_size_max,
+            timeout,
+        });
+    }
+
+    /// Disables promiscuous mode
+    ///
+    /// Subscriptions that were created automatically in promiscuous mode are kept.
+    pub fn disable_promiscuous(&mut self) {
+        self.promiscuous = None;
+    }
+
+    /// Returns true if this receiver is in promiscuous mode
+    pub fn is_promiscuous(&self) -> bool {
+        self.promiscuous.is_some()
+    }
+
+    /// Sets what this receiver does with frames that have more data than its MTU allows
+    ///
+    /// This is useful on a bus where some nodes send CAN FD frames and this receiver is
+    /// configured for Classic CAN, for example during a migration from Classic CAN to CAN FD.
+    /// The default policy is [`OversizeFramePolicy::Accept`].
+    pub fn set_oversize_frame_policy(&mut self, policy: OversizeFramePolicy) {
+        self.oversize_frame_policy = policy;
+    }
+
+    /// Returns what this receiver does with frames that have more data than its MTU allows
+    pub fn oversize_frame_policy(&self) -> OversizeFramePolicy {
+        self.oversize_frame_policy
+    }
+
+    /// Returns the number of frames received that had more data than the MTU allows
+    ///
+    /// This includes frames that were accepted, truncated, and rejected.
+    pub fn oversize_frame_count(&self) -> u64 {
+        self.oversize_frame_count
+    }
+
     /// Handles an incoming CAN or CAN FD frame
     ///
     /// If this frame is the last frame in a transfer, this function returns the completed transfer.
-    /// The transfer type is `Transfer<Vec<u8>>`, which owns the payload buffer.
+    /// The transfer owns its payload buffer, which comes from this receiver's allocator.
     ///
     /// The payload of the returned transfer does not include any tail bytes or CRC.
     ///
@@ -92,10 +186,15 @@ impl<I: Instant> Receiver<I> {
     pub fn accept(
         &mut self,
         frame: Frame<I>,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, OutOfMemoryError> {
+    ) -> Result<Option<Transfer<A::Buffer, I>>, OutOfMemoryError> {
         // The current time is equal to or greater than the frame timestamp. Use that timestamp
         // to clean up expired sessions.
-        self.clean_expired_sessions(frame.timestamp());
+        self.clean_expired(frame.timestamp());
+
+        let frame = match self.apply_oversize_frame_policy(frame) {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
 
         // Part 1: basic frame checks
         let (frame_header, tail) = match Self::frame_sanity_check(&frame) {
@@ -108,14 +207,14 @@ impl<I: Instant> Receiver<I> {
             }
         };
         // Check that the frame is actually destined for this node, and this node can handle services
-        if let Header::Request(service_header) | Header::Response(service_header) = &frame_header {
-            if let Some(this_id) = self.id {
-                if service_header.destination != this_id {
-                    // This frame is a service request or response going to some other node
-                    return Ok(None);
-                }
-            } else {
-                // This node is anonymous, so it must ignore all service frames
+        // (in promiscuous mode, accept service frames for all nodes)
+        if let (Header::Request(service_header) | Header::Response(service_header), None) =
+            (&frame_header, &self.promiscuous)
+        {
+            if self.id != Some(service_header.destination) && !self.sniffing(&frame_header) {
+                // This frame is a service request or response going to some other node,
+                // or this node is anonymous and must ignore all service frames that it is
+                // not sniffing
                 return Ok(None);
             }
         }
@@ -123,20 +222,71 @@ impl<I: Instant> Receiver<I> {
         self.accept_sane_frame(frame, frame_header, tail)
     }
 
+    /// Checks the length of an incoming frame against the MTU and applies the oversize frame
+    /// policy
+    ///
+    /// This returns the frame to handle, or None if the frame should be ignored.
+    fn apply_oversize_frame_policy(&mut self, frame: Frame<I>) -> Option<Frame<I>> {
+        let mtu = self.mtu.as_bytes();
+        if frame.data().len() <= mtu {
+            return Some(frame);
+        }
+        self.oversize_frame_count = self.oversize_frame_count.wrapping_add(1);
+        match self.oversize_frame_policy {
+            OversizeFramePolicy::Accept => Some(frame),
+            OversizeFramePolicy::Truncate => {
+                log::debug!(
+                    "Truncating frame with {} bytes to MTU {}",
+                    frame.data().len(),
+                    mtu
+                );
+                self.increment_error_count();
+                // Keep the tail byte, which is always the last byte
+                let data = frame.data();
+                let mut truncated = [0u8; FRAME_CAPACITY];
+                truncated[..mtu - 1].copy_from_slice(&data[..mtu - 1]);
+                truncated[mtu - 1] = data[data.len() - 1];
+                Some(Frame::new(frame.timestamp(), frame.id(), &truncated[..mtu]))
+            }
+            OversizeFramePolicy::Reject => {
+                log::debug!(
+                    "Rejecting frame with {} bytes, longer than MTU {}",
+                    frame.data().len(),
+                    mtu
+                );
+                self.increment_error_count();
+                None
+            }
+        }
+    }
+
     /// Handles an incoming frame that has passed sanity checks and has a parsed header and tail byte
     fn accept_sane_frame(
         &mut self,
         frame: Frame<I>,
         frame_header: Header<I>,
         tail: TailByte,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, OutOfMemoryError> {
+    ) -> Result<Option<Transfer<A::Buffer, I>>, OutOfMemoryError> {
         let kind = TransferKind::from_header(&frame_header);
-        let subscriptions = self.subscriptions_for_kind(kind);
-        if let Some(subscription) = subscriptions
-            .iter_mut()
-            .find(|subscription| subscription.port_id() == frame_header.port_id())
-        {
-            match subscription.accept(frame, frame_header, tail) {
+        if let Some(settings) = self.promiscuous.clone() {
+            let port_id = frame_header.port_id();
+            let subscribed = find_subscription(self.subscriptions_for_kind(kind), port_id).is_ok();
+            if !subscribed {
+                self.subscribe(kind, port_id, settings.payload_size_max, settings.timeout)?;
+            }
+        }
+        if let Header::Message(message_header) = &frame_header {
+            self.subscribe_from_wildcard(message_header.subject)?;
+        }
+        // Borrow the subscriptions and allocator separately
+        let subscriptions = match kind {
+            TransferKind::Message => &mut self.subscriptions_message,
+            TransferKind::Response => &mut self.subscriptions_response,
+            TransferKind::Request => &mut self.subscriptions_request,
+        };
+        if let Ok(index) = find_subscription(subscriptions, frame_header.port_id()) {
+            let subscription = &mut subscriptions[index];
+            match subscription.accept(frame, frame_header, tail, &mut self.allocator) {
                 Ok(Some(transfer)) => {
                     self.increment_transfer_count();
                     Ok(Some(transfer))
@@ -164,24 +314,8 @@ impl<I: Instant> Receiver<I> {
     /// Runs basic sanity checks on an incoming frame. Returns the header and tail byte if the frame
     /// is valid.
     fn frame_sanity_check(frame: &Frame<I>) -> Option<(Header<I>, TailByte)> {
-        // Frame must have a tail byte to be valid
-        let tail_byte = TailByte::parse(*frame.data().last()?);
-
-        let header = parse_can_id(frame.id(), frame.timestamp(), tail_byte.transfer_id).ok()?;
-
-        // Additional header checks
-        if let Header::Message(message_header) = &header {
-            if message_header.source.is_none() {
-                // Anonymous message transfers must always fit into one frame
-                if !(tail_byte.toggle && tail_byte.start && tail_byte.end) {
-                    log::debug!("Anonymous multi-frame transfer, ignoring");
-                    return None;
-                }
-            }
-        }
-
-        // OK
-        Some((header, tail_byte))
+        let (header, tail_byte) = frame_sanity_check_inner(frame.id(), frame.data())?;
+        Some((add_timestamp(header, frame.timestamp()), tail_byte))
     }
 
     /// Subscribes to messages on a subject
@@ -217,6 +351,71 @@ impl<I: Instant> Receiver<I> {
         self.unsubscribe(TransferKind::Message, PortId::from(subject));
     }
 
+    /// Subscribes to messages on a set of subjects
+    ///
+    /// This is equivalent to calling [`subscribe_message`](#method.subscribe_message) for every
+    /// subject in the set, but uses memory only for subjects that messages actually arrive on.
+    /// When the first frame arrives on a subject in the set, this receiver creates a subscription
+    /// for that subject with the provided maximum payload size and timeout. The subject ID of each
+    /// transfer is in its header.
+    ///
+    /// Existing subscriptions to individual subjects in the set are not changed.
+    pub fn subscribe_message_set(
+        &mut self,
+        subjects: SubjectSet,
+        payload_size_max: usize,
+        timeout: I::Duration,
+    ) -> Result<(), OutOfMemoryError> {
+        FallibleVec::try_push(
+            &mut self.message_wildcards,
+            Wildcard {
+                subjects,
+                payload_size_max,
+                timeout,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Removes a subscription created by [`subscribe_message_set`](#method.subscribe_message_set)
+    ///
+    /// The subscriptions to individual subjects that the set subscription created are also
+    /// removed, unless another set subscription still includes them.
+    pub fn unsubscribe_message_set(&mut self, subjects: &SubjectSet) {
+        self.message_wildcards
+            .retain(|wildcard| wildcard.subjects != *subjects);
+        let wildcards = &self.message_wildcards;
+        self.subscriptions_message.retain(|subscription| {
+            let subject = SubjectId::try_from(subscription.port_id()).unwrap();
+            !(subscription.automatic()
+                && subjects.contains(subject)
+                && !wildcards
+                    .iter()
+                    .any(|wildcard| wildcard.subjects.contains(subject)))
+        });
+    }
+
+    /// If a wildcard subscription includes a subject and there is no subscription to the subject,
+    /// creates a subscription
+    fn subscribe_from_wildcard(&mut self, subject: SubjectId) -> Result<(), OutOfMemoryError> {
+        let port_id = PortId::from(subject);
+        if find_subscription(&self.subscriptions_message, port_id).is_ok() {
+            return Ok(());
+        }
+        let wildcard = self
+            .message_wildcards
+            .iter()
+            .find(|wildcard| wildcard.subjects.contains(subject));
+        if let Some(wildcard) = wildcard {
+            let (payload_size_max, timeout) = (wildcard.payload_size_max, wildcard.timeout);
+            self.subscribe(TransferKind::Message, port_id, payload_size_max, timeout)?;
+            if let Ok(index) = find_subscription(&self.subscriptions_message, port_id) {
+                self.subscriptions_message[index].set_automatic(true);
+            }
+        }
+        Ok(())
+    }
+
     /// Subscribes to requests for a service
     ///
     /// This will enable incoming service request transfers from all nodes on the specified service
@@ -298,6 +497,76 @@ impl<I: Instant> Receiver<I> {
         self.unsubscribe(TransferKind::Response, PortId::from(service));
     }
 
+    /// Subscribes to requests for a service, including requests sent to other nodes
+    ///
+    /// This is intended for bus analyzers and other diagnostic tools. The destination of each
+    /// request is available in the transfer header. Unlike
+    /// [`subscribe_request`](#method.subscribe_request), this also works for an anonymous
+    /// receiver.
+    ///
+    /// The other parameters are the same as for `subscribe_request`. This replaces any existing
+    /// subscription to requests for the service. To stop sniffing, call
+    /// [`unsubscribe_request`](#method.unsubscribe_request).
+    pub fn sniff_requests(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: I::Duration,
+    ) -> Result<(), OutOfMemoryError> {
+        self.subscribe_sniffing(
+            TransferKind::Request,
+            PortId::from(service),
+            payload_size_max,
+            timeout,
+        )
+    }
+
+    /// Subscribes to responses for a service, including responses sent to other nodes
+    ///
+    /// This works in the same way as [`sniff_requests`](#method.sniff_requests). To stop sniffing,
+    /// call [`unsubscribe_response`](#method.unsubscribe_response).
+    pub fn sniff_responses(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: I::Duration,
+    ) -> Result<(), OutOfMemoryError> {
+        self.subscribe_sniffing(
+            TransferKind::Response,
+            PortId::from(service),
+            payload_size_max,
+            timeout,
+        )
+    }
+
+    fn subscribe_sniffing(
+        &mut self,
+        kind: TransferKind,
+        port_id: PortId,
+        payload_size_max: usize,
+        timeout: I::Duration,
+    ) -> Result<(), OutOfMemoryError> {
+        self.subscribe(kind, port_id, payload_size_max, timeout)?;
+        let subscriptions = self.subscriptions_for_kind(kind);
+        if let Ok(index) = find_subscription(subscriptions, port_id) {
+            subscriptions[index].set_sniff(true);
+        }
+        Ok(())
+    }
+
+    /// Returns true if this receiver has a sniffing subscription for the port of a transfer
+    fn sniffing(&self, header: &Header<I>) -> bool {
+        let subscriptions = match header {
+            Header::Message(_) => &self.subscriptions_message,
+            Header::Request(_) => &self.subscriptions_request,
+            Header::Response(_) => &self.subscriptions_response,
+        };
+        match find_subscription(subscriptions, header.port_id()) {
+            Ok(index) => subscriptions[index].sniff(),
+            Err(_) => false,
+        }
+    }
+
     fn subscribe(
         &mut self,
         kind: TransferKind,
@@ -311,21 +580,31 @@ impl<I: Instant> Receiver<I> {
         // Create new subscription
         let new_subscription = Subscription::new(timeout, payload_size_max, port_id, self.mtu);
 
-        // Add this subscription to the list for this transfer kind
+        // Add this subscription to the list for this transfer kind, keeping the list sorted
+        // by port ID
         let subscriptions = self.subscriptions_for_kind(kind);
         // Reserve memory for the new subscription
         // Logical safety: If a subscription previously existed and was removed, this Vec must have
         // space for it. Therefore, this function cannot remove a subscription and fail to add
         // its replacement.
-        FallibleVec::try_push(subscriptions, new_subscription)?;
+        FallibleVec::try_reserve(subscriptions, 1)?;
+        let index = match find_subscription(subscriptions, port_id) {
+            Ok(index) | Err(index) => index,
+        };
+        subscriptions.insert(index, new_subscription);
         Ok(())
     }
     fn unsubscribe(&mut self, kind: TransferKind, port_id: PortId) {
         let subscriptions = self.subscriptions_for_kind(kind);
-        subscriptions.retain(|sub| sub.port_id() != port_id);
+        if let Ok(index) = find_subscription(subscriptions, port_id) {
+            subscriptions.remove(index);
+        }
     }
 
-    fn subscriptions_for_kind(&mut self, kind: TransferKind) -> &mut Vec<Subscription<I>> {
+    fn subscriptions_for_kind(
+        &mut self,
+        kind: TransferKind,
+    ) -> &mut Vec<Subscription<I, A::Buffer>> {
         match kind {
             TransferKind::Message => &mut self.subscriptions_message,
             TransferKind::Response => &mut self.subscriptions_response,
@@ -333,6 +612,89 @@ impl<I: Instant> Receiver<I> {
         }
     }
 
+    /// Enables transfer ID gap detection for a subject that this receiver is subscribed to
+    ///
+    /// When gap detection is enabled, the receiver checks the transfer ID of each message on the
+    /// subject and counts the messages from each source node that did not arrive. Because
+    /// transfer IDs have only 5 bits, a gap of 32 or more messages is counted as a smaller gap.
+    /// When a source node has not sent a message for longer than the subscription's transfer ID
+    /// timeout, the next message from it starts a new sequence and is not checked for a gap.
+    ///
+    /// If this receiver is not subscribed to the subject, this function does nothing.
+    /// Subscribing to the subject again disables gap detection.
+    pub fn enable_gap_detection(&mut self, subject: SubjectId) -> Result<(), OutOfMemoryError> {
+        let subscriptions = &mut self.subscriptions_message;
+        if let Ok(index) = find_subscription(subscriptions, PortId::from(subject)) {
+            subscriptions[index].enable_gap_detection()?;
+        }
+        Ok(())
+    }
+
+    /// Disables transfer ID gap detection for a subject
+    pub fn disable_gap_detection(&mut self, subject: SubjectId) {
+        let subscriptions = &mut self.subscriptions_message;
+        if let Ok(index) = find_subscription(subscriptions, PortId::from(subject)) {
+            subscriptions[index].disable_gap_detection();
+        }
+    }
+
+    /// Returns the number of messages on a subject from a source node that did not arrive
+    ///
+    /// This returns None if gap detection is not enabled for the subject.
+    pub fn missed_transfers(&self, subject: SubjectId, source: NodeId) -> Option<u64> {
+        self.message_sequence(subject)
+            .map(|sequence| sequence.missed(source))
+    }
+
+    /// Returns the number of messages on a subject from all source nodes that did not arrive
+    ///
+    /// This returns None if gap detection is not enabled for the subject.
+    pub fn total_missed_transfers(&self, subject: SubjectId) -> Option<u64> {
+        self.message_sequence(subject)
+            .map(|sequence| sequence.total_missed())
+    }
+
+    /// Sets the missed message counts for a subject to zero
+    pub fn reset_missed_transfers(&mut self, subject: SubjectId) {
+        let subscriptions = &mut self.subscriptions_message;
+        if let Ok(index) = find_subscription(subscriptions, PortId::from(subject)) {
+            if let Some(sequence) = subscriptions[index].sequence_mut() {
+                sequence.reset_counts();
+            }
+        }
+    }
+
+    /// Sets whether this receiver drops anonymous messages on a subject that it is subscribed to
+    ///
+    /// Any node can send an anonymous message, and the pseudo-ID in the CAN ID of an anonymous
+    /// frame is easy to spoof. Applications that act only on messages from identified
+    /// publishers can use this to drop anonymous messages before they are delivered.
+    ///
+    /// If this receiver is not subscribed to the subject, this function does nothing.
+    /// Subscribing to the subject again resets this setting, so that anonymous messages are
+    /// accepted.
+    pub fn set_ignore_anonymous(&mut self, subject: SubjectId, ignore: bool) {
+        let subscriptions = &mut self.subscriptions_message;
+        if let Ok(index) = find_subscription(subscriptions, PortId::from(subject)) {
+            subscriptions[index].set_ignore_anonymous(ignore);
+        }
+    }
+
+    /// Returns the number of anonymous messages on a subject that this receiver has dropped
+    ///
+    /// This returns None if this receiver is not subscribed to the subject.
+    pub fn ignored_anonymous_messages(&self, subject: SubjectId) -> Option<u64> {
+        let subscriptions = &self.subscriptions_message;
+        let index = find_subscription(subscriptions, PortId::from(subject)).ok()?;
+        Some(subscriptions[index].ignored_anonymous())
+    }
+
+    fn message_sequence(&self, subject: SubjectId) -> Option<&sequence::SequenceTracker<I>> {
+        let subscriptions = &self.subscriptions_message;
+        let index = find_subscription(subscriptions, PortId::from(subject)).ok()?;
+        subscriptions[index].sequence()
+    }
+
     /// Returns the number of transfers successfully received
     pub fn transfer_count(&self) -> u64 {
         self.transfer_count
@@ -352,8 +714,11 @@ impl<I: Instant> Receiver<I> {
         self.error_count = self.error_count.wrapping_add(1)
     }
 
-    /// Deletes all sessions that have expired
-    fn clean_expired_sessions(&mut self, now: I) {
+    /// Deletes all sessions that have expired, freeing the memory used for incomplete transfers
+    ///
+    /// This function is called automatically whenever a frame is accepted. Applications should
+    /// also call it periodically so that memory is freed even when no frames are arriving.
+    pub fn clean_expired(&mut self, now: I) {
         clean_sessions_from_subscriptions(&mut self.subscriptions_message, &now);
         clean_sessions_from_subscriptions(&mut self.subscriptions_request, &now);
         clean_sessions_from_subscriptions(&mut self.subscriptions_response, &now);
@@ -361,29 +726,53 @@ impl<I: Instant> Receiver<I> {
 
     /// Returns a set of frame filters that accept only the transfers this receiver is subscribed
     /// to
+    ///
+    /// In promiscuous mode, this returns one filter that accepts all frames.
     pub fn frame_filters(&self) -> Result<Vec<Filter>, OutOfMemoryError> {
-        let service_subscriptions = if self.id.is_some() {
-            self.subscriptions_request.len() + self.subscriptions_response.len()
-        } else {
-            // Node is anonymous and can't handle services
-            0
-        };
-        let total_subscriptions = self.subscriptions_message.len() + service_subscriptions;
+        if self.promiscuous.is_some() {
+            let mut filters: Vec<Filter> = FallibleVec::try_with_capacity(1)?;
+            filters.push(Filter::new(0, 0));
+            return Ok(filters);
+        }
+        let total_subscriptions = self.subscriptions_message.len()
+            + self.subscriptions_request.len()
+            + self.subscriptions_response.len();
         let mut filters: Vec<Filter> = FallibleVec::try_with_capacity(total_subscriptions)?;
 
         for subscription in &self.subscriptions_message {
+            if subscription.automatic() {
+                // Covered by the wildcard filters
+                continue;
+            }
             let subject_id = SubjectId::try_from(subscription.port_id()).unwrap();
             filters.push(subject_filter(subject_id))
         }
-        if let Some(local_id) = self.id {
-            // Only non-anonymous nodes can handle requests and responses
-            for subscription in &self.subscriptions_request {
-                let service_id = ServiceId::try_from(subscription.port_id()).unwrap();
-                filters.push(request_filter(service_id, local_id));
+        let mut memory_result = Ok(());
+        for wildcard in &self.message_wildcards {
+            wildcard.subjects.for_each_mask(|id, mask| {
+                if memory_result.is_ok() {
+                    memory_result =
+                        FallibleVec::try_push(&mut filters, subject_mask_filter(id, mask));
+                }
+            });
+        }
+        memory_result?;
+        // Only non-anonymous nodes can handle requests and responses addressed to them,
+        // but any node can sniff requests and responses
+        for subscription in &self.subscriptions_request {
+            let service_id = ServiceId::try_from(subscription.port_id()).unwrap();
+            if subscription.sniff() {
+                filters.push(request_filter(service_id, None));
+            } else if let Some(local_id) = self.id {
+                filters.push(request_filter(service_id, Some(local_id)));
             }
-            for subscription in &self.subscriptions_response {
-                let service_id = ServiceId::try_from(subscription.port_id()).unwrap();
-                filters.push(response_filter(service_id, local_id));
+        }
+        for subscription in &self.subscriptions_response {
+            let service_id = ServiceId::try_from(subscription.port_id()).unwrap();
+            if subscription.sniff() {
+                filters.push(response_filter(service_id, None));
+            } else if let Some(local_id) = self.id {
+                filters.push(response_filter(service_id, Some(local_id)));
             }
         }
 
@@ -391,24 +780,55 @@ impl<I: Instant> Receiver<I> {
     }
 }
 
-fn clean_sessions_from_subscriptions<I: Instant>(
-    subscriptions: &mut Vec<Subscription<I>>,
+fn clean_sessions_from_subscriptions<I: Instant, B: TransferBuffer>(
+    subscriptions: &mut Vec<Subscription<I, B>>,
     now: &I,
 ) {
     for subscription in subscriptions {
-        let timeout = subscription.timeout();
-        for slot in subscription.sessions_mut().iter_mut() {
-            if let Some(session) = slot.as_deref_mut() {
-                let time_since_first_frame = now.duration_since(&session.transfer_timestamp());
-                if time_since_first_frame > timeout {
-                    // This session has timed out, delete it.
-                    *slot = None;
-                }
-            }
-        }
+        subscription.clean_expired_sessions(now);
     }
 }
 
+/// Finds the subscription with the provided port ID in a list sorted by port ID
+///
+/// This returns the index of the subscription, or the index where a subscription with that
+/// port ID should be inserted.
+fn find_subscription<I: Instant, B: TransferBuffer>(
+    subscriptions: &[Subscription<I, B>],
+    port_id: PortId,
+) -> Result<usize, usize> {
+    subscriptions.binary_search_by_key(&port_id, Subscription::port_id)
+}
+
+/// What a receiver does with a frame that has more data than its MTU allows
+///
+/// Frames can be longer than the MTU only when the `can-fd` feature is enabled and the receiver
+/// is configured with [`Mtu::Can8`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum OversizeFramePolicy {
+    /// Handle the frame in the same way as other frames
+    ///
+    /// A transfer made of long frames may still be dropped if its payload is longer than the
+    /// subscription allows.
+    #[default]
+    Accept,
+    /// Remove the data that does not fit into the MTU, count an error, and handle the
+    /// shortened frame
+    ///
+    /// The tail byte is kept. A single-frame transfer is received with its payload cut short.
+    /// A multi-frame transfer will fail its CRC check and be dropped.
+    Truncate,
+    /// Ignore the frame and count an error
+    Reject,
+}
+
+/// Subscription settings used for ports that are subscribed automatically in promiscuous mode
+#[derive(Debug, Clone)]
+struct PromiscuousSettings<D> {
+    payload_size_max: usize,
+    timeout: D,
+}
+
 #[derive(Debug)]
 pub enum CanIdParseError {
     /// Reserved bit 23 was set
@@ -418,11 +838,61 @@ pub enum CanIdParseError {
 }
 
 /// Parses a transfer header from a CAN ID, frame timestamp, and frame transfer ID
-fn parse_can_id<I>(
+/// Runs basic sanity checks on the ID and data of an incoming frame. Returns the header (without
+/// a timestamp) and tail byte if the frame is valid.
+///
+/// This function is not generic, so only one copy of it is compiled for all instant types.
+fn frame_sanity_check_inner(id: CanId, data: &[u8]) -> Option<(Header<()>, TailByte)> {
+    // Frame must have a tail byte to be valid
+    let tail_byte = TailByte::parse(*data.last()?);
+
+    let header = parse_can_id(id, tail_byte.transfer_id).ok()?;
+
+    // Additional header checks
+    if let Header::Message(message_header) = &header {
+        if message_header.source.is_none() {
+            // Anonymous message transfers must always fit into one frame
+            if !(tail_byte.toggle && tail_byte.start && tail_byte.end) {
+                log::debug!("Anonymous multi-frame transfer, ignoring");
+                return None;
+            }
+        }
+    }
+
+    // OK
+    Some((header, tail_byte))
+}
+
+/// Converts a header without a timestamp into a header with a timestamp
+fn add_timestamp<I>(header: Header<()>, timestamp: I) -> Header<I> {
+    match header {
+        Header::Message(header) => Header::Message(MessageHeader {
+            timestamp,
+            transfer_id: header.transfer_id,
+            priority: header.priority,
+            subject: header.subject,
+            source: header.source,
+        }),
+        Header::Request(header) => Header::Request(add_service_timestamp(header, timestamp)),
+        Header::Response(header) => Header::Response(add_service_timestamp(header, timestamp)),
+    }
+}
+
+fn add_service_timestamp<I>(header: ServiceHeader<()>, timestamp: I) -> ServiceHeader<I> {
+    ServiceHeader {
+        timestamp,
+        transfer_id: header.transfer_id,
+        priority: header.priority,
+        service: header.service,
+        source: header.source,
+        destination: header.destination,
+    }
+}
+
+fn parse_can_id(
     id: CanId,
-    timestamp: I,
     transfer_id: TransferId,
-) -> core::result::Result<Header<I>, CanIdParseError> {
+) -> core::result::Result<Header<()>, CanIdParseError> {
     let bits = u32::from(id);
 
     if bits.bit_set(23) {
@@ -436,7 +906,7 @@ fn parse_can_id<I>(
     let header = if bits.bit_set(25) {
         // Service
         let service_header = ServiceHeader {
-            timestamp,
+            timestamp: (),
             transfer_id,
             priority,
             service: ServiceId::try_from(bits.get_u16(14) & 0x1ff)
@@ -462,7 +932,7 @@ fn parse_can_id<I>(
         let message_source_id = if anonymous { None } else { Some(source_id) };
         let message_header = MessageHeader {
             // Subject ID is 13 bits, 0..=8191
-            timestamp,
+            timestamp: (),
             transfer_id,
             priority,
             subject: SubjectId::try_from(bits.get_u16(8) & 0x1fff)
@@ -482,8 +952,21 @@ fn parse_can_id<I>(
 /// * Subject ID: matching the provided subject ID
 /// * Source node ID: any
 fn subject_filter(subject: SubjectId) -> Filter {
-    let m_id: u32 = 0b0_0000_0110_0000_0000_0000_0000_0000 | u32::from(subject) << 8;
-    let mask: u32 = 0b0_0010_1001_1111_1111_1111_1000_0000;
+    subject_mask_filter(subject.into(), 0x1fff)
+}
+
+/// Returns a filter that matches message transfers on all subjects that match some bits
+///
+/// Criteria:
+/// * Priority: any
+/// * Anonymous: any
+/// * Subject ID: equal to `subject` in the bits that are set in `subject_mask`
+/// * Source node ID: any
+fn subject_mask_filter(subject: u16, subject_mask: u16) -> Filter {
+    let subject_mask = u32::from(subject_mask & 0x1fff);
+    let m_id: u32 =
+        0b0_0000_0110_0000_0000_0000_0000_0000 | (u32::from(subject) & subject_mask) << 8;
+    let mask: u32 = 0b0_0010_1000_0000_0000_0000_1000_0000 | subject_mask << 8;
     Filter::new(mask, m_id)
 }
 
@@ -493,13 +976,11 @@ fn subject_filter(subject: SubjectId) -> Filter {
 /// * Priority: any
 /// * Request or response: request
 /// * Service ID: matching the provided service ID
-/// * Destination: matching the provided node ID
+/// * Destination: matching the provided node ID, or any if destination is None
 /// * Source: any
-fn request_filter(service: ServiceId, destination: NodeId) -> Filter {
-    let dynamic_id_bits = u32::from(service) << 14 | u32::from(destination) << 7;
-    let m_id: u32 = 0b0_0011_0000_0000_0000_0000_0000_0000 | dynamic_id_bits;
-    let mask: u32 = 0b0_0011_1111_1111_1111_1111_1000_0000;
-    Filter::new(mask, m_id)
+fn request_filter(service: ServiceId, destination: Option<NodeId>) -> Filter {
+    let m_id: u32 = 0b0_0011_0000_0000_0000_0000_0000_0000 | u32::from(service) << 14;
+    service_filter(m_id, destination)
 }
 
 /// Returns a filter that matches service response transfers for one service to one node ID
@@ -508,14 +989,25 @@ fn request_filter(service: ServiceId, destination: NodeId) -> Filter {
 /// * Priority: any
 /// * Request or response: response
 /// * Service ID: matching the provided service ID
-/// * Destination: matching the provided node ID
+/// * Destination: matching the provided node ID, or any if destination is None
 /// * Source: any
-fn response_filter(service: ServiceId, destination: NodeId) -> Filter {
-    let dynamic_id_bits =
-        u32::from(u16::from(service)) << 14 | u32::from(u8::from(destination)) << 7;
-    let m_id: u32 = 0b0_0010_0000_0000_0000_0000_0000_0000 | dynamic_id_bits;
-    let mask: u32 = 0b0_0011_1111_1111_1111_1111_1000_0000;
-    Filter::new(mask, m_id)
+fn response_filter(service: ServiceId, destination: Option<NodeId>) -> Filter {
+    let m_id: u32 = 0b0_0010_0000_0000_0000_0000_0000_0000 | u32::from(u16::from(service)) << 14;
+    service_filter(m_id, destination)
+}
+
+/// Adds the destination node ID, if any, to a service filter
+fn service_filter(m_id: u32, destination: Option<NodeId>) -> Filter {
+    match destination {
+        Some(destination) => {
+            let mask: u32 = 0b0_0011_1111_1111_1111_1111_1000_0000;
+            Filter::new(mask, m_id | u32::from(u8::from(destination)) << 7)
+        }
+        None => {
+            let mask: u32 = 0b0_0011_1111_1111_1100_0000_0000_0000;
+            Filter::new(mask, m_id)
+        }
+    }
 }
 
 /// Basic extension trait for extracting bits from a CAN ID
@@ -570,7 +1062,6 @@ mod test {
     use super::*;
     use canadensis_core::transfer::Header;
     use canadensis_core::{ServiceId, SubjectId};
-    use core::fmt::Debug;
 
     #[test]
     fn test_parse_can_id() {
@@ -634,14 +1125,9 @@ mod test {
         );
     }
 
-    fn check_can_id<I: Clone + PartialEq + Debug>(expected_header: Header<I>, bits: u32) {
+    fn check_can_id(expected_header: Header<()>, bits: u32) {
         let id = CanId::try_from(bits).unwrap();
-        let actual_header = parse_can_id(
-            id,
-            expected_header.timestamp(),
-            expected_header.transfer_id(),
-        )
-        .unwrap();
+        let actual_header = parse_can_id(id, expected_header.transfer_id()).unwrap();
         assert_eq!(actual_header, expected_header);
     }
 }
@@ -1,15 +1,12 @@
-use alloc::vec::Vec;
-use core::mem;
-
 use canadensis_core::TransferId;
-use fallible_collections::{FallibleVec, TryReserveError};
 
 use super::TailByte;
+use crate::buffer::TransferBuffer;
 use crate::OutOfMemoryError;
 
 /// Reassembles frames into a transfer
 #[derive(Debug)]
-pub struct Buildup {
+pub struct Buildup<B> {
     /// Transfer ID of expected frames
     transfer_id: TransferId,
     /// The number of frames processed
@@ -19,25 +16,23 @@ pub struct Buildup {
     /// If the next frame should have the toggle bit set
     expect_toggle: bool,
     /// The bytes collected so far, not including tail bytes
-    transfer: Vec<u8>,
+    ///
+    /// This is None after the transfer has been returned.
+    transfer: Option<B>,
 }
 
-impl Buildup {
+impl<B: TransferBuffer> Buildup<B> {
     /// Creates a transfer reassembly object
     ///
-    /// This function attempts to allocate enough memory to hold the largest possible payload.
-    /// It returns an error if memory allocation fails.
-    pub fn new(
-        transfer_id: TransferId,
-        max_payload_length: usize,
-    ) -> Result<Self, OutOfMemoryError> {
-        Ok(Buildup {
+    /// The buffer should be empty and able to hold the largest possible payload.
+    pub fn new(transfer_id: TransferId, buffer: B) -> Self {
+        Buildup {
             transfer_id,
             frames: 0,
             expect_start: true,
             expect_toggle: true,
-            transfer: FallibleVec::try_with_capacity(max_payload_length)?,
-        })
+            transfer: Some(buffer),
+        }
     }
 
     /// Handles an incoming frame for this transfer
@@ -48,7 +43,7 @@ impl Buildup {
     /// If this frame is the last frame in the transfer, this function returns the reassembled
     /// payload, including the padding and transfer CRC (if applicable) but excluding any
     /// tail bytes. After the payload is returned, this Buildup must not be used again.
-    pub fn add(&mut self, frame_data: &[u8]) -> Result<Option<Vec<u8>>, BuildupError> {
+    pub fn add(&mut self, frame_data: &[u8]) -> Result<Option<B>, BuildupError> {
         self.frames += 1;
         assert!(
             !frame_data.is_empty(),
@@ -72,12 +67,14 @@ impl Buildup {
 
         // Copy data
         let frame_without_tail = &frame_data[..frame_data.len() - 1];
-        FallibleVec::try_extend_from_slice(&mut self.transfer, frame_without_tail)?;
+        self.transfer
+            .as_mut()
+            .expect("Buildup used after end of transfer")
+            .try_extend_from_slice(frame_without_tail)?;
 
         if tail.end {
             // End of transfer, return the transfer data
-            let data = mem::take(&mut self.transfer);
-            Ok(Some(data))
+            Ok(self.transfer.take())
         } else {
             // Expect more frames
             Ok(None)
@@ -86,7 +83,10 @@ impl Buildup {
 
     /// Returns the number of payload bytes collected
     pub fn payload_length(&self) -> usize {
-        self.transfer.len()
+        self.transfer
+            .as_ref()
+            .map(|transfer| transfer.len())
+            .unwrap_or(0)
     }
 
     /// Returns the ID of the transfer that is being reassembled
@@ -100,14 +100,15 @@ impl Buildup {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum BuildupError {
-    OutOfMemory(TryReserveError),
+    OutOfMemory(OutOfMemoryError),
     InvalidStart,
     InvalidToggle,
 }
 
-impl From<TryReserveError> for BuildupError {
-    fn from(inner: TryReserveError) -> Self {
+impl From<OutOfMemoryError> for BuildupError {
+    fn from(inner: OutOfMemoryError) -> Self {
         BuildupError::OutOfMemory(inner)
     }
 }
@@ -115,13 +116,14 @@ impl From<TryReserveError> for BuildupError {
 #[cfg(test)]
 mod test {
     use super::*;
+    use alloc::vec::Vec;
     use core::convert::TryFrom;
 
     #[test]
     fn test_buildup_heartbeat() {
         // Heartbeat example from specification section 4.2.3
         for transfer_id in 0u8..=31 {
-            let mut buildup = Buildup::new(TransferId::try_from(transfer_id).unwrap(), 7).unwrap();
+            let mut buildup = Buildup::new(TransferId::try_from(transfer_id).unwrap(), Vec::new());
             let payload = make_heartbeat_payload(u32::from(transfer_id));
 
             // A frame with 7 bytes of payload and a tail byte with first 1, last 1,
@@ -165,7 +167,7 @@ mod test {
             ];
             let frame = make_frame(&payload, transfer_id);
 
-            let mut buildup = Buildup::new(TransferId::try_from(transfer_id).unwrap(), 16).unwrap();
+            let mut buildup = Buildup::new(TransferId::try_from(transfer_id).unwrap(), Vec::new());
 
             // Put in the payload bytes
             assert_eq!(Some(payload.to_vec()), buildup.add(&frame).unwrap());
@@ -185,7 +187,7 @@ mod test {
 
     #[test]
     fn test_node_info_request() {
-        let mut buildup = Buildup::new(TransferId::try_from(1).unwrap(), 0).unwrap();
+        let mut buildup = Buildup::new(TransferId::try_from(1).unwrap(), Vec::new());
         assert_eq!(Some(Vec::new()), buildup.add(&[0xe1]).unwrap());
     }
 
@@ -222,13 +224,13 @@ mod test {
             &[0xe7, 0x61],
         ];
 
-        let mut buildup = Buildup::new(TransferId::try_from(1).unwrap(), 71).unwrap();
+        let mut buildup = Buildup::new(TransferId::try_from(1).unwrap(), Vec::new());
 
         for (i, frame) in frames.iter().enumerate() {
             if i != frames.len() - 1 {
-                assert_eq!(None, buildup.add(*frame).unwrap());
+                assert_eq!(None, buildup.add(frame).unwrap());
             } else {
-                assert_eq!(Some(payload.to_vec()), buildup.add(*frame).unwrap());
+                assert_eq!(Some(payload.to_vec()), buildup.add(frame).unwrap());
             }
         }
     }
@@ -265,13 +267,13 @@ mod test {
                 0x00, 0x00, 0x00, 0xc0, 0x48, 0x40,
             ],
         ];
-        let mut buildup = Buildup::new(TransferId::try_from(0).unwrap(), 63 + 47).unwrap();
+        let mut buildup = Buildup::new(TransferId::try_from(0).unwrap(), Vec::new());
 
         for (i, frame) in frames.iter().enumerate() {
             if i != frames.len() - 1 {
-                assert_eq!(None, buildup.add(*frame).unwrap());
+                assert_eq!(None, buildup.add(frame).unwrap());
             } else {
-                assert_eq!(Some(payload.to_vec()), buildup.add(*frame).unwrap());
+                assert_eq!(Some(payload.to_vec()), buildup.add(frame).unwrap());
             }
         }
     }
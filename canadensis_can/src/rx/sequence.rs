@@ -0,0 +1,147 @@
+use canadensis_core::time::Instant;
+use canadensis_core::{NodeId, TransferId};
+
+/// The number of possible source node IDs
+const SOURCES: usize = NodeId::MAX.to_u8() as usize + 1;
+/// The number of transfer ID values
+const TRANSFER_ID_MODULO: u8 = 32;
+
+/// Tracks the transfer IDs of transfers from each source node and counts the transfers that
+/// were skipped
+///
+/// Because CAN transfer IDs have only 5 bits, a gap of 32 or more transfers looks like a
+/// smaller gap (or no gap).
+///
+/// The state for a source node expires when it has not sent a transfer for longer than the
+/// timeout. The next transfer from that node is not checked for a gap, so a node that restarts
+/// or stops publishing for a while is not counted as missing transfers.
+#[derive(Debug, Clone)]
+pub struct SequenceTracker<I> {
+    /// For each source node, the transfer ID and timestamp of the most recent transfer
+    last: [Option<(TransferId, I)>; SOURCES],
+    /// For each source node, the number of transfers missed
+    missed: [u64; SOURCES],
+}
+
+impl<I: Instant> SequenceTracker<I> {
+    pub fn new() -> Self {
+        SequenceTracker {
+            last: [None; SOURCES],
+            missed: [0; SOURCES],
+        }
+    }
+
+    /// Records a transfer from a source node and returns the number of transfers that were
+    /// missed immediately before it
+    ///
+    /// A repeated transfer ID (for example, from a redundant transport) is not counted as
+    /// a gap. If the previous transfer from the source node was more than `timeout` before
+    /// `timestamp`, the state for the node has expired and no gap is counted.
+    pub fn record(
+        &mut self,
+        source: NodeId,
+        transfer_id: TransferId,
+        timestamp: I,
+        timeout: I::Duration,
+    ) -> u8 {
+        let index = usize::from(source);
+        let gap = match self.last[index] {
+            Some((last, last_time))
+                if last != transfer_id && timestamp.duration_since(&last_time) <= timeout =>
+            {
+                (u8::from(transfer_id) + TRANSFER_ID_MODULO - u8::from(last) - 1)
+                    % TRANSFER_ID_MODULO
+            }
+            _ => 0,
+        };
+        self.last[index] = Some((transfer_id, timestamp));
+        self.missed[index] = self.missed[index].wrapping_add(u64::from(gap));
+        gap
+    }
+
+    /// Returns the number of transfers from a source node that were missed
+    pub fn missed(&self, source: NodeId) -> u64 {
+        self.missed[usize::from(source)]
+    }
+
+    /// Returns the number of transfers from all source nodes that were missed
+    pub fn total_missed(&self) -> u64 {
+        self.missed
+            .iter()
+            .fold(0u64, |total, missed| total.wrapping_add(*missed))
+    }
+
+    /// Sets all missed transfer counts to zero
+    ///
+    /// The most recent transfer IDs are kept, so the next transfer from each node is still
+    /// checked for a gap.
+    pub fn reset_counts(&mut self) {
+        self.missed = [0; SOURCES];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SequenceTracker;
+    use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+    use canadensis_core::{NodeId, TransferId};
+    use core::convert::TryFrom;
+
+    fn id(value: u8) -> TransferId {
+        TransferId::try_from(value).unwrap()
+    }
+
+    fn time(value: u32) -> Microseconds32 {
+        Microseconds32::new(value)
+    }
+
+    fn timeout() -> MicrosecondDuration32 {
+        MicrosecondDuration32::new(1000)
+    }
+
+    #[test]
+    fn gaps() {
+        let node = NodeId::try_from(7).unwrap();
+        let other = NodeId::try_from(8).unwrap();
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(0, tracker.record(node, id(30), time(0), timeout()));
+        assert_eq!(0, tracker.record(node, id(31), time(1), timeout()));
+        // Wraps around, missing 0 and 1
+        assert_eq!(2, tracker.record(node, id(2), time(2), timeout()));
+        // Duplicate
+        assert_eq!(0, tracker.record(node, id(2), time(3), timeout()));
+        assert_eq!(0, tracker.record(other, id(9), time(4), timeout()));
+        assert_eq!(4, tracker.record(other, id(14), time(5), timeout()));
+        assert_eq!(2, tracker.missed(node));
+        assert_eq!(6, tracker.total_missed());
+        tracker.reset_counts();
+        assert_eq!(0, tracker.total_missed());
+        assert_eq!(1, tracker.record(node, id(4), time(6), timeout()));
+    }
+
+    #[test]
+    fn expiry() {
+        let node = NodeId::try_from(7).unwrap();
+        let other = NodeId::try_from(8).unwrap();
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(0, tracker.record(node, id(0), time(0), timeout()));
+        assert_eq!(0, tracker.record(other, id(0), time(0), timeout()));
+        // Exactly the timeout later, the state has not expired
+        assert_eq!(1, tracker.record(node, id(2), time(1000), timeout()));
+        // More than the timeout later, the state has expired
+        assert_eq!(0, tracker.record(other, id(20), time(1001), timeout()));
+        assert_eq!(0, tracker.record(node, id(10), time(2001), timeout()));
+        // A new sequence starts after expiry
+        assert_eq!(1, tracker.record(node, id(12), time(2002), timeout()));
+        assert_eq!(2, tracker.total_missed());
+
+        // Expiry works when the clock wraps around
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(
+            0,
+            tracker.record(node, id(0), time(u32::MAX - 10), timeout())
+        );
+        assert_eq!(1, tracker.record(node, id(2), time(100), timeout()));
+        assert_eq!(0, tracker.record(node, id(9), time(1200), timeout()));
+    }
+}
@@ -1,37 +1,34 @@
+use crate::buffer::TransferBuffer;
 use crate::rx::buildup::{Buildup, BuildupError};
 use crate::rx::TailByte;
 use crate::{Frame, OutOfMemoryError, TransferCrc};
-use alloc::vec::Vec;
 use canadensis_core::time::Instant;
 use canadensis_core::transfer::{Header, Transfer};
 use canadensis_core::TransferId;
 
 /// A receive session, associated with a particular port ID and source node
 #[derive(Debug)]
-pub struct Session<I> {
+pub struct Session<I, B> {
     /// Timestamp of the first frame received in this transfer
     transfer_timestamp: I,
     /// Transfer reassembly
-    buildup: Buildup,
+    buildup: Buildup<B>,
 }
 
-impl<I> Session<I>
+impl<I, B> Session<I, B>
 where
     I: Instant,
+    B: TransferBuffer,
 {
     /// Creates a new session
     ///
-    /// This function attempts to allocate `max_payload_length` bytes of memory, which will be
-    /// used to assemble the received frames.
-    pub fn new(
-        transfer_timestamp: I,
-        transfer_id: TransferId,
-        max_payload_length: usize,
-    ) -> Result<Self, OutOfMemoryError> {
-        Ok(Session {
+    /// The buffer will be used to assemble the received frames. It should be empty and have
+    /// space for the largest possible payload.
+    pub fn new(transfer_timestamp: I, transfer_id: TransferId, buffer: B) -> Self {
+        Session {
             transfer_timestamp,
-            buildup: Buildup::new(transfer_id, max_payload_length)?,
-        })
+            buildup: Buildup::new(transfer_id, buffer),
+        }
     }
 
     /// Accepts a frame associated with this session
@@ -47,7 +44,7 @@ where
         tail: TailByte,
         max_payload_length: usize,
         transfer_timeout: I::Duration,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SessionError> {
+    ) -> Result<Option<Transfer<B, I>>, SessionError> {
         if tail.transfer_id != self.buildup.transfer_id() {
             // This is a frame from some other transfer. Ignore it, but keep this session to receive
             // possible later frames.
@@ -85,9 +82,9 @@ where
 
     fn handle_transfer_data(
         &mut self,
-        mut transfer_data: Vec<u8>,
+        mut transfer_data: B,
         frame_header: Header<I>,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SessionError> {
+    ) -> Result<Option<Transfer<B, I>>, SessionError> {
         // Check CRC, if this transfer used more than one frame
         if self.buildup.frames() > 1 {
             let mut crc = TransferCrc::new();
@@ -124,6 +121,7 @@ where
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum SessionError {
     /// A transfer CRC was invalid
     Crc,
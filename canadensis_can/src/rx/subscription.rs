@@ -1,3 +1,5 @@
+use crate::buffer::{BufferAllocator, TransferBuffer};
+use crate::rx::sequence::SequenceTracker;
 use crate::rx::session::{Session, SessionError};
 use crate::rx::TailByte;
 use crate::{Frame, Mtu, OutOfMemoryError};
@@ -15,32 +17,58 @@ const RX_SESSIONS_PER_SUBSCRIPTION: usize = NodeId::MAX.to_u8() as usize + 1;
 /// Transfer subscription state. The application can register its interest in a particular kind of data exchanged
 /// over the bus by creating such subscription objects. Frames that carry data for which there is no active
 /// subscription will be silently dropped by the library.
-pub struct Subscription<I: Instant> {
+pub struct Subscription<I: Instant, B> {
     /// A session for each node ID
-    sessions: [Option<Box<Session<I>>>; RX_SESSIONS_PER_SUBSCRIPTION],
+    sessions: [Option<Box<Session<I, B>>>; RX_SESSIONS_PER_SUBSCRIPTION],
+    /// A bit for each node ID, set if the corresponding session exists
+    ///
+    /// This allows expired sessions to be found without checking all 128 slots.
+    active_sessions: u128,
+    /// Sessions for sniffed service transfers, keyed by source and destination node
+    ///
+    /// A sniffed transfer may be addressed to any node, and one node can send transfers with the
+    /// same transfer ID to different destinations at the same time. These sessions must be
+    /// separate from the sessions indexed by source node only.
+    sniff_sessions: Vec<SniffSession<I, B>>,
     /// Maximum time difference between the first and last frames in a transfer
     timeout: I::Duration,
     /// Maximum number of payload bytes, space for the padding and CRC if necessary
     payload_size_max: usize,
     /// Subject or service ID that this subscription is about
     port_id: PortId,
+    /// True if this subscription accepts service transfers addressed to other nodes
+    sniff: bool,
+    /// True if this subscription was created by a wildcard subscription
+    automatic: bool,
+    /// Transfer ID tracking for gap detection, if enabled
+    sequence: Option<Box<SequenceTracker<I>>>,
+    /// True if this subscription drops anonymous transfers
+    ignore_anonymous: bool,
+    /// Number of anonymous transfers dropped because ignore_anonymous was set
+    ignored_anonymous: u64,
 }
 
-impl<I: Instant> fmt::Debug for Subscription<I> {
+impl<I: Instant, B: TransferBuffer> fmt::Debug for Subscription<I, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Subscription")
             .field("sessions", &DebugSessions(&self.sessions))
+            .field("sniff_sessions", &self.sniff_sessions.len())
             .field("transfer_id_timeout", &self.timeout)
             .field("payload_size_max", &self.payload_size_max)
             .field("port_id", &self.port_id)
+            .field("sniff", &self.sniff)
+            .field("automatic", &self.automatic)
+            .field("sequence", &self.sequence.is_some())
+            .field("ignore_anonymous", &self.ignore_anonymous)
+            .field("ignored_anonymous", &self.ignored_anonymous)
             .finish()
     }
 }
 
 /// A debug adapter for the session list
-struct DebugSessions<'s, I>(&'s [Option<Box<Session<I>>>; RX_SESSIONS_PER_SUBSCRIPTION]);
+struct DebugSessions<'s, I, B>(&'s [Option<Box<Session<I, B>>>; RX_SESSIONS_PER_SUBSCRIPTION]);
 
-impl<I: Instant> fmt::Debug for DebugSessions<'_, I> {
+impl<I: Instant, B: TransferBuffer> fmt::Debug for DebugSessions<'_, I, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Display as a set, showing only the non-empty entries
         f.debug_set()
@@ -49,7 +77,7 @@ impl<I: Instant> fmt::Debug for DebugSessions<'_, I> {
     }
 }
 
-impl<I: Instant> Subscription<I> {
+impl<I: Instant, B: TransferBuffer> Subscription<I, B> {
     /// Creates a subscription
     ///
     /// The `payload_size_max` value is the maximum number of payload bytes that can be received,
@@ -57,33 +85,125 @@ impl<I: Instant> Subscription<I> {
     pub fn new(timeout: I::Duration, payload_size_max: usize, port_id: PortId, mtu: Mtu) -> Self {
         Subscription {
             sessions: init_rx_sessions(),
+            active_sessions: 0,
+            sniff_sessions: Vec::new(),
             timeout,
             payload_size_max: add_padding_and_crc_space(payload_size_max, mtu),
             port_id,
+            sniff: false,
+            automatic: false,
+            sequence: None,
+            ignore_anonymous: false,
+            ignored_anonymous: 0,
+        }
+    }
+
+    /// Sets whether this subscription was created by a wildcard subscription
+    pub fn set_automatic(&mut self, automatic: bool) {
+        self.automatic = automatic;
+    }
+
+    /// Returns true if this subscription was created by a wildcard subscription
+    pub fn automatic(&self) -> bool {
+        self.automatic
+    }
+
+    /// Sets whether this subscription accepts service transfers addressed to other nodes
+    pub fn set_sniff(&mut self, sniff: bool) {
+        self.sniff = sniff;
+    }
+
+    /// Returns true if this subscription accepts service transfers addressed to other nodes
+    pub fn sniff(&self) -> bool {
+        self.sniff
+    }
+
+    /// Enables transfer ID gap detection, if it is not already enabled
+    pub fn enable_gap_detection(&mut self) -> Result<(), OutOfMemoryError> {
+        if self.sequence.is_none() {
+            self.sequence = Some(FallibleBox::try_new(SequenceTracker::new())?);
         }
+        Ok(())
+    }
+
+    /// Disables transfer ID gap detection and discards the missed transfer counts
+    pub fn disable_gap_detection(&mut self) {
+        self.sequence = None;
+    }
+
+    /// Returns the gap detection state, or None if gap detection is not enabled
+    pub fn sequence(&self) -> Option<&SequenceTracker<I>> {
+        self.sequence.as_deref()
+    }
+
+    /// Returns the gap detection state, or None if gap detection is not enabled
+    pub fn sequence_mut(&mut self) -> Option<&mut SequenceTracker<I>> {
+        self.sequence.as_deref_mut()
+    }
+
+    /// Sets whether this subscription drops anonymous transfers
+    pub fn set_ignore_anonymous(&mut self, ignore: bool) {
+        self.ignore_anonymous = ignore;
+    }
+
+    /// Returns the number of anonymous transfers that this subscription has dropped
+    pub fn ignored_anonymous(&self) -> u64 {
+        self.ignored_anonymous
     }
 
     /// Handles an incoming frame on this subscription's topic
-    pub(crate) fn accept(
+    ///
+    /// The allocator provides memory for the transfer payload.
+    pub(crate) fn accept<A>(
         &mut self,
         frame: Frame<I>,
         frame_header: Header<I>,
         tail: TailByte,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SubscriptionError> {
+        allocator: &mut A,
+    ) -> Result<Option<Transfer<B, I>>, SubscriptionError>
+    where
+        A: BufferAllocator<Buffer = B>,
+    {
         if let Some(source_node) = frame_header.source() {
-            self.accept_non_anonymous(frame, frame_header, source_node, tail)
+            let status =
+                self.accept_non_anonymous(frame, frame_header, source_node, tail, allocator);
+            if let (Ok(Some(transfer)), Some(sequence)) = (&status, self.sequence.as_deref_mut()) {
+                let missed = sequence.record(
+                    source_node,
+                    transfer.header.transfer_id(),
+                    transfer.header.timestamp(),
+                    self.timeout,
+                );
+                if missed != 0 {
+                    log::debug!(
+                        "Missed {} transfers from node {:?} on port {:?}",
+                        missed,
+                        source_node,
+                        self.port_id
+                    );
+                }
+            }
+            status
+        } else if self.ignore_anonymous {
+            log::debug!("Ignoring anonymous transfer on port {:?}", self.port_id);
+            self.ignored_anonymous = self.ignored_anonymous.wrapping_add(1);
+            Ok(None)
         } else {
-            self.accept_anonymous(frame, frame_header)
+            self.accept_anonymous(frame, frame_header, allocator)
         }
     }
 
-    fn accept_non_anonymous(
+    fn accept_non_anonymous<A>(
         &mut self,
         frame: Frame<I>,
         frame_header: Header<I>,
         source_node: NodeId,
         tail: TailByte,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SubscriptionError> {
+        allocator: &mut A,
+    ) -> Result<Option<Transfer<B, I>>, SubscriptionError>
+    where
+        A: BufferAllocator<Buffer = B>,
+    {
         let max_payload_length = self.payload_size_max;
 
         if tail.start && tail.end {
@@ -93,7 +213,7 @@ impl<I: Instant> Subscription<I> {
             }
             // Make a transfer from this frame (remove the tail byte)
             let data_without_tail = &frame.data()[..frame.data().len() - 1];
-            let mut payload = Vec::new();
+            let mut payload = allocator.allocate(data_without_tail.len())?;
             payload.try_extend_from_slice(data_without_tail)?;
             let transfer = Transfer {
                 header: frame_header,
@@ -101,86 +221,88 @@ impl<I: Instant> Subscription<I> {
             };
             Ok(Some(transfer))
         } else {
-            self.accept_with_session(frame, frame_header, source_node, tail)
+            self.accept_with_session(frame, frame_header, source_node, tail, allocator)
         }
     }
 
-    fn accept_with_session(
+    fn accept_with_session<A>(
         &mut self,
         frame: Frame<I>,
         frame_header: Header<I>,
         source_node: NodeId,
         tail: TailByte,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SubscriptionError> {
-        let max_payload_length = self.payload_size_max;
-        let transfer_timeout = self.timeout;
-
-        let slot = &mut self.sessions[usize::from(source_node)];
-        let session = match slot {
-            Some(session) => {
-                log::debug!(
-                    "Using existing session with transfer ID {:?} for port {:?} (frame transfer ID {:?})",
-                    session.transfer_id(),
-                    self.port_id,
-                    tail.transfer_id,
-                );
-                session
-            }
-            None => {
-                // Check if this frame is appropriate for creating a new session
-                if !tail.start {
-                    // Not the start of a transfer, so it must be a fragment of some other transfer.
-                    return Err(SubscriptionError::NotStart);
-                }
-                // Create a new session
-                *slot = Some(FallibleBox::try_new(Session::new(
-                    frame_header.timestamp(),
-                    tail.transfer_id,
-                    self.payload_size_max,
-                )?)?);
-                log::debug!(
-                    "Created new session for transfer ID {:?} on port {:?}",
-                    tail.transfer_id,
-                    self.port_id
-                );
-                slot.as_deref_mut().unwrap()
-            }
+        allocator: &mut A,
+    ) -> Result<Option<Transfer<B, I>>, SubscriptionError>
+    where
+        A: BufferAllocator<Buffer = B>,
+    {
+        let limits = SessionLimits {
+            payload_size_max: self.payload_size_max,
+            timeout: self.timeout,
+            port_id: self.port_id,
         };
-
-        let accept_status = session.accept(
-            frame,
-            frame_header,
-            tail,
-            max_payload_length,
-            transfer_timeout,
-        );
-        match accept_status {
-            Ok(Some(transfer)) => {
-                // Transfer received, this session has served its purpose and can be deleted.
-                *slot = None;
-                Ok(Some(transfer))
+        if let (true, Some(destination)) = (self.sniff, service_destination(&frame_header)) {
+            let key = (source_node, destination);
+            let index = match self
+                .sniff_sessions
+                .iter()
+                .position(|session| session.key == key)
+            {
+                Some(index) => index,
+                None => {
+                    FallibleVec::try_reserve(&mut self.sniff_sessions, 1)?;
+                    self.sniff_sessions
+                        .push(SniffSession { key, session: None });
+                    self.sniff_sessions.len() - 1
+                }
+            };
+            let status = accept_in_slot(
+                &mut self.sniff_sessions[index].session,
+                frame,
+                frame_header,
+                tail,
+                allocator,
+                &limits,
+            );
+            if self.sniff_sessions[index].session.is_none() {
+                self.sniff_sessions.swap_remove(index);
             }
-            Ok(None) => Ok(None),
-            Err(e) => {
-                // This is either out-of-memory or an unexpected frame that invalidates
-                // the session. Delete the session to free memory.
-                *slot = None;
-                Err(e.into())
+            status
+        } else {
+            let index = usize::from(source_node);
+            let status = accept_in_slot(
+                &mut self.sessions[index],
+                frame,
+                frame_header,
+                tail,
+                allocator,
+                &limits,
+            );
+            let session_bit = 1u128 << index;
+            if self.sessions[index].is_some() {
+                self.active_sessions |= session_bit;
+            } else {
+                self.active_sessions &= !session_bit;
             }
+            status
         }
     }
 
-    fn accept_anonymous(
+    fn accept_anonymous<A>(
         &mut self,
         frame: Frame<I>,
         frame_header: Header<I>,
-    ) -> Result<Option<Transfer<Vec<u8>, I>>, SubscriptionError> {
+        allocator: &mut A,
+    ) -> Result<Option<Transfer<B, I>>, SubscriptionError>
+    where
+        A: BufferAllocator<Buffer = B>,
+    {
         // An anonymous transfer is always a single frame and does not have a corresponding session.
         // Just convert it into a transfer.
         // Remove the tail byte
         let data_without_tail = &frame.data()[..frame.data().len() - 1];
 
-        let mut transfer_data = Vec::new();
+        let mut transfer_data = allocator.allocate(data_without_tail.len())?;
         transfer_data.try_extend_from_slice(data_without_tail)?;
 
         Ok(Some(Transfer {
@@ -194,13 +316,128 @@ impl<I: Instant> Subscription<I> {
         self.port_id
     }
 
-    /// Returns a mutable reference to the array of sessions
-    pub fn sessions_mut(&mut self) -> &mut [Option<Box<Session<I>>>; RX_SESSIONS_PER_SUBSCRIPTION] {
-        &mut self.sessions
+    /// Deletes all sessions that started more than the timeout before now
+    pub fn clean_expired_sessions(&mut self, now: &I) {
+        let mut remaining = self.active_sessions;
+        while remaining != 0 {
+            let index = remaining.trailing_zeros() as usize;
+            let session_bit = 1u128 << index;
+            remaining &= !session_bit;
+
+            let slot = &mut self.sessions[index];
+            let expired = match slot.as_deref() {
+                Some(session) => now.duration_since(&session.transfer_timestamp()) > self.timeout,
+                None => true,
+            };
+            if expired {
+                // This session has timed out, delete it.
+                *slot = None;
+                self.active_sessions &= !session_bit;
+            }
+        }
+        let timeout = self.timeout;
+        self.sniff_sessions
+            .retain(|sniff_session| match &sniff_session.session {
+                Some(session) => now.duration_since(&session.transfer_timestamp()) <= timeout,
+                None => false,
+            });
+    }
+}
+
+/// A session for sniffed service transfers from one node to another
+struct SniffSession<I, B> {
+    /// The source and destination nodes
+    key: (NodeId, NodeId),
+    session: Option<Box<Session<I, B>>>,
+}
+
+/// The subscription settings that apply to each session
+struct SessionLimits<I: Instant> {
+    /// Maximum number of payload bytes, including space for the padding and CRC if necessary
+    payload_size_max: usize,
+    /// Maximum time difference between the first and last frames in a transfer
+    timeout: I::Duration,
+    /// The port ID of the subscription, for logging
+    port_id: PortId,
+}
+
+/// Returns the destination node of a service transfer, or None if the header is for a message
+fn service_destination<I>(header: &Header<I>) -> Option<NodeId> {
+    match header {
+        Header::Message(_) => None,
+        Header::Request(header) | Header::Response(header) => Some(header.destination),
     }
-    /// Returns the transfer ID timeout for this subscription
-    pub fn timeout(&self) -> I::Duration {
-        self.timeout
+}
+
+/// Passes a frame to the session in a slot, creating a session if the slot is empty
+///
+/// When the session finishes a transfer or encounters an error, it is deleted and the slot
+/// becomes empty.
+fn accept_in_slot<I, B, A>(
+    slot: &mut Option<Box<Session<I, B>>>,
+    frame: Frame<I>,
+    frame_header: Header<I>,
+    tail: TailByte,
+    allocator: &mut A,
+    limits: &SessionLimits<I>,
+) -> Result<Option<Transfer<B, I>>, SubscriptionError>
+where
+    I: Instant,
+    B: TransferBuffer,
+    A: BufferAllocator<Buffer = B>,
+{
+    let session = match slot {
+        Some(session) => {
+            log::debug!(
+                "Using existing session with transfer ID {:?} for port {:?} (frame transfer ID {:?})",
+                session.transfer_id(),
+                limits.port_id,
+                tail.transfer_id,
+            );
+            session
+        }
+        None => {
+            // Check if this frame is appropriate for creating a new session
+            if !tail.start {
+                // Not the start of a transfer, so it must be a fragment of some other transfer.
+                return Err(SubscriptionError::NotStart);
+            }
+            // Create a new session
+            let buffer = allocator.allocate(limits.payload_size_max)?;
+            *slot = Some(FallibleBox::try_new(Session::new(
+                frame_header.timestamp(),
+                tail.transfer_id,
+                buffer,
+            ))?);
+            log::debug!(
+                "Created new session for transfer ID {:?} on port {:?}",
+                tail.transfer_id,
+                limits.port_id
+            );
+            slot.as_deref_mut().unwrap()
+        }
+    };
+
+    let accept_status = session.accept(
+        frame,
+        frame_header,
+        tail,
+        limits.payload_size_max,
+        limits.timeout,
+    );
+    match accept_status {
+        Ok(Some(transfer)) => {
+            // Transfer received, this session has served its purpose and can be deleted.
+            *slot = None;
+            Ok(Some(transfer))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => {
+            // This is either out-of-memory or an unexpected frame that invalidates
+            // the session. Delete the session to free memory.
+            *slot = None;
+            Err(e.into())
+        }
     }
 }
 
@@ -232,7 +469,7 @@ impl From<TryReserveError> for SubscriptionError {
 }
 
 /// Returns 128 Nones
-fn init_rx_sessions<I>() -> [Option<Box<Session<I>>>; RX_SESSIONS_PER_SUBSCRIPTION] {
+fn init_rx_sessions<I, B>() -> [Option<Box<Session<I, B>>>; RX_SESSIONS_PER_SUBSCRIPTION] {
     [
         None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
         None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
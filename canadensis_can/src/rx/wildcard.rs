@@ -0,0 +1,111 @@
+use core::ops::RangeInclusive;
+
+use canadensis_core::SubjectId;
+
+/// All bits of a subject ID
+const SUBJECT_ID_BITS: u16 = 0x1fff;
+
+/// A set of subject IDs that one wildcard subscription covers
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubjectSet {
+    /// All subject IDs in a range
+    Range(RangeInclusive<SubjectId>),
+    /// All subject IDs that are equal to `id` in the bits that are set in `mask`
+    ///
+    /// For example, an ID of 0x100 with a mask of 0x1f00 includes subjects 0x100 through 0x1ff.
+    Mask {
+        /// The subject ID bits to match
+        id: u16,
+        /// The bits of `id` that must match
+        mask: u16,
+    },
+}
+
+impl SubjectSet {
+    /// Returns true if this set contains a subject ID
+    pub fn contains(&self, subject: SubjectId) -> bool {
+        match self {
+            SubjectSet::Range(range) => range.contains(&subject),
+            SubjectSet::Mask { id, mask } => {
+                (u16::from(subject) ^ id) & mask & SUBJECT_ID_BITS == 0
+            }
+        }
+    }
+
+    /// Calls a function with the (ID, mask) pairs that together match all subjects in this set
+    pub(crate) fn for_each_mask<F>(&self, mut f: F)
+    where
+        F: FnMut(u16, u16),
+    {
+        match self {
+            SubjectSet::Range(range) => {
+                let mut low = u32::from(u16::from(*range.start()));
+                let high = u32::from(u16::from(*range.end()));
+                // Split the range into aligned blocks with power-of-two sizes
+                while low <= high {
+                    let mut size = 1u32;
+                    while low.is_multiple_of(size * 2) && low + size * 2 - 1 <= high {
+                        size *= 2;
+                    }
+                    f(low as u16, !(size as u16 - 1) & SUBJECT_ID_BITS);
+                    low += size;
+                }
+            }
+            SubjectSet::Mask { id, mask } => f(*id & mask, *mask & SUBJECT_ID_BITS),
+        }
+    }
+}
+
+/// A wildcard subscription and the settings for the subscriptions that it creates
+#[derive(Debug)]
+pub(crate) struct Wildcard<D> {
+    pub subjects: SubjectSet,
+    pub payload_size_max: usize,
+    pub timeout: D,
+}
+
+#[cfg(test)]
+mod test {
+    use super::SubjectSet;
+    use alloc::vec::Vec;
+    use canadensis_core::SubjectId;
+    use core::convert::TryFrom;
+
+    fn subject(id: u16) -> SubjectId {
+        SubjectId::try_from(id).unwrap()
+    }
+
+    fn masks(set: &SubjectSet) -> Vec<(u16, u16)> {
+        let mut masks = Vec::new();
+        set.for_each_mask(|id, mask| masks.push((id, mask)));
+        masks
+    }
+
+    #[test]
+    fn range_masks() {
+        let set = SubjectSet::Range(subject(3)..=subject(17));
+        assert_eq!(
+            masks(&set),
+            [(3, 0x1fff), (4, 0x1ffc), (8, 0x1ff8), (16, 0x1ffe)]
+        );
+        assert!(!set.contains(subject(2)));
+        assert!(set.contains(subject(3)));
+        assert!(set.contains(subject(17)));
+        assert!(!set.contains(subject(18)));
+
+        let all = SubjectSet::Range(subject(0)..=subject(8191));
+        assert_eq!(masks(&all), [(0, 0)]);
+    }
+
+    #[test]
+    fn mask() {
+        let set = SubjectSet::Mask {
+            id: 0x1123,
+            mask: 0x1f00,
+        };
+        assert_eq!(masks(&set), [(0x1100, 0x1f00)]);
+        assert!(set.contains(subject(0x1100)));
+        assert!(set.contains(subject(0x11ff)));
+        assert!(!set.contains(subject(0x1200)));
+    }
+}
@@ -0,0 +1,227 @@
+//!
+//! Encoding frames for byte streams and message-based links
+//!
+//! This format carries frames over links that are not CAN buses, like a TCP connection to
+//! a gateway or a WebSocket connection to a browser-based monitor.
+//!
+//! Each frame is encoded as a 4-byte CAN ID (big-endian), a 1-byte data length, and the data.
+//! Timestamps are not included, so the receiver should timestamp frames using its own clock.
+//!
+//! On a byte stream, frames follow each other with no separators, and a [`StreamDecoder`] splits
+//! the stream into frames. On a message-based link like WebSocket, each binary message contains
+//! one or more complete frames, which [`decode_message`] decodes.
+//!
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::{CanId, Frame, FRAME_CAPACITY};
+
+/// The number of bytes before the data of each encoded frame
+pub const HEADER_LENGTH: usize = 5;
+
+/// Errors that can occur when decoding frames
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The CAN ID has more than 29 bits
+    CanId,
+    /// The data length is greater than the capacity of a frame
+    Length,
+    /// A message ended in the middle of a frame
+    Truncated,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::CanId => write!(f, "Invalid CAN ID"),
+            DecodeError::Length => write!(f, "Frame data is too long"),
+            DecodeError::Truncated => write!(f, "Message ended in the middle of a frame"),
+        }
+    }
+}
+
+/// Appends the encoded form of a frame to a buffer
+pub fn encode_frame(id: CanId, data: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(&u32::from(id).to_be_bytes());
+    out.push(data.len() as u8);
+    out.extend_from_slice(data);
+}
+
+/// Encodes frames into one message
+pub fn encode_message<'f, I, F>(frames: F) -> Vec<u8>
+where
+    I: 'f,
+    F: IntoIterator<Item = &'f Frame<I>>,
+{
+    let mut message = Vec::new();
+    for frame in frames {
+        encode_frame(frame.id(), frame.data(), &mut message);
+    }
+    message
+}
+
+/// Decodes a frame from the beginning of some bytes, giving it the provided timestamp
+///
+/// If the bytes start with a complete frame, this function returns the frame and the number of
+/// bytes that it used. If more bytes are needed, this function returns `Ok(None)`.
+pub fn decode_frame<I>(
+    bytes: &[u8],
+    timestamp: I,
+) -> Result<Option<(Frame<I>, usize)>, DecodeError> {
+    if bytes.len() < HEADER_LENGTH {
+        return Ok(None);
+    }
+    let id = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let id = CanId::try_from(id).map_err(|_| DecodeError::CanId)?;
+    let length = usize::from(bytes[4]);
+    if length > FRAME_CAPACITY {
+        return Err(DecodeError::Length);
+    }
+    let end = HEADER_LENGTH + length;
+    if bytes.len() < end {
+        return Ok(None);
+    }
+    let frame = Frame::new(timestamp, id, &bytes[HEADER_LENGTH..end]);
+    Ok(Some((frame, end)))
+}
+
+/// Splits a stream of bytes into frames
+#[derive(Debug, Default)]
+pub struct StreamDecoder {
+    /// Bytes that have been received but not decoded
+    buffer: Vec<u8>,
+}
+
+impl StreamDecoder {
+    /// Creates a decoder with no bytes
+    pub fn new() -> Self {
+        StreamDecoder { buffer: Vec::new() }
+    }
+
+    /// Adds bytes that were received from the stream
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Removes and returns the next complete frame, giving it the provided timestamp
+    ///
+    /// This function returns `Ok(None)` if more bytes are needed. After it returns an error,
+    /// the stream can't be decoded any further.
+    pub fn next_frame<I>(&mut self, timestamp: I) -> Result<Option<Frame<I>>, DecodeError> {
+        match decode_frame(&self.buffer, timestamp)? {
+            Some((frame, length)) => {
+                self.buffer.drain(..length);
+                Ok(Some(frame))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns an iterator over the frames in a message, giving each frame the provided timestamp
+///
+/// If the message ends in the middle of a frame or contains an invalid frame, the iterator
+/// returns an error and then stops.
+pub fn decode_message<I: Clone>(message: &[u8], timestamp: I) -> MessageFrames<'_, I> {
+    MessageFrames {
+        remaining: message,
+        timestamp,
+    }
+}
+
+/// An iterator over the frames in a message
+///
+/// This is created by [`decode_message`].
+pub struct MessageFrames<'m, I> {
+    remaining: &'m [u8],
+    timestamp: I,
+}
+
+impl<I: Clone> Iterator for MessageFrames<'_, I> {
+    type Item = Result<Frame<I>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        match decode_frame(self.remaining, self.timestamp.clone()) {
+            Ok(Some((frame, length))) => {
+                self.remaining = &self.remaining[length..];
+                Some(Ok(frame))
+            }
+            Ok(None) => {
+                self.remaining = &[];
+                Some(Err(DecodeError::Truncated))
+            }
+            Err(e) => {
+                self.remaining = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    fn id(value: u32) -> CanId {
+        CanId::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn stream_partial() {
+        let mut encoded = Vec::new();
+        encode_frame(id(0x107d552a), &[1, 2, 3], &mut encoded);
+        encode_frame(id(0x1e), &[], &mut encoded);
+
+        let mut decoder = StreamDecoder::new();
+        decoder.push_bytes(&encoded[..6]);
+        assert_eq!(decoder.next_frame(()), Ok(None));
+        decoder.push_bytes(&encoded[6..]);
+        assert_eq!(
+            decoder.next_frame(()),
+            Ok(Some(Frame::new((), id(0x107d552a), &[1, 2, 3])))
+        );
+        assert_eq!(
+            decoder.next_frame(()),
+            Ok(Some(Frame::new((), id(0x1e), &[])))
+        );
+        assert_eq!(decoder.next_frame(()), Ok(None));
+    }
+
+    #[test]
+    fn stream_invalid() {
+        let mut decoder = StreamDecoder::new();
+        decoder.push_bytes(&[0xff, 0xff, 0xff, 0xff, 0x00]);
+        assert_eq!(decoder.next_frame(()), Err(DecodeError::CanId));
+
+        let mut decoder = StreamDecoder::new();
+        decoder.push_bytes(&[0x00, 0x00, 0x00, 0x01, 200]);
+        assert_eq!(decoder.next_frame(()), Err(DecodeError::Length));
+    }
+
+    #[test]
+    fn message() {
+        let frames = [
+            Frame::new(7u32, id(0x10), &[0xaa]),
+            Frame::new(7u32, id(0x1234), &[0x01, 0x02]),
+        ];
+        let message = encode_message(&frames);
+        assert_eq!(
+            message,
+            vec![0x00, 0x00, 0x00, 0x10, 0x01, 0xaa, 0x00, 0x00, 0x12, 0x34, 0x02, 0x01, 0x02]
+        );
+        let decoded = decode_message(&message, 7u32).collect::<Vec<_>>();
+        assert_eq!(decoded, vec![Ok(frames[0].clone()), Ok(frames[1].clone())]);
+
+        let decoded = decode_message(&message[..8], 7u32).collect::<Vec<_>>();
+        assert_eq!(
+            decoded,
+            vec![Ok(frames[0].clone()), Err(DecodeError::Truncated)]
+        );
+    }
+}
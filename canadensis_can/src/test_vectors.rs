@@ -0,0 +1,173 @@
+//!
+//! Reference transfers and their canonical CAN frames
+//!
+//! Each [`TestVector`] contains a transfer header, a serialized payload, and the sequence of
+//! classic CAN frames that the UAVCAN/CAN specification requires for that transfer. Other
+//! implementations can use these vectors to check that they split transfers into frames and
+//! reassemble frames into transfers in the same way as canadensis, pyuavcan, and libcanard.
+//!
+//! The CAN IDs follow the examples in section 4.2.3 of the specification. Multi-frame transfer
+//! CRCs use CRC-16/CCITT-FALSE, whose check value for the ASCII string `123456789` is `0x29b1`.
+//!
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use canadensis_core::transfer::{Header, MessageHeader, ServiceHeader, Transfer};
+use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
+
+use crate::{CanId, Frame, Mtu};
+
+/// A reference transfer and the frames that represent it
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    /// A short description of this vector
+    pub name: &'static str,
+    /// The MTU used to split the transfer into frames
+    pub mtu: Mtu,
+    /// The transfer header
+    pub header: Header<()>,
+    /// The serialized transfer payload
+    pub payload: &'static [u8],
+    /// The CAN ID and data of each frame, in transmission order
+    pub frames: &'static [(u32, &'static [u8])],
+}
+
+impl TestVector {
+    /// Returns the transfer that this vector describes
+    pub fn transfer(&self) -> Transfer<&'static [u8], ()> {
+        Transfer {
+            header: self.header.clone(),
+            payload: self.payload,
+        }
+    }
+
+    /// Returns the frames of this vector, with a timestamp added to each frame
+    pub fn frames<I: Clone>(&self, timestamp: I) -> Vec<Frame<I>> {
+        self.frames
+            .iter()
+            .map(|&(id, data)| {
+                Frame::new(
+                    timestamp.clone(),
+                    CanId::try_from(id).expect("Invalid CAN ID in test vector"),
+                    data,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns true if this is an anonymous message
+    ///
+    /// The pseudo-ID in the source node field of an anonymous message is chosen by the
+    /// transmitter. An implementation that chooses pseudo-IDs differently may produce a CAN ID
+    /// that differs from the expected ID in the 7 least significant bits.
+    pub fn is_anonymous(&self) -> bool {
+        matches!(
+            self.header,
+            Header::Message(MessageHeader { source: None, .. })
+        )
+    }
+}
+
+/// Returns all the test vectors
+pub fn test_vectors() -> Vec<TestVector> {
+    vec![
+        // uavcan.node.Heartbeat.1.0 with uptime 258, health ADVISORY, mode MAINTENANCE,
+        // vendor-specific status code 0xab
+        TestVector {
+            name: "heartbeat",
+            mtu: Mtu::Can8,
+            header: Header::Message(MessageHeader {
+                timestamp: (),
+                transfer_id: transfer_id(0),
+                priority: Priority::Nominal,
+                subject: SubjectId::from_truncating(7509),
+                source: Some(NodeId::from_truncating(42)),
+            }),
+            payload: &[0x02, 0x01, 0x00, 0x00, 0x01, 0x02, 0xab],
+            frames: &[(
+                0x107d552a,
+                &[0x02, 0x01, 0x00, 0x00, 0x01, 0x02, 0xab, 0xe0],
+            )],
+        },
+        // Anonymous message with the ASCII payload "Hi"
+        // Pseudo-ID: 0x55 ^ 0x48 ^ 0x69 = 0x74
+        TestVector {
+            name: "anonymous message",
+            mtu: Mtu::Can8,
+            header: Header::Message(MessageHeader {
+                timestamp: (),
+                transfer_id: transfer_id(0),
+                priority: Priority::Nominal,
+                subject: SubjectId::from_truncating(4919),
+                source: None,
+            }),
+            payload: b"Hi",
+            frames: &[(0x11733774, &[0x48, 0x69, 0xe0])],
+        },
+        // uavcan.node.GetInfo.1.0 request (empty payload)
+        TestVector {
+            name: "service request",
+            mtu: Mtu::Can8,
+            header: Header::Request(ServiceHeader {
+                timestamp: (),
+                transfer_id: transfer_id(1),
+                priority: Priority::Nominal,
+                service: ServiceId::from_truncating(430),
+                source: NodeId::from_truncating(123),
+                destination: NodeId::from_truncating(42),
+            }),
+            payload: &[],
+            frames: &[(0x136b957b, &[0xe1])],
+        },
+        // Two-frame response whose payload is the CRC check string
+        TestVector {
+            name: "two-frame service response",
+            mtu: Mtu::Can8,
+            header: Header::Response(ServiceHeader {
+                timestamp: (),
+                transfer_id: transfer_id(1),
+                priority: Priority::Nominal,
+                service: ServiceId::from_truncating(430),
+                source: NodeId::from_truncating(42),
+                destination: NodeId::from_truncating(123),
+            }),
+            payload: b"123456789",
+            frames: &[
+                (0x126bbdaa, b"1234567\xa1"),
+                (0x126bbdaa, &[b'8', b'9', 0x29, 0xb1, 0x41]),
+            ],
+        },
+        // Three-frame message with the largest transfer ID
+        TestVector {
+            name: "three-frame message",
+            mtu: Mtu::Can8,
+            header: Header::Message(MessageHeader {
+                timestamp: (),
+                transfer_id: transfer_id(31),
+                priority: Priority::Nominal,
+                subject: SubjectId::from_truncating(4919),
+                source: Some(NodeId::from_truncating(59)),
+            }),
+            payload: &[
+                0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            ],
+            frames: &[
+                (
+                    0x1073373b,
+                    &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0xbf],
+                ),
+                (
+                    0x1073373b,
+                    &[0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x1f],
+                ),
+                (0x1073373b, &[0x78, 0xcb, 0x7f]),
+            ],
+        },
+    ]
+}
+
+fn transfer_id(value: u8) -> TransferId {
+    TransferId::try_from(value).expect("Invalid transfer ID in test vector")
+}
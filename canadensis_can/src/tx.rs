@@ -3,10 +3,9 @@
 //!
 
 use core::convert::TryFrom;
-use core::iter;
 
 use canadensis_core::transfer::{Header, ServiceHeader, Transfer};
-use canadensis_core::NodeId;
+use canadensis_core::{NodeId, TransferId};
 
 use crate::crc::TransferCrc;
 use crate::data::Frame;
@@ -15,7 +14,10 @@ use crate::queue::FrameSink;
 use crate::tx::breakdown::Breakdown;
 use crate::{CanId, Mtu};
 
+pub use self::chunked::ChunkedTransfer;
+
 mod breakdown;
+mod chunked;
 #[cfg(test)]
 mod tx_test;
 
@@ -24,7 +26,9 @@ pub struct Transmitter<Q> {
     /// Queue of frames waiting to be sent
     frame_queue: Q,
     /// Transport MTU
-    mtu: usize,
+    ///
+    /// A frame queue can override this for the interfaces that it sends to.
+    mtu: Mtu,
     /// Number of transfers successfully transmitted
     ///
     /// Success means that the frames were placed into the frame queue successfully. CAN bus errors
@@ -43,7 +47,7 @@ impl<Q> Transmitter<Q> {
     pub fn new(mtu: Mtu, frame_queue: Q) -> Self {
         Transmitter {
             frame_queue,
-            mtu: mtu as usize,
+            mtu,
             transfer_count: 0,
             error_count: 0,
         }
@@ -53,7 +57,7 @@ impl<Q> Transmitter<Q> {
     ///
     /// This will take effect on the next call to push().
     pub fn set_mtu(&mut self, mtu: Mtu) {
-        self.mtu = mtu as usize;
+        self.mtu = mtu;
     }
 
     /// Breaks a transfer into frames
@@ -86,67 +90,35 @@ impl<Q> Transmitter<Q> {
         }
     }
 
-    fn push_inner<I>(&mut self, transfer: Transfer<&[u8], I>) -> Result<(), OutOfMemoryError>
+    /// Pushes up to `max_frames` frames of a chunked transfer onto the queue and returns
+    /// the number of frames pushed
+    ///
+    /// When the last frame has been pushed, the transfer counts as successfully transmitted.
+    /// If the queue does not have space for the frames, this function returns an error and
+    /// pushes no frames. The application can try again after some frames have been sent.
+    pub fn push_chunk<I>(
+        &mut self,
+        transfer: &mut ChunkedTransfer<'_, I>,
+        max_frames: usize,
+    ) -> Result<usize, OutOfMemoryError>
     where
         Q: FrameSink<I>,
         I: Clone,
     {
-        let frame_stats = crate::calculate_frame_stats(transfer.payload.len(), self.mtu);
-        // Check that enough space is available in the queue for all the frames.
-        // Return an error if space is not available.
-        self.frame_queue.try_reserve(frame_stats.frames)?;
-
-        // Make an iterator over the payload bytes and padding. Run the CRC on that.
-        let mut crc = TransferCrc::new();
-        let payload_and_padding = transfer
-            .payload
-            .iter()
-            .cloned()
-            .chain(iter::repeat(0).take(frame_stats.last_frame_padding))
-            .inspect(|byte| crc.add(*byte));
-        // Break into frames
-        let can_id = make_can_id(&transfer.header, &transfer.payload);
-        let mut breakdown = Breakdown::new(self.mtu, transfer.header.transfer_id());
-        let mut frames = 0;
-        // Do the non-last frames
-        for byte in payload_and_padding {
-            if let Some(frame_data) = breakdown.add(byte) {
-                // Filled up a frame
-                self.push_frame(transfer.header.timestamp(), can_id, &frame_data)?;
-                frames += 1;
-            }
-        }
-        if frames != 0 {
-            // The payload + padding was split across at least one non-last frame (handled above)
-            // and the last frame (still in the Breakdown). It needs a CRC.
-            let crc_value = crc.get();
-            // Add the CRC value, most significant byte first
-            let crc_bytes = [(crc_value >> 8) as u8, crc_value as u8];
-            for &byte in crc_bytes.iter() {
-                if let Some(frame_data) = breakdown.add(byte) {
-                    // Filled up a frame
-                    self.push_frame(transfer.header.timestamp(), can_id, &frame_data)?;
-                }
-            }
+        let was_finished = transfer.is_finished();
+        let pushed = transfer.push_frames(&mut self.frame_queue, max_frames)?;
+        if transfer.is_finished() && !was_finished {
+            self.transfer_count = self.transfer_count.wrapping_add(1);
         }
-        let last_frame_data = breakdown.finish();
-        self.push_frame(transfer.header.timestamp(), can_id, &last_frame_data)?;
-        Ok(())
+        Ok(pushed)
     }
 
-    /// Creates a frame and adds it to a transaction
-    fn push_frame<I>(
-        &mut self,
-        timestamp: I,
-        id: CanId,
-        data: &[u8],
-    ) -> core::result::Result<(), OutOfMemoryError>
+    fn push_inner<I>(&mut self, transfer: Transfer<&[u8], I>) -> Result<(), OutOfMemoryError>
     where
         Q: FrameSink<I>,
         I: Clone,
     {
-        let frame = Frame::new(timestamp, id, data);
-        self.frame_queue.push_frame(frame)
+        self.frame_queue.push_transfer(self.mtu, transfer)
     }
 
     /// Returns a reference to the frame queue, where outgoing frames are stored
@@ -177,6 +149,85 @@ impl<Q> Transmitter<Q> {
     }
 }
 
+/// Splits a transfer into frames of up to `mtu` bytes and pushes them onto a queue
+///
+/// This is the default implementation of [`FrameSink::push_transfer`].
+pub(crate) fn push_transfer_frames<I, Q>(
+    frame_queue: &mut Q,
+    mtu: Mtu,
+    transfer: Transfer<&[u8], I>,
+) -> Result<(), OutOfMemoryError>
+where
+    Q: FrameSink<I> + ?Sized,
+    I: Clone,
+{
+    let mtu = mtu.as_bytes();
+    let frame_stats = crate::calculate_frame_stats(transfer.payload.len(), mtu);
+    // Check that enough space is available in the queue for all the frames.
+    // Return an error if space is not available.
+    frame_queue.try_reserve(frame_stats.frames)?;
+
+    let can_id = make_can_id(&transfer.header, transfer.payload);
+    let timestamp = transfer.header.timestamp();
+    split_into_frames(
+        mtu,
+        transfer.header.transfer_id(),
+        transfer.payload,
+        frame_stats.last_frame_padding,
+        &mut |frame_data| frame_queue.push_frame(Frame::new(timestamp.clone(), can_id, frame_data)),
+    )
+}
+
+/// Splits a transfer payload into frames and passes the data of each frame, including the tail
+/// byte, to `handle_frame`
+///
+/// This function adds `padding` zero bytes after the payload, and a transfer CRC if more than
+/// one frame is needed.
+///
+/// This function is not generic, so only one copy of it is compiled no matter how many queue
+/// and instant types the transmitters in a program use.
+fn split_into_frames(
+    mtu: usize,
+    transfer_id: TransferId,
+    payload: &[u8],
+    padding: usize,
+    handle_frame: &mut dyn FnMut(&[u8]) -> Result<(), OutOfMemoryError>,
+) -> Result<(), OutOfMemoryError> {
+    // Make an iterator over the payload bytes and padding. Run the CRC on that.
+    let mut crc = TransferCrc::new();
+    let payload_and_padding = payload
+        .iter()
+        .cloned()
+        .chain(core::iter::repeat_n(0, padding))
+        .inspect(|byte| crc.add(*byte));
+    // Break into frames
+    let mut breakdown = Breakdown::new(mtu, transfer_id);
+    let mut frames = 0;
+    // Do the non-last frames
+    for byte in payload_and_padding {
+        if let Some(frame_data) = breakdown.add(byte) {
+            // Filled up a frame
+            handle_frame(&frame_data)?;
+            frames += 1;
+        }
+    }
+    if frames != 0 {
+        // The payload + padding was split across at least one non-last frame (handled above)
+        // and the last frame (still in the Breakdown). It needs a CRC.
+        let crc_value = crc.get();
+        // Add the CRC value, most significant byte first
+        let crc_bytes = [(crc_value >> 8) as u8, crc_value as u8];
+        for &byte in crc_bytes.iter() {
+            if let Some(frame_data) = breakdown.add(byte) {
+                // Filled up a frame
+                handle_frame(&frame_data)?;
+            }
+        }
+    }
+    let last_frame_data = breakdown.finish();
+    handle_frame(&last_frame_data)
+}
+
 fn make_can_id<I>(header: &Header<I>, payload: &[u8]) -> CanId {
     let mut bits = 0u32;
 
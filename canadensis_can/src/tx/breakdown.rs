@@ -218,7 +218,7 @@ mod test {
         frames.push(breakdown.finish());
 
         assert_eq!(expected_frames.len(), frames.len());
-        for (expected, actual) in expected_frames.iter().zip(frames.into_iter()) {
+        for (expected, actual) in expected_frames.iter().zip(frames) {
             assert_eq!(*expected, &*actual);
         }
     }
@@ -264,7 +264,7 @@ mod test {
         frames.push(breakdown.finish());
 
         assert_eq!(expected_frames.len(), frames.len());
-        for (expected, actual) in expected_frames.iter().zip(frames.into_iter()) {
+        for (expected, actual) in expected_frames.iter().zip(frames) {
             assert_eq!(*expected, &*actual);
         }
     }
@@ -0,0 +1,153 @@
+use canadensis_core::transfer::Transfer;
+
+use crate::crc::TransferCrc;
+use crate::data::Frame;
+use crate::error::OutOfMemoryError;
+use crate::queue::FrameSink;
+use crate::tx::breakdown::Breakdown;
+use crate::tx::make_can_id;
+use crate::{CanId, Mtu};
+
+/// An outgoing transfer that is split into frames a few at a time
+///
+/// This is useful for large transfers, like file contents or firmware images. Instead of putting
+/// all the frames into the queue at once, the application can push a few frames at a time
+/// (using [`Transmitter::push_chunk`](crate::Transmitter::push_chunk)) when the queue has space,
+/// and use [`bytes_queued`](#method.bytes_queued) to display progress.
+///
+/// The frames are pushed one at a time using [`FrameSink::push_frame`], so a frame queue that
+/// overrides [`FrameSink::push_transfer`] does not apply its override to chunked transfers.
+pub struct ChunkedTransfer<'p, I> {
+    /// The CAN ID of all frames
+    can_id: CanId,
+    /// The deadline of all frames
+    timestamp: I,
+    /// The transfer payload
+    payload: &'p [u8],
+    /// The number of payload bytes, padding bytes, and CRC bytes that have been added to
+    /// the breakdown
+    position: usize,
+    /// The number of padding bytes to add after the payload
+    padding: usize,
+    /// True if the transfer needs more than one frame and has a CRC
+    multi_frame: bool,
+    /// The CRC of the payload and padding
+    crc: TransferCrc,
+    /// The breakdown that makes frames, or None if all frames have been pushed
+    breakdown: Option<Breakdown>,
+    /// The total number of frames in this transfer
+    frames: usize,
+    /// The number of frames that have been pushed
+    frames_pushed: usize,
+}
+
+impl<'p, I> ChunkedTransfer<'p, I>
+where
+    I: Clone,
+{
+    /// Prepares to split a transfer into frames of up to `mtu` bytes
+    pub fn new(mtu: Mtu, transfer: Transfer<&'p [u8], I>) -> Self {
+        let mtu = mtu.as_bytes();
+        let frame_stats = crate::calculate_frame_stats(transfer.payload.len(), mtu);
+        ChunkedTransfer {
+            can_id: make_can_id(&transfer.header, transfer.payload),
+            breakdown: Some(Breakdown::new(mtu, transfer.header.transfer_id())),
+            timestamp: transfer.header.timestamp(),
+            payload: transfer.payload,
+            position: 0,
+            padding: frame_stats.last_frame_padding,
+            multi_frame: frame_stats.frames > 1,
+            crc: TransferCrc::new(),
+            frames: frame_stats.frames,
+            frames_pushed: 0,
+        }
+    }
+
+    /// Pushes up to `max_frames` frames onto a queue and returns the number of frames pushed
+    ///
+    /// This function returns an error if the queue does not have space for the frames. In that
+    /// case, no frames are pushed and this function can be called again later.
+    pub fn push_frames<Q>(
+        &mut self,
+        frame_queue: &mut Q,
+        max_frames: usize,
+    ) -> Result<usize, OutOfMemoryError>
+    where
+        Q: FrameSink<I> + ?Sized,
+    {
+        let count = max_frames.min(self.frames_remaining());
+        frame_queue.try_reserve(count)?;
+        for _ in 0..count {
+            let frame_data = self.next_frame();
+            frame_queue.push_frame(Frame::new(self.timestamp.clone(), self.can_id, &frame_data))?;
+            self.frames_pushed += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns the data of the next frame
+    ///
+    /// This must not be called after the last frame has been returned.
+    fn next_frame(&mut self) -> heapless::Vec<u8, 64> {
+        let payload_and_padding_length = self.payload.len() + self.padding;
+        let stream_length = if self.multi_frame {
+            payload_and_padding_length + 2
+        } else {
+            payload_and_padding_length
+        };
+        let breakdown = self.breakdown.as_mut().expect("All frames already pushed");
+        while self.position != stream_length {
+            let byte = if self.position < self.payload.len() {
+                self.payload[self.position]
+            } else if self.position < payload_and_padding_length {
+                0
+            } else {
+                // CRC, most significant byte first
+                let crc_value = self.crc.get();
+                if self.position == payload_and_padding_length {
+                    (crc_value >> 8) as u8
+                } else {
+                    crc_value as u8
+                }
+            };
+            if self.position < payload_and_padding_length {
+                self.crc.add(byte);
+            }
+            self.position += 1;
+            if let Some(frame_data) = breakdown.add(byte) {
+                return frame_data;
+            }
+        }
+        self.breakdown.take().unwrap().finish()
+    }
+
+    /// Returns true if all frames have been pushed
+    pub fn is_finished(&self) -> bool {
+        self.frames_pushed == self.frames
+    }
+
+    /// Returns the total number of frames in this transfer
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns the number of frames that have not been pushed
+    pub fn frames_remaining(&self) -> usize {
+        self.frames - self.frames_pushed
+    }
+
+    /// Returns the number of payload bytes that are in frames that have been pushed
+    pub fn bytes_queued(&self) -> usize {
+        if self.is_finished() {
+            self.payload.len()
+        } else {
+            // The most recent byte added to the breakdown is not in a pushed frame
+            self.position.saturating_sub(1).min(self.payload.len())
+        }
+    }
+
+    /// Returns the length of the payload
+    pub fn total_bytes(&self) -> usize {
+        self.payload.len()
+    }
+}
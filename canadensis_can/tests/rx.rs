@@ -7,7 +7,11 @@ extern crate canadensis_core;
 
 use core::convert::{TryFrom, TryInto};
 
-use canadensis_can::{CanId, Frame, Mtu, OutOfMemoryError, Receiver, ServiceSubscribeError};
+use canadensis_can::buffer::BlockPool;
+use canadensis_can::queue::{ArrayQueue, FrameQueueSource};
+use canadensis_can::{
+    CanId, Frame, Mtu, OutOfMemoryError, Receiver, ServiceSubscribeError, SubjectSet, Transmitter,
+};
 use canadensis_core::time::{Instant, MicrosecondDuration32, Microseconds32};
 use canadensis_core::transfer::*;
 use canadensis_core::{NodeId, Priority, ServiceId, SubjectId};
@@ -281,6 +285,7 @@ fn test_array() -> Result<(), ServiceSubscribeError> {
 }
 
 #[test]
+#[allow(clippy::unusual_byte_groupings)]
 fn test_multi_frame_anonymous() {
     // Multi-frame anonymous transfers must be ignored
     let mut receiver = Receiver::<Microseconds32>::new(NodeId::try_from(3).unwrap(), Mtu::Can8);
@@ -387,3 +392,413 @@ fn test_ignore_request_to_other_node() {
 
     assert_eq!(transfer, None);
 }
+
+#[test]
+fn test_promiscuous_request_to_other_node() {
+    let mut rx = Receiver::new_anonymous(Mtu::Can8);
+    rx.enable_promiscuous(0, duration(0));
+
+    // This transfer is going to node 42. No subscription is needed in promiscuous mode.
+    let transfer = rx
+        .accept(Frame::new(
+            instant(302),
+            0x136b957b.try_into().unwrap(),
+            &[0xe1],
+        ))
+        .unwrap();
+
+    let expected = Transfer {
+        header: Header::Request(ServiceHeader {
+            timestamp: instant(302),
+            transfer_id: 1.try_into().unwrap(),
+            priority: Priority::Nominal,
+            service: ServiceId::try_from(430).unwrap(),
+            source: 123.try_into().unwrap(),
+            destination: 42.try_into().unwrap(),
+        }),
+        payload: vec![],
+    };
+    assert_eq!(transfer, Some(expected));
+}
+
+#[test]
+fn test_promiscuous_frame_filters() {
+    let mut rx: Receiver<TestInstant> = Receiver::new(NodeId::try_from(42).unwrap(), Mtu::Can8);
+    rx.subscribe_message(SubjectId::try_from(7509).unwrap(), 7, duration(0))
+        .unwrap();
+    assert_eq!(rx.frame_filters().unwrap().len(), 1);
+
+    rx.enable_promiscuous(0, duration(0));
+    let filters = rx.frame_filters().unwrap();
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0].mask(), 0);
+    // Messages, and requests and responses addressed to other nodes
+    for &id in [0x107d552a, 0x11733775, 0x136b957b, 0x126bbdaa].iter() {
+        assert!(filters[0].accepts(id));
+    }
+
+    rx.disable_promiscuous();
+    let filters = rx.frame_filters().unwrap();
+    assert_eq!(filters.len(), 1);
+    assert!(filters[0].accepts(0x107d552a));
+    assert!(!filters[0].accepts(0x136b957b));
+}
+
+#[test]
+fn test_many_subscriptions() -> Result<(), OutOfMemoryError> {
+    let mut rx = Receiver::new(0.try_into().unwrap(), Mtu::Can8);
+    let subjects = [4919u16, 7509, 10, 8191, 300, 0];
+    for &subject in &subjects {
+        rx.subscribe_message(SubjectId::try_from(subject).unwrap(), 7, duration(0))?;
+    }
+    rx.unsubscribe_message(SubjectId::try_from(300).unwrap());
+
+    for &subject in &subjects {
+        let can_id = 0x1060_0000 | (u32::from(subject) << 8) | 42;
+        let transfer = rx.accept(Frame::new(
+            instant(0),
+            can_id.try_into().unwrap(),
+            &[0x01, 0xe0],
+        ))?;
+        if subject == 300 {
+            assert!(transfer.is_none());
+        } else {
+            let transfer = transfer.expect("Didn't get a transfer");
+            match transfer.header {
+                Header::Message(header) => assert_eq!(u16::from(header.subject), subject),
+                _ => panic!("Not a message"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_block_pool() {
+    static POOL: BlockPool<16, 1> = BlockPool::new();
+    let mut rx = Receiver::with_allocator(0.try_into().unwrap(), Mtu::Can8, &POOL);
+    rx.subscribe_message(SubjectId::try_from(4919).unwrap(), 14, duration(1000))
+        .unwrap();
+    rx.subscribe_message(SubjectId::try_from(7509).unwrap(), 7, duration(0))
+        .unwrap();
+
+    let id = CanId::try_from(0x1073373b).unwrap();
+    let first = Frame::new(
+        instant(0),
+        id,
+        &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0xbf],
+    );
+    let second = Frame::new(
+        instant(1),
+        id,
+        &[0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x1f],
+    );
+    let last = Frame::new(instant(2), id, &[0x78, 0xcb, 0x7f]);
+    assert!(rx.accept(first).unwrap().is_none());
+    assert!(rx.accept(second).unwrap().is_none());
+    assert_eq!(POOL.free_blocks(), 0);
+
+    // The only block is in use, so a heartbeat can't be received
+    let heartbeat = Frame::new(
+        instant(3),
+        0x107d552a.try_into().unwrap(),
+        &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0],
+    );
+    assert!(rx.accept(heartbeat).is_err());
+
+    let transfer = rx.accept(last).unwrap().expect("Didn't get a transfer");
+    assert_eq!(
+        &*transfer.payload,
+        &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d]
+    );
+    drop(transfer);
+    assert_eq!(POOL.free_blocks(), 1);
+}
+
+#[test]
+fn test_clean_expired() {
+    static POOL: BlockPool<16, 1> = BlockPool::new();
+    let mut rx = Receiver::with_allocator(0.try_into().unwrap(), Mtu::Can8, &POOL);
+    rx.subscribe_message(SubjectId::try_from(4919).unwrap(), 14, duration(1000))
+        .unwrap();
+
+    let first = Frame::new(
+        instant(0),
+        CanId::try_from(0x1073373b).unwrap(),
+        &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0xbf],
+    );
+    assert!(rx.accept(first).unwrap().is_none());
+    assert_eq!(POOL.free_blocks(), 0);
+
+    // The session has not timed out yet
+    rx.clean_expired(instant(500));
+    assert_eq!(POOL.free_blocks(), 0);
+    // No more frames arrive, but the session times out and releases its memory
+    rx.clean_expired(instant(2000));
+    assert_eq!(POOL.free_blocks(), 1);
+}
+
+#[test]
+fn test_sniff_requests() {
+    let service = ServiceId::try_from(430).unwrap();
+    // Request from node 123 to node 42
+    let request = Frame::new(instant(0), CanId::try_from(0x136b957b).unwrap(), &[0xe1]);
+
+    let mut rx: Receiver<TestInstant> = Receiver::new(7.try_into().unwrap(), Mtu::Can8);
+    rx.subscribe_request(service, 0, duration(0)).unwrap();
+    assert!(rx.accept(request.clone()).unwrap().is_none());
+
+    // An anonymous receiver can sniff requests to other nodes
+    let mut rx: Receiver<TestInstant> = Receiver::new_anonymous(Mtu::Can8);
+    rx.sniff_requests(service, 0, duration(0)).unwrap();
+    let transfer = rx.accept(request.clone()).unwrap().expect("No transfer");
+    match transfer.header {
+        Header::Request(header) => {
+            assert_eq!(header.source, NodeId::try_from(123).unwrap());
+            assert_eq!(header.destination, NodeId::try_from(42).unwrap());
+        }
+        _ => panic!("Not a request"),
+    }
+    let filters = rx.frame_filters().unwrap();
+    assert_eq!(filters.len(), 1);
+    assert!(filters[0].accepts(0x136b957b));
+    assert!(filters[0].accepts(0x136b957b & !(0x7f << 7)));
+
+    rx.unsubscribe_request(service);
+    assert!(rx.accept(request).unwrap().is_none());
+}
+
+/// Returns the frames of a two-frame response from node 123 to a destination node, with
+/// transfer ID 5
+fn two_frame_response(destination: u8, payload: &[u8]) -> Vec<Frame<TestInstant>> {
+    let mut tx = Transmitter::new(Mtu::Can8, ArrayQueue::<TestInstant, 4>::new());
+    tx.push(Transfer {
+        header: Header::Response(ServiceHeader {
+            timestamp: instant(100),
+            transfer_id: 5.try_into().unwrap(),
+            priority: Priority::Nominal,
+            service: ServiceId::try_from(430).unwrap(),
+            source: 123.try_into().unwrap(),
+            destination: destination.try_into().unwrap(),
+        }),
+        payload,
+    })
+    .unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = tx.frame_queue_mut().pop_frame() {
+        frames.push(frame);
+    }
+    assert_eq!(frames.len(), 2);
+    frames
+}
+
+#[test]
+fn test_sniff_same_transfer_id_to_two_destinations() {
+    // Node 123 responds to nodes 42 and 43 with the same transfer ID, and the frames of the two
+    // responses are interleaved
+    let to_42 = two_frame_response(42, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    let to_43 = two_frame_response(43, &[11, 12, 13, 14, 15, 16, 17, 18, 19, 20]);
+
+    let mut rx: Receiver<TestInstant> = Receiver::new_anonymous(Mtu::Can8);
+    rx.sniff_responses(ServiceId::try_from(430).unwrap(), 10, duration(1000))
+        .unwrap();
+    assert!(rx.accept(to_42[0].clone()).unwrap().is_none());
+    assert!(rx.accept(to_43[0].clone()).unwrap().is_none());
+    let first = rx.accept(to_42[1].clone()).unwrap().expect("No transfer");
+    let second = rx.accept(to_43[1].clone()).unwrap().expect("No transfer");
+
+    for (transfer, destination, payload) in [
+        (first, 42, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]),
+        (second, 43, [11, 12, 13, 14, 15, 16, 17, 18, 19, 20]),
+    ] {
+        match transfer.header {
+            Header::Response(header) => {
+                assert_eq!(header.source, NodeId::try_from(123).unwrap());
+                assert_eq!(header.destination, NodeId::try_from(destination).unwrap());
+            }
+            _ => panic!("Not a response"),
+        }
+        assert_eq!(transfer.payload, payload);
+    }
+    assert_eq!(rx.error_count(), 0);
+}
+
+#[test]
+fn test_subject_set() {
+    // Heartbeat from node 42
+    let heartbeat = Frame::new(
+        instant(0),
+        CanId::try_from(0x107d552a).unwrap(),
+        &[0, 0, 0, 0, 0, 0, 0, 0xe0],
+    );
+    let subjects =
+        SubjectSet::Range(SubjectId::try_from(7000).unwrap()..=SubjectId::try_from(8191).unwrap());
+
+    let mut rx: Receiver<TestInstant> = Receiver::new_anonymous(Mtu::Can8);
+    rx.subscribe_message_set(subjects.clone(), 7, duration(0))
+        .unwrap();
+    // All subjects in the range are covered by the filters
+    let filters = rx.frame_filters().unwrap();
+    assert!(filters.iter().any(|filter| filter.accepts(0x107d552a)));
+    assert!(filters.iter().any(|filter| filter.accepts(0x107d582a)));
+    assert!(!filters.iter().any(|filter| filter.accepts(0x101b552a)));
+
+    let transfer = rx.accept(heartbeat.clone()).unwrap().expect("No transfer");
+    match transfer.header {
+        Header::Message(header) => {
+            assert_eq!(header.subject, SubjectId::try_from(7509).unwrap());
+            assert_eq!(header.source, Some(NodeId::try_from(42).unwrap()));
+        }
+        _ => panic!("Not a message"),
+    }
+
+    rx.unsubscribe_message_set(&subjects);
+    assert!(rx.accept(heartbeat).unwrap().is_none());
+    assert!(rx.frame_filters().unwrap().is_empty());
+}
+
+#[test]
+fn test_overlapping_subject_sets() {
+    // Frames from node 42 on subject 7509, which is in both sets, and subject 8000, which is only
+    // in the large set
+    let id_7509 = CanId::try_from(0x107d552a).unwrap();
+    let id_8000 = CanId::try_from(0x107f402a).unwrap();
+    let large =
+        SubjectSet::Range(SubjectId::try_from(7000).unwrap()..=SubjectId::try_from(8191).unwrap());
+    let small =
+        SubjectSet::Range(SubjectId::try_from(7500).unwrap()..=SubjectId::try_from(7600).unwrap());
+
+    let mut rx: Receiver<TestInstant> = Receiver::new_anonymous(Mtu::Can8);
+    rx.subscribe_message_set(large.clone(), 8, duration(1000))
+        .unwrap();
+    rx.subscribe_message_set(small.clone(), 8, duration(1000))
+        .unwrap();
+    let single_frame = Frame::new(instant(0), id_8000, &[0, 0, 0, 0, 0, 0, 0, 0xe0]);
+    assert!(rx.accept(single_frame).unwrap().is_some());
+
+    // Start a two-frame transfer on subject 7509
+    let first_frame = Frame::new(instant(0), id_7509, &[0, 1, 2, 3, 4, 5, 6, 0xa0]);
+    assert!(rx.accept(first_frame).unwrap().is_none());
+
+    // The small set still includes subject 7509, so the transfer continues
+    rx.unsubscribe_message_set(&large);
+    let second_frame = Frame::new(instant(0), id_7509, &[7, 0x17, 0x8d, 0x40]);
+    let transfer = rx.accept(second_frame).unwrap().expect("No transfer");
+    assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7], transfer.payload);
+    let single_frame = Frame::new(instant(0), id_8000, &[0, 0, 0, 0, 0, 0, 0, 0xe1]);
+    assert!(rx.accept(single_frame).unwrap().is_none());
+
+    rx.unsubscribe_message_set(&small);
+    let single_frame = Frame::new(instant(0), id_7509, &[0, 0, 0, 0, 0, 0, 0, 0xe1]);
+    assert!(rx.accept(single_frame).unwrap().is_none());
+    assert!(rx.frame_filters().unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "can-fd")]
+fn test_oversize_frame_policy() {
+    use canadensis_can::OversizeFramePolicy;
+
+    // A single-frame CAN FD transfer with 11 bytes of payload, received on a Classic CAN receiver
+    let frame = Frame::new(
+        instant(0),
+        CanId::try_from(0x107d552a).unwrap(),
+        &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0xe0],
+    );
+    let mut rx: Receiver<TestInstant> = Receiver::new_anonymous(Mtu::Can8);
+    rx.subscribe_message(SubjectId::try_from(7509).unwrap(), 16, duration(0))
+        .unwrap();
+    assert_eq!(OversizeFramePolicy::Accept, rx.oversize_frame_policy());
+
+    let transfer = rx.accept(frame.clone()).unwrap().expect("No transfer");
+    assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10], transfer.payload);
+    assert_eq!(1, rx.oversize_frame_count());
+    assert_eq!(0, rx.error_count());
+
+    rx.set_oversize_frame_policy(OversizeFramePolicy::Truncate);
+    let transfer = rx.accept(frame.clone()).unwrap().expect("No transfer");
+    assert_eq!(vec![0, 1, 2, 3, 4, 5, 6], transfer.payload);
+    assert_eq!(2, rx.oversize_frame_count());
+    assert_eq!(1, rx.error_count());
+
+    rx.set_oversize_frame_policy(OversizeFramePolicy::Reject);
+    assert!(rx.accept(frame).unwrap().is_none());
+    assert_eq!(3, rx.oversize_frame_count());
+    assert_eq!(2, rx.error_count());
+}
+
+#[test]
+fn test_gap_detection() -> Result<(), OutOfMemoryError> {
+    let mut rx = Receiver::new(0.try_into().unwrap(), Mtu::Can8);
+    let heartbeat_subject = SubjectId::try_from(7509).unwrap();
+    let source = NodeId::try_from(42).unwrap();
+    rx.subscribe_message(heartbeat_subject, 7, duration(1000))?;
+    assert_eq!(None, rx.missed_transfers(heartbeat_subject, source));
+    rx.enable_gap_detection(heartbeat_subject)?;
+
+    for (time, transfer_id) in [0u8, 1, 4, 5, 5, 31].iter().enumerate() {
+        let transfer = rx.accept(Frame::new(
+            instant(time as u32),
+            0x107d552a.try_into().unwrap(),
+            &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0 | transfer_id],
+        ))?;
+        assert!(transfer.is_some());
+    }
+    // 2 and 3 missed, then 6 through 30 missed
+    assert_eq!(Some(27), rx.missed_transfers(heartbeat_subject, source));
+    assert_eq!(Some(27), rx.total_missed_transfers(heartbeat_subject));
+    assert_eq!(
+        Some(0),
+        rx.missed_transfers(heartbeat_subject, NodeId::try_from(43).unwrap())
+    );
+    rx.reset_missed_transfers(heartbeat_subject);
+    assert_eq!(Some(0), rx.total_missed_transfers(heartbeat_subject));
+    rx.disable_gap_detection(heartbeat_subject);
+    assert_eq!(None, rx.total_missed_transfers(heartbeat_subject));
+    Ok(())
+}
+
+#[test]
+fn test_ignore_anonymous() -> Result<(), OutOfMemoryError> {
+    let mut rx = Receiver::new(0.try_into().unwrap(), Mtu::Can8);
+    let heartbeat_subject = SubjectId::try_from(7509).unwrap();
+    let data = [0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0];
+    let identified = Frame::new(instant(0), 0x107d552a.try_into().unwrap(), &data);
+    let anonymous = Frame::new(instant(0), 0x117d552a.try_into().unwrap(), &data);
+    assert_eq!(None, rx.ignored_anonymous_messages(heartbeat_subject));
+    rx.subscribe_message(heartbeat_subject, 7, duration(0))?;
+    assert!(rx.accept(anonymous.clone())?.is_some());
+
+    rx.set_ignore_anonymous(heartbeat_subject, true);
+    assert!(rx.accept(anonymous.clone())?.is_none());
+    assert!(rx.accept(identified)?.is_some());
+    assert_eq!(Some(1), rx.ignored_anonymous_messages(heartbeat_subject));
+    assert_eq!(0, rx.error_count());
+
+    rx.set_ignore_anonymous(heartbeat_subject, false);
+    assert!(rx.accept(anonymous)?.is_some());
+    assert_eq!(Some(1), rx.ignored_anonymous_messages(heartbeat_subject));
+    Ok(())
+}
+
+#[test]
+fn test_gap_detection_expiry() -> Result<(), OutOfMemoryError> {
+    let mut rx = Receiver::new(0.try_into().unwrap(), Mtu::Can8);
+    let heartbeat_subject = SubjectId::try_from(7509).unwrap();
+    let source = NodeId::try_from(42).unwrap();
+    rx.subscribe_message(heartbeat_subject, 7, duration(1000))?;
+    rx.enable_gap_detection(heartbeat_subject)?;
+
+    // The source stops sending for longer than the timeout, and restarts with transfer ID 0
+    for &(time, transfer_id) in [(0u32, 10u8), (500, 11), (5000, 0), (5500, 2)].iter() {
+        let transfer = rx.accept(Frame::new(
+            instant(time),
+            0x107d552a.try_into().unwrap(),
+            &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0 | transfer_id],
+        ))?;
+        assert!(transfer.is_some());
+    }
+    // Only transfer 1 after the restart was missed
+    assert_eq!(Some(1), rx.missed_transfers(heartbeat_subject, source));
+    Ok(())
+}
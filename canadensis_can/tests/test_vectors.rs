@@ -0,0 +1,103 @@
+//!
+//! Checks that the transmitter and receiver agree with the reference test vectors
+//!
+
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+
+use canadensis_can::queue::{ArrayQueue, FrameQueueSource};
+use canadensis_can::test_vectors::test_vectors;
+use canadensis_can::{Receiver, Transmitter};
+use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+use canadensis_core::transfer::{Header, MessageHeader, ServiceHeader};
+use canadensis_data_types::uavcan::node::health::Health;
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_data_types::uavcan::node::mode::Mode;
+use canadensis_encoding::Serialize;
+
+#[test]
+fn test_vectors_transmit() {
+    for vector in test_vectors() {
+        let mut tx = Transmitter::new(vector.mtu, ArrayQueue::<(), 8>::new());
+        tx.push(vector.transfer()).unwrap();
+        for expected in vector.frames(()) {
+            assert_eq!(
+                tx.frame_queue_mut().pop_frame(),
+                Some(expected),
+                "{}",
+                vector.name
+            );
+        }
+        assert_eq!(tx.frame_queue_mut().pop_frame(), None, "{}", vector.name);
+    }
+}
+
+#[test]
+fn test_vectors_receive() {
+    for vector in test_vectors() {
+        let mut rx = Receiver::new_anonymous(vector.mtu);
+        rx.enable_promiscuous(vector.payload.len(), MicrosecondDuration32::new(1000));
+        let timestamp = Microseconds32::new(10);
+        let mut frames = vector.frames(timestamp);
+        let last = frames.pop().unwrap();
+        for frame in frames {
+            assert_eq!(rx.accept(frame).unwrap(), None, "{}", vector.name);
+        }
+        let transfer = rx.accept(last).unwrap().expect(vector.name);
+
+        assert_eq!(
+            transfer.header,
+            with_timestamp(&vector.header, timestamp),
+            "{}",
+            vector.name
+        );
+        assert_eq!(transfer.payload, vector.payload, "{}", vector.name);
+    }
+}
+
+fn with_timestamp(header: &Header<()>, timestamp: Microseconds32) -> Header<Microseconds32> {
+    match header.clone() {
+        Header::Message(header) => Header::Message(MessageHeader {
+            timestamp,
+            transfer_id: header.transfer_id,
+            priority: header.priority,
+            subject: header.subject,
+            source: header.source,
+        }),
+        Header::Request(header) => Header::Request(with_service_timestamp(header, timestamp)),
+        Header::Response(header) => Header::Response(with_service_timestamp(header, timestamp)),
+    }
+}
+
+fn with_service_timestamp(
+    header: ServiceHeader<()>,
+    timestamp: Microseconds32,
+) -> ServiceHeader<Microseconds32> {
+    ServiceHeader {
+        timestamp,
+        transfer_id: header.transfer_id,
+        priority: header.priority,
+        service: header.service,
+        source: header.source,
+        destination: header.destination,
+    }
+}
+
+#[test]
+fn test_heartbeat_serialization() {
+    let heartbeat = Heartbeat {
+        uptime: 258,
+        health: Health::Advisory,
+        mode: Mode::Maintenance,
+        vendor_specific_status_code: 0xab,
+    };
+    let mut bytes = [0u8; 7];
+    heartbeat.serialize_to_bytes(&mut bytes);
+    let vector = test_vectors()
+        .into_iter()
+        .find(|vector| vector.name == "heartbeat")
+        .unwrap();
+    assert_eq!(&bytes[..], vector.payload);
+}
@@ -7,8 +7,8 @@ extern crate canadensis_core;
 
 use core::convert::TryFrom;
 
-use canadensis_can::queue::{ArrayQueue, FrameQueueSource};
-use canadensis_can::{CanId, Frame, Mtu, Transmitter};
+use canadensis_can::queue::{ArrayQueue, FrameQueueSource, FrameSink, HeapQueue, SharedQueue};
+use canadensis_can::{CanId, ChunkedTransfer, Frame, Mtu, Transmitter};
 use canadensis_core::time::Microseconds32;
 use canadensis_core::transfer::*;
 use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
@@ -221,3 +221,222 @@ fn test_array() {
     }
     assert_eq!(None, tx.frame_queue_mut().pop_frame());
 }
+
+#[test]
+fn test_dyn_frame_sink() {
+    let mut queue = TestQueue::new();
+    {
+        let sink: &mut dyn FrameSink<Microseconds32> = &mut queue;
+        let mut tx = Transmitter::new(Mtu::Can8, sink);
+        tx.push(Transfer {
+            header: Header::Message(MessageHeader {
+                timestamp: instant(0),
+                transfer_id: TransferId::try_from(0).unwrap(),
+                priority: Priority::Nominal,
+                subject: SubjectId::try_from(7509).unwrap(),
+                source: Some(NodeId::try_from(42).unwrap()),
+            }),
+            payload: &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68],
+        })
+        .unwrap();
+    }
+    assert_eq!(
+        Some(Frame::new(
+            instant(0),
+            CanId::try_from(0x107d552a).unwrap(),
+            &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0]
+        )),
+        queue.pop_frame()
+    );
+    assert_eq!(None, queue.pop_frame());
+}
+
+#[test]
+fn test_return_frame_when_full() {
+    check_return_frame_when_full(ArrayQueue::<Microseconds32, 3>::new());
+    check_return_frame_when_full(HeapQueue::with_capacity(3));
+}
+
+/// Fills a queue with one three-frame transfer, then checks that a driver can pop each frame and
+/// return it without losing or reordering anything
+fn check_return_frame_when_full<Q>(queue: Q)
+where
+    Q: FrameSink<Microseconds32> + FrameQueueSource<Microseconds32>,
+{
+    let mut tx = Transmitter::new(Mtu::Can8, queue);
+    tx.push(Transfer {
+        header: Header::Message(MessageHeader {
+            timestamp: instant(0),
+            transfer_id: TransferId::try_from(0).unwrap(),
+            priority: Priority::Nominal,
+            subject: SubjectId::try_from(4919).unwrap(),
+            source: Some(NodeId::try_from(59).unwrap()),
+        }),
+        payload: &[
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+        ],
+    })
+    .unwrap();
+    let queue = tx.frame_queue_mut();
+
+    let first = queue.pop_frame().unwrap();
+    // The transmit mailbox is busy, so the driver puts the frame back
+    queue.return_frame(first.clone()).unwrap();
+    assert_eq!(Some(&first), queue.peek_frame());
+
+    let first = queue.pop_frame().unwrap();
+    let second = queue.pop_frame().unwrap();
+    // The driver returns frames in reverse order
+    queue.return_frame(second.clone()).unwrap();
+    queue.return_frame(first.clone()).unwrap();
+
+    assert_eq!(Some(first), queue.pop_frame());
+    assert_eq!(Some(second), queue.pop_frame());
+    assert_eq!(&[0x78, 0xcb, 0x60], queue.pop_frame().unwrap().data());
+    assert_eq!(None, queue.pop_frame());
+}
+
+#[test]
+fn test_shared_queue_threads() {
+    let shared = SharedQueue::new(HeapQueue::new());
+    std::thread::scope(|scope| {
+        for source in 1..=4u8 {
+            let sink = shared.sink();
+            scope.spawn(move || {
+                let mut tx = Transmitter::new(Mtu::Can8, sink);
+                for transfer_id in 0..8 {
+                    tx.push(Transfer {
+                        header: Header::Message(MessageHeader {
+                            timestamp: instant(0),
+                            transfer_id: TransferId::try_from(transfer_id).unwrap(),
+                            priority: Priority::Nominal,
+                            subject: SubjectId::try_from(4919).unwrap(),
+                            source: Some(NodeId::try_from(source).unwrap()),
+                        }),
+                        // Three frames
+                        payload: &[0; 14],
+                    })
+                    .unwrap();
+                }
+            });
+        }
+    });
+    let mut queue = shared.into_inner();
+    let mut frames = 0;
+    let mut last_id = 0;
+    while let Some(frame) = queue.pop_frame() {
+        let id = u32::from(frame.id());
+        // Frames from lower node IDs have higher priority
+        assert!(id >= last_id);
+        last_id = id;
+        frames += 1;
+    }
+    assert_eq!(frames, 4 * 8 * 3);
+}
+
+#[test]
+#[cfg(feature = "can-fd")]
+fn test_mixed_mtu_redundant() {
+    use canadensis_can::redundant::{MtuQueue, RedundantQueue};
+
+    let queue = RedundantQueue::new(
+        MtuQueue::new(Mtu::Can8, TestQueue::new()),
+        MtuQueue::new(Mtu::CanFd64, TestQueue::new()),
+    );
+    let mut tx = Transmitter::new(Mtu::Can8, queue);
+    let payload = [0x55u8; 30];
+    tx.push(Transfer {
+        header: Header::Message(MessageHeader {
+            timestamp: instant(0),
+            transfer_id: TransferId::try_from(0).unwrap(),
+            priority: Priority::Nominal,
+            subject: SubjectId::try_from(7509).unwrap(),
+            source: Some(NodeId::try_from(42).unwrap()),
+        }),
+        payload: &payload[..],
+    })
+    .unwrap();
+
+    // Classic CAN: 30 bytes of payload + 2 bytes of CRC, 7 bytes per frame
+    let classic = tx.frame_queue_mut().queue_0_mut();
+    let mut classic_frames = 0;
+    while let Some(frame) = classic.pop_frame() {
+        assert!(frame.data().len() <= 8);
+        classic_frames += 1;
+    }
+    assert_eq!(5, classic_frames);
+
+    // CAN FD: 30 bytes of payload + 1 tail byte, padded to 32 bytes
+    let fd = tx.frame_queue_mut().queue_1_mut();
+    let frame = fd.pop_frame().expect("No CAN FD frame");
+    assert_eq!(32, frame.data().len());
+    assert_eq!(&payload[..], &frame.data()[..30]);
+    assert!(fd.pop_frame().is_none());
+}
+
+#[test]
+fn test_chunked_matches_push() {
+    #[cfg(feature = "can-fd")]
+    let mtus = [Mtu::Can8, Mtu::CanFd64];
+    #[cfg(not(feature = "can-fd"))]
+    let mtus = [Mtu::Can8];
+    let payload: Vec<u8> = (0..=200u8).collect();
+    for &mtu in mtus.iter() {
+        for length in 0..payload.len() {
+            let transfer = Transfer {
+                header: Header::Message(MessageHeader {
+                    timestamp: instant(0),
+                    transfer_id: TransferId::try_from(3).unwrap(),
+                    priority: Priority::Nominal,
+                    subject: SubjectId::try_from(1000).unwrap(),
+                    source: Some(NodeId::try_from(42).unwrap()),
+                }),
+                payload: &payload[..length],
+            };
+            let mut expected = Transmitter::new(mtu, TestQueue::new());
+            expected.push(transfer.clone()).unwrap();
+
+            let mut tx = Transmitter::new(mtu, TestQueue::new());
+            let mut chunked = ChunkedTransfer::new(mtu, transfer);
+            let mut last_queued = 0;
+            while !chunked.is_finished() {
+                assert_eq!(1, tx.push_chunk(&mut chunked, 1).unwrap());
+                assert!(chunked.bytes_queued() >= last_queued);
+                last_queued = chunked.bytes_queued();
+            }
+            assert_eq!(length, chunked.bytes_queued());
+            assert_eq!(0, tx.push_chunk(&mut chunked, 1).unwrap());
+            assert_eq!(1, tx.transfer_count());
+
+            while let Some(expected_frame) = expected.frame_queue_mut().pop_frame() {
+                assert_eq!(Some(expected_frame), tx.frame_queue_mut().pop_frame());
+            }
+            assert_eq!(None, tx.frame_queue_mut().pop_frame());
+        }
+    }
+}
+
+#[test]
+fn test_chunked_queue_full() {
+    let payload = [0u8; 100];
+    let transfer = Transfer {
+        header: Header::Message(MessageHeader {
+            timestamp: instant(0),
+            transfer_id: TransferId::try_from(0).unwrap(),
+            priority: Priority::Nominal,
+            subject: SubjectId::try_from(1000).unwrap(),
+            source: Some(NodeId::try_from(42).unwrap()),
+        }),
+        payload: &payload[..],
+    };
+    let mut tx = Transmitter::new(Mtu::Can8, ArrayQueue::<Microseconds32, 4>::new());
+    let mut chunked = ChunkedTransfer::new(Mtu::Can8, transfer);
+    assert_eq!(15, chunked.frames());
+    assert_eq!(4, tx.push_chunk(&mut chunked, 4).unwrap());
+    assert_eq!(28, chunked.bytes_queued());
+    assert!(tx.push_chunk(&mut chunked, 1).is_err());
+    assert_eq!(11, chunked.frames_remaining());
+    while tx.frame_queue_mut().pop_frame().is_some() {}
+    assert_eq!(4, tx.push_chunk(&mut chunked, 4).unwrap());
+    assert_eq!(0, tx.transfer_count());
+}
@@ -1,14 +1,22 @@
 #![cfg_attr(not(test), no_std)]
+// hash32_derive generates its impls inside an anonymous const
+#![allow(non_local_definitions)]
 
 //!
 //! This library provides types used by other canadensis crates.
 //!
 
+#[cfg(feature = "embedded-time")]
+extern crate embedded_time;
+#[cfg(feature = "fugit")]
+extern crate fugit;
 extern crate hash32;
 extern crate hash32_derive;
 
 pub mod time;
 pub mod transfer;
+pub mod transfer_id_tracker;
+pub mod unique_id;
 
 use core::convert::TryFrom;
 use core::fmt;
@@ -119,7 +127,7 @@ impl From<ServiceId> for usize {
 }
 
 /// A value that can represent a service ID (0..=511) or a subject ID (0..=8192)
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Hash32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Hash32)]
 pub struct PortId(u16);
 
 impl From<SubjectId> for PortId {
@@ -154,7 +162,7 @@ const VALID_NODE_IDS: RangeInclusive<u8> = 0..=127;
 ///
 /// Valid node IDs are in the range 0..=127 (7 bits). IDs 126 and 127 are reserved for diagnostic
 /// and debugging tools.
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Hash32)]
 pub struct NodeId(u8);
 
 impl NodeId {
@@ -294,7 +302,15 @@ impl Default for TransferId {
 }
 
 /// Transfer priority level mnemonics per the recommendations given in the UAVCAN Specification
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+///
+/// Priorities are ordered like CAN arbitration: a priority that compares less than another
+/// priority is more urgent, and its frames win arbitration. `Priority::Exceptional` is the
+/// minimum and `Priority::Optional` is the maximum.
+///
+/// The [`Display`](fmt::Display) and [`FromStr`] implementations use the lowercase level names
+/// from the specification (`exceptional`, `immediate`, `fast`, `high`, `nominal`, `low`, `slow`,
+/// and `optional`). Parsing also accepts uppercase letters and the numeric values 0 through 7.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Priority {
     Exceptional = 0,
     Immediate = 1,
@@ -336,3 +352,84 @@ impl TryFrom<u8> for Priority {
         }
     }
 }
+
+impl Priority {
+    /// All priority levels, from most urgent to least urgent
+    pub const ALL: [Priority; 8] = [
+        Priority::Exceptional,
+        Priority::Immediate,
+        Priority::Fast,
+        Priority::High,
+        Priority::Nominal,
+        Priority::Low,
+        Priority::Slow,
+        Priority::Optional,
+    ];
+
+    /// Returns the lowercase name of this priority level
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Exceptional => "exceptional",
+            Priority::Immediate => "immediate",
+            Priority::Fast => "fast",
+            Priority::High => "high",
+            Priority::Nominal => "nominal",
+            Priority::Low => "low",
+            Priority::Slow => "slow",
+            Priority::Optional => "optional",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Priority {
+    type Err = InvalidValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(priority) = Priority::ALL
+            .iter()
+            .find(|priority| priority.name().eq_ignore_ascii_case(s))
+        {
+            return Ok(*priority);
+        }
+        let value: u8 = s.parse().map_err(|_| InvalidValue)?;
+        Priority::try_from(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn priority_order() {
+        assert!(Priority::Exceptional < Priority::Immediate);
+        assert!(Priority::Slow < Priority::Optional);
+        for pair in Priority::ALL.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(u8::from(pair[0]) < u8::from(pair[1]));
+        }
+    }
+
+    #[test]
+    fn priority_names() {
+        for priority in Priority::ALL.iter() {
+            let name = priority.to_string();
+            assert_eq!(Some(*priority), name.parse().ok());
+            assert_eq!(Some(*priority), name.to_uppercase().parse().ok());
+            assert_eq!(
+                Some(*priority),
+                u8::from(*priority).to_string().parse().ok()
+            );
+        }
+        assert_eq!("nominal", Priority::Nominal.to_string());
+        assert!("8".parse::<Priority>().is_err());
+        assert!("urgent".parse::<Priority>().is_err());
+        assert!(Priority::try_from(8).is_err());
+    }
+}
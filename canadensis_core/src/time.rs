@@ -1,5 +1,9 @@
 //! Instant and duration definitions
 
+#[cfg(feature = "embedded-time")]
+pub mod embedded_time;
+#[cfg(feature = "fugit")]
+pub mod fugit;
 pub mod u48;
 
 use crate::time::u48::U48;
@@ -111,7 +115,8 @@ impl Add<Microseconds32> for MicrosecondDuration32 {
     type Output = Microseconds32;
 
     fn add(self, rhs: Microseconds32) -> Self::Output {
-        Microseconds32(self.0 + rhs.0)
+        // Instants wrap around after about 1 hour
+        Microseconds32(self.0.wrapping_add(rhs.0))
     }
 }
 
@@ -359,6 +364,89 @@ pub trait Clock {
     fn now(&mut self) -> Self::Instant;
 }
 
+/// A clock whose time changes only when it is set or advanced
+///
+/// This is useful for tests and simulations that need to control exactly when timeouts and
+/// periodic tasks happen. Because a node owns its clock, tests usually advance the time through
+/// `Node::clock_mut()`.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock<I> {
+    now: I,
+}
+
+impl<I: Instant> ManualClock<I> {
+    /// Creates a clock that starts at the provided time
+    pub fn new(start: I) -> Self {
+        ManualClock { now: start }
+    }
+
+    /// Sets the current time
+    pub fn set(&mut self, now: I) {
+        self.now = now;
+    }
+
+    /// Advances the current time by a duration
+    pub fn advance(&mut self, duration: I::Duration) {
+        self.now = duration + self.now;
+    }
+
+    /// Advances the current time by a number of milliseconds
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the duration type cannot represent the provided number of
+    /// milliseconds.
+    pub fn advance_millis(&mut self, millis: u32) {
+        self.advance(milliseconds(millis));
+    }
+}
+
+impl<I: Instant> Clock for ManualClock<I> {
+    type Instant = I;
+
+    fn now(&mut self) -> Self::Instant {
+        self.now
+    }
+}
+
+/// Decides when to run a task that should happen at a regular interval
+///
+/// A timer can be used with any clock, but with a [`ManualClock`] it makes periodic tasks
+/// (like `run_per_second_tasks`) run at predictable times.
+///
+/// If the timer is polled less often than its period, it runs the task once for each missed
+/// period so that the average rate stays correct.
+#[derive(Debug, Clone)]
+pub struct PeriodicTimer<I: Instant> {
+    period: I::Duration,
+    next: I,
+}
+
+impl<I: Instant> PeriodicTimer<I> {
+    /// Creates a timer that first expires at start + period
+    pub fn new(start: I, period: I::Duration) -> Self {
+        PeriodicTimer {
+            period,
+            next: period + start,
+        }
+    }
+
+    /// Returns true and schedules the next expiration if the timer has expired at time now
+    pub fn poll(&mut self, now: I) -> bool {
+        if now.overflow_safe_compare(&self.next) == Ordering::Less {
+            false
+        } else {
+            self.next = self.period + self.next;
+            true
+        }
+    }
+
+    /// Returns the time when the timer will next expire
+    pub fn next_expiration(&self) -> I {
+        self.next
+    }
+}
+
 /// Creates a duration from a number of milliseconds
 ///
 /// This is a convenient wrapper for T::from_millis() when T has a long-difficult-to-type name.
@@ -382,3 +470,55 @@ where
 {
     T::from_millis(milliseconds)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Clock, Instant, ManualClock, MicrosecondDuration32, Microseconds32, PeriodicTimer,
+    };
+    use core::cmp::Ordering;
+
+    #[test]
+    fn manual_clock_advance() {
+        let mut clock = ManualClock::new(Microseconds32::new(10));
+        assert_eq!(clock.now(), Microseconds32::new(10));
+        clock.advance(MicrosecondDuration32::new(10));
+        assert_eq!(clock.now(), Microseconds32::new(20));
+        clock.advance_millis(1);
+        assert_eq!(clock.now(), Microseconds32::new(1020));
+        clock.set(Microseconds32::new(5));
+        assert_eq!(clock.now(), Microseconds32::new(5));
+    }
+
+    #[test]
+    fn instant_32_wraps() {
+        let start = Microseconds32::new(u32::MAX - 9);
+        let later = MicrosecondDuration32::new(20) + start;
+        assert_eq!(later, Microseconds32::new(10));
+        assert_eq!(later.duration_since(&start), MicrosecondDuration32::new(20));
+        assert_eq!(later.overflow_safe_compare(&start), Ordering::Greater);
+    }
+
+    #[test]
+    fn periodic_timer() {
+        let mut clock = ManualClock::new(Microseconds32::new(0));
+        let mut timer = PeriodicTimer::new(clock.now(), MicrosecondDuration32::new(1000));
+        let mut runs = 0;
+        for _ in 0..35 {
+            clock.advance(MicrosecondDuration32::new(100));
+            if timer.poll(clock.now()) {
+                runs += 1;
+            }
+        }
+        assert_eq!(runs, 3);
+        assert_eq!(timer.next_expiration(), Microseconds32::new(4000));
+
+        // Catch up after a long gap
+        clock.advance(MicrosecondDuration32::new(3000));
+        let mut runs = 0;
+        while timer.poll(clock.now()) {
+            runs += 1;
+        }
+        assert_eq!(runs, 3);
+    }
+}
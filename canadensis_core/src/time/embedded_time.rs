@@ -0,0 +1,131 @@
+//!
+//! Conversions between canadensis time types and the `embedded-time` crate
+//!
+//! Microsecond durations convert exactly in both directions. An [`EmbeddedTimeClock`] turns any
+//! [`embedded_time::Clock`] into a [`Clock`] that produces [`Microseconds64`] instants.
+//!
+
+use embedded_time::duration::Microseconds;
+
+use crate::time::{Clock, MicrosecondDuration32, MicrosecondDuration64, Microseconds64};
+
+impl From<Microseconds<u32>> for MicrosecondDuration32 {
+    fn from(duration: Microseconds<u32>) -> Self {
+        MicrosecondDuration32::new(duration.0)
+    }
+}
+
+impl From<MicrosecondDuration32> for Microseconds<u32> {
+    fn from(duration: MicrosecondDuration32) -> Self {
+        Microseconds(duration.as_microseconds())
+    }
+}
+
+impl From<Microseconds<u64>> for MicrosecondDuration64 {
+    fn from(duration: Microseconds<u64>) -> Self {
+        MicrosecondDuration64::new(duration.0)
+    }
+}
+
+impl From<MicrosecondDuration64> for Microseconds<u64> {
+    fn from(duration: MicrosecondDuration64) -> Self {
+        Microseconds(duration.as_microseconds())
+    }
+}
+
+/// A [`Clock`] that gets the current time from an `embedded-time` clock
+///
+/// The tick count of the enclosed clock is converted into microseconds, rounding down.
+///
+/// The microsecond values do not overflow when the tick count of the enclosed clock overflows.
+/// If the enclosed clock has a 32-bit tick count that may overflow, this clock will appear to
+/// go backwards.
+///
+/// # Panics
+///
+/// [`now`](Clock::now) panics if the enclosed clock returns an error.
+#[derive(Debug, Clone)]
+pub struct EmbeddedTimeClock<C> {
+    clock: C,
+}
+
+impl<C> EmbeddedTimeClock<C> {
+    /// Creates a clock that gets the current time from an `embedded-time` clock
+    pub fn new(clock: C) -> Self {
+        EmbeddedTimeClock { clock }
+    }
+
+    /// Returns a reference to the enclosed clock
+    pub fn inner(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the enclosed clock
+    pub fn into_inner(self) -> C {
+        self.clock
+    }
+}
+
+impl<C> Clock for EmbeddedTimeClock<C>
+where
+    C: embedded_time::Clock,
+    C::T: Into<u64>,
+{
+    type Instant = Microseconds64;
+
+    fn now(&mut self) -> Self::Instant {
+        let now = self
+            .clock
+            .try_now()
+            .expect("Failed to get the current time");
+        let ticks: u64 = now.duration_since_epoch().integer().into();
+        let microseconds =
+            u128::from(ticks) * u128::from(*C::SCALING_FACTOR.numerator()) * 1_000_000
+                / u128::from(*C::SCALING_FACTOR.denominator());
+        Microseconds64::new(microseconds as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EmbeddedTimeClock;
+    use crate::time::{Clock, MicrosecondDuration32, Microseconds64};
+    use core::cell::Cell;
+    use embedded_time::duration::Microseconds;
+    use embedded_time::fraction::Fraction;
+
+    /// A clock that counts at 32768 Hz
+    struct TestClock {
+        ticks: Cell<u32>,
+    }
+
+    impl embedded_time::Clock for TestClock {
+        type T = u32;
+        const SCALING_FACTOR: Fraction = Fraction::new(1, 32768);
+
+        fn try_now(&self) -> Result<embedded_time::Instant<Self>, embedded_time::clock::Error> {
+            Ok(embedded_time::Instant::new(self.ticks.get()))
+        }
+    }
+
+    #[test]
+    fn duration_round_trip() {
+        let duration = MicrosecondDuration32::new(99);
+        assert_eq!(Microseconds(99u32), Microseconds::<u32>::from(duration));
+        assert_eq!(duration, MicrosecondDuration32::from(Microseconds(99u32)));
+    }
+
+    #[test]
+    fn clock_scaling() {
+        let mut clock = EmbeddedTimeClock::new(TestClock {
+            ticks: Cell::new(32768 * 3 + 1),
+        });
+        // 1/32768 second is about 30.5 microseconds
+        assert_eq!(Microseconds64::new(3_000_030), clock.now());
+        clock.inner().ticks.set(u32::MAX);
+        assert_eq!(
+            Microseconds64::new(u64::from(u32::MAX) * 1_000_000 / 32768),
+            clock.now()
+        );
+    }
+}
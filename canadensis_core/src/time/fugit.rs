@@ -0,0 +1,160 @@
+//!
+//! Conversions between canadensis time types and the `fugit` crate
+//!
+//! Durations and 1 MHz instants convert exactly in both directions. 64-bit instants from a
+//! clock with any tick rate can be converted into [`Microseconds64`].
+//!
+//! A [`FugitClock`] turns a function that returns a `fugit` instant, like the `now` function of
+//! a HAL monotonic timer, into a [`Clock`].
+//!
+
+use core::marker::PhantomData;
+
+use fugit::{MicrosDurationU32, MicrosDurationU64, TimerInstantU32, TimerInstantU64};
+
+use crate::time::{
+    Clock, Instant, MicrosecondDuration32, MicrosecondDuration64, Microseconds32, Microseconds64,
+};
+
+impl From<MicrosDurationU32> for MicrosecondDuration32 {
+    fn from(duration: MicrosDurationU32) -> Self {
+        MicrosecondDuration32::new(duration.ticks())
+    }
+}
+
+impl From<MicrosecondDuration32> for MicrosDurationU32 {
+    fn from(duration: MicrosecondDuration32) -> Self {
+        MicrosDurationU32::from_ticks(duration.as_microseconds())
+    }
+}
+
+impl From<MicrosDurationU64> for MicrosecondDuration64 {
+    fn from(duration: MicrosDurationU64) -> Self {
+        MicrosecondDuration64::new(duration.ticks())
+    }
+}
+
+impl From<MicrosecondDuration64> for MicrosDurationU64 {
+    fn from(duration: MicrosecondDuration64) -> Self {
+        MicrosDurationU64::from_ticks(duration.as_microseconds())
+    }
+}
+
+impl From<TimerInstantU32<1_000_000>> for Microseconds32 {
+    fn from(instant: TimerInstantU32<1_000_000>) -> Self {
+        Microseconds32::new(instant.ticks())
+    }
+}
+
+impl From<Microseconds32> for TimerInstantU32<1_000_000> {
+    fn from(instant: Microseconds32) -> Self {
+        TimerInstantU32::from_ticks(instant.as_microseconds())
+    }
+}
+
+/// Converts an instant from a clock with any tick rate, rounding down to a whole microsecond
+impl<const NOM: u32, const DENOM: u32> From<fugit::Instant<u64, NOM, DENOM>> for Microseconds64 {
+    fn from(instant: fugit::Instant<u64, NOM, DENOM>) -> Self {
+        let microseconds =
+            u128::from(instant.ticks()) * u128::from(NOM) * 1_000_000 / u128::from(DENOM);
+        // Truncate if the clock has been running for more than half a million years
+        Microseconds64::new(microseconds as u64)
+    }
+}
+
+impl From<Microseconds64> for TimerInstantU64<1_000_000> {
+    fn from(instant: Microseconds64) -> Self {
+        TimerInstantU64::from_ticks(instant.as_microseconds())
+    }
+}
+
+/// A clock that gets the current time from a function that returns a `fugit` instant
+///
+/// `I` is the type of instant that this clock produces, usually [`Microseconds32`] for a 32-bit
+/// 1 MHz timer or [`Microseconds64`] for a 64-bit timer.
+///
+/// # Examples
+///
+/// ```
+/// # use canadensis_core::time::fugit::FugitClock;
+/// # use canadensis_core::time::{Clock, Microseconds64};
+/// # use fugit::TimerInstantU64;
+/// // A 64-bit timer that counts at 32768 Hz
+/// let mut ticks = 0;
+/// let mut clock = FugitClock::<_, Microseconds64>::new(move || {
+///     ticks += 32768;
+///     TimerInstantU64::<32768>::from_ticks(ticks)
+/// });
+/// assert_eq!(Microseconds64::new(1_000_000), clock.now());
+/// ```
+pub struct FugitClock<F, I> {
+    now: F,
+    _instant: PhantomData<I>,
+}
+
+impl<F, I> FugitClock<F, I> {
+    /// Creates a clock that calls `now` to get the current time
+    pub fn new(now: F) -> Self {
+        FugitClock {
+            now,
+            _instant: PhantomData,
+        }
+    }
+
+    /// Returns the enclosed function
+    pub fn into_inner(self) -> F {
+        self.now
+    }
+}
+
+impl<F, T, I> Clock for FugitClock<F, I>
+where
+    F: FnMut() -> T,
+    I: Instant + From<T>,
+{
+    type Instant = I;
+
+    fn now(&mut self) -> Self::Instant {
+        I::from((self.now)())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FugitClock;
+    use crate::time::{Clock, MicrosecondDuration32, Microseconds32, Microseconds64};
+    use fugit::{MicrosDurationU32, TimerInstantU32, TimerInstantU64};
+
+    #[test]
+    fn round_trip_32() {
+        let duration = MicrosDurationU32::from_ticks(1234);
+        assert_eq!(
+            MicrosecondDuration32::new(1234),
+            MicrosecondDuration32::from(duration)
+        );
+        let instant = Microseconds32::new(u32::MAX);
+        assert_eq!(
+            instant,
+            Microseconds32::from(TimerInstantU32::<1_000_000>::from(instant))
+        );
+    }
+
+    #[test]
+    fn instant_64_scaling() {
+        // 10 ticks at 3 MHz is 3 microseconds, rounded down
+        let instant = TimerInstantU64::<3_000_000>::from_ticks(10);
+        assert_eq!(Microseconds64::new(3), Microseconds64::from(instant));
+        let instant = TimerInstantU64::<1_000>::from_ticks(u64::from(u32::MAX) + 1);
+        assert_eq!(
+            Microseconds64::new((u64::from(u32::MAX) + 1) * 1000),
+            Microseconds64::from(instant)
+        );
+    }
+
+    #[test]
+    fn clock_32() {
+        let mut clock =
+            FugitClock::<_, Microseconds32>::new(|| TimerInstantU32::<1_000_000>::from_ticks(77));
+        assert_eq!(Microseconds32::new(77), clock.now());
+    }
+}
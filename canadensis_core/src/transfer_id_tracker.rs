@@ -0,0 +1,193 @@
+//!
+//! Transfer ID sequencing for outgoing transfers
+//!
+//! Each outgoing message subject, and each combination of service and destination node for
+//! outgoing requests, has its own sequence of transfer IDs. The node layer tracks these
+//! internally. Code that uses a transmitter directly can use a [`TransferIdTracker`] to get the
+//! same sequencing.
+//!
+
+use crate::{NodeId, ServiceId, SubjectId, TransferId};
+
+/// A port, and a destination if applicable, that has its own sequence of transfer IDs
+///
+/// Responses do not have their own sequences because a response uses the transfer ID
+/// of the corresponding request.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TransferIdKey {
+    /// Messages published on a subject
+    Message(SubjectId),
+    /// Requests sent to a destination node
+    Request(ServiceId, NodeId),
+}
+
+/// An error indicating that a tracker has no space for another key
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TrackerFullError;
+
+/// Keeps track of the next transfer ID for up to `N` subjects or service/destination pairs
+///
+/// A key that the tracker has not seen starts with the default transfer ID. Transfer IDs wrap
+/// around after reaching the maximum value.
+#[derive(Debug, Clone)]
+pub struct TransferIdTracker<const N: usize> {
+    /// Keys and the next transfer IDs to use
+    entries: [Option<(TransferIdKey, TransferId)>; N],
+}
+
+impl<const N: usize> TransferIdTracker<N> {
+    /// Creates a tracker with no keys
+    pub const fn new() -> Self {
+        TransferIdTracker { entries: [None; N] }
+    }
+
+    /// Returns the transfer ID to use for the next transfer with a key, and increments the
+    /// stored transfer ID
+    ///
+    /// This function returns an error if the tracker does not already contain the key and
+    /// has no space to add it.
+    pub fn next(&mut self, key: TransferIdKey) -> Result<TransferId, TrackerFullError> {
+        let entry = self.entry(key)?;
+        let current = *entry;
+        *entry = current.increment();
+        Ok(current)
+    }
+
+    /// Returns the transfer ID to use for the next message on a subject, and increments the
+    /// stored transfer ID
+    pub fn next_message(&mut self, subject: SubjectId) -> Result<TransferId, TrackerFullError> {
+        self.next(TransferIdKey::Message(subject))
+    }
+
+    /// Returns the transfer ID to use for the next request to a destination node, and
+    /// increments the stored transfer ID
+    pub fn next_request(
+        &mut self,
+        service: ServiceId,
+        destination: NodeId,
+    ) -> Result<TransferId, TrackerFullError> {
+        self.next(TransferIdKey::Request(service, destination))
+    }
+
+    /// Returns the transfer ID that the next transfer with a key will use, without incrementing
+    /// it
+    pub fn peek(&self, key: TransferIdKey) -> TransferId {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|(entry_key, _)| *entry_key == key)
+            .map(|(_, transfer_id)| *transfer_id)
+            .unwrap_or_default()
+    }
+
+    /// Sets the transfer ID that the next transfer with a key will use
+    ///
+    /// This can be used to restore transfer IDs after a restart.
+    pub fn set(
+        &mut self,
+        key: TransferIdKey,
+        transfer_id: TransferId,
+    ) -> Result<(), TrackerFullError> {
+        *self.entry(key)? = transfer_id;
+        Ok(())
+    }
+
+    /// Removes a key, so that its next transfer will use the default transfer ID
+    pub fn remove(&mut self, key: TransferIdKey) {
+        for entry in self.entries.iter_mut() {
+            if matches!(entry, Some((entry_key, _)) if *entry_key == key) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Removes all keys
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+    }
+
+    /// Returns the number of keys in this tracker
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    /// Returns true if this tracker has no keys
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    /// Returns an iterator over the keys in this tracker and the next transfer ID for each key
+    pub fn iter(&self) -> impl Iterator<Item = (TransferIdKey, TransferId)> + '_ {
+        self.entries.iter().flatten().copied()
+    }
+
+    /// Returns a reference to the transfer ID for a key, adding the key if necessary
+    fn entry(&mut self, key: TransferIdKey) -> Result<&mut TransferId, TrackerFullError> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Some((entry_key, _)) if *entry_key == key))
+            .or_else(|| self.entries.iter().position(Option::is_none))
+            .ok_or(TrackerFullError)?;
+        Ok(&mut self.entries[index]
+            .get_or_insert((key, TransferId::default()))
+            .1)
+    }
+}
+
+impl<const N: usize> Default for TransferIdTracker<N> {
+    fn default() -> Self {
+        TransferIdTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TrackerFullError, TransferIdKey, TransferIdTracker};
+    use crate::{NodeId, ServiceId, SubjectId, TransferId};
+    use core::convert::TryFrom;
+
+    fn id(value: u8) -> TransferId {
+        TransferId::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn message_sequence_wraps() {
+        let mut tracker = TransferIdTracker::<1>::new();
+        let subject = SubjectId::from_truncating(7509);
+        for expected in (0..=31).chain(0..=1) {
+            assert_eq!(Ok(id(expected)), tracker.next_message(subject));
+        }
+    }
+
+    #[test]
+    fn keys_are_independent() {
+        let mut tracker = TransferIdTracker::<3>::new();
+        let subject = SubjectId::from_truncating(430);
+        let service = ServiceId::from_truncating(430);
+        let node_a = NodeId::from_truncating(1);
+        let node_b = NodeId::from_truncating(2);
+        assert_eq!(Ok(id(0)), tracker.next_message(subject));
+        assert_eq!(Ok(id(1)), tracker.next_message(subject));
+        assert_eq!(Ok(id(0)), tracker.next_request(service, node_a));
+        assert_eq!(Ok(id(0)), tracker.next_request(service, node_b));
+        assert_eq!(id(1), tracker.peek(TransferIdKey::Request(service, node_a)));
+        assert_eq!(3, tracker.len());
+    }
+
+    #[test]
+    fn full() {
+        let mut tracker = TransferIdTracker::<1>::new();
+        let first = TransferIdKey::Message(SubjectId::from_truncating(1));
+        let second = TransferIdKey::Message(SubjectId::from_truncating(2));
+        assert!(tracker.next(first).is_ok());
+        assert_eq!(Err(TrackerFullError), tracker.next(second));
+        // Peeking does not need space
+        assert_eq!(TransferId::default(), tracker.peek(second));
+
+        tracker.remove(first);
+        assert!(tracker.is_empty());
+        assert!(tracker.set(second, id(20)).is_ok());
+        assert_eq!(Ok(id(20)), tracker.next(second));
+    }
+}
@@ -0,0 +1,38 @@
+//!
+//! Sources of 128-bit node unique IDs
+//!
+//! Each node has a unique ID that it reports in `uavcan.node.GetInfo` responses and uses to
+//! request a node ID through plug-and-play allocation. The unique ID must not change when the
+//! node restarts, so that an allocator can give the node the same node ID every time. It should
+//! come from the hardware (like a microcontroller serial number) or from persistent storage,
+//! not from a random number generator.
+//!
+//! Hardware-specific implementations of [`UniqueIdProvider`] are in other crates, like
+//! `canadensis_bxcan` (STM32 unique device ID registers) and `canadensis_linux`
+//! (the systemd machine ID).
+//!
+
+/// Something that can provide the unique ID of a node
+pub trait UniqueIdProvider {
+    /// Returns the unique ID
+    ///
+    /// This function must return the same value every time it is called, including after
+    /// the node restarts.
+    fn unique_id(&self) -> [u8; 16];
+}
+
+/// A fixed unique ID, for example one that has been loaded from persistent storage
+impl UniqueIdProvider for [u8; 16] {
+    fn unique_id(&self) -> [u8; 16] {
+        *self
+    }
+}
+
+impl<P> UniqueIdProvider for &P
+where
+    P: UniqueIdProvider + ?Sized,
+{
+    fn unique_id(&self) -> [u8; 16] {
+        (**self).unique_id()
+    }
+}
@@ -76,7 +76,7 @@ impl<const BYTES: usize> BitArray<BYTES> {
     /// Deserializes this bit set (not including the length). self.bit_length must be set
     /// before this function is called.
     pub fn deserialize_in_place(&mut self, cursor: &mut ReadCursor<'_>) {
-        if self.bit_length % 8 == 0 && cursor.is_aligned_to_8_bits() {
+        if self.bit_length.is_multiple_of(8) && cursor.is_aligned_to_8_bits() {
             self.bytes.fill_with(|| cursor.read_aligned_u8());
         } else {
             for i in 0..self.bit_length {
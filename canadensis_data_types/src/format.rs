@@ -0,0 +1,255 @@
+//!
+//! Human-readable formatting of transfer payloads
+//!
+//! A [`PayloadRegistry`] maps subject and service IDs to functions that decode payloads.
+//! Bus monitors, command-line tools, and log decoders can use a registry to show any transfer
+//! as text, whether its data type is defined in Rust or described at run time.
+//!
+//! Payloads that have no registered decoder, or that fail to decode, are shown as hexadecimal
+//! bytes.
+//!
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use canadensis_core::transfer::Header;
+use canadensis_core::{ServiceId, SubjectId};
+use canadensis_encoding::{Deserialize, DeserializeError, Message, Request, Response};
+
+use crate::port_ids;
+use crate::uavcan::diagnostic::record::Record;
+use crate::uavcan::internet::udp::handle_incoming_packet::{
+    HandleIncomingPacketRequest, HandleIncomingPacketResponse,
+};
+use crate::uavcan::internet::udp::outgoing_packet::OutgoingPacket;
+use crate::uavcan::node::execute_command::{ExecuteCommandRequest, ExecuteCommandResponse};
+use crate::uavcan::node::get_info::{GetInfoRequest, GetInfoResponse};
+use crate::uavcan::node::get_transport_statistics::{
+    GetTransportStatisticsRequest, GetTransportStatisticsResponse,
+};
+use crate::uavcan::node::heartbeat::Heartbeat;
+use crate::uavcan::node::port::list::List;
+use crate::uavcan::pnp::node_id_allocation_data_1_0::NodeIdAllocationData;
+use crate::uavcan::register::access::{AccessRequest, AccessResponse};
+use crate::uavcan::register::list::{ListRequest, ListResponse};
+
+/// A function that decodes a payload into a value that can be formatted
+pub type DecodeFn = dyn Fn(&[u8]) -> Result<Box<dyn fmt::Debug>, DeserializeError>;
+
+/// A data type name and a function that decodes payloads of that type
+pub struct Decoder {
+    name: Cow<'static, str>,
+    decode: Box<DecodeFn>,
+}
+
+impl Decoder {
+    /// Creates a decoder from a data type name and a decoding function
+    pub fn new<N, F>(name: N, decode: F) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        F: Fn(&[u8]) -> Result<Box<dyn fmt::Debug>, DeserializeError> + 'static,
+    {
+        Decoder {
+            name: name.into(),
+            decode: Box::new(decode),
+        }
+    }
+
+    /// Creates a decoder that deserializes payloads as `T`
+    pub fn of<T, N>(name: N) -> Self
+    where
+        T: Deserialize + fmt::Debug + 'static,
+        N: Into<Cow<'static, str>>,
+    {
+        Decoder::new(name, |payload| {
+            T::deserialize_from_bytes(payload).map(|value| Box::new(value) as Box<dyn fmt::Debug>)
+        })
+    }
+
+    /// Returns the data type name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Decodes a payload
+    pub fn decode(&self, payload: &[u8]) -> Result<Box<dyn fmt::Debug>, DeserializeError> {
+        (self.decode)(payload)
+    }
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A collection of decoders for subjects and services
+#[derive(Debug, Default)]
+pub struct PayloadRegistry {
+    messages: BTreeMap<SubjectId, Decoder>,
+    requests: BTreeMap<ServiceId, Decoder>,
+    responses: BTreeMap<ServiceId, Decoder>,
+}
+
+impl PayloadRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        PayloadRegistry::default()
+    }
+
+    /// Creates a registry with decoders for the regulated data types that this crate defines,
+    /// on their fixed port IDs
+    pub fn regulated() -> Self {
+        let mut registry = PayloadRegistry::new();
+        registry.add_message::<Heartbeat, _>(port_ids::HEARTBEAT, "uavcan.node.Heartbeat.1");
+        registry.add_message::<List, _>(port_ids::PORT_LIST, "uavcan.node.port.List.0");
+        registry.add_message::<NodeIdAllocationData, _>(
+            port_ids::NODE_ID_ALLOCATION_DATA_1,
+            "uavcan.pnp.NodeIDAllocationData.1",
+        );
+        registry.add_message::<OutgoingPacket, _>(
+            port_ids::UDP_OUTGOING_PACKET,
+            "uavcan.internet.udp.OutgoingPacket.0",
+        );
+        registry
+            .add_message::<Record, _>(port_ids::DIAGNOSTIC_RECORD, "uavcan.diagnostic.Record.1");
+
+        registry.add_service::<AccessRequest, AccessResponse, _>(
+            port_ids::REGISTER_ACCESS,
+            "uavcan.register.Access.1",
+        );
+        registry.add_service::<ListRequest, ListResponse, _>(
+            port_ids::REGISTER_LIST,
+            "uavcan.register.List.1",
+        );
+        registry.add_service::<GetInfoRequest, GetInfoResponse, _>(
+            port_ids::GET_INFO,
+            "uavcan.node.GetInfo.1",
+        );
+        registry.add_service::<GetTransportStatisticsRequest, GetTransportStatisticsResponse, _>(
+            port_ids::GET_TRANSPORT_STATISTICS,
+            "uavcan.node.GetTransportStatistics.0",
+        );
+        registry.add_service::<ExecuteCommandRequest, ExecuteCommandResponse, _>(
+            port_ids::EXECUTE_COMMAND,
+            "uavcan.node.ExecuteCommand.1",
+        );
+        registry.add_service::<HandleIncomingPacketRequest, HandleIncomingPacketResponse, _>(
+            port_ids::UDP_HANDLE_INCOMING_PACKET,
+            "uavcan.internet.udp.HandleIncomingPacket.0",
+        );
+        registry
+    }
+
+    /// Adds a message data type on a subject, replacing any existing decoder for the subject
+    pub fn add_message<T, N>(&mut self, subject: SubjectId, name: N)
+    where
+        T: Message + Deserialize + fmt::Debug + 'static,
+        N: Into<Cow<'static, str>>,
+    {
+        self.add_message_decoder(subject, Decoder::of::<T, N>(name));
+    }
+
+    /// Adds request and response data types on a service, replacing any existing decoders for
+    /// the service
+    pub fn add_service<Req, Resp, N>(&mut self, service: ServiceId, name: N)
+    where
+        Req: Request + Deserialize + fmt::Debug + 'static,
+        Resp: Response + Deserialize + fmt::Debug + 'static,
+        N: Into<Cow<'static, str>>,
+    {
+        let name = name.into();
+        self.add_request_decoder(service, Decoder::of::<Req, _>(name.clone()));
+        self.add_response_decoder(service, Decoder::of::<Resp, _>(name));
+    }
+
+    /// Adds a decoder for messages on a subject, replacing any existing decoder for the subject
+    pub fn add_message_decoder(&mut self, subject: SubjectId, decoder: Decoder) {
+        self.messages.insert(subject, decoder);
+    }
+
+    /// Adds a decoder for requests on a service, replacing any existing request decoder for the
+    /// service
+    pub fn add_request_decoder(&mut self, service: ServiceId, decoder: Decoder) {
+        self.requests.insert(service, decoder);
+    }
+
+    /// Adds a decoder for responses on a service, replacing any existing response decoder for the
+    /// service
+    pub fn add_response_decoder(&mut self, service: ServiceId, decoder: Decoder) {
+        self.responses.insert(service, decoder);
+    }
+
+    /// Removes the decoder for messages on a subject
+    pub fn remove_message(&mut self, subject: SubjectId) -> Option<Decoder> {
+        self.messages.remove(&subject)
+    }
+
+    /// Removes the request and response decoders for a service
+    pub fn remove_service(&mut self, service: ServiceId) {
+        self.requests.remove(&service);
+        self.responses.remove(&service);
+    }
+
+    /// Returns the decoder for transfers with a header, if one is registered
+    pub fn decoder<I>(&self, header: &Header<I>) -> Option<&Decoder> {
+        match header {
+            Header::Message(header) => self.messages.get(&header.subject),
+            Header::Request(header) => self.requests.get(&header.service),
+            Header::Response(header) => self.responses.get(&header.service),
+        }
+    }
+
+    /// Returns a value that formats a transfer payload
+    ///
+    /// With the alternate flag (`{:#}`), decoded values are formatted on multiple lines.
+    pub fn format<'a, I>(&'a self, header: &Header<I>, payload: &'a [u8]) -> FormattedPayload<'a> {
+        FormattedPayload {
+            decoder: self.decoder(header),
+            payload,
+        }
+    }
+}
+
+/// A transfer payload that can be formatted using a decoder from a [`PayloadRegistry`]
+#[derive(Debug)]
+pub struct FormattedPayload<'a> {
+    decoder: Option<&'a Decoder>,
+    payload: &'a [u8],
+}
+
+impl fmt::Display for FormattedPayload<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.decoder {
+            Some(decoder) => match decoder.decode(self.payload) {
+                Ok(value) => {
+                    if f.alternate() {
+                        write!(f, "{} {:#?}", decoder.name(), value)
+                    } else {
+                        write!(f, "{} {:?}", decoder.name(), value)
+                    }
+                }
+                Err(e) => {
+                    write!(f, "{} (invalid: {:?}) ", decoder.name(), e)?;
+                    write_hex(f, self.payload)
+                }
+            },
+            None => write_hex(f, self.payload),
+        }
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i != 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{:02x}", byte)?;
+    }
+    write!(f, "]")
+}
@@ -6,4 +6,20 @@ extern crate canadensis_encoding;
 extern crate heapless;
 
 pub mod bits;
+pub mod format;
+pub mod port_ids;
+pub mod reg;
 pub mod uavcan;
+
+/// An error that occurs when text is too long to fit in a field of a data type
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TextTooLong;
+
+/// Replaces the content of a byte array field with the bytes of a string
+pub(crate) fn set_text<const N: usize>(
+    field: &mut heapless::Vec<u8, N>,
+    text: &str,
+) -> Result<(), TextTooLong> {
+    *field = heapless::Vec::from_slice(text.as_bytes()).map_err(|_| TextTooLong)?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//!
+//! Fixed port identifiers of the UAVCAN public regulated data types
+//!
+//! This module has constants for the fixed subject and service IDs that the specification
+//! assigns to the public regulated data types, including data types that this crate does not
+//! define. It also has tables that map between IDs and data type names, which can be used to
+//! label transfers in a bus monitor.
+//!
+//! Data type names are the full names without a version, like `uavcan.node.Heartbeat`.
+//! Lookup by name also accepts a name with a major version, like `uavcan.node.Heartbeat.1`.
+//!
+
+use canadensis_core::{ServiceId, SubjectId};
+
+use crate::uavcan::diagnostic::record::Record;
+use crate::uavcan::internet::udp::handle_incoming_packet::HandleIncomingPacketRequest;
+use crate::uavcan::internet::udp::outgoing_packet::OutgoingPacket;
+use crate::uavcan::node::execute_command::ExecuteCommandRequest;
+use crate::uavcan::node::get_info::GetInfoRequest;
+use crate::uavcan::node::get_transport_statistics::GetTransportStatisticsRequest;
+use crate::uavcan::node::heartbeat::Heartbeat;
+use crate::uavcan::node::port::list::List;
+use crate::uavcan::pnp::node_id_allocation_data_1_0::NodeIdAllocationData;
+use crate::uavcan::register::access::AccessRequest;
+use crate::uavcan::register::list::ListRequest;
+
+/// uavcan.time.Synchronization
+pub const TIME_SYNCHRONIZATION: SubjectId = SubjectId::from_truncating(7168);
+/// uavcan.node.Heartbeat
+pub const HEARTBEAT: SubjectId = Heartbeat::SUBJECT;
+/// uavcan.node.port.List
+pub const PORT_LIST: SubjectId = List::SUBJECT;
+/// uavcan.pnp.cluster.Discovery
+pub const PNP_CLUSTER_DISCOVERY: SubjectId = SubjectId::from_truncating(8164);
+/// uavcan.pnp.NodeIDAllocationData version 2
+pub const NODE_ID_ALLOCATION_DATA_2: SubjectId = SubjectId::from_truncating(8165);
+/// uavcan.pnp.NodeIDAllocationData version 1
+pub const NODE_ID_ALLOCATION_DATA_1: SubjectId = NodeIdAllocationData::SUBJECT;
+/// uavcan.internet.udp.OutgoingPacket
+pub const UDP_OUTGOING_PACKET: SubjectId = OutgoingPacket::SUBJECT;
+/// uavcan.diagnostic.Record
+pub const DIAGNOSTIC_RECORD: SubjectId = Record::SUBJECT;
+
+/// uavcan.register.Access
+pub const REGISTER_ACCESS: ServiceId = AccessRequest::SERVICE;
+/// uavcan.register.List
+pub const REGISTER_LIST: ServiceId = ListRequest::SERVICE;
+/// uavcan.pnp.cluster.AppendEntries
+pub const PNP_CLUSTER_APPEND_ENTRIES: ServiceId = ServiceId::from_truncating(390);
+/// uavcan.pnp.cluster.RequestVote
+pub const PNP_CLUSTER_REQUEST_VOTE: ServiceId = ServiceId::from_truncating(391);
+/// uavcan.file.GetInfo
+pub const FILE_GET_INFO: ServiceId = ServiceId::from_truncating(405);
+/// uavcan.file.List
+pub const FILE_LIST: ServiceId = ServiceId::from_truncating(406);
+/// uavcan.file.Modify
+pub const FILE_MODIFY: ServiceId = ServiceId::from_truncating(407);
+/// uavcan.file.Read
+pub const FILE_READ: ServiceId = ServiceId::from_truncating(408);
+/// uavcan.file.Write
+pub const FILE_WRITE: ServiceId = ServiceId::from_truncating(409);
+/// uavcan.node.GetInfo
+pub const GET_INFO: ServiceId = GetInfoRequest::SERVICE;
+/// uavcan.node.GetTransportStatistics
+pub const GET_TRANSPORT_STATISTICS: ServiceId = GetTransportStatisticsRequest::SERVICE;
+/// uavcan.node.ExecuteCommand
+pub const EXECUTE_COMMAND: ServiceId = ExecuteCommandRequest::SERVICE;
+/// uavcan.internet.udp.HandleIncomingPacket
+pub const UDP_HANDLE_INCOMING_PACKET: ServiceId = HandleIncomingPacketRequest::SERVICE;
+/// uavcan.time.GetSynchronizationMasterInfo
+pub const GET_SYNCHRONIZATION_MASTER_INFO: ServiceId = ServiceId::from_truncating(510);
+
+/// A data type that has a fixed port ID
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RegulatedPort<P> {
+    /// The fixed port ID
+    pub id: P,
+    /// The full name of the data type, without a version
+    pub name: &'static str,
+    /// The major version of the data type that uses this port ID
+    pub major_version: u8,
+}
+
+impl<P> RegulatedPort<P> {
+    /// Returns true if a name matches this data type
+    ///
+    /// The name may include a major version.
+    fn matches(&self, name: &str) -> bool {
+        match name.strip_prefix(self.name) {
+            Some("") => true,
+            Some(version) => version
+                .strip_prefix('.')
+                .and_then(|version| version.parse::<u8>().ok())
+                .map(|version| version == self.major_version)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+}
+
+const fn port<P>(id: P, name: &'static str, major_version: u8) -> RegulatedPort<P> {
+    RegulatedPort {
+        id,
+        name,
+        major_version,
+    }
+}
+
+/// All regulated subjects with fixed IDs, sorted by ID
+pub static SUBJECTS: [RegulatedPort<SubjectId>; 8] = [
+    port(TIME_SYNCHRONIZATION, "uavcan.time.Synchronization", 1),
+    port(HEARTBEAT, "uavcan.node.Heartbeat", 1),
+    port(PORT_LIST, "uavcan.node.port.List", 0),
+    port(PNP_CLUSTER_DISCOVERY, "uavcan.pnp.cluster.Discovery", 1),
+    port(
+        NODE_ID_ALLOCATION_DATA_2,
+        "uavcan.pnp.NodeIDAllocationData",
+        2,
+    ),
+    port(
+        NODE_ID_ALLOCATION_DATA_1,
+        "uavcan.pnp.NodeIDAllocationData",
+        1,
+    ),
+    port(UDP_OUTGOING_PACKET, "uavcan.internet.udp.OutgoingPacket", 0),
+    port(DIAGNOSTIC_RECORD, "uavcan.diagnostic.Record", 1),
+];
+
+/// All regulated services with fixed IDs, sorted by ID
+pub static SERVICES: [RegulatedPort<ServiceId>; 14] = [
+    port(REGISTER_ACCESS, "uavcan.register.Access", 1),
+    port(REGISTER_LIST, "uavcan.register.List", 1),
+    port(
+        PNP_CLUSTER_APPEND_ENTRIES,
+        "uavcan.pnp.cluster.AppendEntries",
+        1,
+    ),
+    port(
+        PNP_CLUSTER_REQUEST_VOTE,
+        "uavcan.pnp.cluster.RequestVote",
+        1,
+    ),
+    port(FILE_GET_INFO, "uavcan.file.GetInfo", 0),
+    port(FILE_LIST, "uavcan.file.List", 0),
+    port(FILE_MODIFY, "uavcan.file.Modify", 1),
+    port(FILE_READ, "uavcan.file.Read", 1),
+    port(FILE_WRITE, "uavcan.file.Write", 1),
+    port(GET_INFO, "uavcan.node.GetInfo", 1),
+    port(
+        GET_TRANSPORT_STATISTICS,
+        "uavcan.node.GetTransportStatistics",
+        0,
+    ),
+    port(EXECUTE_COMMAND, "uavcan.node.ExecuteCommand", 1),
+    port(
+        UDP_HANDLE_INCOMING_PACKET,
+        "uavcan.internet.udp.HandleIncomingPacket",
+        0,
+    ),
+    port(
+        GET_SYNCHRONIZATION_MASTER_INFO,
+        "uavcan.time.GetSynchronizationMasterInfo",
+        0,
+    ),
+];
+
+/// Returns the regulated data type that uses a subject ID
+pub fn subject_by_id(id: SubjectId) -> Option<&'static RegulatedPort<SubjectId>> {
+    SUBJECTS.iter().find(|port| port.id == id)
+}
+
+/// Returns the regulated data type that uses a service ID
+pub fn service_by_id(id: ServiceId) -> Option<&'static RegulatedPort<ServiceId>> {
+    SERVICES.iter().find(|port| port.id == id)
+}
+
+/// Returns the regulated subject with a data type name
+///
+/// If the name does not include a major version and more than one version of the data type
+/// has a fixed subject ID, this returns the newest version.
+pub fn subject_by_name(name: &str) -> Option<&'static RegulatedPort<SubjectId>> {
+    SUBJECTS
+        .iter()
+        .filter(|port| port.matches(name))
+        .max_by_key(|port| port.major_version)
+}
+
+/// Returns the regulated service with a data type name
+pub fn service_by_name(name: &str) -> Option<&'static RegulatedPort<ServiceId>> {
+    SERVICES.iter().find(|port| port.matches(name))
+}
@@ -0,0 +1,98 @@
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// reg.udral.service.actuator.common.FaultFlags version 0.1
+///
+/// Each flag is set when the corresponding parameter is outside its safe operating area.
+/// While any flag is set, the health of the service should not be nominal.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FaultFlags {
+    pub overload: bool,
+    pub voltage: bool,
+    pub motor_temperature: bool,
+    pub controller_temperature: bool,
+    pub velocity: bool,
+    pub mechanical: bool,
+    pub vibration: bool,
+    pub configuration: bool,
+    pub control_mode: bool,
+    pub other: bool,
+}
+
+impl FaultFlags {
+    /// Returns true if any flag is set
+    pub fn any(&self) -> bool {
+        self.overload
+            || self.voltage
+            || self.motor_temperature
+            || self.controller_temperature
+            || self.velocity
+            || self.mechanical
+            || self.vibration
+            || self.configuration
+            || self.control_mode
+            || self.other
+    }
+}
+
+impl DataType for FaultFlags {
+    // Sealed type
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
+}
+
+impl Message for FaultFlags {}
+
+impl Serialize for FaultFlags {
+    fn size_bits(&self) -> usize {
+        16
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_bool(self.overload);
+        cursor.write_bool(self.voltage);
+        cursor.write_bool(self.motor_temperature);
+        cursor.write_bool(self.controller_temperature);
+        cursor.write_bool(self.velocity);
+        cursor.write_bool(self.mechanical);
+        cursor.write_bool(self.vibration);
+        cursor.write_bool(self.configuration);
+        cursor.write_bool(self.control_mode);
+        cursor.skip_6();
+        cursor.write_bool(self.other);
+    }
+}
+
+impl Deserialize for FaultFlags {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 16
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.overload = cursor.read_bool();
+        self.voltage = cursor.read_bool();
+        self.motor_temperature = cursor.read_bool();
+        self.controller_temperature = cursor.read_bool();
+        self.velocity = cursor.read_bool();
+        self.mechanical = cursor.read_bool();
+        self.vibration = cursor.read_bool();
+        self.configuration = cursor.read_bool();
+        self.control_mode = cursor.read_bool();
+        cursor.skip_6();
+        self.other = cursor.read_bool();
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = FaultFlags::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
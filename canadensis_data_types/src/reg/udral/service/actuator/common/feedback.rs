@@ -0,0 +1,59 @@
+use crate::reg::udral::service::common::heartbeat::Heartbeat;
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// reg.udral.service.actuator.common.Feedback version 0.1
+///
+/// An actuator publishes this once after it applies each setpoint.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Feedback {
+    pub heartbeat: Heartbeat,
+    /// Output as a percentage of the maximum rated output
+    ///
+    /// This is positive when the actuator is delivering power to the load and negative when
+    /// power is flowing from the load back into the actuator.
+    pub demand_factor_pct: i8,
+}
+
+impl DataType for Feedback {
+    const EXTENT_BYTES: Option<u32> = Some(63);
+    const MAX_SERIALIZED_SIZE: usize = 3;
+}
+
+impl Message for Feedback {}
+
+impl Serialize for Feedback {
+    fn size_bits(&self) -> usize {
+        24
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_composite(&self.heartbeat);
+        cursor.write_aligned_u8(self.demand_factor_pct as u8);
+    }
+}
+
+impl Deserialize for Feedback {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 24
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.heartbeat = cursor.read_composite()?;
+        self.demand_factor_pct = cursor.read_aligned_u8() as i8;
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = Feedback::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,15 @@
+pub mod fault_flags;
+pub mod feedback;
+pub mod sp;
+pub mod status;
+
+/// reg.udral.service.actuator.common.CONTROL_TIMEOUT (seconds)
+///
+/// An actuator may enter a safe state if it has not received a setpoint or readiness message
+/// in this amount of time.
+pub const CONTROL_TIMEOUT: f32 = 1.0;
+/// reg.udral.service.actuator.common.MAX_PUBLICATION_PERIOD (seconds)
+///
+/// Subjects that are published when a setpoint arrives must also be published at least this
+/// often when no setpoints are arriving, unless the actuator is in the SLEEP state.
+pub const MAX_PUBLICATION_PERIOD: u8 = 1;
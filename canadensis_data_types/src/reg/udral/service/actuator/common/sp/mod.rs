@@ -0,0 +1,2 @@
+pub mod scalar;
+pub mod vector31;
@@ -0,0 +1,53 @@
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+use half::f16;
+
+/// reg.udral.service.actuator.common.sp.Scalar version 0.1
+///
+/// A setpoint for one actuator
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scalar {
+    pub value: f16,
+}
+
+impl DataType for Scalar {
+    // Sealed type
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
+}
+
+impl Message for Scalar {}
+
+impl Serialize for Scalar {
+    fn size_bits(&self) -> usize {
+        16
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_f16(self.value);
+    }
+}
+
+impl Deserialize for Scalar {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 16
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.value = cursor.read_f16();
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        Ok(Scalar {
+            value: cursor.read_f16(),
+        })
+    }
+}
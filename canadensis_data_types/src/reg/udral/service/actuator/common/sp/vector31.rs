@@ -0,0 +1,67 @@
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+use half::f16;
+
+/// reg.udral.service.actuator.common.sp.Vector31 version 0.1
+///
+/// Setpoints for a group of up to 31 actuators
+///
+/// Actuators subscribe using this type. A controller for a smaller group can send a shorter
+/// vector type, and the missing elements are read as zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector31 {
+    pub value: [f16; 31],
+}
+
+impl Default for Vector31 {
+    fn default() -> Self {
+        Vector31 {
+            value: [f16::ZERO; 31],
+        }
+    }
+}
+
+impl DataType for Vector31 {
+    const EXTENT_BYTES: Option<u32> = Some(512);
+    const MAX_SERIALIZED_SIZE: usize = 62;
+}
+
+impl Message for Vector31 {}
+
+impl Serialize for Vector31 {
+    fn size_bits(&self) -> usize {
+        496
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        for value in self.value.iter() {
+            cursor.write_f16(*value);
+        }
+    }
+}
+
+impl Deserialize for Vector31 {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 496
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        for value in self.value.iter_mut() {
+            *value = cursor.read_f16();
+        }
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = Vector31::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::reg::udral::service::actuator::common::fault_flags::FaultFlags;
+use crate::uavcan::si::unit::temperature::scalar::Scalar as Temperature;
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// reg.udral.service.actuator.common.Status version 0.1
+///
+/// Actuators usually publish this at 1 Hz for diagnostics and logging.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub motor_temperature: Temperature,
+    pub controller_temperature: Temperature,
+    /// The number of errors since the actuator was last engaged
+    pub error_count: u32,
+    pub fault_flags: FaultFlags,
+}
+
+impl DataType for Status {
+    const EXTENT_BYTES: Option<u32> = Some(63);
+    const MAX_SERIALIZED_SIZE: usize = 14;
+}
+
+impl Message for Status {}
+
+impl Serialize for Status {
+    fn size_bits(&self) -> usize {
+        112
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_composite(&self.motor_temperature);
+        cursor.write_composite(&self.controller_temperature);
+        cursor.write_aligned_u32(self.error_count);
+        cursor.write_composite(&self.fault_flags);
+    }
+}
+
+impl Deserialize for Status {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 112
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.motor_temperature = cursor.read_composite()?;
+        self.controller_temperature = cursor.read_composite()?;
+        self.error_count = cursor.read_aligned_u32();
+        self.fault_flags = cursor.read_composite()?;
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = Status::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
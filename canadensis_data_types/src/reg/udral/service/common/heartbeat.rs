@@ -0,0 +1,61 @@
+use crate::reg::udral::service::common::readiness::Readiness;
+use crate::uavcan::node::health::Health;
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// reg.udral.service.common.Heartbeat version 0.1
+///
+/// This is like `uavcan.node.Heartbeat`, but for one service of a node.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Heartbeat {
+    pub readiness: Readiness,
+    pub health: Health,
+}
+
+impl Heartbeat {
+    pub const MAX_PUBLICATION_PERIOD: u8 = 1;
+}
+
+impl DataType for Heartbeat {
+    // Sealed type
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
+}
+
+impl Message for Heartbeat {}
+
+impl Serialize for Heartbeat {
+    fn size_bits(&self) -> usize {
+        16
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_composite(&self.readiness);
+        cursor.write_composite(&self.health);
+    }
+}
+
+impl Deserialize for Heartbeat {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 16
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.readiness = cursor.read_composite()?;
+        self.health = cursor.read_composite()?;
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = Heartbeat::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,69 @@
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// reg.udral.service.common.Readiness version 0.1
+///
+/// The value 1 is invalid. It is decoded as `Standby`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Readiness {
+    /// Minimal power consumption, possibly with no network activity
+    Sleep = 0,
+    /// Ready to enter normal operation soon, but not performing the main function
+    #[default]
+    Standby = 2,
+    /// Performing the main function
+    Engaged = 3,
+}
+
+impl Readiness {
+    pub const SLEEP: u8 = 0;
+    pub const STANDBY: u8 = 2;
+    pub const ENGAGED: u8 = 3;
+}
+
+impl DataType for Readiness {
+    // Sealed type
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 1;
+}
+
+impl Message for Readiness {}
+
+impl Serialize for Readiness {
+    fn size_bits(&self) -> usize {
+        // Size gets rounded up to 8 because this is a composite type
+        8
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_u2(*self as u8);
+    }
+}
+
+impl Deserialize for Readiness {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 8
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        *self = Readiness::deserialize(cursor)?;
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let readiness = match cursor.read_u2() {
+            Readiness::SLEEP => Readiness::Sleep,
+            Readiness::ENGAGED => Readiness::Engaged,
+            // The specification allows 1 to be interpreted as SLEEP or STANDBY
+            _ => Readiness::Standby,
+        };
+        Ok(readiness)
+    }
+}
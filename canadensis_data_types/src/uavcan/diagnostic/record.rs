@@ -1,9 +1,11 @@
 use crate::uavcan::diagnostic::severity::Severity;
 use crate::uavcan::time::synchronized_timestamp::SynchronizedTimestamp;
+use crate::{set_text, TextTooLong};
 use canadensis_core::SubjectId;
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
 };
+use core::str::{self, Utf8Error};
 
 /// uavcan.diagnostic.Record version 1.1
 #[derive(Debug, Clone, Default)]
@@ -15,12 +17,23 @@ pub struct Record {
 
 impl Record {
     pub const SUBJECT: SubjectId = SubjectId::from_truncating(8184);
+
+    /// Returns the text as a string, or an error if it is not valid UTF-8
+    pub fn text_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.text)
+    }
+
+    /// Sets the text
+    pub fn set_text(&mut self, text: &str) -> Result<(), TextTooLong> {
+        set_text(&mut self.text, text)
+    }
 }
 
 impl Message for Record {}
 
 impl DataType for Record {
     const EXTENT_BYTES: Option<u32> = Some(300);
+    const MAX_SERIALIZED_SIZE: usize = 264;
 }
 
 impl Serialize for Record {
@@ -32,9 +45,7 @@ impl Serialize for Record {
         cursor.write_composite(&self.timestamp);
         cursor.write_composite(&self.severity);
         cursor.write_aligned_u8(self.text.len() as u8);
-        for &byte in self.text.iter() {
-            cursor.write_aligned_u8(byte);
-        }
+        cursor.write_aligned_bytes(&self.text);
     }
 }
 
@@ -45,7 +56,7 @@ impl Deserialize for Record {
             false
         } else {
             let text_bytes_bits = bit_length - 72;
-            text_bytes_bits % 8 == 0 && text_bytes_bits / 8 <= 255
+            text_bytes_bits.is_multiple_of(8) && text_bytes_bits / 8 <= 255
         }
     }
 
@@ -57,12 +68,11 @@ impl Deserialize for Record {
         self.severity = cursor.read_composite()?;
         self.text.clear();
         let text_length = cursor.read_aligned_u8();
-        for _ in 0..text_length {
-            self.text
-                .push(cursor.read_aligned_u8())
-                .expect("Array too long");
-        }
-        Ok(())
+        self.text
+            .resize_default(usize::from(text_length))
+            .expect("Array too long");
+        cursor.read_aligned_bytes(&mut self.text);
+        cursor.check_utf8(&self.text)
     }
 
     fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
@@ -3,11 +3,12 @@ use canadensis_encoding::{
 };
 
 /// uavcan.diagnostic.Severity version 1.0
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum Severity {
     Trace = 0,
     Debug = 1,
     Info = 2,
+    #[default]
     Notice = 3,
     Warning = 4,
     Error = 5,
@@ -15,15 +16,10 @@ pub enum Severity {
     Alert = 7,
 }
 
-impl Default for Severity {
-    fn default() -> Self {
-        Severity::Notice
-    }
-}
-
 impl DataType for Severity {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 1;
 }
 
 impl Message for Severity {}
@@ -1,3 +1,7 @@
+use crate::{set_text, TextTooLong};
+use core::convert::TryFrom;
+use core::str::{self, Utf8Error};
+
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
 };
@@ -11,11 +15,32 @@ pub struct Path {
 impl Path {
     pub const SEPARATOR: u8 = b'/';
     pub const MAX_LENGTH: u8 = 255;
+
+    /// Returns the bytes of this path
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.path
+    }
+
+    /// Returns this path as a string, or an error if it is not valid UTF-8
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.path)
+    }
+}
+
+impl TryFrom<&str> for Path {
+    type Error = TextTooLong;
+
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        let mut value = Path::default();
+        set_text(&mut value.path, path)?;
+        Ok(value)
+    }
 }
 
 impl DataType for Path {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 256;
 }
 
 impl Message for Path {}
@@ -37,7 +62,8 @@ impl Deserialize for Path {
             false
         } else {
             let path_bytes_length = bit_length - 8;
-            path_bytes_length % 8 == 0 && path_bytes_length / 8 <= usize::from(Path::MAX_LENGTH)
+            path_bytes_length.is_multiple_of(8)
+                && path_bytes_length / 8 <= usize::from(Path::MAX_LENGTH)
         }
     }
 
@@ -48,10 +74,9 @@ impl Deserialize for Path {
         let length = cursor.read_aligned_u8();
         // Because the maximum length equals the maximum u8 value, no length check is needed.
         self.path.clear();
-        for _ in 0..length {
-            self.path.push(cursor.read_aligned_u8()).unwrap();
-        }
-        Ok(())
+        self.path.resize_default(usize::from(length)).unwrap();
+        cursor.read_aligned_bytes(&mut self.path);
+        cursor.check_utf8(&self.path)
     }
 
     fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
@@ -0,0 +1,118 @@
+use canadensis_core::ServiceId;
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, ReadCursor, Request, Response, Serialize, WriteCursor,
+};
+
+/// uavcan.internet.udp.HandleIncomingPacket version 0.1 request
+#[derive(Debug, Clone, Default)]
+pub struct HandleIncomingPacketRequest {
+    pub session_id: u16,
+    pub payload: heapless::Vec<u8, { HandleIncomingPacketRequest::PAYLOAD_MAX_LENGTH as usize }>,
+}
+
+impl HandleIncomingPacketRequest {
+    pub const SERVICE: ServiceId = ServiceId::from_truncating(500);
+    pub const PAYLOAD_MAX_LENGTH: u16 = 508;
+}
+
+impl DataType for HandleIncomingPacketRequest {
+    const EXTENT_BYTES: Option<u32> = Some(600);
+    const MAX_SERIALIZED_SIZE: usize = 512;
+}
+
+impl Request for HandleIncomingPacketRequest {}
+
+impl Serialize for HandleIncomingPacketRequest {
+    fn size_bits(&self) -> usize {
+        16 + 16 + 8 * self.payload.len()
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_aligned_u16(self.session_id);
+        cursor.write_aligned_u16(self.payload.len() as u16);
+        cursor.write_aligned_bytes(&self.payload);
+    }
+}
+
+impl Deserialize for HandleIncomingPacketRequest {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        if bit_length < 32 {
+            false
+        } else {
+            let payload_bits = bit_length - 32;
+            payload_bits.is_multiple_of(8)
+                && payload_bits / 8 <= usize::from(HandleIncomingPacketRequest::PAYLOAD_MAX_LENGTH)
+        }
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.session_id = cursor.read_aligned_u16();
+        let payload_length = cursor.read_aligned_u16();
+        if payload_length > HandleIncomingPacketRequest::PAYLOAD_MAX_LENGTH {
+            return Err(DeserializeError::ArrayLength);
+        }
+        self.payload.clear();
+        self.payload
+            .resize_default(usize::from(payload_length))
+            .unwrap();
+        cursor.read_aligned_bytes(&mut self.payload);
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = HandleIncomingPacketRequest::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
+
+/// uavcan.internet.udp.HandleIncomingPacket version 0.1 response
+#[derive(Debug, Clone, Default)]
+pub struct HandleIncomingPacketResponse {}
+
+impl HandleIncomingPacketResponse {
+    pub const SERVICE: ServiceId = ServiceId::from_truncating(500);
+}
+
+impl DataType for HandleIncomingPacketResponse {
+    const EXTENT_BYTES: Option<u32> = Some(63);
+    const MAX_SERIALIZED_SIZE: usize = 0;
+}
+
+impl Response for HandleIncomingPacketResponse {}
+
+impl Serialize for HandleIncomingPacketResponse {
+    fn size_bits(&self) -> usize {
+        0
+    }
+
+    fn serialize(&self, _cursor: &mut WriteCursor<'_>) {
+        // Nothing to do
+    }
+}
+
+impl Deserialize for HandleIncomingPacketResponse {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 0
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        _cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    fn deserialize(_cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        Ok(HandleIncomingPacketResponse {})
+    }
+}
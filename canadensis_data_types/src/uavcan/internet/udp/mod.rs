@@ -0,0 +1,2 @@
+pub mod handle_incoming_packet;
+pub mod outgoing_packet;
@@ -0,0 +1,102 @@
+use canadensis_core::SubjectId;
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// uavcan.internet.udp.OutgoingPacket version 0.1
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingPacket {
+    pub session_id: u16,
+    pub destination_port: u16,
+    pub destination_address:
+        heapless::Vec<u8, { OutgoingPacket::DESTINATION_ADDRESS_MAX_LENGTH as usize }>,
+    pub use_masquerading: bool,
+    pub use_dtls: bool,
+    pub payload: heapless::Vec<u8, { OutgoingPacket::PAYLOAD_MAX_LENGTH as usize }>,
+}
+
+impl OutgoingPacket {
+    pub const SUBJECT: SubjectId = SubjectId::from_truncating(8174);
+    /// The minimum time, in seconds, that a NAT entry must be kept after the last packet
+    pub const NAT_ENTRY_MIN_TTL: u32 = 24 * 60 * 60;
+    pub const DESTINATION_ADDRESS_MAX_LENGTH: u8 = 45;
+    pub const PAYLOAD_MAX_LENGTH: u16 = 260;
+
+    /// The length in bits of all fields except the variable-length arrays
+    const FIXED_BITS: usize = 16 + 16 + 8 + 8 + 16;
+}
+
+impl DataType for OutgoingPacket {
+    const EXTENT_BYTES: Option<u32> = Some(600);
+    const MAX_SERIALIZED_SIZE: usize = 313;
+}
+
+impl Message for OutgoingPacket {}
+
+impl Serialize for OutgoingPacket {
+    fn size_bits(&self) -> usize {
+        OutgoingPacket::FIXED_BITS + 8 * (self.destination_address.len() + self.payload.len())
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_aligned_u16(self.session_id);
+        cursor.write_aligned_u16(self.destination_port);
+        cursor.write_aligned_u8(self.destination_address.len() as u8);
+        cursor.write_aligned_bytes(&self.destination_address);
+        cursor.write_bool(self.use_masquerading);
+        cursor.write_bool(self.use_dtls);
+        cursor.write_u6(0);
+        cursor.write_aligned_u16(self.payload.len() as u16);
+        cursor.write_aligned_bytes(&self.payload);
+    }
+}
+
+impl Deserialize for OutgoingPacket {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        let max_bits = OutgoingPacket::FIXED_BITS
+            + 8 * (usize::from(OutgoingPacket::DESTINATION_ADDRESS_MAX_LENGTH)
+                + usize::from(OutgoingPacket::PAYLOAD_MAX_LENGTH));
+        bit_length.is_multiple_of(8)
+            && bit_length >= OutgoingPacket::FIXED_BITS
+            && bit_length <= max_bits
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.session_id = cursor.read_aligned_u16();
+        self.destination_port = cursor.read_aligned_u16();
+        let address_length = cursor.read_aligned_u8();
+        if address_length > OutgoingPacket::DESTINATION_ADDRESS_MAX_LENGTH {
+            return Err(DeserializeError::ArrayLength);
+        }
+        self.destination_address.clear();
+        self.destination_address
+            .resize_default(usize::from(address_length))
+            .unwrap();
+        cursor.read_aligned_bytes(&mut self.destination_address);
+        self.use_masquerading = cursor.read_bool();
+        self.use_dtls = cursor.read_bool();
+        cursor.read_u6();
+        let payload_length = cursor.read_aligned_u16();
+        if payload_length > OutgoingPacket::PAYLOAD_MAX_LENGTH {
+            return Err(DeserializeError::ArrayLength);
+        }
+        self.payload.clear();
+        self.payload
+            .resize_default(usize::from(payload_length))
+            .unwrap();
+        cursor.read_aligned_bytes(&mut self.payload);
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut value = OutgoingPacket::default();
+        value.deserialize_in_place(cursor)?;
+        Ok(value)
+    }
+}
@@ -1,6 +1,8 @@
 pub mod diagnostic;
 pub mod file;
+pub mod internet;
 pub mod node;
 pub mod pnp;
 pub mod register;
+pub mod si;
 pub mod time;
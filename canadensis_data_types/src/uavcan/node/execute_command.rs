@@ -1,4 +1,5 @@
 use crate::uavcan::file::path::Path;
+use crate::{set_text, TextTooLong};
 use canadensis_core::ServiceId;
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, ReadCursor, Response, Serialize, WriteCursor,
@@ -13,6 +14,28 @@ pub struct ExecuteCommandRequest {
 
 impl ExecuteCommandRequest {
     pub const SERVICE: ServiceId = ServiceId::from_truncating(435);
+    pub const COMMAND_RESTART: u16 = 65535;
+    pub const COMMAND_POWER_OFF: u16 = 65534;
+    pub const COMMAND_BEGIN_SOFTWARE_UPDATE: u16 = 65533;
+    pub const COMMAND_FACTORY_RESET: u16 = 65532;
+    pub const COMMAND_EMERGENCY_STOP: u16 = 65531;
+    pub const COMMAND_STORE_PERSISTENT_STATES: u16 = 65530;
+
+    /// Creates a request with a command and an empty parameter
+    pub fn new(command: Command) -> Self {
+        ExecuteCommandRequest {
+            command,
+            parameter: heapless::Vec::new(),
+        }
+    }
+
+    /// Sets the parameter to the bytes of a string
+    ///
+    /// For a software update command, the parameter is the path of the file to download.
+    pub fn with_parameter(mut self, parameter: &str) -> Result<Self, TextTooLong> {
+        set_text(&mut self.parameter, parameter)?;
+        Ok(self)
+    }
 }
 
 impl Default for ExecuteCommandRequest {
@@ -24,7 +47,7 @@ impl Default for ExecuteCommandRequest {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Command {
     Restart,
     PowerOff,
@@ -38,12 +61,14 @@ pub enum Command {
 impl From<u16> for Command {
     fn from(bits: u16) -> Self {
         match bits {
-            65535 => Command::Restart,
-            65534 => Command::PowerOff,
-            65533 => Command::BeginSoftwareUpdate,
-            65532 => Command::FactoryReset,
-            65531 => Command::EmergencyStop,
-            65530 => Command::StorePersistentStates,
+            ExecuteCommandRequest::COMMAND_RESTART => Command::Restart,
+            ExecuteCommandRequest::COMMAND_POWER_OFF => Command::PowerOff,
+            ExecuteCommandRequest::COMMAND_BEGIN_SOFTWARE_UPDATE => Command::BeginSoftwareUpdate,
+            ExecuteCommandRequest::COMMAND_FACTORY_RESET => Command::FactoryReset,
+            ExecuteCommandRequest::COMMAND_EMERGENCY_STOP => Command::EmergencyStop,
+            ExecuteCommandRequest::COMMAND_STORE_PERSISTENT_STATES => {
+                Command::StorePersistentStates
+            }
             other => Command::Other(other),
         }
     }
@@ -52,12 +77,14 @@ impl From<u16> for Command {
 impl From<Command> for u16 {
     fn from(command: Command) -> Self {
         match command {
-            Command::Restart => 65535,
-            Command::PowerOff => 65534,
-            Command::BeginSoftwareUpdate => 65533,
-            Command::FactoryReset => 65532,
-            Command::EmergencyStop => 65531,
-            Command::StorePersistentStates => 65530,
+            Command::Restart => ExecuteCommandRequest::COMMAND_RESTART,
+            Command::PowerOff => ExecuteCommandRequest::COMMAND_POWER_OFF,
+            Command::BeginSoftwareUpdate => ExecuteCommandRequest::COMMAND_BEGIN_SOFTWARE_UPDATE,
+            Command::FactoryReset => ExecuteCommandRequest::COMMAND_FACTORY_RESET,
+            Command::EmergencyStop => ExecuteCommandRequest::COMMAND_EMERGENCY_STOP,
+            Command::StorePersistentStates => {
+                ExecuteCommandRequest::COMMAND_STORE_PERSISTENT_STATES
+            }
             Command::Other(bits) => bits,
         }
     }
@@ -65,6 +92,7 @@ impl From<Command> for u16 {
 
 impl DataType for ExecuteCommandRequest {
     const EXTENT_BYTES: Option<u32> = Some(300);
+    const MAX_SERIALIZED_SIZE: usize = 258;
 }
 
 impl canadensis_encoding::Request for ExecuteCommandRequest {}
@@ -75,7 +103,7 @@ impl Serialize for ExecuteCommandRequest {
     }
 
     fn serialize(&self, cursor: &mut WriteCursor<'_>) {
-        cursor.write_aligned_u16(self.command.clone().into());
+        cursor.write_aligned_u16(self.command.into());
         cursor.write_aligned_u8(self.parameter.len() as u8);
         cursor.write_aligned_bytes(&self.parameter);
     }
@@ -87,7 +115,7 @@ impl Deserialize for ExecuteCommandRequest {
             false
         } else {
             let parameter_bytes_length = bit_length - 16;
-            parameter_bytes_length % 8 == 0
+            parameter_bytes_length.is_multiple_of(8)
                 && parameter_bytes_length / 8 <= usize::from(Path::MAX_LENGTH)
         }
     }
@@ -99,9 +127,10 @@ impl Deserialize for ExecuteCommandRequest {
         self.command = cursor.read_aligned_u16().into();
         self.parameter.clear();
         let parameter_length = cursor.read_aligned_u8();
-        for _ in 0..parameter_length {
-            self.parameter.push(cursor.read_aligned_u8()).unwrap();
-        }
+        self.parameter
+            .resize_default(usize::from(parameter_length))
+            .unwrap();
+        cursor.read_aligned_bytes(&mut self.parameter);
         Ok(())
     }
 
@@ -123,10 +152,23 @@ pub struct ExecuteCommandResponse {
 
 impl ExecuteCommandResponse {
     pub const SERVICE: ServiceId = ServiceId::from_truncating(435);
+    pub const STATUS_SUCCESS: u8 = 0;
+    pub const STATUS_FAILURE: u8 = 1;
+    pub const STATUS_NOT_AUTHORIZED: u8 = 2;
+    pub const STATUS_BAD_COMMAND: u8 = 3;
+    pub const STATUS_BAD_PARAMETER: u8 = 4;
+    pub const STATUS_BAD_STATE: u8 = 5;
+    pub const STATUS_INTERNAL_ERROR: u8 = 6;
+
+    /// Creates a response with a status
+    pub const fn new(status: Status) -> Self {
+        ExecuteCommandResponse { status }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub enum Status {
+    #[default]
     Success,
     Failure,
     NotAuthorized,
@@ -137,22 +179,16 @@ pub enum Status {
     Other(u8),
 }
 
-impl Default for Status {
-    fn default() -> Self {
-        Status::Success
-    }
-}
-
 impl From<u8> for Status {
     fn from(bits: u8) -> Self {
         match bits {
-            0 => Status::Success,
-            1 => Status::Failure,
-            2 => Status::NotAuthorized,
-            3 => Status::BadCommand,
-            4 => Status::BadParameter,
-            5 => Status::BadState,
-            6 => Status::InternalError,
+            ExecuteCommandResponse::STATUS_SUCCESS => Status::Success,
+            ExecuteCommandResponse::STATUS_FAILURE => Status::Failure,
+            ExecuteCommandResponse::STATUS_NOT_AUTHORIZED => Status::NotAuthorized,
+            ExecuteCommandResponse::STATUS_BAD_COMMAND => Status::BadCommand,
+            ExecuteCommandResponse::STATUS_BAD_PARAMETER => Status::BadParameter,
+            ExecuteCommandResponse::STATUS_BAD_STATE => Status::BadState,
+            ExecuteCommandResponse::STATUS_INTERNAL_ERROR => Status::InternalError,
             other => Status::Other(other),
         }
     }
@@ -161,13 +197,13 @@ impl From<u8> for Status {
 impl From<Status> for u8 {
     fn from(status: Status) -> Self {
         match status {
-            Status::Success => 0,
-            Status::Failure => 1,
-            Status::NotAuthorized => 2,
-            Status::BadCommand => 3,
-            Status::BadParameter => 4,
-            Status::BadState => 5,
-            Status::InternalError => 6,
+            Status::Success => ExecuteCommandResponse::STATUS_SUCCESS,
+            Status::Failure => ExecuteCommandResponse::STATUS_FAILURE,
+            Status::NotAuthorized => ExecuteCommandResponse::STATUS_NOT_AUTHORIZED,
+            Status::BadCommand => ExecuteCommandResponse::STATUS_BAD_COMMAND,
+            Status::BadParameter => ExecuteCommandResponse::STATUS_BAD_PARAMETER,
+            Status::BadState => ExecuteCommandResponse::STATUS_BAD_STATE,
+            Status::InternalError => ExecuteCommandResponse::STATUS_INTERNAL_ERROR,
             Status::Other(other) => other,
         }
     }
@@ -175,6 +211,7 @@ impl From<Status> for u8 {
 
 impl DataType for ExecuteCommandResponse {
     const EXTENT_BYTES: Option<u32> = Some(48);
+    const MAX_SERIALIZED_SIZE: usize = 1;
 }
 
 impl Response for ExecuteCommandResponse {}
@@ -185,7 +222,7 @@ impl Serialize for ExecuteCommandResponse {
     }
 
     fn serialize(&self, cursor: &mut WriteCursor<'_>) {
-        cursor.write_aligned_u8(self.status.clone().into())
+        cursor.write_aligned_u8(self.status.into())
     }
 }
 
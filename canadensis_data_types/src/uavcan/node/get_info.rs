@@ -1,8 +1,10 @@
 use crate::uavcan::node::version::Version;
+use crate::{set_text, TextTooLong};
 use canadensis_core::ServiceId;
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, ReadCursor, Request, Response, Serialize, WriteCursor,
 };
+use core::str::{self, Utf8Error};
 
 /// uavcan.node.GetInfo version 1.0 request
 #[derive(Debug, Clone, Default)]
@@ -15,6 +17,7 @@ impl GetInfoRequest {
 impl DataType for GetInfoRequest {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 0;
 }
 
 impl Request for GetInfoRequest {}
@@ -64,10 +67,21 @@ pub struct GetInfoResponse {
 
 impl GetInfoResponse {
     pub const SERVICE: ServiceId = ServiceId::from_truncating(430);
+
+    /// Returns the node name as a string, or an error if it is not valid UTF-8
+    pub fn name_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.name)
+    }
+
+    /// Sets the node name
+    pub fn set_name(&mut self, name: &str) -> Result<(), TextTooLong> {
+        set_text(&mut self.name, name)
+    }
 }
 
 impl DataType for GetInfoResponse {
     const EXTENT_BYTES: Option<u32> = Some(448);
+    const MAX_SERIALIZED_SIZE: usize = 313;
 }
 
 impl Response for GetInfoResponse {}
@@ -117,7 +131,7 @@ impl Deserialize for GetInfoResponse {
     fn in_bit_length_set(bit_length: usize) -> bool {
         // This may be too permissive
         let bytes = bit_length / 8;
-        bit_length % 8 == 0 && (33..=313).contains(&bytes)
+        bit_length.is_multiple_of(8) && (33..=313).contains(&bytes)
     }
 
     fn deserialize_in_place(
@@ -134,11 +148,11 @@ impl Deserialize for GetInfoResponse {
         if name_length > 50 {
             return Err(DeserializeError::ArrayLength);
         }
-        for _ in 0..name_length {
-            self.name
-                .push(cursor.read_aligned_u8())
-                .expect("Array too long");
-        }
+        self.name
+            .resize_default(usize::from(name_length))
+            .expect("Array too long");
+        cursor.read_aligned_bytes(&mut self.name);
+        cursor.check_utf8(&self.name)?;
         let crc_length = cursor.read_aligned_u8();
         match crc_length {
             0 => self.software_image_crc = None,
@@ -150,11 +164,10 @@ impl Deserialize for GetInfoResponse {
         if coa_length > 222 {
             return Err(DeserializeError::ArrayLength);
         }
-        for _ in 0..coa_length {
-            self.certificate_of_authenticity
-                .push(cursor.read_aligned_u8())
-                .expect("Array too long");
-        }
+        self.certificate_of_authenticity
+            .resize_default(usize::from(coa_length))
+            .expect("Array too long");
+        cursor.read_aligned_bytes(&mut self.certificate_of_authenticity);
         Ok(())
     }
 
@@ -15,6 +15,7 @@ impl GetTransportStatisticsRequest {
 impl DataType for GetTransportStatisticsRequest {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 0;
 }
 
 impl Request for GetTransportStatisticsRequest {}
@@ -68,6 +69,7 @@ impl GetTransportStatisticsResponse {
 
 impl DataType for GetTransportStatisticsResponse {
     const EXTENT_BYTES: Option<u32> = Some(192);
+    const MAX_SERIALIZED_SIZE: usize = 61;
 }
 
 impl Response for GetTransportStatisticsResponse {}
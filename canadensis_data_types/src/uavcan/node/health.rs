@@ -3,23 +3,32 @@ use canadensis_encoding::{
 };
 
 /// uavcan.node.Health version 1.0
-#[derive(Debug, Clone)]
+///
+/// Health values are ordered by severity, so the more severe of two values is their maximum.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Health {
+    #[default]
     Nominal = 0,
     Advisory = 1,
     Caution = 2,
     Warning = 3,
 }
 
-impl Default for Health {
-    fn default() -> Self {
-        Health::Nominal
-    }
+impl Health {
+    /// The value of [`Health::Nominal`] on the wire
+    pub const NOMINAL_U8: u8 = 0;
+    /// The value of [`Health::Advisory`] on the wire
+    pub const ADVISORY_U8: u8 = 1;
+    /// The value of [`Health::Caution`] on the wire
+    pub const CAUTION_U8: u8 = 2;
+    /// The value of [`Health::Warning`] on the wire
+    pub const WARNING_U8: u8 = 3;
 }
 
 impl DataType for Health {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 1;
 }
 
 impl Message for Health {}
@@ -31,7 +40,7 @@ impl Serialize for Health {
     }
 
     fn serialize(&self, cursor: &mut WriteCursor<'_>) {
-        cursor.write_u2(self.clone() as u8);
+        cursor.write_u2(*self as u8);
     }
 }
 
@@ -53,10 +62,10 @@ impl Deserialize for Health {
         Self: Sized,
     {
         let health = match cursor.read_u2() {
-            0 => Health::Nominal,
-            1 => Health::Advisory,
-            2 => Health::Caution,
-            3 => Health::Warning,
+            Health::NOMINAL_U8 => Health::Nominal,
+            Health::ADVISORY_U8 => Health::Advisory,
+            Health::CAUTION_U8 => Health::Caution,
+            Health::WARNING_U8 => Health::Warning,
             _ => unreachable!("A 2-bit integer can't be greater than 3"),
         };
         Ok(health)
@@ -18,10 +18,27 @@ impl Heartbeat {
     pub const SUBJECT: SubjectId = SubjectId::from_truncating(7509);
     pub const MAX_PUBLICATION_PERIOD: u16 = 1;
     pub const OFFLINE_TIMEOUT: u16 = 3;
+
+    /// Creates a heartbeat with a vendor-specific status code of 0
+    pub const fn new(uptime: u32, health: Health, mode: Mode) -> Self {
+        Heartbeat {
+            uptime,
+            health,
+            mode,
+            vendor_specific_status_code: 0,
+        }
+    }
+
+    /// Sets the vendor-specific status code
+    pub const fn with_status_code(mut self, vendor_specific_status_code: u8) -> Self {
+        self.vendor_specific_status_code = vendor_specific_status_code;
+        self
+    }
 }
 
 impl DataType for Heartbeat {
     const EXTENT_BYTES: Option<u32> = Some(12);
+    const MAX_SERIALIZED_SIZE: usize = 7;
 }
 
 impl Message for Heartbeat {}
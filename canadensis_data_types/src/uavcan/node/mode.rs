@@ -3,8 +3,9 @@ use canadensis_encoding::{
 };
 
 /// uavcan.node.Mode version 1.0
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub enum Mode {
+    #[default]
     Operational,
     Initialization,
     Maintenance,
@@ -13,15 +14,46 @@ pub enum Mode {
     Other(u8),
 }
 
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Operational
+impl Mode {
+    /// The value of [`Mode::Operational`] on the wire
+    pub const OPERATIONAL_U8: u8 = 0;
+    /// The value of [`Mode::Initialization`] on the wire
+    pub const INITIALIZATION_U8: u8 = 1;
+    /// The value of [`Mode::Maintenance`] on the wire
+    pub const MAINTENANCE_U8: u8 = 2;
+    /// The value of [`Mode::SoftwareUpdate`] on the wire
+    pub const SOFTWARE_UPDATE_U8: u8 = 3;
+}
+
+impl From<u8> for Mode {
+    /// Converts the 3 least significant bits of a value into a mode
+    fn from(bits: u8) -> Self {
+        match bits & 0b111 {
+            Mode::OPERATIONAL_U8 => Mode::Operational,
+            Mode::INITIALIZATION_U8 => Mode::Initialization,
+            Mode::MAINTENANCE_U8 => Mode::Maintenance,
+            Mode::SOFTWARE_UPDATE_U8 => Mode::SoftwareUpdate,
+            other => Mode::Other(other),
+        }
+    }
+}
+
+impl From<Mode> for u8 {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Operational => Mode::OPERATIONAL_U8,
+            Mode::Initialization => Mode::INITIALIZATION_U8,
+            Mode::Maintenance => Mode::MAINTENANCE_U8,
+            Mode::SoftwareUpdate => Mode::SOFTWARE_UPDATE_U8,
+            Mode::Other(other) => other & 0b111,
+        }
     }
 }
 
 impl DataType for Mode {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 1;
 }
 
 impl Message for Mode {}
@@ -33,14 +65,7 @@ impl Serialize for Mode {
     }
 
     fn serialize(&self, cursor: &mut WriteCursor<'_>) {
-        let bits = match self {
-            Mode::Operational => 0,
-            Mode::Initialization => 1,
-            Mode::Maintenance => 2,
-            Mode::SoftwareUpdate => 3,
-            Mode::Other(other) => other & 0b111,
-        };
-        cursor.write_u3(bits);
+        cursor.write_u3((*self).into());
     }
 }
 
@@ -61,14 +86,6 @@ impl Deserialize for Mode {
     where
         Self: Sized,
     {
-        let bits = cursor.read_u3();
-        let mode = match bits {
-            0 => Mode::Operational,
-            1 => Mode::Initialization,
-            2 => Mode::Maintenance,
-            3 => Mode::SoftwareUpdate,
-            other => Mode::Other(other),
-        };
-        Ok(mode)
+        Ok(cursor.read_u3().into())
     }
 }
@@ -14,6 +14,7 @@ pub enum Id {
 impl DataType for Id {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 3;
 }
 
 impl Message for Id {}
@@ -16,6 +16,7 @@ pub struct IoStatistics {
 impl DataType for IoStatistics {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 15;
 }
 
 impl Message for IoStatistics {}
@@ -22,6 +22,8 @@ impl List {
 impl DataType for List {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    // Each list counts as its extent
+    const MAX_SERIALIZED_SIZE: usize = 8466;
 }
 
 impl Message for List {}
@@ -49,7 +51,7 @@ impl Serialize for List {
 impl Deserialize for List {
     fn in_bit_length_set(bit_length: usize) -> bool {
         // TODO: This may be too permissive
-        bit_length % 8 == 0 && {
+        bit_length.is_multiple_of(8) && {
             let bytes = bit_length / 8;
             (16..=8466).contains(&bytes)
         }
@@ -16,6 +16,7 @@ impl ServiceId {
 impl DataType for ServiceId {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
 }
 
 impl Message for ServiceId {}
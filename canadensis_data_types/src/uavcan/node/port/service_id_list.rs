@@ -7,7 +7,7 @@ use canadensis_encoding::{
 /// uavcan.node.port.ServiceIDList version 0.1
 #[derive(Debug, Clone)]
 pub struct ServiceIdList {
-    pub mask: BitArray<{ (ServiceIdList::CAPACITY as usize + 7) / 8 }>,
+    pub mask: BitArray<{ (ServiceIdList::CAPACITY as usize).div_ceil(8) }>,
 }
 
 impl Default for ServiceIdList {
@@ -24,6 +24,7 @@ impl ServiceIdList {
 
 impl DataType for ServiceIdList {
     const EXTENT_BYTES: Option<u32> = Some(128);
+    const MAX_SERIALIZED_SIZE: usize = 64;
 }
 
 impl Message for ServiceIdList {}
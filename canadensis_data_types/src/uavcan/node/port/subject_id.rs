@@ -16,6 +16,7 @@ impl SubjectId {
 impl DataType for SubjectId {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
 }
 
 impl Message for SubjectId {}
@@ -6,8 +6,9 @@ use canadensis_encoding::{
 
 /// uavcan.node.port.SubjectIDList version 0.1
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum SubjectIdList {
-    Mask(BitArray<{ (SubjectIdList::CAPACITY as usize + 7) / 8 }>),
+    Mask(BitArray<{ (SubjectIdList::CAPACITY as usize).div_ceil(8) }>),
     SparseList(heapless::Vec<SubjectId, 255>),
     /// Total means that all subject IDs are in use
     Total,
@@ -26,6 +27,7 @@ impl SubjectIdList {
 
 impl DataType for SubjectIdList {
     const EXTENT_BYTES: Option<u32> = Some(4097);
+    const MAX_SERIALIZED_SIZE: usize = 1025;
 }
 
 impl Message for SubjectIdList {}
@@ -76,7 +78,7 @@ fn in_sparse_list_bit_length_set(bit_length: usize) -> bool {
         false
     } else {
         let array_element_bits = bit_length - 16;
-        array_element_bits / 16 <= 255 && array_element_bits % 16 == 0
+        array_element_bits / 16 <= 255 && array_element_bits.is_multiple_of(16)
     }
 }
 
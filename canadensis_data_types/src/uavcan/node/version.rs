@@ -3,15 +3,23 @@ use canadensis_encoding::{
 };
 
 /// uavcan.node.Version version 1.0
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Version {
     pub major: u8,
     pub minor: u8,
 }
 
+impl Version {
+    /// Creates a version
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Version { major, minor }
+    }
+}
+
 impl DataType for Version {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
 }
 
 impl Message for Version {}
@@ -20,6 +20,7 @@ impl Message for NodeIdAllocationData {}
 impl DataType for NodeIdAllocationData {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 9;
 }
 
 impl Serialize for NodeIdAllocationData {
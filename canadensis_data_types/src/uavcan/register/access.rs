@@ -22,6 +22,7 @@ impl Request for AccessRequest {}
 impl DataType for AccessRequest {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 515;
 }
 
 impl Serialize for AccessRequest {
@@ -37,7 +38,7 @@ impl Serialize for AccessRequest {
 
 impl Deserialize for AccessRequest {
     fn in_bit_length_set(bit_length: usize) -> bool {
-        bit_length % 8 == 0 && (2..=515).contains(&(bit_length / 8))
+        bit_length.is_multiple_of(8) && (2..=515).contains(&(bit_length / 8))
     }
 
     fn deserialize_in_place(
@@ -77,6 +78,7 @@ impl Response for AccessResponse {}
 impl DataType for AccessResponse {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 267;
 }
 
 impl Serialize for AccessResponse {
@@ -96,7 +98,7 @@ impl Serialize for AccessResponse {
 
 impl Deserialize for AccessResponse {
     fn in_bit_length_set(bit_length: usize) -> bool {
-        bit_length % 8 == 0 && (9..=267).contains(&(bit_length / 8))
+        bit_length.is_multiple_of(8) && (9..=267).contains(&(bit_length / 8))
     }
 
     fn deserialize_in_place(
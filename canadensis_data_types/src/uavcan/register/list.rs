@@ -19,6 +19,7 @@ impl Request for ListRequest {}
 impl DataType for ListRequest {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 2;
 }
 
 impl Serialize for ListRequest {
@@ -69,6 +70,7 @@ impl Response for ListResponse {}
 impl DataType for ListResponse {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 256;
 }
 
 impl Serialize for ListResponse {
@@ -1,3 +1,7 @@
+use crate::{set_text, TextTooLong};
+use core::convert::TryFrom;
+use core::str::{self, Utf8Error};
+
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
 };
@@ -8,11 +12,29 @@ pub struct Name {
     pub name: heapless::Vec<u8, 255>,
 }
 
+impl Name {
+    /// Returns the name as a string, or an error if it is not valid UTF-8
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.name)
+    }
+}
+
+impl TryFrom<&str> for Name {
+    type Error = TextTooLong;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        let mut value = Name::default();
+        set_text(&mut value.name, name)?;
+        Ok(value)
+    }
+}
+
 impl Message for Name {}
 
 impl DataType for Name {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 256;
 }
 
 impl Serialize for Name {
@@ -28,7 +50,7 @@ impl Serialize for Name {
 
 impl Deserialize for Name {
     fn in_bit_length_set(bit_length: usize) -> bool {
-        bit_length % 8 == 0 && (1..=256).contains(&(bit_length / 8))
+        bit_length.is_multiple_of(8) && (1..=256).contains(&(bit_length / 8))
     }
 
     fn deserialize_in_place(
@@ -38,10 +60,9 @@ impl Deserialize for Name {
         self.name.clear();
         let length = cursor.read_aligned_u8();
         if usize::from(length) <= self.name.capacity() {
-            for _ in 0..length {
-                self.name.push(cursor.read_aligned_u8()).unwrap();
-            }
-            Ok(())
+            self.name.resize_default(usize::from(length)).unwrap();
+            cursor.read_aligned_bytes(&mut self.name);
+            cursor.check_utf8(&self.name)
         } else {
             Err(DeserializeError::ArrayLength)
         }
@@ -2,14 +2,41 @@ use crate::bits::BitArray;
 use canadensis_encoding::{
     DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
 };
+use core::convert::TryFrom;
 use core::fmt;
 use half::f16;
 
 /// uavcan.register.Value 1.0
 ///
 /// This type is hand-written to avoid having to hand-write a separate type for each variant.
-#[derive(PartialEq, Clone)]
+///
+/// # Conversions
+///
+/// Numbers, slices, and arrays of numbers can be converted into values of the matching variant.
+/// For example, a `u32` or `[u32; 2]` becomes a `Natural32` value:
+///
+/// ```
+/// # use canadensis_data_types::uavcan::register::value::Value;
+/// # use core::convert::TryFrom;
+/// let bit_rates = Value::try_from([1_000_000u32, 4_000_000]).unwrap();
+/// assert_eq!(<[u32; 2]>::try_from(&bit_rates), Ok([1_000_000, 4_000_000]));
+/// ```
+///
+/// Integer, natural, and real values can be converted into any numeric type if they have
+/// the correct number of elements (exactly one for a single number). When the types differ,
+/// each element is converted using these rules:
+///
+/// * Integers that are outside the range of the target integer type saturate to its minimum
+///   or maximum value
+/// * Floating-point values are rounded toward zero when converted into integers, and saturate
+///   at the range of the integer type. NaN becomes zero.
+/// * Integers converted into floating-point types are rounded to the nearest representable value
+/// * Floating-point values that are too large for a smaller floating-point type become infinite
+///
+/// `bool` values and arrays convert to and from `Bit` values, and `&str` converts into `String`.
+#[derive(PartialEq, Clone, Default)]
 pub enum Value {
+    #[default]
     Empty,
     String(heapless::Vec<u8, 256>),
     Unstructured(heapless::Vec<u8, 256>),
@@ -53,17 +80,12 @@ impl fmt::Debug for Value {
     }
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Empty
-    }
-}
-
 impl Message for Value {}
 
 impl DataType for Value {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 259;
 }
 
 impl Serialize for Value {
@@ -99,12 +121,12 @@ impl Serialize for Value {
             Value::String(bytes) => {
                 cursor.write_aligned_u8(1);
                 cursor.write_aligned_u16(bytes.len() as u16);
-                cursor.write_aligned_bytes(&bytes);
+                cursor.write_aligned_bytes(bytes);
             }
             Value::Unstructured(bytes) => {
                 cursor.write_aligned_u8(2);
                 cursor.write_aligned_u16(bytes.len() as u16);
-                cursor.write_aligned_bytes(&bytes);
+                cursor.write_aligned_bytes(bytes);
             }
             Value::Bit(bit_array) => {
                 cursor.write_aligned_u8(3);
@@ -195,7 +217,7 @@ impl Serialize for Value {
 impl Deserialize for Value {
     fn in_bit_length_set(bit_length: usize) -> bool {
         // This might be too permissive, but since the type is sealed it won't really be used.
-        if bit_length % 8 == 0 {
+        if bit_length.is_multiple_of(8) {
             let byte_length = bit_length / 8;
             (1..=259).contains(&byte_length)
         } else {
@@ -216,10 +238,10 @@ impl Deserialize for Value {
                 let length = cursor.read_aligned_u16();
                 let mut bytes = heapless::Vec::new();
                 if usize::from(length) <= bytes.capacity() {
-                    for _ in 0..length {
-                        bytes.push(cursor.read_aligned_u8()).ok().unwrap();
-                    }
+                    bytes.resize_default(usize::from(length)).ok().unwrap();
+                    cursor.read_aligned_bytes(&mut bytes);
                     if tag == 1 {
+                        cursor.check_utf8(&bytes)?;
                         *self = Value::String(bytes);
                     } else {
                         *self = Value::Unstructured(bytes);
@@ -334,9 +356,8 @@ impl Deserialize for Value {
                 let length = cursor.read_aligned_u16();
                 let mut values = heapless::Vec::new();
                 if usize::from(length) <= values.capacity() {
-                    for _ in 0..length {
-                        values.push(cursor.read_aligned_u8()).ok().unwrap();
-                    }
+                    values.resize_default(usize::from(length)).ok().unwrap();
+                    cursor.read_aligned_bytes(&mut values);
                     *self = Value::Natural8(values);
                 } else {
                     return Err(DeserializeError::ArrayLength);
@@ -395,3 +416,398 @@ impl Deserialize for Value {
         Ok(value)
     }
 }
+
+/// An error that occurs when converting between a register value and another type
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConversionError {
+    /// The value has a variant that can't be converted into the requested type
+    Type,
+    /// The value has the wrong number of elements, or there are too many elements to fit in
+    /// a value
+    Length,
+}
+
+/// A numeric type that register values can be converted into
+trait Element: Copy + Default {
+    fn from_i64(value: i64) -> Self;
+    fn from_u64(value: u64) -> Self;
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! integer_element {
+    ($type:ty) => {
+        impl Element for $type {
+            fn from_i64(value: i64) -> Self {
+                <$type>::try_from(value).unwrap_or(if value < 0 {
+                    <$type>::MIN
+                } else {
+                    <$type>::MAX
+                })
+            }
+            fn from_u64(value: u64) -> Self {
+                <$type>::try_from(value).unwrap_or(<$type>::MAX)
+            }
+            fn from_f64(value: f64) -> Self {
+                // Float to integer casts saturate and convert NaN to zero
+                value as $type
+            }
+        }
+    };
+}
+
+integer_element!(u8);
+integer_element!(u16);
+integer_element!(u32);
+integer_element!(u64);
+integer_element!(i8);
+integer_element!(i16);
+integer_element!(i32);
+integer_element!(i64);
+
+impl Element for f16 {
+    fn from_i64(value: i64) -> Self {
+        f16::from_f64(value as f64)
+    }
+    fn from_u64(value: u64) -> Self {
+        f16::from_f64(value as f64)
+    }
+    fn from_f64(value: f64) -> Self {
+        f16::from_f64(value)
+    }
+}
+impl Element for f32 {
+    fn from_i64(value: i64) -> Self {
+        value as f32
+    }
+    fn from_u64(value: u64) -> Self {
+        value as f32
+    }
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+impl Element for f64 {
+    fn from_i64(value: i64) -> Self {
+        value as f64
+    }
+    fn from_u64(value: u64) -> Self {
+        value as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+/// Converts the elements of a numeric value into `out`, which must have the same length
+/// as the value
+fn read_elements<T: Element>(value: &Value, out: &mut [T]) -> Result<(), ConversionError> {
+    fn convert<S: Copy, T>(
+        source: &[S],
+        out: &mut [T],
+        convert: impl Fn(S) -> T,
+    ) -> Result<(), ConversionError> {
+        if source.len() != out.len() {
+            return Err(ConversionError::Length);
+        }
+        for (out_element, source_element) in out.iter_mut().zip(source) {
+            *out_element = convert(*source_element);
+        }
+        Ok(())
+    }
+    match value {
+        Value::Integer64(values) => convert(values, out, T::from_i64),
+        Value::Integer32(values) => convert(values, out, |v| T::from_i64(v.into())),
+        Value::Integer16(values) => convert(values, out, |v| T::from_i64(v.into())),
+        Value::Integer8(values) => convert(values, out, |v| T::from_i64(v.into())),
+        Value::Natural64(values) => convert(values, out, T::from_u64),
+        Value::Natural32(values) => convert(values, out, |v| T::from_u64(v.into())),
+        Value::Natural16(values) => convert(values, out, |v| T::from_u64(v.into())),
+        Value::Natural8(values) => convert(values, out, |v| T::from_u64(v.into())),
+        Value::Real64(values) => convert(values, out, T::from_f64),
+        Value::Real32(values) => convert(values, out, |v| T::from_f64(v.into())),
+        Value::Real16(values) => convert(values, out, |v| T::from_f64(v.to_f64())),
+        Value::Empty | Value::String(_) | Value::Unstructured(_) | Value::Bit(_) => {
+            Err(ConversionError::Type)
+        }
+    }
+}
+
+macro_rules! numeric_conversions {
+    ($type:ty, $variant:ident) => {
+        impl From<$type> for Value {
+            fn from(value: $type) -> Self {
+                Value::$variant(heapless::Vec::from_slice(&[value]).unwrap())
+            }
+        }
+
+        impl TryFrom<&[$type]> for Value {
+            type Error = ConversionError;
+
+            fn try_from(values: &[$type]) -> Result<Self, Self::Error> {
+                heapless::Vec::from_slice(values)
+                    .map(Value::$variant)
+                    .map_err(|_| ConversionError::Length)
+            }
+        }
+
+        impl<const N: usize> TryFrom<[$type; N]> for Value {
+            type Error = ConversionError;
+
+            fn try_from(values: [$type; N]) -> Result<Self, Self::Error> {
+                Value::try_from(&values[..])
+            }
+        }
+
+        impl TryFrom<&Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                let mut out = [<$type>::default()];
+                read_elements(value, &mut out)?;
+                Ok(out[0])
+            }
+        }
+
+        impl<const N: usize> TryFrom<&Value> for [$type; N] {
+            type Error = ConversionError;
+
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                let mut out = [<$type>::default(); N];
+                read_elements(value, &mut out)?;
+                Ok(out)
+            }
+        }
+    };
+}
+
+numeric_conversions!(u8, Natural8);
+numeric_conversions!(u16, Natural16);
+numeric_conversions!(u32, Natural32);
+numeric_conversions!(u64, Natural64);
+numeric_conversions!(i8, Integer8);
+numeric_conversions!(i16, Integer16);
+numeric_conversions!(i32, Integer32);
+numeric_conversions!(i64, Integer64);
+numeric_conversions!(f16, Real16);
+numeric_conversions!(f32, Real32);
+numeric_conversions!(f64, Real64);
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        let mut bits = BitArray::new(1);
+        bits.set(0, value);
+        Value::Bit(bits)
+    }
+}
+
+impl TryFrom<&[bool]> for Value {
+    type Error = ConversionError;
+
+    fn try_from(values: &[bool]) -> Result<Self, Self::Error> {
+        if values.len() > 2048 {
+            return Err(ConversionError::Length);
+        }
+        let mut bits = BitArray::new(values.len());
+        for (i, value) in values.iter().enumerate() {
+            bits.set(i, *value);
+        }
+        Ok(Value::Bit(bits))
+    }
+}
+
+impl<const N: usize> TryFrom<[bool; N]> for Value {
+    type Error = ConversionError;
+
+    fn try_from(values: [bool; N]) -> Result<Self, Self::Error> {
+        Value::try_from(&values[..])
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let [value] = <[bool; 1]>::try_from(value)?;
+        Ok(value)
+    }
+}
+
+impl<const N: usize> TryFrom<&Value> for [bool; N] {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bit(bits) if bits.len() == N => {
+                let mut out = [false; N];
+                for (i, out_bit) in out.iter_mut().enumerate() {
+                    *out_bit = bits.get(i);
+                }
+                Ok(out)
+            }
+            Value::Bit(_) => Err(ConversionError::Length),
+            _ => Err(ConversionError::Type),
+        }
+    }
+}
+
+impl TryFrom<&str> for Value {
+    type Error = ConversionError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        heapless::Vec::from_slice(value.as_bytes())
+            .map(Value::String)
+            .map_err(|_| ConversionError::Length)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConversionError, Value};
+    use core::convert::TryFrom;
+    use half::f16;
+
+    #[test]
+    fn same_type() {
+        assert_eq!(Ok(200u8), u8::try_from(&Value::from(200u8)));
+        assert_eq!(Ok(-3i64), i64::try_from(&Value::from(-3i64)));
+        assert_eq!(Ok(1.5f32), f32::try_from(&Value::from(1.5f32)));
+        let value = Value::try_from([1u16, 2, 3]).unwrap();
+        assert!(matches!(value, Value::Natural16(_)));
+        assert_eq!(Ok([1u16, 2, 3]), <[u16; 3]>::try_from(&value));
+    }
+
+    #[test]
+    fn integer_saturation() {
+        // Signed to unsigned
+        assert_eq!(Ok(0u8), u8::try_from(&Value::from(-1i32)));
+        assert_eq!(Ok(u8::MAX), u8::try_from(&Value::from(256i32)));
+        assert_eq!(Ok(0u64), u64::try_from(&Value::from(i64::MIN)));
+        // Signed to smaller signed
+        assert_eq!(Ok(i8::MIN), i8::try_from(&Value::from(-129i16)));
+        assert_eq!(Ok(i8::MAX), i8::try_from(&Value::from(128i16)));
+        assert_eq!(Ok(-128i8), i8::try_from(&Value::from(-128i64)));
+        // Unsigned to signed and smaller unsigned
+        assert_eq!(Ok(i8::MAX), i8::try_from(&Value::from(128u8)));
+        assert_eq!(Ok(i64::MAX), i64::try_from(&Value::from(u64::MAX)));
+        assert_eq!(Ok(u16::MAX), u16::try_from(&Value::from(70_000u32)));
+        // Values in range are unchanged
+        assert_eq!(Ok(65_535u16), u16::try_from(&Value::from(65_535u64)));
+        assert_eq!(Ok(-5i16), i16::try_from(&Value::from(-5i8)));
+    }
+
+    #[test]
+    fn float_to_integer() {
+        // Rounded toward zero
+        assert_eq!(Ok(2u8), u8::try_from(&Value::from(2.9f64)));
+        assert_eq!(Ok(-2i32), i32::try_from(&Value::from(-2.9f32)));
+        assert_eq!(Ok(0u32), u32::try_from(&Value::from(-0.5f64)));
+        assert_eq!(Ok(3i16), i16::try_from(&Value::from(f16::from_f32(3.75))));
+        // Saturated
+        assert_eq!(Ok(u8::MAX), u8::try_from(&Value::from(300.0f64)));
+        assert_eq!(Ok(0u8), u8::try_from(&Value::from(-300.0f64)));
+        assert_eq!(Ok(i16::MIN), i16::try_from(&Value::from(-1e10f32)));
+        assert_eq!(Ok(i64::MAX), i64::try_from(&Value::from(f64::INFINITY)));
+        assert_eq!(Ok(u32::MIN), u32::try_from(&Value::from(f32::NEG_INFINITY)));
+        // NaN
+        assert_eq!(Ok(0u8), u8::try_from(&Value::from(f64::NAN)));
+        assert_eq!(Ok(0i64), i64::try_from(&Value::from(f32::NAN)));
+        assert_eq!(Ok(0i8), i8::try_from(&Value::from(f16::NAN)));
+    }
+
+    #[test]
+    fn integer_to_float() {
+        assert_eq!(Ok(-7.0f64), f64::try_from(&Value::from(-7i8)));
+        // 2^24 + 1 is not representable as f32 and rounds to the nearest even value
+        assert_eq!(
+            Ok(16_777_216.0f32),
+            f32::try_from(&Value::from(16_777_217u32))
+        );
+        assert_eq!(
+            Ok(18_446_744_073_709_551_616.0f32),
+            f32::try_from(&Value::from(u64::MAX))
+        );
+        assert_eq!(
+            Ok(f16::from_f32(2048.0)),
+            f16::try_from(&Value::from(2049u16))
+        );
+        // Too large for f16
+        assert_eq!(Ok(f16::INFINITY), f16::try_from(&Value::from(100_000i32)));
+        assert_eq!(
+            Ok(f16::NEG_INFINITY),
+            f16::try_from(&Value::from(-100_000i32))
+        );
+    }
+
+    #[test]
+    fn float_to_float() {
+        assert_eq!(Ok(0.5f64), f64::try_from(&Value::from(f16::from_f32(0.5))));
+        assert_eq!(Ok(0.1f32), f32::try_from(&Value::from(0.1f64)));
+        assert_eq!(Ok(f32::INFINITY), f32::try_from(&Value::from(1e300f64)));
+        assert_eq!(
+            Ok(f32::NEG_INFINITY),
+            f32::try_from(&Value::from(-1e300f64))
+        );
+        assert_eq!(Ok(f16::INFINITY), f16::try_from(&Value::from(1e10f32)));
+        assert!(f32::try_from(&Value::from(f64::NAN)).unwrap().is_nan());
+        assert!(f16::try_from(&Value::from(f64::NAN)).unwrap().is_nan());
+    }
+
+    #[test]
+    fn arrays_convert_each_element() {
+        let value = Value::try_from([-1i32, 1000, 7]).unwrap();
+        assert_eq!(Ok([0u8, 255, 7]), <[u8; 3]>::try_from(&value));
+        let value = Value::try_from([1.9f32, f32::NAN, -1e20]).unwrap();
+        assert_eq!(Ok([1i16, 0, i16::MIN]), <[i16; 3]>::try_from(&value));
+    }
+
+    #[test]
+    fn length_errors() {
+        let value = Value::try_from([1u8, 2]).unwrap();
+        assert_eq!(Err(ConversionError::Length), u8::try_from(&value));
+        assert_eq!(Err(ConversionError::Length), <[u8; 3]>::try_from(&value));
+        assert_eq!(Err(ConversionError::Length), <[f32; 1]>::try_from(&value));
+        // Natural64 values can have at most 32 elements
+        assert_eq!(Err(ConversionError::Length), Value::try_from([0u64; 33]));
+        assert!(Value::try_from([0u64; 32]).is_ok());
+        assert_eq!(
+            Err(ConversionError::Length),
+            Value::try_from(&[true; 2049][..])
+        );
+        let too_long = [b'a'; 257];
+        assert_eq!(
+            Err(ConversionError::Length),
+            Value::try_from(core::str::from_utf8(&too_long).unwrap())
+        );
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(Err(ConversionError::Type), u8::try_from(&Value::Empty));
+        assert_eq!(
+            Err(ConversionError::Type),
+            f32::try_from(&Value::try_from("1.0").unwrap())
+        );
+        assert_eq!(Err(ConversionError::Type), u8::try_from(&Value::from(true)));
+        assert_eq!(
+            Err(ConversionError::Type),
+            bool::try_from(&Value::from(1u8))
+        );
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(Ok(true), bool::try_from(&Value::from(true)));
+        assert_eq!(Ok(false), bool::try_from(&Value::from(false)));
+        let value = Value::try_from([true, false, true]).unwrap();
+        assert_eq!(Ok([true, false, true]), <[bool; 3]>::try_from(&value));
+        assert_eq!(Err(ConversionError::Length), bool::try_from(&value));
+    }
+
+    #[test]
+    fn string() {
+        match Value::try_from("uavcan").unwrap() {
+            Value::String(bytes) => assert_eq!(b"uavcan", &bytes[..]),
+            other => panic!("Unexpected value {:?}", other),
+        }
+    }
+}
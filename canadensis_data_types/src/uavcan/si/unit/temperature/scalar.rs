@@ -0,0 +1,50 @@
+use canadensis_encoding::{
+    DataType, Deserialize, DeserializeError, Message, ReadCursor, Serialize, WriteCursor,
+};
+
+/// uavcan.si.unit.temperature.Scalar version 1.0
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scalar {
+    pub kelvin: f32,
+}
+
+impl DataType for Scalar {
+    // Sealed type
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 4;
+}
+
+impl Message for Scalar {}
+
+impl Serialize for Scalar {
+    fn size_bits(&self) -> usize {
+        32
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_f32(self.kelvin);
+    }
+}
+
+impl Deserialize for Scalar {
+    fn in_bit_length_set(bit_length: usize) -> bool {
+        bit_length == 32
+    }
+
+    fn deserialize_in_place(
+        &mut self,
+        cursor: &mut ReadCursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.kelvin = cursor.read_f32();
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut ReadCursor<'_>) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        Ok(Scalar {
+            kelvin: cursor.read_f32(),
+        })
+    }
+}
@@ -18,6 +18,7 @@ impl Message for SynchronizedTimestamp {}
 impl DataType for SynchronizedTimestamp {
     // Sealed type
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 7;
 }
 
 impl Serialize for SynchronizedTimestamp {
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 extern crate canadensis_derive_register_block;
 extern crate canadensis_node;
 
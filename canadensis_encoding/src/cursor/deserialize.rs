@@ -1,4 +1,5 @@
 use core::cmp;
+use core::convert::TryInto;
 
 use half::f16;
 
@@ -8,6 +9,10 @@ use crate::{Deserialize, DeserializeError};
 ///
 /// Functions that read values will return zero when reading beyond the end of the bytes,
 /// in accordance with the implicit zero extension rule (specification section 3.7.1.5)
+///
+/// Deserialize implementations always check array lengths and union tags. A strict cursor
+/// (created with [`new_strict`](#method.new_strict)) also asks them to check that string fields
+/// contain valid UTF-8.
 pub struct ReadCursor<'b> {
     /// The bytes available to read from
     ///
@@ -21,6 +26,8 @@ pub struct ReadCursor<'b> {
     ///
     /// Invariant: This is in the range 0..=7.
     bit_index: u8,
+    /// True if additional validation is enabled
+    strict: bool,
 }
 
 impl<'b> ReadCursor<'b> {
@@ -29,120 +36,129 @@ impl<'b> ReadCursor<'b> {
         ReadCursor {
             bytes,
             bit_index: 0,
+            strict: false,
         }
     }
 
-    /// Read an x-bit unsigned integer (x must be in the range 0..=8)
-    fn read_up_to_u8(&mut self, bits: u8) -> u8 {
-        debug_assert!(bits <= 8);
+    /// Creates a strict cursor that will read starting at the beginning of the provided slice
+    ///
+    /// When reading from a strict cursor, deserialize implementations return
+    /// [`DeserializeError::InvalidUtf8`] if a string field does not contain valid UTF-8.
+    pub fn new_strict(bytes: &'b [u8]) -> Self {
+        ReadCursor {
+            bytes,
+            bit_index: 0,
+            strict: true,
+        }
+    }
+
+    /// Returns true if this cursor is strict
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Checks the content of a string field that has been read
+    ///
+    /// If this cursor is strict and the bytes are not valid UTF-8, this function returns an
+    /// error. Otherwise, it returns `Ok(())`.
+    pub fn check_utf8(&self, bytes: &[u8]) -> Result<(), DeserializeError> {
+        if self.strict && core::str::from_utf8(bytes).is_err() {
+            Err(DeserializeError::InvalidUtf8)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads a `bits`-bit unsigned integer (`bits` must be in the range 0..=64)
+    ///
+    /// This loads the current 64-bit word of the buffer and extracts the value with shifts and
+    /// masks, instead of reading each byte separately.
+    fn read_bits(&mut self, bits: u8) -> u64 {
+        debug_assert!(bits <= 64);
         if bits == 0 {
             return 0;
         }
-
-        let value = if self.bit_index <= 8 - bits {
-            // Read all bits from the current byte, aligned to the right
-            self.read_current() >> self.bit_index
+        let bit_index = u32::from(self.bit_index);
+        let end_bits = bit_index + u32::from(bits);
+        let word = if end_bits <= 8 {
+            // Everything is in the current byte
+            u64::from(self.bytes.first().cloned().unwrap_or(0))
         } else {
-            // Need to split across two bytes
-            // current_bits: The less significant bits are aligned to the left of the current byte.
-            let current_bits = self.read_current() >> self.bit_index;
-            // next_bits: The more significant bits are aligned to the right of the next byte.
-            let next_bits = self.read_next() << (8 - self.bit_index);
-            next_bits | current_bits
+            self.read_word(end_bits.div_ceil(8) as usize)
         };
-
-        // Constrain value to fit with the correct number of bits
-        // Use 16 bits to correctly handle the case when bits = 8
-        let mask = ((1u16 << u16::from(bits)) - 1) as u8;
-        let value = value & mask;
-
+        let mut value = word >> bit_index;
+        if end_bits > 64 {
+            // The most significant bits are in the byte after the word
+            value |= u64::from(self.bytes.get(8).cloned().unwrap_or(0)) << (64 - bit_index);
+        }
+        if bits != 64 {
+            value &= (1u64 << bits) - 1;
+        }
         self.advance_bits(usize::from(bits));
         value
     }
 
+    /// Read an x-bit unsigned integer (x must be in the range 0..=8)
+    #[inline]
+    fn read_up_to_u8(&mut self, bits: u8) -> u8 {
+        debug_assert!(bits <= 8);
+        self.read_bits(bits) as u8
+    }
+
     /// Reads an x-bit unsigned integer (x must be in the range 1..=16)
+    #[inline]
     fn read_up_to_u16(&mut self, bits: u8) -> u16 {
         debug_assert!(bits <= 16);
-        let mut shift_bits = 0;
-        // Read whole bytes, least significant first
-        let mut value = 0;
-        if bits > 8 {
-            value = u16::from(self.read_up_to_u8(8));
-            shift_bits += 8;
-        }
-        // Write any remaining bits that don't fill up a byte
-        value |= u16::from(self.read_up_to_u8(bits % 8)) << shift_bits;
-        value
+        self.read_bits(bits) as u16
     }
 
     /// Reads an x-bit unsigned integer (x must be in the range 1..=32)
+    #[inline]
     fn read_up_to_u32(&mut self, bits: u8) -> u32 {
         debug_assert!(bits <= 32);
-        let mut shift_bits = 0;
-        // Read whole bytes, least significant first
-        let mut value = 0;
-        for _ in 0..(bits / 8) {
-            value |= u32::from(self.read_up_to_u8(8)) << shift_bits;
-            shift_bits += 8;
-        }
-        // Write any remaining bits that don't fill up a byte
-        value |= u32::from(self.read_up_to_u8(bits % 8)) << shift_bits;
-        value
+        self.read_bits(bits) as u32
     }
 
     /// Reads an x-bit unsigned integer (x must be in the range 1..=64)
+    #[inline]
     fn read_up_to_u64(&mut self, bits: u8) -> u64 {
-        debug_assert!(bits <= 64);
-        let mut shift_bits = 0;
-        // Read whole bytes, least significant first
-        let mut value = 0;
-        for _ in 0..(bits / 8) {
-            value |= u64::from(self.read_up_to_u8(8)) << shift_bits;
-            shift_bits += 8;
-        }
-        // Write any remaining bits that don't fill up a byte
-        if bits != 64 {
-            value |= u64::from(self.read_up_to_u8(bits % 8)) << shift_bits;
-        }
-        value
+        self.read_bits(bits)
     }
 
     pub fn read_aligned_u8(&mut self) -> u8 {
         assert!(self.is_aligned_to_8_bits());
-        let value = self.read_current();
-        self.advance_bytes(1);
-        value
+        self.read_bits(8) as u8
     }
 
     pub fn read_aligned_u16(&mut self) -> u16 {
-        // Least significant byte first
-        let lsb = self.read_aligned_u8();
-        let msb = self.read_aligned_u8();
-        u16::from(msb) << 8 | u16::from(lsb)
+        assert!(self.is_aligned_to_8_bits());
+        self.read_bits(16) as u16
     }
 
     pub fn read_aligned_u32(&mut self) -> u32 {
-        // Least significant byte first
-        let lsbs = self.read_aligned_u16();
-        let msbs = self.read_aligned_u16();
-        u32::from(msbs) << 16 | u32::from(lsbs)
+        assert!(self.is_aligned_to_8_bits());
+        self.read_bits(32) as u32
     }
 
     pub fn read_aligned_u64(&mut self) -> u64 {
-        // Least significant byte first
-        let lsbs = self.read_aligned_u32();
-        let msbs = self.read_aligned_u32();
-        u64::from(msbs) << 32 | u64::from(lsbs)
+        assert!(self.is_aligned_to_8_bits());
+        self.read_bits(64)
     }
 
-    /// Returns the value of the current byte being read, or 0 if the cursor is past the end
-    fn read_current(&self) -> u8 {
-        self.bytes.get(0).cloned().unwrap_or(0)
-    }
-    /// Returns the value of the byte after current byte being read, or 0 if that position is past
-    /// the end
-    fn read_next(&self) -> u8 {
-        self.bytes.get(1).cloned().unwrap_or(0)
+    /// Returns the next 8 bytes as a little-endian word
+    ///
+    /// If fewer than 8 bytes remain, this reads only the first `needed` bytes and fills the rest
+    /// of the word with zeros.
+    fn read_word(&self, needed: usize) -> u64 {
+        match self.bytes.get(..8) {
+            Some(word_bytes) => u64::from_le_bytes(word_bytes.try_into().unwrap()),
+            None => self
+                .bytes
+                .iter()
+                .take(needed)
+                .enumerate()
+                .fold(0, |word, (i, &byte)| word | (u64::from(byte) << (8 * i))),
+        }
     }
 
     /// Advances self.bit_index and self.bytes to reflect that bits have been read
@@ -205,10 +221,32 @@ impl<'b> ReadCursor<'b> {
         f64::from_bits(self.read_aligned_u64())
     }
 
+    /// Reads a byte array that starts on a byte boundary
+    ///
+    /// This copies all available bytes at once instead of reading them one at a time. If there
+    /// are not enough bytes available, the remaining bytes are filled with zero.
+    pub fn read_aligned_bytes(&mut self, bytes: &mut [u8]) {
+        assert!(self.is_aligned_to_8_bits());
+        let available = cmp::min(bytes.len(), self.bytes.len());
+        let (present, missing) = bytes.split_at_mut(available);
+        present.copy_from_slice(&self.bytes[..available]);
+        missing.fill(0);
+        self.advance_bytes(available);
+    }
+
     /// Reads a byte array
     pub fn read_bytes(&mut self, bytes: &mut [u8]) {
-        for byte in bytes {
-            *byte = self.read_u8();
+        if self.is_aligned_to_8_bits() {
+            self.read_aligned_bytes(bytes);
+        } else {
+            // Read 8 bytes at a time
+            let mut chunks = bytes.chunks_exact_mut(8);
+            for chunk in &mut chunks {
+                chunk.copy_from_slice(&self.read_bits(64).to_le_bytes());
+            }
+            for byte in chunks.into_remainder() {
+                *byte = self.read_u8();
+            }
         }
     }
 
@@ -225,13 +263,8 @@ impl<'b> ReadCursor<'b> {
         self.align_to_8_bits();
         let status = if T::EXTENT_BYTES.is_some() {
             // This is a delimited type. Read the header and fork to read the object
-            let composite_length_bytes = self.read_aligned_u32() as usize;
-            if composite_length_bytes > self.bytes.len() {
-                Err(DeserializeError::DelimitedLength)
-            } else {
-                let mut forked = self.fork(composite_length_bytes);
-                T::deserialize(&mut forked)
-            }
+            self.read_delimited()
+                .and_then(|mut forked| T::deserialize(&mut forked))
         } else {
             // Sealed type, read directly
             T::deserialize(self)
@@ -261,6 +294,19 @@ impl<'b> ReadCursor<'b> {
     ///
     /// This function will panic if this cursor is not aligned to a byte boundary (8 bits),
     /// or if bytes is less then the number of bytes remaining for this cursor to read.
+    /// Reads a delimiter header and returns a cursor over the delimited object
+    ///
+    /// This part of [`read_composite`](#method.read_composite) does not depend on the composite
+    /// type, so it is kept separate to avoid compiling a copy for each type.
+    fn read_delimited(&mut self) -> Result<Self, DeserializeError> {
+        let composite_length_bytes = self.read_aligned_u32() as usize;
+        if composite_length_bytes > self.bytes.len() {
+            Err(DeserializeError::DelimitedLength)
+        } else {
+            Ok(self.fork(composite_length_bytes))
+        }
+    }
+
     fn fork(&mut self, fork_bytes: usize) -> Self {
         assert_eq!(self.bit_index, 0, "fork(): Not aligned to a byte");
         assert!(
@@ -271,6 +317,7 @@ impl<'b> ReadCursor<'b> {
         let forked_cursor = ReadCursor {
             bytes: &self.bytes[..fork_bytes],
             bit_index: 0,
+            strict: self.strict,
         };
         self.bytes = &self.bytes[fork_bytes..];
         forked_cursor
@@ -799,6 +846,35 @@ impl ReadCursor<'_> {
 mod test {
     use super::*;
 
+    #[test]
+    fn aligned_bytes() {
+        let bytes = [0x01u8, 0x02, 0x03];
+        let mut cursor = ReadCursor::new(&bytes);
+        let mut first = [0u8; 2];
+        cursor.read_aligned_bytes(&mut first);
+        assert_eq!(first, [0x01, 0x02]);
+        // Implicit zero extension
+        let mut rest = [0xffu8; 3];
+        cursor.read_aligned_bytes(&mut rest);
+        assert_eq!(rest, [0x03, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn strict_utf8() {
+        let bytes = [0x68u8, 0xff];
+        let cursor = ReadCursor::new(&bytes);
+        assert!(cursor.check_utf8(&bytes).is_ok());
+        let mut cursor = ReadCursor::new_strict(&bytes);
+        assert!(cursor.is_strict());
+        assert!(cursor.check_utf8(&bytes[..1]).is_ok());
+        assert!(matches!(
+            cursor.check_utf8(&bytes),
+            Err(DeserializeError::InvalidUtf8)
+        ));
+        // Forked cursors are also strict
+        assert!(cursor.fork(1).is_strict());
+    }
+
     #[test]
     fn u8_one() {
         let bytes = [0xABu8];
@@ -822,8 +898,7 @@ mod test {
 
     #[test]
     fn u64_one() {
-        let bytes = [0x67u8, 0x45, 0x23, 0x01,
-                                0xD4, 0xC3, 0xB2, 0xA1];
+        let bytes = [0x67u8, 0x45, 0x23, 0x01, 0xD4, 0xC3, 0xB2, 0xA1];
         let mut cursor = ReadCursor::new(&bytes);
         assert_eq!(cursor.read_u64(), 0xA1B2C3D401234567);
     }
@@ -844,9 +919,39 @@ mod test {
 
     #[test]
     fn f64_one() {
-        let bytes = [0x67u8, 0x45, 0x23, 0x01,
-                                0xD4, 0xC3, 0xB2, 0xA1];
+        let bytes = [0x67u8, 0x45, 0x23, 0x01, 0xD4, 0xC3, 0xB2, 0xA1];
         let mut cursor = ReadCursor::new(&bytes);
         assert_eq!(cursor.read_f64(), f64::from_bits(0xA1B2C3D401234567));
     }
+
+    #[test]
+    fn u64_unaligned_at_end() {
+        let value = 0xfd569a8b24bca386u64;
+        for offset in 0..8 {
+            let bytes = ((u128::from(value) << offset) | ((1u128 << offset) - 1)).to_le_bytes();
+            let length = (offset + 64usize).div_ceil(8);
+            let mut cursor = ReadCursor::new(&bytes[..length]);
+            for _ in 0..offset {
+                assert!(cursor.read_bool());
+            }
+            assert_eq!(cursor.read_u64(), value, "offset {}", offset);
+            // Implicit zero extension
+            assert_eq!(cursor.read_u64(), 0);
+        }
+    }
+
+    #[test]
+    fn bytes_unaligned() {
+        let bytes = [
+            0x1au8, 0x30, 0x52, 0x74, 0x96, 0xb8, 0xda, 0xfc, 0x0e, 0x21, 0x03,
+        ];
+        let mut cursor = ReadCursor::new(&bytes);
+        assert_eq!(cursor.read_u4(), 0xa);
+        let mut array = [0u8; 12];
+        cursor.read_bytes(&mut array);
+        assert_eq!(
+            array,
+            [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x10, 0x32, 0x00, 0x00]
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use half::f16;
 
-use crate::Serialize;
+use crate::{Serialize, SerializeError};
 use core::convert::TryInto;
 
 /// A cursor over a byte slice for easy serializing of UAVCAN data types
@@ -21,6 +21,8 @@ pub struct WriteCursor<'b> {
     ///
     /// Invariant: This is in the range 0..=7.
     bit_index: u8,
+    /// The first error that was detected while writing
+    error: Option<SerializeError>,
 }
 
 impl<'b> WriteCursor<'b> {
@@ -32,6 +34,7 @@ impl<'b> WriteCursor<'b> {
             bytes,
             bytes_written: 0,
             bit_index: 0,
+            error: None,
         }
     }
 
@@ -49,71 +52,78 @@ impl<'b> WriteCursor<'b> {
         self.bit_index == 0
     }
 
-    /// Writes an x-bit unsigned integer (x must be in the range 1..=64)
-    fn write_up_to_u64(&mut self, mut value: u64, bits: u8) {
+    /// Writes the `bits` least significant bits of a value (`bits` must be in the range 0..=64)
+    ///
+    /// The value is shifted into place and combined with the current 64-bit word of the buffer
+    /// in one operation, instead of writing each byte separately. Because the cursor writes
+    /// sequentially and the buffer starts out zeroed, the bits after the cursor are still zero
+    /// and can be combined with a bitwise OR.
+    fn write_bits(&mut self, value: u64, bits: u8) {
         debug_assert!(bits <= 64);
-        // Write whole bytes, least significant first
-        for _ in 0..(bits / 8) {
-            self.write_up_to_u8(value as u8, 8);
-            value >>= 8;
+        if bits == 0 {
+            return;
+        }
+        self.check_length(usize::from(bits));
+        let value = if bits == 64 {
+            value
+        } else {
+            value & ((1u64 << bits) - 1)
+        };
+        let bit_index = u32::from(self.bit_index);
+        // The number of bytes that this value touches, in the range 1..=9
+        let byte_count = (bit_index + u32::from(bits)).div_ceil(8) as usize;
+        let shifted = value << bit_index;
+
+        let remaining_bytes = self.remaining_bytes();
+        if byte_count == 1 {
+            // Everything fits in the current byte
+            remaining_bytes[0] |= shifted as u8;
+        } else if let Some(word_bytes) = remaining_bytes.get_mut(..8) {
+            let word_bytes: &mut [u8; 8] = word_bytes.try_into().unwrap();
+            let word = u64::from_le_bytes(*word_bytes) | shifted;
+            *word_bytes = word.to_le_bytes();
+        } else {
+            // Near the end of the buffer, combine one byte at a time
+            for (i, &byte) in shifted.to_le_bytes()[..byte_count.min(8)]
+                .iter()
+                .enumerate()
+            {
+                remaining_bytes[i] |= byte;
+            }
+        }
+        if byte_count == 9 {
+            // The most significant bits did not fit into the word
+            remaining_bytes[8] |= (value >> (64 - bit_index)) as u8;
         }
-        // Write any remaining bits that don't fill up a byte
-        self.write_up_to_u8(value as u8, bits % 8);
+
+        self.advance_bits(usize::from(bits));
+    }
+
+    /// Writes an x-bit unsigned integer (x must be in the range 1..=64)
+    #[inline]
+    fn write_up_to_u64(&mut self, value: u64, bits: u8) {
+        self.write_bits(value, bits);
     }
 
     /// Writes an x-bit unsigned integer (x must be in the range 1..=32)
-    fn write_up_to_u32(&mut self, mut value: u32, bits: u8) {
+    #[inline]
+    fn write_up_to_u32(&mut self, value: u32, bits: u8) {
         debug_assert!(bits <= 32);
-        // Write whole bytes, least significant first
-        for _ in 0..(bits / 8) {
-            self.write_up_to_u8(value as u8, 8);
-            value >>= 8;
-        }
-        // Write any remaining bits that don't fill up a byte
-        self.write_up_to_u8(value as u8, bits % 8);
+        self.write_bits(u64::from(value), bits);
     }
 
     /// Writes an x-bit unsigned integer (x must be in the range 1..=16)
-    fn write_up_to_u16(&mut self, mut value: u16, bits: u8) {
+    #[inline]
+    fn write_up_to_u16(&mut self, value: u16, bits: u8) {
         debug_assert!(bits <= 16);
-        // Write whole bytes, least significant first
-        for _ in 0..(bits / 8) {
-            self.write_up_to_u8(value as u8, 8);
-            value >>= 8;
-        }
-        // Write any remaining bits that don't fill up a byte
-        self.write_up_to_u8(value as u8, bits % 8);
+        self.write_bits(u64::from(value), bits);
     }
 
     /// Writes an x-bit unsigned integer (x must be in the range 0..=8)
+    #[inline]
     fn write_up_to_u8(&mut self, value: u8, bits: u8) {
         debug_assert!(bits <= 8);
-        if bits == 0 {
-            return;
-        }
-        self.check_length(usize::from(bits));
-        // Constrain value to fit with the correct number of bits
-        // Use 16 bits to correctly handle the case when bits = 8
-        let mask = ((1u16 << u16::from(bits)) - 1) as u8;
-        let value = value & mask;
-
-        if self.bit_index <= 8 - bits {
-            // Write all bits to the current byte, aligned to the right
-            let bit_index = self.bit_index;
-            let remaining_bytes = self.remaining_bytes();
-            remaining_bytes[0] |= value << bit_index;
-        } else {
-            // Need to split across two bytes
-            // current_bits: The less significant bits are aligned to the left of the current byte.
-            let current_bits = value << self.bit_index;
-            // next_bits: The more significant bits are aligned to the right of the next byte.
-            let next_bits = value >> (8 - self.bit_index);
-            let remaining_bytes = self.remaining_bytes();
-            remaining_bytes[0] |= current_bits;
-            remaining_bytes[1] |= next_bits;
-        }
-
-        self.advance_bits(usize::from(bits));
+        self.write_bits(u64::from(value), bits);
     }
 
     pub fn write_aligned_u8(&mut self, value: u8) {
@@ -193,8 +203,17 @@ impl<'b> WriteCursor<'b> {
 
     /// Writes a byte array
     pub fn write_bytes(&mut self, bytes: &[u8]) {
-        for byte in bytes {
-            self.write_u8(*byte);
+        if self.is_aligned_to_8_bits() {
+            self.write_aligned_bytes(bytes);
+        } else {
+            // Write 8 bytes at a time
+            let mut chunks = bytes.chunks_exact(8);
+            for chunk in &mut chunks {
+                self.write_bits(u64::from_le_bytes(chunk.try_into().unwrap()), 64);
+            }
+            for byte in chunks.remainder() {
+                self.write_u8(*byte);
+            }
         }
     }
 
@@ -205,26 +224,64 @@ impl<'b> WriteCursor<'b> {
         self.advance_bits(8 * bytes.len());
     }
 
+    /// Writes a delimiter header for a composite value with the provided size in bits
+    ///
+    /// This part of [`write_composite`](#method.write_composite) does not depend on the composite
+    /// type, so it is kept separate to avoid compiling a copy for each type.
+    fn write_delimiter_header(&mut self, composite_size_bits: usize) {
+        // Convert bits to bytes, round up
+        let composite_size_bytes: u32 = composite_size_bits
+            .div_ceil(8)
+            .try_into()
+            .expect("Composite too large for u32");
+        self.write_u32(composite_size_bytes);
+    }
+
     /// Writes a composite value, aligned to 8 bits
+    ///
+    /// If the composite type is delimited and the value is larger than the extent of the type,
+    /// the value is still written but this cursor records a [`SerializeError::Extent`] error,
+    /// which can be checked using [`result`](#method.result). A receiver would truncate the value
+    /// to the extent.
     pub fn write_composite<T>(&mut self, value: &T)
     where
         T: Serialize,
     {
         self.align_to_8_bits();
-        if T::EXTENT_BYTES.is_some() {
+        if let Some(extent) = T::EXTENT_BYTES {
+            let size_bits = value.size_bits();
+            self.check_extent(size_bits, extent);
             // Add delimiter header
-            let composite_size_bits = value.size_bits();
-            // Convert bits to bytes, round up
-            let composite_size_bytes: u32 = ((composite_size_bits + 7) / 8)
-                .try_into()
-                .expect("Composite too large for u32");
-            self.write_u32(composite_size_bytes);
+            self.write_delimiter_header(size_bits);
         }
         // Now serialize the components
         value.serialize(self);
         self.align_to_8_bits();
     }
 
+    /// Records an error if a value of the provided size exceeds an extent
+    pub(crate) fn check_extent(&mut self, size_bits: usize, extent_bytes: u32) {
+        if size_bits.div_ceil(8) > extent_bytes as usize {
+            self.record_error(SerializeError::Extent);
+        }
+    }
+
+    /// Records an error, if no error has already been recorded
+    fn record_error(&mut self, error: SerializeError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Returns the first error that was detected while writing, or `Ok(())` if no error was
+    /// detected
+    pub fn result(&self) -> Result<(), SerializeError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
     /// Writes a boolean value (1 bit)
     pub fn write_bool(&mut self, value: bool) {
         self.write_u1(value as u8)
@@ -910,6 +967,37 @@ mod test {
         assert_eq!(bytes, [0x86, 0xa3, 0xbc, 0x24, 0x8b, 0x9a, 0x56, 0xfd]);
     }
 
+    #[test]
+    fn u64_unaligned_at_end() {
+        let value = 0xfd569a8b24bca386u64;
+        for offset in 0..8 {
+            // Exactly enough space for the offset and the value
+            let mut bytes = [0u8; 9];
+            let length = (offset + 64usize).div_ceil(8);
+            let mut cursor = WriteCursor::new(&mut bytes[..length]);
+            for _ in 0..offset {
+                cursor.write_bool(true);
+            }
+            cursor.write_u64(value);
+            assert_eq!(offset + 64, cursor.bits_written());
+
+            let expected = ((u128::from(value) << offset) | ((1u128 << offset) - 1)).to_le_bytes();
+            assert_eq!(bytes, expected[..9], "offset {}", offset);
+        }
+    }
+
+    #[test]
+    fn bytes_unaligned() {
+        let mut bytes = [0u8; 12];
+        let mut cursor = WriteCursor::new(&mut bytes);
+        cursor.write_u4(0xa);
+        cursor.write_bytes(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x10, 0x32]);
+        assert_eq!(
+            bytes,
+            [0x1a, 0x30, 0x52, 0x74, 0x96, 0xb8, 0xda, 0xfc, 0x0e, 0x21, 0x03, 0x00]
+        );
+    }
+
     // #[test]
     // fn test_ceiling_log_2() {
     //     assert_eq!(0, ceiling_log_2(1));
@@ -4,6 +4,8 @@ extern crate half;
 
 mod cursor;
 
+use core::fmt;
+
 pub use crate::cursor::deserialize::ReadCursor;
 pub use crate::cursor::serialize::WriteCursor;
 
@@ -11,6 +13,19 @@ pub use crate::cursor::serialize::WriteCursor;
 pub trait DataType {
     /// The sealed or delimited property of this type
     const EXTENT_BYTES: Option<u32>;
+    /// The maximum length of a serialized value of this type, in bytes
+    ///
+    /// Delimited composite fields count as their extents plus the delimiter headers, because
+    /// a newer version of a field type may be larger.
+    const MAX_SERIALIZED_SIZE: usize;
+    /// The number of bytes that a receiver needs to accept any value of this type
+    ///
+    /// This is the extent of a delimited type, or the maximum serialized size of a sealed type.
+    /// It can be used as the maximum payload size when subscribing to a subject or service.
+    const PAYLOAD_SIZE_MAX: usize = match Self::EXTENT_BYTES {
+        Some(extent) => extent as usize,
+        None => Self::MAX_SERIALIZED_SIZE,
+    };
 }
 
 /// Trait for types that can be serialized into UAVCAN transfers
@@ -31,6 +46,19 @@ pub trait Serialize: DataType {
         let mut cursor = WriteCursor::new(bytes);
         self.serialize(&mut cursor);
     }
+
+    /// Serializes this value into a buffer, and returns an error if this value or any delimited
+    /// composite value inside it is larger than the extent of its type
+    ///
+    /// The value is serialized completely even if an error is returned.
+    fn try_serialize_to_bytes(&self, bytes: &mut [u8]) -> Result<(), SerializeError> {
+        let mut cursor = WriteCursor::new(bytes);
+        if let Some(extent) = Self::EXTENT_BYTES {
+            cursor.check_extent(self.size_bits(), extent);
+        }
+        self.serialize(&mut cursor);
+        cursor.result()
+    }
 }
 
 /// Trait for types that can be deserialized from UAVCAN transfers
@@ -58,6 +86,18 @@ pub trait Deserialize: DataType {
         let mut cursor = ReadCursor::new(bytes);
         Self::deserialize(&mut cursor)
     }
+
+    /// A convenience function that creates a strict cursor around the provided bytes and calls
+    /// deserialize
+    ///
+    /// See [`ReadCursor::new_strict`] for the additional checks that this enables.
+    fn deserialize_from_bytes_strict(bytes: &[u8]) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let mut cursor = ReadCursor::new_strict(bytes);
+        Self::deserialize(&mut cursor)
+    }
 }
 
 /// Marker for message data types
@@ -67,6 +107,16 @@ pub trait Request {}
 /// Marker for service response data types
 pub trait Response {}
 
+/// Errors that can occur when serializing
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SerializeError {
+    /// A delimited composite value was larger than the extent of its type
+    ///
+    /// A receiver would discard the part of the value beyond the extent.
+    Extent,
+}
+
 /// Errors that can occur when deserializing
 #[non_exhaustive]
 #[derive(Debug)]
@@ -77,4 +127,18 @@ pub enum DeserializeError {
     UnionTag,
     /// A delimiter header had a length that was not valid for the expected type
     DelimitedLength,
+    /// A string field did not contain valid UTF-8 (checked only by strict cursors)
+    InvalidUtf8,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            DeserializeError::ArrayLength => "array length greater than the maximum length",
+            DeserializeError::UnionTag => "union tag does not correspond to a variant",
+            DeserializeError::DelimitedLength => "invalid delimiter header length",
+            DeserializeError::InvalidUtf8 => "string field is not valid UTF-8",
+        };
+        f.write_str(description)
+    }
 }
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 extern crate canadensis_encoding;
 
 use canadensis_encoding::{
@@ -15,6 +17,7 @@ struct Inner {
 impl DataType for Inner {
     /// Sealed
     const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 1;
 }
 
 struct Outer {
@@ -32,6 +35,7 @@ struct Outer {
 impl DataType for Outer {
     // 12 bytes = 96 bits extent
     const EXTENT_BYTES: Option<u32> = Some(12);
+    const MAX_SERIALIZED_SIZE: usize = 9;
 }
 
 impl Serialize for Inner {
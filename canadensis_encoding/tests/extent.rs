@@ -0,0 +1,95 @@
+extern crate canadensis_encoding;
+
+use canadensis_encoding::{DataType, Serialize, SerializeError, WriteCursor};
+
+/// A delimited type with a variable number of bytes and an extent of 4 bytes
+///
+/// This is not a valid DSDL type, because its maximum size is greater than its extent.
+struct Bytes(Vec<u8>);
+
+impl DataType for Bytes {
+    const EXTENT_BYTES: Option<u32> = Some(4);
+    const MAX_SERIALIZED_SIZE: usize = 8;
+}
+
+impl Serialize for Bytes {
+    fn size_bits(&self) -> usize {
+        8 * self.0.len()
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_aligned_bytes(&self.0);
+    }
+}
+
+/// A sealed type that contains a delimited type
+struct Outer {
+    inner: Bytes,
+}
+
+impl DataType for Outer {
+    const EXTENT_BYTES: Option<u32> = None;
+    const MAX_SERIALIZED_SIZE: usize = 8;
+}
+
+impl Serialize for Outer {
+    fn size_bits(&self) -> usize {
+        32 + self.inner.size_bits()
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_composite(&self.inner);
+    }
+}
+
+#[test]
+fn nested_within_extent() {
+    let value = Outer {
+        inner: Bytes(vec![1, 2, 3, 4]),
+    };
+    let mut bytes = [0u8; 8];
+    assert_eq!(Ok(()), value.try_serialize_to_bytes(&mut bytes));
+    assert_eq!(bytes, [4, 0, 0, 0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn nested_exceeds_extent() {
+    let value = Outer {
+        inner: Bytes(vec![1, 2, 3, 4, 5]),
+    };
+    let mut bytes = [0u8; 9];
+    assert_eq!(
+        Err(SerializeError::Extent),
+        value.try_serialize_to_bytes(&mut bytes)
+    );
+    // The value is still written
+    assert_eq!(bytes, [5, 0, 0, 0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn top_level_exceeds_extent() {
+    let value = Bytes(vec![1, 2, 3, 4, 5]);
+    let mut bytes = [0u8; 5];
+    assert_eq!(
+        Err(SerializeError::Extent),
+        value.try_serialize_to_bytes(&mut bytes)
+    );
+}
+
+#[test]
+fn cursor_result() {
+    let value = Bytes(vec![0; 6]);
+    let mut bytes = [0u8; 10];
+    let mut cursor = WriteCursor::new(&mut bytes);
+    assert_eq!(Ok(()), cursor.result());
+    cursor.write_composite(&value);
+    assert_eq!(Err(SerializeError::Extent), cursor.result());
+}
+
+#[test]
+fn payload_size_max() {
+    // Delimited: the extent
+    assert_eq!(4, Bytes::PAYLOAD_SIZE_MAX);
+    // Sealed: the maximum serialized size
+    assert_eq!(8, Outer::PAYLOAD_SIZE_MAX);
+}
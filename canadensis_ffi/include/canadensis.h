@@ -0,0 +1,240 @@
+#ifndef CANADENSIS_H
+#define CANADENSIS_H
+
+/* This file is generated by cbindgen from the canadensis_ffi crate. Do not edit it manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The maximum number of subjects that a node can publish on
+#define CANADENSIS_MAX_PUBLISHERS 16
+
+// The maximum number of services that a node can send requests for
+#define CANADENSIS_MAX_REQUESTERS 8
+
+// The maximum number of subjects that a node can subscribe to
+#define CANADENSIS_MAX_MESSAGE_SUBSCRIPTIONS 16
+
+// The maximum number of services that a node can receive requests for
+#define CANADENSIS_MAX_SERVICE_SUBSCRIPTIONS 8
+
+// The maximum number of data bytes in a frame
+#define CANADENSIS_FRAME_CAPACITY 64
+
+// The node ID value that means no node ID (for example, the source of an anonymous message)
+#define CANADENSIS_NODE_ID_UNSET 255
+
+// The kind of a transfer
+typedef enum CanadensisTransferKind {
+  CANADENSIS_TRANSFER_KIND_MESSAGE = 0,
+  CANADENSIS_TRANSFER_KIND_REQUEST = 1,
+  CANADENSIS_TRANSFER_KIND_RESPONSE = 2,
+} CanadensisTransferKind;
+
+// The result of an operation
+typedef enum CanadensisStatus {
+  // The operation succeeded
+  CANADENSIS_STATUS_OK = 0,
+  // A pointer was null, or an ID or other argument was out of range
+  CANADENSIS_STATUS_INVALID_ARGUMENT = -1,
+  // Memory could not be allocated
+  CANADENSIS_STATUS_OUT_OF_MEMORY = -2,
+  // The subject ID or service ID is already in use
+  CANADENSIS_STATUS_DUPLICATE = -3,
+  // The node has no space for another publisher, requester, or subscription
+  CANADENSIS_STATUS_CAPACITY = -4,
+  // The node has not started publishing on the subject or sending requests for the service
+  CANADENSIS_STATUS_NOT_STARTED = -5,
+  // A response to the request has already been sent
+  CANADENSIS_STATUS_ALREADY_RESPONDED = -6,
+} CanadensisStatus;
+
+// A node, which is opaque to C code
+typedef struct CanadensisNode CanadensisNode;
+
+// Sends a response to a request that a node has received
+typedef struct CanadensisResponder CanadensisResponder;
+
+// A CAN frame
+typedef struct CanadensisFrame {
+  // The 29-bit extended CAN ID
+  uint32_t id;
+  // The number of valid bytes in `data`
+  uint8_t length;
+  // The frame data
+  uint8_t data[CANADENSIS_FRAME_CAPACITY];
+  // For received frames, the time in microseconds when the frame was received
+  //
+  // For frames to transmit, the time in microseconds after which the frame should not be sent.
+  uint64_t timestamp;
+} CanadensisFrame;
+
+// An incoming transfer
+//
+// The payload pointer is only valid during the callback that receives the transfer.
+typedef struct CanadensisTransfer {
+  enum CanadensisTransferKind kind;
+  // The subject ID or service ID
+  uint16_t port_id;
+  // The node that sent the transfer, or `CANADENSIS_NODE_ID_UNSET` for an anonymous message
+  uint8_t source;
+  // The node that the transfer was sent to, or `CANADENSIS_NODE_ID_UNSET` for a message
+  uint8_t destination;
+  uint8_t transfer_id;
+  // The priority, from 0 (exceptional) to 7 (optional)
+  uint8_t priority;
+  // The time in microseconds when the first frame of the transfer was received
+  uint64_t timestamp;
+  const uint8_t *payload;
+  size_t payload_length;
+} CanadensisTransfer;
+
+// Functions that the node calls to get the time, transmit frames, and deliver transfers
+//
+// Each function receives `context` as its first argument. `now` and `transmit` are required,
+// and the others may be null.
+typedef struct CanadensisCallbacks {
+  // A pointer that is passed to each callback
+  void *context;
+  // Returns the current time in microseconds
+  uint64_t (*now)(void *context);
+  // Transmits a frame, and returns false if the frame could not be transmitted
+  // (for example, because the CAN controller has no free transmit mailbox)
+  bool (*transmit)(void *context, const struct CanadensisFrame *frame);
+  // Handles an incoming message
+  void (*on_message)(void *context, const struct CanadensisTransfer *transfer);
+  // Handles an incoming request
+  //
+  // The responder can be passed to `canadensis_respond` to send a response. It is only valid
+  // during this callback.
+  void (*on_request)(void *context,
+                     const struct CanadensisTransfer *transfer,
+                     struct CanadensisResponder *responder);
+  // Handles an incoming response
+  void (*on_response)(void *context, const struct CanadensisTransfer *transfer);
+} CanadensisCallbacks;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a node
+//
+// `mtu` is the maximum number of data bytes in each outgoing frame (8, or 64 for CAN FD).
+// The callbacks are copied into the node.
+//
+// This function returns null if the node ID or MTU is not valid, or if `callbacks` is null or
+// does not have `now` and `transmit` functions.
+struct CanadensisNode *canadensis_node_new(uint8_t node_id,
+                                           size_t mtu,
+                                           const struct CanadensisCallbacks *callbacks);
+
+// Frees a node that `canadensis_node_new` created
+//
+// This function does nothing if `node` is null.
+void canadensis_node_free(struct CanadensisNode *node);
+
+// Returns the ID of a node
+uint8_t canadensis_node_id(const struct CanadensisNode *node);
+
+// Handles an incoming frame
+//
+// If the frame completes a transfer, this function calls the matching callback.
+enum CanadensisStatus canadensis_node_accept_frame(struct CanadensisNode *node,
+                                                   const struct CanadensisFrame *frame);
+
+// Passes outgoing frames to the transmit callback until the queue is empty or the callback
+// returns false
+//
+// Frames whose deadlines have passed are discarded. This function returns the number of frames
+// that were transmitted.
+size_t canadensis_node_flush(struct CanadensisNode *node);
+
+// Frees the memory used for incoming transfers that have timed out
+void canadensis_node_clean_expired_sessions(struct CanadensisNode *node);
+
+// Starts publishing messages on a subject
+//
+// Each message must be transmitted within `timeout` microseconds after it is published.
+enum CanadensisStatus canadensis_node_start_publishing(struct CanadensisNode *node,
+                                                       uint16_t subject_id,
+                                                       uint64_t timeout,
+                                                       uint8_t priority);
+
+// Stops publishing messages on a subject
+enum CanadensisStatus canadensis_node_stop_publishing(struct CanadensisNode *node,
+                                                      uint16_t subject_id);
+
+// Publishes a serialized message on a subject
+//
+// `canadensis_node_start_publishing` must be called first.
+enum CanadensisStatus canadensis_node_publish(struct CanadensisNode *node,
+                                              uint16_t subject_id,
+                                              const uint8_t *payload,
+                                              size_t payload_length);
+
+// Subscribes to messages on a subject
+//
+// Longer payloads are truncated to `payload_size_max` bytes. Multi-frame transfers that take
+// longer than `timeout` microseconds to receive are discarded.
+enum CanadensisStatus canadensis_node_subscribe_message(struct CanadensisNode *node,
+                                                        uint16_t subject_id,
+                                                        size_t payload_size_max,
+                                                        uint64_t timeout);
+
+// Unsubscribes from messages on a subject
+enum CanadensisStatus canadensis_node_unsubscribe_message(struct CanadensisNode *node,
+                                                          uint16_t subject_id);
+
+// Subscribes to requests for a service
+enum CanadensisStatus canadensis_node_subscribe_request(struct CanadensisNode *node,
+                                                        uint16_t service_id,
+                                                        size_t payload_size_max,
+                                                        uint64_t timeout);
+
+// Unsubscribes from requests for a service
+enum CanadensisStatus canadensis_node_unsubscribe_request(struct CanadensisNode *node,
+                                                          uint16_t service_id);
+
+// Sets up to send requests for a service, and subscribes to the responses
+//
+// `timeout` is the deadline in microseconds for transmitting each request, and the timeout for
+// receiving multi-frame responses.
+enum CanadensisStatus canadensis_node_start_sending_requests(struct CanadensisNode *node,
+                                                             uint16_t service_id,
+                                                             uint64_t timeout,
+                                                             size_t response_payload_size_max,
+                                                             uint8_t priority);
+
+// Stops sending requests for a service
+enum CanadensisStatus canadensis_node_stop_sending_requests(struct CanadensisNode *node,
+                                                            uint16_t service_id);
+
+// Sends a serialized request to another node
+//
+// `canadensis_node_start_sending_requests` must be called first. If `transfer_id` is not null,
+// the transfer ID of the request is written to it so that the response can be matched with
+// the request.
+enum CanadensisStatus canadensis_node_call(struct CanadensisNode *node,
+                                           uint16_t service_id,
+                                           uint8_t destination,
+                                           const uint8_t *payload,
+                                           size_t payload_length,
+                                           uint8_t *transfer_id);
+
+// Sends a serialized response to a request
+//
+// This function can only be called from an `on_request` callback, with the responder that
+// was passed to the callback. Each request can have only one response.
+enum CanadensisStatus canadensis_respond(struct CanadensisResponder *responder,
+                                         const uint8_t *payload,
+                                         size_t payload_length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CANADENSIS_H */
@@ -0,0 +1,143 @@
+use std::os::raw::c_void;
+
+use canadensis::{Node, ResponseToken, TransferHandler};
+use canadensis_core::time::{Clock, Microseconds64};
+use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
+use canadensis_encoding::{DataType, Message, Request, Response, Serialize, WriteCursor};
+
+use crate::types::{
+    CanadensisCallbacks, CanadensisResponder, CanadensisStatus, CanadensisTransfer,
+    CanadensisTransferKind,
+};
+
+/// A clock that gets the time from a C function
+pub(crate) struct FfiClock {
+    pub(crate) now: extern "C" fn(context: *mut c_void) -> u64,
+    pub(crate) context: *mut c_void,
+}
+
+impl Clock for FfiClock {
+    type Instant = Microseconds64;
+
+    fn now(&mut self) -> Self::Instant {
+        Microseconds64::new((self.now)(self.context))
+    }
+}
+
+/// A payload that the C code has already serialized
+///
+/// The same type is used for messages, requests, and responses, so that one kind of publish token
+/// and service token works for all subjects and services.
+pub(crate) struct RawPayload {
+    bytes: *const u8,
+    length: usize,
+}
+
+impl RawPayload {
+    /// Wraps a payload, or returns None if `bytes` is null and `length` is not zero
+    ///
+    /// # Safety
+    ///
+    /// If `bytes` is not null, it must point to `length` bytes that remain valid while this
+    /// payload exists.
+    pub(crate) unsafe fn new(bytes: *const u8, length: usize) -> Option<Self> {
+        if bytes.is_null() && length != 0 {
+            None
+        } else {
+            Some(RawPayload { bytes, length })
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        if self.length == 0 {
+            &[]
+        } else {
+            // Safety: Checked in the constructor
+            unsafe { std::slice::from_raw_parts(self.bytes, self.length) }
+        }
+    }
+}
+
+impl DataType for RawPayload {
+    const EXTENT_BYTES: Option<u32> = None;
+    // The length is only known at run time
+    const MAX_SERIALIZED_SIZE: usize = usize::MAX;
+}
+
+impl Message for RawPayload {}
+impl Request for RawPayload {}
+impl Response for RawPayload {}
+
+impl Serialize for RawPayload {
+    fn size_bits(&self) -> usize {
+        self.length * 8
+    }
+
+    fn serialize(&self, cursor: &mut WriteCursor<'_>) {
+        cursor.write_aligned_bytes(self.as_slice());
+    }
+}
+
+/// Passes incoming transfers to the C callbacks
+pub(crate) struct FfiHandler<'c> {
+    pub(crate) callbacks: &'c CanadensisCallbacks,
+}
+
+impl TransferHandler<Microseconds64> for FfiHandler<'_> {
+    fn handle_message<N: Node<Instant = Microseconds64>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &MessageTransfer<Vec<u8>, Microseconds64>,
+    ) -> bool {
+        match self.callbacks.on_message {
+            Some(on_message) => {
+                let transfer = CanadensisTransfer::from_message(transfer);
+                on_message(self.callbacks.context, &transfer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn handle_request<N: Node<Instant = Microseconds64>>(
+        &mut self,
+        node: &mut N,
+        token: ResponseToken,
+        transfer: &ServiceTransfer<Vec<u8>, Microseconds64>,
+    ) -> bool {
+        match self.callbacks.on_request {
+            Some(on_request) => {
+                let timeout = node.default_timeout();
+                let mut token = Some(token);
+                let mut respond = |payload: RawPayload| match token.take() {
+                    Some(token) => node.send_response(token, timeout, &payload).into(),
+                    None => CanadensisStatus::AlreadyResponded,
+                };
+                let mut responder = CanadensisResponder {
+                    respond: &mut respond,
+                };
+                let transfer =
+                    CanadensisTransfer::from_service(CanadensisTransferKind::Request, transfer);
+                on_request(self.callbacks.context, &transfer, &mut responder);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn handle_response<N: Node<Instant = Microseconds64>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &ServiceTransfer<Vec<u8>, Microseconds64>,
+    ) -> bool {
+        match self.callbacks.on_response {
+            Some(on_response) => {
+                let transfer =
+                    CanadensisTransfer::from_service(CanadensisTransferKind::Response, transfer);
+                on_response(self.callbacks.context, &transfer);
+                true
+            }
+            None => false,
+        }
+    }
+}
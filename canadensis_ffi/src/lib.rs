@@ -0,0 +1,483 @@
+//!
+//! A C API for canadensis
+//!
+//! This crate builds a static library and a shared library with a C interface to a canadensis
+//! node, so that C firmware can use canadensis as its UAVCAN stack and move code to Rust
+//! gradually. The header `include/canadensis.h` declares the API. It is generated from this crate
+//! with [cbindgen](https://github.com/mozilla/cbindgen):
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/canadensis.h
+//! ```
+//!
+//! The library uses the standard library and the system memory allocator.
+//!
+//! # Usage
+//!
+//! The C code serializes and deserializes payloads, and provides callbacks that get the current
+//! time, transmit frames, and handle incoming transfers.
+//!
+//! ```c
+//! CanadensisCallbacks callbacks = {
+//!     .context = &app,
+//!     .now = app_now,
+//!     .transmit = app_transmit,
+//!     .on_message = app_on_message,
+//!     .on_request = app_on_request,
+//!     .on_response = NULL,
+//! };
+//! CanadensisNode *node = canadensis_node_new(42, 8, &callbacks);
+//! canadensis_node_start_publishing(node, 7509, 1000000, 4);
+//!
+//! for (;;) {
+//!     CanadensisFrame frame;
+//!     if (app_receive(&app, &frame)) {
+//!         // May call on_message, on_request, or on_response
+//!         canadensis_node_accept_frame(node, &frame);
+//!     }
+//!     if (app_heartbeat_due(&app)) {
+//!         canadensis_node_publish(node, 7509, heartbeat, sizeof heartbeat);
+//!     }
+//!     // Calls transmit for each outgoing frame, until transmit returns false
+//!     canadensis_node_flush(node);
+//! }
+//! ```
+//!
+//! A request handler can respond by passing its responder to `canadensis_respond` before
+//! it returns.
+//!
+//! All functions must be called from the same thread, and callbacks are called from the function
+//! that triggered them.
+//!
+//! # Safety
+//!
+//! Every pointer passed to a function in this library must be null or valid for the duration of
+//! the call. Functions return `CANADENSIS_STATUS_INVALID_ARGUMENT` when a required pointer is null.
+//!
+
+// The safety requirements are the same for all functions, and are described above
+#![allow(clippy::missing_safety_doc)]
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_encoding;
+
+mod handler;
+mod types;
+
+pub use crate::types::*;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, Node, PublishToken, ServiceToken};
+use canadensis_can::queue::HeapQueue;
+use canadensis_can::{CanId, Frame, Mtu, FRAME_CAPACITY};
+use canadensis_core::time::{Clock, Instant, MicrosecondDuration64, Microseconds64};
+use canadensis_core::{NodeId, Priority, ServiceId, SubjectId};
+
+use crate::handler::{FfiClock, FfiHandler, RawPayload};
+
+/// The maximum number of subjects that a node can publish on
+pub const CANADENSIS_MAX_PUBLISHERS: usize = 16;
+/// The maximum number of services that a node can send requests for
+pub const CANADENSIS_MAX_REQUESTERS: usize = 8;
+/// The maximum number of subjects that a node can subscribe to
+pub const CANADENSIS_MAX_MESSAGE_SUBSCRIPTIONS: usize = 16;
+/// The maximum number of services that a node can receive requests for
+pub const CANADENSIS_MAX_SERVICE_SUBSCRIPTIONS: usize = 8;
+
+type InnerNode = CoreNode<
+    FfiClock,
+    HeapQueue<Microseconds64>,
+    CANADENSIS_MAX_PUBLISHERS,
+    CANADENSIS_MAX_REQUESTERS,
+    CANADENSIS_MAX_MESSAGE_SUBSCRIPTIONS,
+    CANADENSIS_MAX_SERVICE_SUBSCRIPTIONS,
+>;
+
+/// A node, which is opaque to C code
+pub struct CanadensisNode {
+    node: InnerNode,
+    callbacks: CanadensisCallbacks,
+    publishers: BTreeMap<SubjectId, PublishToken<RawPayload>>,
+    requesters: BTreeMap<ServiceId, ServiceToken<RawPayload>>,
+}
+
+/// Creates a node
+///
+/// `mtu` is the maximum number of data bytes in each outgoing frame (8, or 64 for CAN FD).
+/// The callbacks are copied into the node.
+///
+/// This function returns null if the node ID or MTU is not valid, or if `callbacks` is null or
+/// does not have `now` and `transmit` functions.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_new(
+    node_id: u8,
+    mtu: usize,
+    callbacks: *const CanadensisCallbacks,
+) -> *mut CanadensisNode {
+    let callbacks = match callbacks.as_ref() {
+        Some(callbacks) => *callbacks,
+        None => return std::ptr::null_mut(),
+    };
+    let (node_id, mtu, now) = match (
+        NodeId::try_from(node_id),
+        Mtu::from_bytes(mtu),
+        callbacks.now,
+        callbacks.transmit,
+    ) {
+        (Ok(node_id), Some(mtu), Some(now), Some(_)) => (node_id, mtu, now),
+        _ => return std::ptr::null_mut(),
+    };
+    let clock = FfiClock {
+        now,
+        context: callbacks.context,
+    };
+    let node = CanadensisNode {
+        node: CoreNode::new(clock, node_id, mtu, HeapQueue::new()),
+        callbacks,
+        publishers: BTreeMap::new(),
+        requesters: BTreeMap::new(),
+    };
+    Box::into_raw(Box::new(node))
+}
+
+/// Frees a node that `canadensis_node_new` created
+///
+/// This function does nothing if `node` is null.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_free(node: *mut CanadensisNode) {
+    if !node.is_null() {
+        drop(Box::from_raw(node));
+    }
+}
+
+/// Returns the ID of a node
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_id(node: *const CanadensisNode) -> u8 {
+    match node.as_ref() {
+        Some(node) => node.node.node_id().into(),
+        None => CANADENSIS_NODE_ID_UNSET,
+    }
+}
+
+/// Handles an incoming frame
+///
+/// If the frame completes a transfer, this function calls the matching callback.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_accept_frame(
+    node: *mut CanadensisNode,
+    frame: *const CanadensisFrame,
+) -> CanadensisStatus {
+    let (node, frame) = match (node.as_mut(), frame.as_ref()) {
+        (Some(node), Some(frame)) => (node, frame),
+        _ => return CanadensisStatus::InvalidArgument,
+    };
+    let id = match CanId::try_from(frame.id) {
+        Ok(id) => id,
+        Err(_) => return CanadensisStatus::InvalidArgument,
+    };
+    let length = usize::from(frame.length);
+    if length > FRAME_CAPACITY {
+        return CanadensisStatus::InvalidArgument;
+    }
+    let frame = Frame::new(
+        Microseconds64::new(frame.timestamp),
+        id,
+        &frame.data[..length],
+    );
+    let mut handler = FfiHandler {
+        callbacks: &node.callbacks,
+    };
+    node.node.accept_frame(frame, &mut handler).into()
+}
+
+/// Passes outgoing frames to the transmit callback until the queue is empty or the callback
+/// returns false
+///
+/// Frames whose deadlines have passed are discarded. This function returns the number of frames
+/// that were transmitted.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_flush(node: *mut CanadensisNode) -> usize {
+    let node = match node.as_mut() {
+        Some(node) => node,
+        None => return 0,
+    };
+    let transmit = match node.callbacks.transmit {
+        Some(transmit) => transmit,
+        None => return 0,
+    };
+    let now = node.node.clock_mut().now();
+    let mut count = 0;
+    while let Some(frame) = node.node.pop_frame() {
+        if frame.timestamp().overflow_safe_compare(&now) == std::cmp::Ordering::Less {
+            // Deadline passed, ignore frame
+            continue;
+        }
+        if transmit(node.callbacks.context, &CanadensisFrame::from(&frame)) {
+            count += 1;
+        } else {
+            // Try again next time. There is space for the frame because it was just removed.
+            let _ = node.node.return_frame(frame);
+            break;
+        }
+    }
+    count
+}
+
+/// Frees the memory used for incoming transfers that have timed out
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_clean_expired_sessions(node: *mut CanadensisNode) {
+    if let Some(node) = node.as_mut() {
+        node.node.clean_expired_sessions();
+    }
+}
+
+/// Starts publishing messages on a subject
+///
+/// Each message must be transmitted within `timeout` microseconds after it is published.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_start_publishing(
+    node: *mut CanadensisNode,
+    subject_id: u16,
+    timeout: u64,
+    priority: u8,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let subject = parse_subject(subject_id)?;
+        let priority = parse_priority(priority)?;
+        let token =
+            node.node
+                .start_publishing(subject, MicrosecondDuration64::new(timeout), priority)?;
+        node.publishers.insert(subject, token);
+        Ok(())
+    })
+}
+
+/// Stops publishing messages on a subject
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_stop_publishing(
+    node: *mut CanadensisNode,
+    subject_id: u16,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let token = node
+            .publishers
+            .remove(&parse_subject(subject_id)?)
+            .ok_or(CanadensisStatus::NotStarted)?;
+        node.node.stop_publishing(token);
+        Ok(())
+    })
+}
+
+/// Publishes a serialized message on a subject
+///
+/// `canadensis_node_start_publishing` must be called first.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_publish(
+    node: *mut CanadensisNode,
+    subject_id: u16,
+    payload: *const u8,
+    payload_length: usize,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let payload =
+            RawPayload::new(payload, payload_length).ok_or(CanadensisStatus::InvalidArgument)?;
+        let token = node
+            .publishers
+            .get(&parse_subject(subject_id)?)
+            .ok_or(CanadensisStatus::NotStarted)?;
+        node.node.publish(token, &payload)?;
+        Ok(())
+    })
+}
+
+/// Subscribes to messages on a subject
+///
+/// Longer payloads are truncated to `payload_size_max` bytes. Multi-frame transfers that take
+/// longer than `timeout` microseconds to receive are discarded.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_subscribe_message(
+    node: *mut CanadensisNode,
+    subject_id: u16,
+    payload_size_max: usize,
+    timeout: u64,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        node.node.subscribe_message(
+            parse_subject(subject_id)?,
+            payload_size_max,
+            MicrosecondDuration64::new(timeout),
+        )?;
+        Ok(())
+    })
+}
+
+/// Unsubscribes from messages on a subject
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_unsubscribe_message(
+    node: *mut CanadensisNode,
+    subject_id: u16,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        node.node.unsubscribe_message(parse_subject(subject_id)?);
+        Ok(())
+    })
+}
+
+/// Subscribes to requests for a service
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_subscribe_request(
+    node: *mut CanadensisNode,
+    service_id: u16,
+    payload_size_max: usize,
+    timeout: u64,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        node.node.subscribe_request(
+            parse_service(service_id)?,
+            payload_size_max,
+            MicrosecondDuration64::new(timeout),
+        )?;
+        Ok(())
+    })
+}
+
+/// Unsubscribes from requests for a service
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_unsubscribe_request(
+    node: *mut CanadensisNode,
+    service_id: u16,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        node.node.unsubscribe_request(parse_service(service_id)?);
+        Ok(())
+    })
+}
+
+/// Sets up to send requests for a service, and subscribes to the responses
+///
+/// `timeout` is the deadline in microseconds for transmitting each request, and the timeout for
+/// receiving multi-frame responses.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_start_sending_requests(
+    node: *mut CanadensisNode,
+    service_id: u16,
+    timeout: u64,
+    response_payload_size_max: usize,
+    priority: u8,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let service = parse_service(service_id)?;
+        let priority = parse_priority(priority)?;
+        let token = node.node.start_sending_requests(
+            service,
+            MicrosecondDuration64::new(timeout),
+            response_payload_size_max,
+            priority,
+        )?;
+        node.requesters.insert(service, token);
+        Ok(())
+    })
+}
+
+/// Stops sending requests for a service
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_stop_sending_requests(
+    node: *mut CanadensisNode,
+    service_id: u16,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let token = node
+            .requesters
+            .remove(&parse_service(service_id)?)
+            .ok_or(CanadensisStatus::NotStarted)?;
+        node.node.stop_sending_requests(token);
+        Ok(())
+    })
+}
+
+/// Sends a serialized request to another node
+///
+/// `canadensis_node_start_sending_requests` must be called first. If `transfer_id` is not null,
+/// the transfer ID of the request is written to it so that the response can be matched with
+/// the request.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_node_call(
+    node: *mut CanadensisNode,
+    service_id: u16,
+    destination: u8,
+    payload: *const u8,
+    payload_length: usize,
+    transfer_id: *mut u8,
+) -> CanadensisStatus {
+    status(|| {
+        let node = node.as_mut().ok_or(CanadensisStatus::InvalidArgument)?;
+        let payload =
+            RawPayload::new(payload, payload_length).ok_or(CanadensisStatus::InvalidArgument)?;
+        let destination =
+            NodeId::try_from(destination).map_err(|_| CanadensisStatus::InvalidArgument)?;
+        let token = node
+            .requesters
+            .get(&parse_service(service_id)?)
+            .ok_or(CanadensisStatus::NotStarted)?;
+        let sent_id = node.node.send_request(token, &payload, destination)?;
+        if let Some(transfer_id) = transfer_id.as_mut() {
+            *transfer_id = sent_id.into();
+        }
+        Ok(())
+    })
+}
+
+/// Sends a serialized response to a request
+///
+/// This function can only be called from an `on_request` callback, with the responder that
+/// was passed to the callback. Each request can have only one response.
+#[no_mangle]
+pub unsafe extern "C" fn canadensis_respond(
+    responder: *mut CanadensisResponder<'_>,
+    payload: *const u8,
+    payload_length: usize,
+) -> CanadensisStatus {
+    status(|| {
+        let responder = responder
+            .as_mut()
+            .ok_or(CanadensisStatus::InvalidArgument)?;
+        let payload =
+            RawPayload::new(payload, payload_length).ok_or(CanadensisStatus::InvalidArgument)?;
+        match (responder.respond)(payload) {
+            CanadensisStatus::Ok => Ok(()),
+            e => Err(e),
+        }
+    })
+}
+
+fn status<F>(operation: F) -> CanadensisStatus
+where
+    F: FnOnce() -> Result<(), CanadensisStatus>,
+{
+    operation().into()
+}
+
+fn parse_subject(id: u16) -> Result<SubjectId, CanadensisStatus> {
+    SubjectId::try_from(id).map_err(|_| CanadensisStatus::InvalidArgument)
+}
+
+fn parse_service(id: u16) -> Result<ServiceId, CanadensisStatus> {
+    ServiceId::try_from(id).map_err(|_| CanadensisStatus::InvalidArgument)
+}
+
+fn parse_priority(priority: u8) -> Result<Priority, CanadensisStatus> {
+    Priority::try_from(priority).map_err(|_| CanadensisStatus::InvalidArgument)
+}
@@ -0,0 +1,217 @@
+use std::os::raw::c_void;
+
+use canadensis::{SendError, StartSendError, SubscribeError};
+use canadensis_can::{Frame, OutOfMemoryError};
+use canadensis_core::time::Microseconds64;
+use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
+
+use crate::handler::RawPayload;
+
+/// The maximum number of data bytes in a frame
+pub const CANADENSIS_FRAME_CAPACITY: usize = 64;
+/// The node ID value that means no node ID (for example, the source of an anonymous message)
+pub const CANADENSIS_NODE_ID_UNSET: u8 = 0xff;
+
+/// The result of an operation
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CanadensisStatus {
+    /// The operation succeeded
+    Ok = 0,
+    /// A pointer was null, or an ID or other argument was out of range
+    InvalidArgument = -1,
+    /// Memory could not be allocated
+    OutOfMemory = -2,
+    /// The subject ID or service ID is already in use
+    Duplicate = -3,
+    /// The node has no space for another publisher, requester, or subscription
+    Capacity = -4,
+    /// The node has not started publishing on the subject or sending requests for the service
+    NotStarted = -5,
+    /// A response to the request has already been sent
+    AlreadyResponded = -6,
+}
+
+impl From<OutOfMemoryError> for CanadensisStatus {
+    fn from(_: OutOfMemoryError) -> Self {
+        CanadensisStatus::OutOfMemory
+    }
+}
+
+impl From<StartSendError> for CanadensisStatus {
+    fn from(inner: StartSendError) -> Self {
+        match inner {
+            StartSendError::Memory(_) => CanadensisStatus::OutOfMemory,
+            StartSendError::Duplicate => CanadensisStatus::Duplicate,
+            StartSendError::Capacity => CanadensisStatus::Capacity,
+        }
+    }
+}
+
+impl From<SendError> for CanadensisStatus {
+    fn from(inner: SendError) -> Self {
+        match inner {
+            SendError::Memory(_) => CanadensisStatus::OutOfMemory,
+            // Raw payloads have no extent, so this should not happen
+            SendError::Serialize(_) => CanadensisStatus::InvalidArgument,
+        }
+    }
+}
+
+impl From<SubscribeError> for CanadensisStatus {
+    fn from(inner: SubscribeError) -> Self {
+        match inner {
+            SubscribeError::Memory(_) => CanadensisStatus::OutOfMemory,
+            SubscribeError::Capacity => CanadensisStatus::Capacity,
+            SubscribeError::Duplicate => CanadensisStatus::Duplicate,
+        }
+    }
+}
+
+impl<E> From<Result<(), E>> for CanadensisStatus
+where
+    CanadensisStatus: From<E>,
+{
+    fn from(result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => CanadensisStatus::Ok,
+            Err(e) => e.into(),
+        }
+    }
+}
+
+/// A CAN frame
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct CanadensisFrame {
+    /// The 29-bit extended CAN ID
+    pub id: u32,
+    /// The number of valid bytes in `data`
+    pub length: u8,
+    /// The frame data
+    pub data: [u8; CANADENSIS_FRAME_CAPACITY],
+    /// For received frames, the time in microseconds when the frame was received
+    ///
+    /// For frames to transmit, the time in microseconds after which the frame should not be sent.
+    pub timestamp: u64,
+}
+
+impl From<&Frame<Microseconds64>> for CanadensisFrame {
+    fn from(frame: &Frame<Microseconds64>) -> Self {
+        let mut data = [0u8; CANADENSIS_FRAME_CAPACITY];
+        data[..frame.data().len()].copy_from_slice(frame.data());
+        CanadensisFrame {
+            id: frame.id().into(),
+            length: frame.data().len() as u8,
+            data,
+            timestamp: frame.timestamp().as_microseconds(),
+        }
+    }
+}
+
+/// The kind of a transfer
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CanadensisTransferKind {
+    Message = 0,
+    Request = 1,
+    Response = 2,
+}
+
+/// An incoming transfer
+///
+/// The payload pointer is only valid during the callback that receives the transfer.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CanadensisTransfer {
+    pub kind: CanadensisTransferKind,
+    /// The subject ID or service ID
+    pub port_id: u16,
+    /// The node that sent the transfer, or `CANADENSIS_NODE_ID_UNSET` for an anonymous message
+    pub source: u8,
+    /// The node that the transfer was sent to, or `CANADENSIS_NODE_ID_UNSET` for a message
+    pub destination: u8,
+    pub transfer_id: u8,
+    /// The priority, from 0 (exceptional) to 7 (optional)
+    pub priority: u8,
+    /// The time in microseconds when the first frame of the transfer was received
+    pub timestamp: u64,
+    pub payload: *const u8,
+    pub payload_length: usize,
+}
+
+impl CanadensisTransfer {
+    pub(crate) fn from_message(transfer: &MessageTransfer<Vec<u8>, Microseconds64>) -> Self {
+        CanadensisTransfer {
+            kind: CanadensisTransferKind::Message,
+            port_id: transfer.header.subject.into(),
+            source: transfer
+                .header
+                .source
+                .map(u8::from)
+                .unwrap_or(CANADENSIS_NODE_ID_UNSET),
+            destination: CANADENSIS_NODE_ID_UNSET,
+            transfer_id: transfer.header.transfer_id.into(),
+            priority: transfer.header.priority.into(),
+            timestamp: transfer.header.timestamp.as_microseconds(),
+            payload: transfer.payload.as_ptr(),
+            payload_length: transfer.payload.len(),
+        }
+    }
+
+    pub(crate) fn from_service(
+        kind: CanadensisTransferKind,
+        transfer: &ServiceTransfer<Vec<u8>, Microseconds64>,
+    ) -> Self {
+        CanadensisTransfer {
+            kind,
+            port_id: transfer.header.service.into(),
+            source: transfer.header.source.into(),
+            destination: transfer.header.destination.into(),
+            transfer_id: transfer.header.transfer_id.into(),
+            priority: transfer.header.priority.into(),
+            timestamp: transfer.header.timestamp.as_microseconds(),
+            payload: transfer.payload.as_ptr(),
+            payload_length: transfer.payload.len(),
+        }
+    }
+}
+
+/// Functions that the node calls to get the time, transmit frames, and deliver transfers
+///
+/// Each function receives `context` as its first argument. `now` and `transmit` are required,
+/// and the others may be null.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CanadensisCallbacks {
+    /// A pointer that is passed to each callback
+    pub context: *mut c_void,
+    /// Returns the current time in microseconds
+    pub now: Option<extern "C" fn(context: *mut c_void) -> u64>,
+    /// Transmits a frame, and returns false if the frame could not be transmitted
+    /// (for example, because the CAN controller has no free transmit mailbox)
+    pub transmit:
+        Option<extern "C" fn(context: *mut c_void, frame: *const CanadensisFrame) -> bool>,
+    /// Handles an incoming message
+    pub on_message:
+        Option<extern "C" fn(context: *mut c_void, transfer: *const CanadensisTransfer)>,
+    /// Handles an incoming request
+    ///
+    /// The responder can be passed to `canadensis_respond` to send a response. It is only valid
+    /// during this callback.
+    pub on_request: Option<
+        extern "C" fn(
+            context: *mut c_void,
+            transfer: *const CanadensisTransfer,
+            responder: *mut CanadensisResponder<'_>,
+        ),
+    >,
+    /// Handles an incoming response
+    pub on_response:
+        Option<extern "C" fn(context: *mut c_void, transfer: *const CanadensisTransfer)>,
+}
+
+/// Sends a response to a request that a node has received
+pub struct CanadensisResponder<'r> {
+    pub(crate) respond: &'r mut dyn FnMut(RawPayload) -> CanadensisStatus,
+}
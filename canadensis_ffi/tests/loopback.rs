@@ -0,0 +1,190 @@
+//!
+//! Tests two nodes that send frames to each other through the C API
+//!
+
+extern crate canadensis_ffi;
+
+use std::os::raw::c_void;
+use std::ptr;
+
+use canadensis_ffi::*;
+
+/// The state that the callbacks of one node use
+#[derive(Default)]
+struct App {
+    now: u64,
+    transmitted: Vec<CanadensisFrame>,
+    received: Vec<(CanadensisTransferKind, u16, u8, Vec<u8>)>,
+}
+
+extern "C" fn now(context: *mut c_void) -> u64 {
+    let app = unsafe { &*(context as *const App) };
+    app.now
+}
+
+extern "C" fn transmit(context: *mut c_void, frame: *const CanadensisFrame) -> bool {
+    let app = unsafe { &mut *(context as *mut App) };
+    app.transmitted.push(unsafe { (*frame).clone() });
+    true
+}
+
+extern "C" fn on_transfer(context: *mut c_void, transfer: *const CanadensisTransfer) {
+    let app = unsafe { &mut *(context as *mut App) };
+    let transfer = unsafe { &*transfer };
+    let payload =
+        unsafe { std::slice::from_raw_parts(transfer.payload, transfer.payload_length) }.to_vec();
+    app.received
+        .push((transfer.kind, transfer.port_id, transfer.source, payload));
+}
+
+extern "C" fn on_request(
+    context: *mut c_void,
+    transfer: *const CanadensisTransfer,
+    responder: *mut CanadensisResponder<'_>,
+) {
+    on_transfer(context, transfer);
+    unsafe {
+        assert_eq!(
+            canadensis_respond(responder, [0xaa].as_ptr(), 1),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(
+            canadensis_respond(responder, [0xbb].as_ptr(), 1),
+            CanadensisStatus::AlreadyResponded
+        );
+    }
+}
+
+fn callbacks(app: *mut App) -> CanadensisCallbacks {
+    CanadensisCallbacks {
+        context: app as *mut c_void,
+        now: Some(now),
+        transmit: Some(transmit),
+        on_message: Some(on_transfer),
+        on_request: Some(on_request),
+        on_response: Some(on_transfer),
+    }
+}
+
+/// Flushes the frames from one node and passes them to another node
+unsafe fn deliver(from: *mut CanadensisNode, from_app: *mut App, to: *mut CanadensisNode) {
+    canadensis_node_flush(from);
+    let frames = std::mem::take(&mut (*from_app).transmitted);
+    for frame in frames {
+        assert_eq!(
+            canadensis_node_accept_frame(to, &frame),
+            CanadensisStatus::Ok
+        );
+    }
+}
+
+#[test]
+fn publish_and_call() {
+    let app_a: *mut App = Box::into_raw(Box::default());
+    let app_b: *mut App = Box::into_raw(Box::default());
+    unsafe {
+        let node_a = canadensis_node_new(10, 8, &callbacks(app_a));
+        let node_b = canadensis_node_new(11, 8, &callbacks(app_b));
+        assert!(!node_a.is_null());
+        assert!(!node_b.is_null());
+        assert_eq!(canadensis_node_id(node_a), 10);
+
+        // Message with more than one frame
+        let message: Vec<u8> = (0..20).collect();
+        assert_eq!(
+            canadensis_node_publish(node_a, 100, message.as_ptr(), message.len()),
+            CanadensisStatus::NotStarted
+        );
+        assert_eq!(
+            canadensis_node_start_publishing(node_a, 100, 1_000_000, 4),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(
+            canadensis_node_start_publishing(node_a, 100, 1_000_000, 4),
+            CanadensisStatus::Duplicate
+        );
+        assert_eq!(
+            canadensis_node_subscribe_message(node_b, 100, 64, 1_000_000),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(
+            canadensis_node_publish(node_a, 100, message.as_ptr(), message.len()),
+            CanadensisStatus::Ok
+        );
+        deliver(node_a, app_a, node_b);
+        assert_eq!(
+            (*app_b).received,
+            vec![(CanadensisTransferKind::Message, 100, 10, message)]
+        );
+        (*app_b).received.clear();
+
+        // Request and response
+        assert_eq!(
+            canadensis_node_subscribe_request(node_a, 200, 8, 1_000_000),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(
+            canadensis_node_start_sending_requests(node_b, 200, 1_000_000, 8, 4),
+            CanadensisStatus::Ok
+        );
+        let mut transfer_id = 0xff;
+        assert_eq!(
+            canadensis_node_call(node_b, 200, 10, [1, 2].as_ptr(), 2, &mut transfer_id),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(transfer_id, 0);
+        deliver(node_b, app_b, node_a);
+        assert_eq!(
+            (*app_a).received,
+            vec![(CanadensisTransferKind::Request, 200, 11, vec![1, 2])]
+        );
+        deliver(node_a, app_a, node_b);
+        assert_eq!(
+            (*app_b).received,
+            vec![(CanadensisTransferKind::Response, 200, 10, vec![0xaa])]
+        );
+
+        canadensis_node_free(node_a);
+        canadensis_node_free(node_b);
+        drop(Box::from_raw(app_a));
+        drop(Box::from_raw(app_b));
+    }
+}
+
+#[test]
+fn invalid_arguments() {
+    let app: *mut App = Box::into_raw(Box::default());
+    unsafe {
+        assert!(canadensis_node_new(128, 8, &callbacks(app)).is_null());
+        assert!(canadensis_node_new(1, 9, &callbacks(app)).is_null());
+        assert!(canadensis_node_new(1, 8, ptr::null()).is_null());
+        let node = canadensis_node_new(1, 8, &callbacks(app));
+        assert_eq!(
+            canadensis_node_start_publishing(node, 8192, 1000, 4),
+            CanadensisStatus::InvalidArgument
+        );
+        assert_eq!(
+            canadensis_node_start_publishing(node, 100, 1000, 8),
+            CanadensisStatus::InvalidArgument
+        );
+        assert_eq!(
+            canadensis_node_publish(ptr::null_mut(), 100, ptr::null(), 0),
+            CanadensisStatus::InvalidArgument
+        );
+
+        // Expired frames are not transmitted
+        assert_eq!(
+            canadensis_node_start_publishing(node, 100, 1000, 4),
+            CanadensisStatus::Ok
+        );
+        assert_eq!(
+            canadensis_node_publish(node, 100, ptr::null(), 0),
+            CanadensisStatus::Ok
+        );
+        (*app).now = 2000;
+        assert_eq!(canadensis_node_flush(node), 0);
+        assert!((*app).transmitted.is_empty());
+        canadensis_node_free(node);
+        drop(Box::from_raw(app));
+    }
+}
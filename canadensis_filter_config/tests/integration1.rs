@@ -28,7 +28,7 @@ fn optimize_several_ids() {
                     filter.accepts(*id)
                 );
             }
-            assert!(any_accepts(&optimized_filters, *id));
+            assert!(any_accepts(optimized_filters, *id));
         }
     }
 }
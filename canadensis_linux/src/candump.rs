@@ -0,0 +1,236 @@
+//!
+//! Decoding of log files recorded with `candump -l` (or `candump -L`)
+//!
+//! Each line of a log file looks like `(1436509052.249713) can0 107D552A#00000000047868E0`.
+//! CAN FD frames use two `#` characters followed by a flags digit, like `can0 123##1...`.
+//!
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use canadensis_can::{CanId, Frame, Mtu, OutOfMemoryError, Receiver, FRAME_CAPACITY};
+use canadensis_core::time::{MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::Transfer;
+
+/// A frame read from one line of a candump log file
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// The name of the interface that the frame was received on
+    pub interface: String,
+    /// The frame, with a timestamp in microseconds since the Unix epoch
+    pub frame: Frame<Microseconds64>,
+}
+
+/// Errors that can occur when parsing a log line
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// The line does not have the expected format
+    Format,
+    /// The frame has an 11-bit CAN ID, which UAVCAN does not use
+    StandardId,
+    /// The frame is a remote transmission request, which UAVCAN does not use
+    RemoteFrame,
+    /// The frame has more data than a canadensis frame can hold
+    Length,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Format => write!(f, "Invalid log line format"),
+            ParseError::StandardId => write!(f, "Frame has a standard CAN ID"),
+            ParseError::RemoteFrame => write!(f, "Frame is a remote frame"),
+            ParseError::Length => write!(f, "Frame data is too long"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses one line of a candump log file
+pub fn parse_line(line: &str) -> Result<LogEntry, ParseError> {
+    let mut parts = line.split_whitespace();
+    let timestamp = parts.next().ok_or(ParseError::Format)?;
+    let interface = parts.next().ok_or(ParseError::Format)?;
+    let frame_text = parts.next().ok_or(ParseError::Format)?;
+
+    let timestamp = parse_timestamp(timestamp)?;
+
+    let (id_text, data_text) = frame_text.split_once('#').ok_or(ParseError::Format)?;
+    let data_text = if let Some(fd_data) = data_text.strip_prefix('#') {
+        // CAN FD: one hexadecimal digit of flags, then data
+        let mut chars = fd_data.chars();
+        chars
+            .next()
+            .filter(char::is_ascii_hexdigit)
+            .ok_or(ParseError::Format)?;
+        chars.as_str()
+    } else if data_text.starts_with('R') {
+        return Err(ParseError::RemoteFrame);
+    } else {
+        data_text
+    };
+
+    // candump writes 3 digits for standard IDs and 8 digits for extended IDs
+    let id = match id_text.len() {
+        3 => return Err(ParseError::StandardId),
+        8 => u32::from_str_radix(id_text, 16).map_err(|_| ParseError::Format)?,
+        _ => return Err(ParseError::Format),
+    };
+    let id = CanId::try_from(id).map_err(|_| ParseError::Format)?;
+
+    let data = parse_data(data_text)?;
+    if data.len() > FRAME_CAPACITY {
+        return Err(ParseError::Length);
+    }
+
+    Ok(LogEntry {
+        interface: interface.to_owned(),
+        frame: Frame::new(timestamp, id, &data),
+    })
+}
+
+/// Parses a timestamp like `(1436509052.249713)` into microseconds
+fn parse_timestamp(text: &str) -> Result<Microseconds64, ParseError> {
+    let text = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+        .ok_or(ParseError::Format)?;
+    let (seconds, microseconds) = text.split_once('.').ok_or(ParseError::Format)?;
+    if microseconds.len() != 6 {
+        return Err(ParseError::Format);
+    }
+    let seconds: u64 = seconds.parse().map_err(|_| ParseError::Format)?;
+    let microseconds: u64 = microseconds.parse().map_err(|_| ParseError::Format)?;
+    let total = seconds
+        .checked_mul(1_000_000)
+        .and_then(|total| total.checked_add(microseconds))
+        .ok_or(ParseError::Format)?;
+    Ok(Microseconds64::new(total))
+}
+
+/// Parses frame data written as pairs of hexadecimal digits
+fn parse_data(text: &str) -> Result<Vec<u8>, ParseError> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return Err(ParseError::Format);
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| ParseError::Format))
+        .collect()
+}
+
+/// Reassembles transfers from the frames in a candump log file
+///
+/// The decoder uses a [`Receiver`] in promiscuous mode, so it reconstructs transfers on all ports,
+/// including service transfers between any two nodes. Each transfer has the timestamp of its first
+/// frame as recorded in the log.
+///
+/// The decoded transfers contain serialized payloads. Payloads of known types can be deserialized
+/// with `Deserialize::deserialize_from_bytes` from canadensis_encoding.
+pub struct LogDecoder {
+    receiver: Receiver<Microseconds64>,
+    /// Number of lines that could not be parsed
+    invalid_line_count: u64,
+}
+
+impl LogDecoder {
+    /// Creates a decoder
+    ///
+    /// payload_size_max: The maximum number of payload bytes expected on any port
+    /// (longer transfers will be dropped)
+    ///
+    /// timeout: The maximum time between the first and last frames in a transfer
+    pub fn new(mtu: Mtu, payload_size_max: usize, timeout: MicrosecondDuration64) -> Self {
+        let mut receiver = Receiver::new_anonymous(mtu);
+        receiver.enable_promiscuous(payload_size_max, timeout);
+        LogDecoder {
+            receiver,
+            invalid_line_count: 0,
+        }
+    }
+
+    /// Handles one line of a log file
+    ///
+    /// If the frame on this line completes a transfer, this function returns the transfer.
+    ///
+    /// Empty lines and lines with frames that UAVCAN does not use (standard IDs and remote frames)
+    /// are ignored. Other lines that cannot be parsed increment the invalid line count.
+    pub fn accept_line(
+        &mut self,
+        line: &str,
+    ) -> Result<Option<Transfer<Vec<u8>, Microseconds64>>, OutOfMemoryError> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        match parse_line(line) {
+            Ok(entry) => self.receiver.accept(entry.frame),
+            Err(ParseError::StandardId) | Err(ParseError::RemoteFrame) => Ok(None),
+            Err(e) => {
+                log::debug!("Ignoring invalid log line {:?}: {}", line, e);
+                self.invalid_line_count = self.invalid_line_count.wrapping_add(1);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads a log file to the end and calls a function with each reassembled transfer
+    pub fn decode<R, F>(&mut self, reader: R, mut handler: F) -> Result<(), DecodeError>
+    where
+        R: BufRead,
+        F: FnMut(Transfer<Vec<u8>, Microseconds64>),
+    {
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(transfer) = self.accept_line(&line)? {
+                handler(transfer);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of lines that could not be parsed
+    pub fn invalid_line_count(&self) -> u64 {
+        self.invalid_line_count
+    }
+
+    /// Returns a reference to the receiver used to reassemble transfers
+    ///
+    /// This can be used to get the transfer and error counts.
+    pub fn receiver(&self) -> &Receiver<Microseconds64> {
+        &self.receiver
+    }
+}
+
+/// Errors that can occur when decoding a log file
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Reading the file failed
+    Io(io::Error),
+    /// Memory for a transfer could not be allocated
+    Memory(OutOfMemoryError),
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(inner: io::Error) -> Self {
+        DecodeError::Io(inner)
+    }
+}
+
+impl From<OutOfMemoryError> for DecodeError {
+    fn from(inner: OutOfMemoryError) -> Self {
+        DecodeError::Memory(inner)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(e) => write!(f, "{}", e),
+            DecodeError::Memory(_) => write!(f, "Out of memory"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
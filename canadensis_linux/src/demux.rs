@@ -0,0 +1,256 @@
+//!
+//! Sharing one CAN interface between several nodes in a process
+//!
+//! A [`Demux`] sits between one driver (like a [`LinuxCan`](crate::LinuxCan)) and any number of
+//! independent nodes. Each node gets an endpoint made of a [`DemuxSink`], which the node uses as
+//! its transmit queue, and a [`DemuxReceiver`], which provides the frames that the node should
+//! accept.
+//!
+//! * Frames that the driver receives are passed to [`Demux::deliver`], which gives a copy to
+//!   every endpoint
+//! * [`Demux::pop_frame`] returns the highest-priority frame that any endpoint has sent. It also
+//!   gives a copy of the frame to every other endpoint, because the driver does not receive the
+//!   frames that it sends.
+//!
+//! The demultiplexer and its endpoints can be used from different threads.
+//!
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use canadensis_can::queue::FrameSink;
+use canadensis_can::{Frame, OutOfMemoryError};
+
+/// Shares one CAN interface between several nodes
+pub struct Demux<I> {
+    shared: Arc<Mutex<Shared<I>>>,
+}
+
+struct Shared<I> {
+    /// Frames waiting to be sent, from all endpoints
+    transmit: BinaryHeap<Reverse<TransmitEntry<I>>>,
+    /// The sequence number for the next frame pushed onto the transmit queue
+    next_sequence: u64,
+    /// A receive queue for each endpoint, or None if the endpoint's receiver has been dropped
+    receive: Vec<Option<VecDeque<Frame<I>>>>,
+    /// The maximum number of frames in each receive queue
+    receive_capacity: usize,
+    /// The number of frames that did not fit into a receive queue
+    dropped_frames: u64,
+}
+
+/// A frame in the transmit queue, ordered by CAN ID and then by the order it was added
+struct TransmitEntry<I> {
+    frame: Frame<I>,
+    sequence: u64,
+    /// The index of the endpoint that sent this frame
+    endpoint: usize,
+}
+
+impl<I> TransmitEntry<I> {
+    fn key(&self) -> (u32, u64) {
+        (u32::from(self.frame.id()), self.sequence)
+    }
+}
+
+impl<I> PartialEq for TransmitEntry<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<I> Eq for TransmitEntry<I> {}
+
+impl<I> PartialOrd for TransmitEntry<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I> Ord for TransmitEntry<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl<I: Clone> Demux<I> {
+    /// Creates a demultiplexer with no endpoints
+    ///
+    /// Each endpoint can hold up to `receive_capacity` received frames that its node has not
+    /// accepted yet. When an endpoint's receive queue is full, new frames for that endpoint are
+    /// dropped.
+    pub fn new(receive_capacity: usize) -> Self {
+        Demux {
+            shared: Arc::new(Mutex::new(Shared {
+                transmit: BinaryHeap::new(),
+                next_sequence: 0,
+                receive: Vec::new(),
+                receive_capacity,
+                dropped_frames: 0,
+            })),
+        }
+    }
+
+    /// Creates an endpoint for a node
+    ///
+    /// The node should use the sink as its transmit queue, and accept the frames that the
+    /// receiver provides.
+    pub fn endpoint(&self) -> (DemuxSink<I>, DemuxReceiver<I>) {
+        let mut shared = self.lock();
+        // Reuse the slot of an endpoint whose receiver has been dropped
+        let index = match shared.receive.iter().position(Option::is_none) {
+            Some(index) => {
+                shared.receive[index] = Some(VecDeque::new());
+                index
+            }
+            None => {
+                shared.receive.push(Some(VecDeque::new()));
+                shared.receive.len() - 1
+            }
+        };
+        (
+            DemuxSink {
+                shared: Arc::clone(&self.shared),
+                endpoint: index,
+            },
+            DemuxReceiver {
+                shared: Arc::clone(&self.shared),
+                endpoint: index,
+            },
+        )
+    }
+
+    /// Gives a frame that the driver received to every endpoint
+    pub fn deliver(&self, frame: Frame<I>) {
+        self.lock().deliver(frame, None);
+    }
+
+    /// Removes the highest-priority frame that any endpoint has sent and returns it
+    ///
+    /// A copy of the frame, with its timestamp set to `now`, is given to every endpoint except
+    /// the endpoint that sent it.
+    ///
+    /// The driver should call this function only when it can send a frame, because a frame
+    /// can't be returned after the other endpoints have received it.
+    pub fn pop_frame(&self, now: I) -> Option<Frame<I>> {
+        let mut shared = self.lock();
+        let Reverse(entry) = shared.transmit.pop()?;
+        let local_copy = Frame::new(now, entry.frame.id(), entry.frame.data());
+        shared.deliver(local_copy, Some(entry.endpoint));
+        Some(entry.frame)
+    }
+
+    /// Returns the number of frames waiting to be sent
+    pub fn transmit_len(&self) -> usize {
+        self.lock().transmit.len()
+    }
+
+    /// Returns the number of received frames that were dropped because an endpoint's receive
+    /// queue was full
+    pub fn dropped_frames(&self) -> u64 {
+        self.lock().dropped_frames
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared<I>> {
+        lock(&self.shared)
+    }
+}
+
+impl<I: Clone> Shared<I> {
+    /// Gives a frame to every endpoint except `except`
+    fn deliver(&mut self, frame: Frame<I>, except: Option<usize>) {
+        let capacity = self.receive_capacity;
+        let mut dropped = 0;
+        for (index, queue) in self.receive.iter_mut().enumerate() {
+            if Some(index) == except {
+                continue;
+            }
+            if let Some(queue) = queue {
+                if queue.len() < capacity {
+                    queue.push_back(frame.clone());
+                } else {
+                    dropped += 1;
+                }
+            }
+        }
+        if dropped != 0 {
+            log::warn!("Dropped a received frame for {} endpoints", dropped);
+            self.dropped_frames = self.dropped_frames.wrapping_add(dropped);
+        }
+    }
+}
+
+/// The transmit side of an endpoint, which a node uses as its transmit queue
+pub struct DemuxSink<I> {
+    shared: Arc<Mutex<Shared<I>>>,
+    endpoint: usize,
+}
+
+impl<I> FrameSink<I> for DemuxSink<I> {
+    fn try_reserve(&mut self, additional: usize) -> Result<(), OutOfMemoryError> {
+        lock(&self.shared)
+            .transmit
+            .try_reserve(additional)
+            .map_err(|_| OutOfMemoryError)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        lock(&self.shared).transmit.shrink_to_fit()
+    }
+
+    fn push_frame(&mut self, frame: Frame<I>) -> Result<(), OutOfMemoryError> {
+        let mut shared = lock(&self.shared);
+        let sequence = shared.next_sequence;
+        shared.next_sequence += 1;
+        shared.transmit.push(Reverse(TransmitEntry {
+            frame,
+            sequence,
+            endpoint: self.endpoint,
+        }));
+        Ok(())
+    }
+}
+
+/// The receive side of an endpoint, which provides the frames that a node should accept
+///
+/// When the receiver is dropped, the demultiplexer stops keeping frames for the endpoint.
+pub struct DemuxReceiver<I> {
+    shared: Arc<Mutex<Shared<I>>>,
+    endpoint: usize,
+}
+
+impl<I> DemuxReceiver<I> {
+    /// Removes and returns the oldest frame for this endpoint, if any
+    pub fn receive(&self) -> Option<Frame<I>> {
+        lock(&self.shared).receive[self.endpoint]
+            .as_mut()
+            .and_then(VecDeque::pop_front)
+    }
+
+    /// Returns the number of frames waiting for this endpoint
+    pub fn len(&self) -> usize {
+        lock(&self.shared).receive[self.endpoint]
+            .as_ref()
+            .map(VecDeque::len)
+            .unwrap_or(0)
+    }
+
+    /// Returns true if no frames are waiting for this endpoint
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<I> Drop for DemuxReceiver<I> {
+    fn drop(&mut self) {
+        lock(&self.shared).receive[self.endpoint] = None;
+    }
+}
+
+/// Locks the shared state, ignoring poisoning because the state is always consistent
+fn lock<I>(shared: &Mutex<Shared<I>>) -> MutexGuard<'_, Shared<I>> {
+    shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
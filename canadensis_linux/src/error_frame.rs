@@ -0,0 +1,91 @@
+//!
+//! Decoding SocketCAN error frames
+//!
+//! When a socket has error reporting enabled (see [`LinuxCan::set_error_reporting`]), the kernel
+//! sends error frames that describe bus errors and controller state changes. The error class is
+//! in the CAN ID, and the details are in the data bytes. The format is defined in
+//! `linux/can/error.h`.
+//!
+//! [`LinuxCan::set_error_reporting`]: crate::LinuxCan::set_error_reporting
+//!
+
+use canadensis_can::bus_status::{BusEvent, ErrorCounters, ErrorState};
+use socketcan::CANFrame;
+
+// Error classes, in the CAN ID of an error frame
+const CAN_ERR_TX_TIMEOUT: u32 = 0x1;
+const CAN_ERR_LOSTARB: u32 = 0x2;
+const CAN_ERR_CRTL: u32 = 0x4;
+const CAN_ERR_PROT: u32 = 0x8;
+const CAN_ERR_TRX: u32 = 0x10;
+const CAN_ERR_ACK: u32 = 0x20;
+const CAN_ERR_BUSOFF: u32 = 0x40;
+const CAN_ERR_BUSERROR: u32 = 0x80;
+const CAN_ERR_RESTARTED: u32 = 0x100;
+const CAN_ERR_CNT: u32 = 0x200;
+
+// Controller problems, in data byte 1
+const CAN_ERR_CRTL_RX_OVERFLOW: u8 = 0x1;
+const CAN_ERR_CRTL_TX_OVERFLOW: u8 = 0x2;
+const CAN_ERR_CRTL_RX_WARNING: u8 = 0x4;
+const CAN_ERR_CRTL_TX_WARNING: u8 = 0x8;
+const CAN_ERR_CRTL_RX_PASSIVE: u8 = 0x10;
+const CAN_ERR_CRTL_TX_PASSIVE: u8 = 0x20;
+const CAN_ERR_CRTL_ACTIVE: u8 = 0x40;
+
+/// Decodes an error frame and passes each event that it describes to `handler`
+///
+/// If the frame is not an error frame, this function does nothing.
+pub fn error_events<F>(frame: &CANFrame, mut handler: F)
+where
+    F: FnMut(BusEvent),
+{
+    if !frame.is_error() {
+        return;
+    }
+    let class = frame.err();
+    let data = frame.data();
+    let byte = |index: usize| data.get(index).copied().unwrap_or(0);
+
+    if class & CAN_ERR_CRTL != 0 {
+        let controller = byte(1);
+        if controller & CAN_ERR_CRTL_ACTIVE != 0 {
+            handler(BusEvent::StateChanged(ErrorState::Active));
+        }
+        if controller & (CAN_ERR_CRTL_RX_PASSIVE | CAN_ERR_CRTL_TX_PASSIVE) != 0 {
+            handler(BusEvent::StateChanged(ErrorState::Passive));
+        } else if controller & (CAN_ERR_CRTL_RX_WARNING | CAN_ERR_CRTL_TX_WARNING) != 0 {
+            handler(BusEvent::Warning);
+        }
+        if controller & (CAN_ERR_CRTL_RX_OVERFLOW | CAN_ERR_CRTL_TX_OVERFLOW) != 0 {
+            handler(BusEvent::ReceiveOverflow);
+        }
+    }
+    if class & CAN_ERR_BUSOFF != 0 {
+        handler(BusEvent::StateChanged(ErrorState::BusOff));
+    }
+    if class & CAN_ERR_RESTARTED != 0 {
+        handler(BusEvent::Restarted);
+    }
+    if class & CAN_ERR_TX_TIMEOUT != 0 {
+        handler(BusEvent::TransmitTimeout);
+    }
+    if class & CAN_ERR_LOSTARB != 0 {
+        handler(BusEvent::ArbitrationLost);
+    }
+    if class & CAN_ERR_ACK != 0 {
+        handler(BusEvent::NoAcknowledgement);
+    }
+    if class & (CAN_ERR_PROT | CAN_ERR_BUSERROR) != 0 {
+        handler(BusEvent::ProtocolViolation);
+    }
+    if class & CAN_ERR_TRX != 0 {
+        handler(BusEvent::Transceiver);
+    }
+    if class & CAN_ERR_CNT != 0 {
+        handler(BusEvent::Counters(ErrorCounters {
+            transmit: byte(6).into(),
+            receive: byte(7).into(),
+        }));
+    }
+}
@@ -2,23 +2,80 @@
 //! Utilities for running UAVCAN nodes on Linux using the SocketCAN interface
 //!
 
+extern crate canadensis;
 extern crate canadensis_can;
 extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
 extern crate canadensis_filter_config;
+extern crate heapless;
+extern crate libc;
 extern crate log;
 extern crate socketcan;
 
+pub mod candump;
+pub mod demux;
+pub mod error_frame;
+pub mod machine_id;
+pub mod pcap;
+pub mod tcp;
+pub mod udp_gateway;
+pub mod vcan;
+
+use canadensis_can::bus_status::{BusEvent, BusMonitor, BusStatus, ErrorCounters, ErrorState};
 use canadensis_core::time::{Clock, Instant, Microseconds64};
 use canadensis_filter_config::Filter;
 use socketcan::CANSocket;
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
+
+/// The maximum time to wait for a socket to become writable before trying to send again
+const WRITE_RETRY_INTERVAL_MS: u64 = 10;
+/// The maximum number of error events that are kept until the application reads them
+const MAX_PENDING_EVENTS: usize = 64;
+
+/// An error that can occur when sending a frame
+#[derive(Debug)]
+pub enum SendError {
+    /// The socket can't accept the frame now because its transmit buffer is full
+    ///
+    /// This contains the frame that was not sent.
+    WouldBlock(canadensis_can::Frame<Microseconds64>),
+    /// Another error occurred
+    Io(io::Error),
+}
+
+impl From<SendError> for io::Error {
+    fn from(error: SendError) -> Self {
+        match error {
+            SendError::WouldBlock(_) => {
+                io::Error::new(io::ErrorKind::WouldBlock, "CAN socket transmit buffer full")
+            }
+            SendError::Io(e) => e,
+        }
+    }
+}
 
 /// An adapter between SocketCAN and the canadensis frame format
+///
+/// If error reporting is enabled, this implements [`BusMonitor`] using the error frames that
+/// the kernel sends.
+///
+/// For passive monitoring, the CAN controller can be put in listen-only mode when the interface
+/// is configured (for example, `ip link set can0 type can bitrate 1000000 listen-only on`).
+/// A node that uses a [`ListenOnlyQueue`](canadensis_can::queue::ListenOnlyQueue) will then
+/// never send anything on the bus, including acknowledgements and error frames.
 pub struct LinuxCan {
     socket: CANSocket,
     clock: SystemClock,
+    /// Error events that have been received but not read by the application
+    events: VecDeque<BusEvent>,
+    /// The controller status, based on the error frames received so far
+    status: BusStatus,
 }
 
 impl LinuxCan {
@@ -26,14 +83,44 @@ impl LinuxCan {
         LinuxCan {
             socket,
             clock: SystemClock::new(),
+            events: VecDeque::new(),
+            status: BusStatus {
+                state: ErrorState::Active,
+                counters: ErrorCounters::default(),
+            },
         }
     }
 
+    /// Enables or disables error reporting
+    ///
+    /// When error reporting is enabled, the kernel sends error frames for all error classes.
+    /// [`receive`](#method.receive) decodes them into events, which can be read using
+    /// [`BusMonitor::next_event`]. Error frames are never returned as normal frames.
+    pub fn set_error_reporting(&mut self, enabled: bool) -> io::Result<()> {
+        if enabled {
+            self.socket.error_filter_accept_all()
+        } else {
+            self.socket.error_filter_drop_all()
+        }
+    }
+
+    /// Enables or disables non-blocking mode
+    ///
+    /// In non-blocking mode, [`receive`](#method.receive) returns an error with kind
+    /// [`WouldBlock`](io::ErrorKind::WouldBlock) if no frame is available.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
     /// Receives a frame
+    ///
+    /// Error frames are decoded into events and not returned.
     pub fn receive(&mut self) -> io::Result<canadensis_can::Frame<Microseconds64>> {
         loop {
             let socketcan_frame = self.socket.read_frame()?;
-            if socketcan_frame.data().len() <= canadensis_can::FRAME_CAPACITY {
+            if socketcan_frame.is_error() {
+                self.handle_error_frame(&socketcan_frame);
+            } else if socketcan_frame.data().len() <= canadensis_can::FRAME_CAPACITY {
                 let uavcan_frame = canadensis_can::Frame::new(
                     self.clock.now(),
                     socketcan_frame.id().try_into().expect("Invalid CAN ID"),
@@ -49,8 +136,55 @@ impl LinuxCan {
         }
     }
 
+    /// Decodes an error frame, updates the status, and stores the events
+    fn handle_error_frame(&mut self, frame: &socketcan::CANFrame) {
+        let status = &mut self.status;
+        let events = &mut self.events;
+        error_frame::error_events(frame, |event| {
+            match event {
+                BusEvent::StateChanged(state) => status.state = state,
+                BusEvent::Restarted => {
+                    status.state = ErrorState::Active;
+                    status.counters = ErrorCounters::default();
+                }
+                BusEvent::Counters(counters) => status.counters = counters,
+                _ => {}
+            }
+            if events.len() == MAX_PENDING_EVENTS {
+                log::warn!("Discarding an old CAN error event");
+                events.pop_front();
+            }
+            events.push_back(event);
+        });
+    }
+
     /// Sends a frame, or discards the frame if its deadline has passed
+    ///
+    /// If the socket's transmit buffer is full, this function waits until the socket can accept
+    /// the frame or the frame's deadline passes.
     pub fn send(&mut self, frame: canadensis_can::Frame<Microseconds64>) -> io::Result<()> {
+        let mut frame = frame;
+        loop {
+            match self.try_send(frame) {
+                Ok(()) => return Ok(()),
+                Err(SendError::WouldBlock(returned)) => {
+                    self.wait_writable(returned.timestamp())?;
+                    frame = returned;
+                }
+                Err(SendError::Io(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Sends a frame without waiting, or discards the frame if its deadline has passed
+    ///
+    /// If the socket's transmit buffer is full, this function returns the frame in a
+    /// [`SendError::WouldBlock`] error. The caller can try again later, or return the frame to
+    /// its queue.
+    pub fn try_send(
+        &mut self,
+        frame: canadensis_can::Frame<Microseconds64>,
+    ) -> Result<(), SendError> {
         // Drop this frame if its deadline has passed
         if frame.timestamp().overflow_safe_compare(&self.clock.now()) == Ordering::Less {
             log::warn!("Dropping frame that has missed its deadline");
@@ -59,7 +193,50 @@ impl LinuxCan {
         let socketcan_frame =
             socketcan::CANFrame::new(frame.id().into(), frame.data(), false, false)
                 .expect("Invalid frame format");
-        self.socket.write_frame_insist(&socketcan_frame)
+        loop {
+            match self.socket.write_frame(&socketcan_frame) {
+                Ok(()) => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // The socket is non-blocking and full, or the network interface queue is full
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.raw_os_error() == Some(libc::ENOBUFS) =>
+                {
+                    return Err(SendError::WouldBlock(frame))
+                }
+                Err(e) => return Err(SendError::Io(e)),
+            }
+        }
+    }
+
+    /// Waits until the socket may be able to send a frame, or the deadline passes
+    fn wait_writable(&mut self, deadline: Microseconds64) -> io::Result<()> {
+        let now = self.clock.now();
+        if deadline.overflow_safe_compare(&now) != Ordering::Greater {
+            return Ok(());
+        }
+        let remaining_us = deadline.as_microseconds() - now.as_microseconds();
+        // Some drivers report that the socket is writable while the interface queue is full,
+        // so wait for at most a short time before trying again.
+        let timeout_ms = (remaining_us / 1000).clamp(1, WRITE_RETRY_INTERVAL_MS);
+        let mut poll_fd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        // Safety: poll_fd is valid for the duration of the call, and the count is 1
+        let status = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms as libc::c_int) };
+        if status == -1 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        if poll_fd.revents & libc::POLLOUT != 0 {
+            // Writable, but the interface queue may still be full. Give it some time to drain.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
     }
 
     /// Replaces any configured filters with one filter that accepts all frames
@@ -76,6 +253,31 @@ impl LinuxCan {
     }
 }
 
+impl AsRawFd for LinuxCan {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl BusMonitor for LinuxCan {
+    /// Returns the status based on the error frames received so far
+    ///
+    /// If error reporting is not enabled, this always reports an error-active controller.
+    fn bus_status(&mut self) -> BusStatus {
+        self.status
+    }
+
+    /// Does nothing
+    ///
+    /// SocketCAN interfaces are restarted by the kernel. Automatic restarts can be enabled with
+    /// a command like `ip link set can0 type can restart-ms 100`.
+    fn restart(&mut self) {}
+
+    fn next_event(&mut self) -> Option<BusEvent> {
+        self.events.pop_front()
+    }
+}
+
 /// A clock that uses the operating system's clock
 #[derive(Debug, Clone)]
 pub struct SystemClock {
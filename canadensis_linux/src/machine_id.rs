@@ -0,0 +1,113 @@
+//!
+//! Unique IDs based on the machine ID
+//!
+//! Most Linux systems have a 128-bit machine ID in `/etc/machine-id`, which is generated when
+//! the system is installed and does not change when it restarts. A [`MachineId`] derives the
+//! unique ID of a node from it, so that a node running on Linux keeps the same unique ID and can
+//! get the same node ID from a plug-and-play allocator every time.
+//!
+//! The machine ID is confidential, so it is never sent on the bus. Like
+//! `sd_id128_get_machine_app_specific` in systemd, the unique ID is an HMAC-SHA256 of a
+//! canadensis-specific application ID (and the instance number), keyed with the machine ID.
+//! The machine ID cannot be recovered from the unique ID.
+//!
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use canadensis_core::unique_id::UniqueIdProvider;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The files that may contain the machine ID, in order of preference
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// The application ID that makes the unique IDs different from those of other applications
+const APPLICATION_ID: [u8; 16] = [
+    0x66, 0xde, 0xbe, 0xd4, 0xc6, 0x63, 0x45, 0x9d, 0x00, 0x23, 0x2f, 0x9a, 0x59, 0x99, 0x21, 0xf0,
+];
+
+/// A unique ID derived from the machine ID of this computer
+///
+/// If more than one node runs on the same computer, each node needs a different unique ID.
+/// [`with_instance`](#method.with_instance) changes the ID to make it different for each node.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct MachineId {
+    /// The machine ID
+    machine_id: [u8; 16],
+    /// The instance number
+    instance: u8,
+}
+
+impl MachineId {
+    /// Reads the machine ID from `/etc/machine-id`, or from `/var/lib/dbus/machine-id` if the
+    /// first file does not exist
+    pub fn read() -> io::Result<Self> {
+        let mut last_error = None;
+        for path in MACHINE_ID_PATHS.iter() {
+            match Self::read_from(path) {
+                Ok(id) => return Ok(id),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("No machine ID paths"))
+    }
+
+    /// Reads a machine ID from a file
+    pub fn read_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        parse_machine_id(&text)
+            .map(|machine_id| MachineId {
+                machine_id,
+                instance: 0,
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid machine ID"))
+    }
+
+    /// Returns a copy of this ID that is different for each value of `instance`
+    ///
+    /// The instance number is part of the hashed data, so the unique IDs of different instances
+    /// are not related. [`read`](#method.read) and [`read_from`](#method.read_from) return
+    /// instance 0.
+    pub fn with_instance(mut self, instance: u8) -> Self {
+        self.instance = instance;
+        self
+    }
+}
+
+impl UniqueIdProvider for MachineId {
+    fn unique_id(&self) -> [u8; 16] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.machine_id)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&APPLICATION_ID);
+        mac.update(&[self.instance]);
+        let hash = mac.finalize().into_bytes();
+        let mut id = [0u8; 16];
+        id.copy_from_slice(&hash[..16]);
+        id
+    }
+}
+
+impl std::fmt::Debug for MachineId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't show the machine ID
+        f.debug_struct("MachineId")
+            .field("unique_id", &self.unique_id())
+            .field("instance", &self.instance)
+            .finish()
+    }
+}
+
+/// Parses a machine ID, which is 32 hexadecimal digits optionally followed by whitespace
+pub fn parse_machine_id(text: &str) -> Option<[u8; 16]> {
+    let text = text.trim_end();
+    if text.len() != 32 || !text.is_ascii() {
+        return None;
+    }
+    let mut id = [0u8; 16];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(id)
+}
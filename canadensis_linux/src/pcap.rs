@@ -0,0 +1,557 @@
+//!
+//! Capture of CAN frames to pcapng files, and replay of pcap or pcapng captures
+//!
+//! Frames are stored with the `LINKTYPE_CAN_SOCKETCAN` link type, so the files can also be opened
+//! with Wireshark or tcpdump.
+//!
+//! Only the CAN transport is supported, because canadensis does not have a UDP transport.
+//!
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use canadensis_can::{CanId, Frame, FRAME_CAPACITY};
+use canadensis_core::time::Microseconds64;
+
+/// The link type for frames in the SocketCAN format
+const LINKTYPE_CAN_SOCKETCAN: u16 = 227;
+/// The maximum length of a captured SocketCAN frame (a CAN FD frame with 64 bytes of data)
+const SNAPLEN: u32 = 72;
+
+/// pcapng block types
+const BLOCK_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+/// pcapng byte-order magic
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+/// pcapng option code for interface timestamp resolution
+const OPTION_IF_TSRESOL: u16 = 9;
+
+/// Classic pcap magic numbers with microsecond and nanosecond timestamps
+const PCAP_MAGIC_MICROSECONDS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOSECONDS: u32 = 0xa1b2_3c4d;
+
+/// SocketCAN flag that indicates an extended (29-bit) CAN ID
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+/// SocketCAN flag that indicates a remote transmission request
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+/// SocketCAN flag that indicates an error frame
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+/// SocketCAN CAN FD flag that indicates that a frame is a CAN FD frame
+const CANFD_FDF: u8 = 0x04;
+
+/// Writes frames into a pcapng file
+///
+/// Each frame is written with its timestamp, interpreted as a number of microseconds since the
+/// Unix epoch.
+pub struct PcapngWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Creates a writer and writes the pcapng section header and interface description
+    ///
+    /// interface_name: The name of the interface that the frames were received on, or None
+    pub fn new(mut writer: W, interface_name: Option<&str>) -> io::Result<Self> {
+        // Section header: byte-order magic, version 1.0, unknown section length
+        let mut section_header = Vec::with_capacity(16);
+        section_header.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        section_header.extend_from_slice(&1u16.to_le_bytes());
+        section_header.extend_from_slice(&0u16.to_le_bytes());
+        section_header.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut writer, BLOCK_SECTION_HEADER, &section_header)?;
+
+        // Interface description: link type, reserved, snap length, options
+        let mut interface = Vec::new();
+        interface.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_le_bytes());
+        interface.extend_from_slice(&0u16.to_le_bytes());
+        interface.extend_from_slice(&SNAPLEN.to_le_bytes());
+        if let Some(name) = interface_name {
+            // Option 2: if_name
+            write_option(&mut interface, 2, name.as_bytes());
+        }
+        // Timestamps are in microseconds
+        write_option(&mut interface, OPTION_IF_TSRESOL, &[6]);
+        // End of options
+        write_option(&mut interface, 0, &[]);
+        write_block(&mut writer, BLOCK_INTERFACE_DESCRIPTION, &interface)?;
+
+        Ok(PcapngWriter { writer })
+    }
+
+    /// Writes a frame
+    pub fn write_frame(&mut self, frame: &Frame<Microseconds64>) -> io::Result<()> {
+        let data = encode_socketcan(frame);
+        let timestamp = frame.timestamp().as_microseconds();
+
+        let mut packet = Vec::with_capacity(20 + data.len() + 3);
+        // Interface 0
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        packet.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        // Captured and original lengths
+        packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&data);
+        pad_to_4(&mut packet);
+        write_block(&mut self.writer, BLOCK_ENHANCED_PACKET, &packet)
+    }
+
+    /// Flushes the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Writes a pcapng block with the provided type and body (the body length must be a multiple of 4)
+fn write_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    debug_assert!(body.len().is_multiple_of(4));
+    let total_length = (body.len() + 12) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_length.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&total_length.to_le_bytes())
+}
+
+/// Appends a pcapng option, padded to a multiple of 4 bytes
+fn write_option(buffer: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buffer.extend_from_slice(&code.to_le_bytes());
+    buffer.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(value);
+    pad_to_4(buffer);
+}
+
+fn pad_to_4(buffer: &mut Vec<u8>) {
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+}
+
+/// Encodes a frame in the SocketCAN format (ID and flags in network byte order, length,
+/// CAN FD flags, two reserved bytes, and data)
+fn encode_socketcan<I>(frame: &Frame<I>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + frame.data().len());
+    bytes.extend_from_slice(&(u32::from(frame.id()) | CAN_EFF_FLAG).to_be_bytes());
+    bytes.push(frame.data().len() as u8);
+    bytes.push(if frame.data().len() > 8 { CANFD_FDF } else { 0 });
+    bytes.extend_from_slice(&[0, 0]);
+    bytes.extend_from_slice(frame.data());
+    bytes
+}
+
+/// Decodes a frame in the SocketCAN format
+///
+/// This function returns None for frames that UAVCAN does not use (standard IDs, remote frames,
+/// and error frames) and frames that canadensis can't hold.
+fn decode_socketcan(timestamp: Microseconds64, bytes: &[u8]) -> Option<Frame<Microseconds64>> {
+    if bytes.len() < 8 {
+        return None;
+    }
+    let id_and_flags = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    if id_and_flags & CAN_EFF_FLAG == 0 || id_and_flags & (CAN_RTR_FLAG | CAN_ERR_FLAG) != 0 {
+        return None;
+    }
+    let length = usize::from(bytes[4]);
+    let data = bytes.get(8..8 + length)?;
+    if data.len() > FRAME_CAPACITY {
+        return None;
+    }
+    let id = CanId::try_from(id_and_flags & !(CAN_EFF_FLAG | CAN_RTR_FLAG | CAN_ERR_FLAG)).ok()?;
+    Some(Frame::new(timestamp, id, data))
+}
+
+/// Reads frames from a pcap or pcapng file
+///
+/// The reader accepts classic pcap files with microsecond or nanosecond timestamps and pcapng
+/// files in either byte order. Only packets with the `LINKTYPE_CAN_SOCKETCAN` link type are read.
+/// Packets on other interfaces, standard-ID frames, remote frames, and error frames are skipped.
+///
+/// The timestamp of each frame is the capture timestamp in microseconds since the Unix epoch.
+pub struct CaptureReader<R> {
+    reader: R,
+    format: Format,
+}
+
+/// Information about the file format
+enum Format {
+    Pcap {
+        big_endian: bool,
+        /// Number of timestamp fraction units per second
+        units_per_second: u64,
+        /// True if the link type is CAN_SOCKETCAN
+        socketcan: bool,
+    },
+    Pcapng {
+        big_endian: bool,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// A pcapng interface
+struct Interface {
+    /// True if the link type is CAN_SOCKETCAN
+    socketcan: bool,
+    /// Number of timestamp units per second
+    units_per_second: u64,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Creates a reader and reads the file header
+    pub fn new(mut reader: R) -> Result<Self, CaptureError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let format = match u32::from_le_bytes(magic) {
+            BLOCK_SECTION_HEADER => {
+                let mut format = Format::Pcapng {
+                    big_endian: false,
+                    interfaces: Vec::new(),
+                };
+                read_section_header(&mut reader, &mut format)?;
+                format
+            }
+            magic_le => {
+                let magic_be = u32::from_be_bytes(magic);
+                let (big_endian, units_per_second) = if magic_le == PCAP_MAGIC_MICROSECONDS {
+                    (false, 1_000_000)
+                } else if magic_le == PCAP_MAGIC_NANOSECONDS {
+                    (false, 1_000_000_000)
+                } else if magic_be == PCAP_MAGIC_MICROSECONDS {
+                    (true, 1_000_000)
+                } else if magic_be == PCAP_MAGIC_NANOSECONDS {
+                    (true, 1_000_000_000)
+                } else {
+                    return Err(CaptureError::Format);
+                };
+                // Version (4 bytes), time zone (4), sigfigs (4), snap length (4), link type (4)
+                let mut header = [0u8; 20];
+                reader.read_exact(&mut header)?;
+                let link_type = read_u32(&header[16..], big_endian);
+                Format::Pcap {
+                    big_endian,
+                    units_per_second,
+                    socketcan: link_type == u32::from(LINKTYPE_CAN_SOCKETCAN),
+                }
+            }
+        };
+        Ok(CaptureReader { reader, format })
+    }
+
+    /// Reads the next CAN frame from the file
+    ///
+    /// This function returns `Ok(None)` at the end of the file.
+    pub fn read_frame(&mut self) -> Result<Option<Frame<Microseconds64>>, CaptureError> {
+        loop {
+            let packet = match &self.format {
+                Format::Pcap { .. } => self.read_pcap_packet()?,
+                Format::Pcapng { .. } => self.read_pcapng_packet()?,
+            };
+            match packet {
+                Packet::End => return Ok(None),
+                Packet::Skip => {}
+                Packet::Data { timestamp, data } => {
+                    if let Some(frame) = decode_socketcan(timestamp, &data) {
+                        return Ok(Some(frame));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_pcap_packet(&mut self) -> Result<Packet, CaptureError> {
+        let (big_endian, units_per_second, socketcan) = match self.format {
+            Format::Pcap {
+                big_endian,
+                units_per_second,
+                socketcan,
+            } => (big_endian, units_per_second, socketcan),
+            Format::Pcapng { .. } => unreachable!(),
+        };
+        let mut header = [0u8; 16];
+        if !read_exact_or_end(&mut self.reader, &mut header)? {
+            return Ok(Packet::End);
+        }
+        let seconds = u64::from(read_u32(&header[0..], big_endian));
+        let fraction = u64::from(read_u32(&header[4..], big_endian));
+        let captured_length = read_u32(&header[8..], big_endian) as usize;
+        let data = read_vec(&mut self.reader, captured_length)?;
+        if !socketcan {
+            return Ok(Packet::Skip);
+        }
+        let microseconds = seconds * 1_000_000 + fraction * 1_000_000 / units_per_second;
+        Ok(Packet::Data {
+            timestamp: Microseconds64::new(microseconds),
+            data,
+        })
+    }
+
+    fn read_pcapng_packet(&mut self) -> Result<Packet, CaptureError> {
+        let mut block_header = [0u8; 8];
+        if !read_exact_or_end(&mut self.reader, &mut block_header)? {
+            return Ok(Packet::End);
+        }
+        if u32::from_le_bytes([
+            block_header[0],
+            block_header[1],
+            block_header[2],
+            block_header[3],
+        ]) == BLOCK_SECTION_HEADER
+        {
+            // New section, which may have a different byte order. Go back to the start of the
+            // block body (after the block type).
+            let mut length_and_rest =
+                io::Cursor::new(block_header[4..].to_vec()).chain(&mut self.reader);
+            read_section_header(&mut length_and_rest, &mut self.format)?;
+            return Ok(Packet::Skip);
+        }
+
+        let (big_endian, interfaces) = match &mut self.format {
+            Format::Pcapng {
+                big_endian,
+                interfaces,
+            } => (*big_endian, interfaces),
+            Format::Pcap { .. } => unreachable!(),
+        };
+        let block_type = read_u32(&block_header[0..], big_endian);
+        let total_length = read_u32(&block_header[4..], big_endian) as usize;
+        if total_length < 12 || !total_length.is_multiple_of(4) {
+            return Err(CaptureError::Format);
+        }
+        // Read the body and the trailing length
+        let body_and_trailer = read_vec(&mut self.reader, total_length - 8)?;
+        let body = &body_and_trailer[..body_and_trailer.len() - 4];
+
+        match block_type {
+            BLOCK_INTERFACE_DESCRIPTION => {
+                if body.len() < 8 {
+                    return Err(CaptureError::Format);
+                }
+                let link_type = read_u16(&body[0..], big_endian);
+                let units_per_second = read_tsresol(&body[8..], big_endian)?;
+                interfaces.push(Interface {
+                    socketcan: link_type == LINKTYPE_CAN_SOCKETCAN,
+                    units_per_second,
+                });
+                Ok(Packet::Skip)
+            }
+            BLOCK_ENHANCED_PACKET => {
+                if body.len() < 20 {
+                    return Err(CaptureError::Format);
+                }
+                let interface_id = read_u32(&body[0..], big_endian) as usize;
+                let timestamp = (u64::from(read_u32(&body[4..], big_endian)) << 32)
+                    | u64::from(read_u32(&body[8..], big_endian));
+                let captured_length = read_u32(&body[12..], big_endian) as usize;
+                let data = body
+                    .get(20..20 + captured_length)
+                    .ok_or(CaptureError::Format)?;
+                let interface = interfaces.get(interface_id).ok_or(CaptureError::Format)?;
+                if !interface.socketcan {
+                    return Ok(Packet::Skip);
+                }
+                let microseconds = (u128::from(timestamp) * 1_000_000
+                    / u128::from(interface.units_per_second))
+                    as u64;
+                Ok(Packet::Data {
+                    timestamp: Microseconds64::new(microseconds),
+                    data: data.to_vec(),
+                })
+            }
+            BLOCK_SIMPLE_PACKET => {
+                // Simple packets have no timestamps, so they can't be replayed
+                Ok(Packet::Skip)
+            }
+            _ => Ok(Packet::Skip),
+        }
+    }
+}
+
+/// A packet read from a file
+enum Packet {
+    /// End of file
+    End,
+    /// A block or packet that does not contain a frame
+    Skip,
+    /// Packet data
+    Data {
+        timestamp: Microseconds64,
+        data: Vec<u8>,
+    },
+}
+
+/// Reads a pcapng section header, starting after the block type, and resets the interfaces
+fn read_section_header<R: Read>(reader: &mut R, format: &mut Format) -> Result<(), CaptureError> {
+    let mut length_and_magic = [0u8; 8];
+    reader.read_exact(&mut length_and_magic)?;
+    let big_endian = match u32::from_le_bytes([
+        length_and_magic[4],
+        length_and_magic[5],
+        length_and_magic[6],
+        length_and_magic[7],
+    ]) {
+        BYTE_ORDER_MAGIC => false,
+        magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+        _ => return Err(CaptureError::Format),
+    };
+    let total_length = read_u32(&length_and_magic[0..], big_endian) as usize;
+    if total_length < 28 || !total_length.is_multiple_of(4) {
+        return Err(CaptureError::Format);
+    }
+    // Skip the rest of the block (version, section length, options, trailing length)
+    read_vec(reader, total_length - 12)?;
+    *format = Format::Pcapng {
+        big_endian,
+        interfaces: Vec::new(),
+    };
+    Ok(())
+}
+
+/// Finds the if_tsresol option in a list of interface options and returns the number of timestamp
+/// units per second
+fn read_tsresol(mut options: &[u8], big_endian: bool) -> Result<u64, CaptureError> {
+    while options.len() >= 4 {
+        let code = read_u16(&options[0..], big_endian);
+        let length = usize::from(read_u16(&options[2..], big_endian));
+        let value = options.get(4..4 + length).ok_or(CaptureError::Format)?;
+        if code == 0 {
+            break;
+        }
+        if code == OPTION_IF_TSRESOL {
+            let resolution = *value.first().ok_or(CaptureError::Format)?;
+            let exponent = u32::from(resolution & 0x7f);
+            let units = if resolution & 0x80 == 0 {
+                10u64.checked_pow(exponent)
+            } else {
+                2u64.checked_pow(exponent)
+            };
+            return units.ok_or(CaptureError::Format);
+        }
+        let padded_length = length.div_ceil(4) * 4;
+        options = options.get(4 + padded_length..).unwrap_or(&[]);
+    }
+    // Default resolution is microseconds
+    Ok(1_000_000)
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Reads exactly `length` bytes
+///
+/// The length comes from the file, so the buffer only grows as bytes are actually read. A corrupt
+/// length causes an `UnexpectedEof` error instead of a huge allocation.
+fn read_vec<R: Read>(reader: &mut R, length: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader.take(length as u64).read_to_end(&mut buffer)?;
+    if buffer.len() == length {
+        Ok(buffer)
+    } else {
+        Err(io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+/// Fills a buffer, returning false if the reader was already at the end
+fn read_exact_or_end<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// Reads all frames from a capture and passes them to a function, waiting between frames to
+/// reproduce the original timing
+///
+/// speed: The replay speed relative to the original capture. For example, 1.0 replays in real
+/// time and 10.0 replays ten times faster. If speed is zero, infinite, or not a number, frames are
+/// replayed as quickly as possible.
+///
+/// The frames passed to the handler keep their original capture timestamps. A handler that feeds
+/// the frames into a node may need to replace the timestamps with the current time from the
+/// node's clock.
+pub fn replay<R, F>(
+    reader: &mut CaptureReader<R>,
+    speed: f64,
+    mut handler: F,
+) -> Result<(), CaptureError>
+where
+    R: Read,
+    F: FnMut(Frame<Microseconds64>),
+{
+    let start = std::time::Instant::now();
+    let mut first_timestamp: Option<u64> = None;
+    while let Some(frame) = reader.read_frame()? {
+        if speed.is_normal() && speed > 0.0 {
+            let timestamp = frame.timestamp().as_microseconds();
+            let first = *first_timestamp.get_or_insert(timestamp);
+            let capture_offset = timestamp.saturating_sub(first);
+            let target = Duration::from_secs_f64(capture_offset as f64 / 1_000_000.0 / speed);
+            let elapsed = start.elapsed();
+            if target > elapsed {
+                thread::sleep(target - elapsed);
+            }
+        }
+        handler(frame);
+    }
+    Ok(())
+}
+
+/// Errors that can occur when reading a capture file
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Reading the file failed
+    Io(io::Error),
+    /// The file is not a valid pcap or pcapng file
+    Format,
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(inner: io::Error) -> Self {
+        if inner.kind() == io::ErrorKind::UnexpectedEof {
+            // The file ended in the middle of a block or packet
+            CaptureError::Format
+        } else {
+            CaptureError::Io(inner)
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "{}", e),
+            CaptureError::Format => write!(f, "Invalid capture file format"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
@@ -0,0 +1,383 @@
+//!
+//! Forwarding CAN frames over TCP
+//!
+//! A [`TcpBroker`] runs on a computer that is connected to a CAN bus, like a Raspberry Pi in a
+//! lab. It accepts TCP connections and forwards frames between the bus and every connected
+//! client. On a workstation, a [`TcpCan`] connects to the broker and can be used in the same way
+//! as a [`LinuxCan`] to run nodes and tools against the real bus.
+//!
+//! Frames from a client are sent on the bus and also forwarded to the other clients, because the
+//! CAN controller does not receive the frames that it sends.
+//!
+//! Frames are encoded in the format defined in [`canadensis_can::stream`]. Timestamps are not
+//! sent, so each side timestamps received frames with its own clock, and the broker gives each
+//! frame from a client a new deadline.
+//!
+
+use std::cmp::Ordering;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use canadensis_can::stream::{encode_frame, DecodeError, StreamDecoder, HEADER_LENGTH};
+use canadensis_can::Frame;
+use canadensis_core::time::{Clock, Instant, MicrosecondDuration64, Microseconds64};
+use canadensis_filter_config::Filter;
+
+use crate::{LinuxCan, SystemClock};
+
+/// The time that the broker allows for sending a frame from a client on the bus
+const FORWARD_TIMEOUT_MS: u64 = 100;
+/// The maximum number of bytes waiting to be sent to a client before frames for it are dropped
+const MAX_CLIENT_BACKLOG: usize = 64 * 1024;
+
+fn invalid_data(error: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// A connection to a [`TcpBroker`] that sends and receives frames
+pub struct TcpCan {
+    stream: TcpStream,
+    decoder: StreamDecoder,
+    clock: SystemClock,
+    /// Filters that received frames must match, or None to accept all frames
+    filters: Option<Vec<Filter>>,
+}
+
+impl TcpCan {
+    /// Connects to a broker
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        Ok(TcpCan::new(stream))
+    }
+
+    /// Creates a client from a stream that is connected to a broker
+    pub fn new(stream: TcpStream) -> Self {
+        TcpCan {
+            stream,
+            decoder: StreamDecoder::new(),
+            clock: SystemClock::new(),
+            filters: None,
+        }
+    }
+
+    /// Receives a frame
+    ///
+    /// This function returns an error with kind [`UnexpectedEof`](io::ErrorKind::UnexpectedEof)
+    /// if the broker closes the connection.
+    pub fn receive(&mut self) -> io::Result<Frame<Microseconds64>> {
+        let mut buffer = [0u8; 256];
+        loop {
+            while let Some(frame) = self
+                .decoder
+                .next_frame(self.clock.now())
+                .map_err(invalid_data)?
+            {
+                if self.accepts(&frame) {
+                    return Ok(frame);
+                }
+            }
+            let length = match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Broker closed the connection",
+                    ))
+                }
+                Ok(length) => length,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.decoder.push_bytes(&buffer[..length]);
+        }
+    }
+
+    /// Sends a frame, or discards the frame if its deadline has passed
+    pub fn send(&mut self, frame: Frame<Microseconds64>) -> io::Result<()> {
+        if frame.timestamp().overflow_safe_compare(&self.clock.now()) == Ordering::Less {
+            log::warn!("Dropping frame that has missed its deadline");
+            return Ok(());
+        }
+        let mut encoded = Vec::with_capacity(HEADER_LENGTH + frame.data().len());
+        encode_frame(frame.id(), frame.data(), &mut encoded);
+        self.stream.write_all(&encoded)
+    }
+
+    /// Sets the maximum time that [`receive`](#method.receive) waits for a frame
+    ///
+    /// A timeout of None makes it wait forever.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    /// Removes any configured filters so that all frames are accepted
+    pub fn set_filter_accept_all(&mut self) {
+        self.filters = None;
+    }
+    /// Sets zero or more filters to accept frames
+    ///
+    /// The broker sends all frames, and this client discards the frames that do not match any
+    /// filter.
+    pub fn set_filters(&mut self, filters: &[Filter]) {
+        self.filters = Some(filters.to_vec());
+    }
+
+    fn accepts(&self, frame: &Frame<Microseconds64>) -> bool {
+        match &self.filters {
+            Some(filters) => filters
+                .iter()
+                .any(|filter| filter.accepts(frame.id().into())),
+            None => true,
+        }
+    }
+}
+
+/// Forwards frames between a CAN interface and TCP clients
+pub struct TcpBroker {
+    can: LinuxCan,
+    listener: TcpListener,
+    clients: Vec<Client>,
+    clock: SystemClock,
+    /// The number of frames that were not forwarded to a client because it was too slow
+    dropped_frames: u64,
+}
+
+/// A client connected to a broker
+struct Client {
+    stream: TcpStream,
+    address: SocketAddr,
+    decoder: StreamDecoder,
+    /// Encoded frames waiting to be sent to the client
+    outgoing: Vec<u8>,
+    /// False if the connection has closed or failed
+    connected: bool,
+}
+
+impl Client {
+    /// Adds a frame to the outgoing buffer, or returns false if the buffer is full
+    fn queue(&mut self, frame: &Frame<Microseconds64>) -> bool {
+        if self.outgoing.len() < MAX_CLIENT_BACKLOG {
+            encode_frame(frame.id(), frame.data(), &mut self.outgoing);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reads all available bytes from the client into the decoder
+    fn read_available(&mut self) {
+        let mut buffer = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    log::info!("Client {} disconnected", self.address);
+                    self.connected = false;
+                    return;
+                }
+                Ok(length) => self.decoder.push_bytes(&buffer[..length]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log::warn!("Failed to read from client {}: {}", self.address, e);
+                    self.connected = false;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Writes as many outgoing bytes as the socket can accept
+    fn flush(&mut self) {
+        while self.connected && !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.connected = false,
+                Ok(length) => {
+                    self.outgoing.drain(..length);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log::warn!("Failed to write to client {}: {}", self.address, e);
+                    self.connected = false;
+                }
+            }
+        }
+    }
+}
+
+impl TcpBroker {
+    /// Creates a broker that forwards frames between a CAN interface and the clients that
+    /// connect to a listener
+    ///
+    /// This function makes the CAN socket and the listener non-blocking.
+    pub fn new(mut can: LinuxCan, listener: TcpListener) -> io::Result<Self> {
+        can.set_nonblocking(true)?;
+        listener.set_nonblocking(true)?;
+        Ok(TcpBroker {
+            can,
+            listener,
+            clients: Vec::new(),
+            clock: SystemClock::new(),
+            dropped_frames: 0,
+        })
+    }
+
+    /// Creates a broker that listens for clients on an address
+    pub fn bind<A: ToSocketAddrs>(can: LinuxCan, address: A) -> io::Result<Self> {
+        TcpBroker::new(can, TcpListener::bind(address)?)
+    }
+
+    /// Returns the address that this broker is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns the number of connected clients
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Returns the number of frames that were not forwarded to a client because the client was
+    /// not reading them fast enough
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+
+    /// Accepts new clients and forwards frames, waiting up to `timeout` for something to happen
+    ///
+    /// This function should be called in a loop. It returns an error if the CAN interface or the
+    /// listener fails. Errors on client connections only disconnect those clients.
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<()> {
+        let mut poll_fds = Vec::with_capacity(2 + self.clients.len());
+        poll_fds.push(poll_fd(self.listener.as_raw_fd(), libc::POLLIN));
+        poll_fds.push(poll_fd(self.can.as_raw_fd(), libc::POLLIN));
+        for client in &self.clients {
+            let events = if client.outgoing.is_empty() {
+                libc::POLLIN
+            } else {
+                libc::POLLIN | libc::POLLOUT
+            };
+            poll_fds.push(poll_fd(client.stream.as_raw_fd(), events));
+        }
+        let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        // Safety: poll_fds is valid for the duration of the call, and the count is its length
+        let status = unsafe {
+            libc::poll(
+                poll_fds.as_mut_ptr(),
+                poll_fds.len() as libc::nfds_t,
+                timeout_ms,
+            )
+        };
+        if status == -1 {
+            let error = io::Error::last_os_error();
+            return if error.kind() == io::ErrorKind::Interrupted {
+                Ok(())
+            } else {
+                Err(error)
+            };
+        }
+
+        if poll_fds[1].revents != 0 {
+            self.receive_from_bus()?;
+        }
+        for (index, client_fd) in poll_fds[2..].iter().enumerate() {
+            if client_fd.revents != 0 {
+                self.receive_from_client(index)?;
+            }
+        }
+        for client in self.clients.iter_mut() {
+            client.flush();
+        }
+        self.clients.retain(|client| client.connected);
+        // Accept clients last, so that the client indexes match poll_fds above
+        if poll_fds[0].revents != 0 {
+            self.accept_clients()?;
+        }
+        Ok(())
+    }
+
+    /// Runs the broker until the CAN interface or the listener fails
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            self.poll(Duration::from_secs(1))?;
+        }
+    }
+
+    fn accept_clients(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    log::info!("Client {} connected", address);
+                    self.clients.push(Client {
+                        stream,
+                        address,
+                        decoder: StreamDecoder::new(),
+                        outgoing: Vec::new(),
+                        connected: true,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                // The connection may have been reset before it was accepted
+                Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receives all available frames from the bus and gives them to every client
+    fn receive_from_bus(&mut self) -> io::Result<()> {
+        loop {
+            match self.can.receive() {
+                Ok(frame) => self.forward(&frame, None),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receives frames from a client, sends them on the bus, and gives them to the other clients
+    fn receive_from_client(&mut self, index: usize) -> io::Result<()> {
+        self.clients[index].read_available();
+        loop {
+            let now = self.clock.now();
+            let client = &mut self.clients[index];
+            let frame = match client.decoder.next_frame(now) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    log::warn!("Disconnecting client {}: {}", client.address, e);
+                    client.connected = false;
+                    return Ok(());
+                }
+            };
+            let deadline = MicrosecondDuration64::new(FORWARD_TIMEOUT_MS * 1000) + now;
+            self.can
+                .send(Frame::new(deadline, frame.id(), frame.data()))?;
+            self.forward(&frame, Some(index));
+        }
+    }
+
+    /// Gives a frame to every client except the client at index `except`
+    fn forward(&mut self, frame: &Frame<Microseconds64>, except: Option<usize>) {
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            if Some(index) != except && client.connected && !client.queue(frame) {
+                log::debug!("Dropping a frame for slow client {}", client.address);
+                self.dropped_frames = self.dropped_frames.wrapping_add(1);
+            }
+        }
+    }
+}
+
+fn poll_fd(fd: libc::c_int, events: libc::c_short) -> libc::pollfd {
+    libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    }
+}
@@ -0,0 +1,264 @@
+//!
+//! A gateway that gives nodes on the bus access to UDP networks
+//!
+//! Nodes send `uavcan.internet.udp.OutgoingPacket` messages, and the gateway sends the payloads
+//! to their destinations using UDP sockets on this computer. If a node asks for masquerading,
+//! datagrams that arrive on the socket for its session are sent back to the node using
+//! `uavcan.internet.udp.HandleIncomingPacket` requests.
+//!
+//! Destination addresses must be IP addresses. Resolving a domain name can block for a long
+//! time, which would stop the node from handling other transfers, so packets addressed to domain
+//! names are rejected.
+//!
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::str;
+use std::time::Duration;
+
+use canadensis::{Node, ServiceToken, StartSendError, TransferHandler};
+use canadensis_core::time::{milliseconds, Instant};
+use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
+use canadensis_core::{NodeId, Priority};
+use canadensis_data_types::uavcan::internet::udp::handle_incoming_packet::HandleIncomingPacketRequest;
+use canadensis_data_types::uavcan::internet::udp::outgoing_packet::OutgoingPacket;
+use canadensis_encoding::Deserialize;
+
+/// The largest UDP datagram that this gateway can receive
+const DATAGRAM_CAPACITY: usize = 65536;
+
+/// Bridges `uavcan.internet.udp` messages and services to UDP sockets
+///
+/// To use a gateway:
+/// 1. Create it with [`new`](#method.new), which subscribes to outgoing packet messages
+/// 2. Use it as a [`TransferHandler`] (possibly chained with other handlers) when accepting
+///    frames. It sends the packets that it receives.
+/// 3. Call [`poll`](#method.poll) frequently to forward incoming datagrams to the nodes
+///    that should receive them
+///
+/// Each session (combination of source node ID and session ID) has its own sockets, one for
+/// IPv4 destinations and one for IPv6 destinations. A session is closed when it has not been used
+/// for `OutgoingPacket::NAT_ENTRY_MIN_TTL` seconds.
+///
+/// With masquerading, only datagrams from the destination of the most recent packet in a session
+/// are forwarded to the node. Datagrams from other addresses are dropped.
+///
+/// DTLS is not supported. Packets that request DTLS are dropped.
+pub struct UdpGateway {
+    /// The token used to send HandleIncomingPacket requests
+    token: ServiceToken<HandleIncomingPacketRequest>,
+    /// Open sessions
+    sessions: HashMap<SessionKey, Session>,
+    /// The time after the last outgoing packet when a session is closed
+    session_timeout: Duration,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct SessionKey {
+    node: NodeId,
+    session_id: u16,
+}
+
+struct Session {
+    /// The socket used for IPv4 destinations, if a packet has been sent to one
+    socket_v4: Option<UdpSocket>,
+    /// The socket used for IPv6 destinations, if a packet has been sent to one
+    socket_v6: Option<UdpSocket>,
+    /// The destination of the most recent packet
+    destination: SocketAddr,
+    /// True if incoming datagrams should be forwarded to the node
+    forward_incoming: bool,
+    /// The time when the node last sent a packet in this session
+    last_used: std::time::Instant,
+}
+
+impl UdpGateway {
+    /// Creates a gateway
+    ///
+    /// This function subscribes to outgoing packet messages and sets up the node to send
+    /// incoming packet requests.
+    pub fn new<N: Node>(node: &mut N) -> Result<Self, StartSendError> {
+        node.subscribe_message(OutgoingPacket::SUBJECT, 600, milliseconds(1000))
+            .map_err(StartSendError::from)?;
+        let token = node.start_sending_requests(
+            HandleIncomingPacketRequest::SERVICE,
+            milliseconds(1000),
+            63,
+            Priority::Low,
+        )?;
+        Ok(UdpGateway {
+            token,
+            sessions: HashMap::new(),
+            session_timeout: Duration::from_secs(u64::from(OutgoingPacket::NAT_ENTRY_MIN_TTL)),
+        })
+    }
+
+    /// Sends a packet from a node to its destination
+    pub fn send_packet(&mut self, source: NodeId, packet: &OutgoingPacket) -> io::Result<()> {
+        if packet.use_dtls {
+            log::warn!(
+                "Dropping packet from node {} that requires DTLS, which is not supported",
+                source
+            );
+            return Ok(());
+        }
+        let destination = resolve(&packet.destination_address, packet.destination_port)?;
+        let key = SessionKey {
+            node: source,
+            session_id: packet.session_id,
+        };
+        let session = self.sessions.entry(key).or_insert_with(|| Session {
+            socket_v4: None,
+            socket_v6: None,
+            destination,
+            forward_incoming: false,
+            last_used: std::time::Instant::now(),
+        });
+        session.destination = destination;
+        session.forward_incoming = packet.use_masquerading;
+        session.last_used = std::time::Instant::now();
+        let socket = match destination {
+            SocketAddr::V4(_) => &mut session.socket_v4,
+            SocketAddr::V6(_) => &mut session.socket_v6,
+        };
+        let socket = match socket {
+            Some(socket) => socket,
+            None => socket.insert(open_socket(&destination)?),
+        };
+        socket.send_to(&packet.payload, destination)?;
+        Ok(())
+    }
+
+    /// Forwards datagrams that have arrived on session sockets to their nodes, and closes
+    /// sessions that have not been used recently
+    pub fn poll<N: Node>(&mut self, node: &mut N) -> io::Result<()> {
+        let session_timeout = self.session_timeout;
+        self.sessions
+            .retain(|_, session| session.last_used.elapsed() < session_timeout);
+
+        let mut buffer = vec![0u8; DATAGRAM_CAPACITY];
+        for (key, session) in self.sessions.iter() {
+            let sockets = session.socket_v4.iter().chain(session.socket_v6.iter());
+            for socket in sockets {
+                loop {
+                    let (length, source) = match socket.recv_from(&mut buffer) {
+                        Ok(received) => received,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => return Err(e),
+                    };
+                    if !session.forward_incoming {
+                        continue;
+                    }
+                    if source != session.destination {
+                        log::debug!(
+                            "Dropping datagram from {}, which is not the destination of session {}",
+                            source,
+                            key.session_id
+                        );
+                        continue;
+                    }
+                    let payload = match heapless::Vec::from_slice(&buffer[..length]) {
+                        Ok(payload) => payload,
+                        Err(_) => {
+                            log::warn!(
+                                "Dropping incoming datagram of {} bytes, which is too large",
+                                length
+                            );
+                            continue;
+                        }
+                    };
+                    let request = HandleIncomingPacketRequest {
+                        session_id: key.session_id,
+                        payload,
+                    };
+                    node.send_request(&self.token, &request, key.node)
+                        .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "Queue full"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of open sessions
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
+impl<I, P> TransferHandler<I, P> for UdpGateway
+where
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_message<N: Node<Instant = I>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.subject != OutgoingPacket::SUBJECT {
+            return false;
+        }
+        let source = match transfer.header.source {
+            Some(source) => source,
+            None => {
+                log::warn!("Ignoring outgoing packet from an anonymous node");
+                return true;
+            }
+        };
+        match OutgoingPacket::deserialize_from_bytes(transfer.payload.as_ref()) {
+            Ok(packet) => {
+                if let Err(e) = self.send_packet(source, &packet) {
+                    log::warn!("Failed to send packet from node {}: {}", source, e);
+                }
+            }
+            Err(e) => log::warn!("Invalid outgoing packet from node {}: {:?}", source, e),
+        }
+        true
+    }
+
+    fn handle_response<N: Node<Instant = I>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        // The responses are empty, so there is nothing to do with them
+        transfer.header.service == HandleIncomingPacketRequest::SERVICE
+    }
+}
+
+/// Converts a destination address from an outgoing packet into a socket address
+///
+/// An address is an IPv4 address if it has 4 bytes, or an IPv6 address if it has 16 bytes.
+/// Other addresses must be IP addresses in text form. Domain names are not resolved, because
+/// that can block.
+fn resolve(address: &[u8], port: u16) -> io::Result<SocketAddr> {
+    if let Ok(bytes) = <[u8; 4]>::try_from(address) {
+        Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(bytes)), port))
+    } else if let Ok(bytes) = <[u8; 16]>::try_from(address) {
+        Ok(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(bytes)), port))
+    } else {
+        str::from_utf8(address)
+            .ok()
+            .and_then(|text| text.parse::<IpAddr>().ok())
+            .map(|ip| SocketAddr::new(ip, port))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Destination is not an IP address (domain names are not supported)",
+                )
+            })
+    }
+}
+
+/// Opens a non-blocking socket that can send to an address
+fn open_socket(destination: &SocketAddr) -> io::Result<UdpSocket> {
+    let local: SocketAddr = match destination {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
@@ -0,0 +1,309 @@
+//!
+//! Virtual CAN interfaces for integration tests
+//!
+//! A [`VirtualCan`] creates a `vcan` network interface using netlink, brings it up, and deletes
+//! it when dropped. Any number of [`LinuxCan`] endpoints can be attached to one interface, and
+//! each endpoint receives the frames that the others send.
+//!
+//! Creating an interface requires the `vcan` kernel module and the `CAP_NET_ADMIN` capability.
+//! Tests that use this module should skip themselves when [`VirtualCan::create_isolated`]
+//! returns an error, so that they still pass on computers where virtual CAN is not available.
+//!
+
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use socketcan::CANSocket;
+
+use crate::LinuxCan;
+
+/// A counter used to give interfaces in the default network namespace unique names
+static NEXT_INTERFACE: AtomicU32 = AtomicU32::new(0);
+
+/// A virtual CAN interface that is deleted when this value is dropped
+#[derive(Debug)]
+pub struct VirtualCan {
+    /// The name of the interface
+    name: String,
+    /// True if the interface is in a network namespace that was created for it
+    namespaced: bool,
+}
+
+impl VirtualCan {
+    /// Creates a virtual CAN interface with a name and brings it up
+    ///
+    /// This function returns an error if an interface with the same name already exists.
+    pub fn create(name: &str) -> io::Result<Self> {
+        let mut netlink = Netlink::open()?;
+        netlink.create_vcan(name)?;
+        let interface = VirtualCan {
+            name: name.to_owned(),
+            namespaced: false,
+        };
+        // If this fails, dropping interface deletes it
+        netlink.set_up(interface_index(name)?)?;
+        Ok(interface)
+    }
+
+    /// Creates a virtual CAN interface that other tests can't see and brings it up
+    ///
+    /// If possible, this function moves the calling thread into a new network namespace and
+    /// creates an interface called `vcan0` there. The namespace belongs to the calling thread,
+    /// so endpoints must be opened on the same thread. After they are opened, they can be used
+    /// on any thread. Because each test runs on its own thread, tests that run at the same time
+    /// do not interfere.
+    ///
+    /// If the calling thread can't create a network namespace, this function creates an
+    /// interface with a name that no other `VirtualCan` in this process uses.
+    pub fn create_isolated() -> io::Result<Self> {
+        // Safety: unshare has no memory safety requirements
+        let status = unsafe { libc::unshare(libc::CLONE_NEWNET) };
+        if status == 0 {
+            let mut interface = VirtualCan::create("vcan0")?;
+            interface.namespaced = true;
+            Ok(interface)
+        } else {
+            log::debug!(
+                "Can't create a network namespace ({}), using the current namespace",
+                io::Error::last_os_error()
+            );
+            let number = NEXT_INTERFACE.fetch_add(1, Ordering::Relaxed);
+            // Interface names are limited to 15 characters
+            let name = format!("cvcan{}.{}", std::process::id() % 100_000, number % 1000);
+            VirtualCan::create(&name)
+        }
+    }
+
+    /// Returns the name of this interface
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if this interface is in a network namespace that was created for it
+    pub fn is_namespaced(&self) -> bool {
+        self.namespaced
+    }
+
+    /// Opens a SocketCAN socket on this interface
+    pub fn open_socket(&self) -> io::Result<CANSocket> {
+        CANSocket::open(&self.name).map_err(io::Error::other)
+    }
+
+    /// Opens a socket on this interface and returns an endpoint that uses it
+    pub fn open(&self) -> io::Result<LinuxCan> {
+        self.open_socket().map(LinuxCan::new)
+    }
+}
+
+impl Drop for VirtualCan {
+    fn drop(&mut self) {
+        let result = interface_index(&self.name)
+            .and_then(|index| Netlink::open().and_then(|mut netlink| netlink.delete(index)));
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to delete virtual CAN interface {}: {}",
+                self.name,
+                e
+            );
+        }
+    }
+}
+
+fn interface_index(name: &str) -> io::Result<i32> {
+    let c_name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // Safety: c_name is a valid null-terminated string
+    let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    if index == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        i32::try_from(index).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+// Netlink protocol values from the Linux headers, which are not all available in libc
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_ACK: u16 = 0x4;
+const NLM_F_EXCL: u16 = 0x200;
+const NLM_F_CREATE: u16 = 0x400;
+const NLMSG_ERROR: u16 = 0x2;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+/// The length of a netlink message header
+const NLMSG_HEADER_LENGTH: usize = 16;
+
+/// A route netlink socket that sends one request at a time and waits for acknowledgements
+struct Netlink {
+    fd: libc::c_int,
+    sequence: u32,
+}
+
+impl Netlink {
+    fn open() -> io::Result<Self> {
+        // Safety: socket has no memory safety requirements
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Netlink { fd, sequence: 0 })
+    }
+
+    /// Creates a vcan interface
+    fn create_vcan(&mut self, name: &str) -> io::Result<()> {
+        let mut message = LinkMessage::new(libc::RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL, 0, 0, 0);
+        message.add_attribute(IFLA_IFNAME, &null_terminated(name));
+        let link_info = message.begin_nested(IFLA_LINKINFO);
+        message.add_attribute(IFLA_INFO_KIND, b"vcan");
+        message.end_nested(link_info);
+        self.request(message)
+    }
+
+    /// Brings up an interface
+    fn set_up(&mut self, index: i32) -> io::Result<()> {
+        let up = libc::IFF_UP as u32;
+        self.request(LinkMessage::new(libc::RTM_NEWLINK, 0, index, up, up))
+    }
+
+    /// Deletes an interface
+    fn delete(&mut self, index: i32) -> io::Result<()> {
+        self.request(LinkMessage::new(libc::RTM_DELLINK, 0, index, 0, 0))
+    }
+
+    /// Sends a message and waits for the kernel to acknowledge it
+    fn request(&mut self, message: LinkMessage) -> io::Result<()> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let bytes = message.finish(self.sequence);
+        // Safety: bytes is valid for its length
+        let sent = unsafe { libc::send(self.fd, bytes.as_ptr().cast(), bytes.len(), 0) };
+        if sent == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buffer = [0u8; 4096];
+        loop {
+            // Safety: buffer is valid for its length
+            let length =
+                unsafe { libc::recv(self.fd, buffer.as_mut_ptr().cast(), buffer.len(), 0) };
+            if length == -1 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            let received = &buffer[..length as usize];
+            if received.len() < NLMSG_HEADER_LENGTH + 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Netlink response too short",
+                ));
+            }
+            let message_type = u16::from_ne_bytes([received[4], received[5]]);
+            let sequence =
+                u32::from_ne_bytes([received[8], received[9], received[10], received[11]]);
+            if message_type != NLMSG_ERROR || sequence != self.sequence {
+                // Not the acknowledgement for this request
+                continue;
+            }
+            // The error code is negative, or zero for success
+            let error =
+                i32::from_ne_bytes([received[16], received[17], received[18], received[19]]);
+            return if error == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::from_raw_os_error(-error))
+            };
+        }
+    }
+}
+
+impl Drop for Netlink {
+    fn drop(&mut self) {
+        // Safety: fd is an open socket that nothing else uses
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// A route netlink message with an interface information header and attributes
+struct LinkMessage {
+    bytes: Vec<u8>,
+}
+
+impl LinkMessage {
+    fn new(message_type: u16, flags: u16, index: i32, if_flags: u32, if_change: u32) -> Self {
+        let mut bytes = Vec::with_capacity(128);
+        // Header (the length and sequence number are filled in later)
+        bytes.extend_from_slice(&0u32.to_ne_bytes());
+        bytes.extend_from_slice(&message_type.to_ne_bytes());
+        bytes.extend_from_slice(&(NLM_F_REQUEST | NLM_F_ACK | flags).to_ne_bytes());
+        bytes.extend_from_slice(&0u32.to_ne_bytes());
+        bytes.extend_from_slice(&0u32.to_ne_bytes());
+        // struct ifinfomsg
+        bytes.push(libc::AF_UNSPEC as u8);
+        bytes.push(0);
+        bytes.extend_from_slice(&0u16.to_ne_bytes());
+        bytes.extend_from_slice(&index.to_ne_bytes());
+        bytes.extend_from_slice(&if_flags.to_ne_bytes());
+        bytes.extend_from_slice(&if_change.to_ne_bytes());
+        debug_assert_eq!(
+            bytes.len(),
+            NLMSG_HEADER_LENGTH + mem::size_of::<libc::ifinfomsg>()
+        );
+        LinkMessage { bytes }
+    }
+
+    fn add_attribute(&mut self, attribute_type: u16, value: &[u8]) {
+        let length = u16::try_from(4 + value.len()).expect("Attribute too long");
+        self.bytes.extend_from_slice(&length.to_ne_bytes());
+        self.bytes.extend_from_slice(&attribute_type.to_ne_bytes());
+        self.bytes.extend_from_slice(value);
+        self.pad();
+    }
+
+    /// Starts an attribute that contains other attributes and returns its offset
+    fn begin_nested(&mut self, attribute_type: u16) -> usize {
+        let offset = self.bytes.len();
+        self.bytes.extend_from_slice(&0u16.to_ne_bytes());
+        self.bytes.extend_from_slice(&attribute_type.to_ne_bytes());
+        offset
+    }
+
+    /// Sets the length of a nested attribute after its contents have been added
+    fn end_nested(&mut self, offset: usize) {
+        let length = u16::try_from(self.bytes.len() - offset).expect("Attribute too long");
+        self.bytes[offset..offset + 2].copy_from_slice(&length.to_ne_bytes());
+    }
+
+    /// Adds zeros to align the end of the message to 4 bytes
+    fn pad(&mut self) {
+        while !self.bytes.len().is_multiple_of(4) {
+            self.bytes.push(0);
+        }
+    }
+
+    /// Fills in the length and sequence number and returns the bytes to send
+    fn finish(mut self, sequence: u32) -> Vec<u8> {
+        let length = u32::try_from(self.bytes.len()).unwrap();
+        self.bytes[0..4].copy_from_slice(&length.to_ne_bytes());
+        self.bytes[8..12].copy_from_slice(&sequence.to_ne_bytes());
+        self.bytes
+    }
+}
+
+fn null_terminated(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() + 1);
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(0);
+    bytes
+}
@@ -0,0 +1,64 @@
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_linux;
+
+use std::convert::TryInto;
+
+use canadensis_can::Mtu;
+use canadensis_core::time::{MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::{Header, MessageHeader, Transfer};
+use canadensis_core::Priority;
+use canadensis_linux::candump::{parse_line, LogDecoder, ParseError};
+
+#[test]
+fn test_parse_line() {
+    let entry = parse_line("(1436509052.249713) can0 107D552A#00000000047868E0").unwrap();
+    assert_eq!(entry.interface, "can0");
+    assert_eq!(
+        entry.frame.timestamp(),
+        Microseconds64::new(1_436_509_052_249_713)
+    );
+    assert_eq!(u32::from(entry.frame.id()), 0x107d552a);
+    assert_eq!(
+        entry.frame.data(),
+        &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0]
+    );
+
+    assert_eq!(
+        parse_line("(1436509052.249713) can0 123#00").unwrap_err(),
+        ParseError::StandardId
+    );
+    assert_eq!(
+        parse_line("(1436509052.249713) can0 107D552A#R").unwrap_err(),
+        ParseError::RemoteFrame
+    );
+    assert_eq!(
+        parse_line("can0 107D552A#00").unwrap_err(),
+        ParseError::Format
+    );
+}
+
+#[test]
+fn test_decode_heartbeat() {
+    let log = "(0000000010.000100) can0 107D552A#00000000047868E0\n\
+               \n\
+               not a frame\n";
+    let mut decoder = LogDecoder::new(Mtu::Can8, 7, MicrosecondDuration64::new(1_000_000));
+    let mut transfers = Vec::new();
+    decoder
+        .decode(log.as_bytes(), |transfer| transfers.push(transfer))
+        .unwrap();
+
+    let expected = Transfer {
+        header: Header::Message(MessageHeader {
+            timestamp: Microseconds64::new(10_000_100),
+            transfer_id: 0.try_into().unwrap(),
+            priority: Priority::Nominal,
+            subject: 7509.try_into().unwrap(),
+            source: Some(42.try_into().unwrap()),
+        }),
+        payload: vec![0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68],
+    };
+    assert_eq!(transfers, vec![expected]);
+    assert_eq!(decoder.invalid_line_count(), 1);
+}
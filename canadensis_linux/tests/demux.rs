@@ -0,0 +1,68 @@
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_linux;
+
+use std::convert::TryInto;
+
+use canadensis_can::queue::FrameSink;
+use canadensis_can::Frame;
+use canadensis_core::time::Microseconds64;
+use canadensis_linux::demux::Demux;
+
+fn frame(timestamp: u64, id: u32, data: &[u8]) -> Frame<Microseconds64> {
+    Frame::new(Microseconds64::new(timestamp), id.try_into().unwrap(), data)
+}
+
+#[test]
+fn test_received_frames_go_to_all_endpoints() {
+    let demux = Demux::new(2);
+    let (_sink0, receiver0) = demux.endpoint();
+    let (_sink1, receiver1) = demux.endpoint();
+
+    demux.deliver(frame(1, 0x107d552a, &[0xe0]));
+    demux.deliver(frame(2, 0x107d552b, &[0xe1]));
+    // Does not fit into the receive queues
+    demux.deliver(frame(3, 0x107d552c, &[0xe2]));
+    assert_eq!(2, demux.dropped_frames());
+
+    for receiver in [&receiver0, &receiver1] {
+        assert_eq!(2, receiver.len());
+        assert_eq!(&[0xe0], receiver.receive().unwrap().data());
+        assert_eq!(&[0xe1], receiver.receive().unwrap().data());
+        assert!(receiver.receive().is_none());
+    }
+
+    // Frames are not kept for an endpoint after its receiver is dropped
+    drop(receiver1);
+    demux.deliver(frame(4, 0x107d552a, &[0xe3]));
+    assert_eq!(2, demux.dropped_frames());
+    assert_eq!(1, receiver0.len());
+}
+
+#[test]
+fn test_transmit_by_priority() {
+    let demux = Demux::new(8);
+    let (mut sink0, receiver0) = demux.endpoint();
+    let (mut sink1, receiver1) = demux.endpoint();
+
+    sink0.push_frame(frame(100, 0x1000_0000, &[0])).unwrap();
+    sink1.push_frame(frame(100, 0x0c00_0000, &[1])).unwrap();
+    sink0.push_frame(frame(100, 0x0c00_0000, &[2])).unwrap();
+    assert_eq!(3, demux.transmit_len());
+
+    // Lowest CAN ID first, then first-in, first-out
+    let sent = (0..3)
+        .map(|_| demux.pop_frame(Microseconds64::new(5)).unwrap().data()[0])
+        .collect::<Vec<_>>();
+    assert_eq!(vec![1, 2, 0], sent);
+    assert!(demux.pop_frame(Microseconds64::new(5)).is_none());
+
+    // Each endpoint receives the frames that the other endpoint sent
+    let looped_back = receiver0.receive().unwrap();
+    assert_eq!(&[1], looped_back.data());
+    assert_eq!(Microseconds64::new(5), looped_back.timestamp());
+    assert!(receiver0.receive().is_none());
+    assert_eq!(&[2], receiver1.receive().unwrap().data());
+    assert_eq!(&[0], receiver1.receive().unwrap().data());
+    assert!(receiver1.receive().is_none());
+}
@@ -0,0 +1,58 @@
+extern crate canadensis_can;
+extern crate canadensis_linux;
+extern crate socketcan;
+
+use canadensis_can::bus_status::{BusEvent, ErrorCounters, ErrorState};
+use canadensis_linux::error_frame::error_events;
+use socketcan::CANFrame;
+
+fn decode(class: u32, data: &[u8]) -> Vec<BusEvent> {
+    let frame = CANFrame::new(class, data, false, true).unwrap();
+    let mut events = Vec::new();
+    error_events(&frame, |event| events.push(event));
+    events
+}
+
+#[test]
+fn test_controller_passive_with_counters() {
+    // CAN_ERR_CRTL | CAN_ERR_CNT, CAN_ERR_CRTL_TX_PASSIVE
+    let events = decode(0x204, &[0, 0x20, 0, 0, 0, 0, 130, 4]);
+    assert_eq!(
+        events,
+        [
+            BusEvent::StateChanged(ErrorState::Passive),
+            BusEvent::Counters(ErrorCounters {
+                transmit: 130,
+                receive: 4
+            })
+        ]
+    );
+}
+
+#[test]
+fn test_bus_off_and_restart() {
+    assert_eq!(
+        decode(0x40, &[0; 8]),
+        [BusEvent::StateChanged(ErrorState::BusOff)]
+    );
+    assert_eq!(decode(0x100, &[0; 8]), [BusEvent::Restarted]);
+}
+
+#[test]
+fn test_bus_errors() {
+    // CAN_ERR_ACK | CAN_ERR_PROT | CAN_ERR_BUSERROR
+    let events = decode(0xa8, &[0; 8]);
+    assert_eq!(
+        events,
+        [BusEvent::NoAcknowledgement, BusEvent::ProtocolViolation]
+    );
+    assert!(events.iter().all(BusEvent::is_error));
+}
+
+#[test]
+fn test_data_frame_ignored() {
+    let frame = CANFrame::new(0x40, &[0; 8], false, false).unwrap();
+    let mut count = 0;
+    error_events(&frame, |_| count += 1);
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,46 @@
+extern crate canadensis_core;
+extern crate canadensis_linux;
+
+use canadensis_core::unique_id::UniqueIdProvider;
+use canadensis_linux::machine_id::{parse_machine_id, MachineId};
+
+#[test]
+fn test_parse_machine_id() {
+    assert_eq!(
+        parse_machine_id("0123456789abcdef0123456789ABCDEF\n"),
+        Some([
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+            0xcd, 0xef
+        ])
+    );
+    assert_eq!(parse_machine_id("0123456789abcdef"), None);
+    assert_eq!(parse_machine_id("0123456789abcdef0123456789abcdeg"), None);
+    assert_eq!(parse_machine_id("é123456789abcdef0123456789abcde"), None);
+}
+
+#[test]
+fn test_read_machine_id() {
+    let path = std::env::temp_dir().join(format!("canadensis-machine-id-{}", std::process::id()));
+    std::fs::write(&path, "000102030405060708090a0b0c0d0e0f\n").unwrap();
+    let id = MachineId::read_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let machine_id = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    // HMAC-SHA256 of the application ID and instance number, keyed with the machine ID
+    let instance_0 = [
+        0xc0, 0x95, 0xae, 0x0d, 0x7e, 0xe5, 0x45, 0x7b, 0xc7, 0x0f, 0x7e, 0x34, 0x8d, 0x6c, 0xc8,
+        0x56,
+    ];
+    let instance_1 = [
+        0xd8, 0x01, 0x93, 0xf0, 0xbf, 0xc3, 0x28, 0xb2, 0x79, 0xf8, 0x54, 0x12, 0x61, 0xdb, 0x05,
+        0xd2,
+    ];
+    assert_ne!(id.unique_id(), machine_id);
+    assert_eq!(id.unique_id(), instance_0);
+    assert_eq!(id.with_instance(0).unique_id(), instance_0);
+    assert_eq!(id.with_instance(1).unique_id(), instance_1);
+    // The instance replaces the previous instance
+    assert_eq!(id.with_instance(1).with_instance(0).unique_id(), instance_0);
+    // The debug output does not contain the machine ID
+    assert!(!format!("{:?}", id).contains(&format!("{:?}", machine_id)));
+}
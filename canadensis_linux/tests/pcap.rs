@@ -0,0 +1,102 @@
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_linux;
+
+use std::convert::TryInto;
+
+use canadensis_can::Frame;
+use canadensis_core::time::Microseconds64;
+use canadensis_linux::pcap::{replay, CaptureError, CaptureReader, PcapngWriter};
+
+fn test_frames() -> Vec<Frame<Microseconds64>> {
+    vec![
+        Frame::new(
+            Microseconds64::new(1_436_509_052_249_713),
+            0x107d552a.try_into().unwrap(),
+            &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0],
+        ),
+        Frame::new(
+            Microseconds64::new(1_436_509_052_250_000),
+            0x136b957b.try_into().unwrap(),
+            &[0xe1],
+        ),
+    ]
+}
+
+#[test]
+fn test_pcapng_round_trip() {
+    let frames = test_frames();
+    let mut writer = PcapngWriter::new(Vec::new(), Some("vcan0")).unwrap();
+    for frame in &frames {
+        writer.write_frame(frame).unwrap();
+    }
+    let bytes = writer.into_inner();
+
+    let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+    let mut read_frames = Vec::new();
+    replay(&mut reader, 0.0, |frame| read_frames.push(frame)).unwrap();
+    assert_eq!(read_frames, frames);
+}
+
+#[test]
+fn test_pcap_classic() {
+    // Little-endian pcap header with microsecond timestamps and link type 227
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.extend_from_slice(&72u32.to_le_bytes());
+    bytes.extend_from_slice(&227u32.to_le_bytes());
+    // One packet at 10.000005 seconds
+    let packet = [0x90, 0x7d, 0x55, 0x2a, 1, 0, 0, 0, 0xe0];
+    bytes.extend_from_slice(&10u32.to_le_bytes());
+    bytes.extend_from_slice(&5u32.to_le_bytes());
+    bytes.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&packet);
+
+    let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+    let frame = reader.read_frame().unwrap().unwrap();
+    assert_eq!(frame.timestamp(), Microseconds64::new(10_000_005));
+    assert_eq!(u32::from(frame.id()), 0x107d552a);
+    assert_eq!(frame.data(), &[0xe0]);
+    assert!(reader.read_frame().unwrap().is_none());
+}
+
+#[test]
+fn test_truncated() {
+    let mut writer = PcapngWriter::new(Vec::new(), None).unwrap();
+    writer.write_frame(&test_frames()[0]).unwrap();
+    let mut bytes = writer.into_inner();
+    bytes.truncate(bytes.len() - 3);
+
+    let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+    assert!(matches!(reader.read_frame(), Err(CaptureError::Format)));
+}
+
+#[test]
+fn test_oversized_lengths() {
+    // A classic pcap packet that claims to be almost 4 GiB long
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.extend_from_slice(&72u32.to_le_bytes());
+    bytes.extend_from_slice(&227u32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    bytes.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 16]);
+    let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+    assert!(matches!(reader.read_frame(), Err(CaptureError::Format)));
+
+    // A pcapng block with an enormous length
+    let mut bytes = PcapngWriter::new(Vec::new(), None).unwrap().into_inner();
+    bytes.extend_from_slice(&6u32.to_le_bytes());
+    bytes.extend_from_slice(&0xffff_fff0u32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 16]);
+    let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+    assert!(matches!(reader.read_frame(), Err(CaptureError::Format)));
+}
@@ -0,0 +1,108 @@
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_filter_config;
+extern crate canadensis_linux;
+
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use canadensis_can::stream::encode_frame;
+use canadensis_can::Frame;
+use canadensis_core::time::Microseconds64;
+use canadensis_filter_config::Filter;
+use canadensis_linux::tcp::{TcpBroker, TcpCan};
+use canadensis_linux::vcan::VirtualCan;
+
+fn test_frame(id: u32, data: &[u8]) -> Frame<Microseconds64> {
+    Frame::new(
+        Microseconds64::new(u64::MAX / 2),
+        id.try_into().unwrap(),
+        data,
+    )
+}
+
+#[test]
+fn test_client_exchanges_frames() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let broker = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut encoded = Vec::new();
+        encode_frame(0x10.try_into().unwrap(), &[0xaa], &mut encoded);
+        encode_frame(0x20.try_into().unwrap(), &[0xbb, 0xcc], &mut encoded);
+        stream.write_all(&encoded).unwrap();
+        // Read the frame that the client sends
+        let mut received = [0u8; 7];
+        stream.read_exact(&mut received).unwrap();
+        received
+    });
+
+    let mut client = TcpCan::connect(address).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    // Accept only 0x20
+    client.set_filters(&[Filter::exact_match(0x20)]);
+    let frame = client.receive().unwrap();
+    assert_eq!(u32::from(frame.id()), 0x20);
+    assert_eq!(frame.data(), &[0xbb, 0xcc]);
+
+    client.send(test_frame(0x1234, &[0x01, 0x02])).unwrap();
+    let received = broker.join().unwrap();
+    assert_eq!(received, [0x00, 0x00, 0x12, 0x34, 0x02, 0x01, 0x02]);
+
+    // The broker has closed the connection
+    let error = client.receive().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_broker_forwards_frames() {
+    let interface = match VirtualCan::create_isolated() {
+        Ok(interface) => interface,
+        Err(e) => {
+            eprintln!("Skipping test, virtual CAN is not available: {}", e);
+            return;
+        }
+    };
+    let mut bus_node = interface.open().unwrap();
+    let mut broker = TcpBroker::bind(interface.open().unwrap(), "127.0.0.1:0").unwrap();
+    let address = broker.local_addr().unwrap();
+
+    let mut clients = (0..2)
+        .map(|_| {
+            let mut client = TcpCan::connect(address).unwrap();
+            client
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .unwrap();
+            client
+        })
+        .collect::<Vec<_>>();
+    while broker.client_count() != clients.len() {
+        broker.poll(Duration::from_millis(100)).unwrap();
+    }
+
+    // Bus to clients
+    let frame = test_frame(0x107d552a, &[0x00, 0x04, 0x78, 0x68, 0xe0]);
+    bus_node.send(frame.clone()).unwrap();
+    broker.poll(Duration::from_secs(1)).unwrap();
+    for client in clients.iter_mut() {
+        let received = client.receive().unwrap();
+        assert_eq!(frame.id(), received.id());
+        assert_eq!(frame.data(), received.data());
+    }
+
+    // Client to bus and the other client
+    let frame = test_frame(0x1013373b, &[0x01, 0xe0]);
+    clients[0].send(frame.clone()).unwrap();
+    broker.poll(Duration::from_secs(1)).unwrap();
+    let received = bus_node.receive().unwrap();
+    assert_eq!(frame.id(), received.id());
+    assert_eq!(frame.data(), received.data());
+    let received = clients[1].receive().unwrap();
+    assert_eq!(frame.id(), received.id());
+    assert_eq!(frame.data(), received.data());
+}
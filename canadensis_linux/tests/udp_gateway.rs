@@ -0,0 +1,199 @@
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_linux;
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use canadensis::{CoreNode, Node};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{Mtu, Receiver};
+use canadensis_core::time::{Clock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::Header;
+use canadensis_core::NodeId;
+use canadensis_data_types::uavcan::internet::udp::handle_incoming_packet::HandleIncomingPacketRequest;
+use canadensis_data_types::uavcan::internet::udp::outgoing_packet::OutgoingPacket;
+use canadensis_encoding::Deserialize;
+use canadensis_linux::udp_gateway::UdpGateway;
+
+#[derive(Clone, Default)]
+struct TestClock(Rc<Cell<u64>>);
+
+impl Clock for TestClock {
+    type Instant = Microseconds64;
+
+    fn now(&mut self) -> Self::Instant {
+        Microseconds64::new(self.0.get())
+    }
+}
+
+type TestNode = CoreNode<TestClock, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+const GATEWAY: u8 = 10;
+const CLIENT: u8 = 20;
+const SESSION: u16 = 7;
+
+fn setup() -> (TestNode, UdpGateway) {
+    let mut node: TestNode = CoreNode::new(
+        TestClock::default(),
+        NodeId::try_from(GATEWAY).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let gateway = UdpGateway::new(&mut node).unwrap();
+    (node, gateway)
+}
+
+fn client() -> NodeId {
+    NodeId::try_from(CLIENT).unwrap()
+}
+
+/// Binds a socket with a timeout on a loopback address
+fn bind(ip: IpAddr) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(SocketAddr::new(ip, 0))?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    Ok(socket)
+}
+
+fn packet(destination: &[u8], port: u16, masquerade: bool, payload: &[u8]) -> OutgoingPacket {
+    OutgoingPacket {
+        session_id: SESSION,
+        destination_port: port,
+        destination_address: heapless::Vec::from_slice(destination).unwrap(),
+        use_masquerading: masquerade,
+        use_dtls: false,
+        payload: heapless::Vec::from_slice(payload).unwrap(),
+    }
+}
+
+fn receive(socket: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+    let mut buffer = [0u8; 128];
+    let (length, source) = socket.recv_from(&mut buffer).unwrap();
+    (buffer[..length].to_vec(), source)
+}
+
+/// Polls the gateway for a short time and returns the incoming packet requests that it sent
+fn incoming(node: &mut TestNode, gateway: &mut UdpGateway) -> Vec<HandleIncomingPacketRequest> {
+    let mut receiver = Receiver::new(client(), Mtu::Can8);
+    receiver
+        .subscribe_request(
+            HandleIncomingPacketRequest::SERVICE,
+            600,
+            MicrosecondDuration64::new(1_000_000),
+        )
+        .unwrap();
+    let mut requests = Vec::new();
+    for _ in 0..20 {
+        gateway.poll(node).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        if let Some(transfer) = receiver.accept(frame).unwrap() {
+            assert!(matches!(transfer.header, Header::Request(_)));
+            requests.push(
+                HandleIncomingPacketRequest::deserialize_from_bytes(&transfer.payload).unwrap(),
+            );
+        }
+    }
+    requests
+}
+
+#[test]
+fn test_send_to_ip_addresses() {
+    let (_node, mut gateway) = setup();
+    let server = bind(Ipv4Addr::LOCALHOST.into()).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    // Binary address
+    gateway
+        .send_packet(client(), &packet(&[127, 0, 0, 1], port, false, b"binary"))
+        .unwrap();
+    assert_eq!(b"binary".to_vec(), receive(&server).0);
+    // Text address
+    gateway
+        .send_packet(client(), &packet(b"127.0.0.1", port, false, b"text"))
+        .unwrap();
+    assert_eq!(b"text".to_vec(), receive(&server).0);
+    assert_eq!(1, gateway.session_count());
+}
+
+#[test]
+fn test_domain_names_rejected() {
+    let (_node, mut gateway) = setup();
+    let error = gateway
+        .send_packet(client(), &packet(b"localhost", 9, false, b"hello"))
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+    assert_eq!(0, gateway.session_count());
+}
+
+#[test]
+fn test_mixed_address_families() {
+    let server_v6 = match bind(Ipv6Addr::LOCALHOST.into()) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("IPv6 not available, skipping test: {}", e);
+            return;
+        }
+    };
+    let server_v4 = bind(Ipv4Addr::LOCALHOST.into()).unwrap();
+    let (_node, mut gateway) = setup();
+
+    let port_v4 = server_v4.local_addr().unwrap().port();
+    let port_v6 = server_v6.local_addr().unwrap().port();
+    gateway
+        .send_packet(client(), &packet(&[127, 0, 0, 1], port_v4, false, b"v4"))
+        .unwrap();
+    gateway
+        .send_packet(
+            client(),
+            &packet(&Ipv6Addr::LOCALHOST.octets(), port_v6, false, b"v6"),
+        )
+        .unwrap();
+    assert_eq!(b"v4".to_vec(), receive(&server_v4).0);
+    assert_eq!(b"v6".to_vec(), receive(&server_v6).0);
+    assert_eq!(1, gateway.session_count());
+}
+
+#[test]
+fn test_masquerading_only_from_destination() {
+    let (mut node, mut gateway) = setup();
+    let server = bind(Ipv4Addr::LOCALHOST.into()).unwrap();
+    let other = bind(Ipv4Addr::LOCALHOST.into()).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    gateway
+        .send_packet(client(), &packet(&[127, 0, 0, 1], port, true, b"request"))
+        .unwrap();
+    let (payload, gateway_address) = receive(&server);
+    assert_eq!(b"request".to_vec(), payload);
+
+    other.send_to(b"intruder", gateway_address).unwrap();
+    server.send_to(b"response", gateway_address).unwrap();
+    let requests = incoming(&mut node, &mut gateway);
+    assert_eq!(1, requests.len());
+    assert_eq!(SESSION, requests[0].session_id);
+    assert_eq!(b"response", &requests[0].payload[..]);
+}
+
+#[test]
+fn test_no_forwarding_without_masquerading() {
+    let (mut node, mut gateway) = setup();
+    let server = bind(Ipv4Addr::LOCALHOST.into()).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    gateway
+        .send_packet(client(), &packet(&[127, 0, 0, 1], port, false, b"request"))
+        .unwrap();
+    let (_, gateway_address) = receive(&server);
+    server.send_to(b"response", gateway_address).unwrap();
+    assert!(incoming(&mut node, &mut gateway).is_empty());
+}
@@ -0,0 +1,42 @@
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_linux;
+
+use std::convert::TryInto;
+use std::time::Duration;
+
+use canadensis_can::Frame;
+use canadensis_core::time::Microseconds64;
+use canadensis_linux::vcan::VirtualCan;
+use canadensis_linux::LinuxCan;
+
+#[test]
+fn test_endpoints_exchange_frames() {
+    let interface = match VirtualCan::create_isolated() {
+        Ok(interface) => interface,
+        Err(e) => {
+            eprintln!("Skipping test, virtual CAN is not available: {}", e);
+            return;
+        }
+    };
+    let mut sender = interface.open().unwrap();
+    let receivers = (0..2)
+        .map(|_| {
+            let socket = interface.open_socket().unwrap();
+            socket.set_read_timeout(Duration::from_secs(1)).unwrap();
+            LinuxCan::new(socket)
+        })
+        .collect::<Vec<_>>();
+
+    let frame = Frame::new(
+        Microseconds64::new(u64::MAX / 2),
+        0x107d552a.try_into().unwrap(),
+        &[0x00, 0x00, 0x00, 0x00, 0x04, 0x78, 0x68, 0xe0],
+    );
+    sender.send(frame.clone()).unwrap();
+    for mut receiver in receivers {
+        let received = receiver.receive().unwrap();
+        assert_eq!(frame.id(), received.id());
+        assert_eq!(frame.data(), received.data());
+    }
+}
@@ -3,7 +3,6 @@ extern crate canadensis_can;
 extern crate canadensis_core;
 extern crate canadensis_linux;
 extern crate canadensis_node;
-extern crate rand;
 extern crate socketcan;
 
 use std::convert::TryFrom;
@@ -20,10 +19,9 @@ use canadensis_can::Mtu;
 use canadensis_core::time::{milliseconds, Instant, Microseconds64};
 use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
 use canadensis_core::NodeId;
-use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
-use canadensis_data_types::uavcan::node::version::Version;
+use canadensis_linux::machine_id::MachineId;
 use canadensis_linux::{LinuxCan, SystemClock};
-use canadensis_node::BasicNode;
+use canadensis_node::{node_info, BasicNode};
 use std::io::ErrorKind;
 
 /// Runs a basic UAVCAN node that sends Heartbeat messages, responds to node information requests,
@@ -81,16 +79,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut cans = [open_can(&can_interface_0)?, open_can(&can_interface_1)?];
 
     // Set up information about this node
-    let node_info = GetInfoResponse {
-        protocol_version: Version { major: 1, minor: 0 },
-        hardware_version: Version { major: 0, minor: 0 },
-        software_version: Version { major: 0, minor: 1 },
-        software_vcs_revision_id: 0,
-        unique_id: rand::random(),
-        name: heapless::Vec::from_slice(b"org.samcrow.basic_node_redundant").unwrap(),
-        software_image_crc: None,
-        certificate_of_authenticity: Default::default(),
-    };
+    let node_info = node_info!("org.samcrow.basic_node_redundant")
+        .unique_id_from_provider(&MachineId::read()?.with_instance(node_id.into()))
+        .build();
 
     // Redundant transport utilities
     let transmit_queue = RedundantQueue::new(
@@ -100,7 +91,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut deduplicator = Deduplicator::<_, 2>::new(milliseconds(1000));
 
     // Create a node with capacity for 8 publishers and 8 requesters
-    let core_node: CoreNode<_, _, 8, 8> =
+    let core_node: CoreNode<_, _, 8, 8, 8, 8> =
         CoreNode::new(SystemClock::new(), node_id, Mtu::Can8, transmit_queue);
     let mut node = BasicNode::new(core_node, node_info).unwrap();
 
@@ -146,7 +137,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn open_can(name: &str) -> io::Result<LinuxCan> {
-    let can = CANSocket::open(&name).expect("Failed to open CAN interface");
+    let can = CANSocket::open(name).expect("Failed to open CAN interface");
     can.set_read_timeout(Duration::from_millis(10))?;
     can.set_write_timeout(Duration::from_millis(10))?;
     Ok(LinuxCan::new(can))
@@ -0,0 +1,81 @@
+//!
+//! Reads a log file recorded with `candump -l` and prints the transfers it contains
+//!
+//! Heartbeat messages are decoded. Other transfers are printed as hexadecimal bytes.
+//!
+//! Usage: decode_candump log-file-path
+//!
+
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_linux;
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+use canadensis_can::Mtu;
+use canadensis_core::time::MicrosecondDuration64;
+use canadensis_core::transfer::Header;
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_encoding::Deserialize;
+use canadensis_linux::candump::LogDecoder;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("Expected a log file path");
+        process::exit(-1);
+    });
+    let file = BufReader::new(File::open(path)?);
+
+    let mut decoder = LogDecoder::new(Mtu::Can8, 1024, MicrosecondDuration64::new(1_000_000));
+    decoder.decode(file, |transfer| {
+        let timestamp = transfer.header.timestamp().as_microseconds();
+        match &transfer.header {
+            Header::Message(header) => {
+                print!(
+                    "{} message subject {} from {:?}: ",
+                    timestamp,
+                    u16::from(header.subject),
+                    header.source
+                );
+                if header.subject == Heartbeat::SUBJECT {
+                    match Heartbeat::deserialize_from_bytes(&transfer.payload) {
+                        Ok(heartbeat) => println!("{:?}", heartbeat),
+                        Err(e) => println!("invalid heartbeat: {:?}", e),
+                    }
+                } else {
+                    println!("{:02x?}", transfer.payload);
+                }
+            }
+            Header::Request(header) | Header::Response(header) => {
+                let kind = if matches!(transfer.header, Header::Request(_)) {
+                    "request"
+                } else {
+                    "response"
+                };
+                println!(
+                    "{} {} service {} from {} to {}: {:02x?}",
+                    timestamp,
+                    kind,
+                    u16::from(header.service),
+                    header.source,
+                    header.destination,
+                    transfer.payload
+                );
+            }
+        }
+    })?;
+
+    eprintln!(
+        "{} transfers, {} errors, {} invalid lines",
+        decoder.receiver().transfer_count(),
+        decoder.receiver().error_count(),
+        decoder.invalid_line_count()
+    );
+    Ok(())
+}
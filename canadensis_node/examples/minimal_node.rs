@@ -63,7 +63,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut can = LinuxCan::new(can);
 
     // Create a node with capacity for 1 publisher and 0 requesters
-    let core_node: CoreNode<_, _, 1, 0> = CoreNode::new(
+    let core_node: CoreNode<_, _, 2, 2, 2, 2> = CoreNode::new(
         SystemClock::new(),
         node_id,
         Mtu::Can8,
@@ -3,7 +3,6 @@ extern crate canadensis_can;
 extern crate canadensis_core;
 extern crate canadensis_linux;
 extern crate canadensis_node;
-extern crate rand;
 extern crate socketcan;
 
 use std::convert::TryFrom;
@@ -19,14 +18,13 @@ use canadensis_can::Mtu;
 use canadensis_core::time::{milliseconds, Clock, Microseconds64};
 use canadensis_core::transfer::ServiceTransfer;
 use canadensis_core::{NodeId, Priority, TransferId};
-use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
-use canadensis_data_types::uavcan::node::version::Version;
 use canadensis_data_types::uavcan::register::access::{AccessRequest, AccessResponse};
 use canadensis_data_types::uavcan::register::list::{ListRequest, ListResponse};
 use canadensis_data_types::uavcan::register::value::Value;
 use canadensis_encoding::Deserialize;
+use canadensis_linux::machine_id::MachineId;
 use canadensis_linux::{LinuxCan, SystemClock};
-use canadensis_node::BasicNode;
+use canadensis_node::{node_info, BasicNode};
 use std::collections::BTreeMap;
 use std::io::ErrorKind;
 
@@ -73,19 +71,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut can = LinuxCan::new(can);
 
     // Set up information about this node
-    let node_info = GetInfoResponse {
-        protocol_version: Version { major: 1, minor: 0 },
-        hardware_version: Version { major: 0, minor: 0 },
-        software_version: Version { major: 0, minor: 1 },
-        software_vcs_revision_id: 0,
-        unique_id: rand::random(),
-        name: heapless::Vec::from_slice(b"org.samcrow.register_client").unwrap(),
-        software_image_crc: None,
-        certificate_of_authenticity: Default::default(),
-    };
+    let node_info = node_info!("org.samcrow.register_client")
+        .unique_id_from_provider(&MachineId::read()?.with_instance(node_id.into()))
+        .build();
 
     // Create a node with capacity for 8 publishers and 8 requesters
-    let core_node: CoreNode<_, _, 8, 8> = CoreNode::new(
+    let core_node: CoreNode<_, _, 8, 8, 8, 8> = CoreNode::new(
         SystemClock::new(),
         node_id,
         Mtu::Can8,
@@ -298,6 +289,7 @@ impl RegisterHandler {
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 enum RegisterState {
     Waiting(TransferId),
     Done(Value),
@@ -310,12 +302,12 @@ impl std::fmt::Debug for DebugValue<'_> {
         match self.0 {
             Value::Empty => f.debug_struct("Empty").finish(),
             Value::String(bytes) => {
-                let string = String::from_utf8_lossy(&bytes);
+                let string = String::from_utf8_lossy(bytes);
                 f.debug_tuple("String").field(&string).finish()
             }
             Value::Unstructured(bytes) => f
                 .debug_tuple("Unstructured")
-                .field(&DebugHexBytes(&bytes))
+                .field(&DebugHexBytes(bytes))
                 .finish(),
             Value::Bit(bits) => f.debug_tuple("Bit").field(&bits).finish(),
             Value::Integer64(values) => f.debug_tuple("Integer64").field(&values).finish(),
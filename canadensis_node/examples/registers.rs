@@ -3,7 +3,6 @@ extern crate canadensis_can;
 extern crate canadensis_core;
 extern crate canadensis_linux;
 extern crate canadensis_node;
-extern crate rand;
 extern crate socketcan;
 
 use std::convert::TryFrom;
@@ -18,12 +17,11 @@ use canadensis_can::Mtu;
 use canadensis_core::time::{Clock, Microseconds64};
 use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
 use canadensis_core::NodeId;
-use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
-use canadensis_data_types::uavcan::node::version::Version;
+use canadensis_linux::machine_id::MachineId;
 use canadensis_linux::{LinuxCan, SystemClock};
 use canadensis_node::register::basic::{RegisterString, SimpleRegister};
 use canadensis_node::register::{RegisterBlock, RegisterHandler};
-use canadensis_node::BasicNode;
+use canadensis_node::{node_info, BasicNode};
 use std::io::ErrorKind;
 
 /// Runs a UAVCAN node that sends Heartbeat messages, responds to node information requests,
@@ -90,19 +88,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut can = LinuxCan::new(can);
 
     // Set up information about this node
-    let node_info = GetInfoResponse {
-        protocol_version: Version { major: 1, minor: 0 },
-        hardware_version: Version { major: 0, minor: 0 },
-        software_version: Version { major: 0, minor: 1 },
-        software_vcs_revision_id: 0,
-        unique_id: rand::random(),
-        name: heapless::Vec::from_slice(b"org.samcrow.register_node").unwrap(),
-        software_image_crc: None,
-        certificate_of_authenticity: Default::default(),
-    };
+    let node_info = node_info!("org.samcrow.register_node")
+        .unique_id_from_provider(&MachineId::read()?.with_instance(node_id.into()))
+        .build();
 
     // Create a node with capacity for 8 publishers and 8 requesters
-    let core_node: CoreNode<_, _, 8, 8> = CoreNode::new(
+    let core_node: CoreNode<_, _, 8, 8, 8, 8> = CoreNode::new(
         SystemClock::new(),
         node_id,
         Mtu::Can8,
@@ -1,10 +1,15 @@
+use crate::minimal::{Overrun, PeriodicTask};
 use crate::MinimalNode;
 use alloc::vec::Vec;
+use canadensis::anonymous::AnonymousPublishError;
 use canadensis::{
-    Node, PublishToken, ResponseToken, ServiceToken, StartSendError, TransferHandler,
+    Node, NodeTransmitter, PublishToken, ResponseToken, SendError, ServiceToken, StartSendError,
+    SubscribeError, TransferHandler,
 };
+use canadensis_can::bus_status::{BusEvent, ErrorState};
+use canadensis_can::queue::{FrameQueueStatus, FrameSink};
 use canadensis_can::{Frame, OutOfMemoryError};
-use canadensis_core::time::{milliseconds, Clock, Instant};
+use canadensis_core::time::{milliseconds, Clock, Duration, Instant};
 use canadensis_core::transfer::{MessageTransfer, ServiceTransfer};
 use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
 use canadensis_data_types::bits::BitArray;
@@ -15,8 +20,12 @@ use canadensis_data_types::uavcan::node::mode::Mode;
 use canadensis_data_types::uavcan::node::port::list::List;
 use canadensis_data_types::uavcan::node::port::subject_id;
 use canadensis_data_types::uavcan::node::port::subject_id_list::SubjectIdList;
-use canadensis_encoding::{Message, Request, Response, Serialize};
+use canadensis_encoding::{DataType, Message, Request, Response, Serialize};
 use canadensis_filter_config::Filter;
+use core::cmp::Ordering;
+
+/// The time between port list messages
+const PORT_LIST_PERIOD_MS: u32 = 10_000;
 
 /// A node that provides all basic application-layer functionality
 ///
@@ -34,6 +43,8 @@ where
     port_list: List,
     node_info: GetInfoResponse,
     seconds_since_port_list_published: u8,
+    /// The time when the port list was last published, or None if it has not been published
+    last_port_list_time: Option<N::Instant>,
 }
 
 impl<N> BasicNode<N>
@@ -44,9 +55,14 @@ where
         // The MinimalNode takes care of heartbeats.
         // Do node info and port list here.
 
-        node.subscribe_request(GetInfoRequest::SERVICE, 0, milliseconds(1000))?;
+        node.subscribe_request(
+            GetInfoRequest::SERVICE,
+            GetInfoRequest::PAYLOAD_SIZE_MAX,
+            milliseconds(1000),
+        )?;
+        let port_list_timeout = node.default_timeout();
         let port_list_token =
-            node.start_publishing(List::SUBJECT, milliseconds(1000), Priority::Optional)?;
+            node.start_publishing(List::SUBJECT, port_list_timeout, Priority::Optional)?;
 
         let minimal = MinimalNode::new(node)?;
 
@@ -77,14 +93,16 @@ where
             port_list,
             node_info,
             seconds_since_port_list_published: 0,
+            last_port_list_time: None,
         })
     }
 
     /// This function must be called once per second to send heartbeat and port list messages
-    pub fn run_per_second_tasks(&mut self) -> Result<(), OutOfMemoryError> {
+    pub fn run_per_second_tasks(&mut self) -> Result<(), SendError> {
         self.node.run_per_second_tasks()?;
         if self.seconds_since_port_list_published == 10 {
             self.seconds_since_port_list_published = 1;
+            self.check_port_list_timing();
             self.publish_port_list()?;
         } else {
             self.seconds_since_port_list_published += 1;
@@ -92,10 +110,129 @@ where
         Ok(())
     }
 
-    fn publish_port_list(&mut self) -> Result<(), OutOfMemoryError> {
+    /// Reports an overrun to the watchdog if the port list is being published late
+    ///
+    /// The first port list has no previous publication to measure from, so it is never late.
+    fn check_port_list_timing(&mut self) {
+        let now = self.node.node_mut().clock_mut().now();
+        let last_port_list_time = match self.last_port_list_time.replace(now) {
+            Some(last) => last,
+            None => return,
+        };
+        let period: <N::Instant as Instant>::Duration = milliseconds(PORT_LIST_PERIOD_MS);
+        let deadline = period + last_port_list_time;
+        let missed = if now.overflow_safe_compare(&deadline) == Ordering::Greater {
+            let late_seconds = now.duration_since(&deadline).as_secs();
+            (late_seconds / u64::from(PORT_LIST_PERIOD_MS / 1000)) as u32
+        } else {
+            0
+        };
         self.node
-            .node_mut()
-            .publish(&self.port_list_token, &self.port_list)
+            .check_deadline(PeriodicTask::PortList, deadline, now, missed);
+    }
+
+    fn publish_port_list(&mut self) -> Result<(), SendError> {
+        let node = self.node.node_mut();
+        let timeout = node.default_timeout();
+        node.publish_with_timeout(&self.port_list_token, &self.port_list, timeout)
+    }
+
+    /// Shuts down this node and returns the enclosed node
+    ///
+    /// This function:
+    /// 1. Publishes a final heartbeat with `options.final_status_code` as its vendor-specific
+    ///    status code, if a status code is provided. Other nodes can use the status code to tell
+    ///    that this node is going offline instead of failing.
+    /// 2. Stops publishing heartbeat and port list messages
+    /// 3. Unsubscribes from `uavcan.node.GetInfo` requests and from all messages and requests
+    ///    that were subscribed to through this node
+    /// 4. Calls `send_frames` repeatedly until the outgoing frame queue is empty,
+    ///    `options.flush_timeout` has passed, or `send_frames` has been called
+    ///    `options.max_send_attempts` times
+    ///
+    /// The limit on the number of calls ensures that this function returns even if the clock
+    /// does not advance while it is waiting.
+    ///
+    /// `send_frames` should try to transmit the frames in the node's queue without blocking, like
+    /// the driver code that normally sends frames.
+    ///
+    /// The returned node can still be used. Its other publishers and requesters are not affected.
+    /// The result reports any problem with the final heartbeat or with emptying the queue.
+    pub fn shutdown<F>(
+        mut self,
+        options: ShutdownOptions<<N::Instant as Instant>::Duration>,
+        mut send_frames: F,
+    ) -> (N, Result<(), ShutdownError>)
+    where
+        N::FrameQueue: FrameQueueStatus<N::Instant>,
+        F: FnMut(&mut N),
+    {
+        let heartbeat_status = match options.final_status_code {
+            Some(status_code) => {
+                self.node.set_status_code(status_code);
+                self.node.send_heartbeat()
+            }
+            None => Ok(()),
+        };
+
+        self.release_subscriptions();
+        let BasicNode {
+            node,
+            port_list_token,
+            ..
+        } = self;
+        let mut node = node.into_inner();
+        node.stop_publishing(port_list_token);
+
+        let deadline = options.flush_timeout + node.clock_mut().now();
+        let mut attempts = 0;
+        let flush_status = loop {
+            let remaining = node.frame_queue().len();
+            if remaining == 0 {
+                break Ok(());
+            }
+            let now = node.clock_mut().now();
+            if now.overflow_safe_compare(&deadline) == Ordering::Greater
+                || attempts >= options.max_send_attempts
+            {
+                break Err(ShutdownError::Timeout { remaining });
+            }
+            send_frames(&mut node);
+            attempts += 1;
+        };
+
+        let status = heartbeat_status
+            .map_err(ShutdownError::Heartbeat)
+            .and(flush_status);
+        (node, status)
+    }
+
+    /// Unsubscribes from all messages and requests in the port list
+    fn release_subscriptions(&mut self) {
+        let subjects: Vec<SubjectId> = match &self.port_list.subscribers {
+            SubjectIdList::Mask(mask) => (0..mask.len())
+                .filter(|&subject| mask.get(subject))
+                .map(|subject| SubjectId::from_truncating(subject as u16))
+                .collect(),
+            SubjectIdList::SparseList(list) => list
+                .iter()
+                .map(|subject| SubjectId::from_truncating(subject.value))
+                .collect(),
+            SubjectIdList::Total => (0..SubjectIdList::CAPACITY)
+                .map(SubjectId::from_truncating)
+                .collect(),
+        };
+        for subject in subjects {
+            self.unsubscribe_message(subject);
+        }
+        let servers = &self.port_list.servers.mask;
+        let services: Vec<ServiceId> = (0..servers.len())
+            .filter(|&service| servers.get(service))
+            .map(|service| ServiceId::from_truncating(service as u16))
+            .collect();
+        for service in services {
+            self.unsubscribe_request(service);
+        }
     }
 
     /// Sets the operating mode that will be reported in the heartbeat messages
@@ -106,10 +243,91 @@ where
     pub fn set_health(&mut self, health: Health) {
         self.node.set_health(health);
     }
+    /// Sets the error state of the CAN controller, which will be reflected in the health reported
+    /// in heartbeat messages
+    pub fn set_bus_state(&mut self, state: ErrorState) {
+        self.node.set_bus_state(state);
+    }
+    /// Handles an error event from the CAN controller
+    ///
+    /// See [`MinimalNode::handle_bus_event`] for details.
+    pub fn handle_bus_event(&mut self, event: BusEvent) {
+        self.node.handle_bus_event(event);
+    }
+    /// Enables health degradation when the CAN controller reports errors
+    ///
+    /// See [`MinimalNode::enable_bus_error_health`] for details.
+    pub fn enable_bus_error_health(&mut self, health: Health) {
+        self.node.enable_bus_error_health(health);
+    }
+    /// Disables health degradation when the CAN controller reports errors
+    pub fn disable_bus_error_health(&mut self) {
+        self.node.disable_bus_error_health();
+    }
     /// Sets the vendor-specific status code that will be reported in the heartbeat messages
     pub fn set_status_code(&mut self, status: u8) {
         self.node.set_status_code(status);
     }
+    /// Enables automatic health degradation when the node runs out of resources or its periodic
+    /// tasks run late
+    ///
+    /// See [`MinimalNode::enable_automatic_health`] for details.
+    pub fn enable_automatic_health(
+        &mut self,
+        health: Health,
+        late_threshold: <<N::Clock as Clock>::Instant as Instant>::Duration,
+    ) {
+        self.node.enable_automatic_health(health, late_threshold);
+    }
+    /// Disables automatic health degradation
+    pub fn disable_automatic_health(&mut self) {
+        self.node.disable_automatic_health();
+    }
+    /// Records that a resource problem has happened, which will be reflected in the next
+    /// heartbeat if automatic health is enabled
+    pub fn report_resource_problem(&mut self) {
+        self.node.report_resource_problem();
+    }
+    /// Adds the subjects and services of a transmitter to the port list
+    ///
+    /// A [`NodeTransmitter`] created by splitting a `CoreNode` publishes and sends requests
+    /// without going through this node. This function should be called after splitting, so that
+    /// the port list includes those ports.
+    pub fn add_transmitter_ports<C, Q, const P: usize, const R: usize>(
+        &mut self,
+        transmitter: &NodeTransmitter<C, Q, P, R>,
+    ) where
+        C: Clock,
+        Q: FrameSink<C::Instant>,
+    {
+        for subject in transmitter.subjects() {
+            insert_into_list(&mut self.port_list.publishers, subject);
+        }
+        for service in transmitter.services() {
+            self.port_list.clients.mask.set(service.into(), true);
+        }
+    }
+    /// Enables the periodic task watchdog, which calls `handler` when heartbeat or port list
+    /// messages are published more than `threshold` late
+    ///
+    /// See [`MinimalNode::enable_watchdog`] for details.
+    pub fn enable_watchdog<F>(
+        &mut self,
+        threshold: <<N::Clock as Clock>::Instant as Instant>::Duration,
+        handler: F,
+    ) where
+        F: FnMut(Overrun<<N::Instant as Instant>::Duration>) + Send + 'static,
+    {
+        self.node.enable_watchdog(threshold, handler);
+    }
+    /// Disables the periodic task watchdog
+    pub fn disable_watchdog(&mut self) {
+        self.node.disable_watchdog();
+    }
+    /// Returns the number of overruns that the watchdog has detected since it was enabled
+    pub fn overrun_count(&self) -> u32 {
+        self.node.overrun_count()
+    }
 }
 
 impl<N> Node for BasicNode<N>
@@ -119,6 +337,7 @@ where
     type Clock = N::Clock;
     type Instant = N::Instant;
     type FrameQueue = N::FrameQueue;
+    type Payload = N::Payload;
 
     fn accept_frame<H>(
         &mut self,
@@ -126,14 +345,18 @@ where
         handler: &mut H,
     ) -> Result<(), OutOfMemoryError>
     where
-        H: TransferHandler<Self::Instant>,
+        H: TransferHandler<Self::Instant, Self::Payload>,
     {
         let mut responder = NodeInfoResponder {
             info: &self.node_info,
             inner: handler,
         };
 
-        self.node.node_mut().accept_frame(frame, &mut responder)
+        let status = self.node.node_mut().accept_frame(frame, &mut responder);
+        if status.is_err() {
+            self.node.report_resource_problem();
+        }
+        status
     }
 
     fn start_publishing<T>(
@@ -163,11 +386,53 @@ where
         remove_from_list(&mut self.port_list.publishers, subject);
     }
 
-    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), OutOfMemoryError>
+    fn publish<T>(&mut self, token: &PublishToken<T>, payload: &T) -> Result<(), SendError>
     where
         T: Message + Serialize,
     {
-        self.node.node_mut().publish(token, payload)
+        let status = self.node.node_mut().publish(token, payload);
+        if let Err(SendError::Memory(_)) = status {
+            self.node.report_resource_problem();
+        }
+        status
+    }
+
+    fn publish_with_timeout<T>(
+        &mut self,
+        token: &PublishToken<T>,
+        payload: &T,
+        timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SendError>
+    where
+        T: Message + Serialize,
+    {
+        let status = self
+            .node
+            .node_mut()
+            .publish_with_timeout(token, payload, timeout);
+        if let Err(SendError::Memory(_)) = status {
+            self.node.report_resource_problem();
+        }
+        status
+    }
+
+    fn publish_anonymous<T>(
+        &mut self,
+        subject: SubjectId,
+        payload: &T,
+        priority: Priority,
+    ) -> Result<(), AnonymousPublishError>
+    where
+        T: Message + Serialize,
+    {
+        let status = self
+            .node
+            .node_mut()
+            .publish_anonymous(subject, payload, priority);
+        if let Err(AnonymousPublishError::Memory(_)) = status {
+            self.node.report_resource_problem();
+        }
+        status
     }
 
     fn start_sending_requests<T>(
@@ -206,13 +471,18 @@ where
         token: &ServiceToken<T>,
         payload: &T,
         destination: NodeId,
-    ) -> Result<TransferId, OutOfMemoryError>
+    ) -> Result<TransferId, SendError>
     where
         T: Request + Serialize,
     {
-        self.node
+        let status = self
+            .node
             .node_mut()
-            .send_request(token, payload, destination)
+            .send_request(token, payload, destination);
+        if let Err(SendError::Memory(_)) = status {
+            self.node.report_resource_problem();
+        }
+        status
     }
 
     fn subscribe_message(
@@ -220,7 +490,7 @@ where
         subject: SubjectId,
         payload_size_max: usize,
         timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError> {
+    ) -> Result<(), SubscribeError> {
         self.node
             .node_mut()
             .subscribe_message(subject, payload_size_max, timeout)?;
@@ -236,7 +506,7 @@ where
         service: ServiceId,
         payload_size_max: usize,
         timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
-    ) -> Result<(), OutOfMemoryError> {
+    ) -> Result<(), SubscribeError> {
         self.node
             .node_mut()
             .subscribe_request(service, payload_size_max, timeout)?;
@@ -247,16 +517,56 @@ where
         Ok(())
     }
 
+    fn unsubscribe_message(&mut self, subject: SubjectId) {
+        self.node.node_mut().unsubscribe_message(subject);
+        remove_from_list(&mut self.port_list.subscribers, subject);
+    }
+
+    fn unsubscribe_request(&mut self, service: ServiceId) {
+        self.node.node_mut().unsubscribe_request(service);
+        self.port_list.servers.mask.set(service.into(), false);
+    }
+
+    fn sniff_requests(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError> {
+        self.node
+            .node_mut()
+            .sniff_requests(service, payload_size_max, timeout)
+    }
+
+    fn sniff_responses(
+        &mut self,
+        service: ServiceId,
+        payload_size_max: usize,
+        timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
+    ) -> Result<(), SubscribeError> {
+        self.node
+            .node_mut()
+            .sniff_responses(service, payload_size_max, timeout)
+    }
+
+    fn stop_sniffing_responses(&mut self, service: ServiceId) {
+        self.node.node_mut().stop_sniffing_responses(service);
+    }
+
     fn send_response<T>(
         &mut self,
         token: ResponseToken,
         timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
         payload: &T,
-    ) -> Result<(), OutOfMemoryError>
+    ) -> Result<(), SendError>
     where
         T: Response + Serialize,
     {
-        self.node.node_mut().send_response(token, timeout, payload)
+        let status = self.node.node_mut().send_response(token, timeout, payload);
+        if let Err(SendError::Memory(_)) = status {
+            self.node.report_resource_problem();
+        }
+        status
     }
 
     fn clock(&self) -> &Self::Clock {
@@ -279,9 +589,50 @@ where
         self.node.node().node_id()
     }
 
+    fn default_timeout(&self) -> <<N::Clock as Clock>::Instant as Instant>::Duration {
+        self.node.node().default_timeout()
+    }
+
+    fn set_default_timeout(
+        &mut self,
+        timeout: <<N::Clock as Clock>::Instant as Instant>::Duration,
+    ) {
+        self.node.node_mut().set_default_timeout(timeout)
+    }
+
     fn frame_filters(&self) -> Result<Vec<Filter>, OutOfMemoryError> {
         self.node.node().frame_filters()
     }
+
+    fn clean_expired_sessions(&mut self) {
+        self.node.node_mut().clean_expired_sessions()
+    }
+}
+
+/// Options for [`BasicNode::shutdown`]
+#[derive(Debug, Clone)]
+pub struct ShutdownOptions<D> {
+    /// The vendor-specific status code to send in a final heartbeat, or `None` to not send a
+    /// final heartbeat
+    pub final_status_code: Option<u8>,
+    /// The maximum time to wait for the outgoing frame queue to become empty
+    pub flush_timeout: D,
+    /// The maximum number of times to call the `send_frames` function while waiting for the
+    /// outgoing frame queue to become empty
+    pub max_send_attempts: usize,
+}
+
+/// Problems that can happen when shutting down a node
+#[derive(Debug)]
+pub enum ShutdownError {
+    /// The final heartbeat could not be queued
+    Heartbeat(SendError),
+    /// The outgoing frame queue did not become empty before the flush timeout or the maximum
+    /// number of send attempts
+    Timeout {
+        /// The number of frames still in the queue
+        remaining: usize,
+    },
 }
 
 /// A transfer handler that
@@ -291,12 +642,12 @@ struct NodeInfoResponder<'r, 'h, H> {
     inner: &'h mut H,
 }
 
-impl<'r, 'h, I, H> TransferHandler<I> for NodeInfoResponder<'r, 'h, H>
+impl<'r, 'h, I, P, H> TransferHandler<I, P> for NodeInfoResponder<'r, 'h, H>
 where
     I: Instant,
-    H: TransferHandler<I>,
+    H: TransferHandler<I, P>,
 {
-    fn handle_message<N>(&mut self, node: &mut N, transfer: &MessageTransfer<Vec<u8>, I>) -> bool
+    fn handle_message<N>(&mut self, node: &mut N, transfer: &MessageTransfer<P, I>) -> bool
     where
         N: Node<Instant = I>,
     {
@@ -308,14 +659,15 @@ where
         &mut self,
         node: &mut N,
         token: ResponseToken,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool
     where
         N: Node<Instant = I>,
     {
         if transfer.header.service == GetInfoResponse::SERVICE {
             // Ignore out-of-memory errors
-            let _ = node.send_response(token, milliseconds(1000), self.info);
+            let timeout = node.default_timeout();
+            let _ = node.send_response(token, timeout, self.info);
             // Request handled
             true
         } else {
@@ -324,13 +676,29 @@ where
         }
     }
 
-    fn handle_response<N>(&mut self, node: &mut N, transfer: &ServiceTransfer<Vec<u8>, I>) -> bool
+    fn handle_response<N>(&mut self, node: &mut N, transfer: &ServiceTransfer<P, I>) -> bool
     where
         N: Node<Instant = I>,
     {
         // Forward to inner handler
         self.inner.handle_response(node, transfer)
     }
+
+    fn handle_sniffed_request<N>(&mut self, node: &mut N, transfer: &ServiceTransfer<P, I>) -> bool
+    where
+        N: Node<Instant = I>,
+    {
+        // Forward to inner handler
+        self.inner.handle_sniffed_request(node, transfer)
+    }
+
+    fn handle_sniffed_response<N>(&mut self, node: &mut N, transfer: &ServiceTransfer<P, I>) -> bool
+    where
+        N: Node<Instant = I>,
+    {
+        // Forward to inner handler
+        self.inner.handle_sniffed_response(node, transfer)
+    }
 }
 
 fn insert_into_list(subject_list: &mut SubjectIdList, subject: SubjectId) {
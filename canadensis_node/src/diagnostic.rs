@@ -0,0 +1,247 @@
+//!
+//! Diagnostic messages with a configurable severity threshold
+//!
+//! A [`DiagnosticPublisher`] sends `uavcan.diagnostic.Record` messages. Records less severe
+//! than its threshold are discarded before they are serialized, so a node can contain detailed
+//! debug output that is only sent when someone needs it. A rate limit keeps a node that logs too
+//! much from using all the bandwidth of the bus.
+//!
+//! The threshold can be changed from another node using a [`SeverityRegister`]
+//! (`uavcan.diagnostic.severity`). The register is part of the register block, so the
+//! application copies its value to the publisher after handling register requests:
+//!
+//! ```ignore
+//! diagnostics.set_threshold(register_handler.block().severity.threshold());
+//! ```
+//!
+//! Records can also be mirrored to the `log` crate, for nodes that have a local console.
+//! Mirroring to `defmt` is not supported. An application that uses `defmt` can log records
+//! itself, using [`DiagnosticPublisher::enabled`] to apply the same threshold.
+//!
+
+use canadensis::rate_limit::{RateLimit, TokenBucket};
+use canadensis::{Node, PublishToken, SendError, StartSendError};
+use canadensis_core::time::{Clock, Instant};
+use canadensis_core::Priority;
+use canadensis_data_types::uavcan::diagnostic::record::Record;
+use canadensis_data_types::uavcan::diagnostic::severity::Severity;
+use canadensis_data_types::uavcan::register::value::Value;
+
+use crate::register::basic::ValidatedRegister;
+use crate::register::{Access, Register, WriteError};
+
+/// The standard name of the register that contains the severity threshold
+pub const SEVERITY_REGISTER_NAME: &str = "uavcan.diagnostic.severity";
+
+/// Publishes diagnostic records that are at least as severe as a threshold
+pub struct DiagnosticPublisher<I: Instant> {
+    token: PublishToken<Record>,
+    /// The least severe level that is published
+    threshold: Severity,
+    /// Rate limit for records sent on the bus
+    rate_limit: Option<TokenBucket<I>>,
+    /// The number of records that were less severe than the threshold
+    filtered: u32,
+    /// If records are also sent to the `log` crate
+    mirror_to_log: bool,
+}
+
+impl<I: Instant> DiagnosticPublisher<I> {
+    /// Starts publishing diagnostic records on the standard subject
+    ///
+    /// The threshold is initially `Notice`, the default value of [`Severity`].
+    pub fn new<N>(
+        node: &mut N,
+        timeout: I::Duration,
+        priority: Priority,
+    ) -> Result<Self, StartSendError>
+    where
+        N: Node<Instant = I>,
+    {
+        let token = node.start_publishing(Record::SUBJECT, timeout, priority)?;
+        Ok(DiagnosticPublisher {
+            token,
+            threshold: Severity::default(),
+            rate_limit: None,
+            filtered: 0,
+            mirror_to_log: false,
+        })
+    }
+
+    /// Returns the least severe level that is published
+    pub fn threshold(&self) -> Severity {
+        self.threshold.clone()
+    }
+
+    /// Sets the least severe level that is published
+    pub fn set_threshold(&mut self, threshold: Severity) {
+        self.threshold = threshold;
+    }
+
+    /// Sets or removes the limit on the rate of records sent on the bus
+    ///
+    /// Records that exceed the limit are dropped. They are still mirrored to the `log` crate
+    /// if mirroring is enabled.
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit<I::Duration>>) {
+        self.rate_limit = limit.map(TokenBucket::new);
+    }
+
+    /// Enables or disables sending records to the `log` crate as well as the bus
+    pub fn set_mirror_to_log(&mut self, mirror: bool) {
+        self.mirror_to_log = mirror;
+    }
+
+    /// Returns true if a record with the provided severity would be published
+    ///
+    /// This can be used to avoid formatting text that would be discarded.
+    pub fn enabled(&self, severity: &Severity) -> bool {
+        severity.clone() as u8 >= self.threshold.clone() as u8
+    }
+
+    /// Returns the number of records that have been discarded because they were less severe
+    /// than the threshold (saturating at `u32::MAX`)
+    pub fn filtered(&self) -> u32 {
+        self.filtered
+    }
+
+    /// Returns the number of records that have been dropped because of the rate limit
+    /// (saturating at `u32::MAX`)
+    pub fn rate_limited(&self) -> u32 {
+        self.rate_limit.as_ref().map_or(0, TokenBucket::rejected)
+    }
+
+    /// Publishes a record if its severity is at least the threshold
+    ///
+    /// Text longer than 255 bytes is truncated. The timestamp of the record is zero (unknown),
+    /// because this node may not have a synchronized clock.
+    ///
+    /// Records that are discarded because of the threshold or rate limit do not cause an error.
+    pub fn publish<N>(
+        &mut self,
+        node: &mut N,
+        severity: Severity,
+        text: &str,
+    ) -> Result<(), SendError>
+    where
+        N: Node<Instant = I>,
+    {
+        if !self.enabled(&severity) {
+            self.filtered = self.filtered.saturating_add(1);
+            return Ok(());
+        }
+        if self.mirror_to_log {
+            log::log!(log_level(&severity), "{}", text);
+        }
+        if let Some(rate_limit) = self.rate_limit.as_mut() {
+            if !rate_limit.try_acquire(node.clock_mut().now()) {
+                return Ok(());
+            }
+        }
+
+        let mut record = Record {
+            severity,
+            ..Record::default()
+        };
+        let text = truncate(text, record.text.capacity());
+        record
+            .set_text(text)
+            .expect("Truncated text still too long");
+        node.publish(&self.token, &record)
+    }
+}
+
+/// Returns the `log` level that corresponds to a severity
+fn log_level(severity: &Severity) -> log::Level {
+    match severity {
+        Severity::Trace => log::Level::Trace,
+        Severity::Debug => log::Level::Debug,
+        Severity::Info | Severity::Notice => log::Level::Info,
+        Severity::Warning => log::Level::Warn,
+        Severity::Error | Severity::Critical | Severity::Alert => log::Level::Error,
+    }
+}
+
+/// Returns the longest prefix of `text` that is no longer than `max_length` bytes and ends on a
+/// character boundary
+fn truncate(text: &str, max_length: usize) -> &str {
+    if text.len() <= max_length {
+        return text;
+    }
+    let mut end = max_length;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Converts a severity level number into a severity
+fn severity_from_u8(value: u8) -> Option<Severity> {
+    match value {
+        0 => Some(Severity::Trace),
+        1 => Some(Severity::Debug),
+        2 => Some(Severity::Info),
+        3 => Some(Severity::Notice),
+        4 => Some(Severity::Warning),
+        5 => Some(Severity::Error),
+        6 => Some(Severity::Critical),
+        7 => Some(Severity::Alert),
+        _ => None,
+    }
+}
+
+fn is_valid_severity(value: &u8) -> bool {
+    severity_from_u8(*value).is_some()
+}
+
+/// A mutable and persistent `uavcan.diagnostic.severity` register
+///
+/// The value is a `natural8` severity level, from 0 (trace) to 7 (alert). Writes with other
+/// values are rejected.
+#[derive(Debug)]
+pub struct SeverityRegister {
+    inner: ValidatedRegister<u8>,
+}
+
+impl SeverityRegister {
+    /// Creates a register with an initial threshold
+    pub fn new(threshold: Severity) -> Self {
+        SeverityRegister {
+            inner: ValidatedRegister::with_value(
+                SEVERITY_REGISTER_NAME,
+                true,
+                true,
+                threshold as u8,
+                is_valid_severity,
+            ),
+        }
+    }
+
+    /// Returns the threshold that this register contains
+    pub fn threshold(&self) -> Severity {
+        severity_from_u8(*self.inner.value()).expect("Invalid severity in register")
+    }
+}
+
+impl Default for SeverityRegister {
+    fn default() -> Self {
+        SeverityRegister::new(Severity::default())
+    }
+}
+
+impl Register for SeverityRegister {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn access(&self) -> Access {
+        self.inner.access()
+    }
+
+    fn read(&self) -> Value {
+        self.inner.read()
+    }
+
+    fn write(&mut self, value: &Value) -> Result<(), WriteError> {
+        self.inner.write(value)
+    }
+}
@@ -0,0 +1,164 @@
+//!
+//! Handling of `uavcan.node.ExecuteCommand` requests
+//!
+//! An [`ExecuteCommandHandler`] responds to restart commands and then restarts the node
+//! using a [`Restart`] implementation. Other commands are left for other handlers.
+//!
+
+use core::cmp::Ordering;
+
+use canadensis::{Node, ResponseToken, SubscribeError, TransferHandler};
+use canadensis_core::time::{milliseconds, Clock, Instant};
+use canadensis_core::transfer::ServiceTransfer;
+use canadensis_data_types::uavcan::node::execute_command::{
+    Command, ExecuteCommandRequest, ExecuteCommandResponse, Status,
+};
+use canadensis_encoding::{DataType, Deserialize};
+
+/// Something that can restart the node
+///
+/// Implementations usually reset the microcontroller or restart the process, so `restart`
+/// does not return.
+pub trait Restart {
+    /// Restarts the node
+    fn restart(&mut self);
+}
+
+/// Handles restart commands from `uavcan.node.ExecuteCommand` requests
+///
+/// When this handler receives a restart command, it sends a success response and waits for
+/// the grace period. This gives the driver time to transmit the response and anything else in
+/// the transmit queue. When [`poll`](#method.poll) is called after the grace period,
+/// the handler calls [`Restart::restart`].
+///
+/// Requests with other commands are passed on to the next handler.
+pub struct ExecuteCommandHandler<R, I: Instant> {
+    /// The restart implementation
+    restart: R,
+    /// The time to wait after responding before restarting
+    grace_period: I::Duration,
+    /// The time when the node should restart, if a restart command has been received
+    restart_time: Option<I>,
+}
+
+impl<R, I> ExecuteCommandHandler<R, I>
+where
+    R: Restart,
+    I: Instant,
+{
+    /// Creates a handler
+    pub fn new(restart: R, grace_period: I::Duration) -> Self {
+        ExecuteCommandHandler {
+            restart,
+            grace_period,
+            restart_time: None,
+        }
+    }
+
+    /// Subscribes to execute command requests
+    pub fn subscribe_requests<N>(node: &mut N) -> Result<(), SubscribeError>
+    where
+        N: Node,
+    {
+        node.subscribe_request(
+            ExecuteCommandRequest::SERVICE,
+            ExecuteCommandRequest::PAYLOAD_SIZE_MAX,
+            milliseconds(1000),
+        )
+    }
+
+    /// Returns true if a restart command has been received and the node will restart
+    pub fn restart_pending(&self) -> bool {
+        self.restart_time.is_some()
+    }
+
+    /// Restarts the node if a restart command has been received and the grace period
+    /// has ended
+    ///
+    /// This function should be called frequently.
+    pub fn poll<N>(&mut self, node: &mut N)
+    where
+        N: Node<Instant = I>,
+    {
+        if let Some(restart_time) = self.restart_time {
+            let now = node.clock_mut().now();
+            if now.overflow_safe_compare(&restart_time) != Ordering::Less {
+                log::info!("Restarting");
+                self.restart_time = None;
+                self.restart.restart();
+            }
+        }
+    }
+}
+
+impl<R, I, P> TransferHandler<I, P> for ExecuteCommandHandler<R, I>
+where
+    R: Restart,
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_request<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        token: ResponseToken,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.service != ExecuteCommandRequest::SERVICE {
+            return false;
+        }
+        match ExecuteCommandRequest::deserialize_from_bytes(transfer.payload.as_ref()) {
+            Ok(ExecuteCommandRequest {
+                command: Command::Restart,
+                ..
+            }) => {
+                let response = ExecuteCommandResponse::new(Status::Success);
+                if node
+                    .send_response(token, self.grace_period, &response)
+                    .is_err()
+                {
+                    log::warn!("Out of memory when sending restart response");
+                }
+                let now = node.clock_mut().now();
+                self.restart_time = Some(self.grace_period + now);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Restarts the current process by executing it again with the same arguments
+///
+/// This is only available on Unix-like systems, where a process can replace itself.
+#[cfg(all(feature = "std", unix))]
+#[derive(Debug, Default)]
+pub struct ProcessRestart;
+
+#[cfg(all(feature = "std", unix))]
+impl Restart for ProcessRestart {
+    fn restart(&mut self) {
+        use std::os::unix::process::CommandExt;
+
+        match std::env::current_exe() {
+            Ok(executable) => {
+                let error = std::process::Command::new(executable)
+                    .args(std::env::args_os().skip(1))
+                    .exec();
+                log::error!("Failed to restart process: {}", error);
+            }
+            Err(e) => log::error!("Failed to find executable to restart: {}", e),
+        }
+    }
+}
+
+/// Restarts an ARM Cortex-M microcontroller by requesting a system reset
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+#[derive(Debug, Default)]
+pub struct SystemResetRestart;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl Restart for SystemResetRestart {
+    fn restart(&mut self) {
+        cortex_m::peripheral::SCB::sys_reset()
+    }
+}
@@ -0,0 +1,185 @@
+//!
+//! Node information for `uavcan.node.GetInfo` responses
+//!
+//! The [`node_info!`](crate::node_info) macro creates a [`NodeInfoBuilder`] with the name,
+//! software version, and VCS revision of the crate that uses the macro. The unique ID and
+//! other fields can then be set on the builder.
+//!
+//! ```
+//! use canadensis_node::node_info;
+//!
+//! let info = node_info!("org.example.node")
+//!     .hardware_version(1, 0)
+//!     .unique_id([0x42; 16])
+//!     .build();
+//! assert_eq!(&info.name[..], b"org.example.node");
+//! ```
+//!
+
+use canadensis_core::unique_id::UniqueIdProvider;
+use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
+use canadensis_data_types::uavcan::node::version::Version;
+
+/// The maximum length of a node name, in bytes
+const NAME_LENGTH_MAX: usize = 50;
+
+/// Creates a [`NodeInfoBuilder`](crate::info::NodeInfoBuilder) with information about the crate
+/// that is being compiled
+///
+/// The builder gets these values:
+///
+/// * Name: the provided name, or the `CARGO_PKG_NAME` of the crate if no name is provided
+/// * Software version: `CARGO_PKG_VERSION_MAJOR` and `CARGO_PKG_VERSION_MINOR`
+/// * Software VCS revision ID: the first 16 hexadecimal digits of the `GIT_HASH` environment
+///   variable when the crate was compiled, or 0 if `GIT_HASH` was not set
+///
+/// A build script can set `GIT_HASH` using `cargo:rustc-env=GIT_HASH=...`.
+///
+/// The values are parsed at compile time. A major or minor version greater than 255 causes a
+/// compile error.
+#[macro_export]
+macro_rules! node_info {
+    () => {
+        $crate::node_info!(env!("CARGO_PKG_NAME"))
+    };
+    ($name:expr) => {{
+        // Constants make the compiler parse the values, so an invalid version is a compile error
+        // instead of a panic when the node starts
+        const MAJOR: u8 = $crate::info::parse_u8(env!("CARGO_PKG_VERSION_MAJOR"));
+        const MINOR: u8 = $crate::info::parse_u8(env!("CARGO_PKG_VERSION_MINOR"));
+        const VCS_REVISION_ID: u64 = match option_env!("GIT_HASH") {
+            Some(hash) => $crate::info::parse_hex_u64(hash),
+            None => 0,
+        };
+        $crate::info::NodeInfoBuilder::new($name)
+            .software_version(MAJOR, MINOR)
+            .vcs_revision_id(VCS_REVISION_ID)
+    }};
+}
+
+/// Builds a `uavcan.node.GetInfo` response
+///
+/// The protocol version is always 1.0. Other fields have default values of zero or empty
+/// until they are set.
+#[derive(Debug, Clone)]
+pub struct NodeInfoBuilder {
+    info: GetInfoResponse,
+}
+
+impl NodeInfoBuilder {
+    /// Creates a builder with a node name
+    ///
+    /// Names longer than 50 bytes are truncated.
+    pub fn new(name: &str) -> Self {
+        let name = &name.as_bytes()[..name.len().min(NAME_LENGTH_MAX)];
+        NodeInfoBuilder {
+            info: GetInfoResponse {
+                protocol_version: Version::new(1, 0),
+                hardware_version: Version::new(0, 0),
+                software_version: Version::new(0, 0),
+                software_vcs_revision_id: 0,
+                unique_id: [0; 16],
+                name: heapless::Vec::from_slice(name).expect("Name too long"),
+                software_image_crc: None,
+                certificate_of_authenticity: heapless::Vec::new(),
+            },
+        }
+    }
+
+    /// Sets the hardware version
+    pub fn hardware_version(mut self, major: u8, minor: u8) -> Self {
+        self.info.hardware_version = Version::new(major, minor);
+        self
+    }
+
+    /// Sets the software version
+    pub fn software_version(mut self, major: u8, minor: u8) -> Self {
+        self.info.software_version = Version::new(major, minor);
+        self
+    }
+
+    /// Sets the version control revision ID of the software
+    pub fn vcs_revision_id(mut self, revision: u64) -> Self {
+        self.info.software_vcs_revision_id = revision;
+        self
+    }
+
+    /// Sets the unique ID of this node
+    pub fn unique_id(mut self, unique_id: [u8; 16]) -> Self {
+        self.info.unique_id = unique_id;
+        self
+    }
+
+    /// Sets the unique ID of this node by calling a function that reads it from the hardware
+    pub fn unique_id_from<F>(self, read_unique_id: F) -> Self
+    where
+        F: FnOnce() -> [u8; 16],
+    {
+        self.unique_id(read_unique_id())
+    }
+
+    /// Sets the unique ID of this node from a provider, like one that reads a hardware
+    /// serial number
+    pub fn unique_id_from_provider<P>(self, provider: &P) -> Self
+    where
+        P: UniqueIdProvider + ?Sized,
+    {
+        self.unique_id(provider.unique_id())
+    }
+
+    /// Sets the CRC of the software image
+    pub fn software_image_crc(mut self, crc: u64) -> Self {
+        self.info.software_image_crc = Some(crc);
+        self
+    }
+
+    /// Returns the response
+    pub fn build(self) -> GetInfoResponse {
+        self.info
+    }
+}
+
+/// Parses a decimal integer that is at most 255
+///
+/// This function is used by the [`node_info!`](crate::node_info) macro.
+///
+/// # Panics
+///
+/// This function panics if the string is empty, contains a non-digit character, or represents
+/// a value greater than 255.
+pub const fn parse_u8(s: &str) -> u8 {
+    let bytes = s.as_bytes();
+    assert!(!bytes.is_empty(), "Empty version number");
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        assert!(digit.is_ascii_digit(), "Invalid version number");
+        value = value * 10 + (digit - b'0') as u32;
+        assert!(value <= u8::MAX as u32, "Version number too large");
+        i += 1;
+    }
+    value as u8
+}
+
+/// Parses up to the first 16 hexadecimal digits of a string, stopping at the first character
+/// that is not a hexadecimal digit
+///
+/// This function is used by the [`node_info!`](crate::node_info) macro to parse a git commit
+/// hash.
+pub const fn parse_hex_u64(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    let mut value: u64 = 0;
+    let mut i = 0;
+    while i < bytes.len() && i < 16 {
+        let digit = match bytes[i] {
+            b'0'..=b'9' => bytes[i] - b'0',
+            b'a'..=b'f' => bytes[i] - b'a' + 10,
+            b'A'..=b'F' => bytes[i] - b'A' + 10,
+            _ => break,
+        };
+        value = (value << 4) | digit as u64;
+        i += 1;
+    }
+    value
+}
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 extern crate alloc;
 extern crate canadensis;
@@ -8,13 +8,20 @@ extern crate canadensis_data_types;
 extern crate canadensis_derive_register_block;
 extern crate canadensis_encoding;
 extern crate canadensis_filter_config;
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+extern crate cortex_m;
 extern crate fallible_collections;
 extern crate half;
 extern crate heapless;
 extern crate log;
 
 mod basic;
+pub mod diagnostic;
+pub mod execute_command;
+pub mod info;
 mod minimal;
+pub mod port_monitor;
 pub mod register;
-pub use crate::basic::BasicNode;
-pub use crate::minimal::MinimalNode;
+pub mod timing;
+pub use crate::basic::{BasicNode, ShutdownError, ShutdownOptions};
+pub use crate::minimal::{MinimalNode, Overrun, OverrunHandler, PeriodicTask};
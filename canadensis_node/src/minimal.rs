@@ -1,6 +1,10 @@
-use canadensis::{Node, PublishToken, StartSendError};
-use canadensis_can::OutOfMemoryError;
-use canadensis_core::time::{Clock, Duration, Instant};
+use alloc::boxed::Box;
+use core::cmp::Ordering;
+use core::fmt;
+
+use canadensis::{Node, PublishToken, SendError, StartSendError};
+use canadensis_can::bus_status::{BusEvent, ErrorState};
+use canadensis_core::time::{Clock, Duration, Instant, PeriodicTimer};
 use canadensis_core::Priority;
 use canadensis_data_types::uavcan::node::health::Health;
 use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
@@ -23,6 +27,79 @@ where
     heartbeat: Heartbeat,
     /// The token used to publish heartbeat messages
     heartbeat_token: PublishToken<Heartbeat>,
+    /// The timer that decides when to send heartbeats in `run_periodic_tasks`
+    heartbeat_timer: PeriodicTimer<N::Instant>,
+    /// The health set by the application
+    health: Health,
+    /// The error state of the CAN controller
+    bus_state: ErrorState,
+    /// The health to report when bus errors happen, if enabled
+    bus_error_health: Option<Health>,
+    /// True if a bus error has happened since the last heartbeat
+    bus_error: bool,
+    /// True if the last heartbeat reported a bus error
+    bus_degraded: bool,
+    /// Settings for automatic health degradation, if enabled
+    auto_health: Option<AutoHealth<<N::Instant as Instant>::Duration>>,
+    /// True if a resource problem has happened since the last heartbeat
+    resource_problem: bool,
+    /// True if the last heartbeat reported a resource problem
+    degraded: bool,
+    /// The time when periodic tasks last ran
+    last_tasks_time: N::Instant,
+    /// The watchdog that reports late periodic tasks, if enabled
+    watchdog: Option<Watchdog<<N::Instant as Instant>::Duration>>,
+}
+
+/// A periodic task that a node runs
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PeriodicTask {
+    /// Publishing a `uavcan.node.Heartbeat` message
+    Heartbeat,
+    /// Publishing a `uavcan.node.port.List` message
+    PortList,
+}
+
+/// Information about a periodic task that ran later than it was scheduled to run
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Overrun<D> {
+    /// The task that ran late
+    pub task: PeriodicTask,
+    /// The time between when the task was scheduled to run and when it ran
+    pub lateness: D,
+    /// The number of times the task should have run, but did not, because the node's periodic
+    /// tasks were not called often enough
+    pub missed: u32,
+}
+
+/// A function that handles periodic task overruns
+pub type OverrunHandler<D> = Box<dyn FnMut(Overrun<D>) + Send>;
+
+/// Settings for the periodic task watchdog
+struct Watchdog<D> {
+    /// The time that a task can be late before it counts as an overrun
+    threshold: D,
+    /// The function that handles overruns
+    handler: OverrunHandler<D>,
+    /// The number of overruns detected
+    overruns: u32,
+}
+
+impl<D: fmt::Debug> fmt::Debug for Watchdog<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .field("overruns", &self.overruns)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Settings for automatic health degradation
+struct AutoHealth<D> {
+    /// The health to report when there is a resource problem
+    health: Health,
+    /// The time that periodic tasks can be late before it counts as a problem
+    late_threshold: D,
 }
 
 impl<N> MinimalNode<N>
@@ -31,40 +108,209 @@ where
 {
     pub fn new(mut node: N) -> Result<Self, StartSendError> {
         // Default heartbeat settings
-        let heartbeat = Heartbeat {
-            uptime: 0,
-            health: Health::Nominal,
-            mode: Mode::Operational,
-            vendor_specific_status_code: 0,
-        };
-        let heartbeat_timeout =
-            <<N::Clock as Clock>::Instant as Instant>::Duration::from_millis(500)
-                .expect("Duration type can't represent 500 milliseconds");
-
+        let heartbeat = Heartbeat::new(0, Health::Nominal, Mode::Operational);
+        // Each heartbeat is sent with the default timeout at the time it is published
+        let heartbeat_timeout = node.default_timeout();
         let heartbeat_token =
             node.start_publishing(Heartbeat::SUBJECT, heartbeat_timeout, Priority::Nominal)?;
+        let now = node.clock_mut().now();
+        let heartbeat_timer = PeriodicTimer::new(
+            now,
+            <N::Instant as Instant>::Duration::from_millis(1000)
+                .expect("Duration type can't represent 1 second"),
+        );
 
         Ok(MinimalNode {
             node,
             heartbeat,
             heartbeat_token,
+            heartbeat_timer,
+            health: Health::Nominal,
+            bus_state: ErrorState::Active,
+            bus_error_health: None,
+            bus_error: false,
+            bus_degraded: false,
+            auto_health: None,
+            resource_problem: false,
+            degraded: false,
+            last_tasks_time: now,
+            watchdog: None,
         })
     }
 
+    /// This function should be called frequently (more than once per second) to send heartbeat
+    /// messages and delete expired incoming transfer sessions
+    ///
+    /// This function checks the clock and sends a heartbeat message if one second has passed since
+    /// the last heartbeat. If more than one second has passed, the uptime in the heartbeat
+    /// includes all the missed seconds.
+    pub fn run_periodic_tasks(&mut self) -> Result<(), SendError> {
+        self.node.clean_expired_sessions();
+        let now = self.node.clock_mut().now();
+        self.check_task_timing(now);
+        let deadline = self.heartbeat_timer.next_expiration();
+        let mut elapsed_seconds = 0u32;
+        while self.heartbeat_timer.poll(now) {
+            elapsed_seconds = elapsed_seconds.saturating_add(1);
+        }
+        if elapsed_seconds != 0 {
+            self.check_deadline(PeriodicTask::Heartbeat, deadline, now, elapsed_seconds - 1);
+            // send_heartbeat adds the last second
+            self.heartbeat.uptime = self.heartbeat.uptime.saturating_add(elapsed_seconds - 1);
+            self.send_heartbeat()?;
+        }
+        Ok(())
+    }
+
     /// This function must be called once per second to send heartbeat messages
     ///
     /// Unlike [`run_periodic_tasks`](#method.run_periodic_tasks), this function does not check
     /// if one second has passed since the last time it was called.
     ///
     /// Either `run_periodic_tasks` or `run_per_second_tasks` should be called, but not both.
-    pub fn run_per_second_tasks(&mut self) -> Result<(), OutOfMemoryError> {
+    pub fn run_per_second_tasks(&mut self) -> Result<(), SendError> {
+        self.node.clean_expired_sessions();
+        let now = self.node.clock_mut().now();
+        let deadline = one_second::<N::Instant>() + self.last_tasks_time;
+        self.check_task_timing(now);
+        self.check_deadline(PeriodicTask::Heartbeat, deadline, now, 0);
         self.send_heartbeat()
     }
 
+    /// Enables the periodic task watchdog
+    ///
+    /// When the watchdog is enabled, the node checks the time when each periodic task runs.
+    /// If a task runs more than `threshold` after it was scheduled to run, the node calls
+    /// `handler` with information about the overrun. This usually means that the main loop was
+    /// blocked or too busy to call the periodic task function on time.
+    ///
+    /// The handler is called from [`run_periodic_tasks`](#method.run_periodic_tasks) or
+    /// [`run_per_second_tasks`](#method.run_per_second_tasks). It can log the overrun or record
+    /// it for later. To also report overruns in the heartbeat health, use
+    /// [`enable_automatic_health`](#method.enable_automatic_health).
+    pub fn enable_watchdog<F>(&mut self, threshold: <N::Instant as Instant>::Duration, handler: F)
+    where
+        F: FnMut(Overrun<<N::Instant as Instant>::Duration>) + Send + 'static,
+    {
+        self.watchdog = Some(Watchdog {
+            threshold,
+            handler: Box::new(handler),
+            overruns: 0,
+        });
+    }
+
+    /// Disables the periodic task watchdog
+    pub fn disable_watchdog(&mut self) {
+        self.watchdog = None;
+    }
+
+    /// Returns the number of overruns that the watchdog has detected since it was enabled
+    ///
+    /// This returns zero if the watchdog is not enabled.
+    pub fn overrun_count(&self) -> u32 {
+        self.watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.overruns)
+            .unwrap_or(0)
+    }
+
+    /// Checks if a task that was scheduled to run at `deadline` and is running at `now` is late,
+    /// and reports an overrun if it is
+    pub(crate) fn check_deadline(
+        &mut self,
+        task: PeriodicTask,
+        deadline: N::Instant,
+        now: N::Instant,
+        missed: u32,
+    ) {
+        let watchdog = match &mut self.watchdog {
+            Some(watchdog) => watchdog,
+            None => return,
+        };
+        if now.overflow_safe_compare(&deadline) != Ordering::Greater {
+            return;
+        }
+        let lateness = now.duration_since(&deadline);
+        if lateness > watchdog.threshold {
+            log::warn!("Periodic task {:?} ran {:?} late", task, lateness);
+            watchdog.overruns = watchdog.overruns.saturating_add(1);
+            (watchdog.handler)(Overrun {
+                task,
+                lateness,
+                missed,
+            });
+        }
+    }
+
+    /// Records a resource problem if the time since periodic tasks last ran is more than one
+    /// second plus the late threshold
+    fn check_task_timing(&mut self, now: N::Instant) {
+        if let Some(auto_health) = &self.auto_health {
+            let allowed = auto_health.late_threshold + one_second::<N::Instant>();
+            if now.duration_since(&self.last_tasks_time) > allowed {
+                log::warn!("Periodic tasks ran late");
+                self.resource_problem = true;
+            }
+        }
+        self.last_tasks_time = now;
+    }
+
     /// Publishes a heartbeat message
-    fn send_heartbeat(&mut self) -> Result<(), OutOfMemoryError> {
+    pub(crate) fn send_heartbeat(&mut self) -> Result<(), SendError> {
         self.heartbeat.uptime = self.heartbeat.uptime.saturating_add(1);
-        self.node.publish(&self.heartbeat_token, &self.heartbeat)
+        // Report the problems since the last heartbeat, and start looking for new ones
+        self.degraded = self.auto_health.is_some() && self.resource_problem;
+        self.resource_problem = false;
+        self.bus_degraded = self.bus_error_health.is_some() && self.bus_error;
+        self.bus_error = false;
+        self.update_heartbeat_health();
+        let timeout = self.node.default_timeout();
+        let status =
+            self.node
+                .publish_with_timeout(&self.heartbeat_token, &self.heartbeat, timeout);
+        if status.is_err() {
+            self.report_resource_problem();
+        }
+        status
+    }
+
+    /// Enables automatic health degradation
+    ///
+    /// When automatic health is enabled, the heartbeat reports at least `health` (usually
+    /// `Caution` or `Warning`) if any of these things happened in the second before the heartbeat:
+    ///
+    /// * An outgoing transfer could not be queued because the frame queue was full
+    /// * An incoming transfer was lost because memory could not be allocated
+    /// * Periodic tasks ran more than `late_threshold` after they were supposed to
+    /// * [`report_resource_problem`](#method.report_resource_problem) was called
+    ///
+    /// When a heartbeat is sent and none of these things have happened since the previous
+    /// heartbeat, the health goes back to normal.
+    pub fn enable_automatic_health(
+        &mut self,
+        health: Health,
+        late_threshold: <N::Instant as Instant>::Duration,
+    ) {
+        self.auto_health = Some(AutoHealth {
+            health,
+            late_threshold,
+        });
+    }
+
+    /// Disables automatic health degradation
+    pub fn disable_automatic_health(&mut self) {
+        self.auto_health = None;
+        self.resource_problem = false;
+        self.degraded = false;
+        self.update_heartbeat_health();
+    }
+
+    /// Records that a resource problem (such as a full queue or failed allocation) has
+    /// happened
+    ///
+    /// If automatic health is enabled, the next heartbeat will report a degraded health.
+    pub fn report_resource_problem(&mut self) {
+        self.resource_problem = true;
     }
 
     /// Sets the operating mode that will be reported in the heartbeat messages
@@ -72,14 +318,97 @@ where
         self.heartbeat.mode = mode;
     }
     /// Sets the health status that will be reported in the heartbeat messages
+    ///
+    /// If the CAN controller is in an error state (see [`set_bus_state`](#method.set_bus_state)),
+    /// the heartbeat reports the worse of this health and the health that corresponds to the bus
+    /// state.
     pub fn set_health(&mut self, health: Health) {
-        self.heartbeat.health = health;
+        self.health = health;
+        self.update_heartbeat_health();
+    }
+    /// Sets the error state of the CAN controller, which will be reflected in the health reported
+    /// in heartbeat messages
+    ///
+    /// An error-passive controller makes the health at least `Advisory`, and a bus-off controller
+    /// makes the health at least `Caution`.
+    pub fn set_bus_state(&mut self, state: ErrorState) {
+        self.bus_state = state;
+        self.update_heartbeat_health();
+    }
+
+    /// Handles an error event from the CAN controller
+    ///
+    /// State changes and restarts update the bus state, like
+    /// [`set_bus_state`](#method.set_bus_state). If bus error health is enabled (see
+    /// [`enable_bus_error_health`](#method.enable_bus_error_health)), an event that indicates an
+    /// error makes the next heartbeat report a degraded health.
+    pub fn handle_bus_event(&mut self, event: BusEvent) {
+        match event {
+            BusEvent::StateChanged(state) => self.set_bus_state(state),
+            BusEvent::Restarted => self.set_bus_state(ErrorState::Active),
+            _ => {
+                if event.is_error() {
+                    self.bus_error = true;
+                }
+            }
+        }
+    }
+
+    /// Enables health degradation when bus errors happen
+    ///
+    /// When this is enabled, the heartbeat reports at least `health` if
+    /// [`handle_bus_event`](#method.handle_bus_event) received an error event in the second
+    /// before the heartbeat.
+    pub fn enable_bus_error_health(&mut self, health: Health) {
+        self.bus_error_health = Some(health);
+    }
+
+    /// Disables health degradation when bus errors happen
+    ///
+    /// The bus state still affects the health.
+    pub fn disable_bus_error_health(&mut self) {
+        self.bus_error_health = None;
+        self.bus_error = false;
+        self.bus_degraded = false;
+        self.update_heartbeat_health();
+    }
+
+    fn update_heartbeat_health(&mut self) {
+        let bus_health = match self.bus_state {
+            ErrorState::Active => Health::Nominal,
+            ErrorState::Passive => Health::Advisory,
+            ErrorState::BusOff => Health::Caution,
+        };
+        let bus_error_health = match (self.bus_error_health, self.bus_degraded) {
+            (Some(health), true) => health,
+            _ => Health::Nominal,
+        };
+        let resource_health = match (&self.auto_health, self.degraded) {
+            (Some(auto_health), true) => auto_health.health,
+            _ => Health::Nominal,
+        };
+        self.heartbeat.health = self
+            .health
+            .max(bus_health)
+            .max(bus_error_health)
+            .max(resource_health);
     }
     /// Sets the vendor-specific status code that will be reported in the heartbeat messages
     pub fn set_status_code(&mut self, status: u8) {
         self.heartbeat.vendor_specific_status_code = status;
     }
 
+    /// Stops publishing heartbeat messages and returns the enclosed node
+    pub fn into_inner(self) -> N {
+        let MinimalNode {
+            mut node,
+            heartbeat_token,
+            ..
+        } = self;
+        node.stop_publishing(heartbeat_token);
+        node
+    }
+
     /// Returns a reference to the enclosed node
     pub fn node(&self) -> &N {
         &self.node
@@ -90,3 +419,7 @@ where
         &mut self.node
     }
 }
+
+fn one_second<I: Instant>() -> I::Duration {
+    I::Duration::from_millis(1000).expect("Duration type can't represent 1 second")
+}
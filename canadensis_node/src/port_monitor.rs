@@ -0,0 +1,361 @@
+//!
+//! Discovery of the ports that other nodes use
+//!
+//! A [`PortListMonitor`] is a transfer handler that receives `uavcan.node.port.List` messages
+//! and keeps track of which subjects each node publishes and subscribes to, and which services
+//! each node uses and provides. When the ports of a node change, the monitor records
+//! [`PortEvent`]s that the application can read.
+//!
+//! To receive port lists, the node must subscribe to [`List::SUBJECT`]:
+//!
+//! ```ignore
+//! node.subscribe_message(List::SUBJECT, List::PAYLOAD_SIZE_MAX, milliseconds(1000))?;
+//! ```
+//!
+
+use core::cmp::Ordering;
+
+use canadensis::{Node, TransferHandler};
+use canadensis_core::time::Instant;
+use canadensis_core::transfer::MessageTransfer;
+use canadensis_core::{NodeId, ServiceId, SubjectId};
+use canadensis_data_types::bits::BitArray;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_data_types::uavcan::node::port::service_id_list::ServiceIdList;
+use canadensis_data_types::uavcan::node::port::subject_id_list::SubjectIdList;
+use canadensis_encoding::Deserialize;
+use heapless::{Deque, FnvIndexMap};
+
+/// The number of possible subject IDs
+const SUBJECTS: usize = 8192;
+/// The number of possible service IDs
+const SERVICES: usize = 512;
+
+/// A port that a node uses
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Port {
+    /// The node publishes messages on a subject
+    Publisher(SubjectId),
+    /// The node subscribes to messages on a subject
+    Subscriber(SubjectId),
+    /// The node sends requests for a service
+    Client(ServiceId),
+    /// The node responds to requests for a service
+    Server(ServiceId),
+}
+
+/// A change in the ports that other nodes use
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PortEvent {
+    /// The first port list from a node was received
+    ///
+    /// The ports of the new node are available from [`PortListMonitor::ports`].
+    NodeAdded(NodeId),
+    /// A node has not sent a port list before the timeout passed to
+    /// [`PortListMonitor::remove_expired`], and has been removed
+    NodeRemoved(NodeId),
+    /// A node started using a port
+    PortAdded(NodeId, Port),
+    /// A node stopped using a port
+    PortRemoved(NodeId, Port),
+}
+
+/// The ports that a node uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ports {
+    publishers: BitArray<{ SUBJECTS / 8 }>,
+    subscribers: BitArray<{ SUBJECTS / 8 }>,
+    clients: BitArray<{ SERVICES / 8 }>,
+    servers: BitArray<{ SERVICES / 8 }>,
+}
+
+impl Ports {
+    fn new() -> Self {
+        Ports {
+            publishers: BitArray::new(SUBJECTS),
+            subscribers: BitArray::new(SUBJECTS),
+            clients: BitArray::new(SERVICES),
+            servers: BitArray::new(SERVICES),
+        }
+    }
+
+    fn from_list(list: &List) -> Self {
+        let mut ports = Ports::new();
+        copy_subjects(&list.publishers, &mut ports.publishers);
+        copy_subjects(&list.subscribers, &mut ports.subscribers);
+        copy_services(&list.clients, &mut ports.clients);
+        copy_services(&list.servers, &mut ports.servers);
+        ports
+    }
+
+    /// Returns true if the node publishes messages on a subject
+    pub fn publishes(&self, subject: SubjectId) -> bool {
+        self.publishers.get(usize::from(subject))
+    }
+    /// Returns true if the node subscribes to messages on a subject
+    pub fn subscribes(&self, subject: SubjectId) -> bool {
+        self.subscribers.get(usize::from(subject))
+    }
+    /// Returns true if the node sends requests for a service
+    pub fn is_client(&self, service: ServiceId) -> bool {
+        self.clients.get(usize::from(service))
+    }
+    /// Returns true if the node responds to requests for a service
+    pub fn is_server(&self, service: ServiceId) -> bool {
+        self.servers.get(usize::from(service))
+    }
+
+    /// Returns true if the node uses a port
+    pub fn contains(&self, port: Port) -> bool {
+        match port {
+            Port::Publisher(subject) => self.publishes(subject),
+            Port::Subscriber(subject) => self.subscribes(subject),
+            Port::Client(service) => self.is_client(service),
+            Port::Server(service) => self.is_server(service),
+        }
+    }
+
+    /// Returns an iterator over all the ports that the node uses
+    pub fn iter(&self) -> impl Iterator<Item = Port> + '_ {
+        let publishers = set_bits(&self.publishers)
+            .map(|subject| Port::Publisher(SubjectId::from_truncating(subject)));
+        let subscribers = set_bits(&self.subscribers)
+            .map(|subject| Port::Subscriber(SubjectId::from_truncating(subject)));
+        let clients = set_bits(&self.clients)
+            .map(|service| Port::Client(ServiceId::from_truncating(service)));
+        let servers = set_bits(&self.servers)
+            .map(|service| Port::Server(ServiceId::from_truncating(service)));
+        publishers.chain(subscribers).chain(clients).chain(servers)
+    }
+}
+
+/// Information about one remote node
+#[derive(Debug)]
+struct RemoteNode<I> {
+    ports: Ports,
+    /// The time when the last port list from this node was received
+    last_update: I,
+}
+
+/// Keeps track of the ports used by up to `N` other nodes, and stores up to `E` events
+///
+/// The monitor implements [`TransferHandler`]. It handles `uavcan.node.port.List` messages and
+/// ignores all other transfers.
+///
+/// Nodes are added automatically when their first port list arrives. If `N` nodes are already
+/// being monitored, port lists from other nodes are ignored. Because nodes publish their port
+/// lists at least every [`List::MAX_PUBLICATION_PERIOD`] seconds, the application should
+/// periodically call [`remove_expired`](PortListMonitor::remove_expired) with a somewhat longer
+/// timeout so that nodes that have gone offline are removed.
+///
+/// If the event queue is full, new events are dropped and counted. An application that misses
+/// events can still look up the current ports of each node.
+pub struct PortListMonitor<I: Instant, const N: usize, const E: usize> {
+    nodes: FnvIndexMap<NodeId, RemoteNode<I>, N>,
+    events: Deque<PortEvent, E>,
+    dropped_events: u32,
+}
+
+impl<I: Instant, const N: usize, const E: usize> PortListMonitor<I, N, E> {
+    /// Creates a monitor
+    ///
+    /// `N` must be a power of two and greater than 1.
+    pub fn new() -> Self {
+        PortListMonitor {
+            nodes: FnvIndexMap::new(),
+            events: Deque::new(),
+            dropped_events: 0,
+        }
+    }
+
+    /// Records a port list received from a node
+    ///
+    /// The transfer handler implementation calls this function with each port list message.
+    pub fn update(&mut self, node: NodeId, list: &List, now: I) {
+        let ports = Ports::from_list(list);
+        match self.nodes.get_mut(&node) {
+            Some(remote) => {
+                remote.last_update = now;
+                if remote.ports != ports {
+                    let old_ports = core::mem::replace(&mut remote.ports, ports);
+                    let new_ports = &remote.ports;
+                    for port in old_ports.iter() {
+                        if !new_ports.contains(port) {
+                            push_event(
+                                &mut self.events,
+                                &mut self.dropped_events,
+                                PortEvent::PortRemoved(node, port),
+                            );
+                        }
+                    }
+                    for port in new_ports.iter() {
+                        if !old_ports.contains(port) {
+                            push_event(
+                                &mut self.events,
+                                &mut self.dropped_events,
+                                PortEvent::PortAdded(node, port),
+                            );
+                        }
+                    }
+                }
+            }
+            None => {
+                let remote = RemoteNode {
+                    ports,
+                    last_update: now,
+                };
+                if self.nodes.insert(node, remote).is_ok() {
+                    self.push_event(PortEvent::NodeAdded(node));
+                }
+            }
+        }
+    }
+
+    /// Removes all nodes that have not sent a port list within `timeout` before `now`
+    pub fn remove_expired(&mut self, now: I, timeout: I::Duration) {
+        while let Some(node) = self.nodes.iter().find_map(|(node, remote)| {
+            let expiration = timeout + remote.last_update;
+            if now.overflow_safe_compare(&expiration) == Ordering::Greater {
+                Some(*node)
+            } else {
+                None
+            }
+        }) {
+            self.nodes.remove(&node);
+            self.push_event(PortEvent::NodeRemoved(node));
+        }
+    }
+
+    /// Returns the ports that a node uses, or None if no port list from the node has been
+    /// received
+    pub fn ports(&self, node: NodeId) -> Option<&Ports> {
+        self.nodes.get(&node).map(|remote| &remote.ports)
+    }
+
+    /// Returns an iterator over all known nodes and their ports
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Ports)> {
+        self.nodes
+            .iter()
+            .map(|(node, remote)| (*node, &remote.ports))
+    }
+
+    /// Returns an iterator over the nodes that publish messages on a subject
+    pub fn publishers_of(&self, subject: SubjectId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes_with(Port::Publisher(subject))
+    }
+
+    /// Returns an iterator over the nodes that subscribe to messages on a subject
+    pub fn subscribers_of(&self, subject: SubjectId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes_with(Port::Subscriber(subject))
+    }
+
+    /// Returns an iterator over the nodes that respond to requests for a service
+    pub fn servers_of(&self, service: ServiceId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes_with(Port::Server(service))
+    }
+
+    /// Returns an iterator over the nodes that send requests for a service
+    pub fn clients_of(&self, service: ServiceId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes_with(Port::Client(service))
+    }
+
+    fn nodes_with(&self, port: Port) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes
+            .iter()
+            .filter(move |(_, remote)| remote.ports.contains(port))
+            .map(|(node, _)| *node)
+    }
+
+    /// Removes the oldest event from the queue and returns it
+    pub fn pop_event(&mut self) -> Option<PortEvent> {
+        self.events.pop_front()
+    }
+
+    /// Returns the number of events that have been dropped because the queue was full
+    /// (saturating at `u32::MAX`)
+    pub fn dropped_events(&self) -> u32 {
+        self.dropped_events
+    }
+
+    /// Removes all nodes and events
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.events.clear();
+    }
+
+    fn push_event(&mut self, event: PortEvent) {
+        push_event(&mut self.events, &mut self.dropped_events, event);
+    }
+}
+
+impl<I: Instant, const N: usize, const E: usize> Default for PortListMonitor<I, N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, P, const N: usize, const E: usize> TransferHandler<I, P> for PortListMonitor<I, N, E>
+where
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_message<M: Node<Instant = I>>(
+        &mut self,
+        _node: &mut M,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        if transfer.header.subject != List::SUBJECT {
+            return false;
+        }
+        // Anonymous nodes can't publish port lists
+        if let Some(source) = transfer.header.source {
+            match List::deserialize_from_bytes(transfer.payload.as_ref()) {
+                Ok(list) => self.update(source, &list, transfer.header.timestamp),
+                Err(e) => log::warn!("Invalid port list from node {}: {:?}", source, e),
+            }
+        }
+        true
+    }
+}
+
+fn push_event<const E: usize>(
+    events: &mut Deque<PortEvent, E>,
+    dropped: &mut u32,
+    event: PortEvent,
+) {
+    if events.push_back(event).is_err() {
+        *dropped = dropped.saturating_add(1);
+    }
+}
+
+fn copy_subjects(list: &SubjectIdList, bits: &mut BitArray<{ SUBJECTS / 8 }>) {
+    match list {
+        SubjectIdList::Mask(mask) => {
+            for subject in 0..SUBJECTS {
+                bits.set(subject, mask.get(subject));
+            }
+        }
+        SubjectIdList::SparseList(subjects) => {
+            for subject in subjects {
+                let subject = usize::from(subject.value);
+                if subject < SUBJECTS {
+                    bits.set(subject, true);
+                }
+            }
+        }
+        SubjectIdList::Total => bits.fill(true),
+    }
+}
+
+fn copy_services(list: &ServiceIdList, bits: &mut BitArray<{ SERVICES / 8 }>) {
+    for service in 0..SERVICES {
+        bits.set(service, list.mask.get(service));
+    }
+}
+
+/// Returns an iterator over the indexes of the bits that are set
+fn set_bits<const BYTES: usize>(bits: &BitArray<BYTES>) -> impl Iterator<Item = u16> + '_ {
+    (0..bits.len())
+        .filter(move |&index| bits.get(index))
+        .map(|index| index as u16)
+}
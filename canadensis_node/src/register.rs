@@ -3,19 +3,19 @@
 
 pub mod basic;
 mod block_impl;
+pub mod bootstrap;
+pub mod client;
 
-use alloc::vec::Vec;
 use core::str;
 
-use canadensis::{Node, ResponseToken, TransferHandler};
-use canadensis_can::OutOfMemoryError;
+use canadensis::{Node, ResponseToken, SubscribeError, TransferHandler};
 use canadensis_core::time::{milliseconds, Instant};
 use canadensis_core::transfer::ServiceTransfer;
 use canadensis_data_types::uavcan::register::access::{AccessRequest, AccessResponse};
 use canadensis_data_types::uavcan::register::list::{ListRequest, ListResponse};
 use canadensis_data_types::uavcan::register::name::Name;
 use canadensis_data_types::uavcan::register::value::Value;
-use canadensis_encoding::Deserialize;
+use canadensis_encoding::{DataType, Deserialize};
 
 pub use canadensis_derive_register_block::RegisterBlock;
 
@@ -138,12 +138,20 @@ where
     ///
     /// This function returns an error if the provided node does not have enough space to listen
     /// for requests.
-    pub fn subscribe_requests<N>(node: &mut N) -> Result<(), OutOfMemoryError>
+    pub fn subscribe_requests<N>(node: &mut N) -> Result<(), SubscribeError>
     where
         N: Node,
     {
-        node.subscribe_request(AccessRequest::SERVICE, 515, milliseconds(1000))?;
-        node.subscribe_request(ListRequest::SERVICE, 2, milliseconds(0))?;
+        node.subscribe_request(
+            AccessRequest::SERVICE,
+            AccessRequest::PAYLOAD_SIZE_MAX,
+            milliseconds(1000),
+        )?;
+        node.subscribe_request(
+            ListRequest::SERVICE,
+            ListRequest::PAYLOAD_SIZE_MAX,
+            milliseconds(0),
+        )?;
         Ok(())
     }
 
@@ -161,7 +169,7 @@ where
     }
 
     fn handle_access_request(&mut self, request: &AccessRequest) -> AccessResponse {
-        match str::from_utf8(&request.name.name) {
+        match request.name.as_str() {
             Ok(register_name) => {
                 log::debug!("Handling access request for {}", register_name);
                 if let Some(register) = self.block.register_by_name_mut(register_name) {
@@ -218,22 +226,26 @@ fn register_handle_access(register: &mut dyn Register, request: &AccessRequest)
     }
 }
 
-impl<I, B> TransferHandler<I> for RegisterHandler<B>
+impl<I, P, B> TransferHandler<I, P> for RegisterHandler<B>
 where
     I: Instant,
+    P: AsRef<[u8]>,
     B: RegisterBlock,
 {
     fn handle_request<N: Node<Instant = I>>(
         &mut self,
         node: &mut N,
         token: ResponseToken,
-        transfer: &ServiceTransfer<Vec<u8>, I>,
+        transfer: &ServiceTransfer<P, I>,
     ) -> bool {
         match transfer.header.service {
             AccessRequest::SERVICE => {
-                if let Ok(request) = AccessRequest::deserialize_from_bytes(&transfer.payload) {
+                if let Ok(request) =
+                    AccessRequest::deserialize_from_bytes(transfer.payload.as_ref())
+                {
                     let response = self.handle_access_request(&request);
-                    let status = node.send_response(token, milliseconds(1000), &response);
+                    let timeout = node.default_timeout();
+                    let status = node.send_response(token, timeout, &response);
                     if status.is_err() {
                         log::warn!("Out of memory when sending register access response");
                     }
@@ -243,9 +255,11 @@ where
                 }
             }
             ListRequest::SERVICE => {
-                if let Ok(request) = ListRequest::deserialize_from_bytes(&transfer.payload) {
+                if let Ok(request) = ListRequest::deserialize_from_bytes(transfer.payload.as_ref())
+                {
                     let response = self.handle_list_request(&request);
-                    let status = node.send_response(token, milliseconds(1000), &response);
+                    let timeout = node.default_timeout();
+                    let status = node.send_response(token, timeout, &response);
                     if status.is_err() {
                         log::warn!("Out of memory when sending register list response");
                     }
@@ -91,24 +91,10 @@ where
 /// }
 /// let mut finite_float_register =
 ///     ValidatedRegister::new("test.float", true, true, is_finite_float);
-/// assert!(finite_float_register
-///     .write(&Value::Real32(heapless::Vec::from_slice(&[37.0]).unwrap()))
-///     .is_ok());
-/// assert!(finite_float_register
-///     .write(&Value::Real32(
-///         heapless::Vec::from_slice(&[f32::INFINITY]).unwrap()
-///     ))
-///     .is_err());
-/// assert!(finite_float_register
-///     .write(&Value::Real32(
-///         heapless::Vec::from_slice(&[f32::NEG_INFINITY]).unwrap()
-///     ))
-///     .is_err());
-/// assert!(finite_float_register
-///     .write(&Value::Real32(
-///         heapless::Vec::from_slice(&[f32::NAN]).unwrap()
-///     ))
-///     .is_err());
+/// assert!(finite_float_register.write(&Value::from(37.0f32)).is_ok());
+/// assert!(finite_float_register.write(&Value::from(f32::INFINITY)).is_err());
+/// assert!(finite_float_register.write(&Value::from(f32::NEG_INFINITY)).is_err());
+/// assert!(finite_float_register.write(&Value::from(f32::NAN)).is_err());
 /// ```
 pub struct ValidatedRegister<T, V = fn(&T) -> bool> {
     name: &'static str,
@@ -0,0 +1,259 @@
+//!
+//! Configuring a node from its registers before it starts
+//!
+//! The UAVCAN specification defines standard registers that configure the transport and the
+//! ports of a node. Because the values of these registers are needed to create the node and its
+//! driver, they must be loaded from persistent storage first:
+//!
+//! 1. Create the register block with default values
+//! 2. Call [`bootstrap`] with the block and a [`RegisterStorage`]. This loads the values of the
+//!    persistent registers and returns the transport configuration.
+//! 3. Configure the driver with the bit rates and MTU. If the configuration has no node ID, use
+//!    a plug-and-play client to get one.
+//! 4. Create the node, and use [`publication_subject`], [`subscription_subject`],
+//!    [`client_service`], and [`server_service`] to find the port IDs to use
+//!
+//! When another node writes a register, call [`save_registers`] to make the change persistent.
+//! Changes to the transport configuration usually take effect when the node restarts.
+//!
+
+use core::convert::TryFrom;
+use core::fmt::Write;
+
+use canadensis_can::Mtu;
+use canadensis_core::{NodeId, ServiceId, SubjectId};
+use canadensis_data_types::uavcan::register::value::Value;
+
+use crate::register::{Register, RegisterBlock};
+
+/// The name of the register that contains the node ID
+pub const NODE_ID: &str = "uavcan.node.id";
+/// The name of the register that contains the arbitration and data bit rates
+pub const CAN_BIT_RATE: &str = "uavcan.can.bitrate";
+/// The name of the register that contains the CAN MTU
+pub const CAN_MTU: &str = "uavcan.can.mtu";
+
+/// The register value that means that a node ID or port ID is not set
+const UNSET_ID: u16 = 0xffff;
+
+/// Somewhere to store the values of persistent registers
+///
+/// Implementations usually keep the values in non-volatile memory, like flash or EEPROM.
+pub trait RegisterStorage {
+    /// Loads the value of a register, if one has been saved
+    fn load(&mut self, name: &str) -> Option<Value>;
+    /// Saves the value of a register
+    fn save(&mut self, name: &str, value: &Value);
+}
+
+/// Transport settings from the standard registers
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportConfig {
+    /// The node ID, or None if the node should use plug-and-play allocation
+    pub node_id: Option<NodeId>,
+    /// The arbitration bit rate, in bits per second
+    pub arbitration_bit_rate: u32,
+    /// The data bit rate for CAN FD, in bits per second
+    ///
+    /// For standard CAN, this is the same as the arbitration bit rate.
+    pub data_bit_rate: u32,
+    /// The maximum transmission unit
+    pub mtu: Mtu,
+}
+
+impl TransportConfig {
+    /// Reads the transport settings from the standard registers in a block
+    ///
+    /// Each setting comes from `defaults` if the block does not have the corresponding register,
+    /// or if the register value is not valid. A `uavcan.node.id` register that contains 65535
+    /// means that the node ID is not set, so `node_id` will be None.
+    pub fn from_registers<B>(block: &B, defaults: TransportConfig) -> TransportConfig
+    where
+        B: RegisterBlock + ?Sized,
+    {
+        let node_id = match read_register::<B, u16>(block, NODE_ID) {
+            Some(UNSET_ID) => None,
+            Some(id) => u8::try_from(id)
+                .ok()
+                .and_then(|id| NodeId::try_from(id).ok())
+                .or(defaults.node_id),
+            None => defaults.node_id,
+        };
+        let (arbitration_bit_rate, data_bit_rate) =
+            match read_register::<B, [u32; 2]>(block, CAN_BIT_RATE) {
+                Some([arbitration, data]) if arbitration != 0 => {
+                    // A data bit rate of zero means that CAN FD is not used
+                    (arbitration, if data == 0 { arbitration } else { data })
+                }
+                _ => match read_register::<B, u32>(block, CAN_BIT_RATE) {
+                    Some(bit_rate) if bit_rate != 0 => (bit_rate, bit_rate),
+                    _ => (defaults.arbitration_bit_rate, defaults.data_bit_rate),
+                },
+            };
+        let mtu = read_register::<B, u16>(block, CAN_MTU)
+            .and_then(|bytes| Mtu::from_bytes(bytes.into()))
+            .unwrap_or(defaults.mtu);
+        TransportConfig {
+            node_id,
+            arbitration_bit_rate,
+            data_bit_rate,
+            mtu,
+        }
+    }
+}
+
+/// Loads the values of persistent registers from storage, and then reads the transport settings
+///
+/// This is equivalent to calling [`load_registers`] and then
+/// [`TransportConfig::from_registers`].
+pub fn bootstrap<B, S>(block: &mut B, storage: &mut S, defaults: TransportConfig) -> TransportConfig
+where
+    B: RegisterBlock + ?Sized,
+    S: RegisterStorage + ?Sized,
+{
+    load_registers(block, storage);
+    TransportConfig::from_registers(block, defaults)
+}
+
+/// Loads the values of all persistent and mutable registers from storage
+///
+/// Registers that have no saved value keep their current values. If a saved value has the wrong
+/// type for its register, it is ignored.
+///
+/// This function returns the number of registers that were loaded.
+pub fn load_registers<B, S>(block: &mut B, storage: &mut S) -> usize
+where
+    B: RegisterBlock + ?Sized,
+    S: RegisterStorage + ?Sized,
+{
+    let mut loaded = 0;
+    let mut index = 0;
+    while let Some(register) = block.register_by_index_mut(index) {
+        index += 1;
+        let access = register.access();
+        if !(access.persistent && access.mutable) {
+            continue;
+        }
+        if let Some(value) = storage.load(register.name()) {
+            match register.write(&value) {
+                Ok(()) => loaded += 1,
+                Err(_) => log::warn!("Saved value of register {} is not valid", register.name()),
+            }
+        }
+    }
+    loaded
+}
+
+/// Saves the values of all persistent registers to storage
+pub fn save_registers<B, S>(block: &B, storage: &mut S)
+where
+    B: RegisterBlock + ?Sized,
+    S: RegisterStorage + ?Sized,
+{
+    let mut index = 0;
+    while let Some(register) = block.register_by_index(index) {
+        index += 1;
+        if register.access().persistent {
+            storage.save(register.name(), &register.read());
+        }
+    }
+}
+
+/// Returns the register in a block with the provided name
+pub fn find_register<'b, B>(block: &'b B, name: &str) -> Option<&'b dyn Register>
+where
+    B: RegisterBlock + ?Sized,
+{
+    (0..)
+        .map(|index| block.register_by_index(index))
+        .take_while(Option::is_some)
+        .flatten()
+        .find(|register| register.name() == name)
+}
+
+/// Returns the subject ID that a publisher should use, from its `uavcan.pub.PORT_NAME.id`
+/// register
+///
+/// If the block does not have the register, this function returns `default`. If the register
+/// contains 65535 or an invalid subject ID, the publisher is disabled and this function returns
+/// None.
+pub fn publication_subject<B>(
+    block: &B,
+    port_name: &str,
+    default: Option<SubjectId>,
+) -> Option<SubjectId>
+where
+    B: RegisterBlock + ?Sized,
+{
+    port_id(block, "pub", port_name, default)
+}
+
+/// Returns the subject ID that a subscriber should use, from its `uavcan.sub.PORT_NAME.id`
+/// register
+///
+/// This follows the same rules as [`publication_subject`].
+pub fn subscription_subject<B>(
+    block: &B,
+    port_name: &str,
+    default: Option<SubjectId>,
+) -> Option<SubjectId>
+where
+    B: RegisterBlock + ?Sized,
+{
+    port_id(block, "sub", port_name, default)
+}
+
+/// Returns the service ID that a client should use, from its `uavcan.cln.PORT_NAME.id` register
+///
+/// This follows the same rules as [`publication_subject`].
+pub fn client_service<B>(
+    block: &B,
+    port_name: &str,
+    default: Option<ServiceId>,
+) -> Option<ServiceId>
+where
+    B: RegisterBlock + ?Sized,
+{
+    port_id(block, "cln", port_name, default)
+}
+
+/// Returns the service ID that a server should use, from its `uavcan.srv.PORT_NAME.id` register
+///
+/// This follows the same rules as [`publication_subject`].
+pub fn server_service<B>(
+    block: &B,
+    port_name: &str,
+    default: Option<ServiceId>,
+) -> Option<ServiceId>
+where
+    B: RegisterBlock + ?Sized,
+{
+    port_id(block, "srv", port_name, default)
+}
+
+fn port_id<B, P>(block: &B, kind: &str, port_name: &str, default: Option<P>) -> Option<P>
+where
+    B: RegisterBlock + ?Sized,
+    P: TryFrom<u16>,
+{
+    let mut name = heapless::String::<256>::new();
+    if write!(name, "uavcan.{}.{}.id", kind, port_name).is_err() {
+        // Name too long, so the register can't exist
+        return default;
+    }
+    match read_register::<B, u16>(block, &name) {
+        Some(UNSET_ID) => None,
+        Some(id) => P::try_from(id).ok(),
+        None => default,
+    }
+}
+
+/// Reads a register and converts its value, returning None if the register does not exist or
+/// its value can't be converted
+fn read_register<B, T>(block: &B, name: &str) -> Option<T>
+where
+    B: RegisterBlock + ?Sized,
+    T: for<'v> TryFrom<&'v Value>,
+{
+    find_register(block, name).and_then(|register| T::try_from(&register.read()).ok())
+}
@@ -0,0 +1,532 @@
+//!
+//! Reading and writing the registers of other nodes
+//!
+//! A [`RegisterClient`] reads all registers of another node into a map. The application can then
+//! change values in the map and write back only the registers that have changed.
+//!
+//! Basic steps:
+//! 1. Create a client with [`RegisterClient::new`]
+//! 2. Call [`RegisterClient::fetch`] to start reading the registers of a node
+//! 3. Pass the client as a transfer handler when calling `accept_frame` on the node, and call
+//!    [`RegisterClient::check_timeout`] periodically, until the status is no longer
+//!    [`Status::Fetching`]
+//! 4. Change register values using [`RegisterClient::set`]
+//! 5. Call [`RegisterClient::write_changes`] and handle transfers until the status is no longer
+//!    [`Status::Writing`]
+//! 6. Check the result of each write using [`RemoteRegister::write_result`]
+//!
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::mem;
+
+use canadensis::{Node, SendError, ServiceToken, StartSendError, TransferHandler};
+use canadensis_core::time::{Clock, Instant};
+use canadensis_core::transfer::ServiceTransfer;
+use canadensis_core::{NodeId, Priority, TransferId};
+use canadensis_data_types::uavcan::register::access::{AccessRequest, AccessResponse};
+use canadensis_data_types::uavcan::register::list::{ListRequest, ListResponse};
+use canadensis_data_types::uavcan::register::name::Name;
+use canadensis_data_types::uavcan::register::value::Value;
+use canadensis_encoding::{DataType, Deserialize};
+
+/// The maximum number of access requests that can wait for responses at the same time
+///
+/// Transfer IDs on CAN wrap around after 32 transfers, and access responses do not contain the
+/// register name. Keeping fewer than 32 requests in flight ensures that each pending request has
+/// a different transfer ID, so each response can be matched to its register.
+pub const MAX_IN_FLIGHT: usize = 16;
+
+/// The state of a register client
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Status {
+    /// No registers have been fetched
+    Idle,
+    /// The client is reading the list of registers and their values
+    Fetching,
+    /// All registers have been read, and any writes have finished
+    Ready,
+    /// The client is writing changed registers
+    Writing,
+    /// Fetching the registers failed
+    Failed(FetchError),
+}
+
+/// Errors that can make fetching registers fail
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FetchError {
+    /// The node did not respond before the timeout
+    Timeout,
+    /// Not enough memory was available to send a request
+    Memory,
+}
+
+/// The result of writing a register
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WriteResult {
+    /// The node accepted the new value
+    Written,
+    /// The node responded with a different value, which is now the value of the register
+    ///
+    /// This usually means that the value had the wrong type or was out of range.
+    Rejected,
+    /// The node did not respond before the timeout
+    Timeout,
+    /// Not enough memory was available to send the request
+    Memory,
+}
+
+/// Errors that can occur when changing the value of a register in the map
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EditError {
+    /// The node does not have a register with the provided name
+    NotFound,
+    /// The register can't be written by other nodes
+    NotMutable,
+    /// The new value does not have the same type as the current value
+    Type,
+}
+
+/// A register of another node
+#[derive(Debug, Clone)]
+pub struct RemoteRegister {
+    name: Name,
+    /// The value read from the node
+    original: Value,
+    /// The value, including any local changes
+    value: Value,
+    mutable: bool,
+    persistent: bool,
+    /// The transfer ID of the request waiting for a response
+    pending: Option<TransferId>,
+    write_result: Option<WriteResult>,
+}
+
+impl RemoteRegister {
+    /// Returns the value of this register, including any changes that have not been written
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+    /// Returns the value of this register that was last read from the node
+    pub fn original_value(&self) -> &Value {
+        &self.original
+    }
+    /// Returns true if this register can be written by other nodes
+    pub fn is_mutable(&self) -> bool {
+        self.mutable
+    }
+    /// Returns true if the value of this register is preserved when the node restarts
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+    /// Returns true if the value has been changed and not written
+    pub fn is_changed(&self) -> bool {
+        self.value != self.original
+    }
+    /// Returns the result of the last write to this register, or None if the register has not
+    /// been written
+    pub fn write_result(&self) -> Option<WriteResult> {
+        self.write_result
+    }
+}
+
+/// Reads all registers from a node and writes back the registers that have changed
+///
+/// The client implements [`TransferHandler`]. It handles register list and access responses from
+/// the node that it is fetching from, and ignores all other transfers.
+///
+/// Registers with names that are not valid UTF-8 are ignored.
+pub struct RegisterClient<I: Instant> {
+    list_token: ServiceToken<ListRequest>,
+    access_token: ServiceToken<AccessRequest>,
+    /// The time to wait for each response
+    timeout: I::Duration,
+    status: Status,
+    /// The node whose registers are being accessed
+    target: Option<NodeId>,
+    registers: BTreeMap<String, RemoteRegister>,
+    /// The transfer ID of the list request waiting for a response
+    list_pending: Option<TransferId>,
+    /// The names of registers waiting for an access request to be sent
+    queue: VecDeque<String>,
+    /// The number of access requests waiting for responses
+    in_flight: usize,
+    /// The index of the next register to list
+    next_index: u16,
+    /// The time when the requests that are waiting for responses time out
+    deadline: Option<I>,
+}
+
+impl<I: Instant> RegisterClient<I> {
+    /// Creates a client and sets up a node to send register list and access requests
+    ///
+    /// `timeout` is the time to wait for each response.
+    pub fn new<N>(
+        node: &mut N,
+        timeout: I::Duration,
+        priority: Priority,
+    ) -> Result<Self, StartSendError>
+    where
+        N: Node<Instant = I>,
+    {
+        let list_token = node.start_sending_requests(
+            ListRequest::SERVICE,
+            timeout,
+            ListResponse::PAYLOAD_SIZE_MAX,
+            priority,
+        )?;
+        let access_token = node.start_sending_requests(
+            AccessRequest::SERVICE,
+            timeout,
+            AccessResponse::PAYLOAD_SIZE_MAX,
+            priority,
+        )?;
+        Ok(RegisterClient {
+            list_token,
+            access_token,
+            timeout,
+            status: Status::Idle,
+            target: None,
+            registers: BTreeMap::new(),
+            list_pending: None,
+            queue: VecDeque::new(),
+            in_flight: 0,
+            next_index: 0,
+            deadline: None,
+        })
+    }
+
+    /// Starts reading all registers from a node
+    ///
+    /// This discards all registers that were previously fetched.
+    pub fn fetch<N>(&mut self, node: &mut N, target: NodeId) -> Result<(), SendError>
+    where
+        N: Node<Instant = I>,
+    {
+        self.target = Some(target);
+        self.registers.clear();
+        self.queue.clear();
+        self.in_flight = 0;
+        self.next_index = 0;
+        self.status = Status::Fetching;
+        let result = self.send_list_request(node);
+        if result.is_err() {
+            self.status = Status::Failed(FetchError::Memory);
+        }
+        result
+    }
+
+    /// Returns the state of this client
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Returns the node whose registers are being accessed
+    pub fn target(&self) -> Option<NodeId> {
+        self.target
+    }
+
+    /// Returns the registers that have been read, sorted by name
+    pub fn registers(&self) -> &BTreeMap<String, RemoteRegister> {
+        &self.registers
+    }
+
+    /// Returns a register by name
+    pub fn register(&self, name: &str) -> Option<&RemoteRegister> {
+        self.registers.get(name)
+    }
+
+    /// Changes the value of a register in the map
+    ///
+    /// The new value will be sent to the node when [`write_changes`](Self::write_changes)
+    /// is called.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), EditError> {
+        let register = self.registers.get_mut(name).ok_or(EditError::NotFound)?;
+        if !register.mutable {
+            return Err(EditError::NotMutable);
+        }
+        if mem::discriminant(&value) != mem::discriminant(&register.original) {
+            return Err(EditError::Type);
+        }
+        register.value = value;
+        Ok(())
+    }
+
+    /// Discards all changes that have not been written
+    pub fn revert(&mut self) {
+        for register in self.registers.values_mut() {
+            register.value = register.original.clone();
+        }
+    }
+
+    /// Starts writing all changed mutable registers to the node
+    ///
+    /// This function returns the number of registers that will be written. If it returns more
+    /// than zero, the status changes to [`Status::Writing`]. Up to [`MAX_IN_FLIGHT`] requests are
+    /// sent immediately, and the others are sent as responses arrive. A register that could not
+    /// be sent gets a [`WriteResult::Memory`] result.
+    ///
+    /// If the status is not [`Status::Ready`], this function does nothing and returns zero.
+    pub fn write_changes<N>(&mut self, node: &mut N) -> usize
+    where
+        N: Node<Instant = I>,
+    {
+        if !(self.status == Status::Ready && self.target.is_some()) {
+            return 0;
+        }
+        for (name, register) in self.registers.iter_mut() {
+            if register.mutable && register.is_changed() {
+                register.write_result = None;
+                self.queue.push_back(name.clone());
+            }
+        }
+        let count = self.queue.len();
+        if count != 0 {
+            self.status = Status::Writing;
+            self.send_queued(node);
+            self.check_write_done();
+        }
+        count
+    }
+
+    /// Checks if the requests waiting for responses have timed out
+    ///
+    /// If fetching times out, the status changes to [`Status::Failed`]. If writing times out,
+    /// each register that was not written gets a [`WriteResult::Timeout`] result.
+    ///
+    /// This function should be called frequently.
+    pub fn check_timeout(&mut self, now: I) {
+        let expired = match &self.deadline {
+            Some(deadline) => now.overflow_safe_compare(deadline) != Ordering::Less,
+            None => false,
+        };
+        if !expired {
+            return;
+        }
+        self.deadline = None;
+        match self.status {
+            Status::Fetching => {
+                self.list_pending = None;
+                self.queue.clear();
+                self.in_flight = 0;
+                for register in self.registers.values_mut() {
+                    register.pending = None;
+                }
+                self.status = Status::Failed(FetchError::Timeout);
+            }
+            Status::Writing => {
+                for name in self.queue.drain(..) {
+                    if let Some(register) = self.registers.get_mut(&name) {
+                        register.write_result = Some(WriteResult::Timeout);
+                    }
+                }
+                self.in_flight = 0;
+                for register in self.registers.values_mut() {
+                    if register.pending.take().is_some() {
+                        register.write_result = Some(WriteResult::Timeout);
+                    }
+                }
+                self.status = Status::Ready;
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends access requests for queued registers until [`MAX_IN_FLIGHT`] requests are waiting
+    /// for responses or the queue is empty
+    ///
+    /// While fetching, the requests read the registers. While writing, they write the changed
+    /// values.
+    fn send_queued<N>(&mut self, node: &mut N)
+    where
+        N: Node<Instant = I>,
+    {
+        let target = self.target.expect("No target node");
+        while self.in_flight < MAX_IN_FLIGHT {
+            let name = match self.queue.pop_front() {
+                Some(name) => name,
+                None => break,
+            };
+            let register = match self.registers.get_mut(&name) {
+                Some(register) => register,
+                None => continue,
+            };
+            let request = AccessRequest {
+                name: register.name.clone(),
+                value: match self.status {
+                    Status::Writing => register.value.clone(),
+                    _ => Value::Empty,
+                },
+            };
+            match node.send_request(&self.access_token, &request, target) {
+                Ok(transfer_id) => {
+                    register.pending = Some(transfer_id);
+                    self.in_flight += 1;
+                    self.deadline = Some(self.timeout + node.clock_mut().now());
+                }
+                Err(_) => {
+                    if self.status == Status::Writing {
+                        register.write_result = Some(WriteResult::Memory);
+                    } else {
+                        self.status = Status::Failed(FetchError::Memory);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn send_list_request<N>(&mut self, node: &mut N) -> Result<(), SendError>
+    where
+        N: Node<Instant = I>,
+    {
+        let target = self.target.expect("No target node");
+        let request = ListRequest {
+            index: self.next_index,
+        };
+        let transfer_id = node.send_request(&self.list_token, &request, target)?;
+        self.list_pending = Some(transfer_id);
+        self.next_index = self.next_index.wrapping_add(1);
+        self.deadline = Some(self.timeout + node.clock_mut().now());
+        Ok(())
+    }
+
+    fn handle_list_response<N>(&mut self, node: &mut N, response: ListResponse)
+    where
+        N: Node<Instant = I>,
+    {
+        self.list_pending = None;
+        if response.name.name.is_empty() {
+            // No more registers
+            self.check_fetch_done();
+            return;
+        }
+        match response.name.as_str() {
+            Ok(name) => {
+                let name = name.to_string();
+                self.queue.push_back(name.clone());
+                self.registers.insert(
+                    name,
+                    RemoteRegister {
+                        name: response.name,
+                        original: Value::Empty,
+                        value: Value::Empty,
+                        mutable: false,
+                        persistent: false,
+                        pending: None,
+                        write_result: None,
+                    },
+                );
+                self.send_queued(node);
+                if self.status != Status::Fetching {
+                    return;
+                }
+            }
+            Err(_) => log::warn!("Ignoring register with a non-UTF-8 name"),
+        }
+        if self.send_list_request(node).is_err() {
+            self.status = Status::Failed(FetchError::Memory);
+        }
+    }
+
+    fn handle_access_response<N>(
+        &mut self,
+        node: &mut N,
+        transfer_id: TransferId,
+        response: AccessResponse,
+    ) where
+        N: Node<Instant = I>,
+    {
+        let register = match self
+            .registers
+            .values_mut()
+            .find(|register| register.pending == Some(transfer_id))
+        {
+            Some(register) => register,
+            None => return,
+        };
+        register.pending = None;
+        self.in_flight -= 1;
+        match self.status {
+            Status::Fetching => {
+                register.mutable = response.mutable;
+                register.persistent = response.persistent;
+                register.original = response.value.clone();
+                register.value = response.value;
+                self.send_queued(node);
+                self.check_fetch_done();
+            }
+            Status::Writing => {
+                register.write_result = Some(if response.value == register.value {
+                    WriteResult::Written
+                } else {
+                    WriteResult::Rejected
+                });
+                register.original = response.value.clone();
+                register.value = response.value;
+                self.send_queued(node);
+                self.check_write_done();
+            }
+            _ => {}
+        }
+    }
+
+    fn check_fetch_done(&mut self) {
+        if self.status == Status::Fetching
+            && self.list_pending.is_none()
+            && self.queue.is_empty()
+            && self.in_flight == 0
+        {
+            self.status = Status::Ready;
+            self.deadline = None;
+        }
+    }
+
+    fn check_write_done(&mut self) {
+        if self.queue.is_empty() && self.in_flight == 0 {
+            self.status = Status::Ready;
+            self.deadline = None;
+        }
+    }
+}
+
+impl<I, P> TransferHandler<I, P> for RegisterClient<I>
+where
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_response<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &ServiceTransfer<P, I>,
+    ) -> bool {
+        if self.target != Some(transfer.header.source) {
+            return false;
+        }
+        match transfer.header.service {
+            ListResponse::SERVICE => {
+                if self.status != Status::Fetching
+                    || self.list_pending != Some(transfer.header.transfer_id)
+                {
+                    return false;
+                }
+                match ListResponse::deserialize_from_bytes(transfer.payload.as_ref()) {
+                    Ok(response) => self.handle_list_response(node, response),
+                    Err(e) => log::warn!("Invalid register list response: {:?}", e),
+                }
+                true
+            }
+            AccessResponse::SERVICE => {
+                match AccessResponse::deserialize_from_bytes(transfer.payload.as_ref()) {
+                    Ok(response) => {
+                        self.handle_access_response(node, transfer.header.transfer_id, response)
+                    }
+                    Err(e) => log::warn!("Invalid register access response: {:?}", e),
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//!
+//! Message timing measurement
+//!
+//! A [`TimingMonitor`] is a transfer handler that records when messages arrive on each subject
+//! and calculates inter-arrival statistics. When the application has a synchronized time base,
+//! it can also record end-to-end latency using the timestamps that senders put into messages.
+//!
+
+use canadensis::{Node, TransferHandler};
+use canadensis_core::time::{Duration, Instant};
+use canadensis_core::transfer::MessageTransfer;
+use canadensis_core::SubjectId;
+use heapless::FnvIndexMap;
+
+/// Minimum, maximum, and mean of a set of durations in microseconds
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Statistics {
+    count: u32,
+    min: u64,
+    max: u64,
+    sum: u64,
+}
+
+impl Statistics {
+    /// Adds a value in microseconds
+    pub fn add(&mut self, microseconds: u64) {
+        if self.count == 0 {
+            self.min = microseconds;
+            self.max = microseconds;
+        } else {
+            self.min = self.min.min(microseconds);
+            self.max = self.max.max(microseconds);
+        }
+        self.count = self.count.saturating_add(1);
+        self.sum = self.sum.saturating_add(microseconds);
+    }
+
+    /// Returns the number of values added
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+    /// Returns the smallest value in microseconds, or None if no values have been added
+    pub fn min(&self) -> Option<u64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.min)
+        }
+    }
+    /// Returns the largest value in microseconds, or None if no values have been added
+    pub fn max(&self) -> Option<u64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.max)
+        }
+    }
+    /// Returns the mean value in microseconds, rounded down, or None if no values have been added
+    pub fn mean(&self) -> Option<u64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.sum / u64::from(self.count))
+        }
+    }
+}
+
+/// Timing information about one subject
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SubjectReport {
+    /// The number of messages received
+    pub messages: u32,
+    /// Time between consecutive messages (from any source)
+    pub interval: Statistics,
+    /// Estimated variation in the time between messages, in microseconds
+    ///
+    /// This is calculated as in RFC 3550 section 6.4.1: a running average of the absolute
+    /// difference between consecutive intervals, with a gain of 1/16.
+    pub jitter: u64,
+    /// Time from the sender's timestamp to reception, as recorded by
+    /// [`TimingMonitor::record_latency`]
+    pub latency: Statistics,
+}
+
+/// Timing state for one subject
+#[derive(Debug)]
+struct SubjectTiming<I> {
+    report: SubjectReport,
+    /// Arrival time of the last message
+    last_arrival: Option<I>,
+    /// The last interval in microseconds
+    last_interval: Option<u64>,
+    /// The jitter estimate, in 1/16 microseconds
+    jitter_scaled: u64,
+}
+
+impl<I> Default for SubjectTiming<I> {
+    fn default() -> Self {
+        SubjectTiming {
+            report: SubjectReport::default(),
+            last_arrival: None,
+            last_interval: None,
+            jitter_scaled: 0,
+        }
+    }
+}
+
+/// Records message timing for up to `S` subjects
+///
+/// The monitor implements [`TransferHandler`]. It records every message it sees and does not
+/// mark any message as handled, so it can be chained in front of other handlers.
+///
+/// Subjects are added automatically when their first message arrives. If `S` subjects are already
+/// being recorded, messages on other subjects are ignored.
+#[derive(Debug)]
+pub struct TimingMonitor<I: Instant, const S: usize> {
+    subjects: FnvIndexMap<SubjectId, SubjectTiming<I>, S>,
+}
+
+impl<I: Instant, const S: usize> TimingMonitor<I, S> {
+    /// Creates a monitor
+    ///
+    /// `S` must be a power of two and greater than 1.
+    pub fn new() -> Self {
+        TimingMonitor {
+            subjects: FnvIndexMap::new(),
+        }
+    }
+
+    /// Records the arrival of a message on a subject
+    ///
+    /// The transfer handler implementation calls this function with the transfer timestamp.
+    pub fn record_arrival(&mut self, subject: SubjectId, arrival: I) {
+        let timing = match self.timing_mut(subject) {
+            Some(timing) => timing,
+            None => return,
+        };
+        timing.report.messages = timing.report.messages.saturating_add(1);
+        if let Some(last_arrival) = timing.last_arrival {
+            let interval = to_microseconds(arrival.duration_since(&last_arrival));
+            timing.report.interval.add(interval);
+            if let Some(last_interval) = timing.last_interval {
+                let difference = interval.abs_diff(last_interval);
+                // J = J + (|D| - J) / 16, keeping J scaled by 16
+                timing.jitter_scaled =
+                    (timing.jitter_scaled - timing.jitter_scaled / 16).saturating_add(difference);
+                timing.report.jitter = timing.jitter_scaled / 16;
+            }
+            timing.last_interval = Some(interval);
+        }
+        timing.last_arrival = Some(arrival);
+    }
+
+    /// Records the latency of a message
+    ///
+    /// sent: The time when the message was sent, from a timestamp in the message, in microseconds
+    /// of synchronized time
+    ///
+    /// received: The time when the message was received, converted into synchronized time,
+    /// in microseconds
+    ///
+    /// If received is earlier than sent (because of clock synchronization error), the latency
+    /// is recorded as zero.
+    pub fn record_latency(&mut self, subject: SubjectId, sent: u64, received: u64) {
+        if let Some(timing) = self.timing_mut(subject) {
+            timing.report.latency.add(received.saturating_sub(sent));
+        }
+    }
+
+    /// Returns the timing information for a subject, or None if no messages on the subject
+    /// have been recorded
+    pub fn subject(&self, subject: SubjectId) -> Option<&SubjectReport> {
+        self.subjects.get(&subject).map(|timing| &timing.report)
+    }
+
+    /// Returns an iterator over the timing information for all recorded subjects
+    pub fn subjects(&self) -> impl Iterator<Item = (SubjectId, &SubjectReport)> {
+        self.subjects
+            .iter()
+            .map(|(subject, timing)| (*subject, &timing.report))
+    }
+
+    /// Removes all recorded information
+    pub fn clear(&mut self) {
+        self.subjects.clear();
+    }
+
+    fn timing_mut(&mut self, subject: SubjectId) -> Option<&mut SubjectTiming<I>> {
+        if !self.subjects.contains_key(&subject)
+            && self
+                .subjects
+                .insert(subject, SubjectTiming::default())
+                .is_err()
+        {
+            // Full
+            return None;
+        }
+        self.subjects.get_mut(&subject)
+    }
+}
+
+impl<I: Instant, const S: usize> Default for TimingMonitor<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Instant, P, const S: usize> TransferHandler<I, P> for TimingMonitor<I, S> {
+    fn handle_message<N: Node<Instant = I>>(
+        &mut self,
+        _node: &mut N,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        self.record_arrival(transfer.header.subject, transfer.header.timestamp);
+        false
+    }
+}
+
+fn to_microseconds<D: Duration>(duration: D) -> u64 {
+    duration
+        .as_secs()
+        .saturating_mul(1_000_000)
+        .saturating_add(u64::from(duration.subsec_nanos() / 1000))
+}
@@ -0,0 +1,101 @@
+//!
+//! Tests that transfers sent by a basic node use the current default timeout
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, Node, TransferHandler};
+use canadensis_can::queue::{ArrayQueue, FrameQueueSource, HeapQueue};
+use canadensis_can::{CanId, Frame, Mtu, Transmitter};
+use canadensis_core::time::{Instant, ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::{Header, ServiceHeader, Transfer};
+use canadensis_core::{NodeId, Priority, TransferId};
+use canadensis_data_types::uavcan::node::get_info::{GetInfoRequest, GetInfoResponse};
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_node::BasicNode;
+
+struct IgnoreAll;
+
+impl<I: Instant, P> TransferHandler<I, P> for IgnoreAll {}
+
+type TestNode =
+    BasicNode<CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>>;
+
+/// Returns the subject or service ID in a CAN ID
+fn port_id(id: CanId) -> u16 {
+    let id = u32::from(id);
+    if id & (1 << 25) != 0 {
+        ((id >> 14) & 0x1ff) as u16
+    } else {
+        ((id >> 8) & 0x1fff) as u16
+    }
+}
+
+/// Removes all frames from the node's queue and returns their port IDs and deadlines
+fn sent_frames(node: &mut TestNode) -> Vec<(u16, Microseconds64)> {
+    let mut frames = Vec::new();
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        frames.push((port_id(frame.id()), frame.timestamp()));
+    }
+    frames
+}
+
+fn get_info_request_frame(destination: NodeId) -> Frame<Microseconds64> {
+    let mut transmitter = Transmitter::new(Mtu::Can8, ArrayQueue::<Microseconds64, 1>::new());
+    transmitter
+        .push(Transfer {
+            header: Header::Request(ServiceHeader {
+                timestamp: Microseconds64::new(0),
+                transfer_id: TransferId::default(),
+                priority: Priority::Nominal,
+                service: GetInfoRequest::SERVICE,
+                source: NodeId::try_from(20).unwrap(),
+                destination,
+            }),
+            payload: &[][..],
+        })
+        .unwrap();
+    transmitter.frame_queue_mut().pop_frame().unwrap()
+}
+
+#[test]
+fn timeout_changed_after_construction() {
+    let core = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let mut node: TestNode = BasicNode::new(core, GetInfoResponse::default()).unwrap();
+    let timeout = MicrosecondDuration64::new(250_000);
+    node.set_default_timeout(timeout);
+
+    // Heartbeats every second, and the port list after 10 seconds
+    for second in 1..=11 {
+        node.clock_mut()
+            .set(Microseconds64::new(second * 1_000_000));
+        node.run_per_second_tasks().unwrap();
+        let now = Microseconds64::new(second * 1_000_000);
+        for (port, deadline) in sent_frames(&mut node) {
+            assert!(port == u16::from(Heartbeat::SUBJECT) || port == u16::from(List::SUBJECT));
+            assert_eq!(timeout + now, deadline);
+        }
+    }
+
+    let frame = get_info_request_frame(node.node_id());
+    node.accept_frame(frame, &mut IgnoreAll).unwrap();
+    let frames = sent_frames(&mut node);
+    assert!(!frames.is_empty());
+    for (port, deadline) in frames {
+        assert_eq!(u16::from(GetInfoRequest::SERVICE), port);
+        assert_eq!(timeout + Microseconds64::new(11_000_000), deadline);
+    }
+}
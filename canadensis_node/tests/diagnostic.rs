@@ -0,0 +1,165 @@
+//!
+//! Tests the diagnostic publisher and severity register
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis::rate_limit::RateLimit;
+use canadensis::{CoreNode, Node};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{Mtu, Receiver};
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::{NodeId, Priority};
+use canadensis_data_types::uavcan::diagnostic::record::Record;
+use canadensis_data_types::uavcan::diagnostic::severity::Severity;
+use canadensis_data_types::uavcan::register::value::Value;
+use canadensis_encoding::{DataType, Deserialize};
+use canadensis_node::diagnostic::{DiagnosticPublisher, SeverityRegister};
+use canadensis_node::register::Register;
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+fn setup() -> (TestNode, DiagnosticPublisher<Microseconds64>) {
+    let mut node: TestNode = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let publisher = DiagnosticPublisher::new(
+        &mut node,
+        MicrosecondDuration64::new(1_000_000),
+        Priority::Low,
+    )
+    .unwrap();
+    (node, publisher)
+}
+
+/// Removes all frames from the node's queue and returns the records in them
+fn sent_records(node: &mut TestNode) -> Vec<Record> {
+    let mut receiver = Receiver::new(NodeId::try_from(20).unwrap(), Mtu::Can8);
+    receiver
+        .subscribe_message(
+            Record::SUBJECT,
+            Record::PAYLOAD_SIZE_MAX,
+            MicrosecondDuration64::new(1_000_000),
+        )
+        .unwrap();
+    let mut records = Vec::new();
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        if let Some(transfer) = receiver.accept(frame).unwrap() {
+            records.push(Record::deserialize_from_bytes(&transfer.payload).unwrap());
+        }
+    }
+    records
+}
+
+fn text(record: &Record) -> &str {
+    std::str::from_utf8(&record.text).unwrap()
+}
+
+#[test]
+fn threshold_filtering() {
+    let (mut node, mut publisher) = setup();
+    assert_eq!(Severity::Notice as u8, publisher.threshold() as u8);
+    publisher.set_threshold(Severity::Warning);
+    assert!(!publisher.enabled(&Severity::Notice));
+    assert!(publisher.enabled(&Severity::Warning));
+    assert!(publisher.enabled(&Severity::Alert));
+
+    publisher
+        .publish(&mut node, Severity::Info, "info")
+        .unwrap();
+    publisher
+        .publish(&mut node, Severity::Trace, "trace")
+        .unwrap();
+    publisher
+        .publish(&mut node, Severity::Warning, "warning")
+        .unwrap();
+    publisher
+        .publish(&mut node, Severity::Error, "error")
+        .unwrap();
+    assert_eq!(2, publisher.filtered());
+
+    let records = sent_records(&mut node);
+    assert_eq!(
+        vec!["warning", "error"],
+        records.iter().map(text).collect::<Vec<_>>()
+    );
+    assert_eq!(Severity::Warning as u8, records[0].severity.clone() as u8);
+}
+
+#[test]
+fn rate_limiting() {
+    let (mut node, mut publisher) = setup();
+    publisher.set_rate_limit(Some(RateLimit {
+        burst: 2,
+        interval: MicrosecondDuration64::new(100_000),
+    }));
+    for _ in 0..4 {
+        publisher.publish(&mut node, Severity::Error, "a").unwrap();
+    }
+    assert_eq!(2, sent_records(&mut node).len());
+    assert_eq!(2, publisher.rate_limited());
+
+    node.clock_mut().set(Microseconds64::new(100_000));
+    for _ in 0..2 {
+        publisher.publish(&mut node, Severity::Error, "b").unwrap();
+    }
+    assert_eq!(1, sent_records(&mut node).len());
+    assert_eq!(3, publisher.rate_limited());
+    // Records below the threshold do not use the rate limit
+    assert_eq!(0, publisher.filtered());
+
+    publisher.set_rate_limit(None);
+    for _ in 0..4 {
+        publisher.publish(&mut node, Severity::Error, "c").unwrap();
+    }
+    assert_eq!(4, sent_records(&mut node).len());
+}
+
+#[test]
+fn long_text_truncated() {
+    let (mut node, mut publisher) = setup();
+    // 254 ASCII characters followed by a 2-byte character that does not fit in 255 bytes
+    let long = format!("{}é", "x".repeat(254));
+    assert_eq!(256, long.len());
+    publisher
+        .publish(&mut node, Severity::Error, &long)
+        .unwrap();
+    // Exactly 255 bytes fit
+    let exact = format!("{}é", "y".repeat(253));
+    publisher
+        .publish(&mut node, Severity::Error, &exact)
+        .unwrap();
+
+    let records = sent_records(&mut node);
+    assert_eq!(2, records.len());
+    assert_eq!("x".repeat(254), text(&records[0]));
+    assert_eq!(exact, text(&records[1]));
+}
+
+#[test]
+fn severity_register() {
+    let mut register = SeverityRegister::default();
+    assert_eq!(Severity::Notice as u8, register.threshold() as u8);
+    assert_eq!("uavcan.diagnostic.severity", register.name());
+
+    register.write(&Value::from(7u8)).unwrap();
+    assert_eq!(Severity::Alert as u8, register.threshold() as u8);
+    register.write(&Value::from(0u8)).unwrap();
+    assert_eq!(Severity::Trace as u8, register.threshold() as u8);
+
+    for &invalid in [8u8, 9, 255].iter() {
+        assert!(register.write(&Value::from(invalid)).is_err());
+        assert_eq!(Severity::Trace as u8, register.threshold() as u8);
+    }
+    assert_eq!(Ok(0u8), u8::try_from(&register.read()));
+}
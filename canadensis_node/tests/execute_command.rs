@@ -0,0 +1,185 @@
+//!
+//! Tests handling of execute command requests
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use canadensis::{CoreNode, Node, ResponseToken, TransferHandler};
+use canadensis_can::queue::{ArrayQueue, FrameQueueSource, HeapQueue};
+use canadensis_can::{Frame, Mtu, Receiver, Transmitter};
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::{Header, ServiceHeader, ServiceTransfer, Transfer};
+use canadensis_core::{NodeId, Priority, TransferId};
+use canadensis_data_types::uavcan::node::execute_command::{
+    Command, ExecuteCommandRequest, ExecuteCommandResponse, Status,
+};
+use canadensis_encoding::{Deserialize, Serialize};
+use canadensis_node::execute_command::{ExecuteCommandHandler, Restart};
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+const NODE: u8 = 10;
+const REQUESTER: u8 = 20;
+
+/// Counts restarts
+#[derive(Clone, Default)]
+struct CountRestarts(Rc<Cell<u32>>);
+
+impl Restart for CountRestarts {
+    fn restart(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// Passes requests to an execute command handler and counts the requests that it does not handle
+struct Handler {
+    commands: ExecuteCommandHandler<CountRestarts, Microseconds64>,
+    unhandled: u32,
+}
+
+impl<P: AsRef<[u8]>> TransferHandler<Microseconds64, P> for Handler {
+    fn handle_request<N: Node<Instant = Microseconds64>>(
+        &mut self,
+        node: &mut N,
+        token: ResponseToken,
+        transfer: &ServiceTransfer<P, Microseconds64>,
+    ) -> bool {
+        if !self.commands.handle_request(node, token, transfer) {
+            self.unhandled += 1;
+        }
+        true
+    }
+}
+
+struct Setup {
+    node: TestNode,
+    handler: Handler,
+    restarts: CountRestarts,
+}
+
+fn setup() -> Setup {
+    let mut node: TestNode = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(NODE).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    ExecuteCommandHandler::<CountRestarts, Microseconds64>::subscribe_requests(&mut node).unwrap();
+    let restarts = CountRestarts::default();
+    let handler = Handler {
+        commands: ExecuteCommandHandler::new(restarts.clone(), MicrosecondDuration64::new(200_000)),
+        unhandled: 0,
+    };
+    Setup {
+        node,
+        handler,
+        restarts,
+    }
+}
+
+fn request_frame(request: &ExecuteCommandRequest) -> Frame<Microseconds64> {
+    let mut payload = vec![0u8; request.size_bits().div_ceil(8)];
+    request.serialize_to_bytes(&mut payload);
+    let mut transmitter = Transmitter::new(Mtu::Can8, ArrayQueue::<Microseconds64, 1>::new());
+    transmitter
+        .push(Transfer {
+            header: Header::Request(ServiceHeader {
+                timestamp: Microseconds64::new(0),
+                transfer_id: TransferId::default(),
+                priority: Priority::Nominal,
+                service: ExecuteCommandRequest::SERVICE,
+                source: NodeId::try_from(REQUESTER).unwrap(),
+                destination: NodeId::try_from(NODE).unwrap(),
+            }),
+            payload: &payload[..],
+        })
+        .unwrap();
+    transmitter.frame_queue_mut().pop_frame().unwrap()
+}
+
+/// Removes all frames from the node's queue and returns the responses in them
+fn responses(node: &mut TestNode) -> Vec<ExecuteCommandResponse> {
+    let mut receiver = Receiver::new(NodeId::try_from(REQUESTER).unwrap(), Mtu::Can8);
+    receiver
+        .subscribe_response(
+            ExecuteCommandResponse::SERVICE,
+            8,
+            MicrosecondDuration64::new(1_000_000),
+        )
+        .unwrap();
+    let mut responses = Vec::new();
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        if let Some(transfer) = receiver.accept(frame).unwrap() {
+            assert!(matches!(transfer.header, Header::Response(_)));
+            responses
+                .push(ExecuteCommandResponse::deserialize_from_bytes(&transfer.payload).unwrap());
+        }
+    }
+    responses
+}
+
+#[test]
+fn restart_after_grace_period() {
+    let mut setup = setup();
+    let frame = request_frame(&ExecuteCommandRequest::new(Command::Restart));
+    setup.node.accept_frame(frame, &mut setup.handler).unwrap();
+    let responses = responses(&mut setup.node);
+    assert_eq!(1, responses.len());
+    assert_eq!(Status::Success, responses[0].status);
+    assert_eq!(0, setup.handler.unhandled);
+
+    let command_handler = &mut setup.handler.commands;
+    assert!(command_handler.restart_pending());
+    setup.node.clock_mut().set(Microseconds64::new(199_999));
+    command_handler.poll(&mut setup.node);
+    assert_eq!(0, setup.restarts.0.get());
+    assert!(command_handler.restart_pending());
+
+    setup.node.clock_mut().set(Microseconds64::new(200_000));
+    command_handler.poll(&mut setup.node);
+    assert_eq!(1, setup.restarts.0.get());
+    assert!(!command_handler.restart_pending());
+
+    // Only one restart for each command
+    setup.node.clock_mut().set(Microseconds64::new(400_000));
+    command_handler.poll(&mut setup.node);
+    assert_eq!(1, setup.restarts.0.get());
+}
+
+#[test]
+fn other_commands_passed_on() {
+    let mut setup = setup();
+    let commands = [
+        Command::PowerOff,
+        Command::BeginSoftwareUpdate,
+        Command::FactoryReset,
+        Command::EmergencyStop,
+        Command::StorePersistentStates,
+        Command::Other(1),
+    ];
+    for (i, command) in commands.iter().enumerate() {
+        let frame = request_frame(&ExecuteCommandRequest::new(*command));
+        setup
+            .node
+            .clock_mut()
+            .set(Microseconds64::new(i as u64 * 1_000_000));
+        setup.node.accept_frame(frame, &mut setup.handler).unwrap();
+    }
+    assert_eq!(commands.len() as u32, setup.handler.unhandled);
+    assert!(responses(&mut setup.node).is_empty());
+
+    let command_handler = &mut setup.handler.commands;
+    assert!(!command_handler.restart_pending());
+    setup.node.clock_mut().set(Microseconds64::new(100_000_000));
+    command_handler.poll(&mut setup.node);
+    assert_eq!(0, setup.restarts.0.get());
+}
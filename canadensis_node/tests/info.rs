@@ -0,0 +1,67 @@
+//!
+//! Tests the version parsing functions used by the node_info! macro
+//!
+
+extern crate canadensis_node;
+
+use canadensis_node::info::{parse_hex_u64, parse_u8};
+use canadensis_node::node_info;
+
+#[test]
+fn parse_u8_valid() {
+    assert_eq!(0, parse_u8("0"));
+    assert_eq!(7, parse_u8("7"));
+    assert_eq!(42, parse_u8("042"));
+    assert_eq!(255, parse_u8("255"));
+}
+
+#[test]
+#[should_panic(expected = "Version number too large")]
+fn parse_u8_too_large() {
+    parse_u8("256");
+}
+
+#[test]
+#[should_panic(expected = "Invalid version number")]
+fn parse_u8_not_a_number() {
+    parse_u8("1a");
+}
+
+#[test]
+#[should_panic(expected = "Empty version number")]
+fn parse_u8_empty() {
+    parse_u8("");
+}
+
+#[test]
+fn parse_hex_u64_digits() {
+    assert_eq!(0, parse_hex_u64(""));
+    assert_eq!(0xabcdef, parse_hex_u64("abcdef"));
+    assert_eq!(0xabcdef, parse_hex_u64("ABCDEF"));
+    // Parsing stops at the first character that is not a hexadecimal digit
+    assert_eq!(0x12, parse_hex_u64("12-dirty"));
+    assert_eq!(0, parse_hex_u64("xyz"));
+}
+
+#[test]
+fn parse_hex_u64_long_hash() {
+    // Only the first 16 digits of a 40-digit git hash are used
+    assert_eq!(
+        0x0123456789abcdef,
+        parse_hex_u64("0123456789abcdef0123456789abcdef01234567")
+    );
+}
+
+#[test]
+fn macro_uses_crate_version() {
+    let info = node_info!("org.example.test").build();
+    assert_eq!(
+        parse_u8(env!("CARGO_PKG_VERSION_MAJOR")),
+        info.software_version.major
+    );
+    assert_eq!(
+        parse_u8(env!("CARGO_PKG_VERSION_MINOR")),
+        info.software_version.minor
+    );
+    assert_eq!(&info.name[..], b"org.example.test");
+}
@@ -0,0 +1,195 @@
+//!
+//! Tests the port list monitor
+//!
+
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+use canadensis_core::{NodeId, ServiceId, SubjectId};
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_data_types::uavcan::node::port::subject_id::SubjectId as ListSubjectId;
+use canadensis_data_types::uavcan::node::port::subject_id_list::SubjectIdList;
+use canadensis_node::port_monitor::{Port, PortEvent, PortListMonitor};
+
+type Monitor = PortListMonitor<Microseconds32, 4, 16>;
+
+fn node(id: u8) -> NodeId {
+    NodeId::try_from(id).unwrap()
+}
+
+fn subject(id: u16) -> SubjectId {
+    SubjectId::try_from(id).unwrap()
+}
+
+fn service(id: u16) -> ServiceId {
+    ServiceId::try_from(id).unwrap()
+}
+
+fn time(value: u32) -> Microseconds32 {
+    Microseconds32::new(value)
+}
+
+fn sparse(subjects: &[u16]) -> SubjectIdList {
+    SubjectIdList::SparseList(
+        subjects
+            .iter()
+            .map(|&value| ListSubjectId { value })
+            .collect(),
+    )
+}
+
+fn list(publishers: &[u16], subscribers: &[u16], clients: &[u16], servers: &[u16]) -> List {
+    let mut list = List {
+        publishers: sparse(publishers),
+        subscribers: sparse(subscribers),
+        ..List::default()
+    };
+    for &client in clients {
+        list.clients.mask.set(usize::from(client), true);
+    }
+    for &server in servers {
+        list.servers.mask.set(usize::from(server), true);
+    }
+    list
+}
+
+fn events(monitor: &mut Monitor) -> Vec<PortEvent> {
+    std::iter::from_fn(|| monitor.pop_event()).collect()
+}
+
+#[test]
+fn node_appears() {
+    let mut monitor = Monitor::new();
+    monitor.update(
+        node(1),
+        &list(&[7509, 100], &[200], &[430], &[384]),
+        time(0),
+    );
+    assert_eq!(vec![PortEvent::NodeAdded(node(1))], events(&mut monitor));
+
+    let ports = monitor.ports(node(1)).unwrap();
+    assert_eq!(
+        vec![
+            Port::Publisher(subject(100)),
+            Port::Publisher(subject(7509)),
+            Port::Subscriber(subject(200)),
+            Port::Client(service(430)),
+            Port::Server(service(384)),
+        ],
+        ports.iter().collect::<Vec<_>>()
+    );
+    assert!(ports.publishes(subject(100)));
+    assert!(!ports.subscribes(subject(100)));
+    assert_eq!(
+        vec![node(1)],
+        monitor.publishers_of(subject(100)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![node(1)],
+        monitor.subscribers_of(subject(200)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![node(1)],
+        monitor.clients_of(service(430)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![node(1)],
+        monitor.servers_of(service(384)).collect::<Vec<_>>()
+    );
+    assert!(monitor.ports(node(2)).is_none());
+
+    // The same list again does not cause any events
+    monitor.update(
+        node(1),
+        &list(&[7509, 100], &[200], &[430], &[384]),
+        time(1000),
+    );
+    assert!(events(&mut monitor).is_empty());
+}
+
+#[test]
+fn ports_change() {
+    let mut monitor = Monitor::new();
+    monitor.update(node(1), &list(&[100], &[200], &[430], &[]), time(0));
+    events(&mut monitor);
+
+    monitor.update(node(1), &list(&[101], &[200], &[], &[384]), time(1000));
+    assert_eq!(
+        vec![
+            PortEvent::PortRemoved(node(1), Port::Publisher(subject(100))),
+            PortEvent::PortRemoved(node(1), Port::Client(service(430))),
+            PortEvent::PortAdded(node(1), Port::Publisher(subject(101))),
+            PortEvent::PortAdded(node(1), Port::Server(service(384))),
+        ],
+        events(&mut monitor)
+    );
+    assert_eq!(0, monitor.publishers_of(subject(100)).count());
+    assert_eq!(1, monitor.publishers_of(subject(101)).count());
+}
+
+#[test]
+fn total_subject_list() {
+    let mut monitor = Monitor::new();
+    let all = List {
+        subscribers: SubjectIdList::Total,
+        ..List::default()
+    };
+    monitor.update(node(1), &all, time(0));
+    let ports = monitor.ports(node(1)).unwrap();
+    assert!(ports.subscribes(subject(0)));
+    assert!(ports.subscribes(subject(8191)));
+    assert!(!ports.publishes(subject(0)));
+}
+
+#[test]
+fn nodes_time_out() {
+    let mut monitor = Monitor::new();
+    let timeout = MicrosecondDuration32::new(15_000_000);
+    monitor.update(node(1), &list(&[100], &[], &[], &[]), time(0));
+    monitor.update(node(2), &list(&[100], &[], &[], &[]), time(5_000_000));
+    events(&mut monitor);
+
+    // Exactly at the timeout, the node is still present
+    monitor.remove_expired(time(15_000_000), timeout);
+    assert!(events(&mut monitor).is_empty());
+    monitor.remove_expired(time(15_000_001), timeout);
+    assert_eq!(vec![PortEvent::NodeRemoved(node(1))], events(&mut monitor));
+    assert!(monitor.ports(node(1)).is_none());
+    assert_eq!(
+        vec![node(2)],
+        monitor.publishers_of(subject(100)).collect::<Vec<_>>()
+    );
+
+    // A new port list keeps a node present
+    monitor.update(node(2), &list(&[100], &[], &[], &[]), time(19_000_000));
+    monitor.remove_expired(time(30_000_000), timeout);
+    assert!(events(&mut monitor).is_empty());
+
+    // A node that returns is added again
+    monitor.update(node(1), &list(&[100], &[], &[], &[]), time(31_000_000));
+    assert_eq!(vec![PortEvent::NodeAdded(node(1))], events(&mut monitor));
+}
+
+#[test]
+fn capacity_and_dropped_events() {
+    let mut monitor: PortListMonitor<Microseconds32, 2, 2> = PortListMonitor::new();
+    for id in 1..=3 {
+        monitor.update(node(id), &list(&[100], &[], &[], &[]), time(0));
+    }
+    // Only two nodes fit
+    assert!(monitor.ports(node(3)).is_none());
+    assert_eq!(2, monitor.nodes().count());
+
+    monitor.update(node(1), &list(&[101, 102], &[], &[], &[]), time(0));
+    // Two NodeAdded events fit in the queue, and the port changes were dropped
+    assert_eq!(3, monitor.dropped_events());
+    assert_eq!(Some(PortEvent::NodeAdded(node(1))), monitor.pop_event());
+    assert_eq!(Some(PortEvent::NodeAdded(node(2))), monitor.pop_event());
+    assert_eq!(None, monitor.pop_event());
+    // The ports are still up to date
+    assert!(monitor.ports(node(1)).unwrap().publishes(subject(102)));
+}
@@ -0,0 +1,262 @@
+//!
+//! Tests the register client with a simulated remote node
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, Node, TransferHandler};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{Mtu, Receiver};
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::{Header, ServiceHeader, ServiceTransfer};
+use canadensis_core::{NodeId, Priority, ServiceId, TransferId};
+use canadensis_data_types::uavcan::register::access::{AccessRequest, AccessResponse};
+use canadensis_data_types::uavcan::register::list::{ListRequest, ListResponse};
+use canadensis_data_types::uavcan::register::name::Name;
+use canadensis_data_types::uavcan::register::value::Value;
+use canadensis_encoding::{Deserialize, Serialize};
+use canadensis_node::register::client::{RegisterClient, Status, WriteResult, MAX_IN_FLIGHT};
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+const CLIENT: u8 = 10;
+const SERVER: u8 = 20;
+const REGISTERS: u16 = 40;
+
+/// A request that the client sent
+struct Request {
+    service: ServiceId,
+    transfer_id: TransferId,
+    payload: Vec<u8>,
+}
+
+/// The registers of the simulated remote node
+struct Server {
+    receiver: Receiver<Microseconds64>,
+    registers: BTreeMap<String, u16>,
+    /// The name of a register that rejects all writes
+    read_only: String,
+}
+
+impl Server {
+    fn new() -> Self {
+        let mut receiver = Receiver::new(NodeId::try_from(SERVER).unwrap(), Mtu::Can8);
+        for &service in [ListRequest::SERVICE, AccessRequest::SERVICE].iter() {
+            receiver
+                .subscribe_request(service, 1024, MicrosecondDuration64::new(1_000_000))
+                .unwrap();
+        }
+        Server {
+            receiver,
+            registers: (0..REGISTERS).map(|i| (register_name(i), i)).collect(),
+            read_only: register_name(5),
+        }
+    }
+
+    fn respond(&mut self, request: &Request) -> Vec<u8> {
+        if request.service == ListRequest::SERVICE {
+            let request = ListRequest::deserialize_from_bytes(&request.payload).unwrap();
+            let name = self
+                .registers
+                .keys()
+                .nth(usize::from(request.index))
+                .map(|name| Name::try_from(name.as_str()).unwrap())
+                .unwrap_or_default();
+            to_bytes(&ListResponse { name })
+        } else {
+            let request = AccessRequest::deserialize_from_bytes(&request.payload).unwrap();
+            let name = request.name.as_str().unwrap().to_string();
+            if let Ok(value) = u16::try_from(&request.value) {
+                if name != self.read_only {
+                    self.registers.insert(name.clone(), value);
+                }
+            }
+            to_bytes(&AccessResponse {
+                timestamp: Default::default(),
+                mutable: true,
+                persistent: false,
+                value: Value::from(self.registers[&name]),
+            })
+        }
+    }
+}
+
+fn register_name(i: u16) -> String {
+    format!("test.register.{:02}", i)
+}
+
+fn to_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![0u8; value.size_bits().div_ceil(8)];
+    value.serialize_to_bytes(&mut bytes);
+    bytes
+}
+
+/// Removes all frames from the node's queue and returns the requests that the server received
+fn sent_requests(node: &mut TestNode, server: &mut Server) -> Vec<Request> {
+    let mut requests = Vec::new();
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        if let Some(transfer) = server.receiver.accept(frame).unwrap() {
+            match transfer.header {
+                Header::Request(header) => requests.push(Request {
+                    service: header.service,
+                    transfer_id: header.transfer_id,
+                    payload: transfer.payload.to_vec(),
+                }),
+                _ => panic!("Unexpected transfer"),
+            }
+        }
+    }
+    requests
+}
+
+/// Answers requests in batches until the client stops sending them
+///
+/// In each batch, the responses are sent in the reverse order of the requests.
+fn run(node: &mut TestNode, client: &mut RegisterClient<Microseconds64>, server: &mut Server) {
+    loop {
+        let requests = sent_requests(node, server);
+        if requests.is_empty() {
+            break;
+        }
+        let access_requests: Vec<&Request> = requests
+            .iter()
+            .filter(|request| request.service == AccessRequest::SERVICE)
+            .collect();
+        assert!(access_requests.len() <= MAX_IN_FLIGHT);
+        let transfer_ids: BTreeSet<TransferId> = access_requests
+            .iter()
+            .map(|request| request.transfer_id)
+            .collect();
+        assert_eq!(access_requests.len(), transfer_ids.len());
+
+        for request in requests.iter().rev() {
+            let response = ServiceTransfer {
+                header: ServiceHeader {
+                    timestamp: Microseconds64::new(0),
+                    transfer_id: request.transfer_id,
+                    priority: Priority::Nominal,
+                    service: request.service,
+                    source: NodeId::try_from(SERVER).unwrap(),
+                    destination: NodeId::try_from(CLIENT).unwrap(),
+                },
+                payload: server.respond(request),
+            };
+            assert!(client.handle_response(node, &response));
+        }
+    }
+}
+
+fn setup() -> (TestNode, RegisterClient<Microseconds64>, Server) {
+    let mut node: TestNode = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(CLIENT).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let client = RegisterClient::new(
+        &mut node,
+        MicrosecondDuration64::new(1_000_000),
+        Priority::Nominal,
+    )
+    .unwrap();
+    (node, client, Server::new())
+}
+
+fn fetch(node: &mut TestNode, client: &mut RegisterClient<Microseconds64>, server: &mut Server) {
+    client
+        .fetch(node, NodeId::try_from(SERVER).unwrap())
+        .unwrap();
+    run(node, client, server);
+    assert_eq!(&Status::Ready, client.status());
+    assert_eq!(usize::from(REGISTERS), client.registers().len());
+}
+
+#[test]
+fn fetch_and_write_many_registers() {
+    let (mut node, mut client, mut server) = setup();
+    fetch(&mut node, &mut client, &mut server);
+    for i in 0..REGISTERS {
+        let register = client.register(&register_name(i)).unwrap();
+        assert_eq!(Ok(i), u16::try_from(register.value()));
+        assert!(register.is_mutable());
+    }
+
+    for i in 0..REGISTERS {
+        client
+            .set(&register_name(i), Value::from(1000 + i))
+            .unwrap();
+    }
+    assert_eq!(usize::from(REGISTERS), client.write_changes(&mut node));
+    assert_eq!(&Status::Writing, client.status());
+    run(&mut node, &mut client, &mut server);
+    assert_eq!(&Status::Ready, client.status());
+
+    for i in 0..REGISTERS {
+        let register = client.register(&register_name(i)).unwrap();
+        if i == 5 {
+            assert_eq!(Some(WriteResult::Rejected), register.write_result());
+            assert_eq!(Ok(5), u16::try_from(register.value()));
+        } else {
+            assert_eq!(Some(WriteResult::Written), register.write_result());
+            assert_eq!(Ok(1000 + i), u16::try_from(register.value()));
+            assert_eq!(1000 + i, server.registers[&register_name(i)]);
+        }
+        assert!(!register.is_changed());
+    }
+}
+
+#[test]
+fn write_timeout() {
+    let (mut node, mut client, mut server) = setup();
+    fetch(&mut node, &mut client, &mut server);
+    for i in 0..REGISTERS {
+        client
+            .set(&register_name(i), Value::from(1000 + i))
+            .unwrap();
+    }
+    client.write_changes(&mut node);
+    // Answer only the first batch, in reverse order, and ignore the requests sent after that
+    let requests = sent_requests(&mut node, &mut server);
+    assert_eq!(MAX_IN_FLIGHT, requests.len());
+    for request in requests.iter().rev() {
+        let response = ServiceTransfer {
+            header: ServiceHeader {
+                timestamp: Microseconds64::new(0),
+                transfer_id: request.transfer_id,
+                priority: Priority::Nominal,
+                service: request.service,
+                source: NodeId::try_from(SERVER).unwrap(),
+                destination: NodeId::try_from(CLIENT).unwrap(),
+            },
+            payload: server.respond(request),
+        };
+        client.handle_response(&mut node, &response);
+    }
+    assert_eq!(&Status::Writing, client.status());
+
+    node.clock_mut().set(Microseconds64::new(1_000_000));
+    client.check_timeout(Microseconds64::new(1_000_000));
+    assert_eq!(&Status::Ready, client.status());
+    let results: Vec<Option<WriteResult>> = (0..REGISTERS)
+        .map(|i| client.register(&register_name(i)).unwrap().write_result())
+        .collect();
+    let written = results
+        .iter()
+        .filter(|&&result| result == Some(WriteResult::Written))
+        .count();
+    let timed_out = results
+        .iter()
+        .filter(|&&result| result == Some(WriteResult::Timeout))
+        .count();
+    // Register 5 rejects writes
+    assert_eq!(MAX_IN_FLIGHT - 1, written);
+    assert_eq!(usize::from(REGISTERS) - MAX_IN_FLIGHT, timed_out);
+}
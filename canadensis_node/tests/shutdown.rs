@@ -0,0 +1,118 @@
+//!
+//! Tests shutting down a basic node
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, Node, StartSendError};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::Mtu;
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::{NodeId, Priority};
+use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_node::{BasicNode, ShutdownError, ShutdownOptions};
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+fn basic_node() -> BasicNode<TestNode> {
+    let core = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    BasicNode::new(core, GetInfoResponse::default()).unwrap()
+}
+
+fn options(final_status_code: Option<u8>) -> ShutdownOptions<MicrosecondDuration64> {
+    ShutdownOptions {
+        final_status_code,
+        flush_timeout: MicrosecondDuration64::new(100_000),
+        max_send_attempts: 10,
+    }
+}
+
+#[test]
+fn flush_and_final_heartbeat() {
+    let node = basic_node();
+    let mut sent = Vec::new();
+    let (mut node, status) = node.shutdown(options(Some(0xa5)), |node: &mut TestNode| {
+        if let Some(frame) = node.frame_queue_mut().pop_frame() {
+            sent.push(frame);
+        }
+    });
+    status.unwrap();
+    assert!(node.frame_queue().is_empty());
+    // The heartbeat fits in one frame, and the vendor-specific status code is its last byte
+    assert_eq!(1, sent.len());
+    assert_eq!(0xa5, sent[0].data()[6]);
+
+    // The port list publisher has been removed
+    assert!(node
+        .start_publishing::<List>(
+            List::SUBJECT,
+            MicrosecondDuration64::new(1_000_000),
+            Priority::Optional
+        )
+        .is_ok());
+}
+
+#[test]
+fn no_final_heartbeat() {
+    let node = basic_node();
+    let mut calls = 0;
+    let (node, status) = node.shutdown(options(None), |_: &mut TestNode| calls += 1);
+    status.unwrap();
+    assert_eq!(0, calls);
+    assert!(node.frame_queue().is_empty());
+}
+
+#[test]
+fn clock_does_not_advance() {
+    let node = basic_node();
+    let mut calls = 0;
+    // The frame is never sent, and the clock is stopped
+    let (node, status) = node.shutdown(options(Some(1)), |_: &mut TestNode| calls += 1);
+    assert!(matches!(
+        status,
+        Err(ShutdownError::Timeout { remaining: 1 })
+    ));
+    assert_eq!(10, calls);
+    assert_eq!(1, node.frame_queue().len());
+}
+
+#[test]
+fn flush_timeout() {
+    let node = basic_node();
+    let mut calls = 0;
+    let (_node, status) = node.shutdown(options(Some(1)), |node: &mut TestNode| {
+        calls += 1;
+        node.clock_mut().advance(MicrosecondDuration64::new(60_000));
+    });
+    assert!(matches!(
+        status,
+        Err(ShutdownError::Timeout { remaining: 1 })
+    ));
+    // The deadline has passed after the second call
+    assert_eq!(2, calls);
+}
+
+#[test]
+fn duplicate_publisher_before_shutdown() {
+    let mut node = basic_node();
+    assert!(matches!(
+        node.start_publishing::<List>(
+            List::SUBJECT,
+            MicrosecondDuration64::new(1_000_000),
+            Priority::Optional
+        ),
+        Err(StartSendError::Duplicate)
+    ));
+}
@@ -0,0 +1,91 @@
+//!
+//! Tests the port list of a basic node that was split from a transmitter
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, Node};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{Mtu, Receiver};
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::Header;
+use canadensis_core::{NodeId, Priority, SubjectId};
+use canadensis_data_types::uavcan::node::get_info::{GetInfoRequest, GetInfoResponse};
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_data_types::uavcan::node::port::subject_id_list::SubjectIdList;
+use canadensis_encoding::{DataType, Deserialize};
+use canadensis_node::BasicNode;
+
+#[test]
+fn port_list_includes_transmitter_ports() {
+    let mut node: CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4> =
+        CoreNode::new(
+            ManualClock::new(Microseconds64::new(0)),
+            NodeId::try_from(10).unwrap(),
+            Mtu::Can8,
+            HeapQueue::new(),
+        );
+    let transmitter_subject = SubjectId::try_from(100).unwrap();
+    let _token = node
+        .start_publishing::<Heartbeat>(
+            transmitter_subject,
+            MicrosecondDuration64::new(1_000_000),
+            Priority::Nominal,
+        )
+        .unwrap();
+    let _request_token = node
+        .start_sending_requests::<GetInfoRequest>(
+            GetInfoRequest::SERVICE,
+            MicrosecondDuration64::new(1_000_000),
+            GetInfoResponse::PAYLOAD_SIZE_MAX,
+            Priority::Nominal,
+        )
+        .unwrap();
+    let (transmitter, node) = node.split(HeapQueue::new());
+    let mut node = BasicNode::new(node, GetInfoResponse::default()).unwrap();
+    node.add_transmitter_ports(&transmitter);
+
+    let mut receiver = Receiver::new(NodeId::try_from(20).unwrap(), Mtu::Can8);
+    receiver
+        .subscribe_message(
+            List::SUBJECT,
+            List::PAYLOAD_SIZE_MAX,
+            MicrosecondDuration64::new(1_000_000),
+        )
+        .unwrap();
+    let mut port_list = None;
+    for second in 1..=11 {
+        node.clock_mut()
+            .set(Microseconds64::new(second * 1_000_000));
+        node.run_per_second_tasks().unwrap();
+        while let Some(frame) = node.frame_queue_mut().pop_frame() {
+            if let Some(transfer) = receiver.accept(frame).unwrap() {
+                if let Header::Message(header) = transfer.header {
+                    if header.subject == List::SUBJECT {
+                        port_list = Some(List::deserialize_from_bytes(&transfer.payload).unwrap());
+                    }
+                }
+            }
+        }
+    }
+
+    let port_list = port_list.expect("No port list published");
+    let publishers: Vec<u16> = match port_list.publishers {
+        SubjectIdList::SparseList(list) => list.iter().map(|subject| subject.value).collect(),
+        _ => panic!("Unexpected publisher list type"),
+    };
+    assert!(publishers.contains(&u16::from(transmitter_subject)));
+    assert!(publishers.contains(&u16::from(Heartbeat::SUBJECT)));
+    assert!(port_list
+        .clients
+        .mask
+        .get(usize::from(u16::from(GetInfoRequest::SERVICE))));
+}
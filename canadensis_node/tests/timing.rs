@@ -0,0 +1,161 @@
+//!
+//! Tests the message timing monitor
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+
+use canadensis::{CoreNode, TransferHandler};
+use canadensis_can::queue::HeapQueue;
+use canadensis_can::Mtu;
+use canadensis_core::time::{ManualClock, Microseconds64};
+use canadensis_core::transfer::{MessageHeader, MessageTransfer};
+use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+use canadensis_node::timing::{Statistics, TimingMonitor};
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+type Monitor = TimingMonitor<Microseconds64, 4>;
+
+fn subject(id: u16) -> SubjectId {
+    SubjectId::try_from(id).unwrap()
+}
+
+fn record(monitor: &mut Monitor, id: u16, times: &[u64]) {
+    for &time in times {
+        monitor.record_arrival(subject(id), Microseconds64::new(time));
+    }
+}
+
+#[test]
+fn first_message_has_no_interval() {
+    let mut monitor = Monitor::new();
+    assert!(monitor.subject(subject(100)).is_none());
+    record(&mut monitor, 100, &[5_000]);
+    let report = monitor.subject(subject(100)).unwrap();
+    assert_eq!(1, report.messages);
+    assert_eq!(&Statistics::default(), &report.interval);
+    assert_eq!(None, report.interval.min());
+    assert_eq!(None, report.interval.mean());
+    assert_eq!(0, report.jitter);
+}
+
+#[test]
+fn intervals_and_jitter() {
+    let mut monitor = Monitor::new();
+    record(&mut monitor, 100, &[0, 100_000, 250_000, 300_000]);
+    let report = monitor.subject(subject(100)).unwrap();
+    assert_eq!(4, report.messages);
+    assert_eq!(3, report.interval.count());
+    assert_eq!(Some(50_000), report.interval.min());
+    assert_eq!(Some(150_000), report.interval.max());
+    assert_eq!(Some(100_000), report.interval.mean());
+    // Interval differences are 50000 and 100000:
+    // J1 = 50000 / 16 = 3125
+    // J2 = 3125 + (100000 - 3125) / 16 = 9179.6875
+    assert_eq!(9179, report.jitter);
+}
+
+#[test]
+fn regular_messages_have_no_jitter() {
+    let mut monitor = Monitor::new();
+    let times: Vec<u64> = (0..20).map(|i| i * 10_000).collect();
+    record(&mut monitor, 100, &times);
+    let report = monitor.subject(subject(100)).unwrap();
+    assert_eq!(Some(10_000), report.interval.min());
+    assert_eq!(Some(10_000), report.interval.max());
+    assert_eq!(0, report.jitter);
+}
+
+#[test]
+fn mean_rounds_down() {
+    let mut statistics = Statistics::default();
+    statistics.add(1);
+    statistics.add(2);
+    assert_eq!(Some(1), statistics.mean());
+    assert_eq!(Some(1), statistics.min());
+    assert_eq!(Some(2), statistics.max());
+}
+
+#[test]
+fn latency() {
+    let mut monitor = Monitor::new();
+    monitor.record_latency(subject(100), 1_000, 1_300);
+    monitor.record_latency(subject(100), 2_000, 2_100);
+    // Clock synchronization error makes the message appear to arrive before it was sent
+    monitor.record_latency(subject(100), 3_000, 2_990);
+    let report = monitor.subject(subject(100)).unwrap();
+    assert_eq!(3, report.latency.count());
+    assert_eq!(Some(0), report.latency.min());
+    assert_eq!(Some(300), report.latency.max());
+    assert_eq!(Some(133), report.latency.mean());
+    // Recording latency does not count as a message arrival
+    assert_eq!(0, report.messages);
+}
+
+#[test]
+fn subjects_are_separate() {
+    let mut monitor = Monitor::new();
+    record(&mut monitor, 100, &[0, 1_000]);
+    record(&mut monitor, 200, &[500, 20_500]);
+    assert_eq!(
+        Some(1_000),
+        monitor.subject(subject(100)).unwrap().interval.mean()
+    );
+    assert_eq!(
+        Some(20_000),
+        monitor.subject(subject(200)).unwrap().interval.mean()
+    );
+    let mut subjects: Vec<u16> = monitor
+        .subjects()
+        .map(|(subject, _)| u16::from(subject))
+        .collect();
+    subjects.sort_unstable();
+    assert_eq!(vec![100, 200], subjects);
+
+    monitor.clear();
+    assert_eq!(0, monitor.subjects().count());
+}
+
+#[test]
+fn capacity() {
+    let mut monitor = TimingMonitor::<Microseconds64, 2>::new();
+    for id in 1..=3 {
+        monitor.record_arrival(subject(id), Microseconds64::new(0));
+        monitor.record_latency(subject(id), 0, 10);
+    }
+    assert!(monitor.subject(subject(1)).is_some());
+    assert!(monitor.subject(subject(2)).is_some());
+    assert!(monitor.subject(subject(3)).is_none());
+}
+
+#[test]
+fn transfer_handler() {
+    let mut node: TestNode = CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let mut monitor = Monitor::new();
+    for &time in [1_000u64, 3_000].iter() {
+        let transfer = MessageTransfer {
+            header: MessageHeader {
+                timestamp: Microseconds64::new(time),
+                transfer_id: TransferId::const_default(),
+                priority: Priority::Nominal,
+                subject: subject(100),
+                source: Some(NodeId::try_from(20).unwrap()),
+            },
+            payload: Vec::<u8>::new(),
+        };
+        // The monitor never marks a message as handled
+        assert!(!monitor.handle_message(&mut node, &transfer));
+    }
+    let report = monitor.subject(subject(100)).unwrap();
+    assert_eq!(2, report.messages);
+    assert_eq!(Some(2_000), report.interval.mean());
+}
@@ -0,0 +1,144 @@
+//!
+//! Tests the watchdog that reports late heartbeat and port list tasks
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_node;
+
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+
+use canadensis::{CoreNode, Node};
+use canadensis_can::queue::HeapQueue;
+use canadensis_can::Mtu;
+use canadensis_core::time::{ManualClock, MicrosecondDuration64, Microseconds64};
+use canadensis_core::NodeId;
+use canadensis_data_types::uavcan::node::get_info::GetInfoResponse;
+use canadensis_node::{BasicNode, MinimalNode, Overrun, PeriodicTask};
+
+type TestNode = CoreNode<ManualClock<Microseconds64>, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+type Overruns = Arc<Mutex<Vec<Overrun<MicrosecondDuration64>>>>;
+
+fn core_node() -> TestNode {
+    CoreNode::new(
+        ManualClock::new(Microseconds64::new(0)),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    )
+}
+
+/// Returns a list of overruns and a handler that adds overruns to the list
+fn recorder() -> (
+    Overruns,
+    impl FnMut(Overrun<MicrosecondDuration64>) + Send + 'static,
+) {
+    let overruns = Overruns::default();
+    let handler_overruns = Arc::clone(&overruns);
+    let handler = move |overrun| handler_overruns.lock().unwrap().push(overrun);
+    (overruns, handler)
+}
+
+#[test]
+fn no_overrun_at_startup() {
+    let mut node = BasicNode::new(core_node(), GetInfoResponse::default()).unwrap();
+    let (overruns, handler) = recorder();
+    node.enable_watchdog(MicrosecondDuration64::new(100_000), handler);
+
+    // Long enough for the port list to be published twice
+    for second in 1..=25 {
+        node.clock_mut()
+            .set(Microseconds64::new(second * 1_000_000));
+        node.run_per_second_tasks().unwrap();
+    }
+    assert_eq!(0, node.overrun_count());
+    assert!(overruns.lock().unwrap().is_empty());
+}
+
+#[test]
+fn late_per_second_tasks() {
+    let mut node = BasicNode::new(core_node(), GetInfoResponse::default()).unwrap();
+    let (overruns, handler) = recorder();
+    node.enable_watchdog(MicrosecondDuration64::new(100_000), handler);
+
+    for second in 1..=11 {
+        node.clock_mut()
+            .set(Microseconds64::new(second * 1_000_000));
+        node.run_per_second_tasks().unwrap();
+    }
+    assert_eq!(0, node.overrun_count());
+
+    // A small delay is within the threshold
+    node.clock_mut().set(Microseconds64::new(12_050_000));
+    node.run_per_second_tasks().unwrap();
+    assert_eq!(0, node.overrun_count());
+
+    // The heartbeat is late, and so is the port list that was due 10 seconds after the first one
+    for second in 13..=20 {
+        node.clock_mut()
+            .set(Microseconds64::new(second * 1_000_000 + 50_000));
+        node.run_per_second_tasks().unwrap();
+    }
+    node.clock_mut().set(Microseconds64::new(22_500_000));
+    node.run_per_second_tasks().unwrap();
+
+    let overruns = overruns.lock().unwrap();
+    assert_eq!(
+        vec![
+            Overrun {
+                task: PeriodicTask::Heartbeat,
+                lateness: MicrosecondDuration64::new(1_450_000),
+                missed: 0,
+            },
+            Overrun {
+                task: PeriodicTask::PortList,
+                lateness: MicrosecondDuration64::new(1_500_000),
+                missed: 0,
+            },
+        ],
+        *overruns
+    );
+    assert_eq!(2, node.overrun_count());
+}
+
+#[test]
+fn missed_heartbeats() {
+    let mut node = MinimalNode::new(core_node()).unwrap();
+    let (overruns, handler) = recorder();
+    node.enable_watchdog(MicrosecondDuration64::new(100_000), handler);
+
+    node.node_mut()
+        .clock_mut()
+        .set(Microseconds64::new(1_000_000));
+    node.run_periodic_tasks().unwrap();
+    node.node_mut()
+        .clock_mut()
+        .set(Microseconds64::new(1_500_000));
+    node.run_periodic_tasks().unwrap();
+    assert_eq!(0, node.overrun_count());
+
+    // The heartbeats at 2 and 3 seconds were missed
+    node.node_mut()
+        .clock_mut()
+        .set(Microseconds64::new(4_200_000));
+    node.run_periodic_tasks().unwrap();
+    assert_eq!(
+        vec![Overrun {
+            task: PeriodicTask::Heartbeat,
+            lateness: MicrosecondDuration64::new(2_200_000),
+            missed: 2,
+        }],
+        *overruns.lock().unwrap()
+    );
+
+    node.disable_watchdog();
+    node.node_mut()
+        .clock_mut()
+        .set(Microseconds64::new(9_000_000));
+    node.run_periodic_tasks().unwrap();
+    assert_eq!(0, node.overrun_count());
+    assert_eq!(1, overruns.lock().unwrap().len());
+}
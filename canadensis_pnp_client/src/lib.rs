@@ -15,6 +15,7 @@ use canadensis::anonymous::AnonymousPublisher;
 use canadensis_can::queue::{FrameQueueSource, FrameSink};
 use canadensis_can::{Frame, Mtu, OutOfMemoryError, Receiver, Transmitter};
 use canadensis_core::time::{milliseconds, Clock};
+use canadensis_core::unique_id::UniqueIdProvider;
 use canadensis_core::{NodeId, Priority, SubjectId};
 use canadensis_data_types::uavcan::pnp::node_id_allocation_data_1_0::NodeIdAllocationData;
 use canadensis_encoding::{Deserialize, Message, Serialize};
@@ -43,7 +44,7 @@ where
 {
     pub fn new(mtu: Mtu, unique_id: [u8; 16]) -> Result<Self, OutOfMemoryError> {
         let mut receiver = Receiver::new_anonymous(mtu);
-        receiver.subscribe_message(M::SUBJECT, 9, milliseconds(1000))?;
+        receiver.subscribe_message(M::SUBJECT, M::PAYLOAD_SIZE_MAX, milliseconds(1000))?;
 
         Ok(PnpClient {
             unique_id,
@@ -59,6 +60,14 @@ where
         })
     }
 
+    /// Creates a client that gets the unique ID of this node from a provider
+    pub fn with_unique_id_provider<P>(mtu: Mtu, provider: &P) -> Result<Self, OutOfMemoryError>
+    where
+        P: UniqueIdProvider + ?Sized,
+    {
+        Self::new(mtu, provider.unique_id())
+    }
+
     /// Creates an outgoing node ID allocation message and returns it encoded into one CAN frame
     pub fn assemble_request(&mut self, now: C::Instant) -> Frame<C::Instant> {
         let message = M::with_unique_id(&self.unique_id);
@@ -0,0 +1,117 @@
+use canadensis_data_types::format::PayloadRegistry;
+use canadensis_data_types::uavcan::node::health::Health;
+use canadensis_data_types::uavcan::node::heartbeat::Heartbeat;
+use canadensis_data_types::uavcan::node::mode::Mode;
+use canadensis_encoding::{Deserialize, DeserializeError, Serialize};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::transport::PyTransfer;
+
+/// Formats transfer payloads as text
+///
+/// Payloads of the regulated data types on their fixed port IDs are decoded. Other payloads
+/// are shown as hexadecimal bytes.
+#[pyclass(name = "PayloadFormatter", module = "canadensis_py", unsendable)]
+pub struct PyPayloadFormatter {
+    registry: PayloadRegistry,
+}
+
+#[pymethods]
+impl PyPayloadFormatter {
+    #[new]
+    fn new() -> Self {
+        PyPayloadFormatter {
+            registry: PayloadRegistry::regulated(),
+        }
+    }
+
+    /// Formats the payload of a transfer, on multiple lines if `pretty` is true
+    #[pyo3(signature = (transfer, pretty = false))]
+    fn format(&self, transfer: &PyTransfer, pretty: bool) -> String {
+        let transfer = transfer.transfer();
+        let formatted = self.registry.format(&transfer.header, &transfer.payload);
+        if pretty {
+            format!("{:#}", formatted)
+        } else {
+            formatted.to_string()
+        }
+    }
+}
+
+/// A `uavcan.node.Heartbeat.1.0` message
+#[pyclass(name = "Heartbeat", module = "canadensis_py")]
+pub struct PyHeartbeat {
+    /// Seconds since the node started
+    #[pyo3(get, set)]
+    uptime: u32,
+    /// 0 (nominal), 1 (advisory), 2 (caution), or 3 (warning)
+    #[pyo3(get, set)]
+    health: u8,
+    /// 0 (operational), 1 (initialization), 2 (maintenance), or 3 (software update)
+    #[pyo3(get, set)]
+    mode: u8,
+    #[pyo3(get, set)]
+    vendor_specific_status_code: u8,
+}
+
+#[pymethods]
+impl PyHeartbeat {
+    /// The fixed subject ID of heartbeat messages
+    #[classattr]
+    const SUBJECT_ID: u16 = 7509;
+
+    #[new]
+    #[pyo3(signature = (uptime = 0, health = 0, mode = 0, vendor_specific_status_code = 0))]
+    fn new(uptime: u32, health: u8, mode: u8, vendor_specific_status_code: u8) -> Self {
+        PyHeartbeat {
+            uptime,
+            health,
+            mode,
+            vendor_specific_status_code,
+        }
+    }
+
+    /// Serializes this heartbeat into a transfer payload
+    fn serialize<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let health = match self.health {
+            Health::NOMINAL_U8 => Health::Nominal,
+            Health::ADVISORY_U8 => Health::Advisory,
+            Health::CAUTION_U8 => Health::Caution,
+            Health::WARNING_U8 => Health::Warning,
+            _ => return Err(PyValueError::new_err("Invalid health")),
+        };
+        if self.mode > 7 {
+            return Err(PyValueError::new_err("Invalid mode"));
+        }
+        let heartbeat = Heartbeat::new(self.uptime, health, Mode::from(self.mode))
+            .with_status_code(self.vendor_specific_status_code);
+        let mut payload = vec![0u8; heartbeat.size_bits() / 8];
+        heartbeat.serialize_to_bytes(&mut payload);
+        Ok(PyBytes::new_bound(py, &payload))
+    }
+
+    /// Deserializes a heartbeat from a transfer payload
+    #[staticmethod]
+    fn deserialize(payload: &[u8]) -> PyResult<Self> {
+        let heartbeat = Heartbeat::deserialize_from_bytes(payload).map_err(deserialize_error)?;
+        Ok(PyHeartbeat {
+            uptime: heartbeat.uptime,
+            health: heartbeat.health as u8,
+            mode: heartbeat.mode.into(),
+            vendor_specific_status_code: heartbeat.vendor_specific_status_code,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Heartbeat(uptime={}, health={}, mode={}, vendor_specific_status_code={})",
+            self.uptime, self.health, self.mode, self.vendor_specific_status_code
+        )
+    }
+}
+
+pub(crate) fn deserialize_error(error: DeserializeError) -> PyErr {
+    PyValueError::new_err(format!("Invalid payload: {}", error))
+}
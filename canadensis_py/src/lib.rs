@@ -0,0 +1,51 @@
+//!
+//! Python bindings for canadensis
+//!
+//! This crate builds a Python extension module called `canadensis_py`, so that test scripts and
+//! test rigs can use the same transport and serialization code as the firmware. It can be built
+//! with [maturin](https://github.com/PyO3/maturin):
+//!
+//! ```text
+//! maturin develop --features extension-module
+//! ```
+//!
+//! The module provides these classes:
+//!
+//! * `Frame`: A CAN frame
+//! * `Transmitter`: Splits transfers into frames
+//! * `Receiver`: Reassembles frames into `Transfer`s
+//! * `PayloadFormatter`: Formats the payloads of transfers with regulated data types as text
+//! * `Heartbeat`: Serializes and deserializes `uavcan.node.Heartbeat` messages
+//! * `PortListMonitor`: Keeps track of the ports that other nodes use
+//!
+//! All times (timestamps, deadlines, and timeouts) are integers in microseconds.
+//!
+
+// The code that the pyo3 macros generate for functions that return PyResult triggers this lint
+#![allow(clippy::useless_conversion)]
+
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_node;
+extern crate pyo3;
+
+mod data;
+mod monitor;
+mod transport;
+
+use pyo3::prelude::*;
+
+#[pymodule]
+fn canadensis_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<transport::PyFrame>()?;
+    module.add_class::<transport::PyTransfer>()?;
+    module.add_class::<transport::PyTransmitter>()?;
+    module.add_class::<transport::PyReceiver>()?;
+    module.add_class::<data::PyPayloadFormatter>()?;
+    module.add_class::<data::PyHeartbeat>()?;
+    module.add_class::<monitor::PyPorts>()?;
+    module.add_class::<monitor::PyPortListMonitor>()?;
+    Ok(())
+}
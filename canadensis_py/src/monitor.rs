@@ -0,0 +1,148 @@
+use canadensis_core::time::{MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::Header;
+use canadensis_data_types::uavcan::node::port::list::List;
+use canadensis_encoding::Deserialize;
+use canadensis_node::port_monitor::{Port, PortEvent, PortListMonitor, Ports};
+use pyo3::prelude::*;
+
+use crate::data::deserialize_error;
+use crate::transport::{parse_node_id, PyTransfer};
+
+/// The maximum number of nodes that the monitor can keep track of
+const MAX_NODES: usize = 128;
+/// The maximum number of events that the monitor stores
+const MAX_EVENTS: usize = 256;
+
+/// The ports that a node uses
+#[pyclass(name = "Ports", module = "canadensis_py", get_all)]
+#[derive(Clone)]
+pub struct PyPorts {
+    /// Subject IDs that the node publishes on
+    publishers: Vec<u16>,
+    /// Subject IDs that the node subscribes to
+    subscribers: Vec<u16>,
+    /// Service IDs that the node sends requests for
+    clients: Vec<u16>,
+    /// Service IDs that the node responds to
+    servers: Vec<u16>,
+}
+
+impl From<&Ports> for PyPorts {
+    fn from(ports: &Ports) -> Self {
+        let mut py_ports = PyPorts {
+            publishers: Vec::new(),
+            subscribers: Vec::new(),
+            clients: Vec::new(),
+            servers: Vec::new(),
+        };
+        for port in ports.iter() {
+            match port {
+                Port::Publisher(subject) => py_ports.publishers.push(subject.into()),
+                Port::Subscriber(subject) => py_ports.subscribers.push(subject.into()),
+                Port::Client(service) => py_ports.clients.push(service.into()),
+                Port::Server(service) => py_ports.servers.push(service.into()),
+            }
+        }
+        py_ports
+    }
+}
+
+#[pymethods]
+impl PyPorts {
+    fn __repr__(&self) -> String {
+        format!(
+            "Ports(publishers={:?}, subscribers={:?}, clients={:?}, servers={:?})",
+            self.publishers, self.subscribers, self.clients, self.servers
+        )
+    }
+}
+
+/// Keeps track of the ports that other nodes use, based on their `uavcan.node.port.List`
+/// messages
+#[pyclass(name = "PortListMonitor", module = "canadensis_py")]
+pub struct PyPortListMonitor {
+    monitor: Box<PortListMonitor<Microseconds64, MAX_NODES, MAX_EVENTS>>,
+}
+
+#[pymethods]
+impl PyPortListMonitor {
+    /// The fixed subject ID of port list messages
+    #[classattr]
+    const SUBJECT_ID: u16 = 7510;
+
+    #[new]
+    fn new() -> Self {
+        PyPortListMonitor {
+            monitor: Box::default(),
+        }
+    }
+
+    /// Handles a transfer, and returns true if it was a port list message
+    fn handle(&mut self, transfer: &PyTransfer) -> PyResult<bool> {
+        let transfer = transfer.transfer();
+        match &transfer.header {
+            Header::Message(header) if header.subject == List::SUBJECT => {
+                // Anonymous nodes can't publish port lists
+                if let Some(source) = header.source {
+                    let list = List::deserialize_from_bytes(&transfer.payload)
+                        .map_err(deserialize_error)?;
+                    self.monitor.update(source, &list, header.timestamp);
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Removes all nodes that have not sent a port list within `timeout` before `now`
+    fn remove_expired(&mut self, now: u64, timeout: u64) {
+        self.monitor.remove_expired(
+            Microseconds64::new(now),
+            MicrosecondDuration64::new(timeout),
+        );
+    }
+
+    /// Returns the IDs of the nodes that have sent port lists
+    fn nodes(&self) -> Vec<u8> {
+        self.monitor.nodes().map(|(node, _)| node.into()).collect()
+    }
+
+    /// Returns the ports of a node, or None if no port list from the node has been received
+    fn ports(&self, node_id: u8) -> PyResult<Option<PyPorts>> {
+        let node = parse_node_id(node_id)?;
+        Ok(self.monitor.ports(node).map(PyPorts::from))
+    }
+
+    /// Removes and returns the oldest event, or None if there are no events
+    ///
+    /// Each event is a tuple of the event kind (`"node_added"`, `"node_removed"`, `"port_added"`,
+    /// or `"port_removed"`), the node ID, and for port events a tuple of the port kind
+    /// (`"publisher"`, `"subscriber"`, `"client"`, or `"server"`) and the port ID.
+    #[allow(clippy::type_complexity)]
+    fn pop_event(&mut self) -> Option<(&'static str, u8, Option<(&'static str, u16)>)> {
+        let event = self.monitor.pop_event()?;
+        Some(match event {
+            PortEvent::NodeAdded(node) => ("node_added", node.into(), None),
+            PortEvent::NodeRemoved(node) => ("node_removed", node.into(), None),
+            PortEvent::PortAdded(node, port) => ("port_added", node.into(), Some(port_tuple(port))),
+            PortEvent::PortRemoved(node, port) => {
+                ("port_removed", node.into(), Some(port_tuple(port)))
+            }
+        })
+    }
+
+    /// The number of events that were dropped because the queue was full
+    #[getter]
+    fn dropped_events(&self) -> u32 {
+        self.monitor.dropped_events()
+    }
+}
+
+fn port_tuple(port: Port) -> (&'static str, u16) {
+    match port {
+        Port::Publisher(subject) => ("publisher", subject.into()),
+        Port::Subscriber(subject) => ("subscriber", subject.into()),
+        Port::Client(service) => ("client", service.into()),
+        Port::Server(service) => ("server", service.into()),
+    }
+}
@@ -0,0 +1,418 @@
+use std::convert::TryFrom;
+
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::{
+    CanId, Frame, Mtu, Receiver, ServiceSubscribeError, Transmitter, FRAME_CAPACITY,
+};
+use canadensis_core::time::{MicrosecondDuration64, Microseconds64};
+use canadensis_core::transfer::{Header, MessageHeader, ServiceHeader, Transfer};
+use canadensis_core::{NodeId, Priority, ServiceId, SubjectId, TransferId};
+use pyo3::exceptions::{PyMemoryError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// A CAN frame
+#[pyclass(name = "Frame", module = "canadensis_py")]
+#[derive(Clone)]
+pub struct PyFrame {
+    frame: Frame<Microseconds64>,
+}
+
+#[pymethods]
+impl PyFrame {
+    #[new]
+    #[pyo3(signature = (id, data, timestamp = 0))]
+    fn new(id: u32, data: &[u8], timestamp: u64) -> PyResult<Self> {
+        let id = CanId::try_from(id)
+            .map_err(|_| PyValueError::new_err("CAN ID has more than 29 bits"))?;
+        if data.len() > FRAME_CAPACITY {
+            return Err(PyValueError::new_err("Frame data is too long"));
+        }
+        Ok(PyFrame {
+            frame: Frame::new(Microseconds64::new(timestamp), id, data),
+        })
+    }
+
+    /// The 29-bit CAN ID
+    #[getter]
+    fn id(&self) -> u32 {
+        self.frame.id().into()
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, self.frame.data())
+    }
+
+    /// The time when the frame was received, or the deadline for sending it
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.frame.timestamp().as_microseconds()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Frame(id=0x{:08x}, data={:02x?}, timestamp={})",
+            u32::from(self.frame.id()),
+            self.frame.data(),
+            self.timestamp()
+        )
+    }
+}
+
+/// A transfer that a receiver has reassembled
+#[pyclass(name = "Transfer", module = "canadensis_py")]
+pub struct PyTransfer {
+    transfer: Transfer<Vec<u8>, Microseconds64>,
+}
+
+impl PyTransfer {
+    pub(crate) fn transfer(&self) -> &Transfer<Vec<u8>, Microseconds64> {
+        &self.transfer
+    }
+}
+
+#[pymethods]
+impl PyTransfer {
+    /// `"message"`, `"request"`, or `"response"`
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.transfer.header {
+            Header::Message(_) => "message",
+            Header::Request(_) => "request",
+            Header::Response(_) => "response",
+        }
+    }
+
+    /// The subject ID or service ID
+    #[getter]
+    fn port_id(&self) -> u16 {
+        match &self.transfer.header {
+            Header::Message(header) => header.subject.into(),
+            Header::Request(header) | Header::Response(header) => header.service.into(),
+        }
+    }
+
+    /// The source node ID, or None for an anonymous message
+    #[getter]
+    fn source(&self) -> Option<u8> {
+        self.transfer.header.source().map(u8::from)
+    }
+
+    /// The destination node ID, or None for a message
+    #[getter]
+    fn destination(&self) -> Option<u8> {
+        match &self.transfer.header {
+            Header::Message(_) => None,
+            Header::Request(header) | Header::Response(header) => Some(header.destination.into()),
+        }
+    }
+
+    #[getter]
+    fn transfer_id(&self) -> u8 {
+        self.transfer.header.transfer_id().into()
+    }
+
+    #[getter]
+    fn priority(&self) -> u8 {
+        self.transfer.header.priority().into()
+    }
+
+    /// The time when the first frame of the transfer was received
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.transfer.header.timestamp().as_microseconds()
+    }
+
+    #[getter]
+    fn payload<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.transfer.payload)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Transfer(kind={:?}, port_id={}, source={}, destination={}, transfer_id={}, \
+             payload={:02x?})",
+            self.kind(),
+            self.port_id(),
+            optional_repr(self.source()),
+            optional_repr(self.destination()),
+            self.transfer_id(),
+            self.transfer.payload
+        )
+    }
+}
+
+/// Formats an optional node ID the way Python would
+fn optional_repr(id: Option<u8>) -> String {
+    match id {
+        Some(id) => id.to_string(),
+        None => "None".into(),
+    }
+}
+
+/// Splits transfers into frames
+#[pyclass(name = "Transmitter", module = "canadensis_py")]
+pub struct PyTransmitter {
+    transmitter: Transmitter<HeapQueue<Microseconds64>>,
+    mtu: Mtu,
+}
+
+#[pymethods]
+impl PyTransmitter {
+    /// Creates a transmitter for frames of up to `mtu` bytes (8, or 64 for CAN FD)
+    #[new]
+    #[pyo3(signature = (mtu = 8))]
+    fn new(mtu: usize) -> PyResult<Self> {
+        let mtu = parse_mtu(mtu)?;
+        Ok(PyTransmitter {
+            transmitter: Transmitter::new(mtu, HeapQueue::new()),
+            mtu,
+        })
+    }
+
+    /// Splits a message into frames and returns them
+    ///
+    /// A message with no source node is anonymous, and must fit into one frame.
+    #[pyo3(signature = (subject_id, transfer_id, payload, source = None, priority = 4, deadline = 0))]
+    fn publish(
+        &mut self,
+        subject_id: u16,
+        transfer_id: u8,
+        payload: &[u8],
+        source: Option<u8>,
+        priority: u8,
+        deadline: u64,
+    ) -> PyResult<Vec<PyFrame>> {
+        let source = source.map(parse_node_id).transpose()?;
+        if source.is_none() && payload.len() >= self.mtu.as_bytes() {
+            return Err(PyValueError::new_err(
+                "Anonymous message does not fit into one frame",
+            ));
+        }
+        let header = Header::Message(MessageHeader {
+            timestamp: Microseconds64::new(deadline),
+            transfer_id: parse_transfer_id(transfer_id)?,
+            priority: parse_priority(priority)?,
+            subject: SubjectId::try_from(subject_id)
+                .map_err(|_| PyValueError::new_err("Invalid subject ID"))?,
+            source,
+        });
+        self.push(header, payload)
+    }
+
+    /// Splits a service request into frames and returns them
+    #[pyo3(signature = (service_id, transfer_id, payload, source, destination, priority = 4, deadline = 0))]
+    #[allow(clippy::too_many_arguments)]
+    fn request(
+        &mut self,
+        service_id: u16,
+        transfer_id: u8,
+        payload: &[u8],
+        source: u8,
+        destination: u8,
+        priority: u8,
+        deadline: u64,
+    ) -> PyResult<Vec<PyFrame>> {
+        let header = service_header(
+            service_id,
+            transfer_id,
+            source,
+            destination,
+            priority,
+            deadline,
+        )?;
+        self.push(Header::Request(header), payload)
+    }
+
+    /// Splits a service response into frames and returns them
+    #[pyo3(signature = (service_id, transfer_id, payload, source, destination, priority = 4, deadline = 0))]
+    #[allow(clippy::too_many_arguments)]
+    fn respond(
+        &mut self,
+        service_id: u16,
+        transfer_id: u8,
+        payload: &[u8],
+        source: u8,
+        destination: u8,
+        priority: u8,
+        deadline: u64,
+    ) -> PyResult<Vec<PyFrame>> {
+        let header = service_header(
+            service_id,
+            transfer_id,
+            source,
+            destination,
+            priority,
+            deadline,
+        )?;
+        self.push(Header::Response(header), payload)
+    }
+}
+
+impl PyTransmitter {
+    fn push(&mut self, header: Header<Microseconds64>, payload: &[u8]) -> PyResult<Vec<PyFrame>> {
+        self.transmitter
+            .push(Transfer { header, payload })
+            .map_err(|_| PyMemoryError::new_err("Out of memory"))?;
+        let queue = self.transmitter.frame_queue_mut();
+        let mut frames = Vec::new();
+        while let Some(frame) = queue.pop_frame() {
+            frames.push(PyFrame { frame });
+        }
+        Ok(frames)
+    }
+}
+
+/// Reassembles frames into transfers
+#[pyclass(name = "Receiver", module = "canadensis_py")]
+pub struct PyReceiver {
+    receiver: Receiver<Microseconds64>,
+}
+
+#[pymethods]
+impl PyReceiver {
+    /// Creates a receiver for frames of up to `mtu` bytes
+    ///
+    /// A receiver with no node ID can only receive messages.
+    #[new]
+    #[pyo3(signature = (mtu = 8, node_id = None))]
+    fn new(mtu: usize, node_id: Option<u8>) -> PyResult<Self> {
+        let mtu = parse_mtu(mtu)?;
+        let receiver = match node_id {
+            Some(id) => Receiver::new(parse_node_id(id)?, mtu),
+            None => Receiver::new_anonymous(mtu),
+        };
+        Ok(PyReceiver { receiver })
+    }
+
+    fn subscribe_message(
+        &mut self,
+        subject_id: u16,
+        payload_size_max: usize,
+        timeout: u64,
+    ) -> PyResult<()> {
+        let subject = SubjectId::try_from(subject_id)
+            .map_err(|_| PyValueError::new_err("Invalid subject ID"))?;
+        self.receiver
+            .subscribe_message(
+                subject,
+                payload_size_max,
+                MicrosecondDuration64::new(timeout),
+            )
+            .map_err(|_| PyMemoryError::new_err("Out of memory"))
+    }
+
+    fn unsubscribe_message(&mut self, subject_id: u16) -> PyResult<()> {
+        let subject = SubjectId::try_from(subject_id)
+            .map_err(|_| PyValueError::new_err("Invalid subject ID"))?;
+        self.receiver.unsubscribe_message(subject);
+        Ok(())
+    }
+
+    fn subscribe_request(
+        &mut self,
+        service_id: u16,
+        payload_size_max: usize,
+        timeout: u64,
+    ) -> PyResult<()> {
+        let service = parse_service_id(service_id)?;
+        self.receiver
+            .subscribe_request(
+                service,
+                payload_size_max,
+                MicrosecondDuration64::new(timeout),
+            )
+            .map_err(service_subscribe_error)
+    }
+
+    fn subscribe_response(
+        &mut self,
+        service_id: u16,
+        payload_size_max: usize,
+        timeout: u64,
+    ) -> PyResult<()> {
+        let service = parse_service_id(service_id)?;
+        self.receiver
+            .subscribe_response(
+                service,
+                payload_size_max,
+                MicrosecondDuration64::new(timeout),
+            )
+            .map_err(service_subscribe_error)
+    }
+
+    /// Receives messages on all subjects, in addition to the subscribed ones
+    fn enable_promiscuous(&mut self, payload_size_max: usize, timeout: u64) {
+        self.receiver
+            .enable_promiscuous(payload_size_max, MicrosecondDuration64::new(timeout));
+    }
+
+    /// Handles a frame and returns a transfer if the frame completed one
+    fn accept(&mut self, frame: &PyFrame) -> PyResult<Option<PyTransfer>> {
+        let transfer = self
+            .receiver
+            .accept(frame.frame.clone())
+            .map_err(|_| PyMemoryError::new_err("Out of memory"))?;
+        Ok(transfer.map(|transfer| PyTransfer { transfer }))
+    }
+
+    /// The number of transfers received successfully
+    #[getter]
+    fn transfer_count(&self) -> u64 {
+        self.receiver.transfer_count()
+    }
+
+    /// The number of transfers that could not be received
+    #[getter]
+    fn error_count(&self) -> u64 {
+        self.receiver.error_count()
+    }
+}
+
+fn service_header(
+    service_id: u16,
+    transfer_id: u8,
+    source: u8,
+    destination: u8,
+    priority: u8,
+    deadline: u64,
+) -> PyResult<ServiceHeader<Microseconds64>> {
+    Ok(ServiceHeader {
+        timestamp: Microseconds64::new(deadline),
+        transfer_id: parse_transfer_id(transfer_id)?,
+        priority: parse_priority(priority)?,
+        service: parse_service_id(service_id)?,
+        source: parse_node_id(source)?,
+        destination: parse_node_id(destination)?,
+    })
+}
+
+fn service_subscribe_error(error: ServiceSubscribeError) -> PyErr {
+    match error {
+        ServiceSubscribeError::Anonymous => {
+            PyValueError::new_err("An anonymous receiver can't receive service transfers")
+        }
+        ServiceSubscribeError::Memory(_) => PyMemoryError::new_err("Out of memory"),
+    }
+}
+
+fn parse_mtu(mtu: usize) -> PyResult<Mtu> {
+    Mtu::from_bytes(mtu).ok_or_else(|| PyValueError::new_err("Unsupported MTU"))
+}
+
+pub(crate) fn parse_node_id(id: u8) -> PyResult<NodeId> {
+    NodeId::try_from(id).map_err(|_| PyValueError::new_err("Invalid node ID"))
+}
+
+fn parse_service_id(id: u16) -> PyResult<ServiceId> {
+    ServiceId::try_from(id).map_err(|_| PyValueError::new_err("Invalid service ID"))
+}
+
+fn parse_transfer_id(id: u8) -> PyResult<TransferId> {
+    TransferId::try_from(id).map_err(|_| PyValueError::new_err("Invalid transfer ID"))
+}
+
+fn parse_priority(priority: u8) -> PyResult<Priority> {
+    Priority::try_from(priority).map_err(|_| PyValueError::new_err("Invalid priority"))
+}
@@ -0,0 +1,311 @@
+//!
+//! Electronic speed controller service
+//!
+//! An [`Esc`] implements the `reg.udral.service.actuator.esc` service for one drive in a group.
+//! It uses these subjects:
+//!
+//! * `setpoint` (subscribed): `reg.udral.service.actuator.common.sp.*`
+//! * `readiness` (subscribed): `reg.udral.service.common.Readiness`
+//! * `feedback` (published after each setpoint, and at least at 1 Hz):
+//!   `reg.udral.service.actuator.common.Feedback`
+//! * `status` (published at 1 Hz): `reg.udral.service.actuator.common.Status`
+//!
+//! The power and dynamics subjects are not published.
+//!
+//! The application passes incoming transfers to the ESC (it implements [`TransferHandler`]),
+//! calls [`poll`](Esc::poll) frequently, and drives the motor using the value from
+//! [`setpoint`](Esc::setpoint). The setpoint is zero unless the drive is engaged and a setpoint
+//! has arrived within the control timeout.
+//!
+
+use core::cmp::Ordering;
+
+use canadensis::{Node, PublishToken, SendError, StartSendError, TransferHandler};
+use canadensis_core::time::{Clock, Instant};
+use canadensis_core::transfer::MessageTransfer;
+use canadensis_core::{Priority, SubjectId};
+use canadensis_data_types::reg::udral::service::actuator::common::feedback::Feedback;
+use canadensis_data_types::reg::udral::service::actuator::common::sp::vector31::Vector31;
+use canadensis_data_types::reg::udral::service::actuator::common::status::Status;
+use canadensis_data_types::reg::udral::service::common::heartbeat::Heartbeat;
+use canadensis_data_types::reg::udral::service::common::readiness::Readiness;
+use canadensis_data_types::uavcan::node::health::Health;
+use canadensis_encoding::{DataType, Deserialize};
+
+use crate::readiness::ReadinessTracker;
+use crate::{control_timeout, max_publication_period};
+
+/// The number of drives that one setpoint message can control
+pub const MAX_GROUP_SIZE: u8 = 31;
+
+/// Configuration for an ESC
+#[derive(Debug, Clone)]
+pub struct EscConfig {
+    /// The index of this drive in its group
+    ///
+    /// This must be less than [`MAX_GROUP_SIZE`].
+    pub index: u8,
+    /// The subject that the controller sends setpoints for the group on
+    pub setpoint_subject: SubjectId,
+    /// The subject that the controller sends readiness commands on
+    pub readiness_subject: SubjectId,
+    /// The subject to publish feedback on
+    pub feedback_subject: SubjectId,
+    /// The subject to publish status on
+    pub status_subject: SubjectId,
+    /// The priority of feedback messages
+    ///
+    /// This should be the same as the priority of the setpoint messages.
+    pub feedback_priority: Priority,
+}
+
+/// One electronic speed controller in a group
+///
+/// The meaning of the setpoint depends on the control mode of the drive (ratiometric voltage,
+/// ratiometric current, or speed). UDRAL expects the control mode to be configured using
+/// registers, so this type does not handle it.
+pub struct Esc<I: Instant> {
+    /// The index of this drive in its group
+    index: usize,
+    setpoint_subject: SubjectId,
+    readiness_subject: SubjectId,
+    feedback_token: PublishToken<Feedback>,
+    status_token: PublishToken<Status>,
+    /// The commanded readiness state
+    readiness: ReadinessTracker<I>,
+    /// The readiness state reported in feedback messages, or None to report the commanded state
+    reported_readiness: Option<Readiness>,
+    /// The most recent setpoint
+    setpoint: f32,
+    /// The time when the most recent setpoint arrived
+    setpoint_time: Option<I>,
+    /// Maximum time between setpoints
+    control_timeout: I::Duration,
+    /// Maximum time between feedback messages
+    feedback_period: I::Duration,
+    /// The time when feedback was last published
+    last_feedback: Option<I>,
+    /// The time when status was last published
+    last_status: Option<I>,
+    health: Health,
+    demand_factor_pct: i8,
+    status: Status,
+}
+
+impl<I: Instant> Esc<I> {
+    /// Creates an ESC, subscribes to its setpoint and readiness subjects, and starts publishing
+    /// feedback and status
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `config.index` is not less than [`MAX_GROUP_SIZE`].
+    pub fn new<N>(node: &mut N, config: EscConfig) -> Result<Self, StartSendError>
+    where
+        N: Node<Instant = I>,
+    {
+        assert!(config.index < MAX_GROUP_SIZE, "ESC index too large");
+        let timeout = control_timeout();
+        node.subscribe_message(config.setpoint_subject, Vector31::PAYLOAD_SIZE_MAX, timeout)?;
+        node.subscribe_message(
+            config.readiness_subject,
+            Readiness::PAYLOAD_SIZE_MAX,
+            timeout,
+        )?;
+        let feedback_token =
+            node.start_publishing(config.feedback_subject, timeout, config.feedback_priority)?;
+        let status_token =
+            node.start_publishing(config.status_subject, timeout, Priority::Nominal)?;
+
+        Ok(Esc {
+            index: usize::from(config.index),
+            setpoint_subject: config.setpoint_subject,
+            readiness_subject: config.readiness_subject,
+            feedback_token,
+            status_token,
+            readiness: ReadinessTracker::new(timeout),
+            reported_readiness: None,
+            setpoint: 0.0,
+            setpoint_time: None,
+            control_timeout: timeout,
+            feedback_period: max_publication_period(),
+            last_feedback: None,
+            last_status: None,
+            health: Health::Nominal,
+            demand_factor_pct: 0,
+            status: Status::default(),
+        })
+    }
+
+    /// Returns the readiness state that the controller has commanded
+    ///
+    /// If no readiness command has arrived within the control timeout, this is `Standby`.
+    pub fn readiness(&self, now: I) -> Readiness {
+        self.readiness.state(now)
+    }
+
+    /// Returns the setpoint that the drive should apply now
+    ///
+    /// This is zero if the drive is not engaged, if no setpoint has arrived within the control
+    /// timeout, or if the most recent setpoint was not finite.
+    pub fn setpoint(&self, now: I) -> f32 {
+        if self.readiness(now) != Readiness::Engaged {
+            return 0.0;
+        }
+        match self.setpoint_time {
+            Some(setpoint_time) => {
+                let expiry = self.control_timeout + setpoint_time;
+                if now.overflow_safe_compare(&expiry) == Ordering::Greater {
+                    0.0
+                } else {
+                    self.setpoint
+                }
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Sets the readiness state to report in feedback messages
+    ///
+    /// By default, the reported state is the commanded state. A drive that takes time to change
+    /// state (for example, to spool up before it is engaged) can report its actual state here,
+    /// and call this function again with `None` when it reaches the commanded state.
+    pub fn set_reported_readiness(&mut self, readiness: Option<Readiness>) {
+        self.reported_readiness = readiness;
+    }
+
+    /// Sets the health reported in feedback messages
+    ///
+    /// If any fault flag in the status is set, the reported health is at least `Advisory`.
+    pub fn set_health(&mut self, health: Health) {
+        self.health = health;
+    }
+
+    /// Sets the output of the drive as a percentage of its maximum rated output
+    pub fn set_demand_factor(&mut self, percent: i8) {
+        self.demand_factor_pct = percent;
+    }
+
+    /// Returns the status that will be published
+    ///
+    /// The application updates the temperatures and fault flags here.
+    pub fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
+    }
+
+    /// Adds one to the error count in the status
+    ///
+    /// The error count is reset to zero when the drive is commanded to engage.
+    pub fn increment_error_count(&mut self) {
+        self.status.error_count = self.status.error_count.saturating_add(1);
+    }
+
+    /// Publishes feedback and status messages if they are due
+    ///
+    /// Feedback is published when a setpoint arrives, and here if no feedback has been
+    /// published for the maximum publication period. Status is published once per maximum
+    /// publication period. Nothing is published while the drive is in the `Sleep` state.
+    ///
+    /// This function should be called frequently.
+    pub fn poll<N>(&mut self, node: &mut N) -> Result<(), SendError>
+    where
+        N: Node<Instant = I>,
+    {
+        let now = node.clock_mut().now();
+        if self.readiness(now) == Readiness::Sleep {
+            return Ok(());
+        }
+        if is_due(self.last_feedback, self.feedback_period, now) {
+            self.publish_feedback(node, now)?;
+        }
+        if is_due(self.last_status, self.feedback_period, now) {
+            self.last_status = Some(now);
+            node.publish(&self.status_token, &self.status)?;
+        }
+        Ok(())
+    }
+
+    fn publish_feedback<N>(&mut self, node: &mut N, now: I) -> Result<(), SendError>
+    where
+        N: Node<Instant = I>,
+    {
+        let readiness = self
+            .reported_readiness
+            .unwrap_or_else(|| self.readiness(now));
+        let health = if self.status.fault_flags.any() {
+            self.health.max(Health::Advisory)
+        } else {
+            self.health
+        };
+        let feedback = Feedback {
+            heartbeat: Heartbeat { readiness, health },
+            demand_factor_pct: self.demand_factor_pct,
+        };
+        self.last_feedback = Some(now);
+        node.publish(&self.feedback_token, &feedback)
+    }
+
+    fn handle_setpoint(&mut self, payload: &[u8], now: I) {
+        let setpoints = match Vector31::deserialize_from_bytes(payload) {
+            Ok(setpoints) => setpoints,
+            Err(e) => {
+                log::debug!("Invalid setpoint message: {:?}", e);
+                return;
+            }
+        };
+        let setpoint = setpoints.value[self.index].to_f32();
+        // A non-finite setpoint is treated as zero
+        self.setpoint = if setpoint.is_finite() { setpoint } else { 0.0 };
+        self.setpoint_time = Some(now);
+    }
+
+    fn handle_readiness(&mut self, payload: &[u8], now: I) {
+        let command = match Readiness::deserialize_from_bytes(payload) {
+            Ok(command) => command,
+            Err(e) => {
+                log::debug!("Invalid readiness message: {:?}", e);
+                return;
+            }
+        };
+        if command == Readiness::Engaged && self.readiness(now) != Readiness::Engaged {
+            self.status.error_count = 0;
+        }
+        self.readiness.handle_command(command, now);
+    }
+}
+
+impl<I, P> TransferHandler<I, P> for Esc<I>
+where
+    I: Instant,
+    P: AsRef<[u8]>,
+{
+    fn handle_message<N: Node<Instant = I>>(
+        &mut self,
+        node: &mut N,
+        transfer: &MessageTransfer<P, I>,
+    ) -> bool {
+        let now = transfer.header.timestamp;
+        if transfer.header.subject == self.setpoint_subject {
+            self.handle_setpoint(transfer.payload.as_ref(), now);
+            // Feedback is published after each setpoint, even if the drive is not engaged
+            if self.readiness(now) != Readiness::Sleep && self.publish_feedback(node, now).is_err()
+            {
+                log::warn!("Out of memory when publishing ESC feedback");
+            }
+            true
+        } else if transfer.header.subject == self.readiness_subject {
+            self.handle_readiness(transfer.payload.as_ref(), now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns true if a message that was last published at `last` (or never) should be
+/// published again at `now`
+fn is_due<I: Instant>(last: Option<I>, period: I::Duration, now: I) -> bool {
+    match last {
+        Some(last) => now.overflow_safe_compare(&(period + last)) != Ordering::Less,
+        None => true,
+    }
+}
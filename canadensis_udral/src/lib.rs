@@ -0,0 +1,46 @@
+//!
+//! UDRAL services
+//!
+//! This library implements the device side of some services from the UAVCAN Drone Reference
+//! Architecture Layer (UDRAL). Each service handles the subjects and timing rules that the
+//! specification defines, so that a peripheral only needs to apply setpoints and report its
+//! measurements.
+//!
+//! UDRAL does not assign fixed subject IDs. Integrators usually configure them using registers
+//! named like `uavcan.sub.setpoint.id` and `uavcan.pub.feedback.id`.
+//!
+//! Currently available services:
+//!
+//! * [`esc::Esc`]: An electronic speed controller in a group of drives
+//!
+
+#![no_std]
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate half;
+extern crate log;
+
+pub mod esc;
+pub mod readiness;
+
+use canadensis_core::time::{milliseconds, Duration};
+use canadensis_data_types::reg::udral::service::actuator::common::{
+    CONTROL_TIMEOUT, MAX_PUBLICATION_PERIOD,
+};
+
+/// Returns the actuator control timeout from the specification
+///
+/// An actuator stops if it has not received a setpoint or readiness message in this time.
+pub fn control_timeout<D: Duration>() -> D {
+    milliseconds((CONTROL_TIMEOUT * 1000.0) as u32)
+}
+
+/// Returns the maximum time between messages on actuator subjects that are usually published
+/// when setpoints arrive
+pub fn max_publication_period<D: Duration>() -> D {
+    milliseconds(u32::from(MAX_PUBLICATION_PERIOD) * 1000)
+}
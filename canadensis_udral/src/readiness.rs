@@ -0,0 +1,75 @@
+//!
+//! Readiness commands
+//!
+
+use core::cmp::Ordering;
+
+use canadensis_core::time::Instant;
+use canadensis_data_types::reg::udral::service::common::readiness::Readiness;
+
+/// Keeps track of the readiness state that a controller has commanded
+///
+/// If no readiness command arrives within the timeout, the commanded state reverts to
+/// `Standby`. A service that has not received any command is also in `Standby`.
+#[derive(Debug)]
+pub struct ReadinessTracker<I: Instant> {
+    /// The most recent command
+    commanded: Readiness,
+    /// The time when the most recent command arrived
+    last_command: Option<I>,
+    /// The maximum time between commands
+    timeout: I::Duration,
+}
+
+impl<I: Instant> ReadinessTracker<I> {
+    /// Creates a tracker in the `Standby` state
+    ///
+    /// For actuators, the timeout must not be longer than
+    /// [`control_timeout`](crate::control_timeout).
+    pub fn new(timeout: I::Duration) -> Self {
+        ReadinessTracker {
+            commanded: Readiness::Standby,
+            last_command: None,
+            timeout,
+        }
+    }
+
+    /// Records a readiness command that arrived at the provided time
+    pub fn handle_command(&mut self, command: Readiness, now: I) {
+        self.commanded = command;
+        self.last_command = Some(now);
+    }
+
+    /// Returns the commanded readiness state at the provided time
+    pub fn state(&self, now: I) -> Readiness {
+        match self.last_command {
+            Some(last_command) => {
+                let expiry = self.timeout + last_command;
+                if now.overflow_safe_compare(&expiry) == Ordering::Greater {
+                    Readiness::Standby
+                } else {
+                    self.commanded
+                }
+            }
+            None => Readiness::Standby,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReadinessTracker;
+    use canadensis_core::time::{MicrosecondDuration32, Microseconds32};
+    use canadensis_data_types::reg::udral::service::common::readiness::Readiness;
+
+    #[test]
+    fn timeout() {
+        let mut tracker = ReadinessTracker::new(MicrosecondDuration32::new(1000));
+        assert_eq!(Readiness::Standby, tracker.state(Microseconds32::new(0)));
+        tracker.handle_command(Readiness::Engaged, Microseconds32::new(500));
+        assert_eq!(Readiness::Engaged, tracker.state(Microseconds32::new(1500)));
+        assert_eq!(Readiness::Standby, tracker.state(Microseconds32::new(1501)));
+        tracker.handle_command(Readiness::Sleep, Microseconds32::new(2000));
+        assert_eq!(Readiness::Sleep, tracker.state(Microseconds32::new(2000)));
+    }
+}
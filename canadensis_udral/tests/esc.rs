@@ -0,0 +1,171 @@
+//!
+//! Tests an ESC with setpoint and readiness messages
+//!
+
+extern crate canadensis;
+extern crate canadensis_can;
+extern crate canadensis_core;
+extern crate canadensis_data_types;
+extern crate canadensis_encoding;
+extern crate canadensis_udral;
+extern crate half;
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use canadensis::{CoreNode, Node, TransferHandler};
+use canadensis_can::queue::{FrameQueueSource, HeapQueue};
+use canadensis_can::Mtu;
+use canadensis_core::time::{Clock, Microseconds64};
+use canadensis_core::transfer::{MessageHeader, MessageTransfer};
+use canadensis_core::{NodeId, Priority, SubjectId, TransferId};
+use canadensis_data_types::reg::udral::service::actuator::common::feedback::Feedback;
+use canadensis_data_types::reg::udral::service::common::readiness::Readiness;
+use canadensis_encoding::{Deserialize, Serialize};
+use canadensis_udral::esc::{Esc, EscConfig};
+use half::f16;
+
+#[derive(Clone, Default)]
+struct TestClock(Rc<Cell<u64>>);
+
+impl Clock for TestClock {
+    type Instant = Microseconds64;
+
+    fn now(&mut self) -> Self::Instant {
+        Microseconds64::new(self.0.get())
+    }
+}
+
+type TestNode = CoreNode<TestClock, HeapQueue<Microseconds64>, 4, 4, 4, 4>;
+
+const SETPOINT: u16 = 100;
+const READINESS: u16 = 101;
+const FEEDBACK: u16 = 102;
+const STATUS: u16 = 103;
+
+fn message(subject: u16, time: u64, payload: Vec<u8>) -> MessageTransfer<Vec<u8>, Microseconds64> {
+    MessageTransfer {
+        header: MessageHeader {
+            timestamp: Microseconds64::new(time),
+            transfer_id: TransferId::const_default(),
+            priority: Priority::High,
+            subject: SubjectId::try_from(subject).unwrap(),
+            source: Some(NodeId::try_from(1).unwrap()),
+        },
+        payload,
+    }
+}
+
+/// Returns a setpoint message with values for drives 0 and 1
+fn setpoint(time: u64, values: [f32; 2]) -> MessageTransfer<Vec<u8>, Microseconds64> {
+    let mut payload = Vec::new();
+    for value in values.iter() {
+        payload.extend_from_slice(&f16::from_f32(*value).to_le_bytes());
+    }
+    message(SETPOINT, time, payload)
+}
+
+fn readiness(time: u64, readiness: Readiness) -> MessageTransfer<Vec<u8>, Microseconds64> {
+    let mut payload = vec![0u8; 1];
+    readiness.serialize_to_bytes(&mut payload);
+    message(READINESS, time, payload)
+}
+
+/// Removes all frames from the node's queue and returns the subject IDs and payloads of the
+/// single-frame messages
+fn sent_messages(node: &mut TestNode) -> Vec<(u16, Vec<u8>)> {
+    let mut messages = Vec::new();
+    while let Some(frame) = node.frame_queue_mut().pop_frame() {
+        let subject = ((u32::from(frame.id()) >> 8) & 0x1fff) as u16;
+        let data = frame.data();
+        messages.push((subject, data[..data.len() - 1].to_vec()));
+    }
+    messages
+}
+
+#[test]
+fn setpoint_and_readiness() {
+    let clock = TestClock::default();
+    let mut node: TestNode = CoreNode::new(
+        clock.clone(),
+        NodeId::try_from(10).unwrap(),
+        Mtu::Can8,
+        HeapQueue::new(),
+    );
+    let mut esc = Esc::new(
+        &mut node,
+        EscConfig {
+            index: 1,
+            setpoint_subject: SubjectId::try_from(SETPOINT).unwrap(),
+            readiness_subject: SubjectId::try_from(READINESS).unwrap(),
+            feedback_subject: SubjectId::try_from(FEEDBACK).unwrap(),
+            status_subject: SubjectId::try_from(STATUS).unwrap(),
+            feedback_priority: Priority::High,
+        },
+    )
+    .unwrap();
+    let at = Microseconds64::new;
+
+    // Standby: the setpoint is ignored, but feedback is still sent
+    assert!(esc.handle_message(&mut node, &setpoint(0, [0.25, 0.5])));
+    assert_eq!(Readiness::Standby, esc.readiness(at(0)));
+    assert_eq!(0.0, esc.setpoint(at(0)));
+    let sent = sent_messages(&mut node);
+    assert_eq!(1, sent.len());
+    assert_eq!(FEEDBACK, sent[0].0);
+    let feedback = Feedback::deserialize_from_bytes(&sent[0].1).unwrap();
+    assert_eq!(Readiness::Standby, feedback.heartbeat.readiness);
+
+    // Engaged
+    esc.increment_error_count();
+    assert!(esc.handle_message(&mut node, &readiness(100_000, Readiness::Engaged)));
+    assert_eq!(0, esc.status_mut().error_count);
+    esc.handle_message(&mut node, &setpoint(200_000, [0.25, 0.5]));
+    assert_eq!(0.5, esc.setpoint(at(200_000)));
+    let feedback = Feedback::deserialize_from_bytes(&sent_messages(&mut node)[0].1).unwrap();
+    assert_eq!(Readiness::Engaged, feedback.heartbeat.readiness);
+
+    // Non-finite and missing setpoints are zero
+    esc.handle_message(&mut node, &setpoint(300_000, [0.25, f32::NAN]));
+    assert_eq!(0.0, esc.setpoint(at(300_000)));
+    esc.handle_message(&mut node, &message(SETPOINT, 400_000, vec![0, 0x38]));
+    assert_eq!(0.0, esc.setpoint(at(400_000)));
+    esc.handle_message(&mut node, &setpoint(500_000, [0.25, -1.0]));
+    assert_eq!(-1.0, esc.setpoint(at(500_000)));
+
+    // The setpoint times out, and then the readiness command times out
+    esc.handle_message(&mut node, &readiness(1_000_000, Readiness::Engaged));
+    assert_eq!(-1.0, esc.setpoint(at(1_500_000)));
+    assert_eq!(0.0, esc.setpoint(at(1_500_001)));
+    assert_eq!(Readiness::Engaged, esc.readiness(at(2_000_000)));
+    assert_eq!(Readiness::Standby, esc.readiness(at(2_000_001)));
+    sent_messages(&mut node);
+
+    // Without setpoints, feedback and status are published once per second
+    clock.0.set(2_600_000);
+    esc.poll(&mut node).unwrap();
+    esc.poll(&mut node).unwrap();
+    let subjects: Vec<u16> = sent_messages(&mut node)
+        .into_iter()
+        .map(|(subject, _)| subject)
+        .collect();
+    assert_eq!(
+        1,
+        subjects
+            .iter()
+            .filter(|&&subject| subject == FEEDBACK)
+            .count()
+    );
+    // Status uses more than one frame
+    assert!(subjects.contains(&STATUS));
+    clock.0.set(3_000_000);
+    esc.poll(&mut node).unwrap();
+    assert!(sent_messages(&mut node).is_empty());
+
+    // Nothing is published while sleeping
+    esc.handle_message(&mut node, &readiness(3_000_000, Readiness::Sleep));
+    clock.0.set(3_700_000);
+    esc.poll(&mut node).unwrap();
+    assert!(sent_messages(&mut node).is_empty());
+}
@@ -3,11 +3,11 @@ extern crate object;
 use object::read::{File, Object};
 use object::{ObjectSection, ObjectSymbol};
 use std::borrow::Cow;
+use std::env;
 use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
 use std::process;
-use std::{env, iter};
 
 fn main() {
     match run() {
@@ -115,7 +115,7 @@ impl std::fmt::Display for StringError {
 impl Error for StringError {}
 
 fn get_input_path() -> PathBuf {
-    match env::args_os().skip(1).next() {
+    match env::args_os().nth(1) {
         Some(path) => PathBuf::from(path),
         None => {
             eprintln!("Usage: canadensis_write_crc binary-path");
@@ -147,8 +147,8 @@ fn pad_to_8_bytes(data: &[u8]) -> Cow<'_, [u8]> {
     } else {
         let to_add = 8 - extra;
         let mut data = data.to_vec();
-        data.extend(iter::repeat(0u8).take(to_add));
-        debug_assert!(data.len() % 8 == 0);
+        data.extend(std::iter::repeat_n(0u8, to_add));
+        debug_assert!(data.len().is_multiple_of(8));
         Cow::Owned(data)
     }
 }